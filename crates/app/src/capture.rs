@@ -0,0 +1,61 @@
+use fltk::prelude::{GroupExt, InputExt, WidgetBase, WidgetExt, WindowExt};
+
+/// Opens the quick capture window: one borderless line to type into, near the top
+/// of the screen. Enter appends what was typed to `inbox` and closes it, Escape
+/// just closes it. `on_captured` runs after a successful append.
+pub fn show(inbox: std::path::PathBuf, mut on_captured: impl FnMut() + 'static) {
+    const W: i32 = 480;
+    const H: i32 = 40;
+
+    let (screen_w, screen_h) = fltk::app::screen_size();
+    let mut win = fltk::window::Window::new(
+        (screen_w as i32 - W) / 2,
+        screen_h as i32 / 4,
+        W,
+        H,
+        "Quick Capture",
+    );
+    let mut input = fltk::input::Input::new(6, 6, W - 12, H - 12, "");
+
+    win.end();
+    win.set_border(false);
+    win.make_modal(true);
+    input.set_text_size(16);
+    input.set_tooltip(&format!("Enter adds to {}", inbox.display()));
+
+    input.handle({
+        let mut win = win.clone();
+        move |input, ev| {
+            if ev != fltk::enums::Event::KeyDown {
+                return false;
+            }
+
+            match fltk::app::event_key() {
+                fltk::enums::Key::Enter | fltk::enums::Key::KPEnter => {
+                    match editor_state::capture::append(&inbox, &input.value()) {
+                        Ok(()) => {
+                            win.hide();
+                            on_captured();
+                        }
+                        Err(err) => {
+                            tracing::error!("Error capturing note: {:?}", err);
+                            fltk::dialog::alert_default(&format!(
+                                "Couldn't add to {}: {err}",
+                                inbox.display()
+                            ));
+                        }
+                    }
+                    true
+                }
+                fltk::enums::Key::Escape => {
+                    win.hide();
+                    true
+                }
+                _ => false,
+            }
+        }
+    });
+
+    win.show();
+    let _ = input.take_focus();
+}
@@ -0,0 +1,210 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use editor_state::docking::{Dock, Panel};
+use fltk::prelude::{MenuExt, WidgetExt};
+
+/// The widgets around the editor whose geometry depends on the view state
+/// (panels shown and where they're docked, focus mode on).
+#[derive(Clone)]
+pub struct Chrome {
+    pub window: fltk::window::Window,
+    pub menu: fltk::menu::MenuBar,
+    pub breadcrumb: fltk::frame::Frame,
+    pub outline: fltk::tree::Tree,
+    pub sync_panel: fltk::browser::HoldBrowser,
+    pub upcoming: fltk::browser::HoldBrowser,
+    pub diagnostics: fltk::browser::HoldBrowser,
+    pub smart_folders: fltk::tree::Tree,
+    pub editor: ui::TextEditor,
+    pub status_bar: fltk::frame::Frame,
+    /// Holds the panel layout, saved with the rest of the session.
+    pub session: Rc<RefCell<editor_state::session::SessionStore>>,
+}
+
+impl Chrome {
+    const MENU_H: i32 = 30;
+    const BREADCRUMB_H: i32 = 20;
+    const STATUS_H: i32 = 24;
+    const SIDEBAR_W: i32 = 140;
+    const BOTTOM_H: i32 = 120;
+
+    /// The View menu toggle that shows `panel`.
+    fn menu_label(panel: Panel) -> &'static str {
+        match panel {
+            Panel::Outline => "View/Outline",
+            Panel::SyncStatus => "View/Sync Status",
+            Panel::SmartFolders => "View/Smart Folders",
+            Panel::Upcoming => "View/Upcoming",
+            Panel::Diagnostics => "View/Link Diagnostics",
+        }
+    }
+
+    fn widget(&mut self, panel: Panel) -> &mut dyn WidgetExt {
+        match panel {
+            Panel::Outline => &mut self.outline,
+            Panel::SyncStatus => &mut self.sync_panel,
+            Panel::SmartFolders => &mut self.smart_folders,
+            Panel::Upcoming => &mut self.upcoming,
+            Panel::Diagnostics => &mut self.diagnostics,
+        }
+    }
+
+    /// Ticks the View menu toggles of the panels the saved layout shows.
+    pub fn restore_layout(&mut self) {
+        let layout = self.session.borrow().layout.clone();
+
+        for panel in Panel::ALL {
+            if let Some(mut item) = self.menu.find_item(Self::menu_label(panel)) {
+                if layout.is_shown(panel) {
+                    item.set();
+                } else {
+                    item.clear();
+                }
+            }
+        }
+        self.relayout();
+    }
+
+    /// The panel shown at window coordinates `(x, y)`.
+    pub fn panel_at(&mut self, x: i32, y: i32) -> Option<Panel> {
+        Panel::ALL.into_iter().find(|&panel| {
+            let widget = self.widget(panel);
+
+            widget.visible()
+                && (widget.x()..widget.x() + widget.w()).contains(&x)
+                && (widget.y()..widget.y() + widget.h()).contains(&y)
+        })
+    }
+
+    /// Docks `panel` where it was dropped at `(x, y)`: the left or right third of
+    /// the window docks it in that column, the bottom third below the editor. It
+    /// goes before the panel it was dropped on, or last.
+    pub fn drop_panel(&mut self, panel: Panel, x: i32, y: i32) {
+        let (w, h) = (self.window.w(), self.window.h());
+        let dock = if x < w / 3 {
+            Dock::Left
+        } else if x >= w - w / 3 {
+            Dock::Right
+        } else if y >= h - h / 3 {
+            Dock::Bottom
+        } else {
+            return;
+        };
+        let shown = self.session.borrow().layout.shown_in(dock);
+        let index = shown
+            .iter()
+            .filter(|&&other| other != panel)
+            .position(|&other| {
+                let widget = self.widget(other);
+
+                match dock {
+                    Dock::Bottom => x < widget.x() + widget.w() / 2,
+                    Dock::Left | Dock::Right => y < widget.y() + widget.h() / 2,
+                }
+            })
+            .unwrap_or(shown.len());
+
+        self.session
+            .borrow_mut()
+            .layout
+            .move_panel(panel, dock, index);
+        self.relayout();
+    }
+
+    /// Positions every widget for the current window size and view state.
+    pub fn relayout(&mut self) {
+        let (w, h) = (self.window.w(), self.window.h());
+        let focus_mode = self.editor.state.borrow().focus_mode;
+        let chrome = [
+            &mut self.menu as &mut dyn WidgetExt,
+            &mut self.breadcrumb,
+            &mut self.status_bar,
+        ];
+
+        for widget in chrome {
+            if focus_mode {
+                widget.hide();
+            } else {
+                widget.show();
+            }
+        }
+
+        if focus_mode {
+            for panel in Panel::ALL {
+                self.widget(panel).hide();
+            }
+            self.editor.group.resize(0, 0, w, h);
+        } else {
+            // The View menu toggles are what the layout shows.
+            let layout = {
+                let mut session = self.session.borrow_mut();
+
+                for panel in Panel::ALL {
+                    let shown = self
+                        .menu
+                        .find_item(Self::menu_label(panel))
+                        .is_some_and(|item| item.value());
+
+                    session.layout.set_shown(panel, shown);
+                }
+                session.layout.clone()
+            };
+            let [left, right, bottom] = Dock::ALL.map(|dock| layout.shown_in(dock));
+            let left_w = if left.is_empty() { 0 } else { Self::SIDEBAR_W };
+            let right_w = if right.is_empty() { 0 } else { Self::SIDEBAR_W };
+            let bottom_h = if bottom.is_empty() { 0 } else { Self::BOTTOM_H };
+            let center_w = w - left_w - right_w;
+            let column_h = h - Self::MENU_H - Self::STATUS_H;
+            let body_y = Self::MENU_H + Self::BREADCRUMB_H;
+            let body_h = h - body_y - Self::STATUS_H - bottom_h;
+
+            self.menu.resize(0, 0, w, Self::MENU_H);
+            for panel in Panel::ALL {
+                if !layout.is_shown(panel) {
+                    self.widget(panel).hide();
+                }
+            }
+            // The panels in a dock split it evenly: columns top to bottom, the
+            // bottom strip left to right.
+            for (panels, x) in [(&left, 0), (&right, w - right_w)] {
+                let count = panels.len() as i32;
+
+                for (i, &panel) in panels.iter().enumerate() {
+                    let top = Self::MENU_H + column_h * i as i32 / count;
+                    let bottom = Self::MENU_H + column_h * (i as i32 + 1) / count;
+                    let widget = self.widget(panel);
+
+                    widget.show();
+                    widget.resize(x, top, Self::SIDEBAR_W, bottom - top);
+                }
+            }
+            for (i, &panel) in bottom.iter().enumerate() {
+                let count = bottom.len() as i32;
+                let start = left_w + center_w * i as i32 / count;
+                let end = left_w + center_w * (i as i32 + 1) / count;
+                let widget = self.widget(panel);
+
+                widget.show();
+                widget.resize(start, body_y + body_h, end - start, bottom_h);
+            }
+            self.breadcrumb
+                .resize(left_w, Self::MENU_H, center_w, Self::BREADCRUMB_H);
+            self.editor.group.resize(left_w, body_y, center_w, body_h);
+            self.status_bar
+                .resize(0, h - Self::STATUS_H, w, Self::STATUS_H);
+        }
+
+        self.editor.on_content_changed();
+        self.window.redraw();
+    }
+
+    pub fn toggle_focus_mode(&mut self) {
+        {
+            let mut state = self.editor.state.borrow_mut();
+            state.focus_mode = !state.focus_mode;
+        }
+
+        self.relayout();
+    }
+}
@@ -0,0 +1,100 @@
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+type SharedDocument = RefCell<editor_state::document::Document>;
+
+thread_local! {
+    /// Documents the panic hook rescues. Weak, so a closed document just drops out.
+    static DOCUMENTS: RefCell<Vec<Weak<SharedDocument>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Registers `doc` to be dumped to a recovery file if the app panics.
+pub fn watch(doc: &Rc<SharedDocument>) {
+    DOCUMENTS.with_borrow_mut(|docs| {
+        docs.retain(|weak| weak.strong_count() > 0);
+        docs.push(Rc::downgrade(doc));
+    });
+}
+
+/// Saves every watched document with unsaved changes into `dir`. Documents that
+/// are borrowed right now (the panic happened mid-edit) are returned instead.
+fn rescue_all(
+    dir: &std::path::Path,
+    stamp: u64,
+) -> (Vec<std::path::PathBuf>, Vec<Rc<SharedDocument>>) {
+    let docs: Vec<Rc<SharedDocument>> =
+        DOCUMENTS.with_borrow(|docs| docs.iter().filter_map(Weak::upgrade).collect());
+    let mut recovered = Vec::new();
+    let mut busy = Vec::new();
+
+    for doc in docs {
+        let Ok(d) = doc.try_borrow() else {
+            busy.push(doc.clone());
+            continue;
+        };
+
+        match editor_state::recovery::rescue(&d, dir, stamp) {
+            Ok(Some(path)) => recovered.push(path),
+            Ok(None) => {}
+            Err(err) => eprintln!("Error writing recovery file: {:?}", err),
+        }
+    }
+
+    (recovered, busy)
+}
+
+/// Chains a hook onto the default panic hook that dumps unsaved notes to the
+/// recovery folder and writes a crash report there.
+///
+/// FLTK swallows panics raised inside callbacks, so the app usually keeps running.
+/// A document that was mutably borrowed when the panic hit is saved on the next
+/// turn of the event loop, once unwinding has released it.
+pub fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+
+    std::panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+
+        let Some(dir) = editor_state::recovery::default_dir() else {
+            return;
+        };
+        let stamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let (recovered, busy) = rescue_all(&dir, stamp);
+        let report = editor_state::recovery::crash_report(
+            &info.to_string(),
+            &std::backtrace::Backtrace::force_capture().to_string(),
+            &recovered,
+            busy.len(),
+        );
+        let report_path = dir.join(format!("crash-{stamp}.txt"));
+
+        if let Err(err) =
+            std::fs::create_dir_all(&dir).and_then(|()| std::fs::write(&report_path, report))
+        {
+            eprintln!("Error writing crash report: {:?}", err);
+        }
+
+        // Only the UI thread watches documents, so past this point it's safe to use FLTK.
+        if recovered.is_empty() && busy.is_empty() {
+            return;
+        }
+
+        fltk::app::add_timeout3(0.0, move |_| {
+            for doc in &busy {
+                if let Ok(d) = doc.try_borrow()
+                    && let Err(err) = editor_state::recovery::rescue(&d, &dir, stamp)
+                {
+                    tracing::error!("Error writing recovery file: {:?}", err);
+                }
+            }
+
+            fltk::dialog::alert_default(&format!(
+                "MyNotes ran into an internal error. Unsaved notes were copied to {} \
+                 and a crash report was written next to them.",
+                dir.display()
+            ));
+        });
+    }));
+}
@@ -0,0 +1,98 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+
+use editor_state::search::Index;
+
+/// Keeps the workspace's search index current: loaded from disk when the workspace
+/// changes, caught up with the notes on a worker thread now and then, and fed the
+/// open note as it's saved.
+pub struct Indexer {
+    index: Index,
+    /// Whether the index changed since it was last written to disk.
+    unsaved: bool,
+    refreshing: bool,
+    results: mpsc::Receiver<Index>,
+    report: mpsc::Sender<Index>,
+}
+
+impl Default for Indexer {
+    fn default() -> Self {
+        let (report, results) = mpsc::channel();
+
+        Self {
+            index: Index::default(),
+            unsaved: false,
+            refreshing: false,
+            results,
+            report,
+        }
+    }
+}
+
+impl Indexer {
+    /// How often the app catches up with changes made outside it.
+    pub const INTERVAL: f64 = 30.0;
+
+    #[inline]
+    pub fn index(&self) -> &Index {
+        &self.index
+    }
+
+    /// Catches the index of `root` up with the notes on disk on a worker thread,
+    /// loading its saved copy first if `root` is a different workspace. Does
+    /// nothing while an earlier refresh is still going.
+    pub fn refresh(&mut self, root: PathBuf) {
+        if self.refreshing {
+            return;
+        }
+
+        let current = (self.index.root == root).then(|| self.index.clone());
+        let unsaved = std::mem::take(&mut self.unsaved);
+        let report = self.report.clone();
+
+        self.refreshing = true;
+        std::thread::spawn(move || {
+            let cache = Index::cache_path(&root);
+            let mut index = current.unwrap_or_else(|| match &cache {
+                Some(cache) => Index::load(&root, cache),
+                None => Index::new(&root),
+            });
+
+            if (index.refresh() || unsaved)
+                && let Some(cache) = &cache
+                && let Err(err) = index.save(cache)
+            {
+                tracing::error!("Error saving search index: {:?}", err);
+            }
+            let _ = report.send(index);
+        });
+    }
+
+    /// Picks up a finished refresh. Returns whether the index changed.
+    pub fn poll(&mut self) -> bool {
+        let Ok(index) = self.results.try_recv() else {
+            return false;
+        };
+
+        self.refreshing = false;
+        if index == self.index {
+            return false;
+        }
+        self.index = index;
+        true
+    }
+
+    /// Takes in the text just saved to `path` if the index's copy is older than the
+    /// file, without reading it back. It goes to disk with the next refresh.
+    pub fn saved(&mut self, path: &Path, text: impl FnOnce() -> String) {
+        let Ok(meta) = std::fs::metadata(path) else {
+            return;
+        };
+
+        if self.index.stamp_of(path) != Some(editor_state::search::stamp(&meta))
+            && self.index.update(path, &text())
+        {
+            self.unsaved = true;
+        }
+    }
+}
@@ -1,32 +1,328 @@
-use fltk::prelude::{GroupExt, MenuExt, WidgetExt};
+mod capture;
+mod chrome;
+mod crash;
+mod indexer;
+mod runner;
+mod scheduler;
+mod share;
+mod tools;
+mod tray;
+
+use fltk::prelude::{GroupExt, MenuExt, WidgetBase, WidgetExt, WindowExt};
+
+/// Status bar text: saves still waiting for their drive, then the indentation.
+fn status_label(
+    d: &editor_state::document::Document,
+    queue: &editor_state::save_queue::SaveQueue,
+) -> String {
+    match queue.pending().len() {
+        0 => d.indent_label(),
+        n => format!("{n} pending write(s)    {}", d.indent_label()),
+    }
+}
+
+/// The name shown to pair-editing peers.
+fn user_name() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "Someone".to_string())
+}
+
+/// This computer's address on the local network: the one the OS would send from to
+/// reach an outside address. Nothing is actually sent.
+fn lan_address() -> Option<std::net::IpAddr> {
+    let socket = std::net::UdpSocket::bind("0.0.0.0:0").ok()?;
+
+    socket.connect("192.0.2.1:9").ok()?;
+    Some(socket.local_addr().ok()?.ip())
+}
+
+type SharedSyncProvider = std::sync::Arc<dyn editor_state::sync::SyncProvider + Send + Sync>;
+
+/// Starts syncing the open note's folder in the background. Errors are meant for the user.
+fn start_sync(
+    d: &editor_state::document::Document,
+    provider: Option<&SharedSyncProvider>,
+    background: &mut editor_state::sync::BackgroundSync,
+) -> Result<(), &'static str> {
+    let provider = provider.ok_or("Choose where to sync to first.")?;
+    let root = d
+        .text_buffer
+        .path()
+        .and_then(std::path::Path::parent)
+        .ok_or("Save the note before syncing its folder.")?;
+
+    if !background.start(root.to_path_buf(), provider.clone()) {
+        return Err("A sync is already running.");
+    }
+
+    Ok(())
+}
+
+/// Opens a three-way merge of the open note with the conflict file `copy` that sync
+/// wrote for it under `root`. Applying it replaces the note as one undo step and
+/// deletes the conflict file.
+fn open_merge(
+    backend: &std::rc::Rc<std::cell::RefCell<editor_state::document::Document>>,
+    editor: &ui::TextEditor,
+    root: std::path::PathBuf,
+    copy: std::path::PathBuf,
+) {
+    let remote = match std::fs::read_to_string(&copy) {
+        Ok(text) => text,
+        Err(err) => {
+            tracing::error!("Error reading conflict file: {:?}", err);
+            return;
+        }
+    };
+    // Without a kept ancestor, every difference counts as a conflict.
+    let base = editor_state::sync::SyncState::conflict_base(&root, &copy)
+        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+        .unwrap_or_default();
+    let d = backend.borrow();
+    let local = d.get_lines_stripped(0..d.get_line_count());
+    let merge = editor_state::merge::Merge::new(
+        &base.lines().map(str::to_string).collect::<Vec<_>>(),
+        &local,
+        &remote.lines().map(str::to_string).collect::<Vec<_>>(),
+    );
+    let title = d
+        .text_buffer
+        .path()
+        .and_then(editor_state::workspace::note_name)
+        .unwrap_or("Untitled")
+        .to_string();
+
+    drop(d);
+    ui::MergeView::open(&title, merge, {
+        let backend = backend.clone();
+        let mut editor = editor.clone();
+        move |lines| {
+            backend.borrow_mut().replace_all_lines(lines);
+            editor.on_content_changed();
+
+            if let Err(err) = editor_state::sync::SyncState::resolve_conflict(&root, &copy) {
+                tracing::error!("Error removing conflict file: {:?}", err);
+            }
+            fltk::app::redraw();
+        }
+    });
+}
+
+/// Offers to show `path` as a grid if it's a table. Returns whether it was, so the
+/// caller skips opening it as text.
+fn open_as_grid(path: &std::path::Path) -> bool {
+    if !editor_state::csv::is_table(path)
+        || fltk::dialog::choice2_default(
+            "This file is a table.",
+            "Open as Text",
+            "Open as Grid",
+            "",
+        ) != Some(1)
+    {
+        return false;
+    }
+
+    match editor_state::csv::Table::open(path) {
+        Ok(table) => {
+            ui::CsvGrid::open(table);
+            true
+        }
+        Err(err) => {
+            tracing::error!("Error opening table: {:?}", err);
+            fltk::dialog::alert_default(&format!("Couldn't open as a grid: {err}"));
+            false
+        }
+    }
+}
 
 pub fn main() {
     let app = fltk::app::App::default();
+
+    // `mynotes --capture`, `--today`, `--new` or `--toggle` is what a global hotkey
+    // or tray entry launches, `mynotes mynotes://note/...` what the desktop runs
+    // for a deep link. An app already running takes the request over, so it
+    // happens at once; otherwise this one carries it out.
+    let request = std::env::args()
+        .skip(1)
+        .find_map(|arg| editor_state::instance::Request::from_arg(&arg));
+
+    if let Some(request) = &request
+        && tray::forward(request)
+    {
+        return;
+    }
+
+    // Capturing doesn't need the editor, just the capture window.
+    if request
+        == Some(editor_state::instance::Request::Action(
+            editor_state::instance::Action::QuickCapture,
+        ))
+    {
+        let Some(inbox) = editor_state::capture::inbox() else {
+            fltk::dialog::alert_default("Couldn't tell where the inbox note is.");
+            return;
+        };
+
+        capture::show(inbox, || {});
+        app.run().unwrap();
+        return;
+    }
+
     let mut win = fltk::window::Window::default()
         .with_size(400, 300)
         .with_label("MyNotes");
     let backend = std::rc::Rc::new(std::cell::RefCell::new(
         editor_state::document::Document::new(editor_core::text::TextBuffer::new().unwrap()),
     ));
-    let mut text_editor = ui::TextEditor::new(0, 30, 400, 270, backend.clone());
+    let log = util::logging::init();
+    crash::install_panic_hook();
+    crash::watch(&backend);
+    let mut text_editor = ui::TextEditor::new(0, 50, 400, 226, backend.clone());
+    let breadcrumb_bar = ui::BreadcrumbBar::new(0, 30, 400, 20, &text_editor);
+    let mut outline_panel = ui::OutlinePanel::new(0, 30, 140, 246, text_editor.clone());
+    outline_panel.tree.hide();
+    let mut sync_panel = ui::SyncPanel::new(0, 153, 140, 123);
+    sync_panel.browser.hide();
+    let mut upcoming_panel = ui::UpcomingPanel::new(0, 200, 140, 76);
+    upcoming_panel.browser.hide();
+    let mut diagnostics_panel = ui::DiagnosticsPanel::new(0, 200, 140, 76);
+    diagnostics_panel.browser.hide();
+    let mut status_bar = fltk::frame::Frame::default()
+        .with_pos(0, 276)
+        .with_size(400, 24);
+    status_bar.set_frame(fltk::enums::FrameType::FlatBox);
+    status_bar.set_align(fltk::enums::Align::Right | fltk::enums::Align::Inside);
+    status_bar.set_label(&backend.borrow().indent_label());
     let text_editor_state = text_editor.state.clone();
+    let text_editor_view = text_editor.clone();
+    let status_frame = status_bar.clone();
+    let paste_state = text_editor.state.clone();
+    let paste_canvas = text_editor.canvas.clone();
     let mut menu = fltk::menu::MenuBar::default().with_size(400, 30);
     let menu_backend = backend.clone();
+    let session_path = editor_state::session::SessionStore::default_path();
+    let session = std::rc::Rc::new(std::cell::RefCell::new(
+        session_path
+            .as_deref()
+            .map(editor_state::session::SessionStore::load)
+            .unwrap_or_default(),
+    ));
+    let save_queue = std::rc::Rc::new(std::cell::RefCell::new(
+        editor_state::save_queue::SaveQueue::load(
+            editor_state::save_queue::default_dir()
+                .unwrap_or_else(|| std::env::temp_dir().join("mynotes-pending")),
+        ),
+    ));
+
+    let quick_actions = tray::QuickActions {
+        window: win.clone(),
+        editor: text_editor_view.clone(),
+        status_bar: status_frame.clone(),
+        session: session.clone(),
+    };
+    let workspace_root = {
+        let quick_actions = quick_actions.clone();
+        move || quick_actions.workspace_root()
+    };
+    let saved_searches_path = editor_state::search::saved_searches_path();
+    let saved_searches = std::rc::Rc::new(std::cell::RefCell::new(
+        saved_searches_path
+            .as_deref()
+            .map(editor_state::search::load_searches)
+            .unwrap_or_default(),
+    ));
+    let indexer = std::rc::Rc::new(std::cell::RefCell::new(indexer::Indexer::default()));
+    // Hovered links preview the first lines of their note, as last indexed.
+    text_editor_view.clone().set_note_previewer({
+        let indexer = indexer.clone();
+        move |link| {
+            indexer
+                .try_borrow()
+                .ok()?
+                .index()
+                .find_note(&link.note)
+                .map(|note| note.preview.clone())
+        }
+    });
+    let search_workspace = {
+        let backend = backend.clone();
+        let indexer = indexer.clone();
+        move |query: &str| {
+            let include_archived = backend
+                .try_borrow()
+                .is_ok_and(|d| d.settings.include_archived);
+
+            let Some(query) = editor_state::query::Query::parse(query) else {
+                return Vec::new();
+            };
+
+            indexer.borrow().index().search(&query, include_archived)
+        }
+    };
+    let mut smart_folders = ui::SmartFolders::new(0, 200, 140, 76, search_workspace.clone(), {
+        let mut quick_actions = quick_actions.clone();
+        let mut editor = text_editor_view.clone();
+        move |hit| {
+            quick_actions.open(&hit.path);
+            editor.jump_to_row(hit.row);
+        }
+    });
+    smart_folders.tree.hide();
+    smart_folders.set_searches(&saved_searches.borrow());
+    let resident = std::rc::Rc::new(std::cell::Cell::new(
+        editor_state::instance::stays_resident(),
+    ));
 
     win.resizable(&text_editor.group);
 
+    menu.add(
+        "File/New Note",
+        fltk::enums::Shortcut::Ctrl | 'n',
+        fltk::menu::MenuFlag::Normal,
+        {
+            let mut quick_actions = quick_actions.clone();
+            move |_| quick_actions.run(editor_state::instance::Action::NewNote)
+        },
+    );
+
+    menu.add(
+        "File/Today's Note",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let mut quick_actions = quick_actions.clone();
+            move |_| quick_actions.run(editor_state::instance::Action::TodaysNote)
+        },
+    );
+
     menu.add(
         "File/Open...",
         fltk::enums::Shortcut::Ctrl | 'o',
         fltk::menu::MenuFlag::Normal,
-        move |_| {
-            if let Some(file_path) =
-                fltk::dialog::file_chooser("Open File", "*.{txt,rs,md,log}", ".", false)
-            {
-                menu_backend.borrow_mut().open_file(file_path).unwrap();
-                text_editor.on_content_changed();
+        {
+            let session = session.clone();
+            move |_| {
+                if let Some(file_path) =
+                    fltk::dialog::file_chooser("Open File", "*.{txt,rs,md,log,csv,tsv}", ".", false)
+                {
+                    if open_as_grid(std::path::Path::new(&file_path)) {
+                        return;
+                    }
 
-                fltk::app::redraw();
+                    remember_view(&text_editor, &mut session.borrow_mut());
+                    menu_backend.borrow_mut().open_file(&file_path).unwrap();
+                    text_editor.on_content_changed();
+                    text_editor.reset_read_position();
+
+                    if let Some(view) = session.borrow().recall(std::path::Path::new(&file_path)) {
+                        text_editor.restore_view_state(&view);
+                    }
+
+                    status_bar.set_label(&menu_backend.borrow().indent_label());
+
+                    fltk::app::redraw();
+                }
             }
         },
     );
@@ -35,56 +331,3547 @@ pub fn main() {
         "File/Save...",
         fltk::enums::Shortcut::Ctrl | 's',
         fltk::menu::MenuFlag::Normal,
-        move |_| {
-            // 1. DANGEROUS ZONE AVERTED: Just check if we need a path, then DROP the borrow immediately.
-            let needs_path = {
-                let text_state = text_editor_state.borrow();
-                let d = text_state.doc.borrow();
-                d.text_buffer.path().is_none()
-            }; // The `text_state` and `d` borrows end right here!
-
-            let selected_path = if needs_path {
-                // 2. SAFE ZONE: We hold no borrows here. The timer can happily fire in the background.
+        {
+            let save_queue = save_queue.clone();
+            let mut status_bar = status_frame.clone();
+            move |_| {
+                // 1. DANGEROUS ZONE AVERTED: Just check if we need a path, then DROP the borrow immediately.
+                let needs_path = {
+                    let text_state = text_editor_state.borrow();
+                    let d = text_state.doc.borrow();
+                    d.text_buffer.path().is_none()
+                }; // The `text_state` and `d` borrows end right here!
+
+                let selected_path = if needs_path {
+                    // 2. SAFE ZONE: We hold no borrows here. The timer can happily fire in the background.
+                    let mut dialog = fltk::dialog::NativeFileChooser::new(
+                        fltk::dialog::NativeFileChooserType::BrowseSaveFile,
+                    );
+
+                    dialog.set_title("Save File As...");
+                    // FIX: Changed /t to \t so the native dialog parses the categories correctly
+                    dialog.set_filter("Text\t*.txt\nRust\t*.rs\nMarkdown\t*.md\nAll\t*.*");
+
+                    dialog.show();
+
+                    let path = dialog.filename();
+
+                    if path.as_os_str().is_empty() {
+                        return; // User cancelled
+                    }
+                    Some(path)
+                } else {
+                    None
+                };
+
+                // 3. RE-BORROW: We have our path (or know we don't need one), so borrow again just to save.
+                let text_state = text_editor_state.borrow_mut();
+                let mut d = text_state.doc.borrow_mut();
+
+                if let Some(path) = selected_path {
+                    if let Err(err) = d.save_as(path.as_path()) {
+                        tracing::error!("Error saving file: {:?}", err);
+                    }
+                } else {
+                    let mut queue = save_queue.borrow_mut();
+
+                    match d.save_or_defer(&mut queue) {
+                        Ok(editor_state::save_queue::SaveOutcome::Saved) => {}
+                        Ok(editor_state::save_queue::SaveOutcome::Deferred) => {
+                            status_bar.set_label(&status_label(&d, &queue));
+                        }
+                        Err(err) => {
+                            tracing::error!("Error saving file: {:?}", err);
+                            fltk::dialog::alert_default(&format!("Couldn't save the note: {err}"));
+                        }
+                    }
+                }
+
+                // Save transforms may have rewritten the buffer.
+                fltk::app::redraw();
+            }
+        },
+    );
+
+    // Writes saves that were parked in the local cache once their drive answers again.
+    fltk::app::add_timeout3(10.0, {
+        let backend = backend.clone();
+        let save_queue = save_queue.clone();
+        let mut status_bar = status_frame.clone();
+        move |handle| {
+            let mut queue = save_queue.borrow_mut();
+
+            if !queue.pending().is_empty() {
+                queue.poll();
+                queue.retry();
+
+                if let Ok(d) = backend.try_borrow() {
+                    status_bar.set_label(&status_label(&d, &queue));
+                }
+            }
+            fltk::app::repeat_timeout3(10.0, handle);
+        }
+    });
+
+    menu.add(
+        "File/Revert",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            let mut editor = text_editor_view.clone();
+            move |_| {
+                if let Err(err) = backend.borrow_mut().revert() {
+                    tracing::error!("Error reverting file: {:?}", err);
+                    return;
+                }
+
+                editor.on_content_changed();
+                fltk::app::redraw();
+            }
+        },
+    );
+
+    menu.add(
+        "File/Quick Capture...",
+        fltk::enums::Shortcut::Ctrl | fltk::enums::Shortcut::Alt | ' ',
+        fltk::menu::MenuFlag::Normal,
+        {
+            let mut quick_actions = quick_actions.clone();
+            move |_| quick_actions.run(editor_state::instance::Action::QuickCapture)
+        },
+    );
+
+    menu.add(
+        "File/Set Inbox Note...",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        |_| {
+            let mut dialog = fltk::dialog::NativeFileChooser::new(
+                fltk::dialog::NativeFileChooserType::BrowseSaveFile,
+            );
+
+            dialog.set_title("Quick Captures Go To...");
+            dialog.set_filter("Markdown\t*.md");
+            if let Some(inbox) = editor_state::capture::inbox() {
+                let _ = dialog.set_directory(&inbox.parent().unwrap_or(&inbox));
+                dialog.set_preset_file(&inbox.file_name().unwrap_or_default().to_string_lossy());
+            }
+            dialog.show();
+
+            let inbox = dialog.filename();
+
+            if !inbox.as_os_str().is_empty()
+                && let Err(err) = editor_state::capture::set_inbox(&inbox)
+            {
+                tracing::error!("Error saving inbox choice: {:?}", err);
+            }
+        },
+    );
+
+    menu.add(
+        "File/Edit History/Export...",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            move |_| {
                 let mut dialog = fltk::dialog::NativeFileChooser::new(
                     fltk::dialog::NativeFileChooserType::BrowseSaveFile,
                 );
 
-                dialog.set_title("Save File As...");
-                // FIX: Changed /t to \t so the native dialog parses the categories correctly
-                dialog.set_filter("Text\t*.txt\nRust\t*.rs\nMarkdown\t*.md\nAll\t*.*");
+                dialog.set_title("Export Edit History As...");
+                dialog.set_filter("Edit history\t*.mnhl");
+                dialog.show();
+
+                let path = dialog.filename();
+
+                if path.as_os_str().is_empty() {
+                    return;
+                }
+
+                let log = editor_state::history_log::export(&backend.borrow());
+
+                if let Err(err) = std::fs::write(path.with_extension("mnhl"), log) {
+                    tracing::error!("Error exporting edit history: {:?}", err);
+                    fltk::dialog::alert_default(&format!("Couldn't export the history: {err}"));
+                }
+            }
+        },
+    );
+
+    menu.add(
+        "File/Edit History/Import...",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            move |_| {
+                let mut dialog = fltk::dialog::NativeFileChooser::new(
+                    fltk::dialog::NativeFileChooserType::BrowseFile,
+                );
 
+                dialog.set_title("Import Edit History...");
+                dialog.set_filter("Edit history\t*.mnhl");
                 dialog.show();
 
                 let path = dialog.filename();
 
                 if path.as_os_str().is_empty() {
-                    return; // User cancelled
+                    return;
                 }
-                Some(path)
-            } else {
-                None
-            };
 
-            // 3. RE-BORROW: We have our path (or know we don't need one), so borrow again just to save.
-            let text_state = text_editor_state.borrow_mut();
-            let mut d = text_state.doc.borrow_mut();
+                let imported = std::fs::read(&path).and_then(|log| {
+                    editor_state::history_log::import(&mut backend.borrow_mut(), &log)
+                });
+
+                if let Err(err) = imported {
+                    tracing::error!("Error importing edit history: {:?}", err);
+                    fltk::dialog::alert_default(&format!("Couldn't import the history: {err}"));
+                }
+            }
+        },
+    );
+
+    menu.add(
+        "File/Rename Note...",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            move |_| {
+                // No borrows are held while the dialogs run their event loops.
+                let Some(path) = backend
+                    .borrow()
+                    .text_buffer
+                    .path()
+                    .map(std::path::Path::to_path_buf)
+                else {
+                    fltk::dialog::alert_default("Save the note before renaming it.");
+                    return;
+                };
+                let old_name = editor_state::workspace::note_name(&path)
+                    .unwrap_or_default()
+                    .to_string();
+
+                let Some(new_name) = fltk::dialog::input_default("Rename note to:", &old_name)
+                    .map(|name| name.trim().to_string())
+                    .filter(|name| !name.is_empty() && *name != old_name)
+                else {
+                    return;
+                };
+
+                let referencing = path
+                    .parent()
+                    .map(|root| editor_state::workspace::referencing_notes(root, &old_name))
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|note| *note != path)
+                    .collect::<Vec<_>>();
+                let update_links = referencing.is_empty()
+                    || fltk::dialog::choice2_default(
+                        &format!(
+                            "Update links to [[{old_name}]] in {} note(s)?",
+                            referencing.len()
+                        ),
+                        "Keep Links",
+                        "Update Links",
+                        "",
+                    ) == Some(1);
+
+                if let Err(err) = backend.borrow_mut().rename_note(&new_name, update_links) {
+                    fltk::dialog::alert_default(&format!("Couldn't rename note: {err}"));
+                }
+
+                fltk::app::redraw();
+            }
+        },
+    );
+
+    menu.add(
+        "File/Extract Selection to New Note...",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            let mut status_bar = status_frame.clone();
+            move |_| {
+                if backend.borrow().cursor.no_selection() {
+                    fltk::dialog::alert_default("Select the text to move into a new note.");
+                    return;
+                }
 
-            if let Some(path) = selected_path {
-                match d.text_buffer.save_as(path.as_path()) {
-                    Ok(_) => println!("Success! Saved to {:?}", path),
-                    Err(err) => println!("Error saving file: {:?}", err),
+                let Some(name) = fltk::dialog::input_default("New note name:", "")
+                    .map(|name| name.trim().to_string())
+                    .filter(|name| !name.is_empty())
+                else {
+                    return;
                 };
-            } else {
-                match d.text_buffer.save() {
-                    Ok(_) => println!("Success! Saved to existing path."),
-                    Err(err) => println!("Error saving file: {:?}", err),
+
+                match backend.borrow_mut().extract_selection(&name) {
+                    Ok(path) => {
+                        status_bar.set_label(&format!("Moved to {}", path.display()));
+                        fltk::app::redraw();
+                    }
+                    Err(err) => {
+                        tracing::error!("Error extracting note: {:?}", err);
+                        fltk::dialog::alert_default(&format!("Couldn't extract the note: {err}"));
+                    }
                 }
             }
         },
     );
 
-    win.end();
-    win.show();
+    menu.add(
+        "File/Merge Note into Current...",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            let mut status_bar = status_frame.clone();
+            move |_| {
+                let Some(path) = backend
+                    .borrow()
+                    .text_buffer
+                    .path()
+                    .map(std::path::Path::to_path_buf)
+                else {
+                    fltk::dialog::alert_default("Save the note before merging into it.");
+                    return;
+                };
+                let Some(root) = path.parent() else {
+                    return;
+                };
+                let notes: Vec<_> = editor_state::workspace::notes(root)
+                    .into_iter()
+                    .filter(|note| *note != path)
+                    .collect();
+                let labels: Vec<String> = notes
+                    .iter()
+                    .map(|note| {
+                        note.strip_prefix(root)
+                            .unwrap_or(note)
+                            .display()
+                            .to_string()
+                    })
+                    .collect();
+                let Some(i) = ui::pick("Merge Note into Current", &labels) else {
+                    return;
+                };
+
+                if fltk::dialog::choice2_default(
+                    &format!("Append {} to this note and delete its file?", labels[i]),
+                    "Cancel",
+                    "Merge",
+                    "",
+                ) != Some(1)
+                {
+                    return;
+                }
+
+                match backend.borrow_mut().merge_note(&notes[i]) {
+                    Ok(updated) => {
+                        status_bar.set_label(&format!(
+                            "Merged {}, links updated in {} note(s)",
+                            labels[i],
+                            updated.len()
+                        ));
+                        fltk::app::redraw();
+                    }
+                    Err(err) => {
+                        tracing::error!("Error merging note: {:?}", err);
+                        fltk::dialog::alert_default(&format!("Couldn't merge the note: {err}"));
+                    }
+                }
+            }
+        },
+    );
+
+    for (label, archive) in [("File/Archive Note", true), ("File/Unarchive Note", false)] {
+        let backend = backend.clone();
+        let mut status_bar = status_frame.clone();
+
+        menu.add(
+            label,
+            fltk::enums::Shortcut::None,
+            fltk::menu::MenuFlag::Normal,
+            move |_| match backend.borrow_mut().archive_note(archive) {
+                Ok(path) => {
+                    status_bar.set_label(&format!("Moved to {}", path.display()));
+                    fltk::app::redraw();
+                }
+                Err(err) => {
+                    tracing::error!("Error archiving note: {:?}", err);
+                    fltk::dialog::alert_default(&format!("Couldn't move the note: {err}"));
+                }
+            },
+        );
+    }
+
+    menu.add(
+        "File/Compare With...",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            move |_| {
+                let Some(other) =
+                    fltk::dialog::file_chooser("Compare With", "*.{txt,rs,md,log}", ".", false)
+                else {
+                    return;
+                };
+                let other_text = match std::fs::read_to_string(&other) {
+                    Ok(text) => text,
+                    Err(err) => {
+                        tracing::error!("Error reading file: {:?}", err);
+                        return;
+                    }
+                };
+
+                let d = backend.borrow();
+                let title = |path: Option<&std::path::Path>| {
+                    path.and_then(std::path::Path::file_name)
+                        .map_or("Untitled".to_string(), |name| {
+                            name.to_string_lossy().into_owned()
+                        })
+                };
+
+                ui::DiffView::open(
+                    &title(d.text_buffer.path()),
+                    d.get_lines_stripped(0..d.get_line_count()),
+                    &title(Some(std::path::Path::new(&other))),
+                    other_text.lines().map(str::to_string).collect(),
+                );
+            }
+        },
+    );
+
+    menu.add(
+        "File/Export HTML...",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            let editor_state = text_editor_view.state.clone();
+            move |_| {
+                let current = editor_state.borrow().theme;
+                let Some(theme) = ui::export_dialog(current) else {
+                    return;
+                };
+                let title = backend
+                    .borrow()
+                    .text_buffer
+                    .path()
+                    .and_then(editor_state::workspace::note_name)
+                    .unwrap_or("Untitled")
+                    .to_string();
+                let mut dialog = fltk::dialog::NativeFileChooser::new(
+                    fltk::dialog::NativeFileChooserType::BrowseSaveFile,
+                );
+
+                dialog.set_title("Export HTML");
+                dialog.set_filter("HTML\t*.html");
+                dialog.set_preset_file(&format!("{title}.html"));
+                dialog.show();
+
+                let target = dialog.filename();
+
+                if target.as_os_str().is_empty() {
+                    return;
+                }
+
+                let html = {
+                    let d = backend.borrow();
+                    editor_state::export::to_html(
+                        &d.get_lines_stripped(0..d.get_line_count()),
+                        &title,
+                        &theme.export_palette(),
+                    )
+                };
+
+                if let Err(err) = std::fs::write(&target, html) {
+                    fltk::dialog::alert_default(&format!("Couldn't export: {err}"));
+                }
+            }
+        },
+    );
+
+    for (label, format, email) in [
+        ("File/Share/Email as Text", share::Format::PlainText, true),
+        ("File/Share/Email as HTML", share::Format::Html, true),
+        (
+            "File/Share/Share as Text...",
+            share::Format::PlainText,
+            false,
+        ),
+        ("File/Share/Share as HTML...", share::Format::Html, false),
+    ] {
+        menu.add(
+            label,
+            fltk::enums::Shortcut::None,
+            fltk::menu::MenuFlag::Normal,
+            {
+                let backend = backend.clone();
+                let editor_state = text_editor_view.state.clone();
+                let mut status_bar = status_frame.clone();
+                move |_| {
+                    let palette = editor_state.borrow().theme.export_palette();
+                    let shared = {
+                        let d = backend.borrow();
+                        let title = d
+                            .text_buffer
+                            .path()
+                            .and_then(editor_state::workspace::note_name)
+                            .unwrap_or("Untitled");
+
+                        share::Shared::new(
+                            title,
+                            &d.get_lines_stripped(0..d.get_line_count()),
+                            format,
+                            &palette,
+                        )
+                    };
+                    let shared = if email {
+                        share::email(&shared).map(|()| "Opened in the mail app".to_string())
+                    } else {
+                        share::share(&shared).map(|path| format!("Shared {}", path.display()))
+                    };
+
+                    match shared {
+                        Ok(message) => status_bar.set_label(&message),
+                        Err(err) => fltk::dialog::alert_default(&format!("Couldn't share: {err}")),
+                    }
+                }
+            },
+        );
+    }
+
+    menu.add(
+        "File/Publish Site...",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            let editor_state = text_editor_view.state.clone();
+            let workspace_root = workspace_root.clone();
+            let mut status_bar = status_frame.clone();
+            move |_| {
+                let Some(root) = workspace_root() else {
+                    fltk::dialog::alert_default("Open a note to publish its folder.");
+                    return;
+                };
+                let current = editor_state.borrow().theme;
+                let Some(theme) = ui::export_dialog(current) else {
+                    return;
+                };
+                let mut dialog = fltk::dialog::NativeFileChooser::new(
+                    fltk::dialog::NativeFileChooserType::BrowseDir,
+                );
+
+                dialog.set_title("Publish Site To");
+                dialog.show();
+
+                let out = dialog.filename();
+
+                if out.as_os_str().is_empty() {
+                    return;
+                }
+
+                let include_archived = backend.borrow().settings.include_archived;
+
+                match editor_state::export::publish(
+                    &root,
+                    &out,
+                    &theme.export_palette(),
+                    include_archived,
+                ) {
+                    Ok(count) => status_bar
+                        .set_label(&format!("Published {count} note(s) to {}", out.display())),
+                    Err(err) => {
+                        tracing::error!("Error publishing site: {:?}", err);
+                        fltk::dialog::alert_default(&format!("Couldn't publish: {err}"));
+                    }
+                }
+            }
+        },
+    );
+
+    menu.add(
+        "File/Import OPML...",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let mut quick_actions = quick_actions.clone();
+            move |_| {
+                let mut dialog = fltk::dialog::NativeFileChooser::new(
+                    fltk::dialog::NativeFileChooserType::BrowseFile,
+                );
+
+                dialog.set_title("Import OPML");
+                dialog.set_filter("OPML\t*.{opml,xml}");
+                dialog.show();
+
+                let source = dialog.filename();
+
+                if source.as_os_str().is_empty() {
+                    return;
+                }
+
+                let lines = match std::fs::read_to_string(&source)
+                    .and_then(|xml| editor_state::opml::import(&xml))
+                {
+                    Ok(lines) => lines,
+                    Err(err) => {
+                        tracing::error!("Error importing OPML: {:?}", err);
+                        fltk::dialog::alert_default(&format!("Couldn't import: {err}"));
+                        return;
+                    }
+                };
+
+                let mut dialog = fltk::dialog::NativeFileChooser::new(
+                    fltk::dialog::NativeFileChooserType::BrowseSaveFile,
+                );
+
+                dialog.set_title("Save Imported Note");
+                dialog.set_filter("Markdown\t*.md");
+                dialog.set_preset_file(&source.with_extension("md").to_string_lossy());
+                dialog.show();
+
+                let target = dialog.filename();
+
+                if target.as_os_str().is_empty() {
+                    return;
+                }
+                if let Err(err) = std::fs::write(&target, lines.join("\n") + "\n") {
+                    fltk::dialog::alert_default(&format!("Couldn't save: {err}"));
+                    return;
+                }
+                quick_actions.open(&target);
+            }
+        },
+    );
+
+    menu.add(
+        "File/Export OPML...",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            move |_| {
+                let title = backend
+                    .borrow()
+                    .text_buffer
+                    .path()
+                    .and_then(editor_state::workspace::note_name)
+                    .unwrap_or("Untitled")
+                    .to_string();
+                let mut dialog = fltk::dialog::NativeFileChooser::new(
+                    fltk::dialog::NativeFileChooserType::BrowseSaveFile,
+                );
+
+                dialog.set_title("Export OPML");
+                dialog.set_filter("OPML\t*.opml");
+                dialog.set_preset_file(&format!("{title}.opml"));
+                dialog.show();
+
+                let target = dialog.filename();
+
+                if target.as_os_str().is_empty() {
+                    return;
+                }
+
+                let opml = {
+                    let d = backend.borrow();
+                    editor_state::opml::export(&d.get_lines_stripped(0..d.get_line_count()), &title)
+                };
+
+                if let Err(err) = std::fs::write(&target, opml) {
+                    fltk::dialog::alert_default(&format!("Couldn't export: {err}"));
+                }
+            }
+        },
+    );
+
+    type SaveTransformToggle = fn(&mut editor_state::save_transforms::SaveTransforms) -> &mut bool;
+
+    let save_transform_toggles: [(&str, SaveTransformToggle); 4] = [
+        ("File/On Save/Trim Trailing Whitespace", |t| {
+            &mut t.trim_trailing_whitespace
+        }),
+        ("File/On Save/Ensure Final Newline", |t| {
+            &mut t.ensure_final_newline
+        }),
+        ("File/On Save/Collapse Blank Lines", |t| {
+            &mut t.collapse_blank_lines
+        }),
+        ("File/On Save/Update Table of Contents", |t| {
+            &mut t.update_toc
+        }),
+    ];
+
+    for (label, field) in save_transform_toggles {
+        let backend = backend.clone();
+        let flag = if *field(&mut backend.borrow_mut().save_transforms) {
+            fltk::menu::MenuFlag::Toggle | fltk::menu::MenuFlag::Value
+        } else {
+            fltk::menu::MenuFlag::Toggle
+        };
+
+        menu.add(label, fltk::enums::Shortcut::None, flag, move |m| {
+            let enabled = m.mvalue().is_some_and(|item| item.value());
+            *field(&mut backend.borrow_mut().save_transforms) = enabled;
+        });
+    }
+
+    menu.add(
+        "File/Cooperative File Access",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Toggle,
+        {
+            let backend = backend.clone();
+            move |m| {
+                let mut d = backend.borrow_mut();
+
+                d.settings.cooperative_files = m.mvalue().is_some_and(|item| item.value());
+                let mode = d.settings.access_mode();
+                d.text_buffer.set_access_mode(mode);
+            }
+        },
+    );
+
+    menu.add(
+        "File/Record Line Edit Times",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Toggle,
+        {
+            let backend = backend.clone();
+            move |m| {
+                let mut d = backend.borrow_mut();
+
+                d.settings.line_times = m.mvalue().is_some_and(|item| item.value());
+                let on = d.settings.line_times;
+                d.record_line_times(on);
+            }
+        },
+    );
+
+    // Set through File/Sync; later syncs go to the same place. A saved WebDAV account
+    // whose password is in the keyring is picked up on start.
+    let sync_provider: std::rc::Rc<std::cell::RefCell<Option<SharedSyncProvider>>> =
+        std::rc::Rc::new(std::cell::RefCell::new(
+            webdav::WebDavAccount::default_path()
+                .and_then(|path| webdav::WebDavAccount::load(&path))
+                .and_then(
+                    |account| match webdav::WebDavProvider::from_keyring(&account) {
+                        Ok(provider) => Some(std::sync::Arc::new(provider) as SharedSyncProvider),
+                        Err(err) => {
+                            tracing::warn!("WebDAV sync not started: {}", err);
+                            None
+                        }
+                    },
+                ),
+        ));
+    let background_sync = std::rc::Rc::new(std::cell::RefCell::new(
+        editor_state::sync::BackgroundSync::default(),
+    ));
+
+    menu.add(
+        "File/Sync/Choose Sync Folder...",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let sync_provider = sync_provider.clone();
+            move |_| {
+                let mut dialog = fltk::dialog::NativeFileChooser::new(
+                    fltk::dialog::NativeFileChooserType::BrowseDir,
+                );
+
+                dialog.set_title("Sync Notes With...");
+                dialog.show();
+
+                let root = dialog.filename();
+
+                if !root.as_os_str().is_empty() {
+                    *sync_provider.borrow_mut() =
+                        Some(std::sync::Arc::new(editor_state::sync::FolderProvider {
+                            root,
+                        }));
+                }
+            }
+        },
+    );
+
+    menu.add(
+        "File/Sync/Connect to WebDAV...",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let sync_provider = sync_provider.clone();
+            move |_| {
+                let account_path = webdav::WebDavAccount::default_path();
+                let saved = account_path
+                    .as_deref()
+                    .and_then(webdav::WebDavAccount::load)
+                    .unwrap_or_default();
+
+                let Some(url) = fltk::dialog::input_default("WebDAV folder URL:", &saved.url)
+                    .map(|url| url.trim().to_string())
+                    .filter(|url| !url.is_empty())
+                else {
+                    return;
+                };
+                let Some(user) = fltk::dialog::input_default("User name:", &saved.user) else {
+                    return;
+                };
+                let Some(password) = fltk::dialog::password_default("Password:", "") else {
+                    return;
+                };
+                let mut account = webdav::WebDavAccount {
+                    url,
+                    user,
+                    allow_http: false,
+                };
+
+                if account.is_http() {
+                    account.allow_http = fltk::dialog::choice2_default(
+                        "This server isn't using HTTPS, so your password and notes would \
+                         travel unencrypted. Connect anyway?",
+                        "Cancel",
+                        "Connect Anyway",
+                        "",
+                    ) == Some(1);
+                    if !account.allow_http {
+                        return;
+                    }
+                }
+
+                if let Some(path) = &account_path
+                    && let Err(err) = account.save(path)
+                {
+                    tracing::error!("Error saving WebDAV account: {:?}", err);
+                }
+                // Without the keyring, sync still works until the app quits.
+                if let Err(err) = account.store_password(&password) {
+                    tracing::error!("Error storing WebDAV password: {:?}", err);
+                    fltk::dialog::alert_default(&format!(
+                        "Couldn't store the password in the system keyring: {err}"
+                    ));
+                }
+
+                match webdav::WebDavProvider::new(&account, password) {
+                    Ok(provider) => {
+                        *sync_provider.borrow_mut() = Some(std::sync::Arc::new(provider))
+                    }
+                    Err(err) => fltk::dialog::alert_default(&format!("Couldn't connect: {err}")),
+                }
+            }
+        },
+    );
+
+    menu.add(
+        "File/Sync/Sync Now",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            let sync_provider = sync_provider.clone();
+            let background_sync = background_sync.clone();
+            move |_| {
+                let started = start_sync(
+                    &backend.borrow(),
+                    sync_provider.borrow().as_ref(),
+                    &mut background_sync.borrow_mut(),
+                );
+
+                if let Err(reason) = started {
+                    fltk::dialog::alert_default(reason);
+                }
+            }
+        },
+    );
+
+    fltk::app::add_timeout3(
+        editor_state::sync::BackgroundSync::INTERVAL.as_secs_f64(),
+        {
+            let backend = backend.clone();
+            let sync_provider = sync_provider.clone();
+            let background_sync = background_sync.clone();
+            move |handle| {
+                // Not set up yet, or the open note was never saved: try again next time.
+                if let Ok(d) = backend.try_borrow() {
+                    let _ = start_sync(
+                        &d,
+                        sync_provider.borrow().as_ref(),
+                        &mut background_sync.borrow_mut(),
+                    );
+                }
+                fltk::app::repeat_timeout3(
+                    editor_state::sync::BackgroundSync::INTERVAL.as_secs_f64(),
+                    handle,
+                );
+            }
+        },
+    );
+
+    menu.add(
+        "File/Sync/Merge Conflict Copy...",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            let editor = text_editor_view.clone();
+            move |_| {
+                let Some(root) = backend
+                    .borrow()
+                    .text_buffer
+                    .path()
+                    .and_then(std::path::Path::parent)
+                    .map(std::path::Path::to_path_buf)
+                else {
+                    fltk::dialog::alert_default("Open the note to merge into first.");
+                    return;
+                };
+                let mut dialog = fltk::dialog::NativeFileChooser::new(
+                    fltk::dialog::NativeFileChooserType::BrowseFile,
+                );
+
+                dialog.set_title("Merge With Conflict Copy...");
+                let _ = dialog.set_directory(&root);
+                dialog.show();
+
+                let copy = dialog.filename();
+
+                if !copy.as_os_str().is_empty() {
+                    open_merge(&backend, &editor, root, copy);
+                }
+            }
+        },
+    );
+
+    // Picks up finished syncs.
+    fltk::app::add_timeout3(1.0, {
+        let backend = backend.clone();
+        let mut sync_panel = sync_panel.clone();
+        let mut editor = text_editor_view.clone();
+        move |handle| {
+            fltk::app::repeat_timeout3(1.0, handle);
+
+            let Ok(mut d) = backend.try_borrow_mut() else {
+                return;
+            };
+            let Some(run) = background_sync.borrow_mut().poll() else {
+                return;
+            };
+            let (report, statuses) = match run.result {
+                Ok(done) => done,
+                Err(err) => {
+                    tracing::error!("Error syncing notes: {:?}", err);
+                    sync_panel.show_error(&err.to_string());
+                    return;
+                }
+            };
+
+            sync_panel.show_statuses(&statuses);
+
+            // Pick up a newer copy of the open note, unless it has unsaved edits.
+            let pulled = d.text_buffer.path().and_then(|path| {
+                editor_state::sync::note_key(&run.root, path)
+                    .filter(|name| report.pulled.contains(name))
+            });
+
+            if pulled.is_some() && !d.text_buffer.is_dirty() {
+                if let Err(err) = d.revert() {
+                    tracing::error!("Error reloading synced note: {:?}", err);
+                }
+                drop(d);
+                editor.on_content_changed();
+            }
+
+            let Ok(d) = backend.try_borrow() else {
+                return;
+            };
+            let open_name = d
+                .text_buffer
+                .path()
+                .and_then(|path| editor_state::sync::note_key(&run.root, path));
+            let open_conflict = report
+                .conflicts
+                .iter()
+                .find(|(name, _)| Some(name) == open_name.as_ref())
+                .map(|(_, copy)| copy.clone());
+
+            drop(d);
+            if let Some(copy) = open_conflict {
+                if fltk::dialog::choice2_default(
+                    "This note changed here and on the server since the last sync.",
+                    "Later",
+                    "Merge Now",
+                    "",
+                ) == Some(1)
+                {
+                    open_merge(&backend, &editor, run.root.clone(), copy);
+                }
+            } else if !report.conflicts.is_empty() {
+                let names: Vec<String> = report
+                    .conflicts
+                    .iter()
+                    .map(|(_, copy)| copy.display().to_string())
+                    .collect();
+
+                fltk::dialog::alert_default(&format!(
+                    "Some notes changed on both sides. The other copies were saved as:\n{}",
+                    names.join("\n")
+                ));
+            }
+            fltk::app::redraw();
+        }
+    });
+
+    // The note being edited together with others, if any.
+    let pair_session: std::rc::Rc<std::cell::RefCell<Option<editor_state::pair::PairSession>>> =
+        std::rc::Rc::new(std::cell::RefCell::new(None));
+
+    menu.add(
+        "File/Share/Host Session...",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            let pair_session = pair_session.clone();
+            move |_| {
+                if pair_session.borrow().is_some() {
+                    fltk::dialog::alert_default("Leave the current session first.");
+                    return;
+                }
+                let Some(addr) = fltk::dialog::input_default(
+                    "Address to share this note on (0.0.0.0:port lets others on your network join):",
+                    &editor_state::pair::PairSession::default_address(),
+                ) else {
+                    return;
+                };
+
+                match editor_state::pair::PairSession::host(
+                    &mut backend.borrow_mut(),
+                    addr.trim(),
+                    &user_name(),
+                ) {
+                    Ok(session) => {
+                        let address = if session.is_public() {
+                            lan_address().map_or_else(
+                                || "this computer's address".to_string(),
+                                |ip| ip.to_string(),
+                            )
+                        } else {
+                            "127.0.0.1".to_string()
+                        };
+
+                        fltk::dialog::message_default(&format!(
+                            "Others can join at {address}:{} with the code {}",
+                            session.port().unwrap_or_default(),
+                            session.token().unwrap_or_default()
+                        ));
+                        *pair_session.borrow_mut() = Some(session);
+                    }
+                    Err(err) => {
+                        tracing::error!("Error hosting pair session: {:?}", err);
+                        fltk::dialog::alert_default(&format!("Couldn't share the note: {err}"));
+                    }
+                }
+            }
+        },
+    );
+
+    menu.add(
+        "File/Share/Join Session...",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            let pair_session = pair_session.clone();
+            let mut editor = text_editor_view.clone();
+            move |_| {
+                if pair_session.borrow().is_some() {
+                    fltk::dialog::alert_default("Leave the current session first.");
+                    return;
+                }
+                let Some(addr) = fltk::dialog::input_default("Session address (host:port):", "")
+                    .map(|addr| addr.trim().to_string())
+                    .filter(|addr| !addr.is_empty())
+                else {
+                    return;
+                };
+                let Some(token) = fltk::dialog::input_default("Session code:", "")
+                    .map(|token| token.trim().to_string())
+                else {
+                    return;
+                };
+                if fltk::dialog::choice2_default(
+                    "Joining replaces the text of the open note with the shared one.",
+                    "Cancel",
+                    "Join",
+                    "",
+                ) != Some(1)
+                {
+                    return;
+                }
+
+                let joined = editor_state::pair::PairSession::join(
+                    &mut backend.borrow_mut(),
+                    &addr,
+                    &token,
+                    &user_name(),
+                );
+
+                match joined {
+                    Ok(session) => {
+                        *pair_session.borrow_mut() = Some(session);
+                        editor.on_content_changed();
+                    }
+                    Err(err) => {
+                        tracing::error!("Error joining pair session: {:?}", err);
+                        fltk::dialog::alert_default(&format!("Couldn't join {addr}: {err}"));
+                    }
+                }
+            }
+        },
+    );
+
+    menu.add(
+        "File/Share/Leave Session",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            let pair_session = pair_session.clone();
+            let mut editor = text_editor_view.clone();
+            let save_queue = save_queue.clone();
+            let mut status_bar = status_frame.clone();
+            move |_| {
+                let Some(session) = pair_session.borrow_mut().take() else {
+                    return;
+                };
+                let mut d = backend.borrow_mut();
+
+                session.leave(&mut d);
+                status_bar.set_label(&status_label(&d, &save_queue.borrow()));
+                drop(d);
+                editor.state.borrow_mut().decorations.clear();
+                editor.on_content_changed();
+            }
+        },
+    );
+
+    // Exchanges edits and cursors with pair-editing peers. Their cursors are drawn as
+    // decorations and who's there is shown in the status bar.
+    fltk::app::add_timeout3(0.1, {
+        let backend = backend.clone();
+        let mut editor = text_editor_view.clone();
+        let save_queue = save_queue.clone();
+        let mut status_bar = status_frame.clone();
+        move |handle| {
+            fltk::app::repeat_timeout3(0.1, handle);
+
+            let mut session = pair_session.borrow_mut();
+            let Some(pair) = session.as_mut() else {
+                return;
+            };
+            let Ok(mut d) = backend.try_borrow_mut() else {
+                return;
+            };
+            let changed = pair.poll(&mut d);
+            let decorations: Vec<ui::Decoration> = pair
+                .peers()
+                .into_iter()
+                .map(|peer| ui::Decoration {
+                    row: peer.pos.row,
+                    col: peer.pos.col,
+                    color: editor_state::pair::peer_color(peer.site),
+                    label: peer.name,
+                })
+                .collect();
+            let presence = pair.presence_label();
+            let ended = !pair.is_connected();
+
+            if let Ok(queue) = save_queue.try_borrow() {
+                status_bar.set_label(&format!("{presence}    {}", status_label(&d, &queue)));
+            }
+            if ended && let Some(pair) = session.take() {
+                pair.leave(&mut d);
+            }
+            drop(d);
+            drop(session);
+
+            let Ok(mut state) = editor.state.try_borrow_mut() else {
+                return;
+            };
+            let moved = state.decorations != decorations;
+
+            state.decorations = decorations;
+            drop(state);
+            if changed || moved {
+                editor.on_content_changed();
+            }
+            if ended {
+                fltk::dialog::alert_default("The host ended the pair-editing session.");
+            }
+        }
+    });
+
+    let mut chrome = chrome::Chrome {
+        window: win.clone(),
+        menu: menu.clone(),
+        breadcrumb: breadcrumb_bar.frame.clone(),
+        outline: outline_panel.tree.clone(),
+        sync_panel: sync_panel.browser.clone(),
+        upcoming: upcoming_panel.browser.clone(),
+        diagnostics: diagnostics_panel.browser.clone(),
+        smart_folders: smart_folders.tree.clone(),
+        editor: text_editor_view.clone(),
+        status_bar: status_frame.clone(),
+        session: session.clone(),
+    };
+
+    menu.add(
+        "View/Outline",
+        // Ctrl+Shift+O jumps to a symbol instead.
+        fltk::enums::Shortcut::Ctrl | fltk::enums::Shortcut::Alt | 'o',
+        fltk::menu::MenuFlag::Toggle,
+        {
+            let mut chrome = chrome.clone();
+            move |_| {
+                chrome.relayout();
+                outline_panel.refresh();
+            }
+        },
+    );
+
+    menu.add(
+        "View/Sync Status",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Toggle,
+        {
+            let mut chrome = chrome.clone();
+            move |_| chrome.relayout()
+        },
+    );
+
+    let scheduler = std::rc::Rc::new(std::cell::RefCell::new(scheduler::Scheduler::default()));
+    menu.add(
+        "View/Smart Folders",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Toggle,
+        {
+            let mut chrome = chrome.clone();
+            move |_| chrome.relayout()
+        },
+    );
+
+    menu.add(
+        "View/Upcoming",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Toggle,
+        {
+            let mut chrome = chrome.clone();
+            let scheduler = scheduler.clone();
+            let workspace_root = workspace_root.clone();
+            move |_| {
+                if let Some(root) = workspace_root() {
+                    scheduler.borrow_mut().rescan(root);
+                }
+                chrome.relayout();
+            }
+        },
+    );
+
+    let refresh_diagnostics = {
+        let backend = backend.clone();
+        let workspace_root = workspace_root.clone();
+        let mut diagnostics_panel = diagnostics_panel.clone();
+        move || {
+            let Some(root) = workspace_root() else {
+                return;
+            };
+            let include_archived = backend
+                .try_borrow()
+                .is_ok_and(|d| d.settings.include_archived);
+
+            diagnostics_panel.show_report(
+                &editor_state::diagnostics::check(&root, include_archived),
+                &root,
+            );
+        }
+    };
+    menu.add(
+        "View/Link Diagnostics",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Toggle,
+        {
+            let mut chrome = chrome.clone();
+            let mut refresh_diagnostics = refresh_diagnostics.clone();
+            move |_| {
+                refresh_diagnostics();
+                chrome.relayout();
+            }
+        },
+    );
+
+    menu.add(
+        "View/Reset Panel Layout",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let mut chrome = chrome.clone();
+            move |_| {
+                chrome.session.borrow_mut().layout = Default::default();
+                chrome.restore_layout();
+            }
+        },
+    );
+
+    menu.add(
+        "View/Include Archived Notes",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Toggle,
+        {
+            let backend = backend.clone();
+            let scheduler = scheduler.clone();
+            let workspace_root = workspace_root.clone();
+            move |m| {
+                let on = m.mvalue().is_some_and(|item| item.value());
+
+                backend.borrow_mut().settings.include_archived = on;
+                scheduler.borrow_mut().include_archived = on;
+                if let Some(root) = workspace_root() {
+                    scheduler.borrow_mut().rescan(root);
+                }
+            }
+        },
+    );
+
+    menu.add(
+        "View/Notify When Reminders Are Due",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Toggle,
+        {
+            let scheduler = scheduler.clone();
+            move |m| scheduler.borrow_mut().notify = m.mvalue().is_some_and(|item| item.value())
+        },
+    );
+
+    upcoming_panel.browser.set_callback({
+        let upcoming_panel = upcoming_panel.clone();
+        let mut quick_actions = quick_actions.clone();
+        let mut editor = text_editor_view.clone();
+        move |_| {
+            if let Some(reminder) = upcoming_panel.selected() {
+                quick_actions.open(&reminder.path);
+                editor.jump_to_row(reminder.row);
+            }
+        }
+    });
+
+    // Clicking a problem goes to it; double-clicking a broken link offers to fix it.
+    diagnostics_panel.browser.set_callback({
+        let diagnostics_panel = diagnostics_panel.clone();
+        let backend = backend.clone();
+        let workspace_root = workspace_root.clone();
+        let mut quick_actions = quick_actions.clone();
+        let mut editor = text_editor_view.clone();
+        let mut refresh_diagnostics = refresh_diagnostics.clone();
+        move |_| {
+            let Some(diagnostic) = diagnostics_panel.selected() else {
+                return;
+            };
+            let link = match diagnostic {
+                ui::Diagnostic::Orphan(path) => {
+                    quick_actions.open(&path);
+                    return;
+                }
+                ui::Diagnostic::Broken(link) => link,
+            };
+
+            quick_actions.open(&link.path);
+            editor.jump_to_row(link.row);
+            if !fltk::app::event_clicks() {
+                return;
+            }
+
+            let fix = fltk::dialog::choice2_default(
+                &format!("There's no note called {}.", link.target),
+                "Cancel",
+                "Create Note",
+                "Remove Link",
+            );
+            let fixed = match fix {
+                Some(1) => workspace_root()
+                    .ok_or_else(|| {
+                        std::io::Error::new(std::io::ErrorKind::NotFound, "No workspace folder")
+                    })
+                    .and_then(|root| editor_state::diagnostics::create_missing(&root, &link.target))
+                    .map(|_| ()),
+                // In the open note as an undo step, elsewhere saved right away.
+                Some(2) if backend.borrow().text_buffer.path() == Some(link.path.as_path()) => {
+                    backend.borrow_mut().unlink(&link.target);
+                    editor.on_content_changed();
+                    Ok(())
+                }
+                Some(2) => {
+                    editor_state::workspace::remove_links(&link.path, &link.target).map(|_| ())
+                }
+                _ => return,
+            };
+
+            if let Err(err) = fixed {
+                tracing::error!("Error fixing link: {:?}", err);
+                fltk::dialog::alert_default(&format!("Couldn't fix the link: {err}"));
+            }
+            refresh_diagnostics();
+        }
+    });
+
+    // Re-indexes reminders now and then, the scan itself runs on a worker thread.
+    fltk::app::add_timeout3(0.0, {
+        let scheduler = scheduler.clone();
+        let workspace_root = workspace_root.clone();
+        move |handle| {
+            if let Some(root) = workspace_root() {
+                scheduler.borrow_mut().rescan(root);
+            }
+            fltk::app::repeat_timeout3(scheduler::Scheduler::INTERVAL, handle);
+        }
+    });
+
+    // Keeps the link diagnostics current while they're shown.
+    fltk::app::add_timeout3(indexer::Indexer::INTERVAL, {
+        let diagnostics = diagnostics_panel.browser.clone();
+        let mut refresh_diagnostics = refresh_diagnostics.clone();
+        move |handle| {
+            if diagnostics.visible() {
+                refresh_diagnostics();
+            }
+            fltk::app::repeat_timeout3(indexer::Indexer::INTERVAL, handle);
+        }
+    });
+
+    // Catches the search index up with changes made outside the app now and then.
+    fltk::app::add_timeout3(0.0, {
+        let indexer = indexer.clone();
+        let workspace_root = workspace_root.clone();
+        move |handle| {
+            if let Some(root) = workspace_root() {
+                indexer.borrow_mut().refresh(root);
+            }
+            fltk::app::repeat_timeout3(indexer::Indexer::INTERVAL, handle);
+        }
+    });
+
+    // Picks up finished refreshes, and feeds the open note to the index once saved.
+    fltk::app::add_timeout3(1.0, {
+        let indexer = indexer.clone();
+        let backend = backend.clone();
+        let workspace_root = workspace_root.clone();
+        move |handle| {
+            fltk::app::repeat_timeout3(1.0, handle);
+
+            let mut indexer = indexer.borrow_mut();
+
+            indexer.poll();
+            if let Some(root) = workspace_root()
+                && root != indexer.index().root
+            {
+                indexer.refresh(root);
+                return;
+            }
+
+            let Ok(doc) = backend.try_borrow() else {
+                return;
+            };
+            if doc.text_buffer.is_dirty() {
+                return;
+            }
+            if let Some(path) = doc.text_buffer.path() {
+                indexer.saved(path, || {
+                    (0..doc.get_line_count())
+                        .filter_map(|row| doc.get_line(row))
+                        .collect()
+                });
+            }
+        }
+    });
+
+    // Picks up finished scans and announces what came due.
+    fltk::app::add_timeout3(1.0, {
+        let scheduler = scheduler.clone();
+        let mut status_bar = status_frame.clone();
+        move |handle| {
+            fltk::app::repeat_timeout3(1.0, handle);
+
+            let mut scheduler = scheduler.borrow_mut();
+            let today = editor_state::reminders::Date::from_time(std::time::SystemTime::now());
+
+            if scheduler.poll() {
+                upcoming_panel.show_reminders(scheduler.reminders(), today);
+            }
+
+            let due = scheduler.newly_due(today);
+
+            if let [reminder, ..] = due.as_slice() {
+                status_bar.set_label(&format!("Due today: {}", reminder.text));
+            }
+            if scheduler.notify {
+                for reminder in &due {
+                    if let Err(err) = scheduler::notify(reminder) {
+                        tracing::error!("Error showing notification: {:?}", err);
+                    }
+                }
+            }
+        }
+    });
+
+    for (label, shortcut, delta) in [
+        ("View/Zoom In", fltk::enums::Shortcut::Ctrl | '=', 1),
+        ("View/Zoom Out", fltk::enums::Shortcut::Ctrl | '-', -1),
+        ("View/Reset Zoom", fltk::enums::Shortcut::Ctrl | '0', 0),
+    ] {
+        let mut editor = text_editor_view.clone();
+
+        menu.add(label, shortcut, fltk::menu::MenuFlag::Normal, move |_| {
+            editor.zoom(delta);
+        });
+    }
+
+    menu.add(
+        "View/Focus Mode",
+        fltk::enums::Shortcut::from_key(fltk::enums::Key::F11),
+        fltk::menu::MenuFlag::Normal,
+        {
+            let mut chrome = chrome.clone();
+            move |_| chrome.toggle_focus_mode()
+        },
+    );
+
+    menu.add(
+        "View/Dim Other Paragraphs in Focus Mode",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Toggle | fltk::menu::MenuFlag::Value,
+        {
+            let state = text_editor_view.state.clone();
+            move |m| {
+                state.borrow_mut().dim_unfocused = m.mvalue().is_some_and(|item| item.value());
+                fltk::app::redraw();
+            }
+        },
+    );
+
+    menu.add(
+        "View/Presentation Mode",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Toggle,
+        {
+            let state = text_editor_view.state.clone();
+            move |m| {
+                state.borrow_mut().redactor =
+                    m.mvalue().is_some_and(|item| item.value()).then(|| {
+                        std::rc::Rc::new(
+                            editor_state::redact::Redactor::default_path()
+                                .as_deref()
+                                .map(editor_state::redact::Redactor::load)
+                                .unwrap_or_default(),
+                        )
+                    });
+                fltk::app::redraw();
+            }
+        },
+    );
+
+    menu.add(
+        "View/Inline Images",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Toggle,
+        {
+            let mut editor = text_editor_view.clone();
+            move |m| {
+                editor.state.borrow_mut().inline_images =
+                    m.mvalue().is_some_and(|item| item.value());
+                editor.on_content_changed();
+            }
+        },
+    );
+
+    menu.add(
+        "View/Styled Markdown",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Toggle,
+        {
+            let mut editor = text_editor_view.clone();
+            move |m| {
+                editor.state.borrow_mut().rich_text = m.mvalue().is_some_and(|item| item.value());
+                editor.on_content_changed();
+            }
+        },
+    );
+
+    let theme_mode = std::rc::Rc::new(std::cell::Cell::new(
+        editor_state::theme::ThemeMode::default(),
+    ));
+    let theme_modes = [
+        (
+            "View/Theme/Follow System",
+            editor_state::theme::ThemeMode::FollowSystem,
+        ),
+        (
+            "View/Theme/Dark",
+            editor_state::theme::ThemeMode::Fixed(editor_state::theme::Theme::Dark),
+        ),
+        (
+            "View/Theme/Light",
+            editor_state::theme::ThemeMode::Fixed(editor_state::theme::Theme::Light),
+        ),
+    ];
+
+    for (label, mode) in theme_modes {
+        let flag = if mode == theme_mode.get() {
+            fltk::menu::MenuFlag::Radio | fltk::menu::MenuFlag::Value
+        } else {
+            fltk::menu::MenuFlag::Radio
+        };
+        let theme_mode = theme_mode.clone();
+        let mut editor = text_editor_view.clone();
+
+        menu.add(label, fltk::enums::Shortcut::None, flag, move |_| {
+            theme_mode.set(mode);
+            editor.set_theme(mode.resolve());
+        });
+    }
+
+    for shape in editor_state::caret::CaretShape::ALL {
+        let flag = if shape == editor_state::caret::CaretShape::default() {
+            fltk::menu::MenuFlag::Radio | fltk::menu::MenuFlag::Value
+        } else {
+            fltk::menu::MenuFlag::Radio
+        };
+        let backend = backend.clone();
+
+        menu.add(
+            &format!("View/Caret/{}", shape.name()),
+            fltk::enums::Shortcut::None,
+            flag,
+            move |_| {
+                backend.borrow_mut().settings.caret.shape = shape;
+                fltk::app::redraw();
+            },
+        );
+    }
+
+    menu.add(
+        "View/Caret/Blink",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Toggle | fltk::menu::MenuFlag::Value,
+        {
+            let backend = backend.clone();
+            move |m| {
+                let caret = &mut backend.borrow_mut().settings.caret;
+
+                caret.blink = if m.mvalue().is_some_and(|item| item.value()) {
+                    caret.blink.or(editor_state::caret::Caret::default().blink)
+                } else {
+                    None
+                };
+            }
+        },
+    );
+
+    text_editor_view
+        .clone()
+        .set_theme(theme_mode.get().resolve());
+    // Picks up OS light/dark switches (and, without an OS preference, nightfall).
+    fltk::app::add_timeout3(30.0, {
+        let mut editor = text_editor_view.clone();
+        move |handle| {
+            if theme_mode.get() == editor_state::theme::ThemeMode::FollowSystem {
+                let theme = theme_mode.get().resolve();
+
+                if theme != editor.state.borrow().theme {
+                    editor.set_theme(theme);
+                }
+            }
+            fltk::app::repeat_timeout3(30.0, handle);
+        }
+    });
+
+    // Nothing tells a mapped file that another program cut it short, so look every
+    // couple of seconds, before a read past the new end can crash the app.
+    fltk::app::add_timeout3(2.0, {
+        let backend = backend.clone();
+        let mut editor = text_editor_view.clone();
+        move |handle| {
+            let lost = backend
+                .try_borrow_mut()
+                .ok()
+                .and_then(|mut d| d.check_truncation());
+
+            if let Some(lost) = lost {
+                editor.on_content_changed();
+
+                if lost > 0
+                    && fltk::dialog::choice2_default(
+                        &format!(
+                            "Another program truncated this note on disk, and its last {lost} \
+                             bytes are gone. Keep what's left here, with your changes, or \
+                             reload the note as it is on disk now?"
+                        ),
+                        "Keep",
+                        "Reload",
+                        "",
+                    ) == Some(1)
+                {
+                    let reverted = backend.borrow_mut().revert();
+
+                    if let Err(err) = reverted {
+                        tracing::error!("Error reloading truncated file: {:?}", err);
+                    }
+                    editor.on_content_changed();
+                }
+            }
+            fltk::app::repeat_timeout3(2.0, handle);
+        }
+    });
+
+    menu.add(
+        "View/Performance HUD",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Toggle,
+        {
+            let mut editor = text_editor_view.clone();
+            move |m| {
+                editor.state.borrow_mut().perf_hud = m.mvalue().is_some_and(|item| item.value());
+                editor.on_content_changed();
+            }
+        },
+    );
+
+    menu.add(
+        "View/Column Ruler",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Toggle,
+        {
+            let mut editor = text_editor_view.clone();
+            move |m| {
+                editor.state.borrow_mut().column_ruler =
+                    m.mvalue().is_some_and(|item| item.value());
+                editor.on_content_changed();
+            }
+        },
+    );
+
+    menu.add(
+        "View/Elastic Tabstops",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Toggle,
+        {
+            let mut editor = text_editor_view.clone();
+            move |m| {
+                editor.state.borrow_mut().elastic_tabs =
+                    m.mvalue().is_some_and(|item| item.value());
+                editor.on_content_changed();
+            }
+        },
+    );
+
+    type GutterOption = fn(&mut ui::GutterClicks) -> &mut bool;
+    let gutter_clicks: [(&str, GutterOption); 3] = [
+        ("View/Gutter/Click Line Numbers to Select Lines", |clicks| {
+            &mut clicks.select_lines
+        }),
+        ("View/Gutter/Click Left of Numbers to Bookmark", |clicks| {
+            &mut clicks.bookmarks
+        }),
+        ("View/Gutter/Click Fold Markers to Fold", |clicks| {
+            &mut clicks.fold_markers
+        }),
+    ];
+    for (label, option) in gutter_clicks {
+        let editor_state = text_editor_view.state.clone();
+
+        menu.add(
+            label,
+            fltk::enums::Shortcut::None,
+            fltk::menu::MenuFlag::Toggle | fltk::menu::MenuFlag::Value,
+            move |m| {
+                *option(&mut editor_state.borrow_mut().gutter_clicks) =
+                    m.mvalue().is_some_and(|item| item.value());
+            },
+        );
+    }
+
+    for (label, preview) in [("View/New Window", false), ("View/Detach Preview", true)] {
+        menu.add(
+            label,
+            fltk::enums::Shortcut::None,
+            fltk::menu::MenuFlag::Normal,
+            {
+                let backend = backend.clone();
+                let state = text_editor_view.state.clone();
+                move |_| {
+                    let title = backend
+                        .borrow()
+                        .text_buffer
+                        .path()
+                        .and_then(editor_state::workspace::note_name)
+                        .unwrap_or("Untitled")
+                        .to_string();
+                    let title = if preview {
+                        format!("{title} — Preview")
+                    } else {
+                        title
+                    };
+                    let mut detached = ui::EditorWindow::open(&title, backend.clone(), preview);
+                    let (theme, font_size) = {
+                        let state = state.borrow();
+                        (state.theme, state.font_size)
+                    };
+
+                    detached.editor.state.borrow_mut().font_size = font_size;
+                    detached.editor.set_theme(theme);
+                }
+            },
+        );
+    }
+
+    menu.add(
+        "View/Debug Console",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        move |_| {
+            ui::DebugConsole::open(log.clone());
+        },
+    );
+
+    menu.add(
+        "Edit/Paste and Match Indentation",
+        fltk::enums::Shortcut::Ctrl | fltk::enums::Shortcut::Shift | 'v',
+        fltk::menu::MenuFlag::Normal,
+        {
+            let paste_state = paste_state.clone();
+            let paste_canvas = paste_canvas.clone();
+            move |_| {
+                paste_state.borrow_mut().paste_match_indent = true;
+                fltk::app::paste(&paste_canvas);
+            }
+        },
+    );
+
+    menu.add(
+        "Edit/Swap Selection with Clipboard",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let paste_state = paste_state.clone();
+            move |_| {
+                paste_state.borrow_mut().paste_swap = true;
+                fltk::app::paste(&paste_canvas);
+            }
+        },
+    );
+
+    menu.add(
+        "Edit/Insert Attachment...",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            move |_| {
+                let mut dialog = fltk::dialog::NativeFileChooser::new(
+                    fltk::dialog::NativeFileChooserType::BrowseMultiFile,
+                );
+
+                dialog.set_title("Insert Attachment");
+                dialog.show();
+
+                let sources = dialog.filenames();
+
+                if sources.is_empty() {
+                    return;
+                }
+                if let Err(err) = backend.borrow_mut().insert_attachments(&sources) {
+                    fltk::dialog::alert_default(&format!("Couldn't attach files: {err}"));
+                }
+
+                fltk::app::redraw();
+            }
+        },
+    );
+
+    type DocumentCommand = fn(&mut editor_state::document::Document);
+
+    let document_commands: &[(&str, fltk::enums::Shortcut, DocumentCommand)] = &[
+        ("Edit/Duplicate", fltk::enums::Shortcut::Ctrl | 'd', |d| {
+            d.duplicate()
+        }),
+        (
+            "Edit/Transpose Characters",
+            fltk::enums::Shortcut::Ctrl | 't',
+            |d| {
+                d.transpose_chars();
+            },
+        ),
+        (
+            "Edit/Swap Selection Ends",
+            fltk::enums::Shortcut::None,
+            |d| d.swap_anchor_and_head(),
+        ),
+        (
+            "Edit/Toggle Comment",
+            fltk::enums::Shortcut::Ctrl | '/',
+            |d| d.toggle_comment(),
+        ),
+        (
+            "Edit/Reindent Selection",
+            fltk::enums::Shortcut::None,
+            |d| d.reindent(editor_state::transform::Scope::Selection),
+        ),
+        ("Edit/Reindent Document", fltk::enums::Shortcut::None, |d| {
+            d.reindent(editor_state::transform::Scope::Document)
+        }),
+        (
+            "Edit/Transform/Upper Case",
+            fltk::enums::Shortcut::None,
+            |d| d.transform_text(d.scope, str::to_uppercase),
+        ),
+        (
+            "Edit/Transform/Lower Case",
+            fltk::enums::Shortcut::None,
+            |d| d.transform_text(d.scope, str::to_lowercase),
+        ),
+        (
+            "Edit/Transform/Title Case",
+            fltk::enums::Shortcut::None,
+            |d| d.transform_text(d.scope, editor_state::transform::title_case),
+        ),
+        (
+            "Edit/Transform/Reverse Lines",
+            fltk::enums::Shortcut::None,
+            |d| {
+                d.transform_lines(|mut lines| {
+                    lines.reverse();
+                    lines
+                })
+            },
+        ),
+        (
+            "Edit/Transform/Join Lines",
+            fltk::enums::Shortcut::Ctrl | 'j',
+            |d| d.join_lines(),
+        ),
+        (
+            "Edit/Insert or Update Table of Contents",
+            fltk::enums::Shortcut::None,
+            |d| {
+                d.update_toc();
+            },
+        ),
+        ("Edit/Section/Promote", fltk::enums::Shortcut::None, |d| {
+            d.change_section_level(-1);
+        }),
+        ("Edit/Section/Demote", fltk::enums::Shortcut::None, |d| {
+            d.change_section_level(1);
+        }),
+        ("Edit/Section/Move Up", fltk::enums::Shortcut::None, |d| {
+            d.move_section(false);
+        }),
+        ("Edit/Section/Move Down", fltk::enums::Shortcut::None, |d| {
+            d.move_section(true);
+        }),
+        (
+            "Edit/Lists/Renumber Lists",
+            fltk::enums::Shortcut::None,
+            |d| d.renumber_lists(),
+        ),
+        (
+            "Edit/Lists/Insert Numbered Item",
+            fltk::enums::Shortcut::Ctrl | fltk::enums::Shortcut::Alt | 'n',
+            |d| {
+                d.insert_numbered_item();
+            },
+        ),
+    ];
+
+    type AutoListRule = fn(&mut editor_state::lists::AutoList) -> &mut bool;
+    let autolist_rules: [(&str, AutoListRule); 4] = [
+        ("Edit/Lists/Continue Lists on Enter", |rules| {
+            &mut rules.continue_items
+        }),
+        ("Edit/Lists/End List on Empty Item", |rules| {
+            &mut rules.end_empty_items
+        }),
+        ("Edit/Lists/Indent Empty Item with Tab", |rules| {
+            &mut rules.indent_with_tab
+        }),
+        ("Edit/Lists/Carry Over Checkboxes", |rules| {
+            &mut rules.carry_checkboxes
+        }),
+    ];
+
+    for (label, rule) in autolist_rules {
+        let backend = backend.clone();
+
+        menu.add(
+            label,
+            fltk::enums::Shortcut::None,
+            fltk::menu::MenuFlag::Toggle | fltk::menu::MenuFlag::Value,
+            move |m| {
+                *rule(&mut backend.borrow_mut().settings.autolist) =
+                    m.mvalue().is_some_and(|item| item.value());
+            },
+        );
+    }
+
+    for (label, resolution) in [
+        (
+            "Edit/Conflicts/Accept Ours",
+            editor_state::conflict::Resolution::Ours,
+        ),
+        (
+            "Edit/Conflicts/Accept Theirs",
+            editor_state::conflict::Resolution::Theirs,
+        ),
+        (
+            "Edit/Conflicts/Accept Both",
+            editor_state::conflict::Resolution::Both,
+        ),
+    ] {
+        let backend = backend.clone();
+
+        menu.add(
+            label,
+            fltk::enums::Shortcut::None,
+            fltk::menu::MenuFlag::Normal,
+            move |_| {
+                if backend.borrow_mut().resolve_conflict(resolution) {
+                    fltk::app::redraw();
+                }
+            },
+        );
+    }
+
+    menu.add(
+        "Edit/Go to Symbol...",
+        fltk::enums::Shortcut::Ctrl | fltk::enums::Shortcut::Shift | 'o',
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            let mut editor = text_editor_view.clone();
+            move |_| {
+                let symbols = editor_state::symbols::symbols(&backend.borrow());
+                let labels: Vec<String> = symbols
+                    .iter()
+                    .map(|symbol| {
+                        let label = symbol.display();
+
+                        editor.state.borrow().display_text(&label).into_owned()
+                    })
+                    .collect();
+
+                if let Some(i) = ui::pick("Go to Symbol", &labels) {
+                    editor.jump_to_row(symbols[i].row);
+                }
+            }
+        },
+    );
+
+    menu.add(
+        "Edit/Search Notes...",
+        fltk::enums::Shortcut::Ctrl | fltk::enums::Shortcut::Shift | 'f',
+        fltk::menu::MenuFlag::Normal,
+        {
+            let mut quick_actions = quick_actions.clone();
+            let mut editor = text_editor_view.clone();
+            let search_workspace = search_workspace.clone();
+            let last_query = std::rc::Rc::new(std::cell::RefCell::new(String::new()));
+            move |_| {
+                let Some(query) = fltk::dialog::input_default(
+                    "Search notes (words, \"phrases\", tag:, path:, before:, after:, OR, -):",
+                    &last_query.borrow(),
+                )
+                .filter(|query| !query.trim().is_empty()) else {
+                    return;
+                };
+                let hits = search_workspace(&query);
+
+                *last_query.borrow_mut() = query;
+                if hits.is_empty() {
+                    fltk::dialog::message_default("No notes match.");
+                    return;
+                }
+
+                let labels: Vec<String> = hits
+                    .iter()
+                    .map(|hit| {
+                        let name =
+                            editor_state::workspace::note_name(&hit.path).unwrap_or_default();
+                        format!("{name}    :{}", hit.row + 1)
+                    })
+                    .collect();
+
+                if let Some(i) = ui::pick("Search Results", &labels) {
+                    quick_actions.open(&hits[i].path);
+                    editor.jump_to_row(hits[i].row);
+                }
+            }
+        },
+    );
+
+    menu.add(
+        "Edit/Save Search...",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let saved_searches = saved_searches.clone();
+            let saved_searches_path = saved_searches_path.clone();
+            let mut smart_folders = smart_folders.clone();
+            move |_| {
+                let Some(query) = fltk::dialog::input_default(
+                    "Search to save (words, \"phrases\", tag:, path:, before:, after:, OR, -):",
+                    "",
+                )
+                .filter(|query| !query.trim().is_empty()) else {
+                    return;
+                };
+                let Some(name) =
+                    fltk::dialog::input_default("Show it in the sidebar as:", query.trim())
+                        .map(|name| name.trim().to_string())
+                        .filter(|name| !name.is_empty())
+                else {
+                    return;
+                };
+                let mut searches = saved_searches.borrow_mut();
+
+                searches.retain(|search| search.name != name);
+                searches.push(editor_state::search::SavedSearch { name, query });
+                smart_folders.set_searches(&searches);
+                if let Some(path) = &saved_searches_path
+                    && let Err(err) = editor_state::search::save_searches(path, &searches)
+                {
+                    tracing::error!("Error saving searches: {:?}", err);
+                }
+            }
+        },
+    );
+
+    menu.add(
+        "Edit/Delete Saved Search...",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let saved_searches = saved_searches.clone();
+            let mut smart_folders = smart_folders.clone();
+            move |_| {
+                let names: Vec<String> = saved_searches
+                    .borrow()
+                    .iter()
+                    .map(|search| search.name.clone())
+                    .collect();
+                let Some(i) = ui::pick("Delete Saved Search", &names) else {
+                    return;
+                };
+                let mut searches = saved_searches.borrow_mut();
+
+                searches.remove(i);
+                smart_folders.set_searches(&searches);
+                if let Some(path) = &saved_searches_path
+                    && let Err(err) = editor_state::search::save_searches(path, &searches)
+                {
+                    tracing::error!("Error saving searches: {:?}", err);
+                }
+            }
+        },
+    );
+
+    // The notes rewritten by the last Replace in Notes, to take back one by one.
+    let replaced_notes: std::rc::Rc<std::cell::RefCell<Vec<editor_state::replace::FileChange>>> =
+        std::rc::Rc::default();
+    let replace_history_path = editor_state::replace::ReplaceHistory::default_path();
+    let replace_history = std::rc::Rc::new(std::cell::RefCell::new(
+        replace_history_path
+            .as_deref()
+            .map(editor_state::replace::ReplaceHistory::load)
+            .unwrap_or_default(),
+    ));
+
+    menu.add(
+        "Edit/Replace in Notes...",
+        fltk::enums::Shortcut::Ctrl | fltk::enums::Shortcut::Shift | 'h',
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            let workspace_root = workspace_root.clone();
+            let replaced_notes = replaced_notes.clone();
+            let mut editor = text_editor_view.clone();
+            let mut status_bar = status_frame.clone();
+            move |_| {
+                let Some(root) = workspace_root() else {
+                    return;
+                };
+                let (open, include_archived, in_selection) = {
+                    let d = backend.borrow();
+                    (
+                        d.text_buffer.path().map(std::path::Path::to_path_buf),
+                        d.settings.include_archived,
+                        (!d.cursor.no_selection())
+                            .then_some(d.scope == editor_state::transform::Scope::Selection),
+                    )
+                };
+                let others: Vec<_> = editor_state::workspace::listed_notes(&root, include_archived)
+                    .into_iter()
+                    .filter(|note| Some(note) != open.as_ref())
+                    .collect();
+                // The open note is searched as it is in the editor, unsaved edits
+                // included. In the selection, it's the only one searched.
+                let search = {
+                    let backend = backend.clone();
+                    let open = open.clone();
+                    move |pattern: &editor_state::replace::Pattern, in_selection: bool| {
+                        let mut found = if in_selection {
+                            editor_state::replace::find_in_notes(&[], pattern)?
+                        } else {
+                            editor_state::replace::find_in_notes(&others, pattern)?
+                        };
+
+                        if let Some(path) = &open {
+                            let d = backend.borrow();
+                            let lines = d.get_lines_stripped(0..d.get_line_count());
+                            let mut matches =
+                                editor_state::replace::find_in_lines(pattern, &lines)?;
+
+                            if in_selection {
+                                let (start, end) =
+                                    d.scope_range(editor_state::transform::Scope::Selection);
+
+                                matches = editor_state::replace::within(matches, start, end);
+                            }
+
+                            if !matches.is_empty() {
+                                found.insert(
+                                    0,
+                                    editor_state::replace::FileMatches {
+                                        path: path.clone(),
+                                        lines,
+                                        matches,
+                                    },
+                                );
+                            }
+                        }
+                        Ok(found)
+                    }
+                };
+                let redactor = editor.state.borrow().redactor.clone();
+                let notes = ui::replace_in_notes_dialog(
+                    replace_history.clone(),
+                    in_selection,
+                    redactor,
+                    search,
+                );
+
+                // Pins are kept even when nothing's replaced.
+                if let Some(path) = &replace_history_path
+                    && let Err(err) = replace_history.borrow().save(path)
+                {
+                    tracing::error!("Error saving replace history: {:?}", err);
+                }
+                let Some(notes) = notes else {
+                    return;
+                };
+                let count: usize = notes.iter().map(|note| note.matches.len()).sum();
+                let (in_open, elsewhere): (Vec<_>, Vec<_>) = notes
+                    .into_iter()
+                    .partition(|note| Some(&note.path) == open.as_ref());
+                let note_count = in_open.len() + elsewhere.len();
+                let changes = elsewhere
+                    .iter()
+                    .map(editor_state::replace::FileChange::plan)
+                    .collect::<std::io::Result<Vec<_>>>()
+                    .and_then(|changes| {
+                        editor_state::replace::commit(&changes)?;
+                        Ok(changes)
+                    });
+
+                match changes {
+                    Ok(changes) => {
+                        // The open note changes like any edit, one undo step.
+                        for note in in_open {
+                            backend.borrow_mut().replace_matches(&note.matches);
+                            editor.on_content_changed();
+                        }
+                        status_bar
+                            .set_label(&format!("Replaced {count} matches in {note_count} notes"));
+                        *replaced_notes.borrow_mut() = changes;
+                    }
+                    Err(err) => {
+                        fltk::dialog::alert_default(&format!("Nothing was replaced: {err}"))
+                    }
+                }
+            }
+        },
+    );
+
+    menu.add(
+        "Edit/Undo Replace in Notes...",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let mut status_bar = status_frame.clone();
+            move |_| {
+                let mut changes = replaced_notes.borrow_mut();
+
+                if changes.is_empty() {
+                    fltk::dialog::message_default(
+                        "Replace in Notes hasn't changed any closed notes to undo.",
+                    );
+                    return;
+                }
+
+                let labels: Vec<String> = std::iter::once("All of them".to_string())
+                    .chain(changes.iter().map(|change| {
+                        editor_state::workspace::note_name(&change.path)
+                            .unwrap_or_default()
+                            .to_string()
+                    }))
+                    .collect();
+                let Some(picked) = ui::pick("Undo Replace in", &labels) else {
+                    return;
+                };
+                let undo: Vec<_> = match picked {
+                    0 => std::mem::take(&mut *changes),
+                    i => vec![changes.remove(i - 1)],
+                };
+                let mut kept = 0;
+
+                for change in &undo {
+                    match change.undo() {
+                        Ok(true) => {}
+                        Ok(false) => kept += 1,
+                        Err(err) => {
+                            kept += 1;
+                            tracing::error!(path = ?change.path, "Error undoing replace: {:?}", err);
+                        }
+                    }
+                }
+                status_bar.set_label(&if kept == 0 {
+                    format!("Put back {} notes", undo.len())
+                } else {
+                    format!("Put back {} notes, {kept} changed since", undo.len() - kept)
+                });
+            }
+        },
+    );
+
+    menu.add(
+        "Edit/Find Similar Notes...",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            let indexer = indexer.clone();
+            let mut quick_actions = quick_actions.clone();
+            let mut editor = text_editor_view.clone();
+            let mut status_bar = status_frame.clone();
+            move |_| {
+                let include_archived = backend.borrow().settings.include_archived;
+                let pairs = {
+                    let indexer = indexer.borrow();
+                    let index = indexer.index();
+                    let notes: Vec<&editor_state::search::IndexedNote> = index
+                        .notes
+                        .iter()
+                        .filter(|note| {
+                            include_archived
+                                || !editor_state::workspace::is_archived(&index.root, &note.path)
+                        })
+                        .collect();
+
+                    editor_state::similar::similar_pairs(&notes, editor_state::similar::THRESHOLD)
+                };
+
+                if pairs.is_empty() {
+                    fltk::dialog::message_default("No similar notes found.");
+                    return;
+                }
+
+                let name = |path: &std::path::Path| {
+                    editor_state::workspace::note_name(path)
+                        .unwrap_or_default()
+                        .to_string()
+                };
+                let labels: Vec<String> = pairs
+                    .iter()
+                    .map(|pair| {
+                        format!(
+                            "{}  ~  {}    {:.0}%",
+                            name(&pair.a),
+                            name(&pair.b),
+                            pair.similarity * 100.0
+                        )
+                    })
+                    .collect();
+
+                let Some(i) = ui::pick("Similar Notes", &labels) else {
+                    return;
+                };
+                let pair = &pairs[i];
+
+                match fltk::dialog::choice2_default(
+                    &format!(
+                        "{} and {} are about {:.0}% alike. Merging adds what {} has to {}.",
+                        name(&pair.a),
+                        name(&pair.b),
+                        pair.similarity * 100.0,
+                        name(&pair.b),
+                        name(&pair.a)
+                    ),
+                    "Cancel",
+                    "Open",
+                    "Merge",
+                ) {
+                    Some(1) => quick_actions.open(&pair.a),
+                    Some(2) => {
+                        let from = match std::fs::read_to_string(&pair.b) {
+                            Ok(from) => from,
+                            Err(err) => {
+                                tracing::error!("Error reading note: {:?}", err);
+                                fltk::dialog::alert_default(&format!(
+                                    "Could not read {}: {}",
+                                    pair.b.display(),
+                                    err
+                                ));
+                                return;
+                            }
+                        };
+
+                        quick_actions.open(&pair.a);
+                        if backend.borrow().text_buffer.path() != Some(pair.a.as_path()) {
+                            return;
+                        }
+
+                        let into: String = {
+                            let doc = backend.borrow();
+                            (0..doc.get_line_count())
+                                .filter_map(|row| doc.get_line(row))
+                                .collect()
+                        };
+
+                        backend
+                            .borrow_mut()
+                            .replace_all_lines(editor_state::similar::merge(&into, &from));
+                        editor.on_content_changed();
+                        status_bar.set_label(&format!(
+                            "Merged {} into this note. Save it, then archive or delete {}.",
+                            name(&pair.b),
+                            name(&pair.b)
+                        ));
+                        fltk::app::redraw();
+                    }
+                    _ => {}
+                }
+            }
+        },
+    );
+
+    for (label, shortcut, fold) in [
+        (
+            "View/Fold/Toggle Fold",
+            fltk::enums::Shortcut::Ctrl | '[',
+            None,
+        ),
+        (
+            "View/Fold/Fold All",
+            fltk::enums::Shortcut::Ctrl | fltk::enums::Shortcut::Alt | '[',
+            Some(true),
+        ),
+        (
+            "View/Fold/Unfold All",
+            fltk::enums::Shortcut::Ctrl | fltk::enums::Shortcut::Alt | ']',
+            Some(false),
+        ),
+    ] {
+        let backend = backend.clone();
+        let mut editor = text_editor_view.clone();
+
+        menu.add(label, shortcut, fltk::menu::MenuFlag::Normal, move |_| {
+            {
+                let mut d = backend.borrow_mut();
+
+                match fold {
+                    Some(fold) => d.fold_all(fold),
+                    None => {
+                        let row = d.cursor.head.row;
+
+                        d.toggle_fold(row);
+                    }
+                }
+            }
+            editor.on_content_changed();
+        });
+    }
+
+    menu.add(
+        "Edit/Format Document",
+        fltk::enums::Shortcut::Ctrl | fltk::enums::Shortcut::Shift | 'i',
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            let mut editor = text_editor_view.clone();
+            move |_| {
+                let formatted = backend.borrow_mut().format_document();
+
+                match formatted {
+                    Ok(true) => editor.on_content_changed(),
+                    Ok(false) => {}
+                    Err(err) => fltk::dialog::alert_default(&format!("Couldn't format: {err}")),
+                }
+            }
+        },
+    );
+
+    menu.add(
+        "View/Continue Reading",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let mut editor = text_editor_view.clone();
+            move |_| editor.continue_reading()
+        },
+    );
+
+    menu.add(
+        "Edit/Next Change",
+        fltk::enums::Shortcut::Alt | fltk::enums::Key::F8,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            let mut editor = text_editor_view.clone();
+            move |_| {
+                let next = {
+                    let d = backend.borrow();
+                    d.next_change(d.cursor.head.row)
+                };
+
+                if let Some(row) = next {
+                    editor.jump_to_row(row);
+                }
+            }
+        },
+    );
+
+    menu.add(
+        "Edit/Conflicts/Next Conflict",
+        fltk::enums::Shortcut::from_key(fltk::enums::Key::F8),
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            let mut editor = text_editor_view.clone();
+            move |_| {
+                let next = {
+                    let d = backend.borrow();
+                    d.next_conflict(d.cursor.head.row)
+                };
+
+                if let Some(row) = next {
+                    editor.jump_to_row(row);
+                }
+            }
+        },
+    );
+
+    menu.add(
+        "Edit/Bookmarks/Toggle Bookmark",
+        fltk::enums::Shortcut::Ctrl | fltk::enums::Key::F2,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            move |_| {
+                let mut d = backend.borrow_mut();
+                let row = d.cursor.head.row;
+
+                d.bookmarks.toggle(row);
+                fltk::app::redraw();
+            }
+        },
+    );
+
+    for (label, shortcut, forward) in [
+        (
+            "Edit/Bookmarks/Next Bookmark",
+            fltk::enums::Shortcut::from_key(fltk::enums::Key::F2),
+            true,
+        ),
+        (
+            "Edit/Bookmarks/Previous Bookmark",
+            fltk::enums::Shortcut::Shift | fltk::enums::Key::F2,
+            false,
+        ),
+    ] {
+        let backend = backend.clone();
+        let mut editor = text_editor_view.clone();
+
+        menu.add(label, shortcut, fltk::menu::MenuFlag::Normal, move |_| {
+            let target = {
+                let d = backend.borrow();
+                let row = d.cursor.head.row;
+
+                if forward {
+                    d.bookmarks.next(row)
+                } else {
+                    d.bookmarks.previous(row)
+                }
+            };
+
+            if let Some(row) = target {
+                editor.jump_to_row(row);
+            }
+        });
+    }
+
+    for &(label, shortcut, command) in document_commands {
+        let backend = backend.clone();
+
+        menu.add(label, shortcut, fltk::menu::MenuFlag::Normal, move |_| {
+            command(&mut backend.borrow_mut());
+            fltk::app::redraw();
+        });
+    }
+
+    menu.add(
+        "Edit/Section/Move to Note...",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            let workspace_root = workspace_root.clone();
+            let mut status_bar = status_frame.clone();
+            move |_| {
+                let Some(root) = workspace_root() else {
+                    return;
+                };
+                let open = backend
+                    .borrow()
+                    .text_buffer
+                    .path()
+                    .map(std::path::Path::to_path_buf);
+                let notes: Vec<_> = editor_state::workspace::notes(&root)
+                    .into_iter()
+                    .filter(|note| Some(note) != open.as_ref())
+                    .collect();
+                let labels: Vec<String> = notes
+                    .iter()
+                    .map(|note| {
+                        note.strip_prefix(&root)
+                            .unwrap_or(note)
+                            .display()
+                            .to_string()
+                    })
+                    .collect();
+                let Some(i) = ui::pick("Move Section to Note", &labels) else {
+                    return;
+                };
+
+                match backend.borrow_mut().move_section_to(&notes[i]) {
+                    Ok(()) => {
+                        status_bar.set_label(&format!("Moved section to {}", labels[i]));
+                        fltk::app::redraw();
+                    }
+                    Err(err) => {
+                        tracing::error!("Error moving section: {:?}", err);
+                        fltk::dialog::alert_default(&format!("Couldn't move the section: {err}"));
+                    }
+                }
+            }
+        },
+    );
+
+    menu.add(
+        "Edit/In Selection",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Toggle | fltk::menu::MenuFlag::Value,
+        {
+            let backend = backend.clone();
+            move |m| {
+                backend.borrow_mut().scope = if m.mvalue().is_some_and(|item| item.value()) {
+                    editor_state::transform::Scope::Selection
+                } else {
+                    editor_state::transform::Scope::Document
+                };
+            }
+        },
+    );
+
+    menu.add(
+        "Edit/Smart Typography",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Toggle,
+        {
+            let backend = backend.clone();
+            move |m| {
+                backend.borrow_mut().smart_typography = m.mvalue().is_some_and(|item| item.value());
+            }
+        },
+    );
+
+    menu.add(
+        "Edit/Footnote/Insert Footnote",
+        fltk::enums::Shortcut::Ctrl | fltk::enums::Shortcut::Alt | 'f',
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            let mut editor = text_editor_view.clone();
+            move |_| {
+                backend.borrow_mut().insert_footnote();
+                editor.reveal_cursor();
+            }
+        },
+    );
+
+    menu.add(
+        "Edit/Follow Link",
+        fltk::enums::Shortcut::Ctrl | 'g',
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            let workspace_root = workspace_root.clone();
+            let mut quick_actions = quick_actions.clone();
+            let mut editor = text_editor_view.clone();
+            move |_| {
+                let Some(link) = backend.borrow().link_at_cursor() else {
+                    return;
+                };
+
+                if !link.note.is_empty() {
+                    let Some(path) = workspace_root()
+                        .and_then(|root| editor_state::workspace::find_note(&root, &link.note))
+                    else {
+                        fltk::dialog::alert_default(&format!(
+                            "There's no note called {}.",
+                            link.note
+                        ));
+                        return;
+                    };
+
+                    quick_actions.open(&path);
+                    // Left where it was if the open note couldn't make way.
+                    if backend.borrow().text_buffer.path() != Some(path.as_path()) {
+                        return;
+                    }
+                }
+
+                let row = link.heading.and_then(|heading| {
+                    backend
+                        .borrow()
+                        .outline
+                        .find_anchor(&heading)
+                        .map(|h| h.row)
+                });
+
+                if let Some(row) = row {
+                    editor.jump_to_row(row);
+                }
+            }
+        },
+    );
+
+    // FLTK only puts plain text on the clipboard, so the HTML goes through the
+    // system clipboard, along with the plain text for apps that don't take HTML.
+    // It's kept open, as on X11 what's copied is gone once it's closed.
+    let rich_clipboard: std::rc::Rc<std::cell::RefCell<Option<arboard::Clipboard>>> =
+        std::rc::Rc::default();
+
+    for (label, html) in [
+        ("Edit/Copy as HTML", true),
+        ("Edit/Copy without Formatting", false),
+    ] {
+        let backend = backend.clone();
+        let rich_clipboard = rich_clipboard.clone();
+
+        menu.add(
+            label,
+            fltk::enums::Shortcut::None,
+            fltk::menu::MenuFlag::Normal,
+            move |_| {
+                let selected = backend.borrow().get_selected_text();
+
+                if selected.is_empty() {
+                    return;
+                }
+
+                let lines: Vec<&str> = selected.lines().collect();
+                let plain = editor_state::export::plain_text(&lines);
+
+                if !html {
+                    fltk::app::copy(&plain);
+                    return;
+                }
+
+                let mut rich_clipboard = rich_clipboard.borrow_mut();
+                let copied = match &mut *rich_clipboard {
+                    Some(clipboard) => Ok(clipboard),
+                    None => {
+                        arboard::Clipboard::new().map(|clipboard| rich_clipboard.insert(clipboard))
+                    }
+                }
+                .and_then(|clipboard| {
+                    clipboard.set_html(
+                        editor_state::export::html_fragment(&lines),
+                        Some(plain.clone()),
+                    )
+                });
+
+                if let Err(err) = copied {
+                    tracing::error!("Error copying as HTML: {:?}", err);
+                    fltk::dialog::alert_default(
+                        "Couldn't copy as HTML, so the text was copied without formatting.",
+                    );
+                    fltk::app::copy(&plain);
+                }
+            },
+        );
+    }
+
+    menu.add(
+        "Edit/Copy Link for Other Apps",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            let mut status_bar = status_frame.clone();
+            move |_| {
+                let Some(link) = backend.borrow().deep_link() else {
+                    fltk::dialog::alert_default("Save the note first.");
+                    return;
+                };
+
+                fltk::app::copy(&link.url());
+                status_bar.set_label(&format!("Copied {}", link.url()));
+            }
+        },
+    );
+
+    menu.add(
+        "Edit/Section/Rename Heading...",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            let mut status_bar = status_frame.clone();
+            move |_| {
+                let old = {
+                    let d = backend.borrow();
+
+                    d.outline
+                        .heading_on(d.cursor.head.row)
+                        .map(|h| h.title.clone())
+                };
+                let Some(old) = old else {
+                    fltk::dialog::alert_default("Put the cursor on the heading to rename.");
+                    return;
+                };
+                let Some(title) = fltk::dialog::input_default("Rename heading to:", &old)
+                    .map(|title| title.trim().to_string())
+                    .filter(|title| !title.is_empty() && *title != old)
+                else {
+                    return;
+                };
+
+                match backend.borrow_mut().rename_heading(&title) {
+                    Ok(updated) => {
+                        status_bar.set_label(&format!(
+                            "Links updated in {} other note(s)",
+                            updated.len()
+                        ));
+                        fltk::app::redraw();
+                    }
+                    Err(err) => {
+                        tracing::error!("Error renaming heading: {:?}", err);
+                        fltk::dialog::alert_default(&format!("Couldn't rename the heading: {err}"));
+                    }
+                }
+            }
+        },
+    );
+
+    menu.add(
+        "Edit/Footnote/Go to Footnote or Back",
+        fltk::enums::Shortcut::Ctrl | fltk::enums::Shortcut::Alt | 'g',
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            let mut editor = text_editor_view.clone();
+            move |_| {
+                let moved = backend.borrow_mut().jump_footnote();
+
+                if moved {
+                    editor.reveal_cursor();
+                }
+            }
+        },
+    );
+
+    menu.add(
+        "Edit/Select Next Match",
+        fltk::enums::Shortcut::Ctrl | fltk::enums::Shortcut::Alt | 'd',
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            let mut editor = text_editor_view.clone();
+            move |_| {
+                let selected = backend.borrow_mut().select_next_match();
+
+                if selected {
+                    editor.reveal_cursor();
+                }
+            }
+        },
+    );
+
+    menu.add(
+        "Edit/Selection Stats",
+        fltk::enums::Shortcut::Ctrl | fltk::enums::Shortcut::Shift | 'w',
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            move |_| {
+                let (title, text) = {
+                    let d = backend.borrow();
+
+                    if d.cursor.no_selection() {
+                        let lines = d.get_lines_stripped(0..d.get_line_count());
+
+                        ("Whole note", lines.join("\n"))
+                    } else {
+                        ("Selection", d.get_selected_text())
+                    }
+                };
+
+                ui::stats_popover(title, &editor_state::stats::analyze(&text));
+            }
+        },
+    );
+
+    let lookup = std::rc::Rc::new(editor_state::lookup::Lookup::with_defaults());
+    menu.add(
+        "Edit/Look Up",
+        fltk::enums::Shortcut::Ctrl | fltk::enums::Shortcut::Alt | 'l',
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            let mut status_bar = status_frame.clone();
+            move |_| {
+                let Some(word) = backend.borrow().word_at_cursor() else {
+                    status_bar.set_label("Select a word to look up");
+                    return;
+                };
+
+                ui::lookup_popover(&word, &lookup.lookup(&word));
+            }
+        },
+    );
+
+    menu.add(
+        "Edit/Insert Calculation Result",
+        fltk::enums::Shortcut::Ctrl | fltk::enums::Shortcut::Alt | '=',
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            let mut editor = text_editor_view.clone();
+            let mut status_bar = status_frame.clone();
+            move |_| {
+                let calculated = backend.borrow_mut().insert_calculation();
+
+                match calculated {
+                    Ok(()) => editor.reveal_cursor(),
+                    Err(err) => status_bar.set_label(&err),
+                }
+            }
+        },
+    );
+
+    let runner = std::rc::Rc::new(runner::Runner::default());
+    let run_block = {
+        let backend = backend.clone();
+        let runner = runner.clone();
+        let status_bar = status_frame.clone();
+        move |fence: usize| {
+            let mut status_bar = status_bar.clone();
+            let d = backend.borrow();
+            let Some((fence_line, source)) = d.code_block(fence) else {
+                return;
+            };
+            let Some(interpreter) = runner.interpreter(&fence_line).cloned() else {
+                status_bar.set_label("No interpreter for this code block");
+                return;
+            };
+            let note = d.text_buffer.path().map(std::path::Path::to_path_buf);
+            let dir = note
+                .as_deref()
+                .and_then(std::path::Path::parent)
+                .map(std::path::Path::to_path_buf);
+
+            drop(d);
+            // A block runs with the user's own rights, so a note that came from
+            // elsewhere mustn't get to run one at a keypress.
+            if !runner.is_trusted(note.as_deref(), &interpreter) {
+                match fltk::dialog::choice2_default(
+                    &format!(
+                        "Run this block with {}? It can do anything you can.",
+                        interpreter.program
+                    ),
+                    "Cancel",
+                    "Run",
+                    "Always in This Note",
+                ) {
+                    Some(1) => {}
+                    Some(2) => runner.trust(note.as_deref(), &interpreter),
+                    _ => return,
+                }
+            }
+
+            status_bar.set_label(&format!("Running {}…", interpreter.program));
+            runner.start(fence, source, interpreter, dir);
+        }
+    };
+    text_editor_view.clone().set_block_runner(
+        {
+            let runner = runner.clone();
+            move |fence_line| runner.interpreter(fence_line).is_some()
+        },
+        run_block.clone(),
+    );
+
+    menu.add(
+        "Edit/Run Code Block",
+        fltk::enums::Shortcut::Ctrl | fltk::enums::Shortcut::Alt | 'r',
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            move |_| {
+                let fence = {
+                    let d = backend.borrow();
+                    let row = d.cursor.head.row;
+
+                    match d.outline.code_block_at(row) {
+                        Some(fence) => fence,
+                        None if d.outline.code_block_end(row).is_some() => row,
+                        None => return,
+                    }
+                };
+
+                run_block(fence);
+            }
+        },
+    );
+
+    // Puts the output of finished runs below their blocks.
+    fltk::app::add_timeout3(0.1, {
+        let backend = backend.clone();
+        let mut editor = text_editor_view.clone();
+        let mut status_bar = status_frame.clone();
+        move |handle| {
+            fltk::app::repeat_timeout3(0.1, handle);
+
+            for finished in runner.poll() {
+                let inserted = backend.borrow_mut().insert_run_result(
+                    finished.fence,
+                    &finished.source,
+                    &finished.result,
+                );
+
+                status_bar.set_label(if inserted {
+                    "Code block ran"
+                } else {
+                    "Code block changed while running, output dropped"
+                });
+                editor.on_content_changed();
+            }
+        }
+    });
+
+    let tool_runner = std::rc::Rc::new(tools::ToolRunner::default());
+    if tool_runner.tools.0.is_empty() {
+        menu.add(
+            "Edit/External Tools/No Tools Set Up",
+            fltk::enums::Shortcut::None,
+            fltk::menu::MenuFlag::Inactive,
+            |_| {},
+        );
+    }
+    for tool in &tool_runner.tools.0 {
+        menu.add(
+            &format!("Edit/External Tools/{}", tool.name.replace('/', "\\/")),
+            fltk::enums::Shortcut::None,
+            fltk::menu::MenuFlag::Normal,
+            {
+                let backend = backend.clone();
+                let tool_runner = tool_runner.clone();
+                let tool = tool.clone();
+                let mut status_bar = status_frame.clone();
+                move |_| {
+                    let d = backend.borrow();
+                    let (start, end, input) = d.tool_input();
+                    let dir = d
+                        .text_buffer
+                        .path()
+                        .and_then(std::path::Path::parent)
+                        .map(std::path::Path::to_path_buf);
+
+                    status_bar.set_label(&format!("Running {}…", tool.name));
+                    tool_runner.start(tool.clone(), start, end, input, dir);
+                }
+            },
+        );
+    }
+
+    // Replaces the text given to tools with what they wrote out.
+    fltk::app::add_timeout3(0.1, {
+        let backend = backend.clone();
+        let mut editor = text_editor_view.clone();
+        let mut status_bar = status_frame.clone();
+        move |handle| {
+            fltk::app::repeat_timeout3(0.1, handle);
+
+            for piped in tool_runner.poll() {
+                let output = match piped.output {
+                    Ok(output) => output,
+                    Err(err) => {
+                        status_bar.set_label("");
+                        fltk::dialog::alert_default(&err);
+                        continue;
+                    }
+                };
+                let replaced = backend.borrow_mut().replace_tool_input(
+                    piped.start,
+                    piped.end,
+                    &piped.input,
+                    &output,
+                );
+
+                status_bar.set_label(if replaced {
+                    "Tool output inserted"
+                } else {
+                    "Text changed while the tool ran, output dropped"
+                });
+                editor.on_content_changed();
+            }
+        }
+    });
+
+    menu.add(
+        "Edit/Transform/Sort Lines...",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let backend = backend.clone();
+            move |_| {
+                if let Some(options) = ui::sort_lines_dialog() {
+                    let mut d = backend.borrow_mut();
+                    let scope = d.scope;
+
+                    d.sort_lines(&options, scope);
+                    fltk::app::redraw();
+                }
+            }
+        },
+    );
+
+    for (label, conversion) in [
+        (
+            "Edit/Rich Text Paste/As Markdown",
+            editor_state::clipboard::PasteConversion::Markdown,
+        ),
+        (
+            "Edit/Rich Text Paste/As Plain Text",
+            editor_state::clipboard::PasteConversion::PlainText,
+        ),
+        (
+            "Edit/Rich Text Paste/Keep Markup",
+            editor_state::clipboard::PasteConversion::Off,
+        ),
+    ] {
+        let paste_state = paste_state.clone();
+        let flag = if conversion == paste_state.borrow().paste_conversion {
+            fltk::menu::MenuFlag::Radio | fltk::menu::MenuFlag::Value
+        } else {
+            fltk::menu::MenuFlag::Radio
+        };
+
+        menu.add(label, fltk::enums::Shortcut::None, flag, move |_| {
+            paste_state.borrow_mut().paste_conversion = conversion;
+        });
+    }
+
+    menu.add(
+        "Edit/Paste URL over Selection as Link",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Toggle | fltk::menu::MenuFlag::Value,
+        {
+            let paste_state = paste_state.clone();
+            move |m| {
+                paste_state.borrow_mut().paste_links = m.mvalue().is_some_and(|item| item.value());
+            }
+        },
+    );
+
+    menu.add(
+        "Edit/Virtual Space",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Toggle,
+        {
+            let paste_state = paste_state.clone();
+            move |m| {
+                paste_state.borrow_mut().virtual_space =
+                    m.mvalue().is_some_and(|item| item.value());
+                fltk::app::redraw();
+            }
+        },
+    );
+
+    menu.add(
+        "File/Keep Running in Background",
+        fltk::enums::Shortcut::None,
+        if resident.get() {
+            fltk::menu::MenuFlag::Toggle | fltk::menu::MenuFlag::Value
+        } else {
+            fltk::menu::MenuFlag::Toggle
+        },
+        {
+            let resident = resident.clone();
+            move |m| {
+                let on = m.mvalue().is_some_and(|item| item.value());
+
+                resident.set(on);
+                if let Err(err) = editor_state::instance::set_stays_resident(on) {
+                    tracing::error!("Error saving background setting: {:?}", err);
+                }
+            }
+        },
+    );
+
+    menu.add(
+        "File/Quit",
+        fltk::enums::Shortcut::Ctrl | 'q',
+        fltk::menu::MenuFlag::Normal,
+        {
+            let mut win = win.clone();
+            move |_| {
+                win.do_callback();
+                fltk::app::program_should_quit(true);
+            }
+        },
+    );
+
+    win.end();
+    win.set_callback({
+        let editor = text_editor_view.clone();
+        move |w| {
+            let mut session = session.borrow_mut();
+
+            remember_view(&editor, &mut session);
+            if let Some(path) = &session_path
+                && let Err(err) = session.save(path)
+            {
+                tracing::error!("Error saving session: {:?}", err);
+            }
+
+            w.hide();
+        }
+    });
+    win.resize_callback({
+        let mut chrome = chrome.clone();
+        move |_, _, _, _, _| chrome.relayout()
+    });
+    chrome.restore_layout();
+
+    let lock_path = editor_state::lock::WorkspaceLock::default_path();
+    // The lock of the open note's workspace, read once per workspace: the idle
+    // check stays in memory, and unlocking never waits on the keyring.
+    let cached_lock: std::rc::Rc<std::cell::RefCell<Option<editor_state::lock::WorkspaceLock>>> =
+        std::rc::Rc::default();
+    let workspace_lock = {
+        let workspace_root = workspace_root.clone();
+        let lock_path = lock_path.clone();
+        let cached_lock = cached_lock.clone();
+        move || {
+            let root = workspace_root()?;
+            let mut cached = cached_lock.borrow_mut();
+
+            if cached.as_ref().is_none_or(|lock| lock.root != root) {
+                let mut lock =
+                    editor_state::lock::WorkspaceLock::load(lock_path.as_deref()?, &root);
+
+                lock.read_keyring();
+                *cached = Some(lock);
+            }
+            cached.clone()
+        }
+    };
+    // Added last so it covers everything else.
+    let lock_screen = ui::LockScreen::new(&mut win, {
+        let cached_lock = cached_lock.clone();
+        // Whatever workspace was open when it locked.
+        move |attempt| {
+            cached_lock
+                .borrow()
+                .as_ref()
+                .is_some_and(|lock| lock.verify(attempt))
+        }
+    });
+    let last_activity = std::rc::Rc::new(std::cell::Cell::new(std::time::Instant::now()));
+
+    menu.add(
+        "File/Lock Workspace",
+        fltk::enums::Shortcut::Ctrl | fltk::enums::Shortcut::Shift | 'l',
+        fltk::menu::MenuFlag::Normal,
+        {
+            let workspace_lock = workspace_lock.clone();
+            let mut lock_screen = lock_screen.clone();
+            move |_| {
+                if workspace_lock().is_some_and(|lock| lock.has_passphrase()) {
+                    lock_screen.lock();
+                } else {
+                    fltk::dialog::alert_default("Set a passphrase for this workspace first.");
+                }
+            }
+        },
+    );
+    menu.add(
+        "File/Lock Settings/Set Passphrase...",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let workspace_lock = workspace_lock.clone();
+            let cached_lock = cached_lock.clone();
+            move |_| {
+                let Some(mut lock) = workspace_lock() else {
+                    fltk::dialog::alert_default("Open a note in the workspace to lock first.");
+                    return;
+                };
+                if lock.has_passphrase() {
+                    let current = fltk::dialog::password_default("Current passphrase:", "");
+
+                    if !current.is_some_and(|current| lock.verify(&current)) {
+                        fltk::dialog::alert_default("Wrong passphrase");
+                        return;
+                    }
+                }
+
+                let Some(passphrase) = fltk::dialog::password_default("New passphrase:", "")
+                    .filter(|passphrase| !passphrase.is_empty())
+                else {
+                    return;
+                };
+                if fltk::dialog::password_default("Repeat the passphrase:", "").as_ref()
+                    != Some(&passphrase)
+                {
+                    fltk::dialog::alert_default("The passphrases don't match");
+                    return;
+                }
+                if let Err(err) = lock.set_passphrase(&passphrase) {
+                    fltk::dialog::alert_default(&format!(
+                        "Couldn't store the passphrase, it only holds until MyNotes quits: {err}"
+                    ));
+                }
+                *cached_lock.borrow_mut() = Some(lock);
+            }
+        },
+    );
+    menu.add(
+        "File/Lock Settings/Lock When Idle...",
+        fltk::enums::Shortcut::None,
+        fltk::menu::MenuFlag::Normal,
+        {
+            let workspace_lock = workspace_lock.clone();
+            let cached_lock = cached_lock.clone();
+            move |_| {
+                let (Some(mut lock), Some(path)) = (workspace_lock(), lock_path.as_deref()) else {
+                    fltk::dialog::alert_default("Open a note in the workspace to lock first.");
+                    return;
+                };
+                let current = lock.idle.map_or(0, |idle| idle.as_secs() / 60);
+                let Some(minutes) = fltk::dialog::input_default(
+                    "Lock after this many idle minutes (0 for never):",
+                    &current.to_string(),
+                ) else {
+                    return;
+                };
+                let Ok(minutes) = minutes.trim().parse::<u64>() else {
+                    fltk::dialog::alert_default("Enter a number of minutes");
+                    return;
+                };
+
+                lock.idle = (minutes > 0).then(|| std::time::Duration::from_secs(minutes * 60));
+                if let Err(err) = lock.save(path) {
+                    fltk::dialog::alert_default(&format!("Couldn't save: {err}"));
+                }
+                *cached_lock.borrow_mut() = Some(lock);
+            }
+        },
+    );
+
+    // Locks the workspace once it has sat idle long enough.
+    fltk::app::add_timeout3(5.0, {
+        let mut lock_screen = lock_screen.clone();
+        let last_activity = last_activity.clone();
+        move |handle| {
+            fltk::app::repeat_timeout3(5.0, handle);
+
+            if !lock_screen.is_locked()
+                && workspace_lock().is_some_and(|lock| {
+                    lock.is_due(last_activity.get().elapsed()) && lock.has_passphrase()
+                })
+            {
+                lock_screen.lock();
+            }
+        }
+    });
+
+    // Alt-dragging a panel docks it where it's dropped.
+    let dragged_panel = std::cell::Cell::new(None);
+    // The menu bar is hidden in focus mode, so F11 has to be caught by the window.
+    win.handle(move |w, event| {
+        if matches!(
+            event,
+            fltk::enums::Event::Push
+                | fltk::enums::Event::KeyDown
+                | fltk::enums::Event::Move
+                | fltk::enums::Event::MouseWheel
+        ) {
+            last_activity.set(std::time::Instant::now());
+        }
+        if lock_screen.is_locked() {
+            return false;
+        }
+        match event {
+            fltk::enums::Event::Push
+                if fltk::app::event_state().contains(fltk::enums::EventState::Alt) =>
+            {
+                dragged_panel.set(chrome.panel_at(fltk::app::event_x(), fltk::app::event_y()));
+                if dragged_panel.get().is_some() {
+                    w.set_cursor(fltk::enums::Cursor::Move);
+                    return true;
+                }
+            }
+            fltk::enums::Event::Drag if dragged_panel.get().is_some() => return true,
+            fltk::enums::Event::Released => {
+                if let Some(panel) = dragged_panel.take() {
+                    w.set_cursor(fltk::enums::Cursor::Default);
+                    chrome.drop_panel(panel, fltk::app::event_x(), fltk::app::event_y());
+                    return true;
+                }
+            }
+            _ => {}
+        }
+        if event == fltk::enums::Event::Shortcut
+            && fltk::app::event_key() == fltk::enums::Key::F11
+            && chrome.editor.state.borrow().focus_mode
+        {
+            chrome.toggle_focus_mode();
+            return true;
+        }
+        false
+    });
+
+    // The user's config and the workspace's `.mynotes.toml`, switches and theme
+    // set through their menu items so the menu shows them.
+    let config = editor_state::config::Config::resolve(workspace_root().as_deref());
+
+    config.apply(&mut backend.borrow_mut().settings);
+    for (label, on) in [
+        ("File/Cooperative File Access", config.cooperative_files),
+        ("File/Record Line Edit Times", config.line_times),
+        ("View/Include Archived Notes", config.include_archived),
+        ("View/Caret/Blink", config.caret_blink.map(|ms| ms > 0)),
+    ] {
+        if let Some(on) = on {
+            run_menu_command(&mut menu, label, Some(on));
+        }
+    }
+    if let Some((label, _)) = theme_modes
+        .iter()
+        .find(|(_, mode)| Some(*mode) == config.theme)
+    {
+        run_menu_command(&mut menu, label, Some(true));
+    }
+    if let Some(shape) = config.caret_shape {
+        run_menu_command(
+            &mut menu,
+            &format!("View/Caret/{}", shape.name()),
+            Some(true),
+        );
+    }
+    // One note shows at a time, so the first there is.
+    if let Some(note) = config.open.iter().find(|note| note.is_file()) {
+        quick_actions.clone().open(note);
+    }
+    for command in &config.commands {
+        if !run_menu_command(&mut menu, command, None) {
+            tracing::warn!(command, "Unknown startup command");
+        }
+    }
+
+    win.show();
+
+    tray::listen(&quick_actions);
+    if let Some(request) = request {
+        let mut quick_actions = quick_actions.clone();
+        quick_actions.handle(request);
+    }
+
+    // Closing the window only hides it while the app keeps running in the
+    // background, so quick actions stay instant. File/Quit ends it for good.
+    while !fltk::app::should_program_quit() && (win.shown() || resident.get()) {
+        fltk::app::wait();
+    }
+}
+
+/// Carries out the menu command at `label` as if it was picked, first turning it
+/// `on` or off if it's a toggle or radio item. Returns whether there's one.
+fn run_menu_command(menu: &mut fltk::menu::MenuBar, label: &str, on: Option<bool>) -> bool {
+    let Some(mut item) = menu.find_item(label) else {
+        return false;
+    };
+
+    if item.is_radio() {
+        // Its siblings are the radio items of the same submenu.
+        let submenu = label.rsplit_once('/').map_or("", |(submenu, _)| submenu);
+
+        for i in 0..menu.size() {
+            if let Some(mut other) = menu.at(i)
+                && other.is_radio()
+                && menu
+                    .item_pathname(Some(&other))
+                    .is_ok_and(|path| path.rsplit_once('/').is_some_and(|(s, _)| s == submenu))
+            {
+                other.clear();
+            }
+        }
+    }
+    match on {
+        Some(true) => item.set(),
+        Some(false) if item.is_checkbox() || item.is_radio() => item.clear(),
+        _ => {}
+    }
+    menu.set_item(&item);
+    item.do_callback(menu);
+    true
+}
+
+/// Records the current file's cursor, scroll and zoom so reopening it restores them.
+fn remember_view(editor: &ui::TextEditor, session: &mut editor_state::session::SessionStore) {
+    let state = editor.state.borrow();
+    let path = state
+        .doc
+        .borrow()
+        .text_buffer
+        .path()
+        .map(std::path::Path::to_path_buf);
 
-    app.run().unwrap();
+    if let Some(path) = path {
+        session.remember(&path, state.view_state());
+    }
 }
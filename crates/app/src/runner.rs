@@ -0,0 +1,100 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::mpsc;
+
+use editor_state::run::{Interpreter, Interpreters, RunLimits};
+
+/// A code block run that ended: the block's fence row and source when it started,
+/// and the result block to put below it.
+pub struct Finished {
+    pub fence: usize,
+    pub source: String,
+    pub result: Vec<String>,
+}
+
+/// Runs fenced code blocks on worker threads so the editor stays responsive, see
+/// [`editor_state::run`].
+pub struct Runner {
+    pub interpreters: Interpreters,
+    pub limits: RunLimits,
+    /// The notes and the interpreters the user let run without asking again, for
+    /// this session.
+    trusted: RefCell<HashSet<(Option<PathBuf>, String)>>,
+    results: mpsc::Receiver<Finished>,
+    report: mpsc::Sender<Finished>,
+}
+
+impl Default for Runner {
+    fn default() -> Self {
+        let (report, results) = mpsc::channel();
+
+        Self {
+            interpreters: Interpreters::default_path()
+                .as_deref()
+                .map(Interpreters::load)
+                .unwrap_or_default(),
+            limits: RunLimits::default(),
+            trusted: RefCell::default(),
+            results,
+            report,
+        }
+    }
+}
+
+impl Runner {
+    /// The interpreter for the code block opened by the fence line `fence`.
+    pub fn interpreter(&self, fence: &str) -> Option<&Interpreter> {
+        self.interpreters.for_fence(fence)
+    }
+
+    /// Whether the user let `interpreter` run the blocks of `note` without asking.
+    pub fn is_trusted(&self, note: Option<&std::path::Path>, interpreter: &Interpreter) -> bool {
+        self.trusted
+            .borrow()
+            .contains(&(note.map(PathBuf::from), interpreter.program.clone()))
+    }
+
+    /// Lets `interpreter` run the blocks of `note` from now on without asking.
+    pub fn trust(&self, note: Option<&std::path::Path>, interpreter: &Interpreter) {
+        self.trusted
+            .borrow_mut()
+            .insert((note.map(PathBuf::from), interpreter.program.clone()));
+    }
+
+    /// Starts running `source`, the block opened at row `fence`. A block that
+    /// can't be run at all finishes with the error as its output.
+    pub fn start(
+        &self,
+        fence: usize,
+        source: String,
+        interpreter: Interpreter,
+        dir: Option<PathBuf>,
+    ) {
+        let report = self.report.clone();
+        let limits = self.limits;
+
+        std::thread::spawn(move || {
+            let result = match editor_state::run::run(&interpreter, &source, limits, dir.as_deref())
+            {
+                Ok(output) => output.result_block(limits.timeout),
+                Err(err) => vec![
+                    format!("```{}", editor_state::run::RESULT_TAG),
+                    format!("[couldn't run {}: {err}]", interpreter.program),
+                    "```".to_string(),
+                ],
+            };
+
+            let _ = report.send(Finished {
+                fence,
+                source,
+                result,
+            });
+        });
+    }
+
+    /// The runs that ended since the last poll.
+    pub fn poll(&self) -> Vec<Finished> {
+        self.results.try_iter().collect()
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::mpsc;
+
+use editor_state::reminders::{Date, Reminder};
+
+/// Keeps the workspace's reminders indexed in the background and tells which ones
+/// just came due, for the Upcoming panel and desktop notifications.
+pub struct Scheduler {
+    reminders: Vec<Reminder>,
+    /// Reminders already announced, so each comes up once.
+    announced: HashSet<(PathBuf, usize, Date)>,
+    scanning: bool,
+    results: mpsc::Receiver<Vec<Reminder>>,
+    report: mpsc::Sender<Vec<Reminder>>,
+    /// Whether reminders coming due pop up a desktop notification.
+    pub notify: bool,
+    /// Whether notes in archive folders are indexed too.
+    pub include_archived: bool,
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        let (report, results) = mpsc::channel();
+
+        Self {
+            reminders: Vec::new(),
+            announced: HashSet::new(),
+            scanning: false,
+            results,
+            report,
+            notify: false,
+            include_archived: false,
+        }
+    }
+}
+
+impl Scheduler {
+    /// How often the app re-indexes the workspace and checks what came due.
+    pub const INTERVAL: f64 = 60.0;
+
+    #[inline]
+    pub fn reminders(&self) -> &[Reminder] {
+        &self.reminders
+    }
+
+    /// Indexes the notes under `root` on a worker thread. Does nothing while an
+    /// earlier scan is still going.
+    pub fn rescan(&mut self, root: PathBuf) {
+        if self.scanning {
+            return;
+        }
+
+        let report = self.report.clone();
+        let include_archived = self.include_archived;
+
+        self.scanning = true;
+        std::thread::spawn(move || {
+            let _ = report.send(editor_state::reminders::index(&root, include_archived));
+        });
+    }
+
+    /// Picks up a finished scan. Returns whether the reminders changed.
+    pub fn poll(&mut self) -> bool {
+        let Ok(reminders) = self.results.try_recv() else {
+            return false;
+        };
+
+        self.scanning = false;
+        if reminders == self.reminders {
+            return false;
+        }
+        self.reminders = reminders;
+        true
+    }
+
+    /// Reminders due `today` that haven't been announced yet, now marked as
+    /// announced. Ones already overdue when the app started are left to the panel.
+    pub fn newly_due(&mut self, today: Date) -> Vec<Reminder> {
+        self.reminders
+            .iter()
+            .filter(|reminder| reminder.due == today)
+            .filter(|reminder| {
+                self.announced
+                    .insert((reminder.path.clone(), reminder.row, reminder.due))
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+/// Pops up a desktop notification for `reminder` through the platform's own tool.
+///
+/// # Errors
+///
+/// Returns an error if the tool can't be run, or on platforms without one.
+pub fn notify(reminder: &Reminder) -> std::io::Result<()> {
+    let title = match editor_state::workspace::note_name(&reminder.path) {
+        Some(name) => format!("Due today in {name}"),
+        None => "Due today".to_string(),
+    };
+
+    let mut command = if cfg!(target_os = "macos") {
+        let quote = |text: &str| text.replace('\\', "\\\\").replace('"', "\\\"");
+        let mut command = std::process::Command::new("osascript");
+
+        command.arg("-e").arg(format!(
+            "display notification \"{}\" with title \"{}\"",
+            quote(&reminder.text),
+            quote(&title)
+        ));
+        command
+    } else if cfg!(unix) {
+        let mut command = std::process::Command::new("notify-send");
+
+        // `--` so a reminder starting with `-` isn't read as an option.
+        command.args(["--app-name=MyNotes", "--", &title, &reminder.text]);
+        command
+    } else {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "No notification tool on this platform",
+        ));
+    };
+
+    let mut child = command.spawn()?;
+
+    // Reaped on the side, the tool may take a moment.
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
@@ -0,0 +1,147 @@
+use std::path::PathBuf;
+
+use editor_state::workspace::percent_encode;
+
+/// How a note is handed over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// A standalone page, see [`editor_state::export::to_html`].
+    Html,
+    /// The note's text as it is.
+    PlainText,
+}
+
+impl Format {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Html => "html",
+            Self::PlainText => "txt",
+        }
+    }
+}
+
+/// A note ready to share: its title and text, exported.
+pub struct Shared {
+    pub title: String,
+    pub format: Format,
+    pub content: String,
+}
+
+impl Shared {
+    /// Exports `lines`, the note called `title`, in `format`.
+    pub fn new(
+        title: &str,
+        lines: &[String],
+        format: Format,
+        palette: &editor_state::theme::Palette,
+    ) -> Self {
+        let content = match format {
+            Format::Html => editor_state::export::to_html(lines, title, palette),
+            Format::PlainText => lines.join("\n"),
+        };
+
+        Self {
+            title: title.to_string(),
+            format,
+            content,
+        }
+    }
+
+    /// Writes the export to a file named after the note, in a new folder only the
+    /// user can open, where it stays for the app it's handed to. The folder goes in
+    /// the per-user runtime folder where there is one, and otherwise gets a random
+    /// name in the temporary folder, so nobody else can plant or read it there.
+    fn write(&self) -> std::io::Result<PathBuf> {
+        let base = std::env::var_os("XDG_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_dir())
+            .unwrap_or_else(std::env::temp_dir);
+        let mut folder = tempfile::Builder::new();
+
+        folder.prefix("mynotes-share-");
+        #[cfg(unix)]
+        folder.permissions(std::os::unix::fs::PermissionsExt::from_mode(0o700));
+
+        let dir = folder.tempdir_in(base)?.keep();
+        let name: String = self
+            .title
+            .chars()
+            .map(|c| if "/\\:*?\"<>|".contains(c) { '_' } else { c })
+            .collect();
+        let path = dir.join(format!("{name}.{}", self.format.extension()));
+
+        let mut file = std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+
+        std::io::Write::write_all(&mut file, self.content.as_bytes())?;
+        Ok(path)
+    }
+}
+
+/// Hands `target`, a path or URL, to whatever the platform opens it with.
+fn open(target: &str) -> std::io::Result<()> {
+    let mut command = if cfg!(target_os = "macos") {
+        std::process::Command::new("open")
+    } else if cfg!(windows) {
+        // Not `cmd /C start`, which would take the `&` of a URL as its own.
+        let mut command = std::process::Command::new("rundll32");
+
+        command.arg("url.dll,FileProtocolHandler");
+        command
+    } else {
+        std::process::Command::new("xdg-open")
+    };
+    let mut child = command.arg(target).spawn()?;
+
+    // Reaped on the side, the opener may take a moment.
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// Starts an email with `shared` as its subject and body in the default mail
+/// app. Mail links can't carry markup, so an HTML export goes as a path to its
+/// file in the body; many mail apps cut very long bodies short.
+///
+/// # Errors
+///
+/// Returns an error if the export can't be written or the mail app started.
+pub fn email(shared: &Shared) -> std::io::Result<()> {
+    let body = match shared.format {
+        Format::PlainText => shared.content.replace('\n', "\r\n"),
+        Format::Html => shared.write()?.display().to_string(),
+    };
+
+    open(&format!(
+        "mailto:?subject={}&body={}",
+        percent_encode(&shared.title),
+        percent_encode(&body)
+    ))
+}
+
+/// Writes `shared` out and hands the file to the platform: selected in the file
+/// manager on macOS and Windows, whose Share menu sends it on, otherwise opened
+/// in the app that shows it. Returns the file's path.
+///
+/// # Errors
+///
+/// Returns an error if the export can't be written or handed over.
+pub fn share(shared: &Shared) -> std::io::Result<PathBuf> {
+    let path = shared.write()?;
+    let mut child = if cfg!(target_os = "macos") {
+        std::process::Command::new("open")
+            .arg("-R")
+            .arg(&path)
+            .spawn()?
+    } else if cfg!(windows) {
+        std::process::Command::new("explorer")
+            .arg(format!("/select,{}", path.display()))
+            .spawn()?
+    } else {
+        return open(&path.display().to_string()).map(|()| path);
+    };
+
+    std::thread::spawn(move || child.wait());
+    Ok(path)
+}
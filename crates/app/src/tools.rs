@@ -0,0 +1,79 @@
+use std::path::PathBuf;
+use std::sync::mpsc;
+
+use editor_core::cursor::Position;
+use editor_state::run::RunLimits;
+use editor_state::tools::{Tool, Tools};
+
+/// A tool run that ended: the range and text it was given, and what to replace
+/// them with or why not.
+pub struct Piped {
+    pub start: Position,
+    pub end: Position,
+    pub input: String,
+    pub output: Result<String, String>,
+}
+
+/// Pipes text through external tools on worker threads so the editor stays
+/// responsive, see [`editor_state::tools`].
+pub struct ToolRunner {
+    pub tools: Tools,
+    pub limits: RunLimits,
+    results: mpsc::Receiver<Piped>,
+    report: mpsc::Sender<Piped>,
+}
+
+impl Default for ToolRunner {
+    fn default() -> Self {
+        let (report, results) = mpsc::channel();
+
+        Self {
+            tools: Tools::default_path()
+                .as_deref()
+                .map(Tools::load)
+                .unwrap_or_default(),
+            // Tools are the user's own and may need their environment, e.g. to
+            // find pandoc's data files. Their output replaces the note, so it
+            // can be as long as one.
+            limits: RunLimits {
+                clean_env: false,
+                scratch_dir: false,
+                max_output: 256 * 1024 * 1024,
+                ..RunLimits::default()
+            },
+            results,
+            report,
+        }
+    }
+}
+
+impl ToolRunner {
+    /// Starts piping `input`, the text from `start` to `end`, through `tool`.
+    pub fn start(
+        &self,
+        tool: Tool,
+        start: Position,
+        end: Position,
+        input: String,
+        dir: Option<PathBuf>,
+    ) {
+        let report = self.report.clone();
+        let limits = self.limits;
+
+        std::thread::spawn(move || {
+            let output = editor_state::tools::pipe(&tool, input.clone(), limits, dir.as_deref());
+
+            let _ = report.send(Piped {
+                start,
+                end,
+                input,
+                output,
+            });
+        });
+    }
+
+    /// The tool runs that ended since the last poll.
+    pub fn poll(&self) -> Vec<Piped> {
+        self.results.try_iter().collect()
+    }
+}
@@ -0,0 +1,241 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use editor_state::instance::{Action, DeepLink, Request};
+use fltk::prelude::{WidgetExt, WindowExt};
+
+/// The quick actions a tray menu would offer, carried out on the main window.
+///
+/// The app doesn't put an icon in the tray: FLTK has none of its own. The actions
+/// reach a resident app the way a hotkey does instead: a launcher, dock menu or
+/// hotkey runs `mynotes --today` (or `--new`, `--capture`, `--toggle`), which hands
+/// the action to the running instance through [`editor_state::instance`] and exits.
+/// `mynotes://` links other apps open reach it the same way.
+#[derive(Clone)]
+pub struct QuickActions {
+    pub window: fltk::window::Window,
+    pub editor: ui::TextEditor,
+    pub status_bar: fltk::frame::Frame,
+    pub session: Rc<RefCell<editor_state::session::SessionStore>>,
+}
+
+impl QuickActions {
+    pub fn handle(&mut self, request: Request) {
+        match request {
+            Request::Action(action) => self.run(action),
+            Request::Open(link) => self.follow(&link),
+        }
+    }
+
+    pub fn run(&mut self, action: Action) {
+        match action {
+            Action::ToggleWindow => {
+                if self.window.shown() && self.window.visible() {
+                    // Through the close callback, so the session is saved.
+                    self.window.do_callback();
+                } else {
+                    self.window.show();
+                }
+            }
+            Action::NewNote => {
+                if !self.may_replace() {
+                    return;
+                }
+
+                let doc = self.editor.state.borrow().doc.clone();
+                let mut d = doc.borrow_mut();
+                let Ok(text_buffer) = editor_core::text::TextBuffer::new() else {
+                    return;
+                };
+                let mut blank = editor_state::document::Document::new(text_buffer);
+
+                blank.settings = d.settings.clone();
+                blank.save_transforms = d.save_transforms;
+                *d = blank;
+                drop(d);
+
+                self.editor.on_content_changed();
+                self.window.show();
+            }
+            Action::TodaysNote => {
+                let Some(path) = editor_state::capture::daily_note(std::time::SystemTime::now())
+                else {
+                    return;
+                };
+
+                if !path.exists() {
+                    let created = path
+                        .parent()
+                        .map_or(Ok(()), std::fs::create_dir_all)
+                        .and_then(|()| std::fs::write(&path, ""));
+
+                    if let Err(err) = created {
+                        tracing::error!("Error creating today's note: {:?}", err);
+                        fltk::dialog::alert_default(&format!(
+                            "Couldn't create {}: {err}",
+                            path.display()
+                        ));
+                        return;
+                    }
+                }
+
+                self.open(&path);
+            }
+            Action::QuickCapture => {
+                let Some(inbox) = editor_state::capture::inbox() else {
+                    fltk::dialog::alert_default("Couldn't tell where the inbox note is.");
+                    return;
+                };
+                let doc = self.editor.state.borrow().doc.clone();
+                let mut editor = self.editor.clone();
+                let open_inbox = inbox.clone();
+
+                crate::capture::show(inbox, move || {
+                    // Pick up the capture if the inbox is open with nothing unsaved.
+                    let mut d = doc.borrow_mut();
+
+                    if d.text_buffer.path() == Some(open_inbox.as_path())
+                        && !d.text_buffer.is_dirty()
+                    {
+                        if let Err(err) = d.revert() {
+                            tracing::error!("Error reloading inbox: {:?}", err);
+                        }
+                        drop(d);
+                        editor.on_content_changed();
+                    }
+                });
+            }
+        }
+    }
+
+    /// Opens the note `link` names, at its heading if it has one.
+    pub fn follow(&mut self, link: &DeepLink) {
+        let Some(path) = self.workspace_root().and_then(|root| link.resolve(&root)) else {
+            self.window.show();
+            fltk::dialog::alert_default(&format!("There's no note called {}.", link.note));
+            return;
+        };
+
+        self.open(&path);
+
+        let doc = self.editor.state.borrow().doc.clone();
+        let d = doc.borrow();
+
+        // Left where it was if the open note couldn't make way.
+        if d.text_buffer.path() != Some(path.as_path()) {
+            return;
+        }
+
+        let row = link
+            .heading
+            .as_deref()
+            .and_then(|heading| d.outline.find_anchor(heading))
+            .map(|heading| heading.row);
+
+        drop(d);
+        if let Some(row) = row {
+            self.editor.jump_to_row(row);
+        }
+    }
+
+    /// Where workspace-wide features (reminders, searches, links) look: the open
+    /// note's folder, or the inbox's before a note is open.
+    pub fn workspace_root(&self) -> Option<std::path::PathBuf> {
+        let doc = self.editor.state.try_borrow().ok()?.doc.clone();
+        let open = doc
+            .try_borrow()
+            .ok()?
+            .text_buffer
+            .path()
+            .map(std::path::Path::to_path_buf);
+
+        open.or_else(editor_state::capture::inbox)
+            .and_then(|path| path.parent().map(std::path::Path::to_path_buf))
+    }
+
+    /// Opens `path` in the window like File/Open does, and brings the window up.
+    pub fn open(&mut self, path: &std::path::Path) {
+        let doc = self.editor.state.borrow().doc.clone();
+
+        if doc.borrow().text_buffer.path() != Some(path) {
+            if crate::open_as_grid(path) {
+                return;
+            }
+            if !self.may_replace() {
+                return;
+            }
+
+            crate::remember_view(&self.editor, &mut self.session.borrow_mut());
+            if let Err(err) = doc.borrow_mut().open_file(path) {
+                tracing::error!("Error opening file: {:?}", err);
+                return;
+            }
+            self.editor.on_content_changed();
+            self.editor.reset_read_position();
+
+            if let Some(view) = self.session.borrow().recall(path) {
+                self.editor.restore_view_state(&view);
+            }
+            self.status_bar.set_label(&doc.borrow().indent_label());
+        }
+
+        self.window.show();
+        fltk::app::redraw();
+    }
+
+    /// Whether the open note can make way for another, asking first if it has
+    /// unsaved changes.
+    fn may_replace(&mut self) -> bool {
+        let dirty = self
+            .editor
+            .state
+            .borrow()
+            .doc
+            .borrow()
+            .text_buffer
+            .is_dirty();
+
+        if !dirty {
+            return true;
+        }
+
+        self.window.show();
+        fltk::dialog::choice2_default(
+            "The open note has unsaved changes. Discard them?",
+            "Cancel",
+            "Discard",
+            "",
+        ) == Some(1)
+    }
+}
+
+/// Starts taking quick actions and deep links from other processes while the app runs. If that
+/// can't be set up, each launch just opens a window of its own.
+pub fn listen(actions: &QuickActions) {
+    let Some(path) = editor_state::instance::config_path() else {
+        return;
+    };
+    let mut listener = match editor_state::instance::Listener::bind(&path) {
+        Ok(listener) => listener,
+        Err(err) => {
+            tracing::error!("Error listening for quick actions: {:?}", err);
+            return;
+        }
+    };
+
+    fltk::app::add_timeout3(0.2, {
+        let mut actions = actions.clone();
+        move |handle| {
+            for request in listener.poll() {
+                actions.handle(request);
+            }
+            fltk::app::repeat_timeout3(0.2, handle);
+        }
+    });
+}
+
+/// Hands `request` to an app already running, if there is one.
+pub fn forward(request: &Request) -> bool {
+    editor_state::instance::config_path()
+        .is_some_and(|path| editor_state::instance::send(&path, request).is_ok())
+}
@@ -0,0 +1,216 @@
+//! Benchmarks for the piece table and line index behind `TextBuffer`.
+//!
+//! Run with `cargo bench -p editor-core`. The 100 MB index build is slow to set up;
+//! `cargo bench -p editor-core -- index_build` runs it on its own.
+
+use std::hint::black_box;
+
+/// Small xorshift generator so every run edits the same positions.
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n.max(1) as u64) as usize
+    }
+}
+
+/// `lines` lines of Markdown-ish prose, each ending in `\n`.
+fn sample_text(lines: usize) -> String {
+    const LINES: [&str; 4] = [
+        "# A heading for the next section",
+        "Some prose with *emphasis*, a [link](https://example.com) and `code`.",
+        "- a list item that runs on for a little while",
+        "",
+    ];
+
+    let mut text = String::new();
+
+    for i in 0..lines {
+        text.push_str(LINES[i % LINES.len()]);
+        text.push('\n');
+    }
+
+    text
+}
+
+/// A random position inside the buffer.
+fn random_position(
+    buffer: &editor_core::text::TextBuffer,
+    rng: &mut Rng,
+) -> editor_core::cursor::Position {
+    let row = rng.below(buffer.line_count());
+    let len = buffer.get_line_len_at(row).unwrap_or(0) as usize;
+
+    // Stay before the line ending.
+    editor_core::cursor::Position::new(row, rng.below(len.saturating_sub(1).max(1)))
+}
+
+fn bench_edits(c: &mut criterion::Criterion) {
+    let mut group = c.benchmark_group("edit");
+    let text = sample_text(10_000);
+
+    group.bench_function("insert_random", |b| {
+        b.iter_batched_ref(
+            || {
+                (
+                    editor_core::text::TextBuffer::new_with_text(&text).unwrap(),
+                    Rng(0x2545_f491_4f6c_dd1d),
+                )
+            },
+            |(buffer, rng)| {
+                for _ in 0..1_000 {
+                    let pos = random_position(buffer, rng);
+                    let cursor = editor_core::cursor::Cursor::new(pos.row, pos.col);
+                    black_box(buffer.insert(&cursor, "xy").unwrap());
+                }
+            },
+            criterion::BatchSize::LargeInput,
+        );
+    });
+
+    group.bench_function("delete_random", |b| {
+        b.iter_batched_ref(
+            || {
+                (
+                    editor_core::text::TextBuffer::new_with_text(&text).unwrap(),
+                    Rng(0x9e37_79b9_7f4a_7c15),
+                )
+            },
+            |(buffer, rng)| {
+                for _ in 0..1_000 {
+                    let start = random_position(buffer, rng);
+                    let len = buffer.get_line_len_at(start.row).unwrap_or(0) as usize;
+                    let end = editor_core::cursor::Position::new(
+                        start.row,
+                        (start.col + 3).min(len.saturating_sub(1)),
+                    );
+                    let cursor = editor_core::cursor::Cursor::new_selection(start, end);
+                    black_box(buffer.delete_selection(&cursor).unwrap());
+                }
+            },
+            criterion::BatchSize::LargeInput,
+        );
+    });
+
+    group.finish();
+}
+
+fn bench_index_build(c: &mut criterion::Criterion) {
+    let mut group = c.benchmark_group("index_build");
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("large.md");
+    let line = sample_text(4);
+    let text = line.repeat(100 * 1024 * 1024 / line.len());
+
+    std::fs::write(&path, &text).unwrap();
+    drop(text);
+
+    group.sample_size(10);
+    group.throughput(criterion::Throughput::Bytes(
+        std::fs::metadata(&path).unwrap().len(),
+    ));
+    group.bench_function("open_100mb", |b| {
+        b.iter(|| black_box(editor_core::text::TextBuffer::open(&path).unwrap()));
+    });
+    group.finish();
+}
+
+fn bench_viewport(c: &mut criterion::Criterion) {
+    let mut group = c.benchmark_group("viewport");
+    let mut buffer = editor_core::text::TextBuffer::new_with_text(&sample_text(200_000)).unwrap();
+    let mut rng = Rng(0xdead_beef_cafe_f00d);
+
+    // Scatter edits so reads cross many pieces, as they do after a long session.
+    for _ in 0..2_000 {
+        let pos = random_position(&buffer, &mut rng);
+        let cursor = editor_core::cursor::Cursor::new(pos.row, pos.col);
+        buffer.insert(&cursor, "edit").unwrap();
+    }
+
+    group.bench_function("lines_60", |b| {
+        b.iter(|| {
+            let top = rng.below(buffer.line_count() - 60);
+            black_box(buffer.get_lines_stripped(top..top + 60))
+        });
+    });
+    group.bench_function("line_by_line_60", |b| {
+        b.iter(|| {
+            let top = rng.below(buffer.line_count() - 60);
+            for row in top..top + 60 {
+                black_box(buffer.get_line_stripped(row));
+            }
+        });
+    });
+    group.finish();
+}
+
+/// Records `count` typed inserts the way `Document` does, then replays them as
+/// undo and redo against the buffer.
+fn bench_undo_redo(c: &mut criterion::Criterion) {
+    let mut group = c.benchmark_group("history");
+    let text = sample_text(10_000);
+
+    group.bench_function("undo_redo_1000", |b| {
+        b.iter_batched_ref(
+            || {
+                let mut buffer = editor_core::text::TextBuffer::new_with_text(&text).unwrap();
+                let mut history = editor_core::history::History {
+                    undo_stack: Vec::new(),
+                    redo_stack: Vec::new(),
+                };
+                let mut rng = Rng(0x0123_4567_89ab_cdef);
+
+                for _ in 0..1_000 {
+                    let pos = random_position(&buffer, &mut rng);
+                    let cursor = editor_core::cursor::Cursor::new(pos.row, pos.col);
+                    let after = buffer.insert(&cursor, "word ").unwrap();
+
+                    history
+                        .record_insert(
+                            pos,
+                            "word ",
+                            cursor,
+                            editor_core::cursor::Cursor::new(after.row, after.col),
+                        )
+                        .unwrap();
+                }
+
+                (buffer, history)
+            },
+            |(buffer, history)| {
+                while let Some(transaction) = history.undo() {
+                    for action in transaction.actions.iter().rev() {
+                        if let editor_core::enums::EditAction::Insert { pos, text } = action {
+                            let end =
+                                editor_core::cursor::Position::new(pos.row, pos.col + text.len());
+                            let cursor = editor_core::cursor::Cursor::new_selection(*pos, end);
+                            buffer.delete_selection(&cursor).unwrap();
+                        }
+                    }
+                }
+                while let Some(transaction) = history.redo() {
+                    for action in &transaction.actions {
+                        if let editor_core::enums::EditAction::Insert { pos, text } = action {
+                            let cursor = editor_core::cursor::Cursor::new(pos.row, pos.col);
+                            buffer.insert(&cursor, text).unwrap();
+                        }
+                    }
+                }
+            },
+            criterion::BatchSize::LargeInput,
+        );
+    });
+    group.finish();
+}
+
+criterion::criterion_group!(
+    benches,
+    bench_edits,
+    bench_index_build,
+    bench_viewport,
+    bench_undo_redo
+);
+criterion::criterion_main!(benches);
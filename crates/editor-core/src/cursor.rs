@@ -92,6 +92,34 @@ impl Cursor {
             self.apply_movement(new_pos, shift, false);
         }
     }
+
+    /// Smart Home. The first press goes to the start of the visual row the caret is on;
+    /// pressing again (or on the first visual row) toggles between the first
+    /// non-whitespace character (`indent_end`) and column 0 of the logical line.
+    pub fn move_home(&mut self, visual_start: usize, indent_end: usize, shift: bool) {
+        let col = self.head.col;
+        let target = if visual_start > 0 && col > visual_start {
+            visual_start
+        } else if col == indent_end {
+            0
+        } else {
+            indent_end
+        };
+
+        self.apply_movement(Position::new(self.head.row, target), shift, true);
+    }
+
+    /// Smart End. The first press goes to the end of the visual row, the next one
+    /// to the end of the logical line.
+    pub fn move_end(&mut self, visual_end: usize, line_len: usize, shift: bool) {
+        let target = if self.head.col < visual_end {
+            visual_end
+        } else {
+            line_len
+        };
+
+        self.apply_movement(Position::new(self.head.row, target), shift, true);
+    }
 }
 
 impl Cursor {
@@ -221,6 +249,26 @@ mod tests {
         assert_eq!(end, Position::new(4, 20));
     }
 
+    #[test]
+    fn test_smart_home_and_end() {
+        // "    some long wrapped line" with a visual row starting at byte 14.
+        let mut cursor = Cursor::new(0, 20);
+
+        cursor.move_home(14, 4, false);
+        assert_eq!(cursor.head.col, 14, "First press: visual row start");
+        cursor.move_home(14, 4, false);
+        assert_eq!(cursor.head.col, 4, "Second press: first non-blank");
+        cursor.move_home(0, 4, true);
+        assert_eq!(cursor.head.col, 0, "Then toggles to column 0");
+        assert_eq!(cursor.anchor.col, 4);
+
+        let mut cursor = Cursor::new(0, 2);
+        cursor.move_end(13, 27, false);
+        assert_eq!(cursor.head.col, 13);
+        cursor.move_end(13, 27, false);
+        assert_eq!(cursor.head.col, 27);
+    }
+
     #[test]
     fn test_cursor_invert() {
         let mut cursor = Cursor::new_selection(Position::new(1, 5), Position::new(3, 15));
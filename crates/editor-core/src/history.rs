@@ -3,6 +3,9 @@ pub struct Transaction {
     pub actions: Vec<crate::enums::EditAction>,
     pub cursor_before: crate::cursor::Cursor,
     pub cursor_after: crate::cursor::Cursor,
+    /// Sealed transactions are complete commands (reindent, save cleanup, ...)
+    /// and are never extended by the keystroke batching below.
+    pub sealed: bool,
 }
 
 #[derive(Debug)]
@@ -39,6 +42,29 @@ impl History {
             ],
             cursor_before,
             cursor_after,
+            sealed: false,
+        });
+    }
+
+    /// Records a composite, multi-action edit as one undo step.
+    /// The transaction is sealed so subsequent typing never batches into it.
+    pub fn record_transaction(
+        &mut self,
+        actions: Vec<crate::enums::EditAction>,
+        cursor_before: crate::cursor::Cursor,
+        cursor_after: crate::cursor::Cursor,
+    ) {
+        if actions.is_empty() {
+            return;
+        }
+
+        self.redo_stack.clear();
+
+        self.undo_stack.push(Transaction {
+            actions,
+            cursor_before,
+            cursor_after,
+            sealed: true,
         });
     }
 
@@ -55,6 +81,7 @@ impl History {
         self.redo_stack.clear();
 
         if let Some(last_tx) = self.undo_stack.last_mut()
+            && !last_tx.sealed
             && let Some(crate::enums::EditAction::Insert {
                             pos: last_pos,
                             text: last_text,
@@ -84,6 +111,7 @@ impl History {
             }],
             cursor_before,
             cursor_after,
+            sealed: false,
         });
 
         Ok(())
@@ -101,6 +129,7 @@ impl History {
         self.redo_stack.clear();
 
         if let Some(last_tx) = self.undo_stack.last_mut()
+            && !last_tx.sealed
             && let Some(crate::enums::EditAction::Delete {
                             pos: last_start,
                             end: last_end,
@@ -158,6 +187,7 @@ impl History {
             }],
             cursor_before,
             cursor_after,
+            sealed: false,
         });
 
         Ok(())
@@ -415,6 +445,39 @@ mod tests {
         assert_insert(&tx.actions[1], Position::new(0, 0), "pie");
     }
 
+    #[test]
+    fn test_record_transaction_is_not_batched_into() {
+        let mut history = History {
+            undo_stack: vec![],
+            redo_stack: vec![],
+        };
+
+        history.record_transaction(
+            vec![crate::enums::EditAction::Insert {
+                pos: Position::new(0, 0),
+                text: "ab".to_string(),
+            }],
+            Cursor::new(0, 0),
+            Cursor::new(0, 2),
+        );
+        history
+            .record_insert(
+                Position::new(0, 2),
+                "c",
+                Cursor::new(0, 2),
+                Cursor::new(0, 3),
+            )
+            .unwrap();
+
+        assert_eq!(history.undo_stack.len(), 2);
+        assert!(history.undo_stack[0].sealed);
+        assert_insert(&history.undo_stack[0].actions[0], Position::new(0, 0), "ab");
+
+        // Empty transactions are ignored entirely
+        history.record_transaction(vec![], Cursor::new(0, 3), Cursor::new(0, 3));
+        assert_eq!(history.undo_stack.len(), 2);
+    }
+
     #[test]
     fn test_undo_redo_stack_movement() {
         let mut history = History {
@@ -45,6 +45,17 @@ impl Node {
         }
     }
 
+    /// Number of levels from this node down to the leaves, counting both ends.
+    /// The tree is balanced, so following the first child is enough.
+    pub fn depth(&self) -> usize {
+        match self {
+            Node::Internal(internal_node) => {
+                1 + internal_node.children.first().map_or(0, Node::depth)
+            }
+            Node::Leaf(_) => 1,
+        }
+    }
+
     #[inline]
     pub fn summary_mut(&mut self) -> &mut crate::line_index::line_summary::LineSummary {
         match self {
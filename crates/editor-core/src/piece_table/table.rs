@@ -2,12 +2,14 @@ use std::ops::{Add, AddAssign, Sub, SubAssign};
 
 #[derive(Debug)]
 pub struct PieceTable {
-    /// Original unchanged piece_table (shared, zero-copy).
-    pub original: io::mmap::MmapFile,
+    /// Original unchanged piece_table (shared, zero-copy when mapped).
+    pub original: io::backing::FileBacking,
     /// Append-only buffer storing piece_table to be inserted.
     pub buf: Vec<u8>,
     /// Ordered list of pieces describing the visible document.
     pub pieces: Vec<crate::piece_table::piece::Piece>,
+    /// Number of reads served so far. Instrumentation for the performance HUD.
+    reads: std::cell::Cell<u64>,
 }
 
 pub trait SliceOfWithStartEnd {
@@ -28,7 +30,7 @@ pub trait SliceOfWithStartEnd {
 */
 
 impl PieceTable {
-    pub fn new(mmap_file: io::mmap::MmapFile) -> Result<Self, crate::enums::MathError> {
+    pub fn new(mmap_file: io::backing::FileBacking) -> Result<Self, crate::enums::MathError> {
         let mut pieces = Vec::new();
 
         if !mmap_file.is_empty() {
@@ -42,6 +44,7 @@ impl PieceTable {
             original: mmap_file,
             buf: Vec::with_capacity(crate::piece_table::BASELINE_CAPACITY),
             pieces,
+            reads: std::cell::Cell::new(0),
         })
     }
 }
@@ -61,6 +64,12 @@ impl PieceTable {
         self.pieces.iter().map(super::piece::Piece::len).sum()
     }
 
+    /// How many times the document text has been read, e.g. by `get_string`.
+    #[inline]
+    pub fn reads(&self) -> u64 {
+        self.reads.get()
+    }
+
     #[inline]
     #[allow(dead_code)]
     pub fn is_empty(&self) -> bool {
@@ -363,6 +372,8 @@ impl PieceTable {
     ) -> Result<Vec<u8>, crate::enums::MathError> {
         let mut res = Vec::with_capacity(<u64 as TryInto<usize>>::try_into(len)?);
 
+        self.reads.set(self.reads.get() + 1);
+
         for piece in &self.pieces {
             let piece_len = piece.len();
 
@@ -393,6 +404,36 @@ impl PieceTable {
         Ok(res)
     }
 
+    /// Asks the OS to page in the parts of the original file that back `pos..pos + len`.
+    /// Text typed since opening lives in memory already and is skipped.
+    pub fn prefetch(&self, mut pos: u64, mut len: u64) -> std::io::Result<()> {
+        for piece in &self.pieces {
+            let piece_len = piece.len();
+
+            if pos >= piece_len {
+                pos.sub_assign(piece_len);
+
+                continue;
+            }
+
+            let take = piece_len.sub(pos).min(len);
+
+            if piece.buf_kind == crate::enums::BufferKind::Original {
+                self.original
+                    .prefetch((piece.range.start + pos) as usize, take as usize)?;
+            }
+            len.sub_assign(take);
+
+            if len == 0 {
+                break;
+            }
+
+            pos = 0;
+        }
+
+        Ok(())
+    }
+
     pub fn get_string(&self, pos: u64, len: u64) -> Result<String, crate::enums::MathError> {
         let bytes_vec = self.get_bytes_at(pos, len)?;
 
@@ -440,11 +481,23 @@ impl PieceTable {
 */
 
 impl PieceTable {
+    /// Drops what the pieces take from the original past `len`, after the original
+    /// came out that much shorter.
+    pub fn clip_original(&mut self, len: u64) {
+        for piece in &mut self.pieces {
+            if piece.buf_kind == crate::enums::BufferKind::Original {
+                piece.range.end = piece.range.end.min(len);
+                piece.range.start = piece.range.start.min(piece.range.end);
+            }
+        }
+        self.pieces.retain(|piece| !piece.is_empty());
+    }
+
     /// Resets the piece table state after a successful save.
     ///
     /// This swaps out the backing memory-mapped file, clears the append buffer,
     /// and collapses all pieces into a single piece representing the newly saved file.
-    pub fn reset_to_mmap(&mut self, new_mmap: io::mmap::MmapFile) {
+    pub fn reset_to_mmap(&mut self, new_mmap: io::backing::FileBacking) {
         // 1. Get the size of the newly saved file.
         // We cast the usize length to u64 to match your Piece range fields.
         let file_size = <usize as TryInto<u64>>::try_into(new_mmap.len()).expect("");
@@ -526,7 +579,8 @@ mod piece_table_tests {
 
         let path = temp_file.into_temp_path();
 
-        crate::piece_table::table::PieceTable::new(io::mmap::MmapFile::open(path).unwrap()).unwrap()
+        crate::piece_table::table::PieceTable::new(io::backing::FileBacking::open(path).unwrap())
+            .unwrap()
     }
 
     #[test]
@@ -563,11 +617,11 @@ mod piece_table_tests {
     }
 
     /// Helper function to create a dummy MmapFile with specific text
-    fn create_mock_mmap(content: &[u8]) -> io::mmap::MmapFile {
+    fn create_mock_mmap(content: &[u8]) -> io::backing::FileBacking {
         let mut temp = tempfile::NamedTempFile::new().unwrap();
         temp.write_all(content).unwrap();
         temp.as_file().sync_all().unwrap();
-        io::mmap::MmapFile::open(temp.path()).unwrap()
+        io::backing::FileBacking::open(temp.path()).unwrap()
     }
 
     #[test]
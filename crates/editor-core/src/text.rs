@@ -41,6 +41,88 @@ pub fn detect_line_ending(bytes: &[u8]) -> LineEnding {
     LineEnding::LF
 }
 
+/// Indentation convention found by sampling a file's leading whitespace.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Indentation {
+    Tabs,
+    /// Spaces, with the number of columns per indentation level.
+    Spaces(usize),
+}
+
+/// Only the start of the file is sampled; that's plenty to spot a convention
+/// and keeps opening huge files cheap.
+const INDENT_SAMPLE_BYTES: usize = 64 * 1024;
+
+/// Guesses the indentation of `bytes`. Returns `None` if no line is indented.
+///
+/// Tabs vs spaces is a majority vote. The width is the most common change in
+/// indentation between consecutive indented lines, which is robust to
+/// alignment-only lines and deeply nested blocks.
+pub fn detect_indentation(bytes: &[u8]) -> Option<Indentation> {
+    let sample = &bytes[..bytes.len().min(INDENT_SAMPLE_BYTES)];
+    let mut tab_lines = 0usize;
+    let mut space_lines = 0usize;
+    let mut deltas = [0usize; 9];
+    let mut prev_spaces = 0usize;
+
+    for line in sample.split(|&b| b == b'\n') {
+        if line.iter().all(|b| b.is_ascii_whitespace()) {
+            continue;
+        }
+
+        match line.first() {
+            Some(b'\t') => {
+                tab_lines += 1;
+                prev_spaces = 0;
+            }
+            Some(b' ') => {
+                let spaces = line.iter().take_while(|&&b| b == b' ').count();
+
+                // A tab after spaces is mixed indentation; it tells us nothing reliable.
+                if line.get(spaces) == Some(&b'\t') {
+                    continue;
+                }
+
+                space_lines += 1;
+
+                let delta = spaces.abs_diff(prev_spaces);
+                if (2..deltas.len()).contains(&delta) {
+                    deltas[delta] += 1;
+                }
+                prev_spaces = spaces;
+            }
+            _ => prev_spaces = 0,
+        }
+    }
+
+    if tab_lines == 0 && space_lines == 0 {
+        return None;
+    }
+
+    if tab_lines >= space_lines {
+        return Some(Indentation::Tabs);
+    }
+
+    // Ties go to the larger width: a file indented by 4 also shows steps of 8.
+    let width = (2..deltas.len())
+        .max_by_key(|&w| deltas[w])
+        .filter(|&w| deltas[w] > 0)
+        .unwrap_or(4);
+
+    Some(Indentation::Spaces(width))
+}
+
+/// Snapshot of [`TextBuffer::stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BufferStats {
+    /// Pieces in the piece table; grows with every edit that splits one.
+    pub pieces: usize,
+    /// Levels in the line index B-tree, leaves included.
+    pub index_depth: usize,
+    /// Reads served by the piece table since the buffer was created.
+    pub reads: u64,
+}
+
 /// # The Core Philosophies of This API
 ///
 /// - Coordinate-Based: The UI doesn't know what a byte offset is. It thinks in (line, column). The `TextBuffer`'s job is to take those coordinates, use your B-Tree to resolve them into absolute byte offsets, and feed those offsets to the Piece Table.
@@ -52,6 +134,8 @@ pub struct TextBuffer {
     line_index: crate::line_index::btree::BTreeLineIndex,
 
     pub line_ending: LineEnding,
+    /// Indentation detected when the buffer was created, if the content had any.
+    pub indentation: Option<Indentation>,
 
     /// Tracks if the buffer has unsaved changes.
     is_dirty: bool,
@@ -59,6 +143,9 @@ pub struct TextBuffer {
     /// The file path, if this buffer is tied to a file on disk.
     filepath: Option<std::path::PathBuf>,
 
+    /// How the file is held between opening and saving it.
+    access_mode: io::backing::AccessMode,
+
     /// Keeps the temporary backing file alive for new/unsaved buffers.
     /// Once the file is explicitly saved, we can drop this.
     _temp_backing: Option<tempfile::NamedTempFile>,
@@ -86,7 +173,7 @@ impl TextBuffer {
         file.write_all(b"")?;
         file.sync_all()?;
 
-        let mmap_file = io::mmap::MmapFile::open(tmp_file.path())?;
+        let mmap_file = io::backing::FileBacking::open(tmp_file.path())?;
         let line_index = crate::line_index::btree::BTreeLineIndex::new(mmap_file.as_slice())?;
         let piece_table = crate::piece_table::table::PieceTable::new(mmap_file)?;
 
@@ -94,8 +181,10 @@ impl TextBuffer {
             piece_table,
             line_index,
             line_ending: LineEnding::LF,
+            indentation: None,
             is_dirty: false,
             filepath: None,
+            access_mode: io::backing::AccessMode::default(),
             _temp_backing: Some(tmp_file),
         })
     }
@@ -115,11 +204,12 @@ impl TextBuffer {
         } else {
             LineEnding::LF
         };
+        let indentation = detect_indentation(text.as_bytes());
 
         file.write_all(text.as_bytes())?;
         file.sync_all()?;
 
-        let mmap_file = io::mmap::MmapFile::open(tmp_file.path())?;
+        let mmap_file = io::backing::FileBacking::open(tmp_file.path())?;
         let line_index = crate::line_index::btree::BTreeLineIndex::new(mmap_file.as_slice())?;
         let piece_table = crate::piece_table::table::PieceTable::new(mmap_file)?;
 
@@ -127,8 +217,10 @@ impl TextBuffer {
             piece_table,
             line_index,
             line_ending,
+            indentation,
             is_dirty: false,
             filepath: None,
+            access_mode: io::backing::AccessMode::default(),
             _temp_backing: Some(tmp_file),
         })
     }
@@ -141,15 +233,21 @@ impl TextBuffer {
     /// or if the memory mapping operation fails.
     pub fn open<P: AsRef<std::path::Path>>(path: P) -> crate::errors::TextBufferResult<Self> {
         let path_buf = path.as_ref().to_path_buf();
-        // 1. Load MmapFile.
+        // 1. Load the file, mapped if possible.
         // The OS sets up the page tables but doesn't read the whole file into RAM yet.
-        let mmap_file = io::mmap::MmapFile::open(&path_buf)?;
+        let mmap_file = io::backing::FileBacking::open(&path_buf)?;
         // 3. Scan the MmapFile slice to build the BTreeLineIndex.
         // We do this BEFORE transferring ownership of the mmap_file to the PieceTable.
         // The slice borrow is immediately dropped when `BTreeLineIndex::new` returns.
         // (Assuming `new` returns a Result, if not, remove the `?`).
+        // The scans below read the file front to back; page it in ahead of them.
+        // Paging hints are best-effort, so a rejected one is ignored.
+        let _ = mmap_file.advise(io::mmap::AccessPattern::Sequential);
         let line_ending = detect_line_ending(mmap_file.as_slice());
+        let indentation = detect_indentation(mmap_file.as_slice());
         let line_index = crate::line_index::btree::BTreeLineIndex::new(mmap_file.as_slice())?;
+        // From here on reads follow the viewport and the cursor, so stop reading ahead.
+        let _ = mmap_file.advise(io::mmap::AccessPattern::Random);
         // 2. Initialize PieceTable with the MmapFile.
         // This moves `mmap_file` into the PieceTable, where it will live as read-only backing storage.
         let piece_table = crate::piece_table::table::PieceTable::new(mmap_file)?;
@@ -163,8 +261,10 @@ impl TextBuffer {
             piece_table,
             line_index,
             line_ending,
+            indentation,
             is_dirty: false,
             filepath: Some(path_buf),
+            access_mode: io::backing::AccessMode::default(),
             _temp_backing: None, // This is a real file on disk, no temp backing needed
         })
     }
@@ -174,21 +274,26 @@ impl TextBuffer {
         path: P,
     ) -> crate::errors::TextBufferResult<()> {
         let path_buf = path.as_ref().to_path_buf();
-        // 1. Load MmapFile.
+        // 1. Load the file, mapped if possible.
         // The OS sets up the page tables but doesn't read the whole file into RAM yet.
-        let mmap_file = io::mmap::MmapFile::open(&path_buf)?;
+        let mmap_file = io::backing::FileBacking::open_with(&path_buf, self.access_mode)?;
+        // Same paging hints as `open`: sequential while scanning, random afterwards.
+        let _ = mmap_file.advise(io::mmap::AccessPattern::Sequential);
         let line_ending = detect_line_ending(mmap_file.as_slice());
+        let indentation = detect_indentation(mmap_file.as_slice());
         // 3. Scan the MmapFile slice to build the BTreeLineIndex.
         // We do this BEFORE transferring ownership of the mmap_file to the PieceTable.
         // The slice borrow is immediately dropped when `BTreeLineIndex::new` returns.
         // (Assuming `new` returns a Result, if not, remove the `?`).
         let line_index = crate::line_index::btree::BTreeLineIndex::new(mmap_file.as_slice())?;
+        let _ = mmap_file.advise(io::mmap::AccessPattern::Random);
         // 2. Initialize PieceTable with the MmapFile.
         // This moves `mmap_file` into the PieceTable, where it will live as read-only backing storage.
         let piece_table = crate::piece_table::table::PieceTable::new(mmap_file)?;
 
         self.piece_table = piece_table;
         self.line_ending = line_ending;
+        self.indentation = indentation;
         self.line_index = line_index;
         self.filepath = Some(path_buf);
         self._temp_backing = None;
@@ -242,8 +347,21 @@ impl TextBuffer {
         // back into a standard io::Error so it easily converts into TextBufferResult.
         temp_save_file.persist(filepath).map_err(|e| e.error)?;
 
+        self.adopt_written(filepath.clone())
+    }
+
+    /// Takes `path`, which already holds exactly the text of this buffer, as the file
+    /// behind it, as if the buffer had just been saved there. For writes done outside
+    /// [`Self::save`], such as several files committed together.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be opened.
+    pub fn adopt_written<P: AsRef<std::path::Path>>(&mut self, path: P) -> std::io::Result<()> {
+        let filepath = path.as_ref().to_path_buf();
+
         // 4. Drop the old MmapFile and map the newly saved file.
-        let new_mmap = io::mmap::MmapFile::open(filepath)?;
+        let new_mmap = io::backing::FileBacking::open_with(&filepath, self.access_mode)?;
 
         // 5. Reset the PieceTable state.
         // This method on your PieceTable should:
@@ -254,6 +372,8 @@ impl TextBuffer {
 
         // 6. Reset dirty flag.
         self.is_dirty = false;
+        self.filepath = Some(filepath);
+        self._temp_backing = None;
 
         Ok(())
     }
@@ -321,6 +441,77 @@ impl TextBuffer {
         self.is_dirty
     }
 
+    /// Records that the contents reached safe storage some other way than [`Self::save`],
+    /// e.g. written by a background thread or parked in a local cache.
+    #[inline]
+    pub fn mark_clean(&mut self) {
+        self.is_dirty = false;
+    }
+
+    #[inline]
+    pub fn access_mode(&self) -> io::backing::AccessMode {
+        self.access_mode
+    }
+
+    /// Changes how the file is held from the next open or save on. Switching to
+    /// `Cooperative` also lets go of the current file right away.
+    pub fn set_access_mode(&mut self, mode: io::backing::AccessMode) {
+        self.access_mode = mode;
+
+        if mode == io::backing::AccessMode::Cooperative {
+            self.release_file();
+        }
+    }
+
+    /// Copies the text off its file into memory and lets go of the file, e.g. before
+    /// the file is moved or deleted. The next save or open holds a file again.
+    pub fn release_file(&mut self) {
+        self.detach();
+    }
+
+    /// Moves the text off the file mapping if another program truncated the file, so
+    /// reads can't fault. The bytes that were already gone are dropped from the text.
+    /// Marks the buffer dirty, as it no longer matches the disk. Returns how many
+    /// bytes were lost, or `None` if the file is intact.
+    pub fn detach_if_truncated(&mut self) -> Option<usize> {
+        if !self.piece_table.original.is_truncated() {
+            return None;
+        }
+
+        let lost = self.detach();
+
+        self.is_dirty = true;
+        Some(lost)
+    }
+
+    /// Copies the text off its file, dropping what the file lost on disk. Returns
+    /// how many bytes that was.
+    fn detach(&mut self) -> usize {
+        let lost = self.piece_table.original.detach();
+
+        if lost > 0 {
+            self.piece_table
+                .clip_original(self.piece_table.original.len() as u64);
+
+            let bytes: Vec<u8> = self.piece_table.iter_bytes().flatten().copied().collect();
+
+            if let Ok(line_index) = crate::line_index::btree::BTreeLineIndex::new(&bytes) {
+                self.line_index = line_index;
+            }
+        }
+
+        lost
+    }
+
+    /// Shape of the underlying structures, for performance diagnostics.
+    pub fn stats(&self) -> BufferStats {
+        BufferStats {
+            pieces: self.piece_table.pieces.len(),
+            index_depth: self.line_index.root.depth(),
+            reads: self.piece_table.reads(),
+        }
+    }
+
     #[inline]
     pub fn path(&self) -> Option<&std::path::Path> {
         self.filepath.as_deref()
@@ -359,6 +550,60 @@ impl TextBuffer {
         Some(line)
     }
 
+    /// Returns the lines in `rows` without their line endings.
+    ///
+    /// The byte ranges come from a single `LineRangeIter` walk and the text from a single
+    /// piece table read, instead of one tree lookup and one read per line.
+    pub fn get_lines_stripped(&self, rows: std::ops::Range<usize>) -> Vec<String> {
+        let ranges: Vec<std::ops::Range<u64>> = self
+            .lines(rows.start, rows.end)
+            .map(|(_, range)| range)
+            .collect();
+        let (Some(first), Some(last)) = (ranges.first(), ranges.last()) else {
+            return Vec::new();
+        };
+        let base = first.start;
+        let Ok(text) = self.piece_table.get_string(base, last.end - base) else {
+            return Vec::new();
+        };
+
+        ranges
+            .iter()
+            .map(|range| {
+                let line = &text[(range.start - base) as usize..(range.end - base) as usize];
+                let line = line.strip_suffix('\n').unwrap_or(line);
+                line.strip_suffix('\r').unwrap_or(line).to_string()
+            })
+            .collect()
+    }
+
+    /// Hints the OS to page in the text of `rows` ahead of a read, e.g. the screens
+    /// just past the viewport while scrolling a large file. Rows past the end are
+    /// ignored, and so are failures: it's only a hint.
+    pub fn prefetch_lines(&self, rows: std::ops::Range<usize>) {
+        let line_count = self.line_count();
+        let end = rows.end.min(line_count);
+
+        if rows.start >= end {
+            return;
+        }
+
+        let Some(start_abs) = self.line_index.line_idx_to_abs_idx(rows.start, false) else {
+            return;
+        };
+        let end_abs = if end == line_count {
+            self.byte_length()
+        } else {
+            self.line_index
+                .line_idx_to_abs_idx(end, false)
+                .unwrap_or_else(|| self.byte_length())
+        };
+
+        let _ = self
+            .piece_table
+            .prefetch(start_abs, end_abs.saturating_sub(start_abs));
+    }
+
     /// Returns the LineRangeIter to traverse the B-Tree for a specific range of lines.
     /// This is your hyper-fast path for rendering the visible viewport on screen.
     pub fn lines(
@@ -378,6 +623,18 @@ impl TextBuffer {
     /// `row` is the 0-indexed line number.
     /// `col` is the 0-indexed byte offset within that specific line.
     pub fn point_to_abs_offset(&self, row: usize, col: usize) -> Option<u64> {
+        // A file loaded from disk doesn't get an empty row after its trailing newline,
+        // but the position right after that newline still has to be addressable.
+        if col == 0
+            && row == self.line_count()
+            && row > 0
+            && self
+                .get_line(row - 1)
+                .is_some_and(|line| line.ends_with('\n'))
+        {
+            return Some(self.byte_length());
+        }
+
         // 1. Find where the line starts in the 1D byte stream
         let line_start_abs_idx = self.line_index.line_idx_to_abs_idx(row, false)?;
         // 2. Validate the column doesn't exceed the line's length for safety
@@ -711,6 +968,34 @@ mod text_buffer_getter_tests {
     use super::*;
     use crate::cursor::{Cursor, Position};
 
+    #[test]
+    fn test_end_after_trailing_newline_is_addressable() {
+        let mut text_buffer = TextBuffer::new_with_text("a\nb\n").unwrap();
+        let all = Cursor::new_selection(Position::new(0, 0), Position::new(2, 0));
+
+        assert_eq!(text_buffer.point_to_abs_offset(2, 0), Some(4));
+        assert_eq!(text_buffer.point_to_abs_offset(3, 0), None);
+        assert_eq!(
+            text_buffer.get_cursor_selection(&all).unwrap().as_deref(),
+            Some("a\nb\n")
+        );
+
+        text_buffer.delete_selection(&all).unwrap();
+        assert_eq!(text_buffer.to_string(), "");
+    }
+
+    #[test]
+    fn test_get_lines_stripped() {
+        let text_buffer = TextBuffer::new_with_text("zero\r\none\r\ntwo\r\nthree").unwrap();
+
+        assert_eq!(text_buffer.get_lines_stripped(1..3), vec!["one", "two"]);
+        assert_eq!(
+            text_buffer.get_lines_stripped(2..4),
+            vec!["two".to_string(), "three".to_string()]
+        );
+        assert!(text_buffer.get_lines_stripped(9..12).is_empty());
+    }
+
     #[test]
     fn test_get_line() {
         let mut text_buffer = TextBuffer::new().expect("Failed to create new TextBuffer");
@@ -774,6 +1059,65 @@ mod text_buffer_getter_tests {
                 .is_none()
         );
     }
+
+    #[test]
+    fn test_stats() {
+        let mut buffer = TextBuffer::new_with_text("one\ntwo\n").unwrap();
+        let before = buffer.stats();
+
+        assert_eq!(before.pieces, 1);
+        assert_eq!(before.index_depth, 1);
+
+        buffer.insert(&Cursor::new(0, 1), "X").unwrap();
+        buffer.get_line(0);
+
+        let after = buffer.stats();
+        assert_eq!(after.pieces, 3);
+        assert!(after.reads > before.reads);
+
+        // 16 lines fill a leaf, so 17 lines need a root above two leaves.
+        let tall = TextBuffer::new_with_text(&"x\n".repeat(17)).unwrap();
+        assert_eq!(tall.stats().index_depth, 2);
+    }
+
+    #[test]
+    fn test_prefetch_lines_is_harmless() {
+        let mut buffer = TextBuffer::new_with_text(&"line\n".repeat(100)).unwrap();
+
+        buffer.insert(&Cursor::new(50, 2), "typed\n").unwrap();
+        buffer.prefetch_lines(40..60);
+        buffer.prefetch_lines(90..500);
+        buffer.prefetch_lines(500..600);
+        buffer.prefetch_lines(10..10);
+
+        assert_eq!(buffer.get_line(51).unwrap(), "ne\n");
+        assert_eq!(buffer.line_count(), 101);
+    }
+
+    #[test]
+    fn test_cooperative_access_releases_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("note.md");
+        std::fs::write(&path, "synced\n").unwrap();
+
+        let mut buffer = TextBuffer::open(&path).unwrap();
+        assert!(buffer.piece_table.original.is_mapped());
+
+        buffer.set_access_mode(io::backing::AccessMode::Cooperative);
+        assert!(!buffer.piece_table.original.is_mapped());
+
+        // A sync tool replacing the file leaves the open note alone.
+        std::fs::write(&path, "remote\n").unwrap();
+        assert_eq!(buffer.get_line(0).unwrap(), "synced\n");
+
+        buffer.insert(&Cursor::new(0, 0), "local ").unwrap();
+        buffer.save().unwrap();
+        assert!(!buffer.piece_table.original.is_mapped());
+
+        buffer.open_from(&path).unwrap();
+        assert!(!buffer.piece_table.original.is_mapped());
+        assert_eq!(buffer.get_line(0).unwrap(), "local synced\n");
+    }
 }
 
 #[cfg(test)]
@@ -1057,3 +1401,33 @@ mod text_buffer_editing_tests {
         assert_eq!(buffer.get_line(0), Some("Fixed Me".to_string()));
     }
 }
+
+#[cfg(test)]
+mod text_indentation_tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_indentation() {
+        assert_eq!(detect_indentation(b"no indent\nat all\n"), None);
+        assert_eq!(
+            detect_indentation(b"fn a() {\n\tif x {\n\t\ty();\n\t}\n}\n"),
+            Some(Indentation::Tabs)
+        );
+        assert_eq!(
+            detect_indentation(b"- a\n  - b\n    - c\n  - d\n- e\n"),
+            Some(Indentation::Spaces(2))
+        );
+        assert_eq!(
+            detect_indentation(b"a:\n    b:\n        c\n    d\n\n    e\n"),
+            Some(Indentation::Spaces(4))
+        );
+    }
+
+    #[test]
+    fn test_textbuffer_stores_detected_indentation() {
+        let buffer = TextBuffer::new_with_text("list\n   - item\n      - nested\n").unwrap();
+
+        assert_eq!(buffer.indentation, Some(Indentation::Spaces(3)));
+        assert_eq!(TextBuffer::new().unwrap().indentation, None);
+    }
+}
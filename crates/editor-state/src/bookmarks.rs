@@ -0,0 +1,95 @@
+/// Rows marked to come back to, kept in step with edits like
+/// [`crate::folds::Folds`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Bookmarks {
+    /// Sorted, no repeats.
+    rows: Vec<usize>,
+}
+
+impl Bookmarks {
+    pub fn rows(&self) -> &[usize] {
+        &self.rows
+    }
+
+    pub fn contains(&self, row: usize) -> bool {
+        self.rows.binary_search(&row).is_ok()
+    }
+
+    /// Marks `row`, or unmarks it if it was. Returns whether it's marked now.
+    pub fn toggle(&mut self, row: usize) -> bool {
+        match self.rows.binary_search(&row) {
+            Ok(i) => {
+                self.rows.remove(i);
+                false
+            }
+            Err(i) => {
+                self.rows.insert(i, row);
+                true
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.rows.clear();
+    }
+
+    /// Shifts the marks below an edit that replaced rows `start..=old_end` by
+    /// `start..=new_end`. Marks on rows the edit removed move up to its last row.
+    pub fn update(&mut self, start: usize, old_end: usize, new_end: usize) {
+        for row in &mut self.rows {
+            if *row > old_end {
+                *row = (*row as isize + new_end as isize - old_end as isize) as usize;
+            } else if *row > new_end {
+                *row = new_end.max(start);
+            }
+        }
+        self.rows.dedup();
+    }
+
+    /// The first mark below `row`, wrapping around to the top.
+    pub fn next(&self, row: usize) -> Option<usize> {
+        self.rows
+            .iter()
+            .find(|&&mark| mark > row)
+            .or(self.rows.first())
+            .copied()
+    }
+
+    /// The first mark above `row`, wrapping around to the bottom.
+    pub fn previous(&self, row: usize) -> Option<usize> {
+        self.rows
+            .iter()
+            .rfind(|&&mark| mark < row)
+            .or(self.rows.last())
+            .copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bookmarks_follow_edits() {
+        let mut bookmarks = Bookmarks::default();
+
+        assert!(bookmarks.toggle(2));
+        assert!(bookmarks.toggle(5));
+        assert!(bookmarks.toggle(9));
+        assert!(!bookmarks.toggle(9));
+
+        // Two lines inserted on row 1.
+        bookmarks.update(1, 1, 3);
+        assert_eq!(bookmarks.rows(), &[4, 7]);
+
+        // Rows 4..=7 joined into one.
+        bookmarks.update(4, 7, 4);
+        assert_eq!(bookmarks.rows(), &[4]);
+
+        bookmarks.toggle(10);
+        assert_eq!(bookmarks.next(4), Some(10));
+        assert_eq!(bookmarks.next(10), Some(4));
+        assert_eq!(bookmarks.previous(4), Some(10));
+        assert_eq!(bookmarks.previous(7), Some(4));
+    }
+}
@@ -0,0 +1,226 @@
+/// Recursive descent over arithmetic: `+ -` bind loosest, then `* / %`, then `^`
+/// (right to left), then unary minus and parentheses.
+struct Parser<'a> {
+    rest: &'a str,
+    /// Signs, parentheses and powers around what's being read.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    /// Deeper nesting than this is refused rather than recursed into.
+    const MAX_DEPTH: usize = 64;
+
+    /// Reads an operand nested in the one being read.
+    fn nested(
+        &mut self,
+        read: impl FnOnce(&mut Self) -> Result<f64, String>,
+    ) -> Result<f64, String> {
+        if self.depth == Self::MAX_DEPTH {
+            return Err("Nested too deeply".to_string());
+        }
+
+        self.depth += 1;
+        let read = read(self);
+        self.depth -= 1;
+        read
+    }
+
+    fn skip_space(&mut self) {
+        self.rest = self.rest.trim_start();
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_space();
+        match self.rest.strip_prefix(c) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn sum(&mut self) -> Result<f64, String> {
+        let mut value = self.product()?;
+
+        loop {
+            if self.eat('+') {
+                value += self.product()?;
+            } else if self.eat('-') {
+                value -= self.product()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<f64, String> {
+        let mut value = self.power()?;
+
+        loop {
+            if self.eat('*') || self.eat('×') {
+                value *= self.power()?;
+            } else if self.eat('/') || self.eat('÷') {
+                let by = self.power()?;
+
+                if by == 0.0 {
+                    return Err("Division by zero".to_string());
+                }
+                value /= by;
+            } else if self.eat('%') {
+                let by = self.power()?;
+
+                if by == 0.0 {
+                    return Err("Division by zero".to_string());
+                }
+                value %= by;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn power(&mut self) -> Result<f64, String> {
+        let base = self.unary()?;
+
+        if self.eat('^') {
+            Ok(base.powf(self.nested(Self::power)?))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn unary(&mut self) -> Result<f64, String> {
+        if self.eat('-') {
+            return Ok(-self.nested(Self::unary)?);
+        }
+        if self.eat('+') {
+            return self.nested(Self::unary);
+        }
+        if self.eat('(') {
+            let value = self.nested(Self::sum)?;
+
+            if !self.eat(')') {
+                return Err("Missing )".to_string());
+            }
+            return Ok(value);
+        }
+
+        self.skip_space();
+        let len = self
+            .rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.' && c != '_')
+            .unwrap_or(self.rest.len());
+        let (number, rest) = self.rest.split_at(len);
+
+        self.rest = rest;
+        match number.replace('_', "").parse() {
+            Ok(value) => Ok(value),
+            Err(_) if number.is_empty() => Err("Expected a number".to_string()),
+            Err(_) => Err(format!("Not a number: {number}")),
+        }
+    }
+}
+
+/// The value of an arithmetic expression such as `12*14`, `(1.5 + 2) ^ 2` or
+/// `-7 % 3`. `_` may group digits.
+///
+/// # Errors
+///
+/// Returns why the expression can't be evaluated: a syntax error, nesting too
+/// deep, division by zero or a result that isn't finite.
+pub fn evaluate(expression: &str) -> Result<f64, String> {
+    let mut parser = Parser {
+        rest: expression,
+        depth: 0,
+    };
+    let value = parser.sum()?;
+
+    parser.skip_space();
+    if !parser.rest.is_empty() {
+        return Err(format!("Unexpected {}", parser.rest));
+    }
+    if !value.is_finite() {
+        return Err("The result is too large".to_string());
+    }
+    Ok(value)
+}
+
+/// `value` without a fractional part when it's whole, otherwise rounded to at
+/// most 10 decimals with trailing zeros dropped.
+pub fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        return format!("{}", value as i64);
+    }
+
+    let text = format!("{value:.10}");
+
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// The longest arithmetic [`result_for`] works out.
+pub const MAX_EXPRESSION: usize = 1000;
+
+/// The expression `before`, text ending in `=`, asks to be worked out, e.g.
+/// `168` for `Lunch: 12*14=`. Only the arithmetic right before the `=` is taken,
+/// and it needs an operator, so a lone number such as `x = 3=` doesn't count.
+/// Nor does arithmetic longer than [`MAX_EXPRESSION`] characters.
+pub fn result_for(before: &str) -> Option<String> {
+    let expression = before.strip_suffix('=')?.trim_end();
+    let run: Vec<usize> = expression
+        .char_indices()
+        .rev()
+        .take_while(|&(_, c)| c.is_ascii_digit() || " .+-*/%^()×÷_".contains(c))
+        .take(MAX_EXPRESSION + 1)
+        .map(|(at, _)| at)
+        .collect();
+
+    if run.len() > MAX_EXPRESSION {
+        return None;
+    }
+
+    let start = *run.last()?;
+    let expression = expression[start..].trim();
+    let has_operator = expression
+        .trim_start_matches(['-', '+', '('])
+        .contains(['+', '-', '*', '/', '%', '^', '×', '÷']);
+
+    if !has_operator || !expression.starts_with(|c: char| c.is_ascii_digit() || "-+(".contains(c)) {
+        return None;
+    }
+    evaluate(expression).ok().map(format_number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_evaluate() {
+        assert_eq!(evaluate("12*14"), Ok(168.0));
+        assert_eq!(evaluate("1 + 2 * 3 - 4 / 2"), Ok(5.0));
+        assert_eq!(evaluate("(1.5 + 2) ^ 2"), Ok(12.25));
+        assert_eq!(evaluate("2 ^ 3 ^ 2"), Ok(512.0));
+        assert_eq!(evaluate("-7 % 3"), Ok(-1.0));
+        assert_eq!(evaluate("1_000 × 3"), Ok(3000.0));
+        assert!(evaluate("1 / 0").is_err());
+        assert!(evaluate("2 +").is_err());
+        assert!(evaluate("(1").is_err());
+        assert_eq!(
+            evaluate(&format!("{}1", "(".repeat(100_000))),
+            Err("Nested too deeply".to_string())
+        );
+        assert!(evaluate(&format!("{}1", "-".repeat(100_000))).is_err());
+        assert!(evaluate(&"2^".repeat(100_000)).is_err());
+
+        assert_eq!(format_number(168.0), "168");
+        assert_eq!(format_number(1.0 / 3.0), "0.3333333333");
+
+        assert_eq!(result_for("Lunch: 12*14="), Some("168".into()));
+        assert_eq!(result_for("total (3 + 4) * 2 ="), Some("14".into()));
+        assert_eq!(result_for("x = 3="), None);
+        assert_eq!(result_for("just 42="), None);
+        assert_eq!(result_for("no equals"), None);
+        assert_eq!(result_for(&format!("{}=", "1+".repeat(1000) + "1")), None);
+    }
+}
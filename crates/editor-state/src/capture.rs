@@ -0,0 +1,134 @@
+/// Where the inbox choice is kept: `inbox` next to the session store.
+pub fn config_path() -> Option<std::path::PathBuf> {
+    Some(crate::session::config_dir()?.join("inbox"))
+}
+
+/// The note quick captures go to: the one chosen last, or `Inbox.md` in `~/Notes`.
+pub fn inbox() -> Option<std::path::PathBuf> {
+    let chosen = config_path()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .map(|content| content.trim().to_string())
+        .filter(|path| !path.is_empty());
+
+    match chosen {
+        Some(path) => Some(std::path::PathBuf::from(path)),
+        None => {
+            let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))?;
+            Some(std::path::Path::new(&home).join("Notes").join("Inbox.md"))
+        }
+    }
+}
+
+/// Makes `inbox` the note quick captures go to from now on.
+pub fn set_inbox(inbox: &std::path::Path) -> std::io::Result<()> {
+    let path = config_path()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "No config directory"))?;
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, format!("{}\n", inbox.display()))
+}
+
+/// `YYYY-MM-DD` for the day `now` falls on, in UTC.
+pub fn date(now: std::time::SystemTime) -> String {
+    crate::reminders::Date::from_time(now).to_string()
+}
+
+/// The note for the day `now` falls on: `YYYY-MM-DD.md` next to the inbox.
+pub fn daily_note(now: std::time::SystemTime) -> Option<std::path::PathBuf> {
+    let inbox = inbox()?;
+
+    Some(
+        inbox
+            .parent()
+            .unwrap_or(std::path::Path::new("."))
+            .join(format!("{}.md", date(now))),
+    )
+}
+
+fn io_error(err: editor_core::errors::TextBufferError) -> std::io::Error {
+    match err {
+        editor_core::errors::TextBufferError::IoError(err) => err,
+        other => std::io::Error::other(format!("{other:?}")),
+    }
+}
+
+/// Appends `text` to the end of `inbox` as a paragraph of its own, creating the note
+/// (and its folder) if needed. Goes through a [`crate::document::Document`] without
+/// any view, so the note's line endings and save cleanup apply as in the editor.
+pub fn append(inbox: &std::path::Path, text: &str) -> std::io::Result<()> {
+    let text = text.trim();
+
+    if text.is_empty() {
+        return Ok(());
+    }
+
+    let mut doc =
+        crate::document::Document::new(editor_core::text::TextBuffer::new().map_err(io_error)?);
+    let exists = inbox.exists();
+
+    if exists {
+        doc.open_file(inbox).map_err(io_error)?;
+    } else if let Some(dir) = inbox.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+
+    let content = doc.text_buffer.to_string();
+    let last_row = doc.get_line_count().saturating_sub(1);
+    let last_len = doc.get_line_stripped(last_row).map_or(0, |line| line.len());
+
+    // Right after the last character before the final line ending, if any, which
+    // then ends the capture instead.
+    doc.cursor = editor_core::cursor::Cursor::new(last_row, last_len);
+
+    // A blank line between captures.
+    if !content.trim().is_empty() {
+        doc.insert_newline();
+        doc.insert_newline();
+    }
+    for (i, line) in text.lines().enumerate() {
+        if i > 0 {
+            doc.insert_newline();
+        }
+        doc.insert(line);
+    }
+    if !content.ends_with('\n') {
+        doc.insert_newline();
+    }
+
+    if exists {
+        doc.save()
+    } else {
+        doc.save_as(inbox)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_append_creates_and_extends_the_inbox() {
+        let dir = tempfile::tempdir().unwrap();
+        let inbox = dir.path().join("notes").join("Inbox.md");
+
+        append(&inbox, "  buy milk\n").unwrap();
+        append(&inbox, "call Bob\nabout the trip").unwrap();
+        append(&inbox, "   ").unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&inbox).unwrap(),
+            "buy milk\n\ncall Bob\nabout the trip\n"
+        );
+    }
+
+    #[test]
+    fn test_date() {
+        let at = |secs| std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs);
+
+        assert_eq!(date(at(0)), "1970-01-01");
+        assert_eq!(date(at(951_782_400)), "2000-02-29");
+        assert_eq!(date(at(1_700_000_000)), "2023-11-14");
+    }
+}
@@ -0,0 +1,70 @@
+/// How the caret is drawn at the cursor.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CaretShape {
+    /// A line before the character.
+    #[default]
+    Bar,
+    /// A box over the character.
+    Block,
+    /// A line under the character.
+    Underline,
+}
+
+impl CaretShape {
+    pub const ALL: [Self; 3] = [Self::Bar, Self::Block, Self::Underline];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Bar => "Bar",
+            Self::Block => "Block",
+            Self::Underline => "Underline",
+        }
+    }
+
+    /// The shape called `name`, in any case.
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|shape| shape.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// The caret's look and blinking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Caret {
+    pub shape: CaretShape,
+    /// Thickness of a bar or underline in pixels.
+    pub width: u8,
+    /// `None` draws it in the theme's text color.
+    pub color: Option<crate::theme::Rgb>,
+    /// Time it stays on, then off. `None` keeps it on.
+    pub blink: Option<std::time::Duration>,
+}
+
+impl Caret {
+    /// Blinking only starts once the cursor has rested this long.
+    pub const BLINK_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+}
+
+impl Default for Caret {
+    fn default() -> Self {
+        Self {
+            shape: CaretShape::Bar,
+            width: 2,
+            color: None,
+            blink: Some(std::time::Duration::from_millis(500)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_shape() {
+        assert_eq!(CaretShape::parse("block"), Some(CaretShape::Block));
+        assert_eq!(CaretShape::parse("UNDERLINE"), Some(CaretShape::Underline));
+        assert_eq!(CaretShape::parse("beam"), None);
+    }
+}
@@ -0,0 +1,103 @@
+/// How a line differs from the note as it was opened or last saved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineChange {
+    /// The line wasn't there.
+    Added,
+    /// The line was there, with other contents.
+    Modified,
+}
+
+/// Lines changed since the note was opened or last saved, kept in step with edits
+/// line range by line range. Lines count as changed once edited, even if they were
+/// later typed back to what they were.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChangedLines {
+    /// One entry per line of the document.
+    marks: Vec<Option<LineChange>>,
+}
+
+impl ChangedLines {
+    /// No changes in a document of `line_count` lines.
+    pub fn new(line_count: usize) -> Self {
+        Self {
+            marks: vec![None; line_count],
+        }
+    }
+
+    /// Forgets every change, e.g. once the document is saved.
+    pub fn reset(&mut self, line_count: usize) {
+        *self = Self::new(line_count);
+    }
+
+    /// Rows `start..=old_end` were replaced by `start..=new_end`. Rows taking the
+    /// place of old ones are modified, extra ones added.
+    pub fn update(&mut self, start: usize, old_end: usize, new_end: usize) {
+        let old_end = (old_end + 1).min(self.marks.len());
+        let start = start.min(old_end);
+        let old: Vec<Option<LineChange>> = self.marks.drain(start..old_end).collect();
+        let new = (0..=new_end.saturating_sub(start)).map(|i| match old.get(i) {
+            Some(mark) => mark.or(Some(LineChange::Modified)),
+            None => Some(LineChange::Added),
+        });
+
+        self.marks.splice(start..start, new);
+    }
+
+    #[inline]
+    pub fn get(&self, row: usize) -> Option<LineChange> {
+        self.marks.get(row).copied().flatten()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.marks.iter().all(Option::is_none)
+    }
+
+    /// First row of the next block of changed lines below `row`, wrapping around to
+    /// the top.
+    pub fn next(&self, row: usize) -> Option<usize> {
+        let starts: Vec<usize> = (0..self.marks.len())
+            .filter(|&i| self.marks[i].is_some() && (i == 0 || self.marks[i - 1].is_none()))
+            .collect();
+
+        starts
+            .iter()
+            .find(|&&start| start > row)
+            .or(starts.first())
+            .copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changes_follow_edits() {
+        let mut changes = ChangedLines::new(5);
+
+        // Typed on row 1.
+        changes.update(1, 1, 1);
+        // Enter on row 3 splits it in two.
+        changes.update(3, 3, 4);
+        // Row 0 and the modified row 1 are joined.
+        changes.update(0, 1, 0);
+
+        assert_eq!(
+            (0..6).map(|row| changes.get(row)).collect::<Vec<_>>(),
+            vec![
+                Some(LineChange::Modified),
+                None,
+                Some(LineChange::Modified),
+                Some(LineChange::Added),
+                None,
+                None,
+            ]
+        );
+        assert_eq!(changes.next(0), Some(2));
+        assert_eq!(changes.next(2), Some(0));
+
+        changes.reset(5);
+        assert!(changes.is_empty());
+        assert_eq!(changes.next(0), None);
+    }
+}
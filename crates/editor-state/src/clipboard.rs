@@ -0,0 +1,952 @@
+/// The flavor of text we believe the OS clipboard handed us.
+///
+/// FLTK only ever asks for plain text, but browsers and word processors on
+/// several platforms still put their markup into that flavor, so we sniff it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClipboardFormat {
+    PlainText,
+    Html,
+    Rtf,
+}
+
+/// What pasted rich text should be turned into before it reaches the buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PasteConversion {
+    #[default]
+    Markdown,
+    PlainText,
+    /// Paste the markup exactly as received.
+    Off,
+}
+
+/// Elements whose closing tag is a strong hint that we are looking at HTML
+/// and not, say, a Rust generic or an XML snippet.
+const HTML_HINT_TAGS: &[&str] = &[
+    "p",
+    "div",
+    "span",
+    "b",
+    "strong",
+    "i",
+    "em",
+    "a",
+    "li",
+    "ul",
+    "ol",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "table",
+    "tr",
+    "td",
+    "pre",
+    "code",
+    "body",
+    "html",
+    "blockquote",
+];
+
+/// Sniffs the pasted text and reports which format it most likely is.
+pub fn detect_format(text: &str) -> ClipboardFormat {
+    let t = text.trim_start();
+
+    if t.starts_with("{\\rtf") {
+        return ClipboardFormat::Rtf;
+    }
+
+    // Windows "HTML Format" payloads carry a plain-text header before the markup.
+    if t.starts_with("Version:") && t.contains("StartHTML:") {
+        return ClipboardFormat::Html;
+    }
+
+    if !t.starts_with('<') {
+        return ClipboardFormat::PlainText;
+    }
+
+    let lower = t.to_ascii_lowercase();
+
+    if lower.starts_with("<!doctype html")
+        || lower.starts_with("<html")
+        || lower.starts_with("<meta")
+        || lower.starts_with("<!--startfragment")
+    {
+        return ClipboardFormat::Html;
+    }
+
+    if HTML_HINT_TAGS
+        .iter()
+        .any(|tag| lower.contains(&format!("</{tag}>")))
+    {
+        return ClipboardFormat::Html;
+    }
+
+    ClipboardFormat::PlainText
+}
+
+/// Negotiates the clipboard payload into text that is safe to insert.
+/// Plain text is returned untouched; HTML and RTF are converted per `conversion`.
+pub fn sanitize(text: &str, conversion: PasteConversion) -> String {
+    if conversion == PasteConversion::Off {
+        return text.to_string();
+    }
+
+    match detect_format(text) {
+        ClipboardFormat::PlainText => text.to_string(),
+        ClipboardFormat::Html => {
+            let fragment = extract_html_fragment(text);
+
+            HtmlConverter::new(conversion == PasteConversion::Markdown).convert(fragment)
+        }
+        ClipboardFormat::Rtf => rtf_to_plain_text(text),
+    }
+}
+
+/// The visible text of `html`, as [`sanitize`] converts it for
+/// [`PasteConversion::PlainText`], whether or not it looks like HTML on its own.
+pub fn html_to_plain_text(html: &str) -> String {
+    HtmlConverter::new(false).convert(extract_html_fragment(html))
+}
+
+/// The URL the pasted `text` is, if it's nothing but one: a `scheme://` address or
+/// a `mailto:` link, surrounding whitespace aside.
+pub fn as_url(text: &str) -> Option<&str> {
+    let url = text.trim();
+    let (scheme, rest) = url.split_once("://").or_else(|| {
+        url.split_once(':')
+            .filter(|(scheme, _)| *scheme == "mailto")
+    })?;
+    let valid_scheme = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+
+    (valid_scheme && !rest.is_empty() && !url.contains(char::is_whitespace)).then_some(url)
+}
+
+/// A Markdown link to `url` reading `label`.
+pub fn markdown_link(label: &str, url: &str) -> String {
+    let label = label.replace('[', "\\[").replace(']', "\\]");
+
+    // Angle brackets keep a URL with parentheses in it whole.
+    if url.contains(['(', ')']) {
+        format!("[{label}](<{url}>)")
+    } else {
+        format!("[{label}]({url})")
+    }
+}
+
+/// Strips the Windows CF_HTML header and anything outside the copied fragment.
+fn extract_html_fragment(text: &str) -> &str {
+    const START: &str = "<!--StartFragment-->";
+    const END: &str = "<!--EndFragment-->";
+
+    if let Some(start) = text.find(START) {
+        let body = &text[start + START.len()..];
+
+        return match body.find(END) {
+            Some(end) => &body[..end],
+            None => body,
+        };
+    }
+
+    match text.find('<') {
+        Some(idx) => &text[idx..],
+        None => text,
+    }
+}
+
+/*
+
+================================
+===== HTML -> MARKDOWN/TEXT =====
+================================
+
+*/
+
+#[derive(Debug)]
+enum HtmlToken<'a> {
+    Text(&'a str),
+    Start { name: String, attrs: &'a str },
+    End { name: String },
+}
+
+/// Splits HTML into a flat stream of text runs and tags.
+/// Comments, doctypes and processing instructions are dropped.
+fn tokenize_html(html: &str) -> Vec<HtmlToken<'_>> {
+    let mut tokens = Vec::new();
+    let bytes = html.as_bytes();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] != b'<' {
+            let end = find_tag_start(bytes, i);
+            tokens.push(HtmlToken::Text(&html[i..end]));
+            i = end;
+            continue;
+        }
+
+        let rest = &html[i..];
+
+        if rest.starts_with("<!--") {
+            i += rest.find("-->").map_or(rest.len(), |idx| idx + 3);
+            continue;
+        }
+
+        let Some(close) = rest.find('>') else {
+            // Unterminated tag, treat the remainder as literal text.
+            tokens.push(HtmlToken::Text(rest));
+            break;
+        };
+
+        let inner = &rest[1..close];
+        i += close + 1;
+
+        if inner.starts_with('!') || inner.starts_with('?') {
+            continue;
+        }
+
+        let (is_end, inner) = match inner.strip_prefix('/') {
+            Some(stripped) => (true, stripped),
+            None => (false, inner),
+        };
+        let inner = inner.trim_end_matches('/');
+        let name_end = inner
+            .find(|c: char| c.is_ascii_whitespace())
+            .unwrap_or(inner.len());
+        let name = inner[..name_end].to_ascii_lowercase();
+
+        if name.is_empty() {
+            continue;
+        }
+
+        if is_end {
+            tokens.push(HtmlToken::End { name });
+        } else {
+            tokens.push(HtmlToken::Start {
+                name,
+                attrs: &inner[name_end..],
+            });
+        }
+    }
+
+    tokens
+}
+
+#[inline]
+fn find_tag_start(bytes: &[u8], from: usize) -> usize {
+    bytes[from..]
+        .iter()
+        .position(|&b| b == b'<')
+        .map_or(bytes.len(), |idx| from + idx)
+}
+
+/// Reads a single attribute value out of a raw attribute string.
+fn html_attr(attrs: &str, key: &str) -> Option<String> {
+    let lower = attrs.to_ascii_lowercase();
+    let mut search_from = 0;
+
+    while let Some(found) = lower[search_from..].find(key) {
+        let idx = search_from + found;
+        let before_ok = idx == 0 || lower.as_bytes()[idx - 1].is_ascii_whitespace();
+        let after = lower[idx + key.len()..].trim_start();
+
+        if before_ok && after.starts_with('=') {
+            let value_start = attrs.len() - after.len() + 1;
+            let value = attrs[value_start..].trim_start();
+
+            let raw = match value.chars().next() {
+                Some(q @ ('"' | '\'')) => value[1..].split(q).next().unwrap_or(""),
+                _ => value
+                    .split(|c: char| c.is_ascii_whitespace())
+                    .next()
+                    .unwrap_or(""),
+            };
+
+            return Some(decode_entities(raw));
+        }
+
+        search_from = idx + key.len();
+    }
+
+    None
+}
+
+/// Decodes the handful of named entities browsers actually emit, plus numeric ones.
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+
+        let decoded = rest.find(';').filter(|&semi| semi <= 10).and_then(|semi| {
+            let entity = &rest[1..semi];
+            let ch = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                    .and_then(char::from_u32),
+            };
+
+            ch.map(|c| (c, semi))
+        });
+
+        match decoded {
+            Some((c, semi)) => {
+                out.push(c);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+#[derive(Debug)]
+enum ListKind {
+    Unordered,
+    Ordered(usize),
+}
+
+/// Single-pass converter that walks the token stream and writes either
+/// Markdown or plain text into one output buffer.
+struct HtmlConverter {
+    markdown: bool,
+    out: String,
+    lists: Vec<ListKind>,
+    links: Vec<Option<String>>,
+    quote_depth: usize,
+    in_pre: bool,
+    /// Depth inside elements whose content should never be pasted (script, style...).
+    skip_depth: usize,
+    row_has_header: bool,
+    header_separator_done: bool,
+    row_cells: usize,
+}
+
+impl HtmlConverter {
+    fn new(markdown: bool) -> Self {
+        Self {
+            markdown,
+            out: String::new(),
+            lists: Vec::new(),
+            links: Vec::new(),
+            quote_depth: 0,
+            in_pre: false,
+            skip_depth: 0,
+            row_has_header: false,
+            header_separator_done: false,
+            row_cells: 0,
+        }
+    }
+
+    fn convert(mut self, html: &str) -> String {
+        for token in tokenize_html(html) {
+            match token {
+                HtmlToken::Text(text) if self.skip_depth == 0 => self.text(text),
+                HtmlToken::Text(_) => {}
+                HtmlToken::Start { name, attrs } => self.start_tag(&name, attrs),
+                HtmlToken::End { name } => self.end_tag(&name),
+            }
+        }
+
+        Self::finish(self.out)
+    }
+
+    /// Trims the edges and collapses runs of blank lines left by nested blocks.
+    fn finish(out: String) -> String {
+        let mut result = String::with_capacity(out.len());
+        let mut blank_run = 0;
+
+        for line in out.trim().lines() {
+            let line = line.trim_end();
+
+            if line.is_empty() {
+                blank_run += 1;
+                if blank_run > 1 {
+                    continue;
+                }
+            } else {
+                blank_run = 0;
+            }
+
+            result.push_str(line);
+            result.push('\n');
+        }
+
+        result.truncate(result.trim_end().len());
+        result
+    }
+
+    fn at_line_start(&self) -> bool {
+        self.out.is_empty() || self.out.ends_with('\n')
+    }
+
+    fn line_prefix(&self) -> String {
+        if self.markdown {
+            "> ".repeat(self.quote_depth)
+        } else {
+            String::new()
+        }
+    }
+
+    /// Writes raw output, inserting the blockquote prefix at the start of lines.
+    fn write(&mut self, s: &str) {
+        if s.is_empty() {
+            return;
+        }
+
+        if self.at_line_start() {
+            let prefix = self.line_prefix();
+            self.out.push_str(&prefix);
+        }
+
+        self.out.push_str(s);
+    }
+
+    /// Makes sure the output ends with at least `n` line breaks (no-op at the very start).
+    fn ensure_newlines(&mut self, n: usize) {
+        if self.out.is_empty() {
+            return;
+        }
+
+        let trimmed_len = self.out.trim_end_matches([' ', '\t']).len();
+        self.out.truncate(trimmed_len);
+
+        // Count trailing line breaks, treating quote-marker-only lines as blank.
+        let mut existing = 0;
+        let mut rest = self.out.as_str();
+
+        while let Some(stripped) = rest.strip_suffix('\n') {
+            existing += 1;
+            let line_start = stripped.rfind('\n').map_or(0, |idx| idx + 1);
+
+            if line_start == 0 || !stripped[line_start..].chars().all(|c| c == '>') {
+                break;
+            }
+            rest = &stripped[..line_start];
+        }
+
+        for i in existing..n {
+            // Blank separator lines inside a quote still need the marker to stay in the quote.
+            if i > 0 && self.quote_depth > 0 && self.markdown {
+                self.out.push_str(self.line_prefix().trim_end());
+            }
+            self.out.push('\n');
+        }
+    }
+
+    fn text(&mut self, raw: &str) {
+        let decoded = decode_entities(raw);
+
+        if self.in_pre {
+            for (i, line) in decoded.split('\n').enumerate() {
+                if i > 0 {
+                    self.out.push('\n');
+                }
+                self.write(line);
+            }
+            return;
+        }
+
+        let mut collapsed = String::with_capacity(decoded.len());
+        let mut last_space = self.at_line_start() || self.out.ends_with(' ');
+
+        for c in decoded.chars() {
+            if c.is_whitespace() {
+                if !last_space {
+                    collapsed.push(' ');
+                    last_space = true;
+                }
+            } else {
+                collapsed.push(c);
+                last_space = false;
+            }
+        }
+
+        self.write(&collapsed);
+    }
+
+    fn start_tag(&mut self, name: &str, attrs: &str) {
+        match name {
+            "script" | "style" | "head" | "title" | "noscript" => {
+                self.skip_depth += 1;
+            }
+            _ if self.skip_depth > 0 => {}
+            "p" | "div" | "section" | "article" | "header" | "footer" => {
+                self.ensure_newlines(if name == "p" { 2 } else { 1 });
+            }
+            "br" => {
+                self.out.push('\n');
+            }
+            "hr" => {
+                self.ensure_newlines(2);
+                if self.markdown {
+                    self.write("---");
+                }
+                self.ensure_newlines(2);
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                self.ensure_newlines(2);
+                if self.markdown {
+                    let level = usize::from(name.as_bytes()[1] - b'0');
+                    self.write(&format!("{} ", "#".repeat(level)));
+                }
+            }
+            "strong" | "b" if self.markdown => self.write("**"),
+            "em" | "i" if self.markdown => self.write("*"),
+            "del" | "s" | "strike" if self.markdown => self.write("~~"),
+            "code" if self.markdown && !self.in_pre => self.write("`"),
+            "pre" => {
+                self.ensure_newlines(2);
+                if self.markdown {
+                    self.write("```");
+                    self.out.push('\n');
+                }
+                self.in_pre = true;
+            }
+            "blockquote" => {
+                self.ensure_newlines(2);
+                self.quote_depth += 1;
+            }
+            "ul" => {
+                self.ensure_newlines(if self.lists.is_empty() { 2 } else { 1 });
+                self.lists.push(ListKind::Unordered);
+            }
+            "ol" => {
+                self.ensure_newlines(if self.lists.is_empty() { 2 } else { 1 });
+                let start = html_attr(attrs, "start")
+                    .and_then(|s| s.trim().parse().ok())
+                    .unwrap_or(1);
+                self.lists.push(ListKind::Ordered(start));
+            }
+            "li" => {
+                self.ensure_newlines(1);
+                let indent = "  ".repeat(self.lists.len().saturating_sub(1));
+                let marker = match self.lists.last_mut() {
+                    Some(ListKind::Ordered(n)) => {
+                        let marker = format!("{n}. ");
+                        *n += 1;
+                        marker
+                    }
+                    _ if self.markdown => "- ".to_string(),
+                    _ => "• ".to_string(),
+                };
+                self.write(&format!("{indent}{marker}"));
+            }
+            "a" => {
+                let href = html_attr(attrs, "href").filter(|h| !h.is_empty());
+                if self.markdown && href.is_some() {
+                    self.write("[");
+                }
+                self.links.push(href);
+            }
+            "img" => {
+                let alt = html_attr(attrs, "alt").unwrap_or_default();
+                match html_attr(attrs, "src") {
+                    Some(src) if self.markdown => self.write(&format!("![{alt}]({src})")),
+                    _ => self.write(&alt),
+                }
+            }
+            "tr" => {
+                self.ensure_newlines(1);
+                self.row_has_header = false;
+                self.row_cells = 0;
+                if self.markdown {
+                    self.write("|");
+                }
+            }
+            "td" | "th" => {
+                self.row_has_header |= name == "th";
+                self.row_cells += 1;
+                if self.markdown {
+                    self.write(" ");
+                } else if self.row_cells > 1 {
+                    self.write("\t");
+                }
+            }
+            "table" => {
+                self.ensure_newlines(2);
+                self.header_separator_done = false;
+            }
+            _ => {}
+        }
+    }
+
+    fn end_tag(&mut self, name: &str) {
+        match name {
+            "script" | "style" | "head" | "title" | "noscript" => {
+                self.skip_depth = self.skip_depth.saturating_sub(1);
+            }
+            _ if self.skip_depth > 0 => {}
+            "p" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => self.ensure_newlines(2),
+            "div" | "section" | "article" | "header" | "footer" | "li" => self.ensure_newlines(1),
+            "strong" | "b" if self.markdown => self.write("**"),
+            "em" | "i" if self.markdown => self.write("*"),
+            "del" | "s" | "strike" if self.markdown => self.write("~~"),
+            "code" if self.markdown && !self.in_pre => self.write("`"),
+            "pre" => {
+                self.in_pre = false;
+                self.ensure_newlines(1);
+                if self.markdown {
+                    self.write("```");
+                }
+                self.ensure_newlines(2);
+            }
+            "blockquote" => {
+                self.ensure_newlines(1);
+
+                // Drop the dangling marker-only separator left by the last paragraph.
+                while let Some(stripped) = self.out.strip_suffix('\n') {
+                    let line_start = stripped.rfind('\n').map_or(0, |idx| idx + 1);
+                    let line = &stripped[line_start..];
+
+                    if line.is_empty() || !line.chars().all(|c| c == '>') {
+                        break;
+                    }
+                    self.out.truncate(line_start);
+                }
+
+                self.quote_depth = self.quote_depth.saturating_sub(1);
+                self.ensure_newlines(2);
+            }
+            "ul" | "ol" => {
+                self.lists.pop();
+                self.ensure_newlines(if self.lists.is_empty() { 2 } else { 1 });
+            }
+            "a" => {
+                if let Some(Some(href)) = self.links.pop()
+                    && self.markdown
+                {
+                    self.write(&format!("]({href})"));
+                }
+            }
+            "td" | "th" if self.markdown => self.write(" |"),
+            "tr" => {
+                if self.markdown && self.row_has_header && !self.header_separator_done {
+                    self.ensure_newlines(1);
+                    self.write(&format!("|{}", " --- |".repeat(self.row_cells)));
+                    self.header_separator_done = true;
+                }
+                self.ensure_newlines(1);
+            }
+            "table" => self.ensure_newlines(2),
+            _ => {}
+        }
+    }
+}
+
+/*
+
+===========================
+===== RTF -> PLAINTEXT =====
+===========================
+
+*/
+
+/// Destinations whose contents are metadata and never part of the visible text.
+const RTF_SKIPPED_DESTINATIONS: &[&str] = &[
+    "fonttbl",
+    "colortbl",
+    "stylesheet",
+    "info",
+    "pict",
+    "header",
+    "footer",
+    "generator",
+    "listtable",
+    "listoverridetable",
+    "rsidtbl",
+    "themedata",
+    "colorschememapping",
+    "latentstyles",
+    "datastore",
+    "xmlnstbl",
+    "filetbl",
+];
+
+/// Extracts the visible text of an RTF document, dropping all formatting.
+pub fn rtf_to_plain_text(rtf: &str) -> String {
+    let chars: Vec<char> = rtf.chars().collect();
+    let mut out = String::new();
+    // Each entry says whether the group at that depth is being skipped.
+    let mut groups: Vec<bool> = vec![false];
+    // Number of fallback characters to drop after a `\uN` escape.
+    let mut unicode_skip = 1usize;
+    let mut pending_skip = 0usize;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let skipping = *groups.last().unwrap_or(&false);
+        let c = chars[i];
+
+        match c {
+            '{' => {
+                groups.push(skipping);
+                i += 1;
+            }
+            '}' => {
+                groups.pop();
+                if groups.is_empty() {
+                    break;
+                }
+                i += 1;
+            }
+            '\\' => {
+                i += 1;
+                let Some(&next) = chars.get(i) else {
+                    break;
+                };
+
+                match next {
+                    '\\' | '{' | '}' => {
+                        if !skipping {
+                            out.push(next);
+                        }
+                        i += 1;
+                    }
+                    '*' => {
+                        if let Some(top) = groups.last_mut() {
+                            *top = true;
+                        }
+                        i += 1;
+                    }
+                    '\'' => {
+                        let hex: String = chars.iter().skip(i + 1).take(2).collect();
+                        i += 1 + hex.len();
+                        if pending_skip > 0 {
+                            pending_skip -= 1;
+                        } else if !skipping && let Ok(byte) = u8::from_str_radix(&hex, 16) {
+                            out.push(char::from(byte));
+                        }
+                    }
+                    '~' => {
+                        if !skipping {
+                            out.push(' ');
+                        }
+                        i += 1;
+                    }
+                    '\r' | '\n' => {
+                        if !skipping {
+                            out.push('\n');
+                        }
+                        i += 1;
+                    }
+                    c if c.is_ascii_alphabetic() => {
+                        let word_start = i;
+                        while i < chars.len() && chars[i].is_ascii_alphabetic() {
+                            i += 1;
+                        }
+                        let word: String = chars[word_start..i].iter().collect();
+
+                        let param_start = i;
+                        if i < chars.len() && chars[i] == '-' {
+                            i += 1;
+                        }
+                        while i < chars.len() && chars[i].is_ascii_digit() {
+                            i += 1;
+                        }
+                        let param: Option<i32> = chars[param_start..i]
+                            .iter()
+                            .collect::<String>()
+                            .parse()
+                            .ok();
+
+                        // A single space delimits the control word and is not text.
+                        if i < chars.len() && chars[i] == ' ' {
+                            i += 1;
+                        }
+
+                        if RTF_SKIPPED_DESTINATIONS.contains(&word.as_str()) {
+                            if let Some(top) = groups.last_mut() {
+                                *top = true;
+                            }
+                            continue;
+                        }
+
+                        if skipping {
+                            continue;
+                        }
+
+                        match word.as_str() {
+                            "par" | "line" | "sect" | "page" => out.push('\n'),
+                            "tab" => out.push('\t'),
+                            "emdash" => out.push('\u{2014}'),
+                            "endash" => out.push('\u{2013}'),
+                            "bullet" => out.push('\u{2022}'),
+                            "lquote" => out.push('\u{2018}'),
+                            "rquote" => out.push('\u{2019}'),
+                            "ldblquote" => out.push('\u{201C}'),
+                            "rdblquote" => out.push('\u{201D}'),
+                            "uc" => unicode_skip = param.unwrap_or(1).max(0) as usize,
+                            "u" => {
+                                if let Some(code) = param {
+                                    let code = if code < 0 { code + 65536 } else { code };
+                                    if let Some(ch) = char::from_u32(code as u32) {
+                                        out.push(ch);
+                                    }
+                                    pending_skip = unicode_skip;
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                    _ => {
+                        i += 1;
+                    }
+                }
+            }
+            '\r' | '\n' => {
+                i += 1;
+            }
+            _ => {
+                if pending_skip > 0 {
+                    pending_skip -= 1;
+                } else if !skipping {
+                    out.push(c);
+                }
+                i += 1;
+            }
+        }
+    }
+
+    out.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_format() {
+        assert_eq!(detect_format("hello world"), ClipboardFormat::PlainText);
+        assert_eq!(detect_format("Vec<String>"), ClipboardFormat::PlainText);
+        assert_eq!(
+            detect_format("<T as Into<U>>::into"),
+            ClipboardFormat::PlainText
+        );
+        assert_eq!(detect_format("<p>Hi</p>"), ClipboardFormat::Html);
+        assert_eq!(
+            detect_format("<meta charset='utf-8'><span>Hi</span>"),
+            ClipboardFormat::Html
+        );
+        assert_eq!(detect_format("{\\rtf1\\ansi Hello}"), ClipboardFormat::Rtf);
+    }
+
+    #[test]
+    fn test_html_to_markdown_inline_and_blocks() {
+        let html = "<h2>Title</h2><p>Some <b>bold</b> and <em>italic</em> \
+                    with <a href=\"https://example.com\">a link</a>.</p><p>Use <code>cargo</code></p>";
+
+        assert_eq!(
+            sanitize(html, PasteConversion::Markdown),
+            "## Title\n\nSome **bold** and *italic* with [a link](https://example.com).\n\nUse `cargo`"
+        );
+    }
+
+    #[test]
+    fn test_html_to_markdown_lists() {
+        let html = "<ul><li>one</li><li>two<ol><li>a</li><li>b</li></ol></li></ul>";
+
+        assert_eq!(
+            sanitize(html, PasteConversion::Markdown),
+            "- one\n- two\n  1. a\n  2. b"
+        );
+    }
+
+    #[test]
+    fn test_html_pre_keeps_whitespace() {
+        let html = "<pre>fn main() {\n    println!(\"&lt;hi&gt;\");\n}</pre>";
+
+        assert_eq!(
+            sanitize(html, PasteConversion::Markdown),
+            "```\nfn main() {\n    println!(\"<hi>\");\n}\n```"
+        );
+    }
+
+    #[test]
+    fn test_html_to_plain_text_drops_markup() {
+        let html = "<html><head><style>p{}</style></head><body>\
+                    <p>Tom &amp; <b>Jerry</b></p><ul><li>x</li></ul></body></html>";
+
+        assert_eq!(
+            sanitize(html, PasteConversion::PlainText),
+            "Tom & Jerry\n\n• x"
+        );
+    }
+
+    #[test]
+    fn test_windows_cf_html_header_is_stripped() {
+        let payload = "Version:0.9\r\nStartHTML:0000000105\r\nEndHTML:0000000199\r\n\
+                       <html><body><!--StartFragment--><i>hey</i><!--EndFragment--></body></html>";
+
+        assert_eq!(sanitize(payload, PasteConversion::Markdown), "*hey*");
+    }
+
+    #[test]
+    fn test_blockquote_prefixes_every_line() {
+        let html = "<blockquote><p>first</p><p>second</p></blockquote>";
+
+        assert_eq!(
+            sanitize(html, PasteConversion::Markdown),
+            "> first\n>\n> second"
+        );
+    }
+
+    #[test]
+    fn test_rtf_to_plain_text() {
+        let rtf = "{\\rtf1\\ansi{\\fonttbl{\\f0 Helvetica;}}{\\colortbl;\\red0\\green0\\blue0;}\
+                   \\f0\\fs24 Hello \\b World\\b0\\par Caf\\'e9 \\{ok\\}\\tab end}";
+
+        assert_eq!(rtf_to_plain_text(rtf), "Hello World\nCaf\u{e9} {ok}\tend");
+    }
+
+    #[test]
+    fn test_url_links() {
+        assert_eq!(
+            as_url("  https://example.com/a?b=c\n"),
+            Some("https://example.com/a?b=c")
+        );
+        assert_eq!(
+            as_url("mailto:me@example.com"),
+            Some("mailto:me@example.com")
+        );
+        assert_eq!(as_url("https://example.com and more"), None);
+        assert_eq!(as_url("note: see below"), None);
+        assert_eq!(as_url("://nothing"), None);
+        assert_eq!(
+            markdown_link("see [1]", "https://en.wikipedia.org/wiki/Rust_(language)"),
+            "[see \\[1\\]](<https://en.wikipedia.org/wiki/Rust_(language)>)"
+        );
+    }
+
+    #[test]
+    fn test_conversion_off_and_plain_passthrough() {
+        assert_eq!(sanitize("<p>x</p>", PasteConversion::Off), "<p>x</p>");
+        assert_eq!(
+            sanitize("  plain\ttext\n", PasteConversion::Markdown),
+            "  plain\ttext\n"
+        );
+    }
+}
@@ -0,0 +1,764 @@
+/// Identifies one character for its whole life, across every replica. Ordered by
+/// Lamport clock, then by site, so concurrent inserts sort the same everywhere.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CharId {
+    pub clock: u64,
+    pub site: u32,
+}
+
+/// A change to the shared text, as exchanged between replicas.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op {
+    /// `text` goes right after the character `origin` (`None` for the start). Its
+    /// characters get consecutive clocks starting at `id`.
+    Insert {
+        id: CharId,
+        origin: Option<CharId>,
+        text: String,
+    },
+    Delete {
+        ids: Vec<CharId>,
+    },
+}
+
+/// `\`, tab and newline escaped so text fits in one tab-separated line.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+
+    out
+}
+
+impl CharId {
+    pub(crate) fn encode(self) -> String {
+        format!("{}:{}", self.clock, self.site)
+    }
+
+    pub(crate) fn decode(field: &str) -> Option<Self> {
+        let (clock, site) = field.split_once(':')?;
+
+        Some(Self {
+            clock: clock.parse().ok()?,
+            site: site.parse().ok()?,
+        })
+    }
+}
+
+impl Op {
+    /// One line of text, without the trailing newline:
+    /// `I <id> <origin or -> <text>` or `D <id>,<id>,...`, tab-separated.
+    pub fn encode(&self) -> String {
+        match self {
+            Op::Insert { id, origin, text } => format!(
+                "I\t{}\t{}\t{}",
+                id.encode(),
+                origin.map_or_else(|| "-".to_string(), CharId::encode),
+                escape(text)
+            ),
+            Op::Delete { ids } => {
+                let ids: Vec<String> = ids.iter().map(|id| id.encode()).collect();
+                format!("D\t{}", ids.join(","))
+            }
+        }
+    }
+
+    /// Parses a line written by [`Op::encode`].
+    pub fn decode(line: &str) -> Option<Self> {
+        let mut fields = line.split('\t');
+
+        match fields.next()? {
+            "I" => {
+                let id = CharId::decode(fields.next()?)?;
+                let origin = match fields.next()? {
+                    "-" => None,
+                    origin => Some(CharId::decode(origin)?),
+                };
+
+                Some(Op::Insert {
+                    id,
+                    origin,
+                    text: unescape(fields.next()?),
+                })
+            }
+            "D" => {
+                let ids = fields
+                    .next()?
+                    .split(',')
+                    .filter(|id| !id.is_empty())
+                    .map(CharId::decode)
+                    .collect::<Option<_>>()?;
+
+                Some(Op::Delete { ids })
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Element {
+    id: CharId,
+    ch: char,
+    deleted: bool,
+}
+
+/// One copy of a shared text, as a replicated growable array: every character ever
+/// inserted stays in the sequence, deletions only hide it, and concurrent inserts
+/// at the same spot are ordered by id. Replicas that have seen the same operations
+/// hold the same text, whatever order they arrived in.
+#[derive(Debug, Clone)]
+pub struct Replica {
+    site: u32,
+    clock: u64,
+    elements: Vec<Element>,
+    /// Where each character is in `elements`.
+    index: std::collections::HashMap<CharId, usize>,
+    /// Operations that arrived before the characters they refer to.
+    pending: Vec<Op>,
+}
+
+impl Replica {
+    /// How many operations are held back for characters that haven't arrived. Past
+    /// that, operations whose context may never come are dropped.
+    const MAX_PENDING: usize = 10_000;
+
+    fn with_elements(site: u32, elements: Vec<Element>) -> Self {
+        let index = elements
+            .iter()
+            .enumerate()
+            .map(|(i, element)| (element.id, i))
+            .collect();
+
+        Self {
+            site,
+            clock: elements
+                .iter()
+                .map(|element| element.id.clock)
+                .max()
+                .unwrap_or(0),
+            elements,
+            index,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Site 0 is reserved for the starting text, which every replica builds the same
+    /// way, so peers that opened the same note can start exchanging operations at once.
+    pub fn new(site: u32, text: &str) -> Self {
+        let elements: Vec<Element> = text
+            .chars()
+            .enumerate()
+            .map(|(i, ch)| Element {
+                id: CharId {
+                    clock: i as u64 + 1,
+                    site: 0,
+                },
+                ch,
+                deleted: false,
+            })
+            .collect();
+
+        Self::with_elements(site, elements)
+    }
+
+    #[inline]
+    pub fn site(&self) -> u32 {
+        self.site
+    }
+
+    pub fn text(&self) -> String {
+        self.visible().map(|element| element.ch).collect()
+    }
+
+    /// The whole sequence, deleted characters and ids included, as one line: the
+    /// `clock:site` ids and a `-` for each deleted one, comma-separated, then a tab and
+    /// every character. Lets a peer joining late start from exactly this state.
+    pub fn snapshot(&self) -> String {
+        let ids: Vec<String> = self
+            .elements
+            .iter()
+            .map(|element| {
+                let deleted = if element.deleted { "-" } else { "" };
+                format!("{}{deleted}", element.id.encode())
+            })
+            .collect();
+        let chars: String = self.elements.iter().map(|element| element.ch).collect();
+
+        format!("{}\t{}", ids.join(","), escape(&chars))
+    }
+
+    /// Rebuilds a replica for `site` from a [`Replica::snapshot`].
+    pub fn from_snapshot(site: u32, snapshot: &str) -> Option<Self> {
+        let (ids, chars) = snapshot.split_once('\t')?;
+        let chars = unescape(chars);
+        let ids: Vec<&str> = ids.split(',').filter(|id| !id.is_empty()).collect();
+
+        if ids.len() != chars.chars().count() {
+            return None;
+        }
+
+        let elements: Vec<Element> = ids
+            .into_iter()
+            .zip(chars.chars())
+            .map(|(id, ch)| {
+                let (id, deleted) = match id.strip_suffix('-') {
+                    Some(id) => (id, true),
+                    None => (id, false),
+                };
+
+                Some(Element {
+                    id: CharId::decode(id)?,
+                    ch,
+                    deleted,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let replica = Self::with_elements(site, elements);
+
+        // Every id must be unique for the index to find them.
+        (replica.index.len() == replica.elements.len()).then_some(replica)
+    }
+
+    /// Pins `pos` to the character right before it (`None` at the start), so it can be
+    /// sent to peers and stays put while the text around it changes.
+    pub fn anchor_at(&self, pos: editor_core::cursor::Position) -> Option<CharId> {
+        let offset = self.offset_of(pos);
+
+        offset
+            .checked_sub(1)
+            .map(|before| self.elements[self.element_at(before)].id)
+    }
+
+    /// Where an [`Replica::anchor_at`] anchor is now. An anchor whose character was
+    /// deleted sits where the character was; one not seen yet sits at the start.
+    pub fn resolve_anchor(&self, anchor: Option<CharId>) -> editor_core::cursor::Position {
+        let offset = anchor
+            .and_then(|anchor| self.index_of(anchor))
+            .map_or(0, |i| {
+                self.elements[..=i]
+                    .iter()
+                    .filter(|element| !element.deleted)
+                    .count()
+            });
+
+        self.position_of(offset)
+    }
+
+    fn visible(&self) -> impl Iterator<Item = &Element> {
+        self.elements.iter().filter(|element| !element.deleted)
+    }
+
+    #[inline]
+    fn index_of(&self, id: CharId) -> Option<usize> {
+        self.index.get(&id).copied()
+    }
+
+    /// Index into `elements` of the `n`th visible character, or `elements.len()`.
+    fn element_at(&self, n: usize) -> usize {
+        self.elements
+            .iter()
+            .enumerate()
+            .filter(|(_, element)| !element.deleted)
+            .nth(n)
+            .map_or(self.elements.len(), |(i, _)| i)
+    }
+
+    /// The visible character index of `pos`, whose column is a byte offset.
+    fn offset_of(&self, pos: editor_core::cursor::Position) -> usize {
+        let mut row = 0;
+        let mut col = 0;
+
+        for (i, element) in self.visible().enumerate() {
+            if row == pos.row && col >= pos.col {
+                return i;
+            }
+            if element.ch == '\n' {
+                if row == pos.row {
+                    return i;
+                }
+                row += 1;
+                col = 0;
+            } else {
+                col += element.ch.len_utf8();
+            }
+        }
+
+        self.visible().count()
+    }
+
+    /// The position of the visible character index `offset`.
+    fn position_of(&self, offset: usize) -> editor_core::cursor::Position {
+        let mut pos = editor_core::cursor::Position::new(0, 0);
+
+        for element in self.visible().take(offset) {
+            if element.ch == '\n' {
+                pos = editor_core::cursor::Position::new(pos.row + 1, 0);
+            } else {
+                pos.col += element.ch.len_utf8();
+            }
+        }
+
+        pos
+    }
+
+    /// Records a change made to the local document, returning the operation to send
+    /// to the other replicas. `None` if it changed nothing.
+    pub fn local(&mut self, action: &editor_core::enums::EditAction) -> Option<Op> {
+        match action {
+            editor_core::enums::EditAction::Insert { pos, text } => {
+                if text.is_empty() {
+                    return None;
+                }
+
+                let origin = self.anchor_at(*pos);
+                let op = Op::Insert {
+                    id: CharId {
+                        clock: self.clock + 1,
+                        site: self.site,
+                    },
+                    origin,
+                    text: text.clone(),
+                };
+
+                self.integrate(&op);
+                Some(op)
+            }
+            editor_core::enums::EditAction::Delete { pos, text, .. } => {
+                let start = self.offset_of(*pos);
+                let ids: Vec<CharId> = self
+                    .visible()
+                    .skip(start)
+                    .take(text.chars().count())
+                    .map(|element| element.id)
+                    .collect();
+
+                if ids.is_empty() {
+                    return None;
+                }
+
+                let op = Op::Delete { ids };
+
+                self.integrate(&op);
+                Some(op)
+            }
+        }
+    }
+
+    /// Applies an operation from another replica, returning the changes to make to
+    /// the local document, in order. Operations whose context hasn't arrived yet are
+    /// held back and applied once it has.
+    pub fn remote(&mut self, op: Op) -> Vec<editor_core::enums::EditAction> {
+        let mut actions = Vec::new();
+
+        if !Self::is_valid(&op) {
+            tracing::warn!("Dropped a malformed collaboration operation");
+            return actions;
+        }
+        if !self.is_ready(&op) {
+            if self.pending.len() < Self::MAX_PENDING {
+                self.pending.push(op);
+            } else {
+                tracing::warn!("Dropped a collaboration operation that can't be applied");
+            }
+            return actions;
+        }
+        actions.extend(self.integrate(&op));
+
+        // Applying one operation may unblock others that were waiting on it.
+        while let Some(ready) = self.pending.iter().position(|op| self.is_ready(op)) {
+            let op = self.pending.remove(ready);
+            actions.extend(self.integrate(&op));
+        }
+
+        actions
+    }
+
+    /// Whether `op` could have been made by a replica: it changes something, and its
+    /// characters' clocks all fit.
+    fn is_valid(op: &Op) -> bool {
+        match op {
+            Op::Insert { id, text, .. } => {
+                !text.is_empty() && id.clock.checked_add(text.chars().count() as u64).is_some()
+            }
+            Op::Delete { ids } => !ids.is_empty(),
+        }
+    }
+
+    fn is_ready(&self, op: &Op) -> bool {
+        match op {
+            Op::Insert { origin, .. } => {
+                origin.is_none_or(|origin| self.index_of(origin).is_some())
+            }
+            Op::Delete { ids } => ids.iter().all(|id| self.index_of(*id).is_some()),
+        }
+    }
+
+    /// Folds `op` into the sequence and returns what changed in the visible text.
+    fn integrate(&mut self, op: &Op) -> Vec<editor_core::enums::EditAction> {
+        match op {
+            Op::Insert { id, origin, text } => {
+                // Seen before: operations may be delivered more than once.
+                if self.index_of(*id).is_some() {
+                    return Vec::new();
+                }
+                // The ids of the other characters are taken, which no replica would do.
+                if (1..text.chars().count() as u64).any(|i| {
+                    self.index_of(CharId {
+                        clock: id.clock + i,
+                        site: id.site,
+                    })
+                    .is_some()
+                }) {
+                    tracing::warn!("Dropped a collaboration operation reusing character ids");
+                    return Vec::new();
+                }
+
+                let mut at = origin
+                    .and_then(|origin| self.index_of(origin))
+                    .map_or(0, |i| i + 1);
+
+                // Later inserts at the same spot go first; the ones after them all have
+                // even later clocks, so this skips whole subtrees.
+                while self
+                    .elements
+                    .get(at)
+                    .is_some_and(|element| element.id > *id)
+                {
+                    at += 1;
+                }
+
+                let offset = self.elements[..at]
+                    .iter()
+                    .filter(|element| !element.deleted)
+                    .count();
+                let pos = self.position_of(offset);
+                let count = text.chars().count();
+
+                self.elements.splice(
+                    at..at,
+                    text.chars().enumerate().map(|(i, ch)| Element {
+                        id: CharId {
+                            clock: id.clock + i as u64,
+                            site: id.site,
+                        },
+                        ch,
+                        deleted: false,
+                    }),
+                );
+                // The new characters and everything they pushed along.
+                for (i, element) in self.elements.iter().enumerate().skip(at) {
+                    self.index.insert(element.id, i);
+                }
+                self.clock = self.clock.max(id.clock + count as u64 - 1);
+
+                vec![editor_core::enums::EditAction::Insert {
+                    pos,
+                    text: text.clone(),
+                }]
+            }
+            Op::Delete { ids } => {
+                let mut indexes: Vec<usize> = ids
+                    .iter()
+                    .filter_map(|id| self.index_of(*id))
+                    .filter(|&i| !self.elements[i].deleted)
+                    .collect();
+                indexes.sort_unstable();
+                indexes.dedup();
+
+                // Where each character is, in one pass over the text up to the last.
+                let mut positions = Vec::with_capacity(indexes.len());
+                let mut pos = editor_core::cursor::Position::new(0, 0);
+
+                for (i, element) in self.elements.iter().enumerate() {
+                    if positions.len() == indexes.len() {
+                        break;
+                    }
+                    if indexes[positions.len()] == i {
+                        positions.push(pos);
+                    }
+                    if element.deleted {
+                        continue;
+                    }
+                    if element.ch == '\n' {
+                        pos = editor_core::cursor::Position::new(pos.row + 1, 0);
+                    } else {
+                        pos.col += element.ch.len_utf8();
+                    }
+                }
+
+                // Back to front, so every position is still valid when it's applied.
+                let mut actions = Vec::with_capacity(indexes.len());
+
+                for (i, pos) in indexes.into_iter().zip(positions).rev() {
+                    let ch = self.elements[i].ch;
+                    let end = if ch == '\n' {
+                        editor_core::cursor::Position::new(pos.row + 1, 0)
+                    } else {
+                        editor_core::cursor::Position::new(pos.row, pos.col + ch.len_utf8())
+                    };
+
+                    self.elements[i].deleted = true;
+                    actions.push(editor_core::enums::EditAction::Delete {
+                        pos,
+                        end,
+                        text: ch.to_string(),
+                    });
+                }
+
+                actions
+            }
+        }
+    }
+}
+
+/// Connects a [`crate::document::Document`] to a [`Replica`]: local edits come out
+/// as operations to send, and operations received go into the document.
+///
+/// Remote changes bypass the undo history, so undoing after a collaborator edited
+/// the same lines may not land where expected.
+#[derive(Debug)]
+pub struct CollabSession {
+    pub replica: Replica,
+    /// Operations made locally and not yet taken by [`Self::outgoing`].
+    unsent: Vec<Op>,
+}
+
+impl CollabSession {
+    /// Starts sharing `doc` as `site`, which must differ from every other peer's.
+    pub fn start(doc: &mut crate::document::Document, site: u32) -> Self {
+        doc.edit_log = Some(Vec::new());
+
+        Self {
+            replica: Replica::new(site, &doc.text_buffer.to_string()),
+            unsent: Vec::new(),
+        }
+    }
+
+    /// Joins a shared text as `site`, starting from `replica` (usually rebuilt from a
+    /// peer's [`Replica::snapshot`]): `doc` is replaced with its text first, outside
+    /// the undo history.
+    pub fn join(doc: &mut crate::document::Document, replica: Replica) -> Self {
+        let old = doc.text_buffer.to_string();
+        let last_line = old.rsplit('\n').next().unwrap_or_default();
+        let end = editor_core::cursor::Position::new(old.matches('\n').count(), last_line.len());
+
+        doc.edit_log = None;
+        doc.apply_remote(vec![
+            editor_core::enums::EditAction::Delete {
+                pos: editor_core::cursor::Position::new(0, 0),
+                end,
+                text: old,
+            },
+            editor_core::enums::EditAction::Insert {
+                pos: editor_core::cursor::Position::new(0, 0),
+                text: replica.text(),
+            },
+        ]);
+        doc.history = editor_core::history::History {
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+        };
+        doc.edit_log = Some(Vec::new());
+
+        Self {
+            replica,
+            unsent: Vec::new(),
+        }
+    }
+
+    /// Turns the document's logged edits into operations.
+    fn collect(&mut self, doc: &mut crate::document::Document) {
+        let Some(log) = &mut doc.edit_log else {
+            return;
+        };
+
+        for action in std::mem::take(log) {
+            self.unsent.extend(self.replica.local(&action));
+        }
+    }
+
+    /// The operations for everything edited locally since the last call.
+    pub fn outgoing(&mut self, doc: &mut crate::document::Document) -> Vec<Op> {
+        self.collect(doc);
+        std::mem::take(&mut self.unsent)
+    }
+
+    /// Applies an operation received from a peer to `doc`.
+    pub fn incoming(&mut self, doc: &mut crate::document::Document, op: Op) {
+        // Local edits must be folded in first: they're what the document's
+        // positions are relative to.
+        self.collect(doc);
+        doc.apply_remote(self.replica.remote(op));
+    }
+
+    /// Stops logging edits of `doc`.
+    pub fn stop(self, doc: &mut crate::document::Document) {
+        doc.edit_log = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::Document;
+    use editor_core::cursor::Cursor;
+    use editor_core::text::TextBuffer;
+
+    /// Delivers everything each side sent to the other, encoded as it would go over a
+    /// socket.
+    fn exchange(
+        (a, a_doc): (&mut CollabSession, &mut Document),
+        (b, b_doc): (&mut CollabSession, &mut Document),
+    ) {
+        let from_a: Vec<String> = a.outgoing(a_doc).iter().map(Op::encode).collect();
+        let from_b: Vec<String> = b.outgoing(b_doc).iter().map(Op::encode).collect();
+
+        for line in from_a {
+            b.incoming(b_doc, Op::decode(&line).unwrap());
+        }
+        for line in from_b {
+            a.incoming(a_doc, Op::decode(&line).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_op_encoding_round_trips() {
+        let ops = [
+            Op::Insert {
+                id: CharId { clock: 7, site: 2 },
+                origin: None,
+                text: "tab\there\nback\\slash".to_string(),
+            },
+            Op::Delete {
+                ids: vec![CharId { clock: 1, site: 0 }, CharId { clock: 9, site: 3 }],
+            },
+        ];
+
+        for op in ops {
+            assert_eq!(Op::decode(&op.encode()), Some(op));
+        }
+    }
+
+    #[test]
+    fn test_two_documents_converge() {
+        let text = "hello\nworld";
+        let mut a_doc = Document::new(TextBuffer::new_with_text(text).unwrap());
+        let mut b_doc = Document::new(TextBuffer::new_with_text(text).unwrap());
+        let mut a = CollabSession::start(&mut a_doc, 1);
+        let mut b = CollabSession::start(&mut b_doc, 2);
+
+        // Concurrent edits: both type at the same spot, and B deletes a word A is
+        // typing next to.
+        a_doc.cursor = Cursor::new(0, 5);
+        a_doc.insert(", there");
+        b_doc.cursor = Cursor::new(0, 5);
+        b_doc.insert("!");
+        b_doc.cursor = Cursor::new_selection(
+            editor_core::cursor::Position::new(1, 0),
+            editor_core::cursor::Position::new(1, 5),
+        );
+        b_doc.delete(true);
+        b_doc.insert("friends");
+
+        exchange((&mut a, &mut a_doc), (&mut b, &mut b_doc));
+
+        let merged = a_doc.text_buffer.to_string();
+        assert_eq!(merged, b_doc.text_buffer.to_string());
+        assert_eq!(merged, a.replica.text());
+        assert!(merged.contains(", there") && merged.contains('!'));
+        assert!(merged.ends_with("\nfriends"));
+
+        // Undo and multi-line edits travel too.
+        a_doc.undo();
+        b_doc.cursor = Cursor::new(1, 7);
+        b_doc.insert_newline();
+        b_doc.insert("- more");
+
+        exchange((&mut a, &mut a_doc), (&mut b, &mut b_doc));
+
+        assert_eq!(a_doc.text_buffer.to_string(), b_doc.text_buffer.to_string());
+        assert!(!a_doc.text_buffer.to_string().contains(", there"));
+        assert!(a_doc.text_buffer.to_string().ends_with("friends\n- more"));
+    }
+
+    #[test]
+    fn test_out_of_order_delivery() {
+        let mut a = Replica::new(1, "");
+        let mut b = Replica::new(2, "");
+        let first = a
+            .local(&editor_core::enums::EditAction::Insert {
+                pos: editor_core::cursor::Position::new(0, 0),
+                text: "ab".to_string(),
+            })
+            .unwrap();
+        let second = a
+            .local(&editor_core::enums::EditAction::Insert {
+                pos: editor_core::cursor::Position::new(0, 2),
+                text: "c".to_string(),
+            })
+            .unwrap();
+
+        assert!(b.remote(second).is_empty());
+        assert_eq!(b.remote(first).len(), 2);
+        assert_eq!(b.text(), "abc");
+    }
+
+    #[test]
+    fn test_malformed_ops_are_dropped() {
+        let mut replica = Replica::new(1, "ab");
+        let insert = |clock, text: &str| Op::Insert {
+            id: CharId { clock, site: 2 },
+            origin: None,
+            text: text.to_string(),
+        };
+
+        assert!(replica.remote(insert(0, "")).is_empty());
+        assert!(replica.remote(insert(u64::MAX, "xy")).is_empty());
+        assert!(replica.remote(Op::Delete { ids: Vec::new() }).is_empty());
+        assert!(replica.pending.is_empty());
+
+        // Operations waiting on characters that never come stop piling up.
+        for clock in 0..Replica::MAX_PENDING as u64 + 10 {
+            replica.remote(Op::Insert {
+                id: CharId { clock, site: 3 },
+                origin: Some(CharId { clock, site: 9 }),
+                text: "z".to_string(),
+            });
+        }
+        assert_eq!(replica.pending.len(), Replica::MAX_PENDING);
+
+        // Deleting both characters at once still lands back to front.
+        let actions = replica.remote(Op::Delete {
+            ids: vec![CharId { clock: 2, site: 0 }, CharId { clock: 1, site: 0 }],
+        });
+        assert_eq!(actions.len(), 2);
+        assert_eq!(replica.text(), "");
+
+        // An insert whose later characters reuse ids already taken.
+        assert_eq!(replica.remote(insert(10, "x")).len(), 1);
+        assert!(replica.remote(insert(9, "yz")).is_empty());
+        assert_eq!(replica.text(), "x");
+        assert!(Replica::from_snapshot(4, &replica.snapshot()).is_some());
+    }
+}
@@ -0,0 +1,143 @@
+/// How a file type spells a comment that covers a single line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentStyle {
+    /// A prefix such as `//` or `#`.
+    Line(&'static str),
+    /// A delimiter pair such as `<!--` and `-->`, wrapped around each line.
+    Block(&'static str, &'static str),
+}
+
+impl CommentStyle {
+    /// Picks the comment markers from the file extension, see
+    /// [`crate::filetype::from_path`]. Untitled buffers and unknown extensions are
+    /// treated as Markdown notes.
+    pub fn for_path(path: Option<&std::path::Path>) -> Self {
+        crate::filetype::from_path(path).comment
+    }
+}
+
+/// Splits a commented line into its indentation and uncommented content.
+/// Returns `None` if `line` isn't commented in `style`.
+fn strip_comment(line: &str, style: CommentStyle) -> Option<String> {
+    let indent = crate::indent::leading_whitespace(line);
+    let content = &line[indent.len()..];
+
+    let inner = match style {
+        CommentStyle::Line(prefix) => content.strip_prefix(prefix)?,
+        CommentStyle::Block(open, close) => content
+            .strip_prefix(open)?
+            .trim_end()
+            .strip_suffix(close)
+            .map(|inner| inner.strip_suffix(' ').unwrap_or(inner))?,
+    };
+
+    Some(format!(
+        "{indent}{}",
+        inner.strip_prefix(' ').unwrap_or(inner)
+    ))
+}
+
+/// Toggles comments over `lines`, returning the replacement text for each line that changes.
+///
+/// If every non-blank line is already commented, the markers are removed; otherwise every
+/// non-blank line is commented. Markers go at the shallowest indentation of the block so
+/// the commented lines stay aligned.
+pub fn toggle_comments(lines: &[String], style: CommentStyle) -> Vec<(usize, String)> {
+    let non_blank = || {
+        lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+    };
+
+    if non_blank().next().is_none() {
+        return Vec::new();
+    }
+
+    if non_blank().all(|(_, line)| strip_comment(line, style).is_some()) {
+        return non_blank()
+            .filter_map(|(i, line)| strip_comment(line, style).map(|stripped| (i, stripped)))
+            .collect();
+    }
+
+    let block = non_blank()
+        .map(|(_, line)| line.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let indent_len = crate::indent::common_indent(&block).len();
+
+    non_blank()
+        .map(|(i, line)| {
+            let (indent, content) = line.split_at(indent_len);
+            let commented = match style {
+                CommentStyle::Line(prefix) => format!("{indent}{prefix} {content}"),
+                CommentStyle::Block(open, close) => format!("{indent}{open} {content} {close}"),
+            };
+            (i, commented)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.split('\n').map(String::from).collect()
+    }
+
+    #[test]
+    fn test_style_for_path() {
+        assert_eq!(
+            CommentStyle::for_path(Some(std::path::Path::new("main.rs"))),
+            CommentStyle::Line("//")
+        );
+        assert_eq!(
+            CommentStyle::for_path(Some(std::path::Path::new("config.TOML"))),
+            CommentStyle::Line("#")
+        );
+        assert_eq!(
+            CommentStyle::for_path(None),
+            CommentStyle::Block("<!--", "-->")
+        );
+    }
+
+    #[test]
+    fn test_comment_then_uncomment_line_style() {
+        let original = lines("    a\n\n      b");
+        let commented = toggle_comments(&original, CommentStyle::Line("//"));
+
+        assert_eq!(
+            commented,
+            vec![(0, "    // a".to_string()), (2, "    //   b".to_string())]
+        );
+
+        let round_trip = lines("    // a\n\n    //   b");
+        assert_eq!(
+            toggle_comments(&round_trip, CommentStyle::Line("//")),
+            vec![(0, "    a".to_string()), (2, "      b".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_mixed_block_gets_commented() {
+        let mixed = lines("# done\ntodo");
+
+        assert_eq!(
+            toggle_comments(&mixed, CommentStyle::Line("#")),
+            vec![(0, "# # done".to_string()), (1, "# todo".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_block_style_per_line() {
+        let text = lines("- item");
+        let commented = toggle_comments(&text, CommentStyle::Block("<!--", "-->"));
+
+        assert_eq!(commented, vec![(0, "<!-- - item -->".to_string())]);
+        assert_eq!(
+            toggle_comments(&lines(&commented[0].1), CommentStyle::Block("<!--", "-->")),
+            vec![(0, "- item".to_string())]
+        );
+    }
+}
@@ -0,0 +1,409 @@
+/// Name of the project config a workspace folder may hold.
+pub const PROJECT_FILE: &str = ".mynotes.toml";
+
+/// A value of the TOML subset config files are written in: strings, integers,
+/// booleans and one-line arrays of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Value {
+    String(String),
+    Integer(i64),
+    Boolean(bool),
+    Array(Vec<Value>),
+}
+
+/// The value at the start of `text`, and the rest after it.
+fn parse_value(text: &str) -> Option<(Value, &str)> {
+    let text = text.trim_start();
+
+    if let Some(rest) = text.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => return Some((Value::String(value), &rest[i + 1..])),
+                '\\' => value.push(match chars.next()?.1 {
+                    'n' => '\n',
+                    't' => '\t',
+                    other => other,
+                }),
+                c => value.push(c),
+            }
+        }
+        return None;
+    }
+    if let Some(rest) = text.strip_prefix('\'') {
+        let (value, rest) = rest.split_once('\'')?;
+
+        return Some((Value::String(value.to_string()), rest));
+    }
+    if let Some(mut rest) = text.strip_prefix('[') {
+        let mut values = Vec::new();
+
+        loop {
+            rest = rest.trim_start();
+            if let Some(after) = rest.strip_prefix(']') {
+                return Some((Value::Array(values), after));
+            }
+
+            let (value, after) = parse_value(rest)?;
+
+            values.push(value);
+            rest = after.trim_start();
+            rest = rest.strip_prefix(',').unwrap_or(rest);
+        }
+    }
+
+    let end = text
+        .find(|c: char| c.is_whitespace() || c == ',' || c == ']' || c == '#')
+        .unwrap_or(text.len());
+    let (word, rest) = text.split_at(end);
+    let value = match word {
+        "true" => Value::Boolean(true),
+        "false" => Value::Boolean(false),
+        _ => Value::Integer(word.replace('_', "").parse().ok()?),
+    };
+
+    Some((value, rest))
+}
+
+/// Settings and startup commands from a config file. `None` and empty lists mean
+/// "not specified", so whatever was in effect stays.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Config {
+    pub indent_style: Option<crate::settings::IndentStyle>,
+    pub indent_size: Option<usize>,
+    pub tab_width: Option<usize>,
+    pub cooperative_files: Option<bool>,
+    pub line_times: Option<bool>,
+    pub include_archived: Option<bool>,
+    /// Columns to draw guides at.
+    pub rulers: Vec<usize>,
+    pub caret_shape: Option<crate::caret::CaretShape>,
+    pub caret_width: Option<usize>,
+    pub caret_color: Option<crate::theme::Rgb>,
+    /// How long the caret stays on, then off, in milliseconds. 0 stops it blinking.
+    pub caret_blink: Option<u64>,
+    /// Notes to open on start, resolved against the config file's folder.
+    pub open: Vec<std::path::PathBuf>,
+    pub theme: Option<crate::theme::ThemeMode>,
+    /// Menu commands to carry out on start, by their menu path, e.g.
+    /// `View/Focus Mode`.
+    pub commands: Vec<String>,
+}
+
+impl Config {
+    /// The user's own config, `config.toml` next to the session store.
+    pub fn user_path() -> Option<std::path::PathBuf> {
+        Some(crate::session::config_dir()?.join("config.toml"))
+    }
+
+    /// Reads `content`, a config file in `dir`. Settings go at the top or under
+    /// `[settings]`, `open`, `theme` and `commands` under `[startup]`:
+    ///
+    /// ```toml
+    /// [settings]
+    /// indent_style = "space"
+    /// indent_size = 2
+    ///
+    /// [startup]
+    /// open = ["todo.md", "journal/today.md"]
+    /// theme = "dark"
+    /// commands = ["View/Outline"]
+    /// ```
+    ///
+    /// Unknown keys, sections and bad values are ignored.
+    pub fn parse(content: &str, dir: &std::path::Path) -> Self {
+        let mut config = Self::default();
+        let mut section = String::new();
+
+        for line in content.lines() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_string();
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let Some((value, rest)) = parse_value(value) else {
+                continue;
+            };
+            let rest = rest.trim();
+
+            if !(rest.is_empty() || rest.starts_with('#')) {
+                continue;
+            }
+            config.set(&section, key.trim(), value, dir);
+        }
+
+        config
+    }
+
+    fn set(&mut self, section: &str, key: &str, value: Value, dir: &std::path::Path) {
+        let strings = |value: Value| match value {
+            Value::Array(values) => values
+                .into_iter()
+                .filter_map(|value| match value {
+                    Value::String(s) => Some(s),
+                    _ => None,
+                })
+                .collect(),
+            Value::String(s) => vec![s],
+            _ => Vec::new(),
+        };
+        let size = |value: &Value| match *value {
+            Value::Integer(n) if n > 0 => usize::try_from(n).ok(),
+            _ => None,
+        };
+        let flag = |value: &Value| match *value {
+            Value::Boolean(b) => Some(b),
+            _ => None,
+        };
+
+        match (section, key) {
+            ("" | "settings", "indent_style") => {
+                self.indent_style = match &value {
+                    Value::String(s) if s == "tab" => Some(crate::settings::IndentStyle::Tabs),
+                    Value::String(s) if s == "space" => Some(crate::settings::IndentStyle::Spaces),
+                    _ => self.indent_style,
+                }
+            }
+            ("" | "settings", "indent_size") => self.indent_size = size(&value),
+            ("" | "settings", "tab_width") => self.tab_width = size(&value),
+            ("" | "settings", "cooperative_files") => self.cooperative_files = flag(&value),
+            ("" | "settings", "line_times") => self.line_times = flag(&value),
+            ("" | "settings", "include_archived") => self.include_archived = flag(&value),
+            ("" | "settings", "rulers") => {
+                let values = match value {
+                    Value::Array(values) => values,
+                    value => vec![value],
+                };
+
+                self.rulers = values.iter().filter_map(size).collect();
+            }
+            ("" | "settings", "caret_shape") => {
+                self.caret_shape = match &value {
+                    Value::String(s) => crate::caret::CaretShape::parse(s),
+                    _ => None,
+                }
+            }
+            ("" | "settings", "caret_width") => self.caret_width = size(&value),
+            ("" | "settings", "caret_color") => {
+                self.caret_color = match &value {
+                    Value::String(s) => crate::theme::parse_css_color(s),
+                    _ => None,
+                }
+            }
+            ("" | "settings", "caret_blink") => {
+                self.caret_blink = match value {
+                    Value::Integer(ms) => u64::try_from(ms).ok(),
+                    _ => None,
+                }
+            }
+            ("startup", "open") => {
+                // Only notes inside the config file's folder.
+                self.open = strings(value)
+                    .into_iter()
+                    .filter(|note| {
+                        std::path::Path::new(note)
+                            .components()
+                            .all(|part| matches!(part, std::path::Component::Normal(_)))
+                    })
+                    .map(|note| dir.join(note))
+                    .collect();
+            }
+            ("startup", "theme") => {
+                self.theme = match &value {
+                    Value::String(s) => match s.to_lowercase().as_str() {
+                        "system" => Some(crate::theme::ThemeMode::FollowSystem),
+                        name => crate::theme::Theme::ALL
+                            .into_iter()
+                            .find(|theme| theme.name().to_lowercase() == name)
+                            .map(crate::theme::ThemeMode::Fixed),
+                    },
+                    _ => None,
+                }
+            }
+            ("startup", "commands") => self.commands = strings(value),
+            _ => {}
+        }
+    }
+
+    /// The config file at `path`, empty if there's none.
+    pub fn load(path: &std::path::Path) -> Self {
+        Self::parse(
+            &std::fs::read_to_string(path).unwrap_or_default(),
+            path.parent().unwrap_or(std::path::Path::new("")),
+        )
+    }
+
+    /// The user's config with the project config of the workspace at `root`, if
+    /// any, on top: what the project sets wins, and its startup lists replace
+    /// the user's. Startup commands are only taken from the user's config, since
+    /// opening a folder someone else wrote must not run whatever menu command
+    /// it names.
+    pub fn resolve(root: Option<&std::path::Path>) -> Self {
+        Self::user_path()
+            .as_deref()
+            .map(Self::load)
+            .unwrap_or_default()
+            .with_project(root)
+    }
+
+    /// `self`, the user's config, with the project config of `root` on top, see
+    /// [`Self::resolve`].
+    fn with_project(self, root: Option<&std::path::Path>) -> Self {
+        match root {
+            Some(root) => self.merge(Self {
+                commands: Vec::new(),
+                ..Self::load(&root.join(PROJECT_FILE))
+            }),
+            None => self,
+        }
+    }
+
+    /// `self` overridden by whatever `over` specifies.
+    pub fn merge(self, over: Self) -> Self {
+        fn list<T>(base: Vec<T>, over: Vec<T>) -> Vec<T> {
+            if over.is_empty() { base } else { over }
+        }
+
+        Self {
+            indent_style: over.indent_style.or(self.indent_style),
+            indent_size: over.indent_size.or(self.indent_size),
+            tab_width: over.tab_width.or(self.tab_width),
+            cooperative_files: over.cooperative_files.or(self.cooperative_files),
+            line_times: over.line_times.or(self.line_times),
+            include_archived: over.include_archived.or(self.include_archived),
+            rulers: list(self.rulers, over.rulers),
+            caret_shape: over.caret_shape.or(self.caret_shape),
+            caret_width: over.caret_width.or(self.caret_width),
+            caret_color: over.caret_color.or(self.caret_color),
+            caret_blink: over.caret_blink.or(self.caret_blink),
+            open: list(self.open, over.open),
+            theme: over.theme.or(self.theme),
+            commands: list(self.commands, over.commands),
+        }
+    }
+
+    /// Puts the indentation settings, guides and caret into `settings`. The switches are left to
+    /// the caller, which may have more to turn on or off along with them.
+    pub fn apply(&self, settings: &mut crate::settings::EditorSettings) {
+        if let Some(style) = self.indent_style {
+            settings.indent_style = style;
+        }
+        if let Some(size) = self.indent_size {
+            settings.indent_size = size;
+        }
+        if let Some(width) = self.tab_width {
+            settings.tab_width = width;
+        }
+        if !self.rulers.is_empty() {
+            settings.rulers.clone_from(&self.rulers);
+            settings.rulers.sort_unstable();
+            settings.rulers.dedup();
+        }
+        if let Some(shape) = self.caret_shape {
+            settings.caret.shape = shape;
+        }
+        if let Some(width) = self.caret_width {
+            settings.caret.width = u8::try_from(width).unwrap_or(u8::MAX);
+        }
+        if let Some(color) = self.caret_color {
+            settings.caret.color = Some(color);
+        }
+        if let Some(ms) = self.caret_blink {
+            settings.caret.blink = (ms > 0).then(|| std::time::Duration::from_millis(ms));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_merge() {
+        let dir = std::path::Path::new("/notes");
+        let user = Config::parse(
+            "indent_size = 4\nline_times = true\ncaret_shape = \"block\"\ncaret_blink = 0\n[startup]\ntheme = \"light\"\nopen = 'inbox.md'\n",
+            std::path::Path::new("/home/me"),
+        );
+        let project = Config::parse(
+            r#"
+            # Project settings
+            [settings]
+            indent_style = "space"
+            indent_size = 2   # narrow
+            tab_width = "wide"
+            rulers = [80, 72]
+            caret_color = '#f80'
+            mystery = 1
+
+            [startup]
+            open = ["todo.md", "../secret.md", "/etc/passwd", "a \"b\".md",]
+            commands = ["View/Outline"]
+            "#,
+            dir,
+        );
+
+        assert_eq!(
+            project.indent_style,
+            Some(crate::settings::IndentStyle::Spaces)
+        );
+        assert_eq!(project.tab_width, None);
+        assert_eq!(project.open, [dir.join("todo.md"), dir.join("a \"b\".md")]);
+
+        let config = user.merge(project);
+
+        assert_eq!(config.indent_size, Some(2));
+        assert_eq!(config.line_times, Some(true));
+        assert_eq!(
+            config.theme,
+            Some(crate::theme::ThemeMode::Fixed(crate::theme::Theme::Light))
+        );
+        assert_eq!(config.open.len(), 2);
+        assert_eq!(config.commands, ["View/Outline"]);
+
+        let mut settings = crate::settings::EditorSettings::default();
+        config.apply(&mut settings);
+        assert_eq!(settings.indent_size, 2);
+        assert_eq!(settings.tab_width, 4);
+        assert_eq!(settings.rulers, [72, 80]);
+        assert_eq!(settings.caret.shape, crate::caret::CaretShape::Block);
+        assert_eq!(settings.caret.color, Some((255, 136, 0)));
+        assert_eq!(settings.caret.blink, None);
+    }
+
+    #[test]
+    fn test_project_startup_commands_are_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+
+        std::fs::write(
+            dir.path().join(PROJECT_FILE),
+            "indent_size = 2\n[startup]\ncommands = [\"Tools/Run Block\"]\n",
+        )
+        .unwrap();
+
+        let user = Config::parse(
+            "[startup]\ncommands = [\"View/Outline\"]\n",
+            std::path::Path::new("/home/me"),
+        );
+        let config = user.with_project(Some(dir.path()));
+
+        assert_eq!(config.indent_size, Some(2));
+        assert_eq!(config.commands, ["View/Outline"]);
+        assert!(
+            Config::default()
+                .with_project(Some(dir.path()))
+                .commands
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,173 @@
+/// A `<<<<<<<` ... `>>>>>>>` block left behind by a failed merge. All fields are
+/// the rows of the marker lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Conflict {
+    pub start: usize,
+    /// The `|||||||` line of a diff3-style conflict, which opens the common ancestor.
+    pub base: Option<usize>,
+    pub separator: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Resolution {
+    Ours,
+    Theirs,
+    /// Ours followed by theirs.
+    Both,
+}
+
+/// Whether `line` is a seven character conflict marker made of `c`, optionally
+/// followed by a label such as a branch name.
+fn is_marker(line: &str, c: char) -> bool {
+    let rest = line.trim_start_matches(c);
+
+    line.len() - rest.len() == 7 && (rest.is_empty() || rest.starts_with([' ', '\t']))
+}
+
+impl Conflict {
+    /// Rows of our side of the conflict.
+    pub fn ours(&self) -> std::ops::Range<usize> {
+        self.start + 1..self.base.unwrap_or(self.separator)
+    }
+
+    /// Rows of their side of the conflict.
+    pub fn theirs(&self) -> std::ops::Range<usize> {
+        self.separator + 1..self.end
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn contains(&self, row: usize) -> bool {
+        (self.start..=self.end).contains(&row)
+    }
+
+    /// The lines the block collapses to. `lines` is the whole block, markers
+    /// included, starting at `self.start`.
+    pub fn resolve<S: AsRef<str>>(&self, lines: &[S], resolution: Resolution) -> Vec<String> {
+        let side = |rows: std::ops::Range<usize>| {
+            lines[rows.start - self.start..rows.end - self.start]
+                .iter()
+                .map(|line| line.as_ref().to_string())
+        };
+
+        match resolution {
+            Resolution::Ours => side(self.ours()).collect(),
+            Resolution::Theirs => side(self.theirs()).collect(),
+            Resolution::Both => side(self.ours()).chain(side(self.theirs())).collect(),
+        }
+    }
+}
+
+/// Which conflict marker `line` is, by its character: `<`, `|`, `=` or `>`.
+pub fn marker(line: &str) -> Option<char> {
+    ['<', '|', '=', '>']
+        .into_iter()
+        .find(|&c| is_marker(line, c))
+}
+
+/// Finds every well-formed conflict block. Stray or unbalanced markers are ignored.
+pub fn find_conflicts<S: AsRef<str>>(lines: &[S]) -> Vec<Conflict> {
+    let markers: Vec<(usize, char)> = lines
+        .iter()
+        .enumerate()
+        .filter_map(|(row, line)| Some((row, marker(line.as_ref())?)))
+        .collect();
+
+    pair(&markers)
+}
+
+/// The conflict blocks `markers` make up, given as their rows and [`marker`]s in
+/// order. Lines between markers never change what they pair up to.
+pub fn pair(markers: &[(usize, char)]) -> Vec<Conflict> {
+    let mut conflicts = Vec::new();
+    let mut open: Option<(usize, Option<usize>, Option<usize>)> = None;
+
+    for &(row, marker) in markers {
+        open = match (open, marker) {
+            (_, '<') => Some((row, None, None)),
+            (Some((start, None, None)), '|') => Some((start, Some(row), None)),
+            (Some((start, base, None)), '=') => Some((start, base, Some(row))),
+            (Some((start, base, Some(separator))), '>') => {
+                conflicts.push(Conflict {
+                    start,
+                    base,
+                    separator,
+                    end: row,
+                });
+                None
+            }
+            (open, _) => open,
+        };
+    }
+
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MERGED: &[&str] = &[
+        "intro",
+        "<<<<<<< HEAD",
+        "mine",
+        "=======",
+        "yours",
+        "also yours",
+        ">>>>>>> feature",
+        "middle",
+        "<<<<<<< HEAD",
+        "a",
+        "||||||| base",
+        "original",
+        "=======",
+        "b",
+        ">>>>>>>",
+    ];
+
+    #[test]
+    fn test_find_conflicts() {
+        assert_eq!(
+            find_conflicts(MERGED),
+            vec![
+                Conflict {
+                    start: 1,
+                    base: None,
+                    separator: 3,
+                    end: 6
+                },
+                Conflict {
+                    start: 8,
+                    base: Some(10),
+                    separator: 12,
+                    end: 14
+                },
+            ]
+        );
+        assert!(find_conflicts(&["<<<<<<<< eight", "=======", ">>>>>>>"]).is_empty());
+        assert!(find_conflicts(&["<<<<<<<", "no separator", ">>>>>>>"]).is_empty());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let conflicts = find_conflicts(MERGED);
+        let first = &MERGED[1..=6];
+        let second = &MERGED[8..=14];
+
+        assert_eq!(conflicts[0].resolve(first, Resolution::Ours), ["mine"]);
+        assert_eq!(
+            conflicts[0].resolve(first, Resolution::Theirs),
+            ["yours", "also yours"]
+        );
+        assert_eq!(
+            conflicts[0].resolve(first, Resolution::Both),
+            ["mine", "yours", "also yours"]
+        );
+        assert_eq!(
+            conflicts[1].resolve(second, Resolution::Both),
+            ["a", "b"],
+            "The common ancestor is dropped"
+        );
+    }
+}
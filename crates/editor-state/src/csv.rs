@@ -0,0 +1,217 @@
+/// Whether `path` is a table this app can show as a grid.
+pub fn is_table(path: &std::path::Path) -> bool {
+    path.extension()
+        .and_then(std::ffi::OsStr::to_str)
+        .is_some_and(|ext| matches!(ext.to_ascii_lowercase().as_str(), "csv" | "tsv"))
+}
+
+/// The field separator of a table: tab for `.tsv`, otherwise whichever of `,`, `;`
+/// and tab the first line has most of.
+fn delimiter(path: &std::path::Path, bytes: &[u8]) -> u8 {
+    if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("tsv"))
+    {
+        return b'\t';
+    }
+
+    let first = &bytes[..bytes
+        .iter()
+        .position(|&b| b == b'\n')
+        .unwrap_or(bytes.len())];
+
+    let count = |candidate: u8| first.iter().filter(|&&b| b == candidate).count();
+
+    [b';', b'\t'].into_iter().fold(b',', |best, candidate| {
+        if count(candidate) > count(best) {
+            candidate
+        } else {
+            best
+        }
+    })
+}
+
+/// Where each record of `bytes` starts, in one pass. Line breaks inside quoted
+/// fields don't end a record.
+fn record_starts(bytes: &[u8]) -> Vec<usize> {
+    let mut starts = Vec::new();
+    let mut quoted = false;
+    let mut at_start = true;
+
+    for (i, &b) in bytes.iter().enumerate() {
+        if at_start {
+            starts.push(i);
+            at_start = false;
+        }
+        match b {
+            b'"' => quoted = !quoted,
+            b'\n' if !quoted => at_start = true,
+            _ => {}
+        }
+    }
+
+    starts
+}
+
+/// The fields of the record at the start of `bytes`, unquoted.
+fn parse_record(bytes: &[u8], delimiter: u8) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = Vec::new();
+    let mut quoted = false;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let b = bytes[i];
+
+        match b {
+            b'"' if quoted && bytes.get(i + 1) == Some(&b'"') => {
+                field.push(b'"');
+                i += 1;
+            }
+            b'"' if quoted || field.is_empty() => quoted = !quoted,
+            b'\n' if !quoted => break,
+            _ if b == delimiter && !quoted => {
+                fields.push(String::from_utf8_lossy(&field).into_owned());
+                field.clear();
+            }
+            _ => field.push(b),
+        }
+        i += 1;
+    }
+
+    if field.last() == Some(&b'\r') {
+        field.pop();
+    }
+    fields.push(String::from_utf8_lossy(&field).into_owned());
+    fields
+}
+
+/// A CSV or TSV file read straight from its memory map: opening only finds where
+/// each record starts, and records are parsed as they're asked for, so large files
+/// open at once.
+pub struct Table {
+    backing: io::backing::FileBacking,
+    /// Byte offset of each record, the header first.
+    starts: Vec<usize>,
+    delimiter: u8,
+    /// How many columns the header has.
+    columns: usize,
+}
+
+impl Table {
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be opened.
+    pub fn open(path: &std::path::Path) -> std::io::Result<Self> {
+        let backing = io::backing::FileBacking::open(path)?;
+        let bytes = backing.as_slice();
+        let delimiter = delimiter(path, bytes);
+        let mut starts = record_starts(bytes);
+
+        // A trailing blank line is the end of the file, not an empty record.
+        if starts
+            .last()
+            .is_some_and(|&start| bytes[start..].iter().all(|b| b.is_ascii_whitespace()))
+        {
+            starts.pop();
+        }
+
+        let columns = starts
+            .first()
+            .map_or(0, |&start| parse_record(&bytes[start..], delimiter).len());
+
+        Ok(Self {
+            backing,
+            starts,
+            delimiter,
+            columns,
+        })
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        self.backing.path()
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    /// Records below the header.
+    pub fn rows(&self) -> usize {
+        self.starts.len().saturating_sub(1)
+    }
+
+    pub fn header(&self) -> Vec<String> {
+        self.record(0)
+    }
+
+    /// The fields of row `row` below the header.
+    pub fn row(&self, row: usize) -> Vec<String> {
+        self.record(row + 1)
+    }
+
+    fn record(&self, index: usize) -> Vec<String> {
+        match self.starts.get(index) {
+            Some(&start) => parse_record(&self.backing.as_slice()[start..], self.delimiter),
+            None => Vec::new(),
+        }
+    }
+
+    /// Row numbers ordered by `column`: numerically when both fields are numbers,
+    /// by text otherwise. Equal fields keep their order.
+    pub fn sorted_by(&self, column: usize, descending: bool) -> Vec<usize> {
+        let keys: Vec<(Option<f64>, String)> = (0..self.rows())
+            .map(|row| {
+                let field = self.row(row).into_iter().nth(column).unwrap_or_default();
+
+                (field.trim().parse().ok(), field.to_lowercase())
+            })
+            .collect();
+        let mut order: Vec<usize> = (0..keys.len()).collect();
+
+        order.sort_by(|&a, &b| {
+            let ordering = match (&keys[a], &keys[b]) {
+                ((Some(x), _), (Some(y), _)) => x.total_cmp(y),
+                ((_, x), (_, y)) => x.cmp(y),
+            };
+
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+        order
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fruit.csv");
+
+        std::fs::write(
+            &path,
+            "name,count,note\r\napple,10,\"red, round\"\r\npear,9,\"says \"\"hi\"\"\nover two lines\"\r\nfig,100,\r\n\r\n",
+        )
+        .unwrap();
+
+        let table = Table::open(&path).unwrap();
+
+        assert!(is_table(&path));
+        assert_eq!((table.rows(), table.columns()), (3, 3));
+        assert_eq!(table.header(), vec!["name", "count", "note"]);
+        assert_eq!(table.row(0), vec!["apple", "10", "red, round"]);
+        assert_eq!(
+            table.row(1),
+            vec!["pear", "9", "says \"hi\"\nover two lines"]
+        );
+        assert_eq!(table.sorted_by(1, false), vec![1, 0, 2]);
+        assert_eq!(table.sorted_by(0, true), vec![1, 2, 0]);
+        assert_eq!(delimiter(&path, b"a;b;c,d\n"), b';');
+    }
+}
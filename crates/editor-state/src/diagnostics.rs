@@ -0,0 +1,163 @@
+/// A `[[link]]` to a note that doesn't exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokenLink {
+    /// The note the link is in.
+    pub path: std::path::PathBuf,
+    pub row: usize,
+    /// The missing note's name, as the link spells it.
+    pub target: String,
+}
+
+/// What's wrong with how the notes under a folder link to each other.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    /// Notes no other note links to, sorted.
+    pub orphans: Vec<std::path::PathBuf>,
+    /// Links to missing notes, in note and then row order.
+    pub broken: Vec<BrokenLink>,
+}
+
+impl Report {
+    pub fn is_empty(&self) -> bool {
+        self.orphans.is_empty() && self.broken.is_empty()
+    }
+}
+
+/// Checks the links between the notes under `root`. Archived notes aren't listed
+/// as orphans unless `include_archived`, but their links count either way.
+pub fn check(root: &std::path::Path, include_archived: bool) -> Report {
+    let notes = crate::workspace::notes(root);
+    let names: std::collections::HashSet<String> = notes
+        .iter()
+        .filter_map(|path| crate::workspace::note_name(path))
+        .map(str::to_lowercase)
+        .collect();
+    let mut linked = std::collections::HashSet::new();
+    let mut broken = Vec::new();
+
+    for path in &notes {
+        let Ok(text) = std::fs::read_to_string(path) else {
+            continue;
+        };
+        let own = crate::workspace::note_name(path).map(str::to_lowercase);
+
+        for (row, line) in text.lines().enumerate() {
+            // `[[#heading]]` links stay within the note.
+            for link in crate::workspace::wiki_links(line)
+                .into_iter()
+                .filter(|link| !link.note.is_empty())
+            {
+                let name = link.note.to_lowercase();
+
+                if !names.contains(&name) {
+                    broken.push(BrokenLink {
+                        path: path.clone(),
+                        row,
+                        target: link.note,
+                    });
+                } else if own.as_ref() != Some(&name) {
+                    linked.insert(name);
+                }
+            }
+        }
+    }
+
+    let orphans = notes
+        .into_iter()
+        .filter(|path| include_archived || !crate::workspace::is_archived(root, path))
+        .filter(|path| {
+            crate::workspace::note_name(path)
+                .is_some_and(|name| !linked.contains(&name.to_lowercase()))
+        })
+        .collect();
+
+    Report { orphans, broken }
+}
+
+/// Creates the note a broken link to `name` is missing, under `root`, titled with
+/// its name. Returns its path.
+///
+/// # Errors
+///
+/// Returns an error if a file of that name is already there, or it can't be written.
+pub fn create_missing(root: &std::path::Path, name: &str) -> std::io::Result<std::path::PathBuf> {
+    let path = root.join(format!("{}.md", name.trim()));
+
+    if path.exists() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::AlreadyExists,
+            format!("{} already exists", path.display()),
+        ));
+    }
+
+    let mut batch = crate::workspace::BatchSave::default();
+
+    batch.write(&path, format!("# {}\n", name.trim()));
+    batch.commit()?;
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_orphans_and_broken_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let write = |name: &str, text: &str| std::fs::write(root.join(name), text).unwrap();
+
+        write(
+            "Index.md",
+            "# Index\n- [[Ideas]]\n- [[ideas#Sync]] and [[Missing|later]]\n",
+        );
+        write("Ideas.md", "[[#Sync]] [[Ideas]]\n\n## Sync\n");
+        write("Lonely.md", "[[Gone]]\n");
+        std::fs::create_dir(root.join("archive")).unwrap();
+        std::fs::write(root.join("archive/Old.md"), "").unwrap();
+
+        let report = check(root, false);
+
+        assert_eq!(
+            report.orphans,
+            vec![root.join("Index.md"), root.join("Lonely.md")]
+        );
+        assert_eq!(
+            report.broken,
+            vec![
+                BrokenLink {
+                    path: root.join("Index.md"),
+                    row: 2,
+                    target: "Missing".to_string(),
+                },
+                BrokenLink {
+                    path: root.join("Lonely.md"),
+                    row: 0,
+                    target: "Gone".to_string(),
+                },
+            ]
+        );
+        assert!(
+            check(root, true)
+                .orphans
+                .contains(&root.join("archive/Old.md"))
+        );
+
+        let created = create_missing(root, "Missing").unwrap();
+
+        assert_eq!(std::fs::read_to_string(&created).unwrap(), "# Missing\n");
+        assert!(create_missing(root, "Missing").is_err());
+        assert!(crate::workspace::remove_links(&root.join("Lonely.md"), "gone").unwrap());
+        assert_eq!(
+            std::fs::read_to_string(root.join("Lonely.md")).unwrap(),
+            "Gone\n"
+        );
+
+        let report = check(root, false);
+
+        assert!(report.broken.is_empty());
+        assert!(report.orphans.contains(&root.join("Lonely.md")));
+        assert!(!report.orphans.contains(&root.join("Missing.md")));
+    }
+}
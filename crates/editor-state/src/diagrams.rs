@@ -0,0 +1,140 @@
+/// A ```` ```mermaid ```` code block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagram {
+    pub start: usize,
+    /// The row of the closing fence.
+    pub end: usize,
+    pub source: String,
+}
+
+/// Whether `line` opens or closes a code block. [`fences`] pairs these up; other
+/// lines are only ever code.
+pub fn is_fence(line: &str) -> bool {
+    let trimmed = line.trim();
+
+    trimmed.starts_with("```") || trimmed.starts_with("~~~")
+}
+
+/// The `mermaid` blocks in `lines`. Only closed blocks count.
+pub fn fences<S: AsRef<str>>(lines: &[S]) -> Vec<Diagram> {
+    let mut diagrams = Vec::new();
+    // The fence that opened the current code block, and whether it's a diagram.
+    let mut open: Option<(usize, &str, bool)> = None;
+
+    for (row, line) in lines.iter().enumerate() {
+        let trimmed = line.as_ref().trim();
+
+        match open {
+            Some((start, fence, diagram)) if trimmed.starts_with(fence) => {
+                if diagram {
+                    let source: Vec<&str> = lines[start + 1..row]
+                        .iter()
+                        .map(|line| line.as_ref())
+                        .collect();
+
+                    diagrams.push(Diagram {
+                        start,
+                        end: row,
+                        source: source.join("\n"),
+                    });
+                }
+                open = None;
+            }
+            Some(_) => {}
+            None => {
+                let Some(fence) = ["```", "~~~"]
+                    .into_iter()
+                    .find(|fence| trimmed.starts_with(fence))
+                else {
+                    continue;
+                };
+                let info = trimmed.trim_start_matches(&fence[..1]).trim();
+
+                open = Some((row, fence, info.eq_ignore_ascii_case("mermaid")));
+            }
+        }
+    }
+
+    diagrams
+}
+
+/// Where the image of `source` is cached: `diagrams/<hash of source>.png` next to
+/// the session store, so a diagram is only rendered again once it's edited.
+pub fn cache_path(source: &str) -> Option<std::path::PathBuf> {
+    let (len, hash) = crate::history_log::fingerprint(source.trim());
+
+    Some(
+        crate::session::config_dir()?
+            .join("diagrams")
+            .join(format!("{len:x}-{hash:016x}.png")),
+    )
+}
+
+/// Renders the Mermaid `source` to a PNG at `out` with the Mermaid CLI (`mmdc`),
+/// which has to be installed. Slow: call it off the UI thread.
+///
+/// # Errors
+///
+/// Returns an error if `mmdc` can't be run, or with its message if it fails.
+pub fn render(source: &str, out: &std::path::Path) -> std::io::Result<()> {
+    let dir = out.parent().unwrap_or(std::path::Path::new("."));
+    std::fs::create_dir_all(dir)?;
+
+    let mut input = tempfile::Builder::new().suffix(".mmd").tempfile_in(dir)?;
+    std::io::Write::write_all(&mut input, source.as_bytes())?;
+
+    // mmdc picks the format from the extension, so the image is written whole to
+    // a `.png` beside `out` and only then moved into place.
+    let image = tempfile::Builder::new().suffix(".png").tempfile_in(dir)?;
+    let output = std::process::Command::new("mmdc")
+        .arg("--quiet")
+        .args(["--backgroundColor", "white"])
+        .arg("--input")
+        .arg(input.path())
+        .arg("--output")
+        .arg(image.path())
+        .stdin(std::process::Stdio::null())
+        .output()?;
+
+    if !output.status.success() {
+        let message = String::from_utf8_lossy(&output.stderr);
+        let message = message
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or("mmdc failed");
+
+        return Err(std::io::Error::other(message.trim().to_string()));
+    }
+
+    image.persist(out).map_err(|err| err.error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fences() {
+        let lines = [
+            "```mermaid",
+            "graph TD",
+            "  A --> B",
+            "```",
+            "~~~rust",
+            "```mermaid",
+            "~~~",
+            "``` Mermaid",
+            "pie",
+        ];
+
+        assert_eq!(
+            fences(&lines),
+            vec![Diagram {
+                start: 0,
+                end: 3,
+                source: "graph TD\n  A --> B".into(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,405 @@
+/// One step of an edit script turning `old` into `new`. Indices point into the inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffOp {
+    Equal { old: usize, new: usize },
+    Delete { old: usize },
+    Insert { new: usize },
+}
+
+/// Shortest edit script between two sequences, deletions before insertions in
+/// each changed hunk.
+///
+/// Myers' algorithm in linear space: time grows with the size of the inputs times
+/// the number of differences, not with the product of their lengths. A stretch
+/// differing in more than [`MAX_COST`] places is given up on and scripted as
+/// deleted and inserted whole.
+pub fn diff<T: PartialEq>(old: &[T], new: &[T]) -> Vec<DiffOp> {
+    let mut ops = Vec::with_capacity(old.len().max(new.len()));
+
+    script(old, new, (0, 0), &mut ops);
+
+    // Halves are scripted separately, so a hunk can come out as inserts then deletes.
+    let mut hunk = 0;
+
+    while hunk < ops.len() {
+        let end = (hunk..ops.len())
+            .find(|&i| matches!(ops[i], DiffOp::Equal { .. }))
+            .unwrap_or(ops.len());
+
+        ops[hunk..end].sort_by_key(|op| matches!(op, DiffOp::Insert { .. }));
+        hunk = end + 1;
+    }
+    ops
+}
+
+/// Differences past which a stretch is scripted as replaced whole, see [`diff`].
+pub const MAX_COST: usize = 4096;
+
+/// Appends the script turning `a` into `b`, which start at `at` in the inputs.
+fn script<T: PartialEq>(a: &[T], b: &[T], at: (usize, usize), ops: &mut Vec<DiffOp>) {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (old, new) = (at.0 + prefix, at.1 + prefix);
+    let (a, b) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    ops.extend((0..prefix).map(|i| DiffOp::Equal {
+        old: at.0 + i,
+        new: at.1 + i,
+    }));
+    match middle_snake(a, b) {
+        Some((x, y)) if !a.is_empty() && !b.is_empty() => {
+            script(&a[..x], &b[..y], (old, new), ops);
+            script(&a[x..], &b[y..], (old + x, new + y), ops);
+        }
+        _ => {
+            ops.extend((0..a.len()).map(|i| DiffOp::Delete { old: old + i }));
+            ops.extend((0..b.len()).map(|j| DiffOp::Insert { new: new + j }));
+        }
+    }
+    ops.extend((0..suffix).map(|k| DiffOp::Equal {
+        old: old + a.len() + k,
+        new: new + b.len() + k,
+    }));
+}
+
+/// Where a shortest edit script between `a` and `b` can be split in two, found
+/// by searching from both ends at once until the paths meet. `None` if they
+/// don't within [`MAX_COST`] differences.
+fn middle_snake<T: PartialEq>(a: &[T], b: &[T]) -> Option<(usize, usize)> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max_d = ((n + m + 1) / 2).min(MAX_COST as isize);
+    let offset = max_d + 1;
+    // Furthest x reached on each diagonal k = x - y, from the start and from the end.
+    let mut forward = vec![-1isize; 2 * offset as usize + 1];
+    let mut backward = forward.clone();
+    let delta = n - m;
+    let odd = delta % 2 != 0;
+    // Diagonals that ran off the edges and need no more searching.
+    let (mut f_start, mut f_end, mut b_start, mut b_end) = (0, 0, 0, 0);
+
+    forward[offset as usize + 1] = 0;
+    backward[offset as usize + 1] = 0;
+    for d in 0..max_d {
+        let mut k = -d + f_start;
+
+        while k <= d - f_end {
+            let i = (offset + k) as usize;
+            let mut x = if k == -d || (k != d && forward[i - 1] < forward[i + 1]) {
+                forward[i + 1]
+            } else {
+                forward[i - 1] + 1
+            };
+            let mut y = x - k;
+
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            forward[i] = x;
+            if x > n {
+                f_end += 2;
+            } else if y > m {
+                f_start += 2;
+            } else if odd {
+                let j = offset + delta - k;
+
+                if (0..backward.len() as isize).contains(&j)
+                    && backward[j as usize] != -1
+                    && x >= n - backward[j as usize]
+                {
+                    return Some((x as usize, y as usize));
+                }
+            }
+            k += 2;
+        }
+
+        let mut k = -d + b_start;
+
+        while k <= d - b_end {
+            let i = (offset + k) as usize;
+            let mut x = if k == -d || (k != d && backward[i - 1] < backward[i + 1]) {
+                backward[i + 1]
+            } else {
+                backward[i - 1] + 1
+            };
+            let mut y = x - k;
+
+            while x < n && y < m && a[(n - x - 1) as usize] == b[(m - y - 1) as usize] {
+                x += 1;
+                y += 1;
+            }
+            backward[i] = x;
+            if x > n {
+                b_end += 2;
+            } else if y > m {
+                b_start += 2;
+            } else if !odd {
+                let j = offset + delta - k;
+
+                if (0..forward.len() as isize).contains(&j) && forward[j as usize] != -1 {
+                    let fx = forward[j as usize];
+                    let fy = fx - (j - offset);
+
+                    if fx >= n - x {
+                        return Some((fx as usize, fy as usize));
+                    }
+                }
+            }
+            k += 2;
+        }
+    }
+
+    None
+}
+
+/// Splits a line into words, runs of whitespace and single punctuation characters,
+/// returned as byte ranges.
+fn tokenize(line: &str) -> Vec<std::ops::Range<usize>> {
+    let class = |c: char| {
+        if c.is_alphanumeric() || c == '_' {
+            0
+        } else if c.is_whitespace() {
+            1
+        } else {
+            2
+        }
+    };
+    let mut tokens: Vec<std::ops::Range<usize>> = Vec::new();
+    let mut prev = None;
+
+    for (i, c) in line.char_indices() {
+        let kind = class(c);
+
+        match tokens.last_mut() {
+            Some(last) if prev == Some(kind) && kind != 2 => last.end = i + c.len_utf8(),
+            _ => tokens.push(i..i + c.len_utf8()),
+        }
+        prev = Some(kind);
+    }
+
+    tokens
+}
+
+/// Byte ranges that differ between two versions of a line, as `(old, new)`.
+/// Adjacent changed tokens are merged into one range.
+pub fn word_diff(
+    old: &str,
+    new: &str,
+) -> (Vec<std::ops::Range<usize>>, Vec<std::ops::Range<usize>>) {
+    let old_tokens = tokenize(old);
+    let new_tokens = tokenize(new);
+    let old_words: Vec<&str> = old_tokens.iter().map(|r| &old[r.clone()]).collect();
+    let new_words: Vec<&str> = new_tokens.iter().map(|r| &new[r.clone()]).collect();
+
+    let push = |spans: &mut Vec<std::ops::Range<usize>>, range: &std::ops::Range<usize>| match spans
+        .last_mut()
+    {
+        Some(last) if last.end == range.start => last.end = range.end,
+        _ => spans.push(range.clone()),
+    };
+    let (mut old_spans, mut new_spans) = (Vec::new(), Vec::new());
+
+    for op in diff(&old_words, &new_words) {
+        match op {
+            DiffOp::Equal { .. } => {}
+            DiffOp::Delete { old } => push(&mut old_spans, &old_tokens[old]),
+            DiffOp::Insert { new } => push(&mut new_spans, &new_tokens[new]),
+        }
+    }
+
+    (old_spans, new_spans)
+}
+
+/// A row of a side-by-side view. A `None` side is padding opposite an added or
+/// removed line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffRow {
+    pub left: Option<usize>,
+    pub right: Option<usize>,
+    /// Changed byte ranges on the left line, for lines paired with a changed
+    /// counterpart. Empty otherwise.
+    pub left_spans: Vec<std::ops::Range<usize>>,
+    pub right_spans: Vec<std::ops::Range<usize>>,
+}
+
+impl DiffRow {
+    #[inline]
+    #[must_use]
+    pub fn is_equal(&self) -> bool {
+        self.left.is_some()
+            && self.right.is_some()
+            && self.left_spans.is_empty()
+            && self.right_spans.is_empty()
+    }
+}
+
+/// Aligns two files line by line. Within a changed hunk, removed and added lines
+/// are paired up in order and get word-level spans; the leftovers stand alone.
+pub fn side_by_side<S: AsRef<str> + PartialEq>(old: &[S], new: &[S]) -> Vec<DiffRow> {
+    let mut rows = Vec::new();
+    let mut deleted = Vec::new();
+    let mut inserted = Vec::new();
+
+    let flush = |rows: &mut Vec<DiffRow>, deleted: &mut Vec<usize>, inserted: &mut Vec<usize>| {
+        for k in 0..deleted.len().max(inserted.len()) {
+            let left = deleted.get(k).copied();
+            let right = inserted.get(k).copied();
+            let (left_spans, right_spans) = match (left, right) {
+                (Some(l), Some(r)) => word_diff(old[l].as_ref(), new[r].as_ref()),
+                // A line with no counterpart is changed as a whole; no spans needed.
+                _ => (Vec::new(), Vec::new()),
+            };
+
+            rows.push(DiffRow {
+                left,
+                right,
+                left_spans,
+                right_spans,
+            });
+        }
+        deleted.clear();
+        inserted.clear();
+    };
+
+    for op in diff(old, new) {
+        match op {
+            DiffOp::Delete { old } => deleted.push(old),
+            DiffOp::Insert { new } => inserted.push(new),
+            DiffOp::Equal { old, new } => {
+                flush(&mut rows, &mut deleted, &mut inserted);
+                rows.push(DiffRow {
+                    left: Some(old),
+                    right: Some(new),
+                    left_spans: Vec::new(),
+                    right_spans: Vec::new(),
+                });
+            }
+        }
+    }
+    flush(&mut rows, &mut deleted, &mut inserted);
+
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_diff() {
+        let old = ["a", "b", "c", "d"];
+        let new = ["a", "x", "c", "d", "e"];
+
+        assert_eq!(
+            diff(&old, &new),
+            vec![
+                DiffOp::Equal { old: 0, new: 0 },
+                DiffOp::Delete { old: 1 },
+                DiffOp::Insert { new: 1 },
+                DiffOp::Equal { old: 2, new: 2 },
+                DiffOp::Equal { old: 3, new: 3 },
+                DiffOp::Insert { new: 4 },
+            ]
+        );
+        assert!(diff::<&str>(&[], &[]).is_empty());
+    }
+
+    #[test]
+    fn test_diff_is_shortest() {
+        // Longest common subsequence, the slow way.
+        let lcs = |a: &[u8], b: &[u8]| {
+            let mut table = vec![vec![0; b.len() + 1]; a.len() + 1];
+
+            for i in (0..a.len()).rev() {
+                for j in (0..b.len()).rev() {
+                    table[i][j] = if a[i] == b[j] {
+                        table[i + 1][j + 1] + 1
+                    } else {
+                        table[i + 1][j].max(table[i][j + 1])
+                    };
+                }
+            }
+            table[0][0]
+        };
+        let mut seed = 7u32;
+        let mut random = |len: usize| -> Vec<u8> {
+            (0..len)
+                .map(|_| {
+                    seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                    b"abc"[(seed >> 16) as usize % 3]
+                })
+                .collect()
+        };
+
+        for round in 0..200 {
+            let (a, b) = (random(round % 17), random(round % 13));
+            let ops = diff(&a, &b);
+            let (mut i, mut j) = (0, 0);
+
+            for op in &ops {
+                match *op {
+                    DiffOp::Equal { old, new } => {
+                        assert_eq!((old, new), (i, j));
+                        assert_eq!(a[old], b[new]);
+                        (i, j) = (i + 1, j + 1);
+                    }
+                    DiffOp::Delete { old } => {
+                        assert_eq!(old, i);
+                        i += 1;
+                    }
+                    DiffOp::Insert { new } => {
+                        assert_eq!(new, j);
+                        j += 1;
+                    }
+                }
+            }
+            assert_eq!((i, j), (a.len(), b.len()));
+            let equal = ops
+                .iter()
+                .filter(|op| matches!(op, DiffOp::Equal { .. }))
+                .count();
+            assert_eq!(equal, lcs(&a, &b), "{a:?} {b:?}");
+        }
+
+        // Far apart, it gives up rather than search for long.
+        let (a, b) = (vec![0u32; 20_000], vec![1u32; 20_000]);
+        assert_eq!(diff(&a, &b).len(), 40_000);
+    }
+
+    #[test]
+    fn test_word_diff() {
+        let (old, new) = word_diff("the quick brown fox", "the slow brown fox!");
+
+        assert_eq!(old.len(), 1);
+        assert_eq!(old[0], 4..9);
+        assert_eq!(new, vec![4..8, 18..19]);
+    }
+
+    #[test]
+    fn test_side_by_side_pairs_changed_lines() {
+        let old = ["# Title", "one two", "gone", "end"];
+        let new = ["# Title", "one three", "end", "added"];
+        let rows = side_by_side(&old, &new);
+
+        let sides: Vec<_> = rows.iter().map(|r| (r.left, r.right)).collect();
+        assert_eq!(
+            sides,
+            vec![
+                (Some(0), Some(0)),
+                (Some(1), Some(1)),
+                (Some(2), None),
+                (Some(3), Some(2)),
+                (None, Some(3)),
+            ]
+        );
+        assert!(rows[0].is_equal());
+        assert_eq!(rows[1].left_spans.first(), Some(&(4..7)));
+        assert_eq!(rows[1].right_spans.first(), Some(&(4..9)));
+        assert!(!rows[2].is_equal());
+    }
+}
@@ -0,0 +1,227 @@
+/// A panel that can be docked around the editor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Panel {
+    Outline,
+    SyncStatus,
+    SmartFolders,
+    Upcoming,
+    Diagnostics,
+}
+
+impl Panel {
+    pub const ALL: [Panel; 5] = [
+        Panel::Outline,
+        Panel::SyncStatus,
+        Panel::SmartFolders,
+        Panel::Upcoming,
+        Panel::Diagnostics,
+    ];
+
+    /// The name it's saved under.
+    pub fn key(self) -> &'static str {
+        match self {
+            Panel::Outline => "outline",
+            Panel::SyncStatus => "sync",
+            Panel::SmartFolders => "smart-folders",
+            Panel::Upcoming => "upcoming",
+            Panel::Diagnostics => "diagnostics",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|panel| panel.key() == key)
+    }
+}
+
+/// Where a panel sits: in a column left or right of the editor, or in a strip
+/// below it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Dock {
+    Left,
+    Right,
+    Bottom,
+}
+
+impl Dock {
+    pub const ALL: [Dock; 3] = [Dock::Left, Dock::Right, Dock::Bottom];
+
+    pub fn key(self) -> &'static str {
+        match self {
+            Dock::Left => "left",
+            Dock::Right => "right",
+            Dock::Bottom => "bottom",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|dock| dock.key() == key)
+    }
+}
+
+/// One panel's place in a [`Layout`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Placement {
+    pub panel: Panel,
+    pub dock: Dock,
+    pub shown: bool,
+}
+
+/// Which panels are shown, where they're docked and in what order. Panels in the
+/// same dock share it evenly, in the order they're listed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    placements: Vec<Placement>,
+}
+
+impl Default for Layout {
+    /// Every panel hidden, stacked in the left column.
+    fn default() -> Self {
+        Self {
+            placements: Panel::ALL
+                .into_iter()
+                .map(|panel| Placement {
+                    panel,
+                    dock: Dock::Left,
+                    shown: false,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Layout {
+    fn placement(&mut self, panel: Panel) -> &mut Placement {
+        let at = self
+            .placements
+            .iter()
+            .position(|placement| placement.panel == panel)
+            .expect("every panel is placed");
+
+        &mut self.placements[at]
+    }
+
+    pub fn is_shown(&self, panel: Panel) -> bool {
+        self.placements
+            .iter()
+            .any(|placement| placement.panel == panel && placement.shown)
+    }
+
+    pub fn set_shown(&mut self, panel: Panel, shown: bool) {
+        self.placement(panel).shown = shown;
+    }
+
+    pub fn dock_of(&self, panel: Panel) -> Dock {
+        self.placements
+            .iter()
+            .find(|placement| placement.panel == panel)
+            .map_or(Dock::Left, |placement| placement.dock)
+    }
+
+    /// The panels shown in `dock`, first to last.
+    pub fn shown_in(&self, dock: Dock) -> Vec<Panel> {
+        self.placements
+            .iter()
+            .filter(|placement| placement.dock == dock && placement.shown)
+            .map(|placement| placement.panel)
+            .collect()
+    }
+
+    /// Docks `panel` in `dock`, before the panel shown `index`th there, or last.
+    pub fn move_panel(&mut self, panel: Panel, dock: Dock, index: usize) {
+        let mut moved = *self.placement(panel);
+        let before = self
+            .shown_in(dock)
+            .into_iter()
+            .filter(|&p| p != panel)
+            .nth(index);
+
+        self.placements.retain(|placement| placement.panel != panel);
+        moved.dock = dock;
+
+        let at = before
+            .and_then(|before| self.placements.iter().position(|p| p.panel == before))
+            .unwrap_or(self.placements.len());
+
+        self.placements.insert(at, moved);
+    }
+
+    /// `panel:dock:shown` for each panel in order, space separated, e.g.
+    /// `outline:left:1 upcoming:bottom:0`.
+    pub fn encode(&self) -> String {
+        self.placements
+            .iter()
+            .map(|placement| {
+                format!(
+                    "{}:{}:{}",
+                    placement.panel.key(),
+                    placement.dock.key(),
+                    u8::from(placement.shown)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Reads [`Layout::encode`]'s output. Unknown or repeated entries are skipped
+    /// and panels it leaves out keep their default place after the others.
+    pub fn parse(text: &str) -> Self {
+        let mut placements: Vec<Placement> = Vec::new();
+
+        for entry in text.split_whitespace() {
+            let mut fields = entry.split(':');
+            let (Some(panel), Some(dock), Some(shown)) = (
+                fields.next().and_then(Panel::from_key),
+                fields.next().and_then(Dock::from_key),
+                fields.next(),
+            ) else {
+                continue;
+            };
+
+            if placements.iter().all(|placement| placement.panel != panel) {
+                placements.push(Placement {
+                    panel,
+                    dock,
+                    shown: shown == "1",
+                });
+            }
+        }
+
+        for placement in Self::default().placements {
+            if placements.iter().all(|p| p.panel != placement.panel) {
+                placements.push(placement);
+            }
+        }
+
+        Self { placements }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_move_and_round_trip() {
+        let mut layout = Layout::default();
+
+        for panel in Panel::ALL {
+            layout.set_shown(panel, true);
+        }
+        layout.set_shown(Panel::SyncStatus, false);
+        layout.move_panel(Panel::Upcoming, Dock::Left, 0);
+        layout.move_panel(Panel::Outline, Dock::Right, 0);
+
+        assert_eq!(
+            layout.shown_in(Dock::Left),
+            vec![Panel::Upcoming, Panel::SmartFolders, Panel::Diagnostics]
+        );
+        assert_eq!(layout.shown_in(Dock::Right), vec![Panel::Outline]);
+        assert_eq!(layout.dock_of(Panel::Outline), Dock::Right);
+        assert_eq!(Layout::parse(&layout.encode()), layout);
+        assert_eq!(
+            Layout::parse("upcoming:bottom:1 bogus:left:1 upcoming:left:0").shown_in(Dock::Bottom),
+            vec![Panel::Upcoming]
+        );
+        assert!(!Layout::parse("").is_shown(Panel::Outline));
+    }
+}
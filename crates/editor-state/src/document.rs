@@ -1,8 +1,70 @@
+/// Replaces the text between `start` and `end` with `text`.
+/// Coordinates are expressed against the buffer *before* any edit of the batch is applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextEdit {
+    pub start: editor_core::cursor::Position,
+    pub end: editor_core::cursor::Position,
+    pub text: String,
+}
+
+impl TextEdit {
+    #[must_use]
+    pub fn new(
+        start: editor_core::cursor::Position,
+        end: editor_core::cursor::Position,
+        text: impl Into<String>,
+    ) -> Self {
+        Self {
+            start,
+            end,
+            text: text.into(),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct Document {
     pub text_buffer: editor_core::text::TextBuffer,
     pub history: editor_core::history::History,
     pub cursor: editor_core::cursor::Cursor,
+    /// Cleanup applied to the buffer right before it's written to disk.
+    pub save_transforms: crate::save_transforms::SaveTransforms,
+    /// Editor-wide defaults this document falls back to.
+    pub settings: crate::settings::EditorSettings,
+    /// Per-buffer overrides resolved from `.editorconfig` when the file was opened.
+    pub overrides: crate::editorconfig::Properties,
+    /// Heading index, kept up to date on every edit.
+    pub outline: crate::outline::Outline,
+    /// Blocks collapsed in the view, kept up to date on every edit.
+    pub folds: crate::folds::Folds,
+    /// Rows marked to come back to, kept up to date on every edit.
+    pub bookmarks: crate::bookmarks::Bookmarks,
+    /// Lines changed since the note was opened or last saved, kept up to date on
+    /// every edit.
+    pub changes: crate::changes::ChangedLines,
+    /// When each line was last edited, while recording is on. See
+    /// [`Document::record_line_times`].
+    pub line_times: Option<crate::line_times::LineTimes>,
+    /// Merge conflict blocks, kept up to date on every edit.
+    pub conflicts: Vec<crate::conflict::Conflict>,
+    /// Rows of conflict markers, which [`Document::conflicts`] are paired up from.
+    conflict_markers: crate::journal::RowIndex<char>,
+    /// Bumped whenever the contents change, so views can cache per-line layout.
+    pub revision: u64,
+    /// The latest edits by row and revision, see [`crate::journal`].
+    pub journal: crate::journal::LineJournal,
+    /// When set, every change made to the text here (edits, undo, redo, revert) is
+    /// appended in the order it happened, for replaying elsewhere. See [`crate::collab`].
+    pub edit_log: Option<Vec<editor_core::enums::EditAction>>,
+    /// Curls quotes and turns `--` and `...` into dashes and ellipses as they're
+    /// typed, see [`Document::type_text`].
+    pub smart_typography: bool,
+    /// What scoped commands such as sorting, case changes and replacing work
+    /// on, see [`crate::transform::Scope`].
+    pub scope: crate::transform::Scope,
+    /// The result offered for an expression just typed with a trailing `=`, and
+    /// where it would go. See [`Document::accept_calculation`].
+    pub calculation: Option<(editor_core::cursor::Position, String)>,
 
     /// Prevents undo/redo operations from being recorded as new edits
     is_recording: bool,
@@ -10,15 +72,37 @@ pub struct Document {
 
 impl Document {
     pub fn new(text_buffer: editor_core::text::TextBuffer) -> Self {
-        Self {
+        let outline = crate::outline::Outline::build(&text_buffer);
+        let changes = crate::changes::ChangedLines::new(text_buffer.line_count());
+
+        let mut doc = Self {
             text_buffer,
             history: editor_core::history::History {
                 undo_stack: Vec::new(),
                 redo_stack: Vec::new(),
             },
             cursor: editor_core::cursor::Cursor::default(),
+            save_transforms: crate::save_transforms::SaveTransforms::default(),
+            settings: crate::settings::EditorSettings::default(),
+            overrides: crate::editorconfig::Properties::default(),
+            outline,
+            folds: crate::folds::Folds::default(),
+            bookmarks: crate::bookmarks::Bookmarks::default(),
+            changes,
+            line_times: None,
+            conflicts: Vec::new(),
+            conflict_markers: crate::journal::RowIndex::default(),
+            revision: 0,
+            journal: crate::journal::LineJournal::default(),
+            edit_log: None,
+            smart_typography: false,
+            scope: crate::transform::Scope::default(),
+            calculation: None,
             is_recording: true,
-        }
+        };
+
+        doc.scan_conflicts();
+        doc
     }
 }
 
@@ -46,6 +130,20 @@ impl Document {
 
         let cursor_after = editor_core::cursor::Cursor::new(end_pos.row, end_pos.col);
 
+        self.lines_changed(range_start.row, range_end.row, end_pos.row);
+
+        if let Some(deleted_text) = &selection_text {
+            self.log_edit(editor_core::enums::EditAction::Delete {
+                pos: range_start,
+                end: range_end,
+                text: deleted_text.clone(),
+            });
+        }
+        self.log_edit(editor_core::enums::EditAction::Insert {
+            pos: range_start,
+            text: text.to_string(),
+        });
+
         // 3. Record to History
         if self.is_recording {
             if let Some(deleted_text) = selection_text {
@@ -88,19 +186,28 @@ impl Document {
         };
         let cursor_after = editor_core::cursor::Cursor::new(new_pos.row, new_pos.col);
 
+        // Determine the bounding box of what was actually removed.
+        // If it was a selection, we use the selection's range.
+        // If it was a single char delete/backspace, we use the before/after positions.
+        let (start, end) = if !cursor_before.no_selection() {
+            cursor_before.range()
+        } else if new_pos < cursor_before.head {
+            (new_pos, cursor_before.head)
+        } else {
+            (cursor_before.head, new_pos)
+        };
+
+        if !deleted_text.is_empty() {
+            self.lines_changed(start.row, end.row, start.row);
+            self.log_edit(editor_core::enums::EditAction::Delete {
+                pos: start,
+                end,
+                text: deleted_text.clone(),
+            });
+        }
+
         // 3. Record to History
         if self.is_recording && !deleted_text.is_empty() {
-            // Determine the bounding box of what was actually removed.
-            // If it was a selection, we use the selection's range.
-            // If it was a single char delete/backspace, we use the before/after positions.
-            let (start, end) = if !cursor_before.no_selection() {
-                cursor_before.range()
-            } else if new_pos < cursor_before.head {
-                (new_pos, cursor_before.head)
-            } else {
-                (cursor_before.head, new_pos)
-            };
-
             self.history
                 .record_delete(start, end, &deleted_text, cursor_before, cursor_after)
                 .expect("History batching failed");
@@ -108,348 +215,3816 @@ impl Document {
 
         self.cursor = cursor_after;
     }
-}
 
-impl Document {
-    pub fn undo(&mut self) {
-        if let Some(transaction) = self.history.undo() {
-            self.execute_transaction(transaction, true);
-        }
+    /// "Paste and Match Indentation": strips the clipboard's common indent and
+    /// re-indents every following line to the leading whitespace of the cursor's line.
+    pub fn paste_and_match_indent(&mut self, text: &str) {
+        let row = self.cursor.start().row;
+        let line = self.get_line_stripped(row).unwrap_or_default();
+        let indent = crate::indent::leading_whitespace(&line);
+        let adjusted = crate::indent::match_indentation(text, indent);
+
+        self.insert(&adjusted);
     }
 
-    pub fn redo(&mut self) {
-        if let Some(transaction) = self.history.redo() {
-            self.execute_transaction(transaction, false);
+    /// Turns the selection into a Markdown link to `url`, as one undo step, and puts
+    /// the cursor after it. Returns `false`, doing nothing, without a selection, or
+    /// if the selection is a URL itself, which pasting should just replace.
+    pub fn paste_link(&mut self, url: &str) -> bool {
+        let selected = self.get_selected_text();
+
+        if self.cursor.no_selection() || crate::clipboard::as_url(&selected).is_some() {
+            return false;
         }
-    }
 
-    /// Internal helper to play back a transaction without recording it.
-    fn execute_transaction(
-        &mut self,
-        transaction: editor_core::history::Transaction,
-        is_undo: bool,
-    ) {
-        self.is_recording = false;
+        let (start, end) = (self.cursor.start(), self.cursor.end());
 
-        // When undoing, we apply actions in reverse order (stack logic).
-        // When redoing, we apply them in the original forward order.
-        let actions: Vec<_> = if is_undo {
-            transaction.actions.iter().rev().collect()
-        } else {
-            transaction.actions.iter().collect()
-        };
+        self.apply_edits(vec![TextEdit::new(
+            start,
+            end,
+            crate::clipboard::markdown_link(&selected, url),
+        )]);
+        self.cursor =
+            editor_core::cursor::Cursor::new(self.cursor.end().row, self.cursor.end().col);
+        true
+    }
 
-        for action in actions {
-            match action {
-                editor_core::enums::EditAction::Insert { pos, text } => {
-                    if is_undo {
-                        // Undo Insert -> Delete the text we added
-                        let end_pos = self.calculate_end_position(*pos, text);
-                        let temp_cursor = editor_core::cursor::Cursor::new_selection(*pos, end_pos);
-                        println!(
-                            "{:#?} {:#?} {:#?} {}",
-                            end_pos,
-                            temp_cursor.head,
-                            temp_cursor.anchor,
-                            temp_cursor.no_selection()
-                        );
-                        let _ = self.text_buffer.delete_selection(&temp_cursor);
-                    } else {
-                        // Redo Insert -> Re-insert the text
-                        let temp_cursor = editor_core::cursor::Cursor::new(pos.row, pos.col);
-                        let _ = self.text_buffer.insert(&temp_cursor, text);
-                    }
-                }
-                editor_core::enums::EditAction::Delete {
-                    pos: start, text, ..
-                } => {
-                    if is_undo {
-                        // Undo Delete -> Put the deleted text back
-                        let temp_cursor = editor_core::cursor::Cursor::new(start.row, start.col);
-                        let _ = self.text_buffer.insert(&temp_cursor, text);
-                    } else {
-                        // Redo Delete -> Delete the text again
-                        // Note: EditAction::Delete stores start/end, so we use them
-                        let temp_cursor = editor_core::cursor::Cursor::new_selection(
-                            *start,
-                            self.calculate_end_position(*start, text),
-                        );
-                        let _ = self.text_buffer.delete_selection(&temp_cursor);
-                    }
-                }
-            }
+    /// Inserts text typed at the keyboard. With [`Document::smart_typography`] on,
+    /// a typed character is then substituted as a step of its own, so undo brings
+    /// back what was typed as is, see [`crate::typography::substitute`]. Code
+    /// blocks are left alone.
+    ///
+    /// Typing the `=` after an expression such as `12*14` offers its result, see
+    /// [`crate::calc::result_for`].
+    pub fn type_text(&mut self, text: &str) {
+        let start = self.cursor.start();
+
+        self.insert(text);
+
+        let mut chars = text.chars();
+        let (Some(typed), None) = (chars.next(), chars.next()) else {
+            return;
+        };
+        if self.outline.in_code(start.row) {
+            return;
         }
 
-        // Restore the appropriate cursor state
-        self.cursor = if is_undo {
-            transaction.cursor_before
-        } else {
-            transaction.cursor_after
+        let line = self.get_line_stripped(start.row).unwrap_or_default();
+
+        if typed == '=' {
+            let head = self.cursor.head;
+
+            self.calculation = crate::calc::result_for(&line[..head.col.min(line.len())])
+                .map(|result| (head, result));
+        }
+        if !self.smart_typography {
+            return;
+        }
+        let Some((back, with)) = crate::typography::substitute(&line[..start.col], typed) else {
+            return;
         };
 
-        self.is_recording = true;
+        self.apply_edits(vec![TextEdit::new(
+            editor_core::cursor::Position::new(start.row, start.col - back),
+            editor_core::cursor::Position::new(start.row, start.col + typed.len_utf8()),
+            with,
+        )]);
     }
 
-    /// Helper to find the 2D end position of a string starting at `start`.
-    fn calculate_end_position(
-        &self,
-        start: editor_core::cursor::Position,
-        text: &str,
-    ) -> editor_core::cursor::Position {
-        let mut row = start.row;
-        let mut col = start.col;
+    /// Inserts the result offered for the expression before the cursor. Returns
+    /// whether there was one to insert.
+    pub fn accept_calculation(&mut self) -> bool {
+        match self.calculation.take() {
+            Some((at, result)) if at == self.cursor.head && self.cursor.no_selection() => {
+                self.insert(&result);
+                true
+            }
+            _ => false,
+        }
+    }
 
-        let lines: Vec<&str> = text.split('\n').collect();
+    /// Works out the selected expression and puts ` = result` after it, or the
+    /// result after an expression ending in `=` right before the cursor.
+    ///
+    /// # Errors
+    ///
+    /// Returns why there is nothing to work out, or why it can't be.
+    pub fn insert_calculation(&mut self) -> Result<(), String> {
+        if !self.cursor.no_selection() {
+            let value = crate::calc::evaluate(&self.get_selected_text())?;
+            let end = self.cursor.end();
+
+            self.cursor = editor_core::cursor::Cursor::new(end.row, end.col);
+            self.insert(&format!(" = {}", crate::calc::format_number(value)));
+            return Ok(());
+        }
 
-        if lines.len() > 1 {
-            // We moved down lines. The new row is start.row + count of newlines.
-            row += lines.len() - 1;
-            // The column is simply the length of the very last segment.
-            col = lines.last().unwrap_or(&"").len();
-        } else {
-            // We stayed on the same line. Just add the length to the current column.
-            col += text.len();
+        let head = self.cursor.head;
+        let line = self.get_line_stripped(head.row).unwrap_or_default();
+        let before = line[..head.col.min(line.len())].trim_end();
+
+        match crate::calc::result_for(before) {
+            Some(result) => {
+                self.insert(&result);
+                Ok(())
+            }
+            None => Err("Select an expression, or put the cursor after one ending in =".into()),
         }
+    }
 
-        editor_core::cursor::Position::new(row, col)
+    /// Breaks the line at the cursor and carries the current line's indentation over,
+    /// rewritten in the buffer's indentation style.
+    pub fn insert_newline(&mut self) {
+        let start = self.cursor.start();
+        let line = self.get_line_stripped(start.row).unwrap_or_default();
+        let whitespace = crate::indent::leading_whitespace(&line);
+        // Splitting inside the indentation only carries what's left of the cursor.
+        let whitespace = &whitespace[..whitespace.len().min(start.col)];
+        let columns = crate::indent::indent_columns(whitespace, self.tab_width());
+        let indent = crate::indent::build_indent(columns, self.indent_style(), self.tab_width());
+        let text = format!("{}{indent}", self.text_buffer.line_ending.as_str());
+
+        self.insert(&text);
     }
-}
 
-impl Document {
-    pub fn get_selected_text(&self) -> String {
-        let (start, end) = self.cursor.range();
-        let mut out = String::new();
+    /// What Enter does: continues or ends the list item the cursor is on by the
+    /// `settings.autolist` rules, as one undo step, or else breaks the line like
+    /// [`Document::insert_newline`].
+    pub fn press_enter(&mut self) {
+        let start = self.cursor.start();
+        let line = self.get_line_stripped(start.row).unwrap_or_default();
+        let enter = self
+            .cursor
+            .no_selection()
+            .then(|| self.settings.autolist.on_enter(&line, start.col))
+            .flatten();
+
+        match enter {
+            Some(crate::lists::Enter::Continue(marker))
+                if crate::lists::ordered_item(&line).is_some() =>
+            {
+                self.insert_ordered_item(marker);
+            }
+            Some(crate::lists::Enter::Continue(marker)) => {
+                let text = format!("{}{marker}", self.text_buffer.line_ending.as_str());
 
-        for row in start.row..=end.row {
-            if let Some(line) = self.get_line_stripped(row) {
-                let from = if row == start.row { start.col } else { 0 };
-                let to = if row == end.row { end.col } else { line.len() };
+                self.insert(&text);
+            }
+            Some(crate::lists::Enter::End) => self.apply_edits(vec![TextEdit::new(
+                editor_core::cursor::Position::new(start.row, 0),
+                editor_core::cursor::Position::new(start.row, line.len()),
+                "",
+            )]),
+            None => self.insert_newline(),
+        }
+    }
 
-                out.push_str(&line[from..to]);
+    /// Indents the list item the cursor is on by one level if the
+    /// `settings.autolist` rules say Tab should there, see
+    /// [`crate::lists::AutoList::indents_on_tab`]. Returns whether it did.
+    pub fn indent_list_item(&mut self) -> bool {
+        let head = self.cursor.head;
+        let line = self.get_line_stripped(head.row).unwrap_or_default();
 
-                if row != end.row {
-                    out.push_str(self.text_buffer.line_ending.as_str());
-                }
-            }
+        if !self.cursor.no_selection() || !self.settings.autolist.indents_on_tab(&line, head.col) {
+            return false;
         }
 
-        out
+        let indent = self.indent_unit();
+
+        self.apply_edits(vec![TextEdit::new(
+            editor_core::cursor::Position::new(head.row, 0),
+            editor_core::cursor::Position::new(head.row, 0),
+            indent,
+        )]);
+        true
     }
 
-    #[inline]
-    pub fn get_line_count(&self) -> usize {
-        self.text_buffer.line_count()
+    /// Rows of the paragraph containing `row`: the surrounding run of non-blank lines.
+    /// A blank line is a paragraph of its own.
+    pub fn paragraph_at(&self, row: usize) -> std::ops::Range<usize> {
+        let is_blank = |r: usize| {
+            self.get_line_stripped(r)
+                .is_none_or(|line| line.trim().is_empty())
+        };
+
+        if is_blank(row) {
+            return row..row + 1;
+        }
+
+        let start = (0..row).rev().find(|&r| is_blank(r)).map_or(0, |r| r + 1);
+        let end = (row + 1..self.get_line_count())
+            .find(|&r| is_blank(r))
+            .unwrap_or(self.get_line_count());
+
+        start..end
     }
 
-    #[inline]
-    pub fn get_line_len_at(&self, line_idx: usize) -> Option<u64> {
-        self.text_buffer.get_line_len_at(line_idx)
+    /// Selects rows `anchor` through `head` whole, with the line ending after
+    /// the last one, leaving the cursor on `head`'s side, as a gutter click does.
+    pub fn select_lines(&mut self, anchor: usize, head: usize) {
+        let last = self.get_line_count().saturating_sub(1);
+        let (anchor, head) = (anchor.min(last), head.min(last));
+        let after = |row: usize| {
+            if row < last {
+                editor_core::cursor::Position::new(row + 1, 0)
+            } else {
+                editor_core::cursor::Position::new(
+                    row,
+                    self.get_line_stripped(row).map_or(0, |line| line.len()),
+                )
+            }
+        };
+        let (anchor, head) = if head >= anchor {
+            (editor_core::cursor::Position::new(anchor, 0), after(head))
+        } else {
+            (after(anchor), editor_core::cursor::Position::new(head, 0))
+        };
+
+        self.cursor = editor_core::cursor::Cursor::new_selection(anchor, head);
     }
 
-    pub fn get_visible_line_len_at(&self, line_idx: usize) -> Option<u64> {
-        Some(
-            self.get_line_stripped(line_idx)?
-                .trim_end_matches(&['\n', '\r'][..])
-                .chars()
-                .count() as u64,
-        )
+    /// Rows covered by the selection, or just the cursor's row.
+    /// A selection ending at column 0 doesn't really include that last line.
+    pub fn selected_rows(&self) -> std::ops::Range<usize> {
+        let start = self.cursor.start();
+        let end = self.cursor.end();
+        let last = if end.col == 0 && end.row > start.row {
+            end.row - 1
+        } else {
+            end.row
+        };
+
+        start.row..last + 1
     }
 
-    #[inline]
-    pub fn get_line(&self, idx: usize) -> Option<String> {
-        self.text_buffer.get_line(idx)
+    /// Rows `scope` covers: those touched by the selection (or the cursor's
+    /// row), or all of them.
+    pub fn scope_rows(&self, scope: crate::transform::Scope) -> std::ops::Range<usize> {
+        match scope {
+            crate::transform::Scope::Selection => self.selected_rows(),
+            crate::transform::Scope::Document => 0..self.get_line_count(),
+        }
     }
 
-    #[inline]
-    pub fn get_line_stripped(&self, idx: usize) -> Option<String> {
-        self.text_buffer.get_line_stripped(idx)
+    /// Where `scope` starts and ends: the selection, or the whole document.
+    pub fn scope_range(
+        &self,
+        scope: crate::transform::Scope,
+    ) -> (editor_core::cursor::Position, editor_core::cursor::Position) {
+        match scope {
+            crate::transform::Scope::Selection => self.cursor.range(),
+            crate::transform::Scope::Document => {
+                let last = self.get_line_count().saturating_sub(1);
+                let len = self.get_line_stripped(last).map_or(0, |line| line.len());
+
+                (
+                    editor_core::cursor::Position::new(0, 0),
+                    editor_core::cursor::Position::new(last, len),
+                )
+            }
+        }
     }
 
-    pub fn open_file<P: AsRef<std::path::Path>>(
-        &mut self,
-        path: P,
-    ) -> editor_core::errors::TextBufferResult<()> {
-        self.text_buffer.open_from(path)
+    /// Normalizes the leading whitespace of every line in `scope` as one undo
+    /// step. The selection stays on its text.
+    pub fn reindent(&mut self, scope: crate::transform::Scope) {
+        self.reindent_rows(self.scope_rows(scope));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use editor_core::cursor::{Cursor, Position};
-    use editor_core::text::TextBuffer;
+    fn reindent_rows(&mut self, rows: std::ops::Range<usize>) {
+        let lines = self.get_lines_stripped(0..rows.end.min(self.get_line_count()));
+        let edits = crate::indent::reindent(
+            &lines,
+            rows,
+            self.indent_style(),
+            self.indent_size(),
+            self.tab_width(),
+        )
+        .into_iter()
+        .map(|(row, whitespace)| {
+            let old = crate::indent::leading_whitespace(&lines[row]).len();
+            TextEdit::new(
+                editor_core::cursor::Position::new(row, 0),
+                editor_core::cursor::Position::new(row, old),
+                whitespace,
+            )
+        })
+        .collect();
+
+        self.apply_edits(edits);
+    }
 
-    fn setup() -> Document {
-        Document::new(TextBuffer::new().unwrap())
+    /// Comments or uncomments every line touched by the selection as one undo step,
+    /// using the markers for the buffer's file type.
+    pub fn toggle_comment(&mut self) {
+        let rows = self.selected_rows();
+        let lines = self.get_lines_stripped(rows.clone());
+        let style = self.file_type().comment;
+        let changes = crate::comment::toggle_comments(&lines, style)
+            .into_iter()
+            .map(|(i, text)| (rows.start + i, text))
+            .collect();
+
+        self.replace_lines(changes);
     }
 
-    #[test]
-    fn test_newline_insertion_math() {
-        let mut doc = setup();
+    /// Replaces the text in `scope` with `f` applied to it, as one undo step. In
+    /// the selection (or the cursor's line) the result is left selected, the
+    /// same way round; over the whole document the cursor stays where it was.
+    pub fn transform_text(
+        &mut self,
+        scope: crate::transform::Scope,
+        f: impl FnOnce(&str) -> String,
+    ) {
+        let before = self.cursor;
+        let (start, end) =
+            if scope == crate::transform::Scope::Selection && self.cursor.no_selection() {
+                let row = self.cursor.head.row;
+                let len = self.get_line_stripped(row).map_or(0, |line| line.len());
+                (
+                    editor_core::cursor::Position::new(row, 0),
+                    editor_core::cursor::Position::new(row, len),
+                )
+            } else {
+                self.scope_range(scope)
+            };
+        let old = self
+            .text_buffer
+            .get_cursor_selection(&editor_core::cursor::Cursor::new_selection(start, end))
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+        let new = f(&old);
+
+        if new == old {
+            return;
+        }
 
-        // Scenario: Pressing Enter on an empty line
-        doc.insert("\n");
-        assert_eq!(
-            doc.cursor.head,
-            Position::new(1, 0),
-            "Cursor should be at start of line 2"
-        );
+        let new_end = self.calculate_end_position(start, &new);
 
-        // Scenario: Inserting text then Enter
-        doc.insert("Hi\n");
-        assert_eq!(
-            doc.cursor.head,
-            Position::new(2, 0),
-            "Cursor should be at start of line 3"
-        );
+        self.apply_edits(vec![TextEdit::new(start, end, new)]);
+        self.cursor = match scope {
+            crate::transform::Scope::Selection if before.anchor > before.head => {
+                editor_core::cursor::Cursor::new_selection(new_end, start)
+            }
+            crate::transform::Scope::Selection => {
+                editor_core::cursor::Cursor::new_selection(start, new_end)
+            }
+            crate::transform::Scope::Document => editor_core::cursor::Cursor::new_selection(
+                self.clamp_position(before.anchor),
+                self.clamp_position(before.head),
+            ),
+        };
     }
 
-    #[test]
-    fn test_undo_redo_newline_boundaries_fixed() {
-        let mut doc = setup();
+    /// Replaces the lines touched by the selection with `f` applied to them, as one
+    /// undo step, and selects the resulting block.
+    pub fn transform_lines(&mut self, f: impl FnOnce(Vec<String>) -> Vec<String>) {
+        let rows = self.selected_rows();
 
-        println!("BEFORE LINE 1: {:#?}", doc);
-        // 1. First Insert
-        doc.insert("Line1");
-        // Manually break batching if your History allows it,
-        // or just accept that "Line1\nLine2" might be one transaction.
+        self.transform_rows(rows, f);
+    }
 
-        println!("BEFORE LINE 2: {:#?}", doc);
-        // 2. Second Insert starting with newline
-        doc.insert("\nLine2");
+    /// Duplicates the selection right after itself, or the cursor's line below itself,
+    /// as one undo step. The cursor moves onto the copy.
+    pub fn duplicate(&mut self) {
+        if self.cursor.no_selection() {
+            let head = self.cursor.head;
+            let line = self.get_line_stripped(head.row).unwrap_or_default();
+            let end = editor_core::cursor::Position::new(head.row, line.len());
+            let text = format!("{}{line}", self.text_buffer.line_ending.as_str());
+
+            self.apply_edits(vec![TextEdit::new(end, end, text)]);
+            self.cursor = editor_core::cursor::Cursor::new(head.row + 1, head.col);
+            return;
+        }
 
-        println!("BEFORE UNDO: {:#?}", doc);
-        // If batched, one undo goes to (0,0). If not, it goes to (0,5).
-        doc.undo();
+        let end = self.cursor.end();
+        let text = self.get_selected_text();
+        let copy_end = self.calculate_end_position(end, &text);
 
-        let current_line = doc.text_buffer.get_line(0);
-        assert_eq!(current_line.unwrap(), "Line1");
-        assert_eq!(doc.cursor.head, Position::new(0, 5));
+        self.apply_edits(vec![TextEdit::new(end, end, text)]);
+        self.cursor = editor_core::cursor::Cursor::new_selection(end, copy_end);
     }
 
-    #[test]
-    fn test_get_line_stripped_edge_cases() {
-        let mut doc = setup();
+    /// Fills the cursor's line with spaces up to `col` and puts the cursor there,
+    /// for text typed in virtual space past the line's end. Returns whether the
+    /// line was short of `col`; nothing happens with a selection.
+    pub fn pad_line_to(&mut self, col: usize) -> bool {
+        let row = self.cursor.head.row;
+        let len = self.get_line_stripped(row).map_or(0, |line| line.len());
 
-        // Case 1: Empty line
-        doc.insert("\n");
-        assert_eq!(doc.text_buffer.get_line_stripped(0).unwrap(), "");
+        if !self.cursor.no_selection() || col <= len {
+            return false;
+        }
 
-        // Case 2: Text without newline
-        doc.insert("Hello");
-        println!("{:#?}", doc);
-        assert_eq!(doc.text_buffer.get_line_stripped(1).unwrap(), "Hello");
+        let end = editor_core::cursor::Position::new(row, len);
 
-        // Case 3: Mixed content
-        doc.undo();
-        doc.undo(); // Clear
-        doc.insert("First\nSecond\nThird");
-        assert_eq!(doc.text_buffer.get_line_stripped(0).unwrap(), "First");
-        assert_eq!(doc.text_buffer.get_line_stripped(1).unwrap(), "Second");
-        assert_eq!(doc.text_buffer.get_line_stripped(2).unwrap(), "Third");
+        self.apply_edits(vec![TextEdit::new(end, end, " ".repeat(col - len))]);
+        self.cursor = editor_core::cursor::Cursor::new(row, col);
+        true
     }
 
-    #[test]
-    fn test_backspace_at_line_boundary() {
-        let mut doc = setup();
-        doc.insert("ABC\nDEF");
-        doc.cursor = Cursor::new(1, 0); // Cursor at start of "DEF"
+    /// Swaps the characters either side of the cursor and steps past them; at the
+    /// end of a line, the last two. Returns whether anything was swapped.
+    pub fn transpose_chars(&mut self) -> bool {
+        if !self.cursor.no_selection() {
+            return false;
+        }
 
-        // Backspace should delete the '\n'
+        let head = self.cursor.head;
+        let line = self.get_line_stripped(head.row).unwrap_or_default();
+        let col = head.col.min(line.len());
+        // The character after the cursor, or the one before it at the line's end.
+        let second = line[col..]
+            .chars()
+            .next()
+            .map(|c| (col, c))
+            .or_else(|| line[..col].char_indices().next_back());
+        let Some((second_at, second)) = second else {
+            return false;
+        };
+        let Some((first_at, first)) = line[..second_at].char_indices().next_back() else {
+            return false;
+        };
+        let end = second_at + second.len_utf8();
+
+        self.apply_edits(vec![TextEdit::new(
+            editor_core::cursor::Position::new(head.row, first_at),
+            editor_core::cursor::Position::new(head.row, end),
+            format!("{second}{first}"),
+        )]);
+        self.cursor = editor_core::cursor::Cursor::new(head.row, end);
+        true
+    }
+
+    /// Puts `text` in place of the selection, left selected, in one undo step.
+    /// Returns the text it replaced, `None` with nothing selected.
+    pub fn swap_selection(&mut self, text: &str) -> Option<String> {
+        if self.cursor.no_selection() {
+            return None;
+        }
+
+        let selected = self.get_selected_text();
+        let (start, end) = self.cursor.range();
+        let text_end = self.calculate_end_position(start, text);
+
+        self.apply_edits(vec![TextEdit::new(start, end, text)]);
+        self.cursor = editor_core::cursor::Cursor::new_selection(start, text_end);
+        Some(selected)
+    }
+
+    /// Moves the cursor to the other end of the selection, keeping it selected.
+    pub fn swap_anchor_and_head(&mut self) {
+        self.cursor =
+            editor_core::cursor::Cursor::new_selection(self.cursor.head, self.cursor.anchor);
+    }
+
+    /// Sorts the lines in `scope`. In the selection the sorted lines are left
+    /// selected, the same way round; with nothing selected, or over the whole
+    /// document, all lines are sorted and the cursor stays where it was.
+    pub fn sort_lines(
+        &mut self,
+        options: &crate::transform::SortOptions,
+        scope: crate::transform::Scope,
+    ) {
+        let before = self.cursor;
+        let whole = scope == crate::transform::Scope::Document || before.no_selection();
+        let rows = if whole {
+            0..self.get_line_count()
+        } else {
+            self.selected_rows()
+        };
+        let revision = self.revision;
+
+        self.transform_rows(rows, |lines| crate::transform::sort_lines(lines, options));
+        if self.revision == revision {
+            return;
+        }
+        if whole {
+            self.cursor = editor_core::cursor::Cursor::new_selection(
+                self.clamp_position(before.anchor),
+                self.clamp_position(before.head),
+            );
+        } else if before.anchor > before.head {
+            self.cursor =
+                editor_core::cursor::Cursor::new_selection(self.cursor.head, self.cursor.anchor);
+        }
+    }
+
+    /// Joins the selected lines into one, or the cursor's line with the next one.
+    pub fn join_lines(&mut self) {
+        let mut rows = self.selected_rows();
+
+        if rows.len() == 1 && rows.end < self.get_line_count() {
+            rows.end += 1;
+        }
+
+        self.transform_rows(rows, |lines| vec![crate::transform::join_lines(&lines)]);
+    }
+
+    fn transform_rows(
+        &mut self,
+        rows: std::ops::Range<usize>,
+        f: impl FnOnce(Vec<String>) -> Vec<String>,
+    ) {
+        let lines = self.get_lines_stripped(rows.clone());
+        let Some(last_len) = lines.last().map(String::len) else {
+            return;
+        };
+        let new_lines = f(lines.clone());
+
+        if new_lines == lines {
+            return;
+        }
+
+        let start = editor_core::cursor::Position::new(rows.start, 0);
+        let end = editor_core::cursor::Position::new(rows.end - 1, last_len);
+        let text = new_lines.join(self.text_buffer.line_ending.as_str());
+        let new_end = self.calculate_end_position(start, &text);
+
+        self.apply_edits(vec![TextEdit::new(start, end, text)]);
+        self.cursor = editor_core::cursor::Cursor::new_selection(start, new_end);
+    }
+
+    /// Renumbers the ordered lists in the selected lines, or in the whole note when
+    /// nothing is selected, as one undo step. See [`crate::lists::renumber`].
+    pub fn renumber_lists(&mut self) {
+        let rows = if self.cursor.no_selection() {
+            0..self.get_line_count()
+        } else {
+            self.selected_rows()
+        };
+        let lines = self.get_lines_stripped(rows.clone());
+        let changes = crate::lists::renumber(&lines)
+            .into_iter()
+            .zip(lines)
+            .zip(rows)
+            .filter(|((new, old), _)| new != old)
+            .map(|((new, _), row)| (row, new))
+            .collect();
+
+        self.replace_lines(changes);
+    }
+
+    /// The edit bringing the table of contents between [`crate::toc::START_MARKER`]
+    /// and [`crate::toc::END_MARKER`] up to date with the headings below it, or
+    /// `None` if there's no table or it's current.
+    pub fn toc_edit(&self) -> Option<TextEdit> {
+        let lines = self.get_lines_stripped(0..self.get_line_count());
+        let (start, end) = crate::toc::find(&lines)?;
+        let toc = crate::toc::generate(&self.outline.headings, end + 1);
+
+        if lines[start + 1..end] == toc[..] {
+            return None;
+        }
+
+        let eol = self.text_buffer.line_ending.as_str();
+
+        Some(TextEdit::new(
+            editor_core::cursor::Position::new(start + 1, 0),
+            editor_core::cursor::Position::new(end, 0),
+            toc.iter()
+                .map(|line| format!("{line}{eol}"))
+                .collect::<String>(),
+        ))
+    }
+
+    /// Brings the table of contents up to date, or inserts one listing the
+    /// headings below the cursor's line, between markers, above that line. One
+    /// undo step either way. Returns whether anything changed.
+    pub fn update_toc(&mut self) -> bool {
+        let lines = self.get_lines_stripped(0..self.get_line_count());
+
+        if crate::toc::find(&lines).is_some() {
+            let Some(edit) = self.toc_edit() else {
+                return false;
+            };
+
+            self.apply_edits(vec![edit]);
+            return true;
+        }
+
+        let row = self.cursor.head.row;
+        let eol = self.text_buffer.line_ending.as_str();
+        let text: String = std::iter::once(crate::toc::START_MARKER.to_string())
+            .chain(crate::toc::generate(&self.outline.headings, row))
+            .chain([crate::toc::END_MARKER.to_string(), String::new()])
+            .map(|line| format!("{line}{eol}"))
+            .collect();
+        let at = editor_core::cursor::Position::new(row, 0);
+
+        self.apply_edits(vec![TextEdit::new(at, at, text)]);
+        true
+    }
+
+    /// Promotes (`delta` -1) or demotes (+1) the heading of the section the cursor
+    /// is in, along with all its subheadings, as one undo step. Returns `false`,
+    /// doing nothing, outside a section or if a level would leave 1 to 6.
+    pub fn change_section_level(&mut self, delta: i8) -> bool {
+        let Some(index) = self.outline.section_at(self.cursor.head.row) else {
+            return false;
+        };
+        let rows = self.outline.section_rows(index, self.get_line_count());
+        let mut changes = Vec::new();
+
+        for heading in self
+            .outline
+            .headings
+            .iter()
+            .filter(|heading| rows.contains(&heading.row))
+        {
+            let Some(level) = heading
+                .level
+                .checked_add_signed(delta)
+                .filter(|level| (1..=6).contains(level))
+            else {
+                return false;
+            };
+            let line = self.get_line_stripped(heading.row).unwrap_or_default();
+
+            if let Some(line) = crate::outline::with_level(&line, level) {
+                changes.push((heading.row, line));
+            }
+        }
+
+        self.replace_lines(changes);
+        true
+    }
+
+    /// Swaps the section the cursor is in, subsections included, with the one
+    /// before or after it at the same level, as one undo step. Blank lines
+    /// between sections stay where they are and the cursor moves along with its
+    /// section. Returns `false` if there's no such section.
+    pub fn move_section(&mut self, down: bool) -> bool {
+        let head = self.cursor.head;
+        let Some(index) = self.outline.section_at(head.row) else {
+            return false;
+        };
+        let Some(other) = self.outline.sibling_of(index, down) else {
+            return false;
+        };
+        let count = self.get_line_count();
+        let (first, second) = if down { (index, other) } else { (other, index) };
+        let upper = self.outline.section_rows(first, count);
+        let lower = self.outline.section_rows(second, count);
+        let moving = self.outline.section_rows(index, count);
+        let split = |lines: Vec<String>| {
+            let content = lines.len()
+                - lines
+                    .iter()
+                    .rev()
+                    .take_while(|line| line.trim().is_empty())
+                    .count();
+            let mut content_lines = lines;
+            let gap = content_lines.split_off(content);
+
+            (content_lines, gap)
+        };
+        let (upper_text, upper_gap) = split(self.get_lines_stripped(upper.clone()));
+        let (lower_text, lower_gap) = split(self.get_lines_stripped(lower.clone()));
+        let new_start = if down {
+            upper.start + lower_text.len() + upper_gap.len()
+        } else {
+            upper.start
+        };
+        let shift = |pos: editor_core::cursor::Position| {
+            editor_core::cursor::Position::new(pos.row - moving.start + new_start, pos.col)
+        };
+        let anchor = self.cursor.anchor;
+
+        self.transform_rows(upper.start..lower.end, |_| {
+            [lower_text, upper_gap, upper_text, lower_gap].concat()
+        });
+        let (anchor, head) = if moving.contains(&anchor.row) {
+            (shift(anchor), shift(head))
+        } else {
+            (shift(head), shift(head))
+        };
+
+        self.cursor = editor_core::cursor::Cursor::new_selection(
+            self.clamp_position(anchor),
+            self.clamp_position(head),
+        );
+        true
+    }
+
+    /// Moves the section the cursor is in, subsections included, to the end of
+    /// the note at `other`, after a blank line, and takes it out of this one as
+    /// one undo step.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the cursor isn't in a section, or `other` is this note
+    /// or can't be read or written.
+    pub fn move_section_to(&mut self, other: &std::path::Path) -> std::io::Result<()> {
+        let Some(index) = self.outline.section_at(self.cursor.head.row) else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "The cursor isn't under a heading",
+            ));
+        };
+
+        if self.text_buffer.path() == Some(other) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "The section is already in this note",
+            ));
+        }
+
+        let count = self.get_line_count();
+        let rows = self.outline.section_rows(index, count);
+        let mut lines = self.get_lines_stripped(rows.clone());
+
+        while lines.last().is_some_and(|line| line.trim().is_empty()) {
+            lines.pop();
+        }
+
+        let target = std::fs::read_to_string(other)?;
+        let eol = if target.contains("\r\n") {
+            "\r\n"
+        } else {
+            "\n"
+        };
+        let kept = target.trim_end();
+        let mut batch = crate::workspace::BatchSave::default();
+
+        batch.write(
+            other,
+            format!(
+                "{kept}{}{}{eol}",
+                if kept.is_empty() {
+                    String::new()
+                } else {
+                    eol.repeat(2)
+                },
+                lines.join(eol)
+            ),
+        );
+        batch.commit()?;
+
+        // The line break before the section goes when it's the last one.
+        let (start, end) = if rows.end < count {
+            (
+                editor_core::cursor::Position::new(rows.start, 0),
+                editor_core::cursor::Position::new(rows.end, 0),
+            )
+        } else {
+            let last = self.get_line_stripped(count - 1).unwrap_or_default();
+            let start = match rows.start.checked_sub(1) {
+                Some(row) => editor_core::cursor::Position::new(
+                    row,
+                    self.get_line_stripped(row).unwrap_or_default().len(),
+                ),
+                None => editor_core::cursor::Position::new(0, 0),
+            };
+
+            (
+                start,
+                editor_core::cursor::Position::new(count - 1, last.len()),
+            )
+        };
+
+        self.apply_edits(vec![TextEdit::new(start, end, "")]);
+        Ok(())
+    }
+
+    /// Starts the next item of the ordered list the cursor is in, numbered one up
+    /// from the cursor's item, and renumbers the items after it to match, as one
+    /// undo step. Returns `false`, doing nothing, off an ordered list item.
+    pub fn insert_numbered_item(&mut self) -> bool {
+        let Some(marker) = self
+            .get_line_stripped(self.cursor.end().row)
+            .and_then(|line| crate::lists::next_marker(&line))
+        else {
+            return false;
+        };
+
+        self.insert_ordered_item(marker);
+        true
+    }
+
+    /// Breaks the line at the cursor, on an ordered list item, starting the next
+    /// item with `marker`, and renumbers the items after it to match.
+    fn insert_ordered_item(&mut self, marker: String) {
+        let head = self.cursor.end();
+        let line = self.get_line_stripped(head.row).unwrap_or_default();
+        let start = self.cursor.start();
+        let col = head.col.min(line.len());
+        let mut lines = self.get_lines_stripped(head.row..self.get_line_count());
+
+        lines.truncate(crate::lists::list_len(&lines));
+        // The items below are renumbered as if the new one were already there.
+        lines[0] = format!("{marker}{}", &line[col..]);
+        let renumbered =
+            crate::lists::renumber(&[vec![line[..col].to_string()], lines.clone()].concat());
+        let mut edits: Vec<TextEdit> = renumbered[2..]
+            .iter()
+            .zip(&lines[1..])
+            .zip(head.row + 1..)
+            .filter(|((new, old), _)| new != old)
+            .map(|((new, old), row)| {
+                TextEdit::new(
+                    editor_core::cursor::Position::new(row, 0),
+                    editor_core::cursor::Position::new(row, old.len()),
+                    new.as_str(),
+                )
+            })
+            .collect();
+
+        edits.push(TextEdit::new(
+            start,
+            head,
+            format!("{}{marker}", self.text_buffer.line_ending.as_str()),
+        ));
+        self.cursor = editor_core::cursor::Cursor::new(start.row, start.col);
+        self.apply_edits(edits);
+        self.cursor = editor_core::cursor::Cursor::new(start.row + 1, marker.len());
+    }
+
+    /// Replaces the whole note with `lines` as one undo step, e.g. with the result
+    /// of a merge.
+    pub fn replace_all_lines(&mut self, lines: Vec<String>) {
+        self.transform_rows(0..self.get_line_count(), |_| lines);
+        self.cursor = editor_core::cursor::Cursor::new(0, 0);
+    }
+
+    /// The blocks of this document that can fold, see [`crate::folds::regions`].
+    pub fn fold_regions(&self) -> Vec<crate::folds::Fold> {
+        crate::folds::regions(
+            &self.get_lines_stripped(0..self.get_line_count()),
+            crate::folds::Structure::of(self.text_buffer.path()),
+        )
+    }
+
+    /// Expands the fold starting at `row`, or else collapses the innermost block
+    /// holding it. Returns whether anything changed.
+    pub fn toggle_fold(&mut self, row: usize) -> bool {
+        if self.folds.unfold_at(row) {
+            return true;
+        }
+
+        let Some(fold) = self
+            .fold_regions()
+            .into_iter()
+            .filter(|fold| fold.start <= row && row <= fold.end)
+            .max_by_key(|fold| fold.start)
+        else {
+            return false;
+        };
+
+        self.folds.fold(fold);
+        if fold.hides(self.cursor.head.row) {
+            self.cursor = editor_core::cursor::Cursor::new(fold.start, 0);
+        }
+        true
+    }
+
+    /// The collapsed folds, keyed to be found again after the note changed, see
+    /// [`crate::folds::FoldKey`].
+    pub fn fold_keys(&self) -> Vec<crate::folds::FoldKey> {
+        crate::folds::keys(
+            &self.get_lines_stripped(0..self.get_line_count()),
+            self.folds.folded(),
+        )
+    }
+
+    /// Collapses the blocks `keys` name, as [`Document::fold_keys`] gave them, and
+    /// expands the rest. The cursor's row stays in view.
+    pub fn restore_folds(&mut self, keys: &[crate::folds::FoldKey]) {
+        let lines = self.get_lines_stripped(0..self.get_line_count());
+        let regions =
+            crate::folds::regions(&lines, crate::folds::Structure::of(self.text_buffer.path()));
+
+        self.folds.clear();
+        for fold in crate::folds::find(&lines, &regions, keys) {
+            self.folds.fold(fold);
+        }
+        self.folds.reveal(self.cursor.head.row);
+    }
+
+    /// Collapses every block, or expands them all with `fold` unset.
+    pub fn fold_all(&mut self, fold: bool) {
+        self.folds.clear();
+        if fold {
+            for region in self.fold_regions() {
+                self.folds.fold(region);
+            }
+            self.folds.reveal(self.cursor.head.row);
+        }
+    }
+
+    /// Pretty-prints a JSON or YAML document as one undo step, see
+    /// [`crate::format`]. The cursor stays on its row as far as the new text
+    /// allows. Returns whether anything changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the document is neither JSON nor YAML, or isn't valid
+    /// JSON.
+    pub fn format_document(&mut self) -> std::io::Result<bool> {
+        let lines = self.get_lines_stripped(0..self.get_line_count());
+        let indent = self.indent_unit();
+        let mut formatted = match crate::folds::Structure::of(self.text_buffer.path()) {
+            crate::folds::Structure::Json => crate::format::json(&lines.join("\n"), &indent)?,
+            crate::folds::Structure::Yaml => crate::format::yaml(&lines, &indent),
+            crate::folds::Structure::Markdown => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    "Only JSON and YAML documents can be formatted",
+                ));
+            }
+        };
+
+        // Keeps the final newline.
+        if lines.last().is_some_and(String::is_empty) {
+            formatted.push(String::new());
+        }
+        if formatted == lines {
+            return Ok(false);
+        }
+
+        let row = self.cursor.head.row.min(formatted.len().saturating_sub(1));
+
+        self.replace_all_lines(formatted);
+        self.cursor = editor_core::cursor::Cursor::new(row, 0);
+        Ok(true)
+    }
+
+    /// Puts a new footnote reference `[^n]` at the cursor and its definition at the
+    /// end of the note, as one undo step, then moves the cursor there to write it.
+    /// [`Document::jump_footnote`] goes back.
+    pub fn insert_footnote(&mut self) {
+        let lines = self.get_lines_stripped(0..self.get_line_count());
+        let id = crate::footnotes::next_id(&lines);
+        let last = lines.len() - 1;
+        let eol = self.text_buffer.line_ending.as_str();
+        // A blank line sets the definitions apart from the text, then each has one.
+        let mut definition = match lines.iter().rev().find(|line| !line.trim().is_empty()) {
+            Some(line) if crate::footnotes::definition(line).is_some() => String::new(),
+            Some(_) => eol.to_string(),
+            None => String::new(),
+        };
+
+        if !lines[last].is_empty() {
+            definition.insert_str(0, eol);
+        }
+        definition.push_str(&format!("[^{id}]: "));
+
+        let head = self.cursor.head;
+        let end = editor_core::cursor::Position::new(last, lines[last].len());
+
+        self.cursor = editor_core::cursor::Cursor::new(head.row, head.col);
+        self.apply_edits(vec![
+            TextEdit::new(head, head, format!("[^{id}]")),
+            TextEdit::new(end, end, definition),
+        ]);
+
+        let row = self.get_line_count() - 1;
+        let col = self.get_line_stripped(row).map_or(0, |line| line.len());
+
+        self.cursor = editor_core::cursor::Cursor::new(row, col);
+    }
+
+    /// The fence line and the source of the closed code block opened at `fence`.
+    pub fn code_block(&self, fence: usize) -> Option<(String, String)> {
+        let close = self.outline.code_block_end(fence)?;
+        let mut lines = self.get_lines_stripped(fence..close).into_iter();
+        let fence_line = lines.next()?;
+
+        Some((fence_line, lines.map(|line| line + "\n").collect()))
+    }
+
+    /// Puts `result` right below the code block opened at `fence`, replacing the
+    /// output block of an earlier run there, as one undo step. Does nothing and
+    /// returns `false` if the block no longer holds `source`, i.e. it was edited
+    /// while running.
+    pub fn insert_run_result(&mut self, fence: usize, source: &str, result: &[String]) -> bool {
+        if self
+            .code_block(fence)
+            .is_none_or(|(_, have)| have != source)
+        {
+            return false;
+        }
+
+        let Some(close) = self.outline.code_block_end(fence) else {
+            return false;
+        };
+        let line_end = |d: &Self, row: usize| {
+            editor_core::cursor::Position::new(row, d.get_line_stripped(row).map_or(0, |l| l.len()))
+        };
+        let eol = self.text_buffer.line_ending.as_str();
+        let text = result.join(eol);
+        let earlier = self
+            .get_line_stripped(close + 1)
+            .filter(|line| line.trim() == format!("```{}", crate::run::RESULT_TAG))
+            .and_then(|_| self.outline.code_block_end(close + 1));
+        let edit = match earlier {
+            Some(end) => TextEdit::new(
+                editor_core::cursor::Position::new(close + 1, 0),
+                line_end(self, end),
+                text,
+            ),
+            None => {
+                let at = line_end(self, close);
+
+                TextEdit::new(at, at, format!("{eol}{text}"))
+            }
+        };
+
+        self.apply_edits(vec![edit]);
+        true
+    }
+
+    /// From the footnote reference at the cursor to its definition, or from a
+    /// definition back to where it's first referred to. Returns whether the cursor moved.
+    pub fn jump_footnote(&mut self) -> bool {
+        let head = self.cursor.head;
+        let Some(line) = self.get_line_stripped(head.row) else {
+            return false;
+        };
+        let lines = self.get_lines_stripped(0..self.get_line_count());
+        // Anywhere on a definition goes back, as its text is usually what's been
+        // written last.
+        let target = match crate::footnotes::definition(&line)
+            .and_then(|(label, _)| label.strip_prefix('^'))
+        {
+            Some(id) => crate::footnotes::find_reference(&lines, id),
+            None => match crate::footnotes::footnote_at(&line, head.col) {
+                Some(id) => crate::footnotes::find_definition(&lines, id),
+                None => return false,
+            },
+        };
+
+        match target {
+            Some((row, col)) => {
+                self.cursor = editor_core::cursor::Cursor::new(row, col);
+                self.folds.reveal(row);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Starts or stops recording when each line is edited. Starting picks up the times
+    /// saved next to the note, if any.
+    pub fn record_line_times(&mut self, on: bool) {
+        let line_count = self.text_buffer.line_count();
+
+        self.line_times = on.then(|| {
+            self.text_buffer
+                .path()
+                .and_then(crate::line_times::LineTimes::sidecar_path)
+                .map_or_else(
+                    || crate::line_times::LineTimes::new(line_count),
+                    |sidecar| crate::line_times::LineTimes::load(&sidecar, line_count),
+                )
+        });
+    }
+
+    /// Bookkeeping once the buffer was written: nothing counts as changed anymore,
+    /// and line times are saved along with the note.
+    fn saved(&mut self) {
+        self.changes.reset(self.text_buffer.line_count());
+
+        let sidecar = self
+            .text_buffer
+            .path()
+            .and_then(crate::line_times::LineTimes::sidecar_path);
+
+        if let (Some(times), Some(sidecar)) = (&self.line_times, sidecar)
+            && let Err(err) = times.save(&sidecar)
+        {
+            tracing::warn!("Error saving line times: {:?}", err);
+        }
+    }
+
+    /// Start row of the next block of changed lines below `row`, wrapping around to
+    /// the top.
+    #[inline]
+    pub fn next_change(&self, row: usize) -> Option<usize> {
+        self.changes.next(row)
+    }
+
+    /// Start row of the first conflict below `row`, wrapping around to the top.
+    pub fn next_conflict(&self, row: usize) -> Option<usize> {
+        self.conflicts
+            .iter()
+            .find(|conflict| conflict.start > row)
+            .or(self.conflicts.first())
+            .map(|conflict| conflict.start)
+    }
+
+    /// Replaces the conflict block under the cursor with the chosen side(s) as one
+    /// undo step. Returns `false` if the cursor isn't inside a conflict.
+    pub fn resolve_conflict(&mut self, resolution: crate::conflict::Resolution) -> bool {
+        let row = self.cursor.head.row;
+        let Some(conflict) = self.conflicts.iter().find(|c| c.contains(row)).copied() else {
+            return false;
+        };
+
+        let block = self.get_lines_stripped(conflict.start..conflict.end + 1);
+        let line_ending = self.text_buffer.line_ending.as_str();
+        let start = editor_core::cursor::Position::new(conflict.start, 0);
+        let resolved = conflict.resolve(&block, resolution);
+
+        // Swallow the closing marker's line ending too, unless it's the last line.
+        let edit = if self
+            .get_line(conflict.end)
+            .is_some_and(|line| line.ends_with('\n'))
+        {
+            TextEdit::new(
+                start,
+                editor_core::cursor::Position::new(conflict.end + 1, 0),
+                resolved
+                    .iter()
+                    .map(|line| format!("{line}{line_ending}"))
+                    .collect::<String>(),
+            )
+        } else {
+            TextEdit::new(
+                start,
+                editor_core::cursor::Position::new(conflict.end, block[block.len() - 1].len()),
+                resolved.join(line_ending),
+            )
+        };
+
+        self.apply_edits(vec![edit]);
+        self.cursor = editor_core::cursor::Cursor::new(conflict.start, 0);
+        true
+    }
+
+    /// Rewrites whole lines as one undo step. Each line is diffed against its new
+    /// contents so only the part that actually changed is edited, which keeps the
+    /// cursor in place on the unchanged text.
+    pub fn replace_lines(&mut self, changes: Vec<(usize, String)>) {
+        let edits = changes
+            .into_iter()
+            .filter_map(|(row, new)| {
+                let old = self.get_line_stripped(row)?;
+                let prefix = old
+                    .char_indices()
+                    .zip(new.chars())
+                    .find(|((_, a), b)| a != b)
+                    .map_or(old.len().min(new.len()), |((i, _), _)| i);
+                let suffix = old[prefix..]
+                    .chars()
+                    .rev()
+                    .zip(new[prefix..].chars().rev())
+                    .take_while(|(a, b)| a == b)
+                    .map(|(a, _)| a.len_utf8())
+                    .sum::<usize>();
+
+                (old != new).then(|| {
+                    TextEdit::new(
+                        editor_core::cursor::Position::new(row, prefix),
+                        editor_core::cursor::Position::new(row, old.len() - suffix),
+                        &new[prefix..new.len() - suffix],
+                    )
+                })
+            })
+            .collect();
+
+        self.apply_edits(edits);
+    }
+
+    /// Applies a batch of non-overlapping edits as a single, sealed undo step.
+    ///
+    /// Edits are played bottom-to-top so the coordinates of the ones above stay valid,
+    /// and the cursor is carried along through every edit.
+    pub fn apply_edits(&mut self, mut edits: Vec<TextEdit>) {
+        edits.retain(|edit| edit.start != edit.end || !edit.text.is_empty());
+
+        if edits.is_empty() {
+            return;
+        }
+
+        edits.sort_by_key(|edit| edit.start);
+
+        let cursor_before = self.cursor;
+        let mut anchor = self.cursor.anchor;
+        let mut head = self.cursor.head;
+        let mut actions = Vec::with_capacity(edits.len() * 2);
+
+        for edit in edits.iter().rev() {
+            let range = editor_core::cursor::Cursor::new_selection(edit.start, edit.end);
+            let (_, deleted) = self
+                .text_buffer
+                .delete_selection(&range)
+                .expect("Edit range out of bounds");
+
+            if !deleted.is_empty() {
+                actions.push(editor_core::enums::EditAction::Delete {
+                    pos: edit.start,
+                    end: edit.end,
+                    text: deleted,
+                });
+                self.log_edit(actions[actions.len() - 1].clone());
+            }
+
+            if !edit.text.is_empty() {
+                self.text_buffer
+                    .insert(
+                        &editor_core::cursor::Cursor::new(edit.start.row, edit.start.col),
+                        &edit.text,
+                    )
+                    .expect("Buffer insertion failed");
+                actions.push(editor_core::enums::EditAction::Insert {
+                    pos: edit.start,
+                    text: edit.text.clone(),
+                });
+                self.log_edit(actions[actions.len() - 1].clone());
+            }
+
+            let edit_end = self.calculate_end_position(edit.start, &edit.text);
+            self.lines_changed(edit.start.row, edit.end.row, edit_end.row);
+            anchor = map_position_through_edit(anchor, edit, edit_end);
+            head = map_position_through_edit(head, edit, edit_end);
+        }
+
+        self.cursor = editor_core::cursor::Cursor::new_selection(anchor, head);
+
+        if self.is_recording {
+            self.history
+                .record_transaction(actions, cursor_before, self.cursor);
+        }
+    }
+}
+
+/// Shifts `pos` to where it ends up after `edit` replaced its range with text ending at `edit_end`.
+/// Positions inside the replaced range collapse onto the end of the replacement.
+fn map_position_through_edit(
+    pos: editor_core::cursor::Position,
+    edit: &TextEdit,
+    edit_end: editor_core::cursor::Position,
+) -> editor_core::cursor::Position {
+    if pos <= edit.start {
+        return pos;
+    }
+
+    if pos < edit.end {
+        return edit_end;
+    }
+
+    if pos.row == edit.end.row {
+        editor_core::cursor::Position::new(edit_end.row, edit_end.col + (pos.col - edit.end.col))
+    } else {
+        editor_core::cursor::Position::new(pos.row - edit.end.row + edit_end.row, pos.col)
+    }
+}
+
+impl Document {
+    pub fn undo(&mut self) {
+        if let Some(transaction) = self.history.undo() {
+            self.execute_transaction(transaction, true);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(transaction) = self.history.redo() {
+            self.execute_transaction(transaction, false);
+        }
+    }
+
+    /// Internal helper to play back a transaction without recording it.
+    fn execute_transaction(
+        &mut self,
+        transaction: editor_core::history::Transaction,
+        is_undo: bool,
+    ) {
+        self.is_recording = false;
+
+        // When undoing, we apply actions in reverse order (stack logic).
+        // When redoing, we apply them in the original forward order.
+        let actions: Vec<_> = if is_undo {
+            transaction.actions.iter().rev().collect()
+        } else {
+            transaction.actions.iter().collect()
+        };
+
+        for action in actions {
+            match action {
+                editor_core::enums::EditAction::Insert { pos, text } => {
+                    if is_undo {
+                        // Undo Insert -> Delete the text we added
+                        let end_pos = self.calculate_end_position(*pos, text);
+                        let temp_cursor = editor_core::cursor::Cursor::new_selection(*pos, end_pos);
+                        tracing::trace!(?end_pos, cursor = ?temp_cursor, "Undoing insert");
+                        let _ = self.text_buffer.delete_selection(&temp_cursor);
+                        self.lines_changed(pos.row, end_pos.row, pos.row);
+                        self.log_edit(editor_core::enums::EditAction::Delete {
+                            pos: *pos,
+                            end: end_pos,
+                            text: text.clone(),
+                        });
+                    } else {
+                        // Redo Insert -> Re-insert the text
+                        let temp_cursor = editor_core::cursor::Cursor::new(pos.row, pos.col);
+                        let _ = self.text_buffer.insert(&temp_cursor, text);
+                        let end_pos = self.calculate_end_position(*pos, text);
+                        self.lines_changed(pos.row, pos.row, end_pos.row);
+                        self.log_edit(action.clone());
+                    }
+                }
+                editor_core::enums::EditAction::Delete {
+                    pos: start, text, ..
+                } => {
+                    if is_undo {
+                        // Undo Delete -> Put the deleted text back
+                        let temp_cursor = editor_core::cursor::Cursor::new(start.row, start.col);
+                        let _ = self.text_buffer.insert(&temp_cursor, text);
+                        let end_pos = self.calculate_end_position(*start, text);
+                        self.lines_changed(start.row, start.row, end_pos.row);
+                        self.log_edit(editor_core::enums::EditAction::Insert {
+                            pos: *start,
+                            text: text.clone(),
+                        });
+                    } else {
+                        // Redo Delete -> Delete the text again
+                        // Note: EditAction::Delete stores start/end, so we use them
+                        let end_pos = self.calculate_end_position(*start, text);
+                        let temp_cursor =
+                            editor_core::cursor::Cursor::new_selection(*start, end_pos);
+                        let _ = self.text_buffer.delete_selection(&temp_cursor);
+                        self.lines_changed(start.row, end_pos.row, start.row);
+                        self.log_edit(action.clone());
+                    }
+                }
+            }
+        }
+
+        // Restore the appropriate cursor state
+        self.cursor = if is_undo {
+            transaction.cursor_before
+        } else {
+            transaction.cursor_after
+        };
+
+        self.is_recording = true;
+    }
+
+    /// Lines [`Document::select_next_match`] looks through before giving up.
+    pub const MATCH_WINDOW: usize = 10_000;
+
+    /// Keeps line-based indexes in sync after rows `start..=old_end` were replaced
+    /// by `start..=new_end`. Every buffer mutation funnels through here.
+    fn lines_changed(&mut self, start: usize, old_end: usize, new_end: usize) {
+        tracing::debug!(start, old_end, new_end, "Lines changed");
+        self.revision += 1;
+        self.journal.record(
+            self.revision,
+            crate::journal::LineEdit {
+                start,
+                old_end,
+                new_end,
+            },
+        );
+        self.calculation = None;
+        self.outline
+            .update(&self.text_buffer, start, old_end, new_end);
+        self.folds.update(start, old_end, new_end);
+        self.bookmarks.update(start, old_end, new_end);
+        self.changes.update(start, old_end, new_end);
+        if let Some(times) = &mut self.line_times {
+            times.update(start, old_end, new_end, std::time::SystemTime::now());
+        }
+
+        // Only the edited lines are looked at; the blocks are paired up again from
+        // the markers, and only if the edit added or removed one, or moved them.
+        let markers_changed = self.conflict_markers.edit(
+            crate::journal::LineEdit {
+                start,
+                old_end,
+                new_end,
+            },
+            &self.text_buffer.get_lines_stripped(start..new_end + 1),
+            crate::conflict::marker,
+        );
+
+        if markers_changed || !self.conflicts.is_empty() {
+            self.conflicts = crate::conflict::pair(self.conflict_markers.rows());
+        }
+    }
+
+    fn log_edit(&mut self, action: editor_core::enums::EditAction) {
+        if let Some(log) = &mut self.edit_log {
+            log.push(action);
+        }
+    }
+
+    /// Applies changes made elsewhere, e.g. by a collaborator. They go around the
+    /// undo history and the edit log, and the cursor is carried along.
+    pub fn apply_remote(&mut self, actions: Vec<editor_core::enums::EditAction>) {
+        let (mut anchor, mut head) = (self.cursor.anchor, self.cursor.head);
+
+        for action in actions {
+            let edit = match action {
+                editor_core::enums::EditAction::Insert { pos, text } => {
+                    let _ = self
+                        .text_buffer
+                        .insert(&editor_core::cursor::Cursor::new(pos.row, pos.col), &text);
+                    TextEdit::new(pos, pos, text)
+                }
+                editor_core::enums::EditAction::Delete { pos, end, .. } => {
+                    let _ = self
+                        .text_buffer
+                        .delete_selection(&editor_core::cursor::Cursor::new_selection(pos, end));
+                    TextEdit::new(pos, end, "")
+                }
+            };
+            let edit_end = self.calculate_end_position(edit.start, &edit.text);
+
+            self.lines_changed(edit.start.row, edit.end.row, edit_end.row);
+            anchor = map_position_through_edit(anchor, &edit, edit_end);
+            head = map_position_through_edit(head, &edit, edit_end);
+        }
+
+        self.cursor = editor_core::cursor::Cursor::new_selection(anchor, head);
+    }
+
+    fn scan_conflicts(&mut self) {
+        self.conflict_markers = crate::journal::RowIndex::from_lines(
+            &self
+                .text_buffer
+                .get_lines_stripped(0..self.text_buffer.line_count()),
+            crate::conflict::marker,
+        );
+        self.conflicts = crate::conflict::pair(self.conflict_markers.rows());
+    }
+
+    /// What to do when the file of an open note may have changed on disk: if it was
+    /// truncated, keep what's left of the text by detaching it from the file, and
+    /// leave the note unsaved so saving writes it back. Returns the number of bytes
+    /// that were already gone and are dropped from the text, or `None` if the file
+    /// is intact. The undo history is cleared then, as it no longer lines up.
+    pub fn check_truncation(&mut self) -> Option<usize> {
+        let old_last = self.text_buffer.line_count().saturating_sub(1);
+        let lost = self.text_buffer.detach_if_truncated()?;
+
+        tracing::warn!(
+            lost,
+            path = ?self.text_buffer.path(),
+            "File truncated on disk; detached the note from it"
+        );
+
+        if lost > 0 {
+            let last = self.text_buffer.line_count().saturating_sub(1);
+
+            self.history.undo_stack.clear();
+            self.history.redo_stack.clear();
+            self.cursor = editor_core::cursor::Cursor::new_selection(
+                self.clamp_position(self.cursor.anchor),
+                self.clamp_position(self.cursor.head),
+            );
+            self.lines_changed(0, old_last, last);
+        }
+
+        Some(lost)
+    }
+
+    /// Helper to find the 2D end position of a string starting at `start`.
+    fn calculate_end_position(
+        &self,
+        start: editor_core::cursor::Position,
+        text: &str,
+    ) -> editor_core::cursor::Position {
+        let mut row = start.row;
+        let mut col = start.col;
+
+        let lines: Vec<&str> = text.split('\n').collect();
+
+        if lines.len() > 1 {
+            // We moved down lines. The new row is start.row + count of newlines.
+            row += lines.len() - 1;
+            // The column is simply the length of the very last segment.
+            col = lines.last().unwrap_or(&"").len();
+        } else {
+            // We stayed on the same line. Just add the length to the current column.
+            col += text.len();
+        }
+
+        editor_core::cursor::Position::new(row, col)
+    }
+
+    /// `pos` moved onto the document if it's past the end of its line or of the
+    /// document, and onto a character boundary.
+    fn clamp_position(&self, pos: editor_core::cursor::Position) -> editor_core::cursor::Position {
+        let row = pos.row.min(self.get_line_count().saturating_sub(1));
+        let line = self.get_line_stripped(row).unwrap_or_default();
+        let mut col = pos.col.min(line.len());
+
+        while !line.is_char_boundary(col) {
+            col -= 1;
+        }
+        editor_core::cursor::Position::new(row, col)
+    }
+}
+
+impl Document {
+    pub fn get_selected_text(&self) -> String {
+        let (start, end) = self.cursor.range();
+
+        self.get_text_between(start, end)
+    }
+
+    /// The cursor's whole line with its line ending, as copied when nothing is
+    /// selected. The last line gets a `\n`, so it pastes as a line too.
+    pub fn get_current_line(&self) -> String {
+        let row = self.cursor.head.row;
+
+        if row + 1 < self.get_line_count() {
+            self.get_text_between(
+                editor_core::cursor::Position::new(row, 0),
+                editor_core::cursor::Position::new(row + 1, 0),
+            )
+        } else {
+            format!("{}\n", self.get_line_stripped(row).unwrap_or_default())
+        }
+    }
+
+    /// Removes the cursor's whole line, line ending included, leaving the cursor
+    /// at the start of the line taking its place. Returns what was removed, see
+    /// [`Self::get_current_line`].
+    pub fn cut_line(&mut self) -> String {
+        let text = self.get_current_line();
+        let row = self.cursor.head.row;
+        let line_end = |row: usize| {
+            editor_core::cursor::Position::new(
+                row,
+                self.get_line_stripped(row).map_or(0, |line| line.len()),
+            )
+        };
+        let (start, end) = if row + 1 < self.get_line_count() {
+            (
+                editor_core::cursor::Position::new(row, 0),
+                editor_core::cursor::Position::new(row + 1, 0),
+            )
+        } else if row > 0 {
+            // The last line takes the line break above it along.
+            (line_end(row - 1), line_end(row))
+        } else {
+            (editor_core::cursor::Position::new(0, 0), line_end(0))
+        };
+
+        self.apply_edits(vec![TextEdit::new(start, end, "")]);
+        self.cursor =
+            editor_core::cursor::Cursor::new(row.min(self.get_line_count().saturating_sub(1)), 0);
+
+        text
+    }
+
+    /// Inserts `lines`, whole lines each ending in a line break, above the
+    /// cursor's line. The cursor stays on the text it was on.
+    pub fn paste_lines(&mut self, lines: &str) {
+        let head = self.cursor.head;
+        let at = editor_core::cursor::Position::new(head.row, 0);
+
+        self.apply_edits(vec![TextEdit::new(at, at, lines)]);
+        self.cursor =
+            editor_core::cursor::Cursor::new(head.row + lines.matches('\n').count(), head.col);
+    }
+
+    /// The text from `start` to `end`, with the note's line endings.
+    pub fn get_text_between(
+        &self,
+        start: editor_core::cursor::Position,
+        end: editor_core::cursor::Position,
+    ) -> String {
+        let mut out = String::new();
+
+        for row in start.row..=end.row {
+            if let Some(line) = self.get_line_stripped(row) {
+                let from = if row == start.row { start.col } else { 0 };
+                let to = if row == end.row { end.col } else { line.len() };
+
+                out.push_str(&line[from..to]);
+
+                if row != end.row {
+                    out.push_str(self.text_buffer.line_ending.as_str());
+                }
+            }
+        }
+
+        out
+    }
+
+    #[inline]
+    pub fn get_line_count(&self) -> usize {
+        self.text_buffer.line_count()
+    }
+
+    #[inline]
+    pub fn get_line_len_at(&self, line_idx: usize) -> Option<u64> {
+        self.text_buffer.get_line_len_at(line_idx)
+    }
+
+    pub fn get_visible_line_len_at(&self, line_idx: usize) -> Option<u64> {
+        Some(
+            self.get_line_stripped(line_idx)?
+                .trim_end_matches(&['\n', '\r'][..])
+                .chars()
+                .count() as u64,
+        )
+    }
+
+    #[inline]
+    pub fn get_line(&self, idx: usize) -> Option<String> {
+        self.text_buffer.get_line(idx)
+    }
+
+    #[inline]
+    pub fn get_line_stripped(&self, idx: usize) -> Option<String> {
+        self.text_buffer.get_line_stripped(idx)
+    }
+
+    #[inline]
+    pub fn get_lines_stripped(&self, rows: std::ops::Range<usize>) -> Vec<String> {
+        self.text_buffer.get_lines_stripped(rows)
+    }
+
+    pub fn open_file<P: AsRef<std::path::Path>>(
+        &mut self,
+        path: P,
+    ) -> editor_core::errors::TextBufferResult<()> {
+        self.text_buffer
+            .set_access_mode(self.settings.access_mode());
+        self.text_buffer.open_from(&path)?;
+        tracing::info!(path = ?path.as_ref(), "Opened");
+        self.outline = crate::outline::Outline::build(&self.text_buffer);
+        self.folds.clear();
+        self.bookmarks.clear();
+        self.changes.reset(self.text_buffer.line_count());
+        self.record_line_times(self.settings.line_times);
+        self.scan_conflicts();
+        self.revision += 1;
+        self.journal.reset(self.revision);
+        self.overrides = crate::editorconfig::resolve(path.as_ref());
+        // The file's own modeline has the last word.
+        if let Some(modeline) = crate::modeline::find(&self.edge_lines()) {
+            self.overrides.apply(&modeline.settings);
+        }
+
+        if let Some(line_ending) = self.overrides.end_of_line {
+            self.text_buffer.line_ending = line_ending;
+        }
+
+        Ok(())
+    }
+
+    /// Reloads the file from disk into this document, keeping the cursor where it was
+    /// as far as the new contents allow. The swap is recorded as one undo step, so an
+    /// accidental revert can be undone.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer has no path or the file can't be re-opened.
+    pub fn revert(&mut self) -> editor_core::errors::TextBufferResult<()> {
+        let path = self
+            .text_buffer
+            .path()
+            .map(std::path::Path::to_path_buf)
+            .ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::NotFound, "Buffer has no file path")
+            })?;
+        let cursor_before = self.cursor;
+        let old_text = self.text_buffer.to_string();
+
+        self.text_buffer.open_from(&path)?;
+        self.outline = crate::outline::Outline::build(&self.text_buffer);
+        self.folds.clear();
+        self.bookmarks.clear();
+        self.changes.reset(self.text_buffer.line_count());
+        self.record_line_times(self.line_times.is_some());
+        self.scan_conflicts();
+        self.revision += 1;
+        self.journal.reset(self.revision);
+
+        let new_text = self.text_buffer.to_string();
+        self.cursor = editor_core::cursor::Cursor::new_selection(
+            self.clamp_position(cursor_before.anchor),
+            self.clamp_position(cursor_before.head),
+        );
+
+        if old_text != new_text {
+            // Only the span between what the two texts start and end with changed,
+            // so that's all the undo step keeps rather than two copies of the file.
+            let mut prefix = old_text
+                .bytes()
+                .zip(new_text.bytes())
+                .take_while(|(old, new)| old == new)
+                .count();
+            while !old_text.is_char_boundary(prefix) {
+                prefix -= 1;
+            }
+            let mut suffix = old_text[prefix..]
+                .bytes()
+                .rev()
+                .zip(new_text[prefix..].bytes().rev())
+                .take_while(|(old, new)| old == new)
+                .count();
+            while !old_text.is_char_boundary(old_text.len() - suffix) {
+                suffix -= 1;
+            }
+
+            let start = self.calculate_end_position(
+                editor_core::cursor::Position::new(0, 0),
+                &old_text[..prefix],
+            );
+            let removed = &old_text[prefix..old_text.len() - suffix];
+            let inserted = &new_text[prefix..new_text.len() - suffix];
+            let mut actions = Vec::with_capacity(2);
+
+            if !removed.is_empty() {
+                actions.push(editor_core::enums::EditAction::Delete {
+                    pos: start,
+                    end: self.calculate_end_position(start, removed),
+                    text: removed.to_string(),
+                });
+            }
+            if !inserted.is_empty() {
+                actions.push(editor_core::enums::EditAction::Insert {
+                    pos: start,
+                    text: inserted.to_string(),
+                });
+            }
+
+            for action in &actions {
+                self.log_edit(action.clone());
+            }
+            self.history
+                .record_transaction(actions, cursor_before, self.cursor);
+        }
+
+        Ok(())
+    }
+
+    /// Copies `sources` into the note's assets folder and inserts a Markdown link
+    /// to each at the cursor, one per line, as a single undo step.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the note hasn't been saved yet or a copy fails.
+    pub fn insert_attachments(&mut self, sources: &[std::path::PathBuf]) -> std::io::Result<()> {
+        let note = self
+            .text_buffer
+            .path()
+            .map(std::path::Path::to_path_buf)
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    "Save the note before attaching files",
+                )
+            })?;
+        let links = sources
+            .iter()
+            .map(|source| {
+                let target = crate::workspace::import_attachment(&note, source)?;
+                let label = source.file_name().unwrap_or_default().to_string_lossy();
+
+                Ok(crate::workspace::attachment_link(&label, &target))
+            })
+            .collect::<std::io::Result<Vec<_>>>()?;
+
+        self.insert(&links.join(self.text_buffer.line_ending.as_str()));
+        Ok(())
+    }
+
+    /// Renames the note on disk to `new_name`, keeping its extension. With
+    /// `update_links`, `[[old name]]` links across the workspace (the note's folder)
+    /// are pointed at the new name; the ones in this note become a single undo step.
+    ///
+    /// Returns the other notes that were rewritten.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer has no path, the new name isn't a plain file
+    /// name, the new name is taken, or a file can't be written.
+    pub fn rename_note(
+        &mut self,
+        new_name: &str,
+        update_links: bool,
+    ) -> std::io::Result<Vec<std::path::PathBuf>> {
+        let old_path = self
+            .text_buffer
+            .path()
+            .map(std::path::Path::to_path_buf)
+            .ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::NotFound, "Buffer has no file path")
+            })?;
+        let old_name = crate::workspace::note_name(&old_path)
+            .unwrap_or_default()
+            .to_string();
+
+        if new_name.trim().is_empty()
+            || new_name == "."
+            || new_name == ".."
+            || new_name
+                .chars()
+                .any(|c| std::path::is_separator(c) || c == '\\')
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{new_name:?} isn't a valid note name"),
+            ));
+        }
+
+        let new_path = match old_path.extension() {
+            Some(ext) => old_path.with_file_name(format!("{new_name}.{}", ext.to_string_lossy())),
+            None => old_path.with_file_name(new_name),
+        };
+
+        if new_path == old_path {
+            return Ok(Vec::new());
+        }
+
+        // Only the case changes: on a case-insensitive disk the new path already
+        // "exists" as the note itself, so look for a file by that exact name.
+        let case_only = new_name.to_lowercase() == old_name.to_lowercase();
+        let taken = if case_only {
+            let new_file = new_path.file_name();
+
+            std::fs::read_dir(new_path.parent().unwrap_or(std::path::Path::new(".")))?
+                .filter_map(Result::ok)
+                .any(|entry| Some(entry.file_name().as_os_str()) == new_file)
+        } else {
+            new_path.exists()
+        };
+
+        if taken {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("{} already exists", new_path.display()),
+            ));
+        }
+
+        if update_links {
+            let changes = self
+                .get_lines_stripped(0..self.get_line_count())
+                .iter()
+                .enumerate()
+                .filter_map(|(row, line)| {
+                    crate::workspace::rename_links(line, &old_name, new_name).map(|l| (row, l))
+                })
+                .collect();
+
+            self.replace_lines(changes);
+        }
+
+        // The note under its new name, the old file's removal and every rewritten
+        // link land together or not at all.
+        let mut batch = crate::workspace::BatchSave::default();
+        let mut updated = Vec::new();
+
+        // A case-only rename can't write the new path and remove the old one, which
+        // may be the same file: it's saved in place and renamed afterwards.
+        if case_only {
+            batch.write(&old_path, self.text_buffer.to_string());
+        } else {
+            batch.write(&new_path, self.text_buffer.to_string());
+            batch.remove(&old_path);
+        }
+
+        if update_links && let Some(root) = new_path.parent() {
+            for note in crate::workspace::referencing_notes(root, &old_name) {
+                if note != old_path
+                    && let Some(rewritten) =
+                        crate::workspace::links_rewritten(&note, &old_name, new_name)?
+                {
+                    batch.write(&note, rewritten);
+                    updated.push(note);
+                }
+            }
+        }
+
+        // Windows won't delete a file that's still mapped.
+        self.text_buffer.release_file();
+        batch.commit()?;
+        if case_only {
+            // Through a temporary name, as some disks take renaming a file to
+            // itself in another case as doing nothing.
+            let temporary = old_path.with_file_name(format!(".{new_name}.renaming"));
+
+            std::fs::rename(&old_path, &temporary)?;
+            if let Err(err) = std::fs::rename(&temporary, &new_path) {
+                let _ = std::fs::rename(&temporary, &old_path);
+                return Err(err);
+            }
+        }
+        self.text_buffer.adopt_written(&new_path)?;
+        tracing::info!(path = ?new_path, "Saved");
+
+        Ok(updated)
+    }
+
+    /// The `[[note#heading]]` wiki link the cursor is on.
+    pub fn link_at_cursor(&self) -> Option<crate::workspace::WikiLink> {
+        let head = self.cursor.head;
+
+        crate::workspace::WikiLink::at(&self.get_line_stripped(head.row)?, head.col)
+    }
+
+    /// Turns every `[[name]]` link in this note into plain text as one undo step,
+    /// see [`crate::workspace::unlink`]. Returns whether there were any.
+    pub fn unlink(&mut self, name: &str) -> bool {
+        let edits: Vec<TextEdit> = self
+            .get_lines_stripped(0..self.get_line_count())
+            .iter()
+            .enumerate()
+            .filter_map(|(row, line)| {
+                Some(TextEdit::new(
+                    editor_core::cursor::Position::new(row, 0),
+                    editor_core::cursor::Position::new(row, line.len()),
+                    crate::workspace::unlink(line, name)?,
+                ))
+            })
+            .collect();
+        let changed = !edits.is_empty();
+
+        self.apply_edits(edits);
+        changed
+    }
+
+    /// A `mynotes://` link to this note at the section the cursor is in, for other
+    /// apps to bring it back up with. `None` for an unsaved note.
+    pub fn deep_link(&self) -> Option<crate::instance::DeepLink> {
+        let name = crate::workspace::note_name(self.text_buffer.path()?)?;
+        let heading = self
+            .outline
+            .section_at(self.cursor.head.row)
+            .map(|index| crate::toc::anchors(&self.outline.headings).swap_remove(index));
+
+        Some(crate::instance::DeepLink {
+            note: crate::toc::slug(name),
+            heading,
+        })
+    }
+
+    /// Retitles the heading on the cursor's line `title`, and points the links to
+    /// it at the new title as one undo step: `[[#heading]]` and `(#anchor)`
+    /// links in this note and, if it's saved, `[[note#heading]]` links across
+    /// the workspace (the note's folder). This note is then saved along with the
+    /// others, all landing together or not at all.
+    ///
+    /// Returns the other notes that were rewritten.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the cursor isn't on a heading or a file can't be
+    /// written.
+    pub fn rename_heading(&mut self, title: &str) -> std::io::Result<Vec<std::path::PathBuf>> {
+        let row = self.cursor.head.row;
+        let Some(index) = self
+            .outline
+            .headings
+            .binary_search_by_key(&row, |heading| heading.row)
+            .ok()
+        else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "The cursor isn't on a heading",
+            ));
+        };
+        let heading = &self.outline.headings[index];
+        let old = heading.title.clone();
+        let old_anchor = format!("(#{})", crate::toc::anchors(&self.outline.headings)[index]);
+        let new_anchor = format!("(#{})", crate::toc::slug(title));
+        let line = self.get_line_stripped(row).unwrap_or_default();
+        let indent = &line[..line.len() - line.trim_start_matches(' ').len()];
+        let path = self.text_buffer.path().map(std::path::Path::to_path_buf);
+        let name = path
+            .as_deref()
+            .and_then(crate::workspace::note_name)
+            .unwrap_or_default()
+            .to_string();
+        let changes = self
+            .get_lines_stripped(0..self.get_line_count())
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, line)| {
+                if i == row {
+                    return Some((
+                        i,
+                        format!("{indent}{} {title}", "#".repeat(heading.level.into())),
+                    ));
+                }
+
+                let mut rewritten = crate::workspace::rename_heading_links(&line, "", &old, title);
+
+                if !name.is_empty()
+                    && let Some(more) = crate::workspace::rename_heading_links(
+                        rewritten.as_deref().unwrap_or(&line),
+                        &name,
+                        &old,
+                        title,
+                    )
+                {
+                    rewritten = Some(more);
+                }
+                if rewritten.as_deref().unwrap_or(&line).contains(&old_anchor) {
+                    rewritten = Some(
+                        rewritten
+                            .as_deref()
+                            .unwrap_or(&line)
+                            .replace(&old_anchor, &new_anchor),
+                    );
+                }
+                rewritten.map(|rewritten| (i, rewritten))
+            })
+            .collect();
+
+        self.replace_lines(changes);
+
+        let Some(path) = path else {
+            return Ok(Vec::new());
+        };
+        let mut batch = crate::workspace::BatchSave::default();
+        let mut updated = Vec::new();
+
+        batch.write(&path, self.text_buffer.to_string());
+
+        if let Some(root) = path.parent() {
+            for note in crate::workspace::notes(root) {
+                if note != path
+                    && let Some(rewritten) =
+                        crate::workspace::heading_links_rewritten(&note, &name, &old, title)?
+                {
+                    batch.write(&note, rewritten);
+                    updated.push(note);
+                }
+            }
+        }
+
+        self.text_buffer.release_file();
+        batch.commit()?;
+        self.text_buffer.adopt_written(&path)?;
+        tracing::info!(?path, "Saved");
+
+        Ok(updated)
+    }
+
+    /// Moves the selection into a new note called `name`, next to this one and of
+    /// the same kind, and puts a `[[name]]` link in its place as one undo step,
+    /// with the cursor after it. Returns the new note's path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer has no path, nothing is selected, the name is
+    /// taken, or the new note can't be written.
+    pub fn extract_selection(&mut self, name: &str) -> std::io::Result<std::path::PathBuf> {
+        let path = self
+            .text_buffer
+            .path()
+            .map(std::path::Path::to_path_buf)
+            .ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::NotFound, "Buffer has no file path")
+            })?;
+
+        if self.cursor.no_selection() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Nothing is selected",
+            ));
+        }
+
+        let new_path = match path.extension() {
+            Some(ext) => path.with_file_name(format!("{name}.{}", ext.to_string_lossy())),
+            None => path.with_file_name(name),
+        };
+
+        if new_path.exists() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("{} already exists", new_path.display()),
+            ));
+        }
+
+        let eol = self.text_buffer.line_ending.as_str();
+        let mut text = self.get_selected_text();
+        let mut batch = crate::workspace::BatchSave::default();
+
+        if !text.ends_with('\n') {
+            text.push_str(eol);
+        }
+        batch.write(&new_path, text);
+        batch.commit()?;
+
+        let (start, end) = self.cursor.range();
+
+        self.apply_edits(vec![TextEdit::new(start, end, format!("[[{name}]]"))]);
+        self.cursor = editor_core::cursor::Cursor::new(self.cursor.head.row, self.cursor.head.col);
+        Ok(new_path)
+    }
+
+    /// Appends the note at `other` to this one, after a blank line, and removes its
+    /// file. Links to it in this note and the others in its folder are pointed at
+    /// this one. The change to this note is one undo step, which doesn't bring the
+    /// other file back; this note is saved along with the removal and the links,
+    /// all landing together or not at all.
+    ///
+    /// Returns the other notes whose links were rewritten.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer has no path, `other` is this note or can't be
+    /// read, or a file can't be written.
+    pub fn merge_note(
+        &mut self,
+        other: &std::path::Path,
+    ) -> std::io::Result<Vec<std::path::PathBuf>> {
+        let path = self
+            .text_buffer
+            .path()
+            .map(std::path::Path::to_path_buf)
+            .ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::NotFound, "Buffer has no file path")
+            })?;
+
+        if other == path {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Can't merge a note into itself",
+            ));
+        }
+
+        let name = crate::workspace::note_name(&path)
+            .unwrap_or_default()
+            .to_string();
+        let other_name = crate::workspace::note_name(other)
+            .unwrap_or_default()
+            .to_string();
+        let eol = self.text_buffer.line_ending.as_str();
+        let merged: Vec<String> = std::fs::read_to_string(other)?
+            .lines()
+            .map(|line| {
+                let line = line.trim_end_matches('\r');
+
+                crate::workspace::rename_links(line, &other_name, &name)
+                    .unwrap_or_else(|| line.to_string())
+            })
+            .collect();
+        let lines = self.get_lines_stripped(0..self.get_line_count());
+        let mut edits: Vec<TextEdit> = lines
+            .iter()
+            .enumerate()
+            .filter_map(|(row, line)| {
+                let renamed = crate::workspace::rename_links(line, &other_name, &name)?;
+
+                Some(TextEdit::new(
+                    editor_core::cursor::Position::new(row, 0),
+                    editor_core::cursor::Position::new(row, line.len()),
+                    renamed,
+                ))
+            })
+            .collect();
+        let last = lines.len().saturating_sub(1);
+        let end = editor_core::cursor::Position::new(last, lines.last().map_or(0, String::len));
+        let separator = if lines.iter().all(|line| line.is_empty()) {
+            ""
+        } else if lines[last].is_empty() {
+            eol
+        } else {
+            &format!("{eol}{eol}")
+        };
+
+        edits.retain(|edit| edit.start.row != last);
+        edits.push(TextEdit::new(
+            editor_core::cursor::Position::new(last, 0),
+            end,
+            format!(
+                "{}{separator}{}",
+                crate::workspace::rename_links(&lines[last], &other_name, &name)
+                    .unwrap_or_else(|| lines[last].clone()),
+                merged.join(eol)
+            ),
+        ));
+        self.apply_edits(edits);
+
+        let mut batch = crate::workspace::BatchSave::default();
+        let mut updated = Vec::new();
+
+        batch.write(&path, self.text_buffer.to_string());
+        batch.remove(other);
+
+        if let Some(root) = path.parent() {
+            for note in crate::workspace::referencing_notes(root, &other_name) {
+                if note != path
+                    && note != other
+                    && let Some(rewritten) =
+                        crate::workspace::links_rewritten(&note, &other_name, &name)?
+                {
+                    batch.write(&note, rewritten);
+                    updated.push(note);
+                }
+            }
+        }
+
+        self.text_buffer.release_file();
+        batch.commit()?;
+        self.text_buffer.adopt_written(&path)?;
+        tracing::info!(?path, "Saved");
+
+        Ok(updated)
+    }
+
+    /// Moves the note into the [`crate::workspace::ARCHIVE_DIR`] next to it, or back
+    /// out with `archive` off. Relative links in the note are rebased so they keep
+    /// pointing at the same files; that rewrite is one undo step. Returns the new path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer has no path, the note is already where it's
+    /// asked to go, the target is taken, or a file can't be written.
+    pub fn archive_note(&mut self, archive: bool) -> std::io::Result<std::path::PathBuf> {
+        let old_path = self
+            .text_buffer
+            .path()
+            .map(std::path::Path::to_path_buf)
+            .ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::NotFound, "Buffer has no file path")
+            })?;
+        let new_path = if archive {
+            crate::workspace::archived_path(&old_path)
+        } else {
+            crate::workspace::unarchived_path(&old_path)
+        }
+        .ok_or_else(|| {
+            let message = if archive {
+                "The note is archived already"
+            } else {
+                "The note isn't archived"
+            };
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, message)
+        })?;
+
+        if new_path.exists() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("{} already exists", new_path.display()),
+            ));
+        }
+
+        let changes = self
+            .get_lines_stripped(0..self.get_line_count())
+            .iter()
+            .enumerate()
+            .filter_map(|(row, line)| {
+                crate::workspace::rebase_links(line, archive).map(|l| (row, l))
+            })
+            .collect();
+
+        self.replace_lines(changes);
+
+        let mut batch = crate::workspace::BatchSave::default();
+
+        batch.write(&new_path, self.text_buffer.to_string());
+        batch.remove(&old_path);
+
+        if let Some(dir) = new_path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        // Windows won't delete a file that's still mapped.
+        self.text_buffer.release_file();
+        batch.commit()?;
+        self.text_buffer.adopt_written(&new_path)?;
+        tracing::info!(path = ?new_path, "Saved");
+
+        Ok(new_path)
+    }
+
+    /// Indentation style in effect: `.editorconfig`, then what was detected in the
+    /// file, then its file type's convention, then the editor-wide setting.
+    pub fn indent_style(&self) -> crate::settings::IndentStyle {
+        self.overrides
+            .indent_style
+            .unwrap_or(match self.text_buffer.indentation {
+                Some(editor_core::text::Indentation::Tabs) => crate::settings::IndentStyle::Tabs,
+                Some(editor_core::text::Indentation::Spaces(_)) => {
+                    crate::settings::IndentStyle::Spaces
+                }
+                None => self
+                    .file_type()
+                    .indent
+                    .map_or(self.settings.indent_style, |(style, _)| style),
+            })
+    }
+
+    /// Columns per indentation level, with the same precedence as [`Document::indent_style`].
+    pub fn indent_size(&self) -> usize {
+        self.overrides
+            .indent_size
+            .unwrap_or_else(|| match self.text_buffer.indentation {
+                Some(editor_core::text::Indentation::Spaces(width)) => width,
+                Some(editor_core::text::Indentation::Tabs) => self.tab_width(),
+                None => self
+                    .file_type()
+                    .indent
+                    .map_or(self.settings.indent_size, |(_, size)| size),
+            })
+    }
+
+    #[inline]
+    pub fn tab_width(&self) -> usize {
+        self.overrides.tab_width.unwrap_or(self.settings.tab_width)
+    }
+
+    /// What kind of file this is, from its modeline, `#!` line or extension, see
+    /// [`crate::filetype::detect`]. Decides comment markers, default indentation
+    /// and highlighting.
+    pub fn file_type(&self) -> &'static crate::filetype::FileType {
+        crate::filetype::detect(self.text_buffer.path(), &self.edge_lines())
+    }
+
+    /// The first and last five lines, where modelines go.
+    fn edge_lines(&self) -> Vec<String> {
+        let count = self.get_line_count();
+        let mut lines = self.get_lines_stripped(0..count.min(5));
+
+        if count > 5 {
+            lines.extend(self.get_lines_stripped(count.saturating_sub(5).max(5)..count));
+        }
+        lines
+    }
+
+    /// Whether `c` can be part of a word in this kind of file.
+    pub fn is_word_char(&self, c: char) -> bool {
+        self.file_type().is_word_char(c)
+    }
+
+    /// The selected text, trimmed, or else the word at the cursor. `None` when
+    /// there's neither.
+    pub fn word_at_cursor(&self) -> Option<String> {
+        if !self.cursor.no_selection() {
+            let text = self.get_selected_text().trim().to_string();
+
+            return (!text.is_empty()).then_some(text);
+        }
+
+        let (start, end) = self.word_bounds_at_cursor()?;
+
+        self.get_line_stripped(self.cursor.head.row)
+            .map(|line| line[start..end].to_string())
+    }
+
+    /// Columns the word the cursor is on or right after starts and ends at.
+    fn word_bounds_at_cursor(&self) -> Option<(usize, usize)> {
+        let head = self.cursor.head;
+        let line = self.get_line_stripped(head.row)?;
+        let col = head.col.min(line.len());
+        let start = line[..col]
+            .char_indices()
+            .rev()
+            .take_while(|&(_, c)| self.is_word_char(c))
+            .last()
+            .map_or(col, |(i, _)| i);
+        let end = line[col..]
+            .char_indices()
+            .find(|&(_, c)| !self.is_word_char(c))
+            .map_or(line.len(), |(i, _)| col + i);
+
+        (start < end).then_some((start, end))
+    }
+
+    /// Selects the word at the cursor or, with it selected, moves the selection on
+    /// to the next place the same text occurs, case and all, from the top again
+    /// past the last. There's one selection, so this moves it rather than adding
+    /// one. Only a selection within a line is looked for, and only in the
+    /// [`Document::MATCH_WINDOW`] lines from the cursor on. Returns whether the
+    /// selection changed.
+    pub fn select_next_match(&mut self) -> bool {
+        let row = self.cursor.head.row;
+
+        if self.cursor.no_selection() {
+            let Some((start, end)) = self.word_bounds_at_cursor() else {
+                return false;
+            };
+
+            self.cursor = editor_core::cursor::Cursor::new_selection(
+                editor_core::cursor::Position::new(row, start),
+                editor_core::cursor::Position::new(row, end),
+            );
+            return true;
+        }
+
+        let (start, end) = self.cursor.range();
+        if start.row != end.row {
+            return false;
+        }
+
+        let needle = self.get_selected_text();
+        let count = self.get_line_count();
+        // The rest of the selection's row, the rows after it, then around from the
+        // top back to the selection's row.
+        let found = (0..=count.min(Self::MATCH_WINDOW)).find_map(|i| {
+            let row = (end.row + i) % count;
+            let line = self.get_line_stripped(row)?;
+            let from = if i == 0 { end.col.min(line.len()) } else { 0 };
+
+            line.get(from..)?.find(&needle).map(|at| (row, from + at))
+        });
+        let Some((row, col)) = found.filter(|&found| found != (start.row, start.col)) else {
+            return false;
+        };
+
+        self.cursor = editor_core::cursor::Cursor::new_selection(
+            editor_core::cursor::Position::new(row, col),
+            editor_core::cursor::Position::new(row, col + needle.len()),
+        );
+        true
+    }
+
+    /// What an external tool gets to work on: the selection, or the whole note
+    /// without one, as its range and text. See [`crate::tools`].
+    pub fn tool_input(
+        &self,
+    ) -> (
+        editor_core::cursor::Position,
+        editor_core::cursor::Position,
+        String,
+    ) {
+        let (start, end) = if self.cursor.no_selection() {
+            let last = self.get_line_count().saturating_sub(1);
+            let len = self.get_line_stripped(last).map_or(0, |line| line.len());
+
+            (
+                editor_core::cursor::Position::new(0, 0),
+                editor_core::cursor::Position::new(last, len),
+            )
+        } else {
+            self.cursor.range()
+        };
+
+        (start, end, self.get_text_between(start, end))
+    }
+
+    /// Replaces `input`, the text from `start` to `end` a tool was given, with its
+    /// `output` as one undo step, see [`crate::tools::fit_output`]. Does nothing and
+    /// returns `false` if the text there changed while the tool ran.
+    pub fn replace_tool_input(
+        &mut self,
+        start: editor_core::cursor::Position,
+        end: editor_core::cursor::Position,
+        input: &str,
+        output: &str,
+    ) -> bool {
+        let last = self.get_line_count().saturating_sub(1);
+
+        if end.row > last
+            || end.col > self.get_line_stripped(end.row).map_or(0, |line| line.len())
+            || self.get_text_between(start, end) != input
+        {
+            return false;
+        }
+
+        let text = crate::tools::fit_output(input, output, self.text_buffer.line_ending.as_str());
+
+        self.cursor = editor_core::cursor::Cursor::new_selection(start, end);
+        self.apply_edits(vec![TextEdit::new(start, end, text)]);
+        true
+    }
+
+    /// Replaces `matches`, found in this note's lines, as one undo step. See
+    /// [`crate::replace`]. A selection around them stays on its text.
+    pub fn replace_matches(&mut self, matches: &[crate::replace::Match]) {
+        self.apply_edits(
+            matches
+                .iter()
+                .map(|found| {
+                    TextEdit::new(
+                        editor_core::cursor::Position::new(found.row, found.start),
+                        editor_core::cursor::Position::new(found.row, found.end),
+                        found.replacement.clone(),
+                    )
+                })
+                .collect(),
+        );
+    }
+
+    /// The text inserted for one level of indentation.
+    pub fn indent_unit(&self) -> String {
+        match self.indent_style() {
+            crate::settings::IndentStyle::Tabs => "\t".to_string(),
+            crate::settings::IndentStyle::Spaces => " ".repeat(self.indent_size()),
+        }
+    }
+
+    /// Short description of the indentation in effect, e.g. for a status bar.
+    pub fn indent_label(&self) -> String {
+        match self.indent_style() {
+            crate::settings::IndentStyle::Tabs => format!("Tabs: {}", self.tab_width()),
+            crate::settings::IndentStyle::Spaces => format!("Spaces: {}", self.indent_size()),
+        }
+    }
+
+    /// Save transforms with `.editorconfig` overrides layered on top of the user's toggles.
+    pub fn effective_save_transforms(&self) -> crate::save_transforms::SaveTransforms {
+        let mut transforms = self.save_transforms;
+
+        if let Some(trim) = self.overrides.trim_trailing_whitespace {
+            transforms.trim_trailing_whitespace = trim;
+        }
+
+        if let Some(final_newline) = self.overrides.insert_final_newline {
+            transforms.ensure_final_newline = final_newline;
+        }
+        if let Some(line_ending) = self.overrides.end_of_line {
+            transforms.line_ending = Some(line_ending);
+        }
+
+        transforms
+    }
+
+    /// Runs the configured save transforms as one undoable transaction, then saves.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer has no path or the atomic save fails.
+    pub fn save(&mut self) -> std::io::Result<()> {
+        self.apply_save_transforms();
+        self.text_buffer.save()?;
+        self.saved();
+        tracing::info!(path = ?self.text_buffer.path(), "Saved");
+        Ok(())
+    }
+
+    /// Same as [`Document::save`], but writes to (and adopts) a new path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the new destination cannot be written to.
+    pub fn save_as<P: AsRef<std::path::Path>>(&mut self, path: P) -> std::io::Result<()> {
+        self.apply_save_transforms();
+        self.text_buffer.save_as(&path)?;
+        self.saved();
+        tracing::info!(path = ?path.as_ref(), "Saved");
+        Ok(())
+    }
+
+    /// [`Document::save`] for drives that may be slow or unreachable. The write gives
+    /// up after `queue.timeout`; if it timed out or the drive looks gone, the note is
+    /// parked in the queue's local cache to be written later, and counts as saved.
+    /// A note already waiting in the queue goes straight behind it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the buffer has no path, if the write was refused for a
+    /// reason other than the network, or if the local cache can't be written either.
+    pub fn save_or_defer(
+        &mut self,
+        queue: &mut crate::save_queue::SaveQueue,
+    ) -> std::io::Result<crate::save_queue::SaveOutcome> {
+        let path = self
+            .text_buffer
+            .path()
+            .map(std::path::Path::to_path_buf)
+            .ok_or_else(|| {
+                std::io::Error::new(std::io::ErrorKind::NotFound, "Buffer has no file path")
+            })?;
+
+        self.apply_save_transforms();
+
+        let bytes = self.text_buffer.to_string().into_bytes();
+        let result = if queue.is_pending(&path) {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                "An earlier save of this note is still pending",
+            ))
+        } else {
+            crate::save_queue::write_with_timeout(&path, bytes.clone(), queue.timeout)
+        };
+
+        let outcome = match result {
+            Ok(()) => {
+                tracing::info!(?path, "Saved");
+                crate::save_queue::SaveOutcome::Saved
+            }
+            Err(err) if crate::save_queue::is_network_failure(&err) => {
+                queue.defer(&path, &bytes, &err)?;
+                crate::save_queue::SaveOutcome::Deferred
+            }
+            Err(err) => return Err(err),
+        };
+
+        self.text_buffer.mark_clean();
+        self.saved();
+        Ok(outcome)
+    }
+
+    /// Applies trailing-whitespace, blank-line and final-newline cleanup to the buffer.
+    pub fn apply_save_transforms(&mut self) {
+        let edits = self.effective_save_transforms().compute_edits(self);
+
+        self.apply_edits(edits);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use editor_core::cursor::{Cursor, Position};
+    use editor_core::text::TextBuffer;
+
+    fn setup() -> Document {
+        Document::new(TextBuffer::new().unwrap())
+    }
+
+    #[test]
+    fn test_newline_insertion_math() {
+        let mut doc = setup();
+
+        // Scenario: Pressing Enter on an empty line
+        doc.insert("\n");
+        assert_eq!(
+            doc.cursor.head,
+            Position::new(1, 0),
+            "Cursor should be at start of line 2"
+        );
+
+        // Scenario: Inserting text then Enter
+        doc.insert("Hi\n");
+        assert_eq!(
+            doc.cursor.head,
+            Position::new(2, 0),
+            "Cursor should be at start of line 3"
+        );
+    }
+
+    #[test]
+    fn test_undo_redo_newline_boundaries_fixed() {
+        let mut doc = setup();
+
+        println!("BEFORE LINE 1: {:#?}", doc);
+        // 1. First Insert
+        doc.insert("Line1");
+        // Manually break batching if your History allows it,
+        // or just accept that "Line1\nLine2" might be one transaction.
+
+        println!("BEFORE LINE 2: {:#?}", doc);
+        // 2. Second Insert starting with newline
+        doc.insert("\nLine2");
+
+        println!("BEFORE UNDO: {:#?}", doc);
+        // If batched, one undo goes to (0,0). If not, it goes to (0,5).
+        doc.undo();
+
+        let current_line = doc.text_buffer.get_line(0);
+        assert_eq!(current_line.unwrap(), "Line1");
+        assert_eq!(doc.cursor.head, Position::new(0, 5));
+    }
+
+    #[test]
+    fn test_get_line_stripped_edge_cases() {
+        let mut doc = setup();
+
+        // Case 1: Empty line
+        doc.insert("\n");
+        assert_eq!(doc.text_buffer.get_line_stripped(0).unwrap(), "");
+
+        // Case 2: Text without newline
+        doc.insert("Hello");
+        println!("{:#?}", doc);
+        assert_eq!(doc.text_buffer.get_line_stripped(1).unwrap(), "Hello");
+
+        // Case 3: Mixed content
+        doc.undo();
+        doc.undo(); // Clear
+        doc.insert("First\nSecond\nThird");
+        assert_eq!(doc.text_buffer.get_line_stripped(0).unwrap(), "First");
+        assert_eq!(doc.text_buffer.get_line_stripped(1).unwrap(), "Second");
+        assert_eq!(doc.text_buffer.get_line_stripped(2).unwrap(), "Third");
+    }
+
+    #[test]
+    fn test_backspace_at_line_boundary() {
+        let mut doc = setup();
+        doc.insert("ABC\nDEF");
+        doc.cursor = Cursor::new(1, 0); // Cursor at start of "DEF"
+
+        // Backspace should delete the '\n'
+        doc.delete(true);
+
+        assert_eq!(doc.text_buffer.get_line_stripped(0).unwrap(), "ABCDEF");
+        assert_eq!(doc.cursor.head, Position::new(0, 3));
+
+        doc.undo();
+        assert_eq!(doc.text_buffer.get_line_stripped(0).unwrap(), "ABC");
+        assert_eq!(doc.text_buffer.get_line_stripped(1).unwrap(), "DEF");
+        assert_eq!(doc.cursor.head, Position::new(1, 0));
+    }
+
+    #[test]
+    fn test_redo_restores_correct_cursor() {
+        let mut doc = setup();
+        doc.insert("Hello");
+        let pos_after_hello = doc.cursor.head;
+
+        doc.undo();
+        assert_eq!(doc.cursor.head, Position::new(0, 0));
+
+        doc.redo();
+        assert_eq!(doc.cursor.head, pos_after_hello);
+        assert_eq!(doc.text_buffer.get_line_stripped(0).unwrap(), "Hello");
+    }
+
+    #[test]
+    fn test_replace_selection_across_lines() {
+        let mut doc = setup();
+        doc.insert("Hello\nWorld\nEnd");
+
+        // Select "ello\nWorld\nE"
+        doc.cursor = Cursor::new_selection(Position::new(0, 1), Position::new(2, 1));
+
+        // Replace with "!"
+        doc.insert("!");
+
+        // Buffer should now be "H!nd"
+        // Line 0: "H!nd"
+        let line = doc.text_buffer.get_line(0).unwrap();
+        assert!(line.contains("H!nd"));
+        assert_eq!(doc.cursor.head, Position::new(0, 2));
+
+        doc.undo();
+        // Should restore original 3 lines
+        assert_eq!(doc.text_buffer.get_line(1).unwrap().trim_end(), "World");
+    }
+
+    #[test]
+    fn test_backspace_at_start_of_line_wraps() {
+        let mut doc = setup();
+        doc.insert("A\nB");
+        doc.cursor = Cursor::new(1, 0); // At start of 'B'
+
+        doc.delete(true); // Backspace
+
+        // Should have merged lines into "AB"
+        let line = doc.text_buffer.get_line(0).unwrap();
+        assert!(line.contains("AB"));
+        assert_eq!(doc.cursor.head, Position::new(0, 1));
+
+        doc.undo();
+        assert_eq!(doc.cursor.head, Position::new(1, 0));
+    }
+
+    #[test]
+    fn test_delete_forward_at_end_of_line() {
+        let mut doc = setup();
+        doc.insert("A\nB");
+        doc.cursor = Cursor::new(0, 1); // After 'A', before '\n'
+
+        doc.delete(false); // Forward Delete
+
+        let line = doc.text_buffer.get_line(0).unwrap();
+        assert!(line.contains("AB"));
+
+        doc.undo();
+        assert_eq!(doc.cursor.head, Position::new(0, 1));
+    }
+
+    #[test]
+    fn test_paste_and_match_indent() {
+        let mut doc = setup();
+        doc.insert("fn main() {\n    ");
+
+        doc.paste_and_match_indent("  let a = 1;\n  if a {\n      run();\n  }");
+
+        assert_eq!(
+            doc.text_buffer.to_string(),
+            "fn main() {\n    let a = 1;\n    if a {\n        run();\n    }"
+        );
+        assert_eq!(doc.cursor.head, Position::new(4, 5));
+
+        doc.undo();
+        assert_eq!(doc.text_buffer.to_string(), "fn main() {\n    ");
+    }
+
+    #[test]
+    fn test_paste_link() {
+        let mut doc = Document::new(TextBuffer::new_with_text("read the docs now").unwrap());
+
+        doc.cursor = Cursor::new(0, 5);
+        assert!(!doc.paste_link("https://docs.rs"));
+
+        doc.cursor = Cursor::new_selection(Position::new(0, 13), Position::new(0, 5));
+        assert!(doc.paste_link("https://docs.rs"));
+        assert_eq!(
+            doc.text_buffer.to_string(),
+            "read [the docs](https://docs.rs) now"
+        );
+        assert_eq!(doc.cursor, Cursor::new(0, 32));
+
+        doc.undo();
+        assert_eq!(doc.text_buffer.to_string(), "read the docs now");
+    }
+
+    #[test]
+    fn test_line_wise_cut_and_paste() {
+        let mut doc = Document::new(TextBuffer::new_with_text("one\ntwo\nthree").unwrap());
+
+        doc.cursor = Cursor::new(2, 3);
+        assert_eq!(doc.cut_line(), "three\n");
+        assert_eq!(doc.text_buffer.to_string(), "one\ntwo");
+        assert_eq!(doc.cursor, Cursor::new(1, 0));
+
+        doc.cursor = Cursor::new(0, 2);
+        doc.paste_lines("three\n");
+        assert_eq!(doc.text_buffer.to_string(), "three\none\ntwo");
+        assert_eq!(doc.cursor, Cursor::new(1, 2));
+
+        doc.cursor = Cursor::new(0, 1);
+        assert_eq!(doc.get_current_line(), "three\n");
+        assert_eq!(doc.cut_line(), "three\n");
+        assert_eq!(doc.text_buffer.to_string(), "one\ntwo");
+    }
+
+    #[test]
+    fn test_select_lines() {
+        let mut doc = Document::new(TextBuffer::new_with_text("one\ntwo\nthree").unwrap());
+
+        doc.select_lines(0, 1);
+        assert_eq!(
+            doc.cursor,
+            Cursor::new_selection(Position::new(0, 0), Position::new(2, 0))
+        );
+
+        doc.select_lines(2, 1);
+        assert_eq!(
+            doc.cursor,
+            Cursor::new_selection(Position::new(2, 5), Position::new(1, 0))
+        );
+    }
+
+    #[test]
+    fn test_apply_edits_is_single_sealed_transaction() {
+        let mut doc = Document::new(TextBuffer::new_with_text("one\ntwo\nthree").unwrap());
+        doc.cursor = Cursor::new(2, 2);
+
+        doc.apply_edits(vec![
+            TextEdit::new(Position::new(2, 0), Position::new(2, 0), "> "),
+            TextEdit::new(Position::new(0, 0), Position::new(1, 0), ""),
+        ]);
+
+        assert_eq!(doc.text_buffer.to_string(), "two\n> three");
+        assert_eq!(doc.cursor.head, Position::new(1, 4));
+        assert_eq!(doc.history.undo_stack.len(), 1);
+
+        // Typing right after a command must not be merged into it.
+        doc.insert("x");
+        assert_eq!(doc.history.undo_stack.len(), 2);
+
+        doc.undo();
+        doc.undo();
+        assert_eq!(doc.text_buffer.to_string(), "one\ntwo\nthree");
+        assert_eq!(doc.cursor.head, Position::new(2, 2));
+    }
+
+    #[test]
+    fn test_replace_all_lines_is_one_undo_step() {
+        let mut doc = Document::new(TextBuffer::new_with_text("one\ntwo").unwrap());
+
+        doc.replace_all_lines(vec![
+            "one".to_string(),
+            "2".to_string(),
+            "three".to_string(),
+        ]);
+        assert_eq!(doc.text_buffer.to_string(), "one\n2\nthree");
+
+        doc.undo();
+        assert_eq!(doc.text_buffer.to_string(), "one\ntwo");
+    }
+
+    #[test]
+    fn test_consecutive_inserts_batching() {
+        let mut doc = setup();
+        doc.insert("a");
+        doc.insert("b");
+        doc.insert("c");
+
+        // Since we are typing character by character, History should batch them
+        assert_eq!(doc.history.undo_stack.len(), 1);
+
+        doc.undo();
+        assert_eq!(doc.cursor.head, Position::new(0, 0));
+        assert!(
+            doc.text_buffer.get_line(0).is_none()
+                || doc.text_buffer.get_line(0).unwrap().is_empty()
+        );
+    }
+
+    #[test]
+    fn test_open_file_applies_editorconfig_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("note.md");
+        std::fs::write(&path, "hello\n").unwrap();
+        std::fs::write(
+            dir.path().join(".editorconfig"),
+            "root = true\n[*.md]\nindent_style = space\nindent_size = 2\nend_of_line = crlf\ntrim_trailing_whitespace = true\n",
+        )
+        .unwrap();
+
+        let mut doc = setup();
+        doc.open_file(&path).unwrap();
+
+        assert_eq!(doc.indent_unit(), "  ");
+        assert_eq!(doc.tab_width(), 2);
+        assert_eq!(
+            doc.text_buffer.line_ending,
+            editor_core::text::LineEnding::CRLF
+        );
+        assert!(doc.effective_save_transforms().trim_trailing_whitespace);
+        assert!(
+            !doc.save_transforms.trim_trailing_whitespace,
+            "Overrides never touch the global toggles"
+        );
+
+        doc.save().unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "hello\r\n");
+    }
+
+    #[test]
+    fn test_detected_indentation_drives_tab_and_newline() {
+        let mut doc = Document::new(TextBuffer::new_with_text("- a\n  - b\n").unwrap());
+
+        assert_eq!(doc.indent_unit(), "  ");
+        assert_eq!(doc.indent_label(), "Spaces: 2");
+
+        doc.cursor = Cursor::new(1, 5);
+        doc.insert_newline();
+        assert_eq!(doc.get_line_stripped(2).as_deref(), Some("  "));
+        assert_eq!(doc.cursor.head, Position::new(2, 2));
+
+        doc.overrides.indent_style = Some(crate::settings::IndentStyle::Tabs);
+        assert_eq!(doc.indent_unit(), "\t", ".editorconfig wins over detection");
+    }
+
+    #[test]
+    fn test_reindent_selection_only_touches_selected_lines() {
+        let mut doc = Document::new(TextBuffer::new_with_text("a\n  b\n   c\n  d").unwrap());
+        doc.overrides.indent_style = Some(crate::settings::IndentStyle::Spaces);
+        doc.overrides.indent_size = Some(4);
+        doc.cursor = Cursor::new_selection(Position::new(2, 0), Position::new(3, 0));
+
+        doc.reindent(crate::transform::Scope::Selection);
+
+        assert_eq!(doc.text_buffer.to_string(), "a\n  b\n        c\n  d");
+        assert_eq!(doc.history.undo_stack.len(), 1);
+        assert_eq!(
+            doc.cursor.range(),
+            (Position::new(2, 0), Position::new(3, 0)),
+            "Selection kept"
+        );
+
+        doc.reindent(crate::transform::Scope::Document);
+        assert_eq!(doc.text_buffer.to_string(), "a\n    b\n        c\n    d");
+
+        doc.undo();
+        assert_eq!(doc.text_buffer.to_string(), "a\n  b\n        c\n  d");
+    }
+
+    #[test]
+    fn test_toggle_comment_over_selection() {
+        let mut doc =
+            Document::new(TextBuffer::new_with_text("fn a() {\n    b();\n\n    c();\n}").unwrap());
+        let dir = tempfile::tempdir().unwrap();
+        doc.text_buffer.save_as(dir.path().join("x.rs")).unwrap();
+        doc.cursor = Cursor::new_selection(Position::new(1, 6), Position::new(3, 2));
+
+        doc.toggle_comment();
+
+        assert_eq!(
+            doc.text_buffer.to_string(),
+            "fn a() {\n    // b();\n\n    // c();\n}"
+        );
+        assert_eq!(
+            doc.cursor.anchor,
+            Position::new(1, 9),
+            "Cursor stays on its text"
+        );
+        assert_eq!(doc.history.undo_stack.len(), 1);
+
+        doc.toggle_comment();
+        assert_eq!(
+            doc.text_buffer.to_string(),
+            "fn a() {\n    b();\n\n    c();\n}"
+        );
+    }
+
+    #[test]
+    fn test_case_transform_keeps_selection() {
+        let mut doc = Document::new(TextBuffer::new_with_text("hello world\nsecond").unwrap());
+        doc.cursor = Cursor::new_selection(Position::new(0, 6), Position::new(1, 3));
+
+        doc.transform_text(crate::transform::Scope::Selection, str::to_uppercase);
+
+        assert_eq!(doc.text_buffer.to_string(), "hello WORLD\nSECond");
+        assert_eq!(doc.cursor.start(), Position::new(0, 6));
+        assert_eq!(doc.cursor.end(), Position::new(1, 3));
+
+        doc.cursor = Cursor::new(1, 0);
+        doc.transform_text(
+            crate::transform::Scope::Selection,
+            crate::transform::title_case,
+        );
+        assert_eq!(doc.text_buffer.to_string(), "hello WORLD\nSecond");
+
+        doc.cursor = Cursor::new_selection(Position::new(1, 3), Position::new(0, 6));
+        doc.transform_text(crate::transform::Scope::Selection, str::to_lowercase);
+        assert_eq!(doc.text_buffer.to_string(), "hello world\nsecond");
+        assert_eq!(doc.cursor.head, Position::new(0, 6), "Same way round");
+
+        doc.transform_text(crate::transform::Scope::Document, str::to_uppercase);
+        assert_eq!(doc.text_buffer.to_string(), "HELLO WORLD\nSECOND");
+        assert_eq!(
+            doc.cursor.range(),
+            (Position::new(0, 6), Position::new(1, 3))
+        );
+    }
+
+    #[test]
+    fn test_line_transforms() {
+        let mut doc = Document::new(TextBuffer::new_with_text("c\na\nb\nz").unwrap());
+        doc.cursor = Cursor::new_selection(Position::new(0, 0), Position::new(3, 0));
+
+        doc.transform_lines(|mut lines| {
+            lines.sort();
+            lines
+        });
+        assert_eq!(doc.text_buffer.to_string(), "a\nb\nc\nz");
+
+        doc.transform_lines(|mut lines| {
+            lines.reverse();
+            lines
+        });
+        assert_eq!(doc.text_buffer.to_string(), "c\nb\na\nz");
+        assert_eq!(doc.history.undo_stack.len(), 2);
+
+        doc.cursor = Cursor::new_selection(Position::new(1, 1), Position::new(0, 0));
+        doc.sort_lines(
+            &crate::transform::SortOptions::default(),
+            crate::transform::Scope::Selection,
+        );
+        assert_eq!(doc.text_buffer.to_string(), "b\nc\na\nz");
+        assert_eq!(
+            (doc.cursor.anchor, doc.cursor.head),
+            (Position::new(1, 1), Position::new(0, 0)),
+            "Same way round"
+        );
+
+        doc.sort_lines(
+            &crate::transform::SortOptions::default(),
+            crate::transform::Scope::Document,
+        );
+        assert_eq!(doc.text_buffer.to_string(), "a\nb\nc\nz");
+        assert_eq!(doc.cursor.head, Position::new(0, 0), "Cursor stays");
+
+        doc.cursor = Cursor::new(2, 0);
+        doc.join_lines();
+        assert_eq!(doc.text_buffer.to_string(), "a\nb\nc z");
+    }
+
+    #[test]
+    fn test_duplicate_line_and_selection() {
+        let mut doc = Document::new(TextBuffer::new_with_text("one\ntwo").unwrap());
+        doc.cursor = Cursor::new(1, 2);
+
+        doc.duplicate();
+        assert_eq!(doc.text_buffer.to_string(), "one\ntwo\ntwo");
+        assert_eq!(doc.cursor.head, Position::new(2, 2));
+
+        doc.cursor = Cursor::new_selection(Position::new(0, 1), Position::new(1, 1));
+        doc.duplicate();
+        assert_eq!(doc.text_buffer.to_string(), "one\ntne\ntwo\ntwo");
+        assert_eq!(doc.cursor.start(), Position::new(1, 1));
+        assert_eq!(doc.cursor.end(), Position::new(2, 1));
+        assert_eq!(doc.history.undo_stack.len(), 2);
+
+        doc.undo();
+        assert_eq!(doc.text_buffer.to_string(), "one\ntwo\ntwo");
+    }
+
+    #[test]
+    fn test_pad_line_to() {
+        let mut doc = Document::new(TextBuffer::new_with_text("ab\ncd").unwrap());
+
+        doc.cursor = Cursor::new(0, 2);
+        assert!(doc.pad_line_to(5));
+        assert_eq!(doc.text_buffer.to_string(), "ab   \ncd");
+        assert_eq!(doc.cursor, Cursor::new(0, 5));
+        assert!(!doc.pad_line_to(3));
+    }
+
+    #[test]
+    fn test_swaps() {
+        let mut doc = Document::new(TextBuffer::new_with_text("tihs é").unwrap());
+
+        doc.cursor = Cursor::new(0, 2);
+        assert!(doc.transpose_chars());
+        assert_eq!(doc.text_buffer.to_string(), "this é");
+        assert_eq!(doc.cursor, Cursor::new(0, 3));
+
+        doc.cursor = Cursor::new(0, 7);
+        assert!(doc.transpose_chars());
+        assert_eq!(doc.text_buffer.to_string(), "thisé ");
+
+        doc.cursor = Cursor::new(0, 0);
+        assert!(!doc.transpose_chars());
+
+        doc.cursor = Cursor::new_selection(Position::new(0, 0), Position::new(0, 4));
+        assert_eq!(doc.swap_selection("that"), Some("this".to_string()));
+        assert_eq!(doc.text_buffer.to_string(), "thaté ");
+
+        doc.swap_anchor_and_head();
+        assert_eq!(
+            doc.cursor,
+            Cursor::new_selection(Position::new(0, 4), Position::new(0, 0))
+        );
+
+        doc.undo();
+        assert_eq!(doc.text_buffer.to_string(), "thisé ");
+    }
+
+    #[test]
+    fn test_outline_follows_edits_and_undo() {
+        let mut doc = Document::new(TextBuffer::new_with_text("# A\ntext\n## B").unwrap());
+        doc.cursor = Cursor::new(1, 0);
+
+        doc.insert("## New\n");
+        assert_eq!(
+            doc.outline.headings,
+            crate::outline::Outline::build(&doc.text_buffer).headings
+        );
+        assert_eq!(doc.outline.headings.len(), 3);
+
+        doc.undo();
+        assert_eq!(doc.outline.headings.len(), 2);
+        assert_eq!(doc.outline.headings[1].row, 2);
+
+        doc.redo();
+        doc.cursor = Cursor::new_selection(Position::new(0, 0), Position::new(1, 0));
         doc.delete(true);
+        assert_eq!(
+            doc.outline.headings,
+            crate::outline::Outline::build(&doc.text_buffer).headings
+        );
+    }
 
-        assert_eq!(doc.text_buffer.get_line_stripped(0).unwrap(), "ABCDEF");
-        assert_eq!(doc.cursor.head, Position::new(0, 3));
+    #[test]
+    fn test_paragraph_at() {
+        let doc = Document::new(TextBuffer::new_with_text("a\nb\n\nc\nd\ne\n  \nf").unwrap());
 
-        doc.undo();
-        assert_eq!(doc.text_buffer.get_line_stripped(0).unwrap(), "ABC");
-        assert_eq!(doc.text_buffer.get_line_stripped(1).unwrap(), "DEF");
-        assert_eq!(doc.cursor.head, Position::new(1, 0));
+        assert_eq!(doc.paragraph_at(1), 0..2);
+        assert_eq!(doc.paragraph_at(4), 3..6);
+        assert_eq!(doc.paragraph_at(2), 2..3);
+        assert_eq!(doc.paragraph_at(7), 7..8);
     }
 
     #[test]
-    fn test_redo_restores_correct_cursor() {
+    fn test_revert_is_undoable_and_keeps_cursor() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("note.md");
+        std::fs::write(&path, "# Title\nline two\n").unwrap();
+
         let mut doc = setup();
-        doc.insert("Hello");
-        let pos_after_hello = doc.cursor.head;
+        doc.open_file(&path).unwrap();
+        doc.cursor = Cursor::new(1, 4);
+        doc.insert("XXXXXXXXX");
+        doc.cursor = Cursor::new(1, 12);
+
+        doc.revert().unwrap();
+        assert_eq!(doc.text_buffer.to_string(), "# Title\nline two\n");
+        assert_eq!(
+            doc.cursor.head,
+            Position::new(1, 8),
+            "Clamped to the reloaded line"
+        );
+        assert_eq!(
+            doc.history.undo_stack.last().unwrap().actions,
+            vec![editor_core::enums::EditAction::Delete {
+                pos: Position::new(1, 4),
+                end: Position::new(1, 13),
+                text: "XXXXXXXXX".to_string(),
+            }],
+            "Only the changed span is kept for undo"
+        );
 
         doc.undo();
-        assert_eq!(doc.cursor.head, Position::new(0, 0));
+        assert_eq!(doc.text_buffer.to_string(), "# Title\nlineXXXXXXXXX two\n");
 
         doc.redo();
-        assert_eq!(doc.cursor.head, pos_after_hello);
-        assert_eq!(doc.text_buffer.get_line_stripped(0).unwrap(), "Hello");
+        assert_eq!(doc.text_buffer.to_string(), "# Title\nline two\n");
     }
 
     #[test]
-    fn test_replace_selection_across_lines() {
+    fn test_format_document_and_fold() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data.json");
+        std::fs::write(&path, "{\"a\": [1, 2], \"b\": {}}\n").unwrap();
+
         let mut doc = setup();
-        doc.insert("Hello\nWorld\nEnd");
+        doc.open_file(&path).unwrap();
+        doc.settings.indent_size = 2;
+        doc.settings.indent_style = crate::settings::IndentStyle::Spaces;
 
-        // Select "ello\nWorld\nE"
-        doc.cursor = Cursor::new_selection(Position::new(0, 1), Position::new(2, 1));
+        assert!(doc.format_document().unwrap());
+        assert_eq!(
+            doc.text_buffer.to_string(),
+            "{\n  \"a\": [\n    1,\n    2\n  ],\n  \"b\": {}\n}\n"
+        );
 
-        // Replace with "!"
-        doc.insert("!");
+        doc.cursor = Cursor::new(2, 0);
+        assert!(doc.toggle_fold(2));
+        assert!(doc.folds.is_hidden(3) && !doc.folds.is_hidden(5));
+        assert_eq!(doc.cursor.head, Position::new(1, 0));
 
-        // Buffer should now be "H!nd"
-        // Line 0: "H!nd"
-        let line = doc.text_buffer.get_line(0).unwrap();
-        assert!(line.contains("H!nd"));
-        assert_eq!(doc.cursor.head, Position::new(0, 2));
+        // Editing above the fold moves it along.
+        doc.cursor = Cursor::new(0, 1);
+        doc.insert("\n");
+        assert_eq!(
+            doc.folds.folded(),
+            &[crate::folds::Fold { start: 2, end: 5 }]
+        );
 
         doc.undo();
-        // Should restore original 3 lines
-        assert_eq!(doc.text_buffer.get_line(1).unwrap().trim_end(), "World");
+        doc.undo();
+        assert_eq!(doc.text_buffer.to_string(), "{\"a\": [1, 2], \"b\": {}}\n");
     }
 
     #[test]
-    fn test_backspace_at_start_of_line_wraps() {
+    fn test_open_file_applies_modeline() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(".editorconfig"),
+            "[*]\nindent_style = tab\n",
+        )
+        .unwrap();
+        let path = dir.path().join("notes.md");
+        std::fs::write(&path, "text\n<!-- vim: set et sw=3 tw=60: -->\n").unwrap();
+
         let mut doc = setup();
-        doc.insert("A\nB");
-        doc.cursor = Cursor::new(1, 0); // At start of 'B'
+        doc.open_file(&path).unwrap();
 
-        doc.delete(true); // Backspace
+        assert_eq!(doc.indent_unit(), "   ");
+        assert_eq!(doc.overrides.max_line_length, Some(60));
+    }
 
-        // Should have merged lines into "AB"
-        let line = doc.text_buffer.get_line(0).unwrap();
-        assert!(line.contains("AB"));
-        assert_eq!(doc.cursor.head, Position::new(0, 1));
+    #[test]
+    fn test_file_type_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("deploy");
+        std::fs::write(&path, "#!/usr/bin/env python3\nrun()\n").unwrap();
+
+        let mut doc = setup();
+        doc.open_file(&path).unwrap();
+
+        assert_eq!(doc.file_type().name, "python");
+        assert_eq!(doc.indent_unit(), "    ");
+
+        doc.cursor = Cursor::new(1, 0);
+        doc.toggle_comment();
+        assert_eq!(doc.get_line_stripped(1).unwrap(), "# run()");
+    }
+
+    #[test]
+    fn test_type_text_with_smart_typography() {
+        let mut doc = Document::new(TextBuffer::new_with_text("```\n```\n\n").unwrap());
+
+        doc.smart_typography = true;
+        doc.cursor = Cursor::new(2, 0);
+        for c in "\"It's--done...\"".chars() {
+            doc.type_text(&c.to_string());
+        }
+        assert_eq!(doc.get_line_stripped(2).unwrap(), "“It’s–done…”");
+
+        doc.undo();
+        assert_eq!(doc.get_line_stripped(2).unwrap(), "“It’s–done…\"");
+
+        doc.cursor = Cursor::new(1, 0);
+        doc.type_text("'");
+        assert_eq!(doc.get_line_stripped(1).unwrap(), "'```");
+    }
+
+    #[test]
+    fn test_insert_and_jump_footnote() {
+        let mut doc = Document::new(TextBuffer::new_with_text("See this.\n").unwrap());
+
+        doc.cursor = Cursor::new(0, 8);
+        doc.insert_footnote();
+        assert_eq!(doc.text_buffer.to_string(), "See this[^1].\n\n[^1]: \n");
+        assert_eq!(doc.cursor.head, Position::new(2, 6));
+
+        doc.insert("Source.");
+        assert!(doc.jump_footnote());
+        assert_eq!(doc.cursor.head, Position::new(0, 12));
+
+        doc.cursor = Cursor::new(0, 9);
+        assert!(doc.jump_footnote());
+        assert_eq!(doc.cursor.head, Position::new(2, 6));
+
+        doc.undo();
+        doc.undo();
+        assert_eq!(doc.text_buffer.to_string(), "See this.\n");
+    }
+
+    #[test]
+    fn test_insert_run_result_replaces_earlier_output() {
+        let text = "```sh\necho hi\n```\nafter\n";
+        let mut doc = Document::new(TextBuffer::new_with_text(text).unwrap());
+        let (fence, source) = doc.code_block(0).unwrap();
+
+        assert_eq!((fence.as_str(), source.as_str()), ("```sh", "echo hi\n"));
+        assert!(doc.insert_run_result(
+            0,
+            &source,
+            &["```output".into(), "hi".into(), "```".into()]
+        ));
+        assert!(doc.insert_run_result(
+            0,
+            &source,
+            &["```output".into(), "again".into(), "```".into()]
+        ));
+        assert_eq!(
+            doc.text_buffer.to_string(),
+            "```sh\necho hi\n```\n```output\nagain\n```\nafter\n"
+        );
+        assert!(!doc.insert_run_result(0, "echo bye\n", &[]));
 
         doc.undo();
+        assert!(doc.text_buffer.to_string().contains("\nhi\n"));
+    }
+
+    #[test]
+    fn test_calculations() {
+        let mut doc = Document::new(TextBuffer::new_with_text("\n").unwrap());
+
+        for c in "Lunch 12*14=".chars() {
+            doc.type_text(&c.to_string());
+        }
+        assert_eq!(doc.calculation, Some((Position::new(0, 12), "168".into())));
+        assert!(doc.accept_calculation());
+        assert!(!doc.accept_calculation());
+
+        doc.type_text(" (1+2");
+        assert_eq!(doc.calculation, None);
+        doc.cursor = Cursor::new_selection(Position::new(0, 17), Position::new(0, 20));
+        assert_eq!(doc.insert_calculation(), Ok(()));
+        assert_eq!(doc.text_buffer.to_string(), "Lunch 12*14=168 (1+2 = 3\n");
+        assert!(doc.insert_calculation().is_err());
+    }
+
+    #[test]
+    fn test_numbered_items() {
+        let text = "1. One\n2. Two\n3. Three\n\nThen\n\n1. Other\n1. List\n";
+        let mut doc = Document::new(TextBuffer::new_with_text(text).unwrap());
+
+        doc.cursor = Cursor::new(0, 6);
+        assert!(doc.insert_numbered_item());
+        assert_eq!(
+            doc.text_buffer.to_string(),
+            "1. One\n2. \n3. Two\n4. Three\n\nThen\n\n1. Other\n1. List\n"
+        );
+        assert_eq!(doc.cursor.head, Position::new(1, 3));
+
+        doc.undo();
+        assert_eq!(doc.text_buffer.to_string(), text);
+
+        doc.replace_all_lines(vec!["1. [x] a".into(), "2. b".into()]);
+        doc.cursor = Cursor::new(0, 8);
+        doc.press_enter();
+        assert_eq!(doc.text_buffer.to_string(), "1. [x] a\n2. [ ] \n3. b\n");
+        assert!(doc.indent_list_item());
+        assert_eq!(
+            doc.cursor.head,
+            Position::new(1, doc.indent_unit().len() + 7)
+        );
+        doc.press_enter();
+        assert_eq!(doc.text_buffer.to_string(), "1. [x] a\n\n3. b\n");
         assert_eq!(doc.cursor.head, Position::new(1, 0));
+
+        doc.replace_all_lines(vec!["1. a".into(), "1. b".into(), "1. c".into()]);
+        doc.cursor = Cursor::new(0, 0);
+        doc.renumber_lists();
+        assert_eq!(doc.text_buffer.to_string(), "1. a\n2. b\n3. c\n");
     }
 
     #[test]
-    fn test_delete_forward_at_end_of_line() {
-        let mut doc = setup();
-        doc.insert("A\nB");
-        doc.cursor = Cursor::new(0, 1); // After 'A', before '\n'
+    fn test_word_at_cursor() {
+        let mut doc = Document::new(TextBuffer::new_with_text("look up words\n").unwrap());
+
+        doc.cursor = Cursor::new(0, 6);
+        assert_eq!(doc.word_at_cursor().as_deref(), Some("up"));
+        doc.cursor = Cursor::new(0, 13);
+        assert_eq!(doc.word_at_cursor().as_deref(), Some("words"));
+        doc.cursor = Cursor::new_selection(Position::new(0, 4), Position::new(0, 8));
+        assert_eq!(doc.word_at_cursor().as_deref(), Some("up"));
+    }
 
-        doc.delete(false); // Forward Delete
+    #[test]
+    fn test_select_next_match() {
+        let mut doc = Document::new(TextBuffer::new_with_text("ab Ab\nx ab\n").unwrap());
 
-        let line = doc.text_buffer.get_line(0).unwrap();
-        assert!(line.contains("AB"));
+        doc.cursor = Cursor::new(0, 1);
+        assert!(doc.select_next_match());
+        assert_eq!(
+            doc.cursor,
+            Cursor::new_selection(Position::new(0, 0), Position::new(0, 2))
+        );
+
+        assert!(doc.select_next_match());
+        assert_eq!(
+            doc.cursor,
+            Cursor::new_selection(Position::new(1, 2), Position::new(1, 4))
+        );
+
+        // Around to the top again.
+        assert!(doc.select_next_match());
+        assert_eq!(doc.cursor.start(), Position::new(0, 0));
+
+        doc.cursor = Cursor::new_selection(Position::new(0, 3), Position::new(0, 5));
+        assert!(!doc.select_next_match());
+    }
+
+    #[test]
+    fn test_replace_tool_input() {
+        let mut doc = Document::new(TextBuffer::new_with_text("one two\nthree\n").unwrap());
+
+        doc.cursor = Cursor::new_selection(Position::new(0, 4), Position::new(1, 2));
+
+        let (start, end, input) = doc.tool_input();
+
+        assert_eq!(input, "two\nth");
+        assert!(doc.replace_tool_input(start, end, &input, "TWO\nTH\n"));
+        assert_eq!(doc.text_buffer.to_string(), "one TWO\nTHree\n");
+        assert_eq!(doc.history.undo_stack.len(), 1);
+        assert!(
+            !doc.replace_tool_input(start, end, &input, "x"),
+            "Changed meanwhile"
+        );
+
+        doc.cursor = Cursor::new(0, 0);
+        let (start, end, input) = doc.tool_input();
+
+        assert_eq!(input, "one TWO\nTHree", "The final line break stays");
+        assert!(doc.replace_tool_input(start, end, &input, "done\n"));
+        assert_eq!(doc.text_buffer.to_string(), "done\n");
+    }
+
+    #[test]
+    fn test_replace_matches() {
+        let mut doc = Document::new(TextBuffer::new_with_text("teh one\nand teh teh\n").unwrap());
+        let pattern = crate::replace::Pattern {
+            find: "teh".into(),
+            replace: "the".into(),
+            case_sensitive: true,
+            regex: false,
+        };
+        let lines = doc.get_lines_stripped(0..doc.get_line_count());
+        let matches = crate::replace::find_in_lines(&pattern, &lines).unwrap();
+
+        doc.replace_matches(&matches);
+        assert_eq!(doc.text_buffer.to_string(), "the one\nand the the\n");
+        assert_eq!(doc.history.undo_stack.len(), 1);
+
+        // Only the selected "the"s, and the selection stays on them.
+        doc.cursor = Cursor::new_selection(Position::new(1, 0), Position::new(1, 7));
+        let pattern = crate::replace::Pattern {
+            find: "the".into(),
+            replace: "a".into(),
+            ..pattern
+        };
+        let lines = doc.get_lines_stripped(0..doc.get_line_count());
+        let (start, end) = doc.scope_range(crate::transform::Scope::Selection);
+        let matches = crate::replace::within(
+            crate::replace::find_in_lines(&pattern, &lines).unwrap(),
+            start,
+            end,
+        );
+
+        doc.replace_matches(&matches);
+        assert_eq!(doc.text_buffer.to_string(), "the one\nand a the\n");
+        assert_eq!(
+            doc.cursor.range(),
+            (Position::new(1, 0), Position::new(1, 5))
+        );
+    }
+
+    #[test]
+    fn test_resolve_conflict_is_one_undo_step() {
+        let text = "top\n<<<<<<< HEAD\nmine\n=======\nyours\n>>>>>>> other\nbottom\n";
+        let mut doc = Document::new(TextBuffer::new_with_text(text).unwrap());
+
+        assert_eq!(doc.conflicts.len(), 1);
+        assert_eq!(doc.next_conflict(0), Some(1));
+        assert_eq!(doc.next_conflict(3), Some(1), "Wraps around");
+
+        doc.cursor = Cursor::new(0, 0);
+        assert!(!doc.resolve_conflict(crate::conflict::Resolution::Ours));
+
+        doc.cursor = Cursor::new(4, 2);
+        assert!(doc.resolve_conflict(crate::conflict::Resolution::Theirs));
+        assert_eq!(doc.text_buffer.to_string(), "top\nyours\nbottom\n");
+        assert!(doc.conflicts.is_empty());
+        assert_eq!(doc.history.undo_stack.len(), 1);
 
         doc.undo();
-        assert_eq!(doc.cursor.head, Position::new(0, 1));
+        assert_eq!(doc.text_buffer.to_string(), text);
+        assert_eq!(doc.conflicts.len(), 1);
     }
 
     #[test]
-    fn test_consecutive_inserts_batching() {
+    fn test_conflicts_follow_edits() {
+        let text = "<<<<<<< a\n=======\n<<<<<<< b\n>>>>>>>\n";
+        let mut doc = Document::new(TextBuffer::new_with_text(text).unwrap());
+
+        assert!(doc.conflicts.is_empty());
+
+        // Removing the stray marker pairs up the others.
+        doc.apply_edits(vec![TextEdit::new(
+            Position::new(2, 0),
+            Position::new(3, 0),
+            "",
+        )]);
+        assert_eq!(doc.conflicts.len(), 1);
+
+        doc.apply_edits(vec![TextEdit::new(
+            Position::new(0, 0),
+            Position::new(0, 0),
+            "x\n<\n",
+        )]);
+        assert_eq!(doc.conflicts[0].start, 2);
+        assert_eq!(doc.conflicts[0].end, 4);
+    }
+
+    #[test]
+    fn test_resolve_conflict_at_end_of_file() {
+        let mut doc =
+            Document::new(TextBuffer::new_with_text("<<<<<<<\na\n=======\nb\n>>>>>>>").unwrap());
+
+        doc.cursor = Cursor::new(0, 0);
+        assert!(doc.resolve_conflict(crate::conflict::Resolution::Both));
+        assert_eq!(doc.text_buffer.to_string(), "a\nb");
+    }
+
+    #[test]
+    fn test_rename_note_updates_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let note = dir.path().join("draft.md");
+        let index = dir.path().join("index.md");
+        std::fs::write(&note, "self: [[draft]]\n").unwrap();
+        std::fs::write(&index, "- [[Draft|my draft]]\n- [[other]]\n").unwrap();
+
         let mut doc = setup();
-        doc.insert("a");
-        doc.insert("b");
-        doc.insert("c");
+        doc.open_file(&note).unwrap();
 
-        // Since we are typing character by character, History should batch them
+        let updated = doc.rename_note("final", true).unwrap();
+
+        assert_eq!(updated, vec![index.clone()]);
+        assert!(!note.exists());
+        assert_eq!(
+            doc.text_buffer.path(),
+            Some(dir.path().join("final.md").as_path())
+        );
+        assert_eq!(doc.text_buffer.to_string(), "self: [[final]]\n");
+        assert_eq!(
+            std::fs::read_to_string(&index).unwrap(),
+            "- [[final|my draft]]\n- [[other]]\n"
+        );
+        assert!(
+            doc.rename_note("index", false).is_err(),
+            "Name already taken"
+        );
+        for name in ["../escaped", "sub/note", "..", " "] {
+            assert_eq!(
+                doc.rename_note(name, false).unwrap_err().kind(),
+                std::io::ErrorKind::InvalidInput
+            );
+        }
+
+        doc.rename_note("Final", false).unwrap();
+        let names: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert!(names.contains(&"Final.md".into()) && !names.contains(&"final.md".into()));
+        assert_eq!(doc.text_buffer.to_string(), "self: [[final]]\n");
+    }
+
+    #[test]
+    fn test_section_refactoring() {
+        let dir = tempfile::tempdir().unwrap();
+        let note = dir.path().join("note.md");
+        let other = dir.path().join("other.md");
+        std::fs::write(&note, "# Top\n## A\na\n### A.1\n\n## B\nb\n").unwrap();
+        std::fs::write(&other, "# Other\n\n").unwrap();
+
+        let mut doc = setup();
+        doc.open_file(&note).unwrap();
+        doc.cursor = Cursor::new(2, 1);
+
+        assert!(doc.move_section(true));
+        assert_eq!(
+            doc.text_buffer.to_string(),
+            "# Top\n## B\nb\n\n## A\na\n### A.1\n",
+            "The blank line between them stays"
+        );
+        assert_eq!(doc.cursor.head, Position::new(5, 1), "Moved along");
+        assert!(!doc.move_section(true), "Last one");
+        assert!(doc.move_section(false));
+        assert_eq!(
+            doc.text_buffer.to_string(),
+            "# Top\n## A\na\n### A.1\n\n## B\nb\n"
+        );
+
+        assert!(doc.change_section_level(-1));
+        assert_eq!(
+            doc.text_buffer.to_string(),
+            "# Top\n# A\na\n## A.1\n\n## B\nb\n"
+        );
+        assert!(!doc.change_section_level(-1), "Already at level 1");
+        assert_eq!(doc.history.undo_stack.len(), 3);
+
+        doc.cursor = Cursor::new(6, 0);
+        doc.move_section_to(&other).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&other).unwrap(),
+            "# Other\n\n## B\nb\n"
+        );
+        assert_eq!(doc.text_buffer.to_string(), "# Top\n# A\na\n## A.1\n\n");
+        assert!(doc.move_section_to(&note).is_err(), "Same note");
+    }
+
+    #[test]
+    fn test_rename_heading_updates_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let note = dir.path().join("trips.md");
+        let index = dir.path().join("index.md");
+        std::fs::write(
+            &note,
+            "- [Day Two](#day-two)\n\n## Day Two\nsee [[#Day Two]]\n",
+        )
+        .unwrap();
+        std::fs::write(&index, "[[Trips#day-two|then]] [[Trips#Day One]]\n").unwrap();
+
+        let mut doc = setup();
+        doc.open_file(&note).unwrap();
+        doc.cursor = Cursor::new(3, 7);
+        assert_eq!(
+            doc.link_at_cursor(),
+            Some(crate::workspace::WikiLink {
+                note: String::new(),
+                heading: Some("Day Two".to_string())
+            })
+        );
+        assert!(doc.rename_heading("Arrival").is_err(), "Not on a heading");
+
+        doc.cursor = Cursor::new(2, 4);
+        let updated = doc.rename_heading("Arrival").unwrap();
+
+        assert_eq!(updated, vec![index.clone()]);
+        assert_eq!(
+            doc.text_buffer.to_string(),
+            "- [Day Two](#arrival)\n\n## Arrival\nsee [[#Arrival]]\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(&note).unwrap(),
+            doc.text_buffer.to_string()
+        );
+        assert_eq!(
+            std::fs::read_to_string(&index).unwrap(),
+            "[[Trips#Arrival|then]] [[Trips#Day One]]\n"
+        );
+        assert_eq!(doc.outline.find_anchor("arrival").map(|h| h.row), Some(2));
         assert_eq!(doc.history.undo_stack.len(), 1);
+    }
+
+    #[test]
+    fn test_extract_and_merge_notes() {
+        let dir = tempfile::tempdir().unwrap();
+        let note = dir.path().join("project.md");
+        let index = dir.path().join("index.md");
+        std::fs::write(&note, "# Project\nIdeas:\n- one\n- two\nsee [[index]]\n").unwrap();
+        std::fs::write(&index, "- [[project]]\n").unwrap();
+
+        let mut doc = setup();
+        doc.open_file(&note).unwrap();
+        doc.cursor = Cursor::new_selection(Position::new(2, 0), Position::new(4, 0));
+
+        let ideas = doc.extract_selection("ideas").unwrap();
+
+        assert_eq!(ideas, dir.path().join("ideas.md"));
+        assert_eq!(std::fs::read_to_string(&ideas).unwrap(), "- one\n- two\n");
+        assert_eq!(
+            doc.text_buffer.to_string(),
+            "# Project\nIdeas:\n[[ideas]]see [[index]]\n"
+        );
+        assert!(doc.extract_selection("other").is_err(), "Nothing selected");
 
         doc.undo();
-        assert_eq!(doc.cursor.head, Position::new(0, 0));
+        assert_eq!(
+            doc.text_buffer.to_string(),
+            "# Project\nIdeas:\n- one\n- two\nsee [[index]]\n"
+        );
+
+        std::fs::write(&ideas, "more, back in [[project]]\n").unwrap();
+        std::fs::write(&index, "- [[project]]\n- [[ideas|Ideas]]\n").unwrap();
+
+        let updated = doc.merge_note(&ideas).unwrap();
+
+        assert_eq!(updated, vec![index.clone()]);
+        assert!(!ideas.exists());
+        assert_eq!(
+            doc.text_buffer.to_string(),
+            "# Project\nIdeas:\n- one\n- two\nsee [[index]]\n\nmore, back in [[project]]\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(&note).unwrap(),
+            doc.text_buffer.to_string()
+        );
+        assert_eq!(
+            std::fs::read_to_string(&index).unwrap(),
+            "- [[project]]\n- [[project|Ideas]]\n"
+        );
+        assert!(doc.merge_note(&note).is_err(), "Not into itself");
+    }
+
+    #[test]
+    fn test_insert_attachments() {
+        let dir = tempfile::tempdir().unwrap();
+        let note = dir.path().join("note.md");
+        let image = dir.path().join("photo.jpg");
+        std::fs::write(&note, "").unwrap();
+        std::fs::write(&image, "jpg").unwrap();
+
+        let mut doc = setup();
         assert!(
-            doc.text_buffer.get_line(0).is_none()
-                || doc.text_buffer.get_line(0).unwrap().is_empty()
+            doc.insert_attachments(std::slice::from_ref(&image))
+                .is_err()
+        );
+
+        doc.open_file(&note).unwrap();
+        doc.insert_attachments(&[image.clone(), image]).unwrap();
+
+        assert_eq!(
+            doc.text_buffer.to_string(),
+            "![photo](assets/photo.jpg)\n![photo](assets/photo-1.jpg)"
+        );
+        assert_eq!(doc.history.undo_stack.len(), 1);
+    }
+
+    #[test]
+    fn test_check_truncation_detaches() {
+        let dir = tempfile::tempdir().unwrap();
+        let note = dir.path().join("note.md");
+        std::fs::write(&note, "keep\ngone\n").unwrap();
+
+        let mut doc = setup();
+        doc.open_file(&note).unwrap();
+        assert_eq!(doc.check_truncation(), None);
+        doc.cursor = editor_core::cursor::Cursor::new(1, 3);
+
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(&note)
+            .unwrap()
+            .set_len(5)
+            .unwrap();
+
+        assert_eq!(doc.check_truncation(), Some(5));
+        assert!(doc.text_buffer.is_dirty());
+        assert_eq!(doc.text_buffer.to_string(), "keep\n");
+        assert_eq!(doc.cursor.head.row, 0);
+        assert_eq!(doc.check_truncation(), None);
+    }
+
+    #[test]
+    fn test_save_or_defer() {
+        let dir = tempfile::tempdir().unwrap();
+        let note = dir.path().join("note.md");
+        std::fs::write(&note, "a\n").unwrap();
+
+        let mut queue = crate::save_queue::SaveQueue::load(dir.path().join("pending"));
+        let mut doc = setup();
+        doc.open_file(&note).unwrap();
+        doc.insert("b");
+
+        assert_eq!(
+            doc.save_or_defer(&mut queue).unwrap(),
+            crate::save_queue::SaveOutcome::Saved
+        );
+        assert!(!doc.text_buffer.is_dirty());
+        assert_eq!(std::fs::read_to_string(&note).unwrap(), "ba\n");
+
+        // Queued behind an earlier pending write of the same note.
+        let offline = std::io::Error::new(std::io::ErrorKind::TimedOut, "offline");
+        queue.defer(&note, b"older", &offline).unwrap();
+        doc.insert("c");
+
+        assert_eq!(
+            doc.save_or_defer(&mut queue).unwrap(),
+            crate::save_queue::SaveOutcome::Deferred
+        );
+        assert_eq!(queue.pending().len(), 1);
+        assert_eq!(
+            std::fs::read_to_string(&queue.pending()[0].cached).unwrap(),
+            "bca\n"
         );
     }
 }
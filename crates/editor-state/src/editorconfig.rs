@@ -0,0 +1,452 @@
+/// Per-buffer overrides resolved from `.editorconfig` files.
+/// `None` means "not specified", so the editor-wide setting applies. `charset` is
+/// left out: notes are always read and written as UTF-8.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Properties {
+    pub indent_style: Option<crate::settings::IndentStyle>,
+    pub indent_size: Option<usize>,
+    pub tab_width: Option<usize>,
+    pub end_of_line: Option<editor_core::text::LineEnding>,
+    pub trim_trailing_whitespace: Option<bool>,
+    pub insert_final_newline: Option<bool>,
+    /// Column lines should end by, if one is set.
+    pub max_line_length: Option<usize>,
+}
+
+impl Properties {
+    /// Applies a single `key = value` pair. Unknown keys and bad values are ignored,
+    /// and the special value `unset` clears a property inherited from a parent file.
+    fn set(&mut self, key: &str, value: &str) {
+        let value = value.to_ascii_lowercase();
+        let unset = value == "unset";
+        let as_bool = || match value.as_str() {
+            "true" => Some(true),
+            "false" => Some(false),
+            _ => None,
+        };
+
+        match key {
+            "indent_style" => {
+                self.indent_style = match value.as_str() {
+                    "tab" => Some(crate::settings::IndentStyle::Tabs),
+                    "space" => Some(crate::settings::IndentStyle::Spaces),
+                    _ if unset => None,
+                    _ => self.indent_style,
+                }
+            }
+            "indent_size" => {
+                self.indent_size = match value.as_str() {
+                    // Resolved against `tab_width` once every file has been read.
+                    "tab" => Some(0),
+                    _ if unset => None,
+                    _ => value.parse().ok().filter(|&n| n > 0).or(self.indent_size),
+                }
+            }
+            "tab_width" => {
+                self.tab_width = if unset {
+                    None
+                } else {
+                    value.parse().ok().filter(|&n| n > 0).or(self.tab_width)
+                }
+            }
+            "end_of_line" => {
+                self.end_of_line = match value.as_str() {
+                    "lf" => Some(editor_core::text::LineEnding::LF),
+                    "crlf" => Some(editor_core::text::LineEnding::CRLF),
+                    _ if unset => None,
+                    // `cr` line endings are not supported by the buffer.
+                    _ => self.end_of_line,
+                }
+            }
+            "trim_trailing_whitespace" => {
+                self.trim_trailing_whitespace = if unset {
+                    None
+                } else {
+                    as_bool().or(self.trim_trailing_whitespace)
+                }
+            }
+            "insert_final_newline" => {
+                self.insert_final_newline = if unset {
+                    None
+                } else {
+                    as_bool().or(self.insert_final_newline)
+                }
+            }
+            "max_line_length" => {
+                self.max_line_length = match value.as_str() {
+                    "off" => None,
+                    _ if unset => None,
+                    _ => value
+                        .parse()
+                        .ok()
+                        .filter(|&n| n > 0)
+                        .or(self.max_line_length),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Applies `settings`, given as `.editorconfig` keys and values, on top of these,
+    /// e.g. those of a [`crate::modeline::Modeline`].
+    pub fn apply(&mut self, settings: &[(&str, String)]) {
+        for (key, value) in settings {
+            self.set(key, value);
+        }
+    }
+
+    /// Fills in the values the spec derives from one another.
+    fn finalize(&mut self) {
+        if self.indent_size == Some(0) {
+            self.indent_size = self.tab_width;
+        }
+
+        if self.indent_size.is_none()
+            && self.indent_style == Some(crate::settings::IndentStyle::Tabs)
+        {
+            self.indent_size = self.tab_width;
+        }
+
+        if self.tab_width.is_none() {
+            self.tab_width = self.indent_size;
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Section {
+    pub glob: String,
+    pub properties: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EditorConfigFile {
+    /// `root = true` stops the search from looking at parent directories.
+    pub root: bool,
+    pub sections: Vec<Section>,
+}
+
+/// Parses the INI-like `.editorconfig` format.
+pub fn parse(content: &str) -> EditorConfigFile {
+    let mut file = EditorConfigFile::default();
+
+    for raw_line in content.lines() {
+        let line = raw_line.trim();
+
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if let Some(glob) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            file.sections.push(Section {
+                glob: glob.to_string(),
+                properties: Vec::new(),
+            });
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let value = value.trim().to_string();
+
+        match file.sections.last_mut() {
+            Some(section) => section.properties.push((key, value)),
+            None if key == "root" => file.root = value.eq_ignore_ascii_case("true"),
+            None => {}
+        }
+    }
+
+    file
+}
+
+/// Walks from the file's directory up to the filesystem root (or the first
+/// `root = true` file) and merges every matching section. Closer files win.
+pub fn resolve(path: &std::path::Path) -> Properties {
+    let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut files = Vec::new();
+    let mut dir = path.parent();
+
+    while let Some(current) = dir {
+        if let Ok(content) = std::fs::read_to_string(current.join(".editorconfig")) {
+            let parsed = parse(&content);
+            let is_root = parsed.root;
+
+            files.push((current.to_path_buf(), parsed));
+
+            if is_root {
+                break;
+            }
+        }
+
+        dir = current.parent();
+    }
+
+    let mut properties = Properties::default();
+
+    for (dir, file) in files.iter().rev() {
+        let Ok(relative) = path.strip_prefix(dir) else {
+            continue;
+        };
+        let relative = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        for section in file
+            .sections
+            .iter()
+            .filter(|s| section_matches(&s.glob, &relative))
+        {
+            for (key, value) in &section.properties {
+                properties.set(key, value);
+            }
+        }
+    }
+
+    properties.finalize();
+    properties
+}
+
+/// Matches a section glob against a `/`-separated path relative to the `.editorconfig`.
+pub fn section_matches(glob: &str, relative_path: &str) -> bool {
+    let pattern = if let Some(anchored) = glob.strip_prefix('/') {
+        anchored.to_string()
+    } else if glob.contains('/') {
+        glob.to_string()
+    } else {
+        // Globs without a slash match the file name in any directory.
+        format!("**/{glob}")
+    };
+
+    glob_match(&pattern, relative_path)
+        || (pattern.starts_with("**/") && glob_match(&pattern[3..], relative_path))
+}
+
+/// EditorConfig glob matching: `*`, `**`, `?`, `[set]`, `[!set]`, `{a,b}` and `{n..m}`.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    match_here(&pattern, &text)
+}
+
+fn match_here(p: &[char], s: &[char]) -> bool {
+    let Some(&first) = p.first() else {
+        return s.is_empty();
+    };
+
+    match first {
+        '*' if p.get(1) == Some(&'*') => {
+            let rest = &p[2..];
+            (0..=s.len()).any(|i| match_here(rest, &s[i..]))
+        }
+        '*' => {
+            let rest = &p[1..];
+            let limit = s.iter().position(|&c| c == '/').unwrap_or(s.len());
+            (0..=limit).any(|i| match_here(rest, &s[i..]))
+        }
+        '?' => matches!(s.first(), Some(&c) if c != '/') && match_here(&p[1..], &s[1..]),
+        '[' => match p.iter().position(|&c| c == ']') {
+            Some(close) if close > 1 => {
+                let Some(&c) = s.first() else {
+                    return false;
+                };
+                let (negated, set) = match p[1] {
+                    '!' => (true, &p[2..close]),
+                    _ => (false, &p[1..close]),
+                };
+
+                char_in_set(c, set) != negated && c != '/' && match_here(&p[close + 1..], &s[1..])
+            }
+            _ => s.first() == Some(&'[') && match_here(&p[1..], &s[1..]),
+        },
+        '{' => match_braces(p, s),
+        '\\' if p.len() > 1 => s.first() == Some(&p[1]) && match_here(&p[2..], &s[1..]),
+        literal => s.first() == Some(&literal) && match_here(&p[1..], &s[1..]),
+    }
+}
+
+fn char_in_set(c: char, set: &[char]) -> bool {
+    let mut i = 0;
+
+    while i < set.len() {
+        if i + 2 < set.len() && set[i + 1] == '-' {
+            if set[i] <= c && c <= set[i + 2] {
+                return true;
+            }
+            i += 3;
+        } else {
+            if set[i] == c {
+                return true;
+            }
+            i += 1;
+        }
+    }
+
+    false
+}
+
+fn match_braces(p: &[char], s: &[char]) -> bool {
+    let mut depth = 0;
+    let mut close = None;
+
+    for (i, &c) in p.iter().enumerate() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    close = Some(i);
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let Some(close) = close else {
+        return s.first() == Some(&'{') && match_here(&p[1..], &s[1..]);
+    };
+
+    let inner: String = p[1..close].iter().collect();
+    let rest = &p[close + 1..];
+
+    if let Some((lo, hi)) = inner.split_once("..")
+        && let (Ok(lo), Ok(hi)) = (lo.parse::<i64>(), hi.parse::<i64>())
+    {
+        let digits = s
+            .iter()
+            .enumerate()
+            .take_while(|&(i, &c)| c.is_ascii_digit() || (i == 0 && c == '-'))
+            .count();
+
+        return (1..=digits).any(|len| {
+            let candidate: String = s[..len].iter().collect();
+            candidate
+                .parse::<i64>()
+                .is_ok_and(|n| lo.min(hi) <= n && n <= lo.max(hi))
+                && match_here(rest, &s[len..])
+        });
+    }
+
+    // Split on top-level commas only, so nested braces keep working.
+    let mut alternatives = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0;
+
+    for c in inner.chars() {
+        match c {
+            ',' if depth == 0 => alternatives.push(std::mem::take(&mut current)),
+            '{' => {
+                depth += 1;
+                current.push(c);
+            }
+            '}' => {
+                depth -= 1;
+                current.push(c);
+            }
+            _ => current.push(c),
+        }
+    }
+    alternatives.push(current);
+
+    if alternatives.len() == 1 {
+        // `{single}` is not an alternation, it's literal text.
+        return s.first() == Some(&'{') && match_here(&p[1..], &s[1..]);
+    }
+
+    alternatives.iter().any(|alt| {
+        let mut expanded: Vec<char> = alt.chars().collect();
+        expanded.extend_from_slice(rest);
+        match_here(&expanded, s)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sections_and_root() {
+        let file = parse(
+            "# top comment\nroot = true\n\n[*]\nindent_style = space\nindent_size = 2\n\n; other\n[*.md]\ntrim_trailing_whitespace = false\n",
+        );
+
+        assert!(file.root);
+        assert_eq!(file.sections.len(), 2);
+        assert_eq!(file.sections[0].glob, "*");
+        assert_eq!(
+            file.sections[0].properties,
+            vec![
+                ("indent_style".to_string(), "space".to_string()),
+                ("indent_size".to_string(), "2".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_glob_matching() {
+        assert!(section_matches("*", "notes/todo.md"));
+        assert!(section_matches("*.md", "notes/todo.md"));
+        assert!(!section_matches("*.md", "notes/todo.txt"));
+        assert!(section_matches("*.{md,txt}", "a.txt"));
+        assert!(section_matches("/notes/*.md", "notes/todo.md"));
+        assert!(!section_matches("/notes/*.md", "notes/deep/todo.md"));
+        assert!(section_matches("notes/**.md", "notes/deep/todo.md"));
+        assert!(section_matches("file[0-9].txt", "file7.txt"));
+        assert!(!section_matches("file[!0-9].txt", "file7.txt"));
+        assert!(section_matches("day{1..31}.md", "day12.md"));
+        assert!(!section_matches("day{1..31}.md", "day40.md"));
+        assert!(section_matches("?.rs", "a.rs"));
+        assert!(section_matches("Makefile", "sub/Makefile"));
+    }
+
+    #[test]
+    fn test_resolve_walks_up_and_closer_files_win() {
+        let root = tempfile::tempdir().unwrap();
+        let nested = root.path().join("journal");
+        std::fs::create_dir(&nested).unwrap();
+
+        std::fs::write(
+            root.path().join(".editorconfig"),
+            "root = true\n[*]\nindent_style = space\nindent_size = 4\nend_of_line = crlf\ninsert_final_newline = true\n",
+        )
+        .unwrap();
+        std::fs::write(
+            nested.join(".editorconfig"),
+            "[*.md]\nindent_style = tab\ntab_width = 8\ntrim_trailing_whitespace = true\nend_of_line = unset\n",
+        )
+        .unwrap();
+
+        let props = resolve(&nested.join("2024-07-01.md"));
+
+        assert_eq!(props.indent_style, Some(crate::settings::IndentStyle::Tabs));
+        assert_eq!(props.indent_size, Some(4));
+        assert_eq!(props.tab_width, Some(8));
+        assert_eq!(props.end_of_line, None);
+        assert_eq!(props.trim_trailing_whitespace, Some(true));
+        assert_eq!(props.insert_final_newline, Some(true));
+
+        let other = resolve(&nested.join("script.sh"));
+        assert_eq!(
+            other.indent_style,
+            Some(crate::settings::IndentStyle::Spaces)
+        );
+        assert_eq!(other.tab_width, Some(4));
+        assert_eq!(other.end_of_line, Some(editor_core::text::LineEnding::CRLF));
+    }
+
+    #[test]
+    fn test_indent_size_tab_resolves_to_tab_width() {
+        let mut props = Properties::default();
+        props.set("indent_style", "tab");
+        props.set("indent_size", "tab");
+        props.set("tab_width", "3");
+        props.finalize();
+
+        assert_eq!(props.indent_size, Some(3));
+    }
+}
@@ -0,0 +1,871 @@
+/// Escapes the characters HTML treats as markup.
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            _ => out.push(c),
+        }
+    }
+
+    out
+}
+
+/// Where the links in a note point once it's exported.
+pub trait Links {
+    /// The `href` of a `[[name]]` wiki link, or `None` to leave the link as text.
+    fn wiki(&self, name: &str) -> Option<String>;
+
+    /// The `href` or `src` for a Markdown link or image target as written.
+    fn target(&self, target: &str) -> String {
+        target.to_string()
+    }
+
+    /// The `href` of a `[text][label]` reference link or a `[^label]` footnote,
+    /// or `None` to leave it as text.
+    fn reference(&self, _label: &str) -> Option<String> {
+        None
+    }
+}
+
+/// `links` plus the reference and footnote definitions of the note being
+/// converted, see [`crate::footnotes::definitions`].
+struct WithDefinitions<'a> {
+    links: &'a dyn Links,
+    definitions: std::collections::HashMap<String, String>,
+}
+
+impl Links for WithDefinitions<'_> {
+    fn wiki(&self, name: &str) -> Option<String> {
+        self.links.wiki(name)
+    }
+
+    fn target(&self, target: &str) -> String {
+        self.links.target(target)
+    }
+
+    fn reference(&self, label: &str) -> Option<String> {
+        let label = label.to_lowercase();
+        let definition = self.definitions.get(&label)?;
+
+        Some(match label.strip_prefix('^') {
+            Some(id) => format!("#fn-{id}"),
+            // Leaving out a `"title"` after the target.
+            None => self.target(
+                definition
+                    .split_whitespace()
+                    .next()?
+                    .trim_start_matches('<')
+                    .trim_end_matches('>'),
+            ),
+        })
+    }
+}
+
+/// Links of a note exported on its own: targets as written, wiki links as text.
+pub struct Unresolved;
+
+impl Links for Unresolved {
+    fn wiki(&self, _name: &str) -> Option<String> {
+        None
+    }
+}
+
+/// Plain text with `[label](target)` links, `![alt](target)` images, and `[[name]]`
+/// wiki links, `[text][label]` reference links and `[^label]` footnotes that
+/// `links` resolves turned into tags.
+fn push_text(out: &mut String, text: &str, links: &dyn Links) {
+    let mut rest = text;
+
+    while let Some(open) = rest.find('[') {
+        let wiki = rest[open..]
+            .strip_prefix("[[")
+            .and_then(|after| Some(&after[..after.find("]]")?]))
+            .and_then(|inner| {
+                let (target, alias) = match inner.split_once('|') {
+                    Some((target, alias)) => (target, Some(alias)),
+                    None => (inner, None),
+                };
+                let (name, heading) = match target.split_once('#') {
+                    Some((name, heading)) => (name.trim(), Some(heading)),
+                    None => (target.trim(), None),
+                };
+                let href = match heading {
+                    // A heading of the same note.
+                    Some(heading) if name.is_empty() => format!("#{}", crate::toc::slug(heading)),
+                    Some(heading) => format!("{}#{}", links.wiki(name)?, crate::toc::slug(heading)),
+                    None => links.wiki(name)?,
+                };
+
+                Some((inner.len(), href, alias.unwrap_or(target).trim()))
+            });
+
+        if let Some((len, href, label)) = wiki {
+            out.push_str(&escape(&rest[..open]));
+            out.push_str(&format!(
+                "<a href=\"{}\">{}</a>",
+                escape(&href),
+                escape(label)
+            ));
+            rest = &rest[open + len + 4..];
+            continue;
+        }
+
+        let image = rest[..open].ends_with('!');
+        let parsed = rest[open + 1..]
+            .split_once("](")
+            .and_then(|(label, after)| {
+                let close = after.find(')')?;
+                (!label.contains('[')).then_some((label, &after[..close], after.len() - close - 1))
+            });
+        let Some((label, target, left)) = parsed else {
+            match reference(&rest[open..], links) {
+                Some((len, href, text)) if text.starts_with('^') => {
+                    let id = &text[1..];
+
+                    out.push_str(&escape(&rest[..open]));
+                    out.push_str(&format!(
+                        "<sup id=\"fnref-{}\"><a href=\"{}\">{}</a></sup>",
+                        escape(&id.to_lowercase()),
+                        escape(&href),
+                        escape(id)
+                    ));
+                    rest = &rest[open + len..];
+                }
+                Some((len, href, text)) if image => {
+                    out.push_str(&escape(&rest[..open - 1]));
+                    out.push_str(&format!(
+                        "<img src=\"{}\" alt=\"{}\">",
+                        escape(&href),
+                        escape(text)
+                    ));
+                    rest = &rest[open + len..];
+                }
+                Some((len, href, text)) => {
+                    out.push_str(&escape(&rest[..open]));
+                    out.push_str(&format!(
+                        "<a href=\"{}\">{}</a>",
+                        escape(&href),
+                        escape(text)
+                    ));
+                    rest = &rest[open + len..];
+                }
+                None => {
+                    out.push_str(&escape(&rest[..=open]));
+                    rest = &rest[open + 1..];
+                }
+            }
+            continue;
+        };
+        let target =
+            escape(&links.target(target.trim().trim_start_matches('<').trim_end_matches('>')));
+
+        if image {
+            out.push_str(&escape(&rest[..open - 1]));
+            out.push_str(&format!("<img src=\"{target}\" alt=\"{}\">", escape(label)));
+        } else {
+            out.push_str(&escape(&rest[..open]));
+            out.push_str(&format!("<a href=\"{target}\">{}</a>", escape(label)));
+        }
+        rest = &rest[rest.len() - left..];
+    }
+
+    out.push_str(&escape(rest));
+}
+
+/// A `[text][label]`, `[text][]` or `[text]` reference at the start of `text`
+/// that `links` knows: its length, `href` and text. Footnotes are `[^label]`.
+fn reference<'t>(text: &'t str, links: &dyn Links) -> Option<(usize, String, &'t str)> {
+    let (inner, after) = text.strip_prefix('[')?.split_once(']')?;
+
+    if inner.is_empty() || inner.contains('[') {
+        return None;
+    }
+
+    let (label, len) = match after
+        .strip_prefix('[')
+        .and_then(|after| after.split_once(']'))
+    {
+        Some(("", _)) => (inner, inner.len() + 4),
+        Some((label, _)) if !label.contains('[') => (label, inner.len() + label.len() + 4),
+        _ => (inner, inner.len() + 2),
+    };
+
+    Some((len, links.reference(label)?, inner))
+}
+
+/// One line of inline Markdown as HTML.
+fn inline_html(line: &str, links: &dyn Links) -> String {
+    let mut out = String::new();
+    let mut at = 0;
+
+    for span in crate::highlight::inline_spans(line) {
+        push_text(&mut out, &line[at..span.range.start], links);
+
+        let inner = &line[span.range.clone()];
+
+        match span.style {
+            crate::highlight::SpanStyle::Marker => {}
+            crate::highlight::SpanStyle::Emphasis => {
+                out.push_str(&format!("<em>{}</em>", escape(inner)));
+            }
+            crate::highlight::SpanStyle::Strong => {
+                out.push_str(&format!("<strong>{}</strong>", escape(inner)));
+            }
+            crate::highlight::SpanStyle::Code => {
+                let code = inner.trim_matches('`');
+                // One space on both sides pads code that starts or ends with a backtick.
+                let code = match code.strip_prefix(' ').and_then(|c| c.strip_suffix(' ')) {
+                    Some(stripped) if !stripped.is_empty() => stripped,
+                    _ => code,
+                };
+
+                out.push_str(&format!("<code>{}</code>", escape(code)));
+            }
+            crate::highlight::SpanStyle::Math => {
+                let tex = &inner[1..inner.len() - 1];
+
+                out.push_str(&format!(
+                    "<span class=\"math\">\\({}\\)</span>",
+                    escape(tex)
+                ));
+            }
+            // Only found in fenced code.
+            crate::highlight::SpanStyle::Keyword
+            | crate::highlight::SpanStyle::String
+            | crate::highlight::SpanStyle::Number
+            | crate::highlight::SpanStyle::Comment => out.push_str(&escape(inner)),
+        }
+        at = span.range.end;
+    }
+
+    push_text(&mut out, &line[at..], links);
+    out
+}
+
+/// One line of fenced code as HTML, its tokens wrapped in `<span class="...">` if
+/// the block's language is known.
+fn code_html(line: &str, language: Option<&crate::highlight::Language>) -> String {
+    let Some(language) = language else {
+        return escape(line);
+    };
+    let mut out = String::new();
+    let mut at = 0;
+
+    for span in crate::highlight::code_spans(line, language) {
+        let class = match span.style {
+            crate::highlight::SpanStyle::Keyword => "kw",
+            crate::highlight::SpanStyle::String => "str",
+            crate::highlight::SpanStyle::Number => "num",
+            _ => "com",
+        };
+
+        out.push_str(&escape(&line[at..span.range.start]));
+        out.push_str(&format!(
+            "<span class=\"{class}\">{}</span>",
+            escape(&line[span.range.clone()])
+        ));
+        at = span.range.end;
+    }
+
+    out.push_str(&escape(&line[at..]));
+    out
+}
+
+/// The kind of block a line continues, so consecutive lines can share one tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Block {
+    Paragraph,
+    Quote,
+    BulletList,
+    NumberedList,
+}
+
+impl Block {
+    fn close_tag(self) -> &'static str {
+        match self {
+            Block::Paragraph => "</p>\n",
+            Block::Quote => "</p></blockquote>\n",
+            Block::BulletList => "</ul>\n",
+            Block::NumberedList => "</ol>\n",
+        }
+    }
+}
+
+/// Text of a `- item`, `* item` or `+ item` line.
+fn bullet_item(line: &str) -> Option<&str> {
+    let trimmed = line.trim_start();
+
+    ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|marker| trimmed.strip_prefix(marker))
+}
+
+/// Text of a `1. item` or `1) item` line.
+fn numbered_item(line: &str) -> Option<&str> {
+    let trimmed = line.trim_start();
+    let digits = trimmed.bytes().take_while(u8::is_ascii_digit).count();
+
+    if digits == 0 {
+        return None;
+    }
+
+    trimmed[digits..]
+        .strip_prefix(". ")
+        .or_else(|| trimmed[digits..].strip_prefix(") "))
+}
+
+/// A `$$` display formula, left as TeX for MathJax to typeset.
+fn push_math(out: &mut String, tex: &[&str]) {
+    out.push_str(&format!(
+        "<div class=\"math\">\\[{}\\]</div>\n",
+        escape(tex.join("\n").trim())
+    ));
+}
+
+/// Converts the body of a note to HTML: headings, paragraphs, block quotes,
+/// lists, fenced code, math, links, images, emphasis and code spans.
+fn body_html<S: AsRef<str>>(lines: &[S], links: &dyn Links) -> String {
+    let links = &WithDefinitions {
+        links,
+        definitions: crate::footnotes::definitions(lines),
+    };
+    let mut footnotes = Vec::new();
+    let mut in_footnote = false;
+    let mut out = String::new();
+    let mut open: Option<Block> = None;
+    let mut in_code = false;
+    // Heading ids, for `#anchor` links to them.
+    let mut anchors = std::collections::HashSet::new();
+    // Mermaid blocks go out as their source for mermaid.js to draw.
+    let mut in_diagram = false;
+    let mut language = None;
+    let mut math: Option<Vec<&str>> = None;
+
+    let close = |out: &mut String, open: &mut Option<Block>| {
+        if let Some(block) = open.take() {
+            out.push_str(block.close_tag());
+        }
+    };
+
+    for line in lines {
+        let line = line.as_ref();
+        let fence = line.trim_start().starts_with("```") || line.trim_start().starts_with("~~~");
+
+        if in_code {
+            if fence {
+                out.push_str(if in_diagram {
+                    "</pre>\n"
+                } else {
+                    "</code></pre>\n"
+                });
+                in_code = false;
+            } else {
+                out.push_str(&code_html(line, language));
+                out.push('\n');
+            }
+            continue;
+        }
+
+        if let Some(tex) = &mut math {
+            match line.trim().strip_suffix("$$") {
+                Some(last) => {
+                    tex.push(last);
+                    push_math(&mut out, tex);
+                    math = None;
+                }
+                None => tex.push(line.trim()),
+            }
+            continue;
+        }
+
+        // Definitions go out as the links and footnotes using them.
+        if let Some((label, _)) = crate::footnotes::definition(line) {
+            close(&mut out, &mut open);
+            in_footnote = label.starts_with('^');
+            if in_footnote && !footnotes.contains(&label.to_lowercase()) {
+                footnotes.push(label.to_lowercase());
+            }
+            continue;
+        }
+        if in_footnote && line.starts_with("    ") {
+            continue;
+        }
+        in_footnote = false;
+
+        if fence {
+            let info = line.trim().trim_start_matches(['`', '~']).trim();
+
+            close(&mut out, &mut open);
+            in_code = true;
+            in_diagram = info.eq_ignore_ascii_case("mermaid");
+            language = crate::highlight::Language::from_tag(info).filter(|_| !in_diagram);
+            match info.split_whitespace().next() {
+                _ if in_diagram => out.push_str("<pre class=\"mermaid\">"),
+                Some(tag) => out.push_str(&format!(
+                    "<pre><code class=\"language-{}\">",
+                    escape(&tag.to_lowercase())
+                )),
+                None => out.push_str("<pre><code>"),
+            }
+        } else if line.trim().is_empty() {
+            close(&mut out, &mut open);
+        } else if let Some(rest) = line.trim().strip_prefix("$$") {
+            close(&mut out, &mut open);
+            match rest.strip_suffix("$$") {
+                Some(tex) => push_math(&mut out, &[tex]),
+                None => math = Some(vec![rest]),
+            }
+        } else if let Some((level, title)) = crate::outline::parse_heading(line) {
+            close(&mut out, &mut open);
+            out.push_str(&format!(
+                "<h{level} id=\"{}\">{}</h{level}>\n",
+                escape(&crate::toc::unique_anchor(&mut anchors, title)),
+                inline_html(title, links)
+            ));
+        } else if let Some(item) = bullet_item(line) {
+            if open != Some(Block::BulletList) {
+                close(&mut out, &mut open);
+                out.push_str("<ul>\n");
+                open = Some(Block::BulletList);
+            }
+            out.push_str(&format!("<li>{}</li>\n", inline_html(item, links)));
+        } else if let Some(item) = numbered_item(line) {
+            if open != Some(Block::NumberedList) {
+                close(&mut out, &mut open);
+                out.push_str("<ol>\n");
+                open = Some(Block::NumberedList);
+            }
+            out.push_str(&format!("<li>{}</li>\n", inline_html(item, links)));
+        } else if let Some(quoted) = line.trim_start().strip_prefix('>') {
+            if open == Some(Block::Quote) {
+                out.push('\n');
+            } else {
+                close(&mut out, &mut open);
+                out.push_str("<blockquote><p>");
+                open = Some(Block::Quote);
+            }
+            out.push_str(&inline_html(quoted.trim(), links));
+        } else {
+            if open == Some(Block::Paragraph) {
+                out.push('\n');
+            } else {
+                close(&mut out, &mut open);
+                out.push_str("<p>");
+                open = Some(Block::Paragraph);
+            }
+            out.push_str(&inline_html(line.trim(), links));
+        }
+    }
+
+    if in_code {
+        out.push_str(if in_diagram {
+            "</pre>\n"
+        } else {
+            "</code></pre>\n"
+        });
+    }
+    if let Some(tex) = math {
+        push_math(&mut out, &tex);
+    }
+    close(&mut out, &mut open);
+
+    if !footnotes.is_empty() {
+        out.push_str("<section class=\"footnotes\">\n<ol>\n");
+        for label in footnotes {
+            let id = escape(&label[1..]);
+
+            out.push_str(&format!(
+                "<li id=\"fn-{id}\">{} <a href=\"#fnref-{id}\">↩</a></li>\n",
+                inline_html(&links.definitions[&label], links)
+            ));
+        }
+        out.push_str("</ol>\n</section>\n");
+    }
+
+    out
+}
+
+/// `lines` as HTML without a page around them, e.g. to copy into a rich text
+/// editor. Wiki links stay text.
+pub fn html_fragment<S: AsRef<str>>(lines: &[S]) -> String {
+    body_html(lines, &Unresolved)
+}
+
+/// `lines` as they read once rendered: Markdown markers dropped, list bullets
+/// and blank lines between blocks kept.
+pub fn plain_text<S: AsRef<str>>(lines: &[S]) -> String {
+    crate::clipboard::html_to_plain_text(&body_html(lines, &Unresolved))
+}
+
+/// A standalone HTML page of the note, colored with `palette`. Meant to be given
+/// a theme's export palette so the page prints well whatever the screen shows.
+pub fn to_html<S: AsRef<str>>(lines: &[S], title: &str, palette: &crate::theme::Palette) -> String {
+    page(lines, title, palette, &Unresolved, "")
+}
+
+/// The page around a note's body, with `nav` (HTML) above it.
+fn page<S: AsRef<str>>(
+    lines: &[S],
+    title: &str,
+    palette: &crate::theme::Palette,
+    links: &dyn Links,
+    nav: &str,
+) -> String {
+    let css = crate::theme::css_color;
+    let body = body_html(lines, links);
+    // Typeset math and draw diagrams where there are some; without the scripts
+    // their source shows.
+    let mathjax = if body.contains("<span class=\"math\">") || body.contains("<div class=\"math\">")
+    {
+        "<script async src=\"https://cdn.jsdelivr.net/npm/mathjax@3/es5/tex-chtml.js\"></script>\n"
+    } else {
+        ""
+    };
+    let mermaid = if body.contains("<pre class=\"mermaid\">") {
+        "<script type=\"module\">import mermaid from \"https://cdn.jsdelivr.net/npm/mermaid@10/dist/mermaid.esm.min.mjs\"; mermaid.initialize({ startOnLoad: true });</script>\n"
+    } else {
+        ""
+    };
+
+    format!(
+        "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>{title}</title>
+<style>
+body {{ background: {background}; color: {text}; font-family: Georgia, serif; line-height: 1.5; max-width: 42em; margin: 2em auto; padding: 0 1em; }}
+h1, h2, h3, h4, h5, h6 {{ color: {heading}; }}
+a {{ color: {link}; }}
+img {{ max-width: 100%; }}
+nav {{ font-size: 0.9em; margin-bottom: 2em; }}
+code, pre {{ background: {code_background}; color: {code_text}; font-family: Menlo, Consolas, monospace; }}
+pre {{ padding: 0.75em; overflow-x: auto; }}
+pre .kw {{ color: {heading}; font-weight: bold; }}
+pre .str, pre .num {{ color: {link}; }}
+pre .com {{ color: {muted}; font-style: italic; }}
+blockquote {{ color: {muted}; border-left: 3px solid {muted}; margin-left: 0; padding-left: 1em; }}
+div.math {{ overflow-x: auto; }}
+section.footnotes {{ border-top: 1px solid {muted}; font-size: 0.9em; }}
+@media print {{ body {{ margin: 0; max-width: none; }} }}
+</style>
+{mathjax}{mermaid}</head>
+<body>
+{nav}{body}</body>
+</html>
+",
+        title = escape(title),
+        background = css(palette.background),
+        text = css(palette.text),
+        heading = css(palette.heading),
+        link = css(palette.link),
+        code_background = css(palette.code_background),
+        code_text = css(palette.code_text),
+        muted = css(palette.muted),
+    )
+}
+
+/// Links between the pages of a published site, from the page at `prefix` (the
+/// `../` leading back to the site's top).
+struct SiteLinks<'a> {
+    /// Page of each note, by lowercased name, relative to the top.
+    pages: &'a std::collections::HashMap<String, String>,
+    prefix: String,
+}
+
+impl Links for SiteLinks<'_> {
+    fn wiki(&self, name: &str) -> Option<String> {
+        let page = self.pages.get(&name.to_lowercase())?;
+
+        Some(format!("{}{page}", self.prefix))
+    }
+
+    /// Relative links to other notes go to their pages instead.
+    fn target(&self, target: &str) -> String {
+        let (path, anchor) = match target.find('#') {
+            Some(at) => target.split_at(at),
+            None => (target, ""),
+        };
+
+        if target.contains("://")
+            || target.starts_with('/')
+            || !crate::workspace::is_note(std::path::Path::new(path))
+        {
+            return target.to_string();
+        }
+        match path.rfind('.') {
+            Some(dot) => format!("{}.html{anchor}", &path[..dot]),
+            None => target.to_string(),
+        }
+    }
+}
+
+/// The page a note at `relative` (to the workspace) is published as, with `/`
+/// separators and spaces escaped.
+fn page_path(relative: &std::path::Path) -> String {
+    let page = relative.with_extension("html");
+
+    page.components()
+        .map(|part| part.as_os_str().to_string_lossy().replace(' ', "%20"))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Every file in an [`crate::workspace::ASSETS_DIR`] folder under `root`, except
+/// below `skip`.
+fn assets(root: &std::path::Path, skip: &std::path::Path) -> Vec<std::path::PathBuf> {
+    let mut found = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        let in_assets = dir
+            .file_name()
+            .is_some_and(|name| name == crate::workspace::ASSETS_DIR);
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+
+            if entry.file_name().to_string_lossy().starts_with('.') || path.starts_with(skip) {
+                continue;
+            }
+            match entry.file_type() {
+                Ok(kind) if kind.is_dir() => pending.push(path),
+                Ok(kind) if kind.is_file() && in_assets => found.push(path),
+                _ => {}
+            }
+        }
+    }
+
+    found
+}
+
+/// Publishes the notes under `root` as a static site in `out`: a page per note
+/// where wiki links and links between notes lead to each other's pages, an
+/// `index.html` listing them all, and the notes' assets folders copied alongside.
+/// Archived notes only with `include_archived`. Returns how many notes were
+/// published.
+///
+/// # Errors
+///
+/// Returns an error if a note can't be read or the site can't be written.
+pub fn publish(
+    root: &std::path::Path,
+    out: &std::path::Path,
+    palette: &crate::theme::Palette,
+    include_archived: bool,
+) -> std::io::Result<usize> {
+    let notes: Vec<std::path::PathBuf> = crate::workspace::listed_notes(root, include_archived)
+        .into_iter()
+        .filter(|note| !note.starts_with(out))
+        .collect();
+    let relative = |path: &std::path::Path| path.strip_prefix(root).unwrap_or(path).to_path_buf();
+
+    let mut pages = std::collections::HashMap::new();
+    for note in &notes {
+        if let Some(name) = crate::workspace::note_name(note) {
+            pages
+                .entry(name.to_lowercase())
+                .or_insert_with(|| page_path(&relative(note)));
+        }
+    }
+
+    let mut index = Vec::new();
+
+    for note in &notes {
+        let relative = relative(note);
+        let text = std::fs::read_to_string(note)?;
+        let lines: Vec<&str> = text.lines().collect();
+        let title = crate::workspace::note_name(note).unwrap_or("Untitled");
+        let prefix = "../".repeat(relative.components().count() - 1);
+        let links = SiteLinks {
+            pages: &pages,
+            prefix: prefix.clone(),
+        };
+        let nav = format!("<nav><a href=\"{prefix}index.html\">Index</a></nav>\n");
+        let target = out.join(relative.with_extension("html"));
+
+        if let Some(dir) = target.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(&target, page(&lines, title, palette, &links, &nav))?;
+
+        index.push(format!("- [{title}]({})", page_path(&relative)));
+    }
+
+    for asset in assets(root, out) {
+        let target = out.join(relative(&asset));
+
+        if let Some(dir) = target.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::copy(&asset, target)?;
+    }
+
+    let title = root.file_name().map_or_else(
+        || "Notes".to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    );
+
+    std::fs::create_dir_all(out)?;
+    std::fs::write(
+        out.join("index.html"),
+        page(&index, &title, palette, &Unresolved, ""),
+    )?;
+
+    Ok(notes.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_body_html() {
+        let note = [
+            "# Title *one*",
+            "Some `a<b` text",
+            "continues [here](https://x.y/?a&b).",
+            "",
+            "- first",
+            "- **second**",
+            "1. numbered",
+            "> quoted",
+            "```",
+            "# not a heading <tag>",
+            "```",
+            "![cat](assets/cat.png)",
+        ];
+
+        assert_eq!(
+            body_html(&note, &Unresolved),
+            "<h1 id=\"title-one\">Title <em>one</em></h1>\n\
+             <p>Some <code>a&lt;b</code> text\n\
+             continues <a href=\"https://x.y/?a&amp;b\">here</a>.</p>\n\
+             <ul>\n<li>first</li>\n<li><strong>second</strong></li>\n</ul>\n\
+             <ol>\n<li>numbered</li>\n</ol>\n\
+             <blockquote><p>quoted</p></blockquote>\n\
+             <pre><code># not a heading &lt;tag&gt;\n</code></pre>\n\
+             <p><img src=\"assets/cat.png\" alt=\"cat\"></p>\n"
+        );
+    }
+
+    #[test]
+    fn test_copy_formats() {
+        let lines = ["Some **bold** text", "", "- [link](https://x.y)"];
+
+        assert_eq!(
+            html_fragment(&lines),
+            "<p>Some <strong>bold</strong> text</p>\n\
+             <ul>\n<li><a href=\"https://x.y\">link</a></li>\n</ul>\n"
+        );
+        assert_eq!(plain_text(&lines), "Some bold text\n\n• link");
+    }
+
+    #[test]
+    fn test_to_html_uses_palette() {
+        let palette = crate::theme::Theme::Dark.export_palette();
+        let html = to_html(&["text"], "A & B", &palette);
+
+        assert!(html.contains("<title>A &amp; B</title>"));
+        assert!(html.contains("background: #ffffff; color: #000000;"));
+        assert!(html.contains("<p>text</p>"));
+    }
+
+    #[test]
+    fn test_math_html() {
+        let palette = crate::theme::Theme::Light.export_palette();
+        let html = to_html(
+            &["Area $\\pi r^2$ for $5", "$$", "a < b", "$$"],
+            "Math",
+            &palette,
+        );
+
+        assert!(html.contains("<p>Area <span class=\"math\">\\(\\pi r^2\\)</span> for $5</p>"));
+        assert!(html.contains("<div class=\"math\">\\[a &lt; b\\]</div>"));
+        assert!(html.contains("mathjax"));
+        assert!(!to_html(&["$5"], "Money", &palette).contains("mathjax"));
+    }
+
+    #[test]
+    fn test_references_and_footnotes() {
+        let note = [
+            "See [the docs][Docs], [docs] and this[^a].",
+            "",
+            "[docs]: https://x.y \"Title\"",
+            "[^a]: A *note*,",
+            "    continued.",
+        ];
+
+        assert_eq!(
+            body_html(&note, &Unresolved),
+            "<p>See <a href=\"https://x.y\">the docs</a>, <a href=\"https://x.y\">docs</a> and \
+             this<sup id=\"fnref-a\"><a href=\"#fn-a\">a</a></sup>.</p>\n\
+             <section class=\"footnotes\">\n<ol>\n\
+             <li id=\"fn-a\">A <em>note</em>, continued. <a href=\"#fnref-a\">↩</a></li>\n\
+             </ol>\n</section>\n"
+        );
+    }
+
+    #[test]
+    fn test_code_html() {
+        assert_eq!(
+            body_html(&["```rust", "fn f() -> u8 { 1 } // <", "```"], &Unresolved),
+            "<pre><code class=\"language-rust\"><span class=\"kw\">fn</span> f() -&gt; u8 { \
+             <span class=\"num\">1</span> } <span class=\"com\">// &lt;</span>\n</code></pre>\n"
+        );
+    }
+
+    #[test]
+    fn test_mermaid_html() {
+        let palette = crate::theme::Theme::Light.export_palette();
+        let html = to_html(&["```mermaid", "A --> B", "```"], "Flow", &palette);
+
+        assert!(html.contains("<pre class=\"mermaid\">A --&gt; B\n</pre>"));
+        assert!(html.contains("mermaid.initialize"));
+    }
+
+    #[test]
+    fn test_publish_links_pages() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("notes");
+        let out = dir.path().join("site");
+        let palette = crate::theme::Theme::Light.export_palette();
+
+        std::fs::create_dir_all(root.join("trips").join("assets")).unwrap();
+        std::fs::write(
+            root.join("home.md"),
+            "See [[Paris|the trip]], [[paris#Day Two]] and [[missing]].",
+        )
+        .unwrap();
+        std::fs::write(
+            root.join("trips").join("paris.md"),
+            "Back [home](../home.md#top)\n![map](assets/map.png)",
+        )
+        .unwrap();
+        std::fs::write(root.join("trips").join("assets").join("map.png"), "png").unwrap();
+
+        assert_eq!(publish(&root, &out, &palette, false).unwrap(), 2);
+
+        let home = std::fs::read_to_string(out.join("home.html")).unwrap();
+        let paris = std::fs::read_to_string(out.join("trips").join("paris.html")).unwrap();
+        let index = std::fs::read_to_string(out.join("index.html")).unwrap();
+
+        assert!(home.contains(
+            "<a href=\"trips/paris.html\">the trip</a>, \
+             <a href=\"trips/paris.html#day-two\">paris#Day Two</a> and [[missing]]."
+        ));
+        assert!(paris.contains("<a href=\"../index.html\">Index</a>"));
+        assert!(paris.contains("<a href=\"../home.html#top\">home</a>"));
+        assert!(paris.contains("<img src=\"assets/map.png\" alt=\"map\">"));
+        assert!(out.join("trips").join("assets").join("map.png").exists());
+        assert!(index.contains("<a href=\"trips/paris.html\">paris</a>"));
+    }
+}
@@ -0,0 +1,230 @@
+/// The editing conventions of a kind of file, see [`detect`].
+#[derive(Debug, PartialEq, Eq)]
+pub struct FileType {
+    pub name: &'static str,
+    extensions: &'static [&'static str],
+    /// Programs named on a `#!` line that mean this type.
+    interpreters: &'static [&'static str],
+    pub comment: crate::comment::CommentStyle,
+    /// Indentation used when neither `.editorconfig` nor the file itself says.
+    pub indent: Option<(crate::settings::IndentStyle, usize)>,
+    /// Info string of the [`crate::highlight::Language`] the whole file is
+    /// highlighted as, for code rather than notes.
+    pub highlight: Option<&'static str>,
+    /// Characters besides letters and digits that words are made of.
+    pub word_chars: &'static str,
+}
+
+impl FileType {
+    pub fn is_word_char(&self, c: char) -> bool {
+        c.is_alphanumeric() || self.word_chars.contains(c)
+    }
+
+    /// The highlighter for the whole file, `None` for notes.
+    pub fn language(&self) -> Option<&'static crate::highlight::Language> {
+        crate::highlight::Language::from_tag(self.highlight?)
+    }
+}
+
+const fn file_type(
+    name: &'static str,
+    extensions: &'static [&'static str],
+    interpreters: &'static [&'static str],
+    comment: crate::comment::CommentStyle,
+) -> FileType {
+    FileType {
+        name,
+        extensions,
+        interpreters,
+        comment,
+        indent: None,
+        highlight: None,
+        word_chars: "_",
+    }
+}
+
+const SPACES: crate::settings::IndentStyle = crate::settings::IndentStyle::Spaces;
+const LINE_SLASHES: crate::comment::CommentStyle = crate::comment::CommentStyle::Line("//");
+const LINE_HASH: crate::comment::CommentStyle = crate::comment::CommentStyle::Line("#");
+const LINE_DASHES: crate::comment::CommentStyle = crate::comment::CommentStyle::Line("--");
+
+/// Notes, and whatever isn't recognized.
+pub const MARKDOWN: FileType = FileType {
+    word_chars: "_'",
+    ..file_type(
+        "markdown",
+        &["md", "markdown", "mdown", "mkd"],
+        &[],
+        crate::comment::CommentStyle::Block("<!--", "-->"),
+    )
+};
+
+const FILE_TYPES: &[FileType] = &[
+    FileType {
+        indent: Some((SPACES, 4)),
+        highlight: Some("rust"),
+        ..file_type("rust", &["rs"], &[], LINE_SLASHES)
+    },
+    FileType {
+        indent: Some((SPACES, 4)),
+        highlight: Some("python"),
+        ..file_type("python", &["py", "pyw"], &["python"], LINE_HASH)
+    },
+    FileType {
+        indent: Some((SPACES, 2)),
+        highlight: Some("javascript"),
+        word_chars: "_$",
+        ..file_type(
+            "javascript",
+            &["js", "jsx", "mjs", "cjs", "ts", "tsx"],
+            &["node", "deno"],
+            LINE_SLASHES,
+        )
+    },
+    FileType {
+        highlight: Some("c"),
+        ..file_type(
+            "c",
+            &[
+                "c", "h", "cpp", "hpp", "cc", "cs", "java", "go", "swift", "kt", "dart",
+            ],
+            &[],
+            LINE_SLASHES,
+        )
+    },
+    FileType {
+        highlight: Some("sh"),
+        word_chars: "_$",
+        ..file_type(
+            "sh",
+            &["sh", "bash", "zsh"],
+            &["sh", "bash", "zsh", "dash", "ksh"],
+            LINE_HASH,
+        )
+    },
+    FileType {
+        indent: Some((SPACES, 2)),
+        word_chars: "_?!",
+        ..file_type("ruby", &["rb"], &["ruby"], LINE_HASH)
+    },
+    FileType {
+        word_chars: "_$@%",
+        ..file_type("perl", &["pl"], &["perl"], LINE_HASH)
+    },
+    FileType {
+        highlight: Some("sql"),
+        ..file_type("sql", &["sql"], &[], LINE_DASHES)
+    },
+    file_type("lua", &["lua"], &["lua"], LINE_DASHES),
+    FileType {
+        indent: Some((SPACES, 2)),
+        word_chars: "_'",
+        ..file_type("haskell", &["hs"], &["runghc", "runhaskell"], LINE_DASHES)
+    },
+    FileType {
+        indent: Some((SPACES, 2)),
+        highlight: Some("json"),
+        ..file_type(
+            "json",
+            &["json"],
+            &[],
+            crate::comment::CommentStyle::Block("<!--", "-->"),
+        )
+    },
+    FileType {
+        indent: Some((SPACES, 2)),
+        highlight: Some("yaml"),
+        word_chars: "_-",
+        ..file_type("yaml", &["yaml", "yml"], &[], LINE_HASH)
+    },
+    FileType {
+        highlight: Some("toml"),
+        word_chars: "_-",
+        ..file_type("toml", &["toml", "ini", "conf", "cfg"], &[], LINE_HASH)
+    },
+    FileType {
+        word_chars: "_-",
+        ..file_type(
+            "css",
+            &["css"],
+            &[],
+            crate::comment::CommentStyle::Block("/*", "*/"),
+        )
+    },
+    FileType {
+        word_chars: "_-$",
+        ..file_type("scss", &["scss"], &[], LINE_SLASHES)
+    },
+    file_type("r", &["r"], &["rscript"], LINE_HASH),
+    file_type("text", &["txt", "log"], &[], LINE_HASH),
+];
+
+/// The file type called `name`, or with `name` as an extension, whatever the case.
+pub fn by_name(name: &str) -> Option<&'static FileType> {
+    let name = name.to_ascii_lowercase();
+
+    std::iter::once(&MARKDOWN)
+        .chain(FILE_TYPES)
+        .find(|file_type| file_type.name == name || file_type.extensions.contains(&name.as_str()))
+}
+
+/// The file type of `path` by its extension; [`MARKDOWN`] for none or an unknown one.
+pub fn from_path(path: Option<&std::path::Path>) -> &'static FileType {
+    path.and_then(std::path::Path::extension)
+        .and_then(std::ffi::OsStr::to_str)
+        .and_then(by_name)
+        .unwrap_or(&MARKDOWN)
+}
+
+/// The file type a `#!` line names, going through `env` and ignoring versions,
+/// so `#!/usr/bin/env python3.12` is Python.
+fn from_shebang(line: &str) -> Option<&'static FileType> {
+    let mut words = line.strip_prefix("#!")?.split_whitespace();
+    let mut program = words.next()?.rsplit('/').next()?;
+
+    if program == "env" {
+        program = words.find(|word| !word.starts_with('-'))?;
+    }
+
+    let program = program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+
+    FILE_TYPES
+        .iter()
+        .find(|file_type| file_type.interpreters.contains(&program))
+}
+
+/// The file type of a file at `path` (if it has one) starting with `lines`: a
+/// modeline wins, then a `#!` line, then the extension.
+pub fn detect<S: AsRef<str>>(path: Option<&std::path::Path>, lines: &[S]) -> &'static FileType {
+    crate::modeline::find(lines)
+        .and_then(|modeline| by_name(&modeline.file_type?))
+        .or_else(|| from_shebang(lines.first()?.as_ref()))
+        .unwrap_or_else(|| from_path(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let path = |p: &'static str| Some(std::path::Path::new(p));
+
+        assert_eq!(detect::<&str>(path("lib.RS"), &[]).name, "rust");
+        assert_eq!(detect::<&str>(None, &[]).name, "markdown");
+        assert_eq!(
+            detect(path("deploy"), &["#!/usr/bin/env -S python3.12 -u"]).name,
+            "python"
+        );
+        assert_eq!(detect(path("run"), &["#!/bin/bash", "echo"]).name, "sh");
+        assert_eq!(
+            detect(path("notes.txt"), &["a", "b", "# vim: set ts=2 ft=yaml:"]).name,
+            "yaml"
+        );
+        assert_eq!(
+            detect(path("x.md"), &["# -*- mode: ruby; tab-width: 2 -*-"]).name,
+            "ruby"
+        );
+        assert!(by_name("js").unwrap().is_word_char('$'));
+    }
+}
@@ -0,0 +1,406 @@
+/// A block that can be collapsed: `start` stays visible, `start + 1..=end` hide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Fold {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Fold {
+    /// Whether collapsing this fold hides `row`.
+    pub fn hides(&self, row: usize) -> bool {
+        row > self.start && row <= self.end
+    }
+}
+
+/// How a file's blocks are found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Structure {
+    /// Objects and arrays, by their brackets.
+    Json,
+    /// Keys and list items, by indentation.
+    Yaml,
+    /// Sections, from a heading to the next one at its level or above.
+    Markdown,
+}
+
+impl Structure {
+    pub fn of(path: Option<&std::path::Path>) -> Self {
+        let ext = path
+            .and_then(std::path::Path::extension)
+            .and_then(std::ffi::OsStr::to_str)
+            .map(str::to_ascii_lowercase);
+
+        match ext.as_deref() {
+            Some("json") => Structure::Json,
+            Some("yaml" | "yml") => Structure::Yaml,
+            _ => Structure::Markdown,
+        }
+    }
+}
+
+/// Every block of `lines` spanning more than one row, sorted by start row and then
+/// outermost first.
+pub fn regions<S: AsRef<str>>(lines: &[S], structure: Structure) -> Vec<Fold> {
+    let mut folds = match structure {
+        Structure::Json => json_regions(lines),
+        Structure::Yaml => yaml_regions(lines),
+        Structure::Markdown => markdown_regions(lines),
+    };
+
+    folds.retain(|fold| fold.end > fold.start);
+    folds.sort_by_key(|fold| (fold.start, std::cmp::Reverse(fold.end)));
+    folds.dedup_by_key(|fold| fold.start);
+    folds
+}
+
+/// From each `{` or `[` to the row of its closing bracket. Brackets in strings
+/// don't count.
+fn json_regions<S: AsRef<str>>(lines: &[S]) -> Vec<Fold> {
+    let mut folds = Vec::new();
+    let mut open = Vec::new();
+    let mut in_string = false;
+
+    for (row, line) in lines.iter().enumerate() {
+        let mut escaped = false;
+
+        for c in line.as_ref().chars() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' if in_string => escaped = true,
+                '"' => in_string = !in_string,
+                '{' | '[' if !in_string => open.push(row),
+                '}' | ']' if !in_string => {
+                    if let Some(start) = open.pop() {
+                        folds.push(Fold { start, end: row });
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    folds
+}
+
+fn indent_of(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// From each line to the last one below it that's indented further, blank lines
+/// aside. Comments don't start blocks.
+fn yaml_regions<S: AsRef<str>>(lines: &[S]) -> Vec<Fold> {
+    let mut folds = Vec::new();
+
+    for (start, line) in lines.iter().enumerate() {
+        let line = line.as_ref();
+
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+
+        let own = indent_of(line);
+        let end = lines[start + 1..]
+            .iter()
+            .map(AsRef::as_ref)
+            .enumerate()
+            .take_while(|(_, below)| below.trim().is_empty() || indent_of(below) > own)
+            .filter(|(_, below)| !below.trim().is_empty())
+            .last()
+            .map(|(i, _)| start + 1 + i);
+
+        if let Some(end) = end {
+            folds.push(Fold { start, end });
+        }
+    }
+
+    folds
+}
+
+/// Heading sections, ending before the next heading at the same level or above,
+/// or the last non-blank line. `#` lines in code blocks aren't headings.
+fn markdown_regions<S: AsRef<str>>(lines: &[S]) -> Vec<Fold> {
+    let mut headings = Vec::new();
+    let mut in_code = false;
+
+    for (row, line) in lines.iter().enumerate() {
+        let line = line.as_ref();
+
+        if line.trim_start().starts_with("```") || line.trim_start().starts_with("~~~") {
+            in_code = !in_code;
+        } else if !in_code && let Some((level, _)) = crate::outline::parse_heading(line) {
+            headings.push((row, level));
+        }
+    }
+
+    let last_text = |from: usize, to: usize| {
+        (from..to)
+            .rev()
+            .find(|&row| !lines[row].as_ref().trim().is_empty())
+            .unwrap_or(from)
+    };
+
+    headings
+        .iter()
+        .enumerate()
+        .map(|(i, &(start, level))| {
+            let next = headings[i + 1..]
+                .iter()
+                .find(|&&(_, other)| other <= level)
+                .map_or(lines.len(), |&(row, _)| row);
+
+            Fold {
+                start,
+                end: last_text(start, next),
+            }
+        })
+        .collect()
+}
+
+/// A collapsed fold as it's remembered between runs: by the text of its first
+/// line, e.g. a heading, rather than its row, so edits elsewhere in the note
+/// don't move it onto another block.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FoldKey {
+    /// The first line, trimmed.
+    pub line: String,
+    /// How many lines above it read the same.
+    pub occurrence: usize,
+}
+
+impl FoldKey {
+    /// `occurrence:line`, with the line percent-encoded so it holds no tabs or
+    /// commas.
+    pub fn encode(&self) -> String {
+        format!(
+            "{}:{}",
+            self.occurrence,
+            crate::workspace::percent_encode(&self.line)
+        )
+    }
+
+    pub fn parse(text: &str) -> Option<Self> {
+        let (occurrence, line) = text.split_once(':')?;
+
+        Some(Self {
+            line: crate::workspace::percent_decode(line),
+            occurrence: occurrence.parse().ok()?,
+        })
+    }
+}
+
+/// The key of each row of `lines`.
+fn row_keys<S: AsRef<str>>(lines: &[S]) -> Vec<FoldKey> {
+    let mut seen: std::collections::HashMap<&str, usize> = std::collections::HashMap::new();
+
+    lines
+        .iter()
+        .map(|line| {
+            let line = line.as_ref().trim();
+            let occurrence = seen.entry(line).or_default();
+            let key = FoldKey {
+                line: line.to_string(),
+                occurrence: *occurrence,
+            };
+
+            *occurrence += 1;
+            key
+        })
+        .collect()
+}
+
+/// The keys of `folds` in `lines`.
+pub fn keys<S: AsRef<str>>(lines: &[S], folds: &[Fold]) -> Vec<FoldKey> {
+    let rows = row_keys(lines);
+
+    folds
+        .iter()
+        .filter_map(|fold| rows.get(fold.start).cloned())
+        .collect()
+}
+
+/// The blocks among `regions` of `lines` that `keys` name. Keys whose line is
+/// gone, or no longer starts a block, find nothing.
+pub fn find<S: AsRef<str>>(lines: &[S], regions: &[Fold], keys: &[FoldKey]) -> Vec<Fold> {
+    let rows = row_keys(lines);
+
+    regions
+        .iter()
+        .copied()
+        .filter(|fold| rows.get(fold.start).is_some_and(|key| keys.contains(key)))
+        .collect()
+}
+
+/// The folds collapsed in a document, kept in step with its edits.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Folds {
+    /// Sorted by start row. Folds may nest.
+    folded: Vec<Fold>,
+    /// Bumped on every change so views can cheaply tell when to lay out again.
+    pub revision: u64,
+}
+
+impl Folds {
+    pub fn folded(&self) -> &[Fold] {
+        &self.folded
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.folded.is_empty()
+    }
+
+    /// Whether `row` is hidden inside a collapsed fold.
+    pub fn is_hidden(&self, row: usize) -> bool {
+        self.folded.iter().any(|fold| fold.hides(row))
+    }
+
+    /// The collapsed fold starting at `row`, if any.
+    pub fn folded_at(&self, row: usize) -> Option<Fold> {
+        self.folded.iter().copied().find(|fold| fold.start == row)
+    }
+
+    pub fn fold(&mut self, fold: Fold) {
+        if let Err(i) = self.folded.binary_search(&fold) {
+            self.folded.insert(i, fold);
+            self.revision += 1;
+        }
+    }
+
+    /// Expands the fold starting at `row`. Returns whether there was one.
+    pub fn unfold_at(&mut self, row: usize) -> bool {
+        let before = self.folded.len();
+
+        self.folded.retain(|fold| fold.start != row);
+        self.changed(before)
+    }
+
+    /// Expands every fold hiding `row`, so it can be seen.
+    pub fn reveal(&mut self, row: usize) -> bool {
+        let before = self.folded.len();
+
+        self.folded.retain(|fold| !fold.hides(row));
+        self.changed(before)
+    }
+
+    pub fn clear(&mut self) {
+        let before = self.folded.len();
+
+        self.folded.clear();
+        self.changed(before);
+    }
+
+    fn changed(&mut self, before: usize) -> bool {
+        let changed = self.folded.len() != before;
+
+        if changed {
+            self.revision += 1;
+        }
+        changed
+    }
+
+    /// Shifts the folds below an edit that replaced rows `start..=old_end` by
+    /// `start..=new_end`. A fold the edit reaches into is expanded, the lines it
+    /// hid may be gone.
+    pub fn update(&mut self, start: usize, old_end: usize, new_end: usize) {
+        if self.folded.is_empty() {
+            return;
+        }
+
+        let delta = new_end as isize - old_end as isize;
+
+        self.folded.retain_mut(|fold| {
+            if fold.end < start {
+                true
+            } else if fold.start > old_end {
+                fold.start = (fold.start as isize + delta) as usize;
+                fold.end = (fold.end as isize + delta) as usize;
+                true
+            } else {
+                // An edit confined to the visible first row leaves the block alone.
+                start == fold.start && old_end == fold.start && new_end == fold.start
+            }
+        });
+        self.revision += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regions() {
+        let json = ["{", "  \"a\": [1,", "    2],", "  \"b\": \"]{\"", "}"];
+        let yaml = [
+            "root:",
+            "  list:",
+            "    - name: x",
+            "      size: 1",
+            "",
+            "  other: 2",
+            "top: 3",
+        ];
+        let markdown = [
+            "# A", "text", "## B", "```", "# no", "```", "", "# C", "end",
+        ];
+        let fold = |start, end| Fold { start, end };
+
+        assert_eq!(
+            regions(&json, Structure::Json),
+            vec![fold(0, 4), fold(1, 2)]
+        );
+        assert_eq!(
+            regions(&yaml, Structure::Yaml),
+            vec![fold(0, 5), fold(1, 3), fold(2, 3)]
+        );
+        assert_eq!(
+            regions(&markdown, Structure::Markdown),
+            vec![fold(0, 5), fold(2, 5), fold(7, 8)]
+        );
+    }
+
+    #[test]
+    fn test_folds_follow_edits() {
+        let mut folds = Folds::default();
+
+        folds.fold(Fold { start: 2, end: 4 });
+        folds.fold(Fold { start: 8, end: 9 });
+        assert!(folds.is_hidden(3) && !folds.is_hidden(2));
+
+        // Two lines inserted above both.
+        folds.update(0, 0, 2);
+        assert_eq!(
+            folds.folded(),
+            &[Fold { start: 4, end: 6 }, Fold { start: 10, end: 11 }]
+        );
+
+        // An edit inside the first expands it.
+        folds.update(5, 5, 5);
+        assert_eq!(folds.folded(), &[Fold { start: 10, end: 11 }]);
+        assert!(folds.reveal(11));
+        assert!(folds.is_empty());
+    }
+
+    #[test]
+    fn test_fold_keys_survive_edits() {
+        let before = ["# Log", "## Day", "a", "## Day", "b", "# Ideas", "c"];
+        let regions_before = regions(&before, Structure::Markdown);
+        let folded = [Fold { start: 3, end: 4 }, Fold { start: 5, end: 6 }];
+        let saved: Vec<String> = keys(&before, &folded).iter().map(FoldKey::encode).collect();
+
+        assert_eq!(saved, ["1:%23%23%20Day", "0:%23%20Ideas"]);
+        assert_eq!(
+            find(&before, &regions_before, &keys(&before, &folded)),
+            folded
+        );
+
+        let after = [
+            "intro", "# Log", "## Day", "a", "more", "## Day", "b", "# Ideas", "c",
+        ];
+        let restored: Vec<FoldKey> = saved.iter().filter_map(|key| FoldKey::parse(key)).collect();
+
+        assert_eq!(
+            find(&after, &regions(&after, Structure::Markdown), &restored),
+            [Fold { start: 5, end: 6 }, Fold { start: 7, end: 8 }]
+        );
+    }
+}
@@ -0,0 +1,133 @@
+/// Reads a `[label]: target` definition, of a reference link or, with a `^` in
+/// front of the label, of a footnote. Up to three spaces may come before it.
+pub fn definition(line: &str) -> Option<(&str, &str)> {
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    let (label, rest) = line[indent..].strip_prefix('[')?.split_once("]:")?;
+
+    (indent <= 3 && !label.is_empty() && !label.contains(['[', ']']))
+        .then_some((label, rest.trim()))
+}
+
+/// The definitions in `lines` by lowercased label, footnotes keeping their `^`.
+/// A footnote's text runs on over the indented lines below it. The first of two
+/// definitions of a label wins.
+pub fn definitions<S: AsRef<str>>(lines: &[S]) -> std::collections::HashMap<String, String> {
+    let mut found = std::collections::HashMap::new();
+    let mut footnote: Option<String> = None;
+
+    for line in lines {
+        let line = line.as_ref();
+
+        if let Some((label, text)) = definition(line) {
+            let label = label.to_lowercase();
+
+            footnote = label.starts_with('^').then(|| label.clone());
+            found.entry(label).or_insert_with(|| text.to_string());
+        } else if let Some(label) = &footnote
+            && line.starts_with("    ")
+            && let Some(text) = found.get_mut(label)
+        {
+            text.push(' ');
+            text.push_str(line.trim());
+        } else {
+            footnote = None;
+        }
+    }
+
+    found
+}
+
+/// The id of the footnote reference or definition `[^id]` at byte `col` of `line`.
+pub fn footnote_at(line: &str, col: usize) -> Option<&str> {
+    let mut from = 0;
+
+    while let Some(at) = line[from..].find("[^").map(|at| from + at) {
+        let close = at + line[at..].find(']')?;
+
+        if (at..=close).contains(&col) {
+            let id = &line[at + 2..close];
+            return (!id.is_empty() && !id.contains(char::is_whitespace)).then_some(id);
+        }
+        from = close;
+    }
+
+    None
+}
+
+/// The id for a new footnote: one past the highest numbered one so far.
+pub fn next_id<S: AsRef<str>>(lines: &[S]) -> String {
+    let highest = lines
+        .iter()
+        .flat_map(|line| {
+            line.as_ref()
+                .match_indices("[^")
+                .filter_map(|(at, _)| {
+                    let rest = &line.as_ref()[at + 2..];
+                    rest[..rest.find(']')?].parse::<u32>().ok()
+                })
+                .collect::<Vec<_>>()
+        })
+        .max()
+        .unwrap_or(0);
+
+    (highest + 1).to_string()
+}
+
+/// Where footnote `id` is defined: the row and the byte its text starts at.
+pub fn find_definition<S: AsRef<str>>(lines: &[S], id: &str) -> Option<(usize, usize)> {
+    lines.iter().enumerate().find_map(|(row, line)| {
+        let line = line.as_ref();
+        let (label, _) = definition(line)?;
+
+        (label.strip_prefix('^')? == id).then(|| {
+            let after = line.find("]:").expect("Found by definition") + 2;
+            (
+                row,
+                after + line[after..].len() - line[after..].trim_start().len(),
+            )
+        })
+    })
+}
+
+/// Where footnote `id` is first referred to: the row and the byte right after the
+/// reference.
+pub fn find_reference<S: AsRef<str>>(lines: &[S], id: &str) -> Option<(usize, usize)> {
+    let reference = format!("[^{id}]");
+
+    lines.iter().enumerate().find_map(|(row, line)| {
+        let line = line.as_ref();
+        let skip = definition(line).map_or(0, |_| line.find("]:").expect("Found by definition"));
+
+        line[skip..]
+            .find(&reference)
+            .map(|at| (row, skip + at + reference.len()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_footnotes() {
+        let lines = [
+            "As seen[^1] and in [the docs][Docs].",
+            "",
+            "[^1]: First note,",
+            "    continued.",
+            "[docs]: https://example.com",
+            "[^2]: Second, see [^1].",
+        ];
+
+        let found = definitions(&lines);
+        assert_eq!(found["^1"], "First note, continued.");
+        assert_eq!(found["docs"], "https://example.com");
+
+        assert_eq!(footnote_at(lines[0], 9), Some("1"));
+        assert_eq!(footnote_at(lines[0], 2), None);
+        assert_eq!(next_id(&lines), "3");
+        assert_eq!(find_definition(&lines, "1"), Some((2, 6)));
+        assert_eq!(find_reference(&lines, "1"), Some((0, 11)));
+        assert_eq!(find_reference(&lines, "2"), None);
+    }
+}
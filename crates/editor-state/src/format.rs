@@ -0,0 +1,351 @@
+fn invalid(line: usize, message: &str) -> std::io::Error {
+    std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        format!("Invalid JSON on line {line}: {message}"),
+    )
+}
+
+/// Pretty-prints JSON as it reads it: one member or element per line, nested
+/// `indent` deeper per level, empty objects and arrays kept as `{}` and `[]`.
+struct JsonPrinter<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    indent: &'a str,
+    out: String,
+    /// 1-based, for errors.
+    line: usize,
+}
+
+impl JsonPrinter<'_> {
+    /// Objects and arrays nested deeper than this are refused rather than recursed
+    /// into, so that `[[[[…` can't overflow the stack.
+    const MAX_DEPTH: usize = 128;
+
+    fn skip_space(&mut self) {
+        while let Some(&c) = self.chars.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+            if c == '\n' {
+                self.line += 1;
+            }
+            self.chars.next();
+        }
+    }
+
+    fn expect(&mut self, wanted: char) -> std::io::Result<()> {
+        self.skip_space();
+        match self.chars.next() {
+            Some(c) if c == wanted => Ok(()),
+            Some(c) => Err(invalid(
+                self.line,
+                &format!("expected `{wanted}`, found `{c}`"),
+            )),
+            None => Err(invalid(
+                self.line,
+                &format!("expected `{wanted}`, found the end"),
+            )),
+        }
+    }
+
+    fn newline(&mut self, depth: usize) {
+        self.out.push('\n');
+        self.out.push_str(&self.indent.repeat(depth));
+    }
+
+    fn value(&mut self, depth: usize) -> std::io::Result<()> {
+        self.skip_space();
+        match self.chars.peek() {
+            Some('{') => self.container('}', depth),
+            Some('[') => self.container(']', depth),
+            Some('"') => self.string(),
+            Some(_) => self.scalar(),
+            None => Err(invalid(self.line, "expected a value, found the end")),
+        }
+    }
+
+    /// An object or array, closed by `close`.
+    fn container(&mut self, close: char, depth: usize) -> std::io::Result<()> {
+        if depth >= Self::MAX_DEPTH {
+            return Err(invalid(self.line, "nested too deeply"));
+        }
+
+        let open = self.chars.next().unwrap_or_default();
+
+        self.out.push(open);
+        self.skip_space();
+        if self.chars.next_if_eq(&close).is_some() {
+            self.out.push(close);
+            return Ok(());
+        }
+
+        loop {
+            self.newline(depth + 1);
+            if close == '}' {
+                self.skip_space();
+                if self.chars.peek() != Some(&'"') {
+                    return Err(invalid(self.line, "expected a quoted key"));
+                }
+                self.string()?;
+                self.expect(':')?;
+                self.out.push_str(": ");
+            }
+            self.value(depth + 1)?;
+            self.skip_space();
+
+            match self.chars.next() {
+                Some(',') => self.out.push(','),
+                Some(c) if c == close => break,
+                Some(c) => return Err(invalid(self.line, &format!("unexpected `{c}`"))),
+                None => return Err(invalid(self.line, &format!("missing `{close}`"))),
+            }
+        }
+
+        self.newline(depth);
+        self.out.push(close);
+        Ok(())
+    }
+
+    /// A string, copied as written, escapes included.
+    fn string(&mut self) -> std::io::Result<()> {
+        self.chars.next();
+        self.out.push('"');
+
+        while let Some(c) = self.chars.next() {
+            match c {
+                '"' => {
+                    self.out.push('"');
+                    return Ok(());
+                }
+                '\\' => {
+                    self.out.push(c);
+                    self.out.extend(self.chars.next());
+                }
+                '\n' => return Err(invalid(self.line, "line break in a string")),
+                _ => self.out.push(c),
+            }
+        }
+
+        Err(invalid(self.line, "unterminated string"))
+    }
+
+    /// `true`, `false`, `null` or a number.
+    fn scalar(&mut self) -> std::io::Result<()> {
+        let mut word = String::new();
+
+        while let Some(c) = self
+            .chars
+            .next_if(|c| !c.is_whitespace() && !matches!(c, ',' | ':' | ']' | '}' | '[' | '{'))
+        {
+            word.push(c);
+        }
+
+        let number = word.starts_with(|c: char| c == '-' || c.is_ascii_digit())
+            && word
+                .chars()
+                .all(|c| c.is_ascii_digit() || "+-.eE".contains(c))
+            && word.parse::<f64>().is_ok();
+
+        if !number && !matches!(word.as_str(), "true" | "false" | "null") {
+            let found = if word.is_empty() {
+                self.chars.peek().map(char::to_string).unwrap_or_default()
+            } else {
+                word
+            };
+
+            return Err(invalid(self.line, &format!("unexpected `{found}`")));
+        }
+
+        self.out.push_str(&word);
+        Ok(())
+    }
+}
+
+/// `text` pretty-printed, nested `indent` deeper per level.
+///
+/// # Errors
+///
+/// Returns an `InvalidData` error naming the line if `text` isn't valid JSON or
+/// nests too deeply to print.
+pub fn json(text: &str, indent: &str) -> std::io::Result<Vec<String>> {
+    let mut printer = JsonPrinter {
+        chars: text.chars().peekable(),
+        indent,
+        out: String::new(),
+        line: 1,
+    };
+
+    printer.value(0)?;
+    printer.skip_space();
+    if let Some(c) = printer.chars.next() {
+        return Err(invalid(
+            printer.line,
+            &format!("unexpected `{c}` after the end"),
+        ));
+    }
+
+    Ok(printer.out.lines().map(str::to_string).collect())
+}
+
+fn indent_of(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Whether `line` opens a `|` or `>` block scalar, e.g. `script: |-`.
+fn opens_block(line: &str) -> bool {
+    let header = line
+        .trim_end()
+        .trim_end_matches(|c: char| c == '-' || c == '+' || c.is_ascii_digit());
+    let Some(before) = header.strip_suffix(['|', '>']) else {
+        return false;
+    };
+    let before = before.trim();
+
+    before.is_empty() || before.ends_with(':') || before.ends_with('-')
+}
+
+/// YAML re-indented `indent` deeper per level of nesting, with trailing whitespace
+/// trimmed and runs of blank lines collapsed. Keys inside a `- ` list item stay
+/// lined up with its first key, and `|`/`>` block scalars keep their own
+/// indentation, moved as a whole.
+pub fn yaml<S: AsRef<str>>(lines: &[S], indent: &str) -> Vec<String> {
+    let step = indent.len().max(1);
+    let mut out: Vec<String> = Vec::new();
+    // `(indent as written, indent now)` of the lines enclosing the current one.
+    let mut levels: Vec<(usize, usize)> = Vec::new();
+    // In a block scalar: its header's indent as written and now, then the first
+    // content line's indent as written once seen.
+    let mut block: Option<(usize, usize, Option<usize>)> = None;
+
+    for line in lines {
+        let line = line.as_ref().trim_end();
+        let own = indent_of(line);
+
+        if let Some((header, header_now, base)) = &mut block {
+            if line.is_empty() {
+                out.push(String::new());
+                continue;
+            }
+            if own > *header {
+                let base = *base.get_or_insert(own);
+                let now = (*header_now + step + own).saturating_sub(base);
+
+                out.push(format!("{}{}", " ".repeat(now), line.trim_start()));
+                continue;
+            }
+            block = None;
+        }
+
+        if line.is_empty() {
+            if out.last().is_some_and(|last| !last.is_empty()) {
+                out.push(String::new());
+            }
+            continue;
+        }
+
+        while levels.last().is_some_and(|&(written, _)| written > own) {
+            levels.pop();
+        }
+
+        let now = match levels.last() {
+            Some(&(written, now)) if written == own => now,
+            Some(&(_, now)) => {
+                levels.push((own, now + step));
+                now + step
+            }
+            None => {
+                levels.push((own, 0));
+                0
+            }
+        };
+
+        let content = line.trim_start();
+
+        // What follows a `- ` on the next lines lines up with the text after the dash.
+        if let Some(after) = content
+            .strip_prefix('-')
+            .filter(|after| after.starts_with(' '))
+        {
+            let offset = 1 + after.len() - after.trim_start().len();
+
+            levels.push((own + offset, now + offset));
+        }
+        if opens_block(line) {
+            block = Some((own, now, None));
+        }
+        out.push(format!("{}{content}", " ".repeat(now)));
+    }
+
+    while out.last().is_some_and(String::is_empty) {
+        out.pop();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json() {
+        let text = "{\"a\": [1, 2.5e3, {}], \"b\":{\"c\":\"x, ]\\\"\" ,\"d\":null},\"e\":[]}";
+
+        assert_eq!(
+            json(text, "  ").unwrap(),
+            vec![
+                "{",
+                "  \"a\": [",
+                "    1,",
+                "    2.5e3,",
+                "    {}",
+                "  ],",
+                "  \"b\": {",
+                "    \"c\": \"x, ]\\\"\",",
+                "    \"d\": null",
+                "  },",
+                "  \"e\": []",
+                "}",
+            ]
+        );
+        assert_eq!(
+            json("{\n\"a\": tru}", "  ").unwrap_err().to_string(),
+            "Invalid JSON on line 2: unexpected `tru`"
+        );
+        assert!(json("[1,]", "  ").is_err());
+        assert!(json("[1] 2", "  ").is_err());
+        assert_eq!(
+            json(&"[".repeat(100_000), "  ").unwrap_err().to_string(),
+            "Invalid JSON on line 1: nested too deeply"
+        );
+    }
+
+    #[test]
+    fn test_yaml() {
+        let lines = [
+            "root:   ",
+            "   items:",
+            "   - name: a",
+            "     size: 1",
+            "",
+            "",
+            "   script: |",
+            "         echo hi",
+            "           indented",
+            "top: 1",
+        ];
+
+        assert_eq!(
+            yaml(&lines, "  "),
+            vec![
+                "root:",
+                "  items:",
+                "  - name: a",
+                "    size: 1",
+                "",
+                "  script: |",
+                "    echo hi",
+                "      indented",
+                "top: 1",
+            ]
+        );
+    }
+}
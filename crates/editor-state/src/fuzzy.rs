@@ -0,0 +1,72 @@
+/// How well `query` matches `candidate` as a subsequence, ignoring case: higher is
+/// better, `None` if it doesn't match at all. Runs of consecutive characters and
+/// matches at the start of words count extra, so `oi` ranks `Open Ideas` above
+/// `todo list`.
+pub fn score(query: &str, candidate: &str) -> Option<i64> {
+    let mut query = query.chars().filter(|c| !c.is_whitespace()).peekable();
+    let mut total = 0;
+    let mut previous: Option<char> = None;
+    let mut last_match: Option<usize> = None;
+
+    for (i, c) in candidate.chars().enumerate() {
+        let Some(&wanted) = query.peek() else {
+            break;
+        };
+
+        if c.to_lowercase().eq(wanted.to_lowercase()) {
+            let word_start = previous.is_none_or(|previous| {
+                !previous.is_alphanumeric() || (previous.is_lowercase() && c.is_uppercase())
+            });
+
+            total += 1;
+            if word_start {
+                total += 8;
+            }
+            if last_match.is_some_and(|last| last + 1 == i) {
+                total += 5;
+            }
+            last_match = Some(i);
+            query.next();
+        }
+        previous = Some(c);
+    }
+
+    if query.peek().is_some() {
+        return None;
+    }
+
+    // Among equals, shorter candidates are closer matches.
+    Some(total * 1000 - candidate.chars().count() as i64)
+}
+
+/// Indexes of the `candidates` matching `query`, best first. Ties keep their order,
+/// and an empty query matches everything as is.
+pub fn filter<S: AsRef<str>>(query: &str, candidates: &[S]) -> Vec<usize> {
+    if query.trim().is_empty() {
+        return (0..candidates.len()).collect();
+    }
+
+    let mut matches: Vec<(usize, i64)> = candidates
+        .iter()
+        .enumerate()
+        .filter_map(|(i, candidate)| Some((i, score(query, candidate.as_ref())?)))
+        .collect();
+
+    matches.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
+    matches.into_iter().map(|(i, _)| i).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_ranks_word_starts_and_runs() {
+        let candidates = ["todo list", "Open Ideas", "nada", "openIdeas draft"];
+
+        assert_eq!(filter("oi", &candidates), vec![1, 3, 0]);
+        assert_eq!(filter("open", &candidates), vec![1, 3]);
+        assert_eq!(filter("", &candidates), vec![0, 1, 2, 3]);
+        assert_eq!(score("xyz", "Open Ideas"), None);
+    }
+}
@@ -0,0 +1,543 @@
+/// How a run of inline Markdown is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpanStyle {
+    /// Syntax characters such as `*`, `**` or `_` around emphasis.
+    Marker,
+    Emphasis,
+    Strong,
+    /// A `` `code` `` span, backticks included.
+    Code,
+    /// A `$formula$`, dollars included, see [`crate::math::inline_end`].
+    Math,
+    /// Tokens of fenced code, see [`code_spans`].
+    Keyword,
+    String,
+    Number,
+    Comment,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Span {
+    pub range: std::ops::Range<usize>,
+    pub style: SpanStyle,
+}
+
+impl Span {
+    fn new(range: std::ops::Range<usize>, style: SpanStyle) -> Self {
+        Self { range, style }
+    }
+}
+
+/// Length of the run of `byte` starting at `at`.
+fn run_len(bytes: &[u8], at: usize, byte: u8) -> usize {
+    bytes[at..].iter().take_while(|&&b| b == byte).count()
+}
+
+/// Finds the closing delimiter for emphasis opened with `n` copies of `byte`
+/// just before `from`. It must directly follow non-whitespace, and `_` must not
+/// sit inside a word.
+fn find_closer(bytes: &[u8], from: usize, byte: u8, n: usize) -> Option<usize> {
+    let mut i = from;
+
+    while i < bytes.len() {
+        if bytes[i] == b'`' {
+            // Code spans win over emphasis, so skip over them whole.
+            let ticks = run_len(bytes, i, b'`');
+            i = find_code_end(bytes, i + ticks, ticks).unwrap_or(i + ticks);
+            continue;
+        }
+
+        let run = run_len(bytes, i, byte);
+
+        if run == 0 {
+            i += 1;
+            continue;
+        }
+
+        let after_word = !bytes[i - 1].is_ascii_whitespace();
+        let word_follows = bytes.get(i + run).is_some_and(u8::is_ascii_alphanumeric);
+
+        if run == n && i > from && after_word && !(byte == b'_' && word_follows) {
+            return Some(i);
+        }
+
+        i += run;
+    }
+
+    None
+}
+
+/// End (exclusive) of a code span whose opening run of `ticks` backticks ends at `from`.
+fn find_code_end(bytes: &[u8], from: usize, ticks: usize) -> Option<usize> {
+    let mut i = from;
+
+    while i < bytes.len() {
+        let run = run_len(bytes, i, b'`');
+
+        if run == ticks {
+            return Some(i + run);
+        }
+
+        i += run.max(1);
+    }
+
+    None
+}
+
+/// Inline Markdown spans of a single line: emphasis, strong emphasis, code and
+/// math. Plain text isn't listed. Emphasis doesn't nest; the outermost span wins.
+pub fn inline_spans(line: &str) -> Vec<Span> {
+    let bytes = line.as_bytes();
+    let mut spans = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'`' => {
+                let ticks = run_len(bytes, i, b'`');
+
+                match find_code_end(bytes, i + ticks, ticks) {
+                    Some(end) => {
+                        spans.push(Span::new(i..end, SpanStyle::Code));
+                        i = end;
+                    }
+                    None => i += ticks,
+                }
+            }
+            b'$' => match crate::math::inline_end(line, i) {
+                Some(end) => {
+                    spans.push(Span::new(i..end, SpanStyle::Math));
+                    i = end;
+                }
+                None => i += run_len(bytes, i, b'$'),
+            },
+            byte @ (b'*' | b'_') => {
+                let run = run_len(bytes, i, byte);
+                let n = run.min(2);
+                let opens = bytes.get(i + run).is_some_and(|b| !b.is_ascii_whitespace())
+                    && !(byte == b'_' && i > 0 && bytes[i - 1].is_ascii_alphanumeric());
+
+                match find_closer(bytes, i + n, byte, n).filter(|_| run <= 2 && opens) {
+                    Some(close) => {
+                        let style = if n == 2 {
+                            SpanStyle::Strong
+                        } else {
+                            SpanStyle::Emphasis
+                        };
+
+                        spans.push(Span::new(i..i + n, SpanStyle::Marker));
+                        spans.push(Span::new(i + n..close, style));
+                        spans.push(Span::new(close..close + n, SpanStyle::Marker));
+                        i = close + n;
+                    }
+                    None => i += run,
+                }
+            }
+            _ => i += 1,
+        }
+    }
+
+    spans
+}
+
+/// What code in a fenced block is highlighted by, see [`Language::from_tag`].
+pub struct Language {
+    keywords: &'static [&'static str],
+    /// Keywords match whatever their case, as in SQL.
+    ignore_case: bool,
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [u8],
+}
+
+const C_KEYWORDS: &[&str] = &[
+    "auto",
+    "bool",
+    "break",
+    "case",
+    "catch",
+    "char",
+    "class",
+    "const",
+    "continue",
+    "default",
+    "defer",
+    "delete",
+    "do",
+    "double",
+    "else",
+    "enum",
+    "extends",
+    "extern",
+    "false",
+    "final",
+    "float",
+    "for",
+    "func",
+    "go",
+    "goto",
+    "if",
+    "implements",
+    "import",
+    "int",
+    "interface",
+    "long",
+    "map",
+    "namespace",
+    "new",
+    "null",
+    "nullptr",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "short",
+    "signed",
+    "sizeof",
+    "static",
+    "struct",
+    "super",
+    "switch",
+    "template",
+    "this",
+    "throw",
+    "throws",
+    "true",
+    "try",
+    "type",
+    "typedef",
+    "union",
+    "unsigned",
+    "using",
+    "var",
+    "virtual",
+    "void",
+    "volatile",
+    "while",
+];
+
+const LANGUAGES: &[(&[&str], Language)] = &[
+    (
+        &["rust", "rs"],
+        Language {
+            keywords: &[
+                "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else",
+                "enum", "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match",
+                "mod", "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct",
+                "super", "trait", "true", "type", "unsafe", "use", "where", "while",
+            ],
+            ignore_case: false,
+            line_comments: &["//"],
+            block_comment: Some(("/*", "*/")),
+            quotes: b"\"",
+        },
+    ),
+    (
+        &["python", "py"],
+        Language {
+            keywords: &[
+                "and", "as", "assert", "async", "await", "break", "class", "continue", "def",
+                "del", "elif", "else", "except", "False", "finally", "for", "from", "global", "if",
+                "import", "in", "is", "lambda", "None", "nonlocal", "not", "or", "pass", "raise",
+                "return", "self", "True", "try", "while", "with", "yield",
+            ],
+            ignore_case: false,
+            line_comments: &["#"],
+            block_comment: None,
+            quotes: b"\"'",
+        },
+    ),
+    (
+        &["javascript", "js", "jsx", "typescript", "ts", "tsx"],
+        Language {
+            keywords: &[
+                "async",
+                "await",
+                "break",
+                "case",
+                "catch",
+                "class",
+                "const",
+                "continue",
+                "default",
+                "delete",
+                "do",
+                "else",
+                "export",
+                "extends",
+                "false",
+                "finally",
+                "for",
+                "from",
+                "function",
+                "if",
+                "import",
+                "in",
+                "instanceof",
+                "interface",
+                "let",
+                "new",
+                "null",
+                "of",
+                "return",
+                "super",
+                "switch",
+                "this",
+                "throw",
+                "true",
+                "try",
+                "type",
+                "typeof",
+                "undefined",
+                "var",
+                "void",
+                "while",
+                "yield",
+            ],
+            ignore_case: false,
+            line_comments: &["//"],
+            block_comment: Some(("/*", "*/")),
+            quotes: b"\"'`",
+        },
+    ),
+    (
+        &[
+            "c", "h", "cpp", "c++", "hpp", "cs", "csharp", "java", "kotlin", "kt", "swift", "go",
+        ],
+        Language {
+            keywords: C_KEYWORDS,
+            ignore_case: false,
+            line_comments: &["//"],
+            block_comment: Some(("/*", "*/")),
+            quotes: b"\"'",
+        },
+    ),
+    (
+        &["sh", "bash", "zsh", "shell", "console"],
+        Language {
+            keywords: &[
+                "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function",
+                "if", "in", "local", "return", "then", "until", "while",
+            ],
+            ignore_case: false,
+            line_comments: &["#"],
+            block_comment: None,
+            quotes: b"\"'",
+        },
+    ),
+    (
+        &["sql"],
+        Language {
+            keywords: &[
+                "and", "as", "by", "create", "delete", "from", "group", "having", "insert", "into",
+                "join", "left", "limit", "not", "null", "on", "or", "order", "select", "set",
+                "table", "update", "values", "where",
+            ],
+            ignore_case: true,
+            line_comments: &["--"],
+            block_comment: Some(("/*", "*/")),
+            quotes: b"'",
+        },
+    ),
+    (
+        &["json", "toml", "yaml", "yml", "ini"],
+        Language {
+            keywords: &["true", "false", "null"],
+            ignore_case: false,
+            line_comments: &["#"],
+            block_comment: None,
+            quotes: b"\"'",
+        },
+    ),
+];
+
+impl Language {
+    /// The language a fence's info string (`` ```rust ``) names, by name or usual
+    /// file extension.
+    pub fn from_tag(info: &str) -> Option<&'static Language> {
+        let tag = info.split_whitespace().next()?.to_lowercase();
+
+        LANGUAGES
+            .iter()
+            .find(|(tags, _)| tags.contains(&tag.as_str()))
+            .map(|(_, language)| language)
+    }
+
+    /// The language of the code block opened by the fence line `fence`.
+    pub fn of_fence(fence: &str) -> Option<&'static Language> {
+        Self::from_tag(fence.trim().trim_start_matches(['`', '~']))
+    }
+
+    fn is_keyword(&self, word: &str) -> bool {
+        if self.ignore_case {
+            self.keywords.iter().any(|k| k.eq_ignore_ascii_case(word))
+        } else {
+            self.keywords.contains(&word)
+        }
+    }
+}
+
+/// Keywords, strings, numbers and comments on one line of code. Lines are taken
+/// one at a time, so strings and block comments end with the line.
+pub fn code_spans(line: &str, language: &Language) -> Vec<Span> {
+    let bytes = line.as_bytes();
+    let is_word = |b: u8| b.is_ascii_alphanumeric() || b == b'_';
+    let mut spans = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let rest = &line[i..];
+        let word_start = i == 0 || !is_word(bytes[i - 1]);
+
+        if language.line_comments.iter().any(|c| rest.starts_with(c)) {
+            spans.push(Span::new(i..line.len(), SpanStyle::Comment));
+            break;
+        }
+
+        if let Some((open, close)) = language.block_comment
+            && rest.starts_with(open)
+        {
+            let end = rest[open.len()..]
+                .find(close)
+                .map_or(line.len(), |at| i + open.len() + at + close.len());
+
+            spans.push(Span::new(i..end, SpanStyle::Comment));
+            i = end;
+        } else if language.quotes.contains(&bytes[i]) {
+            let mut end = i + 1;
+
+            while end < bytes.len() && bytes[end] != bytes[i] {
+                end += if bytes[end] == b'\\' { 2 } else { 1 };
+            }
+            end = (end + 1).min(bytes.len());
+            spans.push(Span::new(i..end, SpanStyle::String));
+            i = end;
+        } else if is_word(bytes[i]) && word_start {
+            let end = i + bytes[i..].iter().take_while(|&&b| is_word(b)).count();
+            let end = if bytes[i].is_ascii_digit() {
+                // `1.5`, `0x1f`, `2e10`: the fraction too.
+                end + bytes[end..]
+                    .iter()
+                    .take_while(|&&b| is_word(b) || b == b'.')
+                    .count()
+            } else {
+                end
+            };
+
+            if bytes[i].is_ascii_digit() {
+                spans.push(Span::new(i..end, SpanStyle::Number));
+            } else if language.is_keyword(&line[i..end]) {
+                spans.push(Span::new(i..end, SpanStyle::Keyword));
+            }
+            i = end;
+        } else {
+            i += rest.chars().next().map_or(1, char::len_utf8);
+        }
+    }
+
+    spans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn styled(line: &str) -> Vec<(&str, SpanStyle)> {
+        inline_spans(line)
+            .into_iter()
+            .map(|span| (&line[span.range], span.style))
+            .collect()
+    }
+
+    #[test]
+    fn test_emphasis_and_strong() {
+        assert_eq!(
+            styled("a *b* and **c d**"),
+            vec![
+                ("*", SpanStyle::Marker),
+                ("b", SpanStyle::Emphasis),
+                ("*", SpanStyle::Marker),
+                ("**", SpanStyle::Marker),
+                ("c d", SpanStyle::Strong),
+                ("**", SpanStyle::Marker),
+            ]
+        );
+        assert_eq!(styled("_under_")[1], ("under", SpanStyle::Emphasis));
+    }
+
+    #[test]
+    fn test_non_emphasis() {
+        assert!(styled("2 * 3 * 4").is_empty());
+        assert!(styled("snake_case_name").is_empty());
+        assert!(styled("- list item *").is_empty());
+        assert!(styled("***").is_empty());
+    }
+
+    #[test]
+    fn test_code_spans() {
+        assert_eq!(
+            styled("run `cargo *test*` now"),
+            vec![("`cargo *test*`", SpanStyle::Code)]
+        );
+        assert_eq!(styled("``a ` b``"), vec![("``a ` b``", SpanStyle::Code)]);
+        assert_eq!(
+            styled("*see `x*`*"),
+            vec![
+                ("*", SpanStyle::Marker),
+                ("see `x*`", SpanStyle::Emphasis),
+                ("*", SpanStyle::Marker),
+            ]
+        );
+        assert!(styled("`unclosed").is_empty());
+    }
+
+    #[test]
+    fn test_math_spans() {
+        assert_eq!(
+            styled(r"*so* $a*b$ and `$x$` cost $5"),
+            vec![
+                ("*", SpanStyle::Marker),
+                ("so", SpanStyle::Emphasis),
+                ("*", SpanStyle::Marker),
+                ("$a*b$", SpanStyle::Math),
+                ("`$x$`", SpanStyle::Code),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fenced_code_spans() {
+        let rust = Language::of_fence("```Rust ignore").unwrap();
+        let line = r#"let s = "a \" b"; // fn"#;
+        let styled: Vec<(&str, SpanStyle)> = code_spans(line, rust)
+            .into_iter()
+            .map(|span| (&line[span.range], span.style))
+            .collect();
+
+        assert_eq!(
+            styled,
+            vec![
+                ("let", SpanStyle::Keyword),
+                (r#""a \" b""#, SpanStyle::String),
+                ("// fn", SpanStyle::Comment),
+            ]
+        );
+
+        let sql = Language::from_tag("sql").unwrap();
+        let line = "SELECT x1 FROM t WHERE n > 1.5 -- é";
+        let styles: Vec<SpanStyle> = code_spans(line, sql).into_iter().map(|s| s.style).collect();
+
+        assert_eq!(
+            styles,
+            vec![
+                SpanStyle::Keyword,
+                SpanStyle::Keyword,
+                SpanStyle::Keyword,
+                SpanStyle::Number,
+                SpanStyle::Comment,
+            ]
+        );
+        assert!(Language::from_tag("brainfuck").is_none());
+    }
+}
@@ -0,0 +1,319 @@
+/// Starts every history log.
+const MAGIC: &[u8; 4] = b"MNHL";
+
+/// Bumped whenever the layout changes.
+pub const VERSION: u8 = 1;
+
+const INSERT: u8 = 0;
+const DELETE: u8 = 1;
+
+/// FNV-1a of the text plus its length: enough to tell whether a log belongs to it.
+pub fn fingerprint(text: &str) -> (u64, u64) {
+    let hash = text.bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    });
+
+    (text.len() as u64, hash)
+}
+
+pub(crate) fn invalid(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string())
+}
+
+pub(crate) fn put_number(out: &mut Vec<u8>, mut n: u64) {
+    loop {
+        let byte = (n & 0x7f) as u8;
+
+        n >>= 7;
+        if n == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+pub(crate) fn put_text(out: &mut Vec<u8>, text: &str) {
+    put_number(out, text.len() as u64);
+    out.extend_from_slice(text.as_bytes());
+}
+
+fn put_position(out: &mut Vec<u8>, pos: editor_core::cursor::Position) {
+    put_number(out, pos.row as u64);
+    put_number(out, pos.col as u64);
+}
+
+fn put_transaction(out: &mut Vec<u8>, transaction: &editor_core::history::Transaction) {
+    for cursor in [transaction.cursor_before, transaction.cursor_after] {
+        put_position(out, cursor.anchor);
+        put_position(out, cursor.head);
+    }
+    out.push(transaction.sealed as u8);
+    put_number(out, transaction.actions.len() as u64);
+
+    for action in &transaction.actions {
+        match action {
+            editor_core::enums::EditAction::Insert { pos, text } => {
+                out.push(INSERT);
+                put_position(out, *pos);
+                put_text(out, text);
+            }
+            editor_core::enums::EditAction::Delete { pos, end, text } => {
+                out.push(DELETE);
+                put_position(out, *pos);
+                put_position(out, *end);
+                put_text(out, text);
+            }
+        }
+    }
+}
+
+/// Reads what the `put_*` functions wrote.
+pub(crate) struct Reader<'a> {
+    pub bytes: &'a [u8],
+    /// What is being read, for error messages: "History log", ...
+    pub what: &'static str,
+}
+
+impl Reader<'_> {
+    fn cut_short(&self) -> std::io::Error {
+        invalid(&format!("{} is cut short", self.what))
+    }
+
+    pub fn byte(&mut self) -> std::io::Result<u8> {
+        let (&byte, rest) = self.bytes.split_first().ok_or_else(|| self.cut_short())?;
+
+        self.bytes = rest;
+        Ok(byte)
+    }
+
+    pub fn number(&mut self) -> std::io::Result<u64> {
+        let mut n = 0_u64;
+
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+
+            n |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+
+        Err(invalid(&format!("{} has an oversized number", self.what)))
+    }
+
+    /// A count of things still to read, checked against what's left so a corrupt
+    /// log can't make the reader allocate wildly.
+    pub fn count(&mut self) -> std::io::Result<usize> {
+        let n = self.number()?;
+
+        if n > self.bytes.len() as u64 {
+            return Err(self.cut_short());
+        }
+
+        Ok(n as usize)
+    }
+
+    pub fn text(&mut self) -> std::io::Result<String> {
+        let len = self.count()?;
+        let (text, rest) = self.bytes.split_at(len);
+
+        self.bytes = rest;
+        String::from_utf8(text.to_vec())
+            .map_err(|_| invalid(&format!("{} has invalid text", self.what)))
+    }
+
+    fn position(&mut self) -> std::io::Result<editor_core::cursor::Position> {
+        Ok(editor_core::cursor::Position::new(
+            self.number()? as usize,
+            self.number()? as usize,
+        ))
+    }
+
+    fn cursor(&mut self) -> std::io::Result<editor_core::cursor::Cursor> {
+        let anchor = self.position()?;
+
+        Ok(editor_core::cursor::Cursor::new_selection(
+            anchor,
+            self.position()?,
+        ))
+    }
+
+    fn transaction(&mut self) -> std::io::Result<editor_core::history::Transaction> {
+        let cursor_before = self.cursor()?;
+        let cursor_after = self.cursor()?;
+        let sealed = self.byte()? != 0;
+        let actions = (0..self.count()?)
+            .map(|_| match self.byte()? {
+                INSERT => Ok(editor_core::enums::EditAction::Insert {
+                    pos: self.position()?,
+                    text: self.text()?,
+                }),
+                DELETE => Ok(editor_core::enums::EditAction::Delete {
+                    pos: self.position()?,
+                    end: self.position()?,
+                    text: self.text()?,
+                }),
+                _ => Err(invalid("History log has an unknown edit")),
+            })
+            .collect::<std::io::Result<_>>()?;
+
+        Ok(editor_core::history::Transaction {
+            actions,
+            cursor_before,
+            cursor_after,
+            sealed,
+        })
+    }
+
+    fn stack(&mut self) -> std::io::Result<Vec<editor_core::history::Transaction>> {
+        (0..self.count()?).map(|_| self.transaction()).collect()
+    }
+}
+
+/// `history` in a compact binary form, so undo can outlive the session, be replayed
+/// elsewhere or travel with a synced note. `text` is the document's text now.
+///
+/// Layout: the magic `MNHL`, a [`VERSION`] byte, a [`fingerprint`] of `text`, then
+/// the undo and redo stacks. Numbers are LEB128 varints and text is length-prefixed
+/// UTF-8.
+pub fn encode(history: &editor_core::history::History, text: &str) -> Vec<u8> {
+    let mut out = MAGIC.to_vec();
+    let (len, hash) = fingerprint(text);
+
+    out.push(VERSION);
+    put_number(&mut out, len);
+    out.extend_from_slice(&hash.to_le_bytes());
+
+    for stack in [&history.undo_stack, &history.redo_stack] {
+        put_number(&mut out, stack.len() as u64);
+        for transaction in stack {
+            put_transaction(&mut out, transaction);
+        }
+    }
+
+    out
+}
+
+/// Reads a log written by [`encode`], returning the history and the fingerprint of
+/// the text it belongs to.
+pub fn decode(bytes: &[u8]) -> std::io::Result<(editor_core::history::History, (u64, u64))> {
+    let rest = bytes
+        .strip_prefix(MAGIC)
+        .ok_or_else(|| invalid("Not a MyNotes history log"))?;
+    let mut reader = Reader {
+        bytes: rest,
+        what: "History log",
+    };
+
+    match reader.byte()? {
+        VERSION => {}
+        version => {
+            return Err(invalid(&format!(
+                "History log version {version} is newer than this app"
+            )));
+        }
+    }
+
+    let len = reader.number()?;
+    let mut hash = [0; 8];
+
+    for byte in &mut hash {
+        *byte = reader.byte()?;
+    }
+
+    let history = editor_core::history::History {
+        undo_stack: reader.stack()?,
+        redo_stack: reader.stack()?,
+    };
+
+    if !reader.bytes.is_empty() {
+        return Err(invalid("History log has trailing data"));
+    }
+
+    Ok((history, (len, u64::from_le_bytes(hash))))
+}
+
+/// The undo and redo history of `doc` as a log.
+pub fn export(doc: &crate::document::Document) -> Vec<u8> {
+    encode(&doc.history, &doc.text_buffer.to_string())
+}
+
+/// Replaces the history of `doc` with the one in `bytes`. Refused unless the log was
+/// exported from the very same text, since its positions mean nothing elsewhere.
+pub fn import(doc: &mut crate::document::Document, bytes: &[u8]) -> std::io::Result<()> {
+    let (history, belongs_to) = decode(bytes)?;
+
+    if belongs_to != fingerprint(&doc.text_buffer.to_string()) {
+        return Err(invalid(
+            "History log belongs to a different version of the note",
+        ));
+    }
+
+    doc.history = history;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::Document;
+    use editor_core::cursor::Cursor;
+    use editor_core::text::TextBuffer;
+
+    #[test]
+    fn test_history_round_trips() {
+        let mut doc = Document::new(TextBuffer::new_with_text("one\ntwo").unwrap());
+
+        doc.cursor = Cursor::new(0, 3);
+        doc.insert(" héllo");
+        doc.insert_newline();
+        doc.insert("\tthree");
+        doc.cursor = Cursor::new_selection(
+            editor_core::cursor::Position::new(0, 0),
+            editor_core::cursor::Position::new(0, 3),
+        );
+        doc.delete(true);
+        doc.undo();
+
+        let log = export(&doc);
+        let (history, _) = decode(&log).unwrap();
+
+        assert_eq!(history.undo_stack, doc.history.undo_stack);
+        assert_eq!(history.redo_stack, doc.history.redo_stack);
+        assert_eq!(encode(&history, &doc.text_buffer.to_string()), log);
+
+        // A fresh document with the same text can pick up where this one left off.
+        let text = doc.text_buffer.to_string();
+        let mut reopened = Document::new(TextBuffer::new_with_text(&text).unwrap());
+
+        import(&mut reopened, &log).unwrap();
+        reopened.redo();
+        doc.redo();
+        assert_eq!(
+            reopened.text_buffer.to_string(),
+            doc.text_buffer.to_string()
+        );
+        while reopened.history.undo_stack.last().is_some() {
+            reopened.undo();
+        }
+        assert_eq!(reopened.text_buffer.to_string(), "one\ntwo");
+    }
+
+    #[test]
+    fn test_rejects_foreign_and_damaged_logs() {
+        let mut doc = Document::new(TextBuffer::new_with_text("text").unwrap());
+        doc.insert("more ");
+        let log = export(&doc);
+
+        let mut other = Document::new(TextBuffer::new_with_text("text").unwrap());
+        assert!(import(&mut other, &log).is_err());
+        assert!(decode(&log[..log.len() - 1]).is_err());
+        assert!(decode(b"MNHL\x09").is_err());
+        assert!(decode(b"nope").is_err());
+
+        let mut newer = log.clone();
+        newer[4] = VERSION + 1;
+        assert!(decode(&newer).is_err());
+    }
+}
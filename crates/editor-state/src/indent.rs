@@ -0,0 +1,316 @@
+/// Returns the leading run of spaces and tabs of `line`.
+#[inline]
+pub fn leading_whitespace(line: &str) -> &str {
+    let end = line
+        .find(|c: char| c != ' ' && c != '\t')
+        .unwrap_or(line.len());
+
+    &line[..end]
+}
+
+/// Finds the longest whitespace prefix shared by every non-blank line of `text`.
+/// Blank lines are ignored so an empty separator line doesn't zero the result.
+pub fn common_indent(text: &str) -> &str {
+    let mut common: Option<&str> = None;
+
+    for line in text.split('\n') {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let indent = leading_whitespace(line);
+
+        common = Some(match common {
+            None => indent,
+            Some(prev) => {
+                let shared = prev
+                    .bytes()
+                    .zip(indent.bytes())
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                &prev[..shared]
+            }
+        });
+    }
+
+    common.unwrap_or("")
+}
+
+/// Removes the common leading indentation from every line of `text`.
+pub fn dedent(text: &str) -> String {
+    let common = common_indent(text).len();
+
+    if common == 0 {
+        return text.to_string();
+    }
+
+    text.split('\n')
+        .map(|line| line.get(common..).unwrap_or_else(|| line.trim_start()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Re-indents multi-line `text` so it nests under a line indented by `indent`.
+///
+/// The common indentation is stripped first. The first line is left bare since it
+/// lands at the cursor, which already sits after the target line's indentation.
+/// Blank lines are not indented so pasting never introduces trailing whitespace.
+pub fn match_indentation(text: &str, indent: &str) -> String {
+    let dedented = dedent(text);
+
+    if !dedented.contains('\n') {
+        return dedented.trim_start_matches([' ', '\t']).to_string();
+    }
+
+    let mut out = String::with_capacity(dedented.len() + indent.len() * 4);
+
+    for (i, line) in dedented.split('\n').enumerate() {
+        if i == 0 {
+            out.push_str(line.trim_start_matches([' ', '\t']));
+            continue;
+        }
+
+        out.push('\n');
+
+        if !line.trim().is_empty() {
+            out.push_str(indent);
+        }
+        out.push_str(line);
+    }
+
+    out
+}
+
+/// Visual width in columns of a run of leading whitespace.
+pub fn indent_columns(whitespace: &str, tab_width: usize) -> usize {
+    let tab_width = tab_width.max(1);
+
+    whitespace.chars().fold(0, |col, c| match c {
+        '\t' => (col / tab_width + 1) * tab_width,
+        _ => col + 1,
+    })
+}
+
+/// Builds leading whitespace spanning `columns` in the given style.
+/// With tabs, any remainder that doesn't fill a whole tab stop is padded with spaces.
+pub fn build_indent(
+    columns: usize,
+    style: crate::settings::IndentStyle,
+    tab_width: usize,
+) -> String {
+    match style {
+        crate::settings::IndentStyle::Spaces => " ".repeat(columns),
+        crate::settings::IndentStyle::Tabs => {
+            let tab_width = tab_width.max(1);
+            let mut out = "\t".repeat(columns / tab_width);
+            out.push_str(&" ".repeat(columns % tab_width));
+            out
+        }
+    }
+}
+
+/// Width of a Markdown list marker plus the spaces after it, i.e. the column the
+/// item's content starts at relative to the marker. `None` if `text` isn't a list item.
+pub fn list_marker_width(text: &str) -> Option<usize> {
+    let digits = text.bytes().take_while(u8::is_ascii_digit).count();
+    let marker = match text.as_bytes().get(digits) {
+        Some(b'-' | b'*' | b'+') if digits == 0 => 1,
+        Some(b'.' | b')') if (1..=9).contains(&digits) => digits + 1,
+        _ => return None,
+    };
+    let spaces = text[marker..].bytes().take_while(|&b| b == b' ').count();
+
+    (spaces > 0 || marker == text.len()).then_some(marker + spaces.max(1))
+}
+
+/// Computes normalized leading whitespace for `lines`, returning `(row, new_whitespace)`
+/// for every row in `rows` whose indentation changes.
+///
+/// Nesting is inferred from the existing indentation: a line indented deeper than the
+/// line above it is one level further in. Children of a Markdown list item are indented
+/// at least as far as the item's content, so nested lists stay nested. Fenced code is
+/// shifted along with its fence but otherwise left untouched.
+pub fn reindent(
+    lines: &[String],
+    rows: std::ops::Range<usize>,
+    style: crate::settings::IndentStyle,
+    indent_size: usize,
+    tab_width: usize,
+) -> Vec<(usize, String)> {
+    // (old columns, new columns, step for children) of each enclosing line.
+    let mut stack: Vec<(usize, usize, usize)> = Vec::new();
+    let mut fence: Option<(&str, isize)> = None;
+    let mut changes = Vec::new();
+
+    for (row, line) in lines.iter().enumerate().take(rows.end) {
+        let whitespace = leading_whitespace(line);
+        let content = &line[whitespace.len()..];
+        let old = indent_columns(whitespace, tab_width);
+
+        let new = if let Some((marker, shift)) = fence {
+            if content.starts_with(marker) && content.trim_end() == marker {
+                fence = None;
+            }
+
+            if content.is_empty() {
+                0
+            } else {
+                old.saturating_add_signed(shift)
+            }
+        } else if content.is_empty() {
+            0
+        } else {
+            while stack.last().is_some_and(|&(col, _, _)| col > old) {
+                stack.pop();
+            }
+
+            let new = match stack.last() {
+                Some(&(col, new, _)) if col == old => {
+                    stack.pop();
+                    new
+                }
+                Some(&(_, new, step)) => new + step,
+                None if old > 0 => indent_size,
+                None => 0,
+            };
+            let step = list_marker_width(content).map_or(indent_size, |w| w.max(indent_size));
+
+            stack.push((old, new, step));
+
+            if content.starts_with("```") || content.starts_with("~~~") {
+                let marker = &content[..3];
+                let shift = new as isize - old as isize;
+                fence = Some((marker, shift));
+            }
+
+            new
+        };
+
+        if row < rows.start {
+            continue;
+        }
+
+        let replacement = build_indent(new, style, tab_width);
+
+        if replacement != whitespace {
+            changes.push((row, replacement));
+        }
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leading_whitespace() {
+        assert_eq!(leading_whitespace("\t  foo  "), "\t  ");
+        assert_eq!(leading_whitespace("foo"), "");
+        assert_eq!(leading_whitespace("   "), "   ");
+    }
+
+    #[test]
+    fn test_common_indent_ignores_blank_lines() {
+        assert_eq!(common_indent("    a\n\n      b\n    c"), "    ");
+        assert_eq!(common_indent("  a\n\tb"), "");
+        assert_eq!(common_indent(""), "");
+    }
+
+    #[test]
+    fn test_dedent() {
+        assert_eq!(dedent("    a\n      b\n\n    c\n"), "a\n  b\n\nc\n");
+        assert_eq!(dedent("a\n  b"), "a\n  b");
+    }
+
+    #[test]
+    fn test_match_indentation() {
+        let pasted = "        if x {\n            y();\n        }\n";
+
+        assert_eq!(
+            match_indentation(pasted, "\t"),
+            "if x {\n\t    y();\n\t}\n",
+            "Nested lines keep their relative indentation under the target indent"
+        );
+    }
+
+    #[test]
+    fn test_match_indentation_single_line() {
+        assert_eq!(match_indentation("    foo", "  "), "foo");
+    }
+
+    #[test]
+    fn test_match_indentation_crlf() {
+        assert_eq!(match_indentation("  a\r\n    b", "\t"), "a\r\n\t  b");
+    }
+
+    #[test]
+    fn test_indent_columns_and_build_indent() {
+        use crate::settings::IndentStyle;
+
+        assert_eq!(indent_columns("\t  ", 4), 6);
+        assert_eq!(indent_columns("  \t", 4), 4);
+        assert_eq!(build_indent(6, IndentStyle::Tabs, 4), "\t  ");
+        assert_eq!(build_indent(6, IndentStyle::Spaces, 4), "      ");
+    }
+
+    fn reindent_all(text: &str, style: crate::settings::IndentStyle, size: usize) -> String {
+        let mut lines: Vec<String> = text.split('\n').map(String::from).collect();
+
+        for (row, whitespace) in reindent(&lines, 0..lines.len(), style, size, 4) {
+            let old = leading_whitespace(&lines[row]).len();
+            lines[row].replace_range(..old, &whitespace);
+        }
+
+        lines.join("\n")
+    }
+
+    #[test]
+    fn test_list_marker_width() {
+        assert_eq!(list_marker_width("- item"), Some(2));
+        assert_eq!(list_marker_width("12. item"), Some(4));
+        assert_eq!(list_marker_width("1)  item"), Some(4));
+        assert_eq!(list_marker_width("-item"), None);
+        assert_eq!(list_marker_width("plain"), None);
+    }
+
+    #[test]
+    fn test_reindent_normalizes_levels() {
+        use crate::settings::IndentStyle;
+
+        assert_eq!(
+            reindent_all("a\n   b\n      c\n   d\n  \ne", IndentStyle::Spaces, 4),
+            "a\n    b\n        c\n    d\n\ne"
+        );
+        assert_eq!(
+            reindent_all("a\n  b\n    c", IndentStyle::Tabs, 4),
+            "a\n\tb\n\t\tc"
+        );
+    }
+
+    #[test]
+    fn test_reindent_keeps_markdown_lists_nested() {
+        use crate::settings::IndentStyle;
+
+        assert_eq!(
+            reindent_all("10. a\n  - b\n     - c\n11. d", IndentStyle::Spaces, 2),
+            "10. a\n    - b\n      - c\n11. d",
+            "Children of `10. ` must start at the item's content column"
+        );
+    }
+
+    #[test]
+    fn test_reindent_shifts_fenced_code_verbatim() {
+        use crate::settings::IndentStyle;
+
+        assert_eq!(
+            reindent_all(
+                "- a\n   ```\n   x\n       y\n   ```",
+                IndentStyle::Spaces,
+                2
+            ),
+            "- a\n  ```\n  x\n      y\n  ```"
+        );
+    }
+}
@@ -0,0 +1,335 @@
+/// A quick action another process asks the running app to take, e.g. a tray menu
+/// entry or a hotkey launching `mynotes --today`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Show the window if it's hidden, hide it otherwise.
+    ToggleWindow,
+    NewNote,
+    /// Open today's note, see [`crate::capture::daily_note`].
+    TodaysNote,
+    QuickCapture,
+}
+
+impl Action {
+    pub const ALL: [Action; 4] = [
+        Action::ToggleWindow,
+        Action::NewNote,
+        Action::TodaysNote,
+        Action::QuickCapture,
+    ];
+
+    /// The command-line flag that asks for this action.
+    pub fn flag(self) -> &'static str {
+        match self {
+            Action::ToggleWindow => "--toggle",
+            Action::NewNote => "--new",
+            Action::TodaysNote => "--today",
+            Action::QuickCapture => "--capture",
+        }
+    }
+
+    pub fn from_flag(flag: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|action| action.flag() == flag.trim())
+    }
+}
+
+/// The URL scheme deep links into notes use, see [`DeepLink`].
+pub const SCHEME: &str = "mynotes";
+
+/// A `mynotes://note/<slug>#<heading>` link another app (a calendar, a task
+/// manager) can hold on to to bring up a note, optionally at one of its headings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeepLink {
+    /// The note's name, or its slug, see [`crate::toc::slug`].
+    pub note: String,
+    /// The heading's title or anchor.
+    pub heading: Option<String>,
+}
+
+impl DeepLink {
+    pub fn parse(url: &str) -> Option<Self> {
+        let url = url.trim();
+        let (scheme, rest) = url.split_once("://")?;
+
+        if !scheme.eq_ignore_ascii_case(SCHEME) {
+            return None;
+        }
+
+        let rest = rest.strip_prefix("note/")?.trim_end_matches('/');
+        let (note, heading) = match rest.split_once('#') {
+            Some((note, heading)) => (note, Some(heading)),
+            None => (rest, None),
+        };
+        let note = crate::workspace::percent_decode(note.trim_end_matches('/'));
+
+        if note.is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            note,
+            heading: heading
+                .map(crate::workspace::percent_decode)
+                .filter(|heading| !heading.is_empty()),
+        })
+    }
+
+    pub fn url(&self) -> String {
+        let mut url = format!(
+            "{SCHEME}://note/{}",
+            crate::workspace::percent_encode(&self.note)
+        );
+
+        if let Some(heading) = &self.heading {
+            url.push('#');
+            url.push_str(&crate::workspace::percent_encode(heading));
+        }
+        url
+    }
+
+    /// The note under `root` the link names: one called exactly that, ignoring
+    /// case, or else the first whose name has that slug.
+    pub fn resolve(&self, root: &std::path::Path) -> Option<std::path::PathBuf> {
+        crate::workspace::find_note(root, &self.note).or_else(|| {
+            let slug = crate::toc::slug(&self.note);
+
+            crate::workspace::notes(root).into_iter().find(|path| {
+                crate::workspace::note_name(path).is_some_and(|name| crate::toc::slug(name) == slug)
+            })
+        })
+    }
+}
+
+/// What another process hands the running app.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Request {
+    Action(Action),
+    Open(DeepLink),
+}
+
+impl Request {
+    /// The request a command-line argument stands for: an action's flag or a
+    /// deep link, as the desktop passes it to the registered handler.
+    pub fn from_arg(arg: &str) -> Option<Self> {
+        Action::from_flag(arg)
+            .map(Request::Action)
+            .or_else(|| DeepLink::parse(arg).map(Request::Open))
+    }
+
+    /// The line [`send`] writes for it; [`Request::from_arg`] reads it back.
+    fn line(&self) -> String {
+        match self {
+            Request::Action(action) => format!("{}\n", action.flag()),
+            Request::Open(link) => format!("{}\n", link.url()),
+        }
+    }
+}
+
+/// `instance` next to the session store: the port of the running app, while it
+/// listens for actions.
+pub fn config_path() -> Option<std::path::PathBuf> {
+    Some(crate::session::config_dir()?.join("instance"))
+}
+
+/// `resident` next to the session store, present while the app should keep running
+/// with its window closed.
+fn resident_path() -> Option<std::path::PathBuf> {
+    Some(crate::session::config_dir()?.join("resident"))
+}
+
+/// Whether closing the window should leave the app running in the background.
+pub fn stays_resident() -> bool {
+    resident_path().is_some_and(|path| path.exists())
+}
+
+pub fn set_stays_resident(on: bool) -> std::io::Result<()> {
+    let path = resident_path()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "No config directory"))?;
+
+    if on {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, "")
+    } else {
+        match std::fs::remove_file(path) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Accepts [`Request`]s from other processes on a loopback port, written to `path`
+/// so [`send`] can find it. Nothing blocks: call [`Listener::poll`] from a timer.
+pub struct Listener {
+    listener: std::net::TcpListener,
+    path: std::path::PathBuf,
+    /// Connections whose request hasn't fully arrived yet: what came so far, and
+    /// when they were accepted.
+    reading: Vec<(std::net::TcpStream, Vec<u8>, std::time::Instant)>,
+}
+
+impl Listener {
+    pub fn bind(path: &std::path::Path) -> std::io::Result<Self> {
+        let listener = std::net::TcpListener::bind((std::net::Ipv4Addr::LOCALHOST, 0))?;
+
+        listener.set_nonblocking(true)?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, format!("{}\n", listener.local_addr()?.port()))?;
+
+        Ok(Self {
+            listener,
+            path: path.to_path_buf(),
+            reading: Vec::new(),
+        })
+    }
+
+    /// Longest request line taken; links to notes are far shorter.
+    const MAX_LINE: usize = 4096;
+    /// How long a connection may take to send its request before it's dropped.
+    const PATIENCE: std::time::Duration = std::time::Duration::from_secs(2);
+
+    /// The requests that fully arrived since the last poll, oldest first. Only reads
+    /// what's already there, so a slow or silent sender never holds up the caller.
+    pub fn poll(&mut self) -> Vec<Request> {
+        while let Ok((stream, _)) = self.listener.accept() {
+            if stream.set_nonblocking(true).is_ok() {
+                self.reading
+                    .push((stream, Vec::new(), std::time::Instant::now()));
+            }
+        }
+
+        let mut requests = Vec::new();
+
+        self.reading.retain_mut(|(stream, line, accepted)| {
+            let mut chunk = [0; 512];
+            let closed = loop {
+                match std::io::Read::read(stream, &mut chunk) {
+                    Ok(0) => break true,
+                    Ok(read) => line.extend_from_slice(&chunk[..read]),
+                    Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => break false,
+                    Err(_) => break true,
+                }
+                if line.contains(&b'\n') || line.len() > Self::MAX_LINE {
+                    break true;
+                }
+            };
+
+            if !closed {
+                return accepted.elapsed() < Self::PATIENCE;
+            }
+            let request = line
+                .iter()
+                .position(|&b| b == b'\n')
+                .filter(|&end| end <= Self::MAX_LINE)
+                .and_then(|end| std::str::from_utf8(&line[..end]).ok())
+                .and_then(Request::from_arg);
+
+            requests.extend(request);
+            false
+        });
+
+        requests
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        // Only if another instance hasn't taken over the file since.
+        let ours = self.listener.local_addr().ok().map(|addr| addr.port());
+        let written = std::fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|content| content.trim().parse().ok());
+
+        if ours.is_some() && ours == written {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Hands `request` to the app listening through `path`. Fails quickly if there
+/// is none, so the caller can carry it out itself.
+pub fn send(path: &std::path::Path, request: &Request) -> std::io::Result<()> {
+    let port: u16 = std::fs::read_to_string(path)?
+        .trim()
+        .parse()
+        .map_err(|_| std::io::Error::new(std::io::ErrorKind::InvalidData, "Bad instance port"))?;
+    let mut stream = std::net::TcpStream::connect_timeout(
+        &(std::net::Ipv4Addr::LOCALHOST, port).into(),
+        std::time::Duration::from_millis(200),
+    )?;
+
+    std::io::Write::write_all(&mut stream, request.line().as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_send_reaches_the_listener() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("instance");
+
+        assert!(send(&path, &Request::Action(Action::NewNote)).is_err());
+
+        let mut listener = Listener::bind(&path).unwrap();
+        let link = DeepLink::parse("mynotes://note/project-ideas#sync%20%26%20backup").unwrap();
+
+        assert_eq!(link.note, "project-ideas");
+        assert_eq!(link.heading.as_deref(), Some("sync & backup"));
+        assert_eq!(
+            link.url(),
+            "mynotes://note/project-ideas#sync%20%26%20backup"
+        );
+        assert_eq!(DeepLink::parse("mynotes://note/"), None);
+        assert_eq!(DeepLink::parse("https://note/project-ideas"), None);
+
+        // A sender that never finishes its line holds nothing up.
+        let port: u16 = std::fs::read_to_string(&path)
+            .unwrap()
+            .trim()
+            .parse()
+            .unwrap();
+        let mut silent =
+            std::net::TcpStream::connect((std::net::Ipv4Addr::LOCALHOST, port)).unwrap();
+        std::io::Write::write_all(&mut silent, b"--to").unwrap();
+
+        send(&path, &Request::Action(Action::TodaysNote)).unwrap();
+        send(&path, &Request::Open(link.clone())).unwrap();
+        send(&path, &Request::Action(Action::QuickCapture)).unwrap();
+
+        let mut requests = Vec::new();
+        for _ in 0..50 {
+            requests.extend(listener.poll());
+            if requests.len() == 3 {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        assert_eq!(
+            requests,
+            vec![
+                Request::Action(Action::TodaysNote),
+                Request::Open(link),
+                Request::Action(Action::QuickCapture)
+            ]
+        );
+
+        std::fs::write(dir.path().join("Project Ideas.md"), "").unwrap();
+        assert_eq!(
+            DeepLink::parse("mynotes://note/project-ideas")
+                .unwrap()
+                .resolve(dir.path()),
+            Some(dir.path().join("Project Ideas.md"))
+        );
+
+        drop(listener);
+        assert!(!path.exists());
+    }
+}
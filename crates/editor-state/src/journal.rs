@@ -0,0 +1,392 @@
+/// Rows `start..=old_end` replaced by `start..=new_end`, as one edit left them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineEdit {
+    pub start: usize,
+    pub old_end: usize,
+    pub new_end: usize,
+}
+
+impl LineEdit {
+    /// Where `row` is after the edit, or `None` if the edit replaced it.
+    pub fn shift(&self, row: usize) -> Option<usize> {
+        if row < self.start {
+            Some(row)
+        } else if row > self.old_end {
+            Some((row as isize + self.new_end as isize - self.old_end as isize) as usize)
+        } else {
+            None
+        }
+    }
+}
+
+/// The latest edits of a document by the revision each made, so views can bring
+/// what they derive from its lines up to date instead of deriving it all again.
+#[derive(Debug, Clone, Default)]
+pub struct LineJournal {
+    /// Oldest first.
+    edits: std::collections::VecDeque<(u64, LineEdit)>,
+    /// The earliest revision the kept edits lead on from.
+    floor: u64,
+}
+
+impl LineJournal {
+    /// Edits kept; a view further behind than that starts over.
+    const CAPACITY: usize = 1024;
+
+    pub fn record(&mut self, revision: u64, edit: LineEdit) {
+        if self.edits.len() == Self::CAPACITY
+            && let Some((dropped, _)) = self.edits.pop_front()
+        {
+            self.floor = dropped;
+        }
+        self.edits.push_back((revision, edit));
+    }
+
+    /// Forgets every edit, the whole text changed at `revision`.
+    pub fn reset(&mut self, revision: u64) {
+        self.edits.clear();
+        self.floor = revision;
+    }
+
+    /// The edits that took the text from `revision` to now, oldest first, or `None`
+    /// if they go back further than what's kept.
+    pub fn since(&self, revision: u64) -> Option<Vec<LineEdit>> {
+        if revision < self.floor {
+            return None;
+        }
+
+        let from = self.edits.partition_point(|(made, _)| *made <= revision);
+
+        Some(self.edits.range(from..).map(|(_, edit)| *edit).collect())
+    }
+}
+
+/// The edits to catch up on for a view last brought up to date at `revision`, or
+/// `None` when it has to start over.
+fn pending(d: &crate::document::Document, revision: Option<u64>) -> Option<Vec<LineEdit>> {
+    d.journal.since(revision?)
+}
+
+/// The rows of a document that carry a `T`, such as an image link, kept in step
+/// with it through its [`LineJournal`], so that only edited lines are looked at again.
+#[derive(Debug, Clone)]
+pub struct RowIndex<T> {
+    /// The document revision the rows are up to date with.
+    revision: Option<u64>,
+    /// Sorted by row.
+    rows: Vec<(usize, T)>,
+}
+
+impl<T> Default for RowIndex<T> {
+    fn default() -> Self {
+        Self {
+            revision: None,
+            rows: Vec::new(),
+        }
+    }
+}
+
+impl<T> RowIndex<T> {
+    pub fn rows(&self) -> &[(usize, T)] {
+        &self.rows
+    }
+
+    pub fn get(&self, row: usize) -> Option<&T> {
+        let i = self.rows.binary_search_by_key(&row, |(at, _)| *at).ok()?;
+
+        Some(&self.rows[i].1)
+    }
+
+    /// Brings the rows up to date with `d`, asking `derive` about the lines edited
+    /// since the last time, or about every line the first time and whenever the
+    /// journal doesn't reach back that far.
+    pub fn update(&mut self, d: &crate::document::Document, derive: impl FnMut(&str) -> Option<T>) {
+        if self.revision != Some(d.revision) {
+            let edits = pending(d, self.revision);
+
+            self.apply(d, edits.as_deref(), derive);
+            self.revision = Some(d.revision);
+        }
+    }
+
+    /// Shifts the rows past `edit` and derives those it replaced from `lines`, the
+    /// text of rows `edit.start..=edit.new_end` after it, for an index kept up to
+    /// date on every edit rather than through [`RowIndex::update`]. Returns whether
+    /// a row was derived or dropped.
+    pub fn edit<S: AsRef<str>>(
+        &mut self,
+        edit: LineEdit,
+        lines: &[S],
+        derive: impl FnMut(&str) -> Option<T>,
+    ) -> bool {
+        self.shift(edit);
+        self.derive(edit.start, lines, derive)
+    }
+
+    /// The rows of `lines` `derive` finds something on.
+    pub fn from_lines<S: AsRef<str>>(lines: &[S], derive: impl FnMut(&str) -> Option<T>) -> Self {
+        let mut index = Self::default();
+
+        index.derive(0, lines, derive);
+        index
+    }
+
+    fn apply(
+        &mut self,
+        d: &crate::document::Document,
+        edits: Option<&[LineEdit]>,
+        derive: impl FnMut(&str) -> Option<T>,
+    ) {
+        let count = d.get_line_count();
+        let Some(edits) = edits else {
+            self.rows.clear();
+            self.derive(0, &d.get_lines_stripped(0..count), derive);
+            return;
+        };
+        // Rows edited since, where they are now.
+        let mut stale: Option<(usize, usize)> = None;
+
+        for &edit in edits {
+            self.shift(edit);
+            stale = Some(match stale {
+                Some((from, to)) => (
+                    from.min(edit.start),
+                    edit.shift(to)
+                        .map_or(edit.new_end, |to| to.max(edit.new_end)),
+                ),
+                None => (edit.start, edit.new_end),
+            });
+        }
+
+        if let Some((from, to)) = stale.filter(|(from, _)| *from < count) {
+            self.derive(
+                from,
+                &d.get_lines_stripped(from..(to + 1).min(count)),
+                derive,
+            );
+        }
+    }
+
+    /// Moves the rows past `edit` and drops those it replaced.
+    fn shift(&mut self, edit: LineEdit) {
+        self.rows.retain_mut(|(row, _)| match edit.shift(*row) {
+            Some(moved) => {
+                *row = moved;
+                true
+            }
+            None => false,
+        });
+    }
+
+    /// Replaces the rows `from..from + lines.len()` by what `derive` finds on `lines`.
+    fn derive<S: AsRef<str>>(
+        &mut self,
+        from: usize,
+        lines: &[S],
+        mut derive: impl FnMut(&str) -> Option<T>,
+    ) -> bool {
+        let at = self.rows.partition_point(|(row, _)| *row < from);
+        let end = self
+            .rows
+            .partition_point(|(row, _)| *row < from + lines.len());
+        let derived: Vec<(usize, T)> = lines
+            .iter()
+            .enumerate()
+            .filter_map(|(i, line)| Some((from + i, derive(line.as_ref())?)))
+            .collect();
+        let changed = at < end || !derived.is_empty();
+
+        self.rows.splice(at..end, derived);
+        changed
+    }
+}
+
+/// Something found on a run of rows, such as a fenced code block, see [`BlockIndex`].
+pub trait RowSpan {
+    /// First and last row.
+    fn rows(&self) -> (usize, usize);
+
+    /// Moves it to start on `row`, keeping its length.
+    fn move_to(&mut self, row: usize);
+}
+
+impl RowSpan for crate::math::MathBlock {
+    fn rows(&self) -> (usize, usize) {
+        (self.start, self.end)
+    }
+
+    fn move_to(&mut self, row: usize) {
+        self.end = row + self.end - self.start;
+        self.start = row;
+    }
+}
+
+impl RowSpan for crate::redact::KeyBlock {
+    fn rows(&self) -> (usize, usize) {
+        (self.start, self.end)
+    }
+
+    fn move_to(&mut self, row: usize) {
+        self.end = row + self.end - self.start;
+        self.start = row;
+    }
+}
+
+impl RowSpan for crate::diagrams::Diagram {
+    fn rows(&self) -> (usize, usize) {
+        (self.start, self.end)
+    }
+
+    fn move_to(&mut self, row: usize) {
+        self.end = row + self.end - self.start;
+        self.start = row;
+    }
+}
+
+/// Blocks a scanner finds in a document, kept in step with it through its
+/// [`LineJournal`]. Blocks open and close on marker lines, and lines between
+/// markers never change how they pair up, so an edit only has the markers paired
+/// again and the blocks it touched scanned again; the others just move.
+#[derive(Debug, Clone)]
+pub struct BlockIndex<B> {
+    revision: Option<u64>,
+    /// The text of every marker line.
+    markers: RowIndex<String>,
+    /// Sorted by row.
+    blocks: Vec<B>,
+}
+
+impl<B> Default for BlockIndex<B> {
+    fn default() -> Self {
+        Self {
+            revision: None,
+            markers: RowIndex::default(),
+            blocks: Vec::new(),
+        }
+    }
+}
+
+impl<B: RowSpan + Clone> BlockIndex<B> {
+    pub fn blocks(&self) -> &[B] {
+        &self.blocks
+    }
+
+    /// Brings the blocks up to date with `d`. `is_marker` tells which lines can
+    /// open or close a block, and `scan` finds the blocks in some lines, the way it
+    /// would in the whole document.
+    pub fn update(
+        &mut self,
+        d: &crate::document::Document,
+        is_marker: impl Fn(&str) -> bool,
+        scan: impl Fn(&[String]) -> Vec<B>,
+    ) {
+        if self.revision == Some(d.revision) {
+            return;
+        }
+
+        let edits = pending(d, self.revision);
+        let mut kept = Vec::new();
+
+        self.revision = Some(d.revision);
+        self.markers.apply(d, edits.as_deref(), |line| {
+            is_marker(line).then(|| line.to_string())
+        });
+        if let Some(edits) = &edits {
+            kept = std::mem::take(&mut self.blocks);
+            for edit in edits {
+                kept.retain_mut(|block| {
+                    let (start, end) = block.rows();
+
+                    if end < edit.start {
+                        true
+                    } else if let Some(moved) = edit.shift(start).filter(|_| start > edit.old_end) {
+                        block.move_to(moved);
+                        true
+                    } else {
+                        false
+                    }
+                });
+            }
+        }
+
+        let markers: Vec<String> = self
+            .markers
+            .rows()
+            .iter()
+            .map(|(_, line)| line.clone())
+            .collect();
+        let mut kept = kept.into_iter().peekable();
+
+        self.blocks = scan(&markers)
+            .iter()
+            .filter_map(|paired| {
+                let (first, last) = paired.rows();
+                let (start, end) = (self.markers.rows[first].0, self.markers.rows[last].0);
+
+                while kept.next_if(|block| block.rows().0 < start).is_some() {}
+                if let Some(block) = kept.next_if(|block| block.rows() == (start, end)) {
+                    return Some(block);
+                }
+
+                let mut block = scan(&d.get_lines_stripped(start..end + 1))
+                    .into_iter()
+                    .find(|block| block.rows() == (0, end - start))?;
+
+                block.move_to(start);
+                Some(block)
+            })
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::{Document, TextEdit};
+    use editor_core::cursor::Position;
+    use editor_core::text::TextBuffer;
+
+    #[test]
+    fn test_indexes_follow_edits() {
+        let text = "![a](a.png)\n```mermaid\ngraph\n```\ntext\n![b](b.png)\n```mermaid\nflow\n```\n$$\nx^2\n$$";
+        let mut doc = Document::new(TextBuffer::new_with_text(text).unwrap());
+        let mut images = RowIndex::default();
+        let mut fences = BlockIndex::default();
+        let mut math = BlockIndex::default();
+        let mut check = |doc: &Document| {
+            let lines = doc.get_lines_stripped(0..doc.get_line_count());
+            let expected: Vec<(usize, String)> = lines
+                .iter()
+                .enumerate()
+                .filter_map(|(row, line)| {
+                    Some((row, crate::workspace::image_link_target(line)?.to_string()))
+                })
+                .collect();
+
+            images.update(doc, |line| {
+                crate::workspace::image_link_target(line).map(str::to_string)
+            });
+            fences.update(doc, crate::diagrams::is_fence, crate::diagrams::fences);
+            assert_eq!(images.rows(), expected);
+            math.update(doc, crate::math::is_marker, crate::math::blocks);
+            assert_eq!(fences.blocks(), crate::diagrams::fences(&lines));
+            assert_eq!(math.blocks(), crate::math::blocks(&lines));
+        };
+
+        check(&doc);
+        let edits = [
+            (Position::new(2, 5), Position::new(2, 5), "\nTD"),
+            (Position::new(0, 0), Position::new(0, 0), "new\n"),
+            (Position::new(5, 0), Position::new(7, 0), ""),
+            (Position::new(2, 0), Position::new(2, 0), "![c](c.png)\n"),
+            (Position::new(1, 3), Position::new(1, 3), "\n```"),
+        ];
+
+        for (start, end, text) in edits {
+            doc.apply_edits(vec![TextEdit::new(start, end, text.to_string())]);
+            check(&doc);
+        }
+        assert_eq!(fences.blocks().len(), 1);
+        assert_eq!(images.get(3).map(String::as_str), Some("c.png"));
+    }
+}
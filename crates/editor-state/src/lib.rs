@@ -1 +1,59 @@
+pub mod bookmarks;
+pub mod calc;
+pub mod capture;
+pub mod caret;
+pub mod changes;
+pub mod clipboard;
+pub mod collab;
+pub mod comment;
+pub mod config;
+pub mod conflict;
+pub mod csv;
+pub mod diagnostics;
+pub mod diagrams;
+pub mod diff;
+pub mod docking;
 pub mod document;
+pub mod editorconfig;
+pub mod export;
+pub mod filetype;
+pub mod folds;
+pub mod footnotes;
+pub mod format;
+pub mod fuzzy;
+pub mod highlight;
+pub mod history_log;
+pub mod indent;
+pub mod instance;
+pub mod journal;
+pub mod line_times;
+pub mod lists;
+pub mod lock;
+pub mod lookup;
+pub mod math;
+pub mod merge;
+pub mod modeline;
+pub mod opml;
+pub mod outline;
+pub mod pair;
+pub mod query;
+pub mod recovery;
+pub mod redact;
+pub mod reminders;
+pub mod replace;
+pub mod run;
+pub mod save_queue;
+pub mod save_transforms;
+pub mod search;
+pub mod session;
+pub mod settings;
+pub mod similar;
+pub mod stats;
+pub mod symbols;
+pub mod sync;
+pub mod theme;
+pub mod toc;
+pub mod tools;
+pub mod transform;
+pub mod typography;
+pub mod workspace;
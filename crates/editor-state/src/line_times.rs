@@ -0,0 +1,142 @@
+/// When each line of a note was last edited, kept in step with edits line range by
+/// line range and stored in a hidden sidecar next to the note.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LineTimes {
+    /// One entry per line of the document; `None` for lines not edited since
+    /// recording started.
+    times: Vec<Option<std::time::SystemTime>>,
+}
+
+impl LineTimes {
+    /// No times known yet for a document of `line_count` lines.
+    pub fn new(line_count: usize) -> Self {
+        Self {
+            times: vec![None; line_count],
+        }
+    }
+
+    /// `.<file name>.times` in the note's folder.
+    pub fn sidecar_path(note: &std::path::Path) -> Option<std::path::PathBuf> {
+        let name = note.file_name()?.to_string_lossy();
+
+        Some(note.with_file_name(format!(".{name}.times")))
+    }
+
+    /// Reads a sidecar written by [`LineTimes::save`], fitted to `line_count` lines in
+    /// case the note was edited elsewhere. A missing or unreadable sidecar means no
+    /// times are known.
+    pub fn load(path: &std::path::Path, line_count: usize) -> Self {
+        let mut times: Vec<Option<std::time::SystemTime>> = std::fs::read_to_string(path)
+            .unwrap_or_default()
+            .lines()
+            .map(|line| {
+                let secs = line.trim().parse::<u64>().ok()?;
+                Some(std::time::UNIX_EPOCH + std::time::Duration::from_secs(secs))
+            })
+            .collect();
+
+        times.resize(line_count, None);
+        Self { times }
+    }
+
+    /// One line per line of the note: seconds since the Unix epoch, or `-`.
+    pub fn save(&self, path: &std::path::Path) -> std::io::Result<()> {
+        let mut out = String::new();
+
+        for time in &self.times {
+            match time.and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok()) {
+                Some(since) => out.push_str(&since.as_secs().to_string()),
+                None => out.push('-'),
+            }
+            out.push('\n');
+        }
+
+        std::fs::write(path, out)
+    }
+
+    /// Rows `start..=old_end` were replaced by `start..=new_end` at `now`.
+    pub fn update(
+        &mut self,
+        start: usize,
+        old_end: usize,
+        new_end: usize,
+        now: std::time::SystemTime,
+    ) {
+        let old_end = (old_end + 1).min(self.times.len());
+        let start = start.min(old_end);
+        let count = new_end.saturating_sub(start) + 1;
+
+        self.times
+            .splice(start..old_end, std::iter::repeat_n(Some(now), count));
+    }
+
+    #[inline]
+    pub fn get(&self, row: usize) -> Option<std::time::SystemTime> {
+        self.times.get(row).copied().flatten()
+    }
+}
+
+/// How long before `now` `time` was, in words, e.g. `3 hours ago`.
+pub fn ago(time: std::time::SystemTime, now: std::time::SystemTime) -> String {
+    let secs = now.duration_since(time).unwrap_or_default().as_secs();
+    let (n, unit) = match secs {
+        0..60 => return "just now".to_string(),
+        60..3_600 => (secs / 60, "minute"),
+        3_600..86_400 => (secs / 3_600, "hour"),
+        86_400..2_592_000 => (secs / 86_400, "day"),
+        2_592_000..31_536_000 => (secs / 2_592_000, "month"),
+        _ => (secs / 31_536_000, "year"),
+    };
+    let plural = if n == 1 { "" } else { "s" };
+
+    format!("{n} {unit}{plural} ago")
+}
+
+/// What the gutter shows for a line edited at `time`.
+pub fn describe(time: std::time::SystemTime, now: std::time::SystemTime) -> String {
+    format!(
+        "Edited {} ({})",
+        ago(time, now),
+        httpdate::fmt_http_date(time)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_times_follow_edits_and_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let note = dir.path().join("meeting.md");
+        let sidecar = LineTimes::sidecar_path(&note).unwrap();
+        let monday = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        let tuesday = monday + std::time::Duration::from_secs(86_400);
+        let mut times = LineTimes::new(3);
+
+        times.update(1, 1, 1, monday);
+        // A new line after row 1, a day later.
+        times.update(1, 1, 2, tuesday);
+
+        assert_eq!(sidecar, dir.path().join(".meeting.md.times"));
+        assert_eq!(
+            (0..4).map(|row| times.get(row)).collect::<Vec<_>>(),
+            vec![None, Some(tuesday), Some(tuesday), None]
+        );
+
+        times.save(&sidecar).unwrap();
+        assert_eq!(LineTimes::load(&sidecar, 4), times);
+        // Fitted to a note that lost a line elsewhere.
+        assert_eq!(LineTimes::load(&sidecar, 3).get(2), Some(tuesday));
+        assert_eq!(
+            LineTimes::load(&dir.path().join("missing"), 2),
+            LineTimes::new(2)
+        );
+
+        assert_eq!(ago(monday, tuesday), "1 day ago");
+        assert_eq!(
+            describe(monday, monday + std::time::Duration::from_secs(7_200)),
+            "Edited 2 hours ago (Tue, 14 Nov 2023 22:13:20 GMT)"
+        );
+    }
+}
@@ -0,0 +1,341 @@
+/// An ordered list item such as `  3. Buy milk` or `1) First`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OrderedItem {
+    /// Bytes of indentation before the number.
+    pub indent: usize,
+    pub number: u64,
+    /// `.` or `)`.
+    pub delimiter: char,
+    /// Byte offset right after the delimiter.
+    pub marker_end: usize,
+}
+
+/// The ordered list item `line` starts, if it's one. CommonMark allows up to nine
+/// digits and needs a space (or nothing) after the delimiter.
+pub fn ordered_item(line: &str) -> Option<OrderedItem> {
+    let indent = line.len() - line.trim_start_matches([' ', '\t']).len();
+    let rest = &line[indent..];
+    let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    let delimiter = rest[digits..].chars().next()?;
+    let after = &rest[digits + 1..];
+
+    if !(1..=9).contains(&digits)
+        || !matches!(delimiter, '.' | ')')
+        || !(after.is_empty() || after.starts_with([' ', '\t']))
+    {
+        return None;
+    }
+
+    Some(OrderedItem {
+        indent,
+        number: rest[..digits].parse().ok()?,
+        delimiter,
+        marker_end: indent + digits + 1,
+    })
+}
+
+fn with_number(line: &str, item: OrderedItem, number: u64) -> String {
+    format!(
+        "{}{number}{}{}",
+        &line[..item.indent],
+        item.delimiter,
+        &line[item.marker_end..]
+    )
+}
+
+/// The marker of the item to add after `line`, e.g. `  4. ` after `  3. Milk`.
+pub fn next_marker(line: &str) -> Option<String> {
+    let item = ordered_item(line)?;
+
+    Some(format!(
+        "{}{}{} ",
+        &line[..item.indent],
+        item.number + 1,
+        item.delimiter
+    ))
+}
+
+/// A bullet (`-`, `*`, `+`) or ordered list item, maybe a task with a checkbox.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ListItem {
+    /// Bytes of indentation before the marker.
+    pub indent: usize,
+    /// Byte offset right after the bullet, or the number's delimiter.
+    pub marker_end: usize,
+    /// Whether a `[ ]` or `[x]` checkbox follows the marker, and it's ticked.
+    pub checkbox: Option<bool>,
+    /// Byte offset of the item's text.
+    pub content_start: usize,
+}
+
+/// The list item `line` starts, if it's one.
+pub fn list_item(line: &str) -> Option<ListItem> {
+    let indent = line.len() - line.trim_start_matches([' ', '\t']).len();
+    let rest = &line[indent..];
+    let marker_end = match ordered_item(line) {
+        Some(item) => item.marker_end,
+        None if rest.starts_with(['-', '*', '+'])
+            && (rest.len() == 1 || rest[1..].starts_with([' ', '\t'])) =>
+        {
+            indent + 1
+        }
+        None => return None,
+    };
+    let spaced = line[marker_end..].trim_start_matches([' ', '\t']);
+    let checkbox = match spaced.get(..3) {
+        Some("[ ]") => Some(false),
+        Some("[x]" | "[X]") => Some(true),
+        _ => None,
+    }
+    .filter(|_| spaced.len() == 3 || spaced[3..].starts_with([' ', '\t']));
+    let text = match checkbox {
+        Some(_) => spaced[3..].trim_start_matches([' ', '\t']),
+        None => spaced,
+    };
+
+    Some(ListItem {
+        indent,
+        marker_end,
+        checkbox,
+        content_start: line.len() - text.len(),
+    })
+}
+
+/// What Enter does on a list item, see [`AutoList::on_enter`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Enter {
+    /// Starts the next item with this marker, e.g. `  - [ ] ` or `4. `.
+    Continue(String),
+    /// Clears the line of the item, which has no text, ending the list.
+    End,
+}
+
+/// Rules for Enter and Tab on list items, each of which can be turned off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AutoList {
+    /// Enter on an item starts the next one: the same bullet, or the next number.
+    pub continue_items: bool,
+    /// Enter on an item with no text removes its marker instead, ending the list.
+    pub end_empty_items: bool,
+    /// Tab on an item with no text yet indents it a level.
+    pub indent_with_tab: bool,
+    /// The item after a task starts with an unticked checkbox of its own.
+    pub carry_checkboxes: bool,
+}
+
+impl Default for AutoList {
+    fn default() -> Self {
+        Self {
+            continue_items: true,
+            end_empty_items: true,
+            indent_with_tab: true,
+            carry_checkboxes: true,
+        }
+    }
+}
+
+impl AutoList {
+    /// What Enter at byte `col` of `line` does under these rules, `None` for a
+    /// plain line break. Enter within the marker always breaks the line.
+    pub fn on_enter(&self, line: &str, col: usize) -> Option<Enter> {
+        let item = list_item(line)?;
+
+        if col < item.content_start {
+            return None;
+        }
+        if line[item.content_start..].trim().is_empty() {
+            return self.end_empty_items.then_some(Enter::End);
+        }
+        if !self.continue_items {
+            return None;
+        }
+
+        let marker = match next_marker(line) {
+            Some(marker) => marker,
+            None => format!("{} ", &line[..item.marker_end]),
+        };
+        let checkbox = if self.carry_checkboxes && item.checkbox.is_some() {
+            "[ ] "
+        } else {
+            ""
+        };
+
+        Some(Enter::Continue(format!("{marker}{checkbox}")))
+    }
+
+    /// Whether Tab at byte `col` of `line` indents the item there rather than
+    /// inserting indentation at `col`: the item has no text, the cursor is after
+    /// its marker.
+    pub fn indents_on_tab(&self, line: &str, col: usize) -> bool {
+        self.indent_with_tab
+            && list_item(line).is_some_and(|item| {
+                col >= item.content_start && line[item.content_start..].trim().is_empty()
+            })
+    }
+}
+
+/// `lines` with every ordered list counting up by one from its first item's
+/// number. Nested lists are numbered on their own; blank lines and lines indented
+/// under an item don't break a list, anything else at its indentation does.
+pub fn renumber(lines: &[String]) -> Vec<String> {
+    // Indentation of each open list and the number its next item gets.
+    let mut open: Vec<(usize, u64)> = Vec::new();
+
+    lines
+        .iter()
+        .map(|line| {
+            if line.trim().is_empty() {
+                return line.clone();
+            }
+
+            let Some(item) = ordered_item(line) else {
+                let indent = line.len() - line.trim_start_matches([' ', '\t']).len();
+
+                open.retain(|&(list, _)| list < indent);
+                return line.clone();
+            };
+
+            open.retain(|&(list, _)| list <= item.indent);
+            match open.last_mut() {
+                Some((list, next)) if *list == item.indent => {
+                    let number = *next;
+
+                    *next += 1;
+                    with_number(line, item, number)
+                }
+                _ => {
+                    open.push((item.indent, item.number + 1));
+                    line.clone()
+                }
+            }
+        })
+        .collect()
+}
+
+/// How many of `lines` belong to the list whose item `lines[0]` is, by the rules
+/// of [`renumber`]: up to the last line before one that ends it.
+pub fn list_len(lines: &[String]) -> usize {
+    let Some(indent) = lines
+        .first()
+        .and_then(|line| ordered_item(line))
+        .map(|item| item.indent)
+    else {
+        return 0;
+    };
+    let mut len = 1;
+
+    for (i, line) in lines.iter().enumerate().skip(1) {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let line_indent = line.len() - line.trim_start_matches([' ', '\t']).len();
+        let in_list = match ordered_item(line) {
+            Some(item) => item.indent >= indent,
+            None => line_indent > indent,
+        };
+
+        if !in_list {
+            break;
+        }
+        len = i + 1;
+    }
+
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_renumber() {
+        let lines = [
+            "1. One",
+            "1. Two",
+            "   continued",
+            "   1) Nested",
+            "   7) Nested too",
+            "",
+            "5. Three",
+            "- bullet",
+            "3. New list",
+            "9. Its second",
+        ]
+        .map(String::from);
+
+        assert_eq!(
+            renumber(&lines),
+            [
+                "1. One",
+                "2. Two",
+                "   continued",
+                "   1) Nested",
+                "   2) Nested too",
+                "",
+                "3. Three",
+                "- bullet",
+                "3. New list",
+                "4. Its second",
+            ]
+        );
+        assert_eq!(list_len(&lines), 7);
+        assert_eq!(list_len(&lines[8..]), 2);
+        assert_eq!(ordered_item("12.5 kg"), None);
+        assert_eq!(next_marker("  3) Milk").as_deref(), Some("  4) "));
+    }
+
+    #[test]
+    fn test_autolist_rules() {
+        let rules = AutoList::default();
+        let enter = |rules: AutoList, line: &str| rules.on_enter(line, line.len());
+
+        assert_eq!(
+            enter(rules, "  - [x] done"),
+            Some(Enter::Continue("  - [ ] ".to_string()))
+        );
+        assert_eq!(
+            enter(rules, "3. [ ] task"),
+            Some(Enter::Continue("4. [ ] ".to_string()))
+        );
+        assert_eq!(
+            enter(rules, "* note"),
+            Some(Enter::Continue("* ".to_string()))
+        );
+        assert_eq!(enter(rules, "  - [ ] "), Some(Enter::End));
+        assert_eq!(enter(rules, "---"), None);
+        assert_eq!(rules.on_enter("- item", 1), None);
+
+        let plain = AutoList {
+            carry_checkboxes: false,
+            end_empty_items: false,
+            ..rules
+        };
+
+        assert_eq!(
+            enter(plain, "- [x] done"),
+            Some(Enter::Continue("- ".to_string()))
+        );
+        assert_eq!(enter(plain, "- "), None);
+        assert_eq!(
+            enter(
+                AutoList {
+                    continue_items: false,
+                    ..rules
+                },
+                "- item"
+            ),
+            None
+        );
+
+        assert!(rules.indents_on_tab("- [ ] ", 6));
+        assert!(!rules.indents_on_tab("- item", 6));
+        assert!(
+            !AutoList {
+                indent_with_tab: false,
+                ..rules
+            }
+            .indents_on_tab("- ", 2)
+        );
+    }
+}
@@ -0,0 +1,235 @@
+/// Keyring service the workspace passphrases are stored under, one entry per
+/// workspace folder.
+const KEYRING_SERVICE: &str = "MyNotes Lock";
+
+/// PBKDF2-HMAC-SHA256 rounds for new passphrases.
+const ROUNDS: u32 = 600_000;
+
+/// When a workspace locks itself. A salted hash of the passphrase that unlocks it
+/// lives in the OS keyring, never in this file, and is held in memory from
+/// [`WorkspaceLock::read_keyring`] on, so a keyring that stops answering can't keep
+/// the workspace locked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceLock {
+    pub root: std::path::PathBuf,
+    /// Lock after this long without a key press or mouse movement, never if unset.
+    pub idle: Option<std::time::Duration>,
+    /// What [`hash_passphrase`] made of the passphrase.
+    hash: Option<String>,
+}
+
+impl WorkspaceLock {
+    /// `<config>/mynotes/lock`, next to the session file.
+    pub fn default_path() -> Option<std::path::PathBuf> {
+        Some(crate::session::config_dir()?.join("lock"))
+    }
+
+    /// The idle timeout saved at `path` for the workspace at `root`, as
+    /// `root\tminutes` lines. Without one the workspace only locks on demand.
+    pub fn load(path: &std::path::Path, root: &std::path::Path) -> Self {
+        let idle = std::fs::read_to_string(path)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| line.rsplit_once('\t'))
+            .find(|(have, _)| std::path::Path::new(have) == root)
+            .and_then(|(_, minutes)| minutes.trim().parse().ok())
+            .filter(|&minutes: &u64| minutes > 0)
+            .map(|minutes| std::time::Duration::from_secs(minutes * 60));
+
+        Self {
+            root: root.to_path_buf(),
+            idle,
+            hash: None,
+        }
+    }
+
+    /// Saves the idle timeout at `path`, keeping those of other workspaces.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be written.
+    pub fn save(&self, path: &std::path::Path) -> std::io::Result<()> {
+        let root = self.root.display().to_string();
+        let mut content: String = std::fs::read_to_string(path)
+            .unwrap_or_default()
+            .lines()
+            .filter(|line| line.rsplit_once('\t').is_some_and(|(have, _)| have != root))
+            .map(|line| format!("{line}\n"))
+            .collect();
+        let mut batch = crate::workspace::BatchSave::default();
+
+        if let Some(idle) = self.idle {
+            content.push_str(&format!("{root}\t{}\n", idle.as_secs().div_ceil(60)));
+        }
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        batch.write(path, content);
+        batch.commit().map(|_| ())
+    }
+
+    fn keyring_entry(&self) -> std::io::Result<keyring::Entry> {
+        keyring::Entry::new(KEYRING_SERVICE, &self.root.display().to_string())
+            .map_err(std::io::Error::other)
+    }
+
+    /// Reads the passphrase hash from the OS keyring. One stored in plain text by
+    /// an earlier version is hashed and stored again.
+    pub fn read_keyring(&mut self) {
+        let stored = match self
+            .keyring_entry()
+            .and_then(|entry| entry.get_password().map_err(std::io::Error::other))
+        {
+            Ok(stored) => stored,
+            Err(err) => {
+                tracing::debug!("No workspace passphrase in the keyring: {:?}", err);
+                return;
+            }
+        };
+
+        if parse_hash(&stored).is_some() {
+            self.hash = Some(stored);
+        } else if let Err(err) = self.set_passphrase(&stored) {
+            tracing::warn!("Error rehashing the workspace passphrase: {:?}", err);
+        }
+    }
+
+    /// Stores a hash of `passphrase` in the OS keyring as the one that unlocks this
+    /// workspace, replacing any earlier one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the keyring refuses it. The passphrase still unlocks
+    /// the workspace until the app quits.
+    pub fn set_passphrase(&mut self, passphrase: &str) -> std::io::Result<()> {
+        let hash = hash_passphrase(passphrase, ROUNDS)?;
+
+        self.hash = Some(hash.clone());
+        self.keyring_entry()?
+            .set_password(&hash)
+            .map_err(std::io::Error::other)
+    }
+
+    /// Whether a passphrase was set, i.e. whether the workspace can be locked.
+    pub fn has_passphrase(&self) -> bool {
+        self.hash.is_some()
+    }
+
+    /// Whether `attempt` is this workspace's passphrase.
+    pub fn verify(&self, attempt: &str) -> bool {
+        self.hash
+            .as_deref()
+            .is_some_and(|hash| verify_passphrase(hash, attempt))
+    }
+
+    /// Whether the workspace has sat idle long enough to lock, `idle_for` after
+    /// the last key press or mouse movement.
+    pub fn is_due(&self, idle_for: std::time::Duration) -> bool {
+        self.idle.is_some_and(|idle| idle_for >= idle)
+    }
+}
+
+/// Compares without stopping at the first difference, so how long it takes
+/// doesn't tell how much of a guess was right.
+fn same(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn derive(passphrase: &str, salt: &[u8], rounds: u32) -> [u8; 32] {
+    let mut key = [0; 32];
+
+    pbkdf2::pbkdf2_hmac::<sha2::Sha256>(passphrase.as_bytes(), salt, rounds, &mut key);
+    key
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// `passphrase` salted and stretched, as `pbkdf2-sha256$rounds$salt$key` in hex.
+fn hash_passphrase(passphrase: &str, rounds: u32) -> std::io::Result<String> {
+    let mut salt = [0; 16];
+
+    getrandom::getrandom(&mut salt).map_err(|err| std::io::Error::other(err.to_string()))?;
+    Ok(format!(
+        "pbkdf2-sha256${rounds}${}${}",
+        to_hex(&salt),
+        to_hex(&derive(passphrase, &salt, rounds))
+    ))
+}
+
+/// The rounds, salt and key of a [`hash_passphrase`] hash.
+fn parse_hash(hash: &str) -> Option<(u32, Vec<u8>, Vec<u8>)> {
+    let mut fields = hash.strip_prefix("pbkdf2-sha256$")?.split('$');
+    let rounds = fields.next()?.parse().ok().filter(|&rounds| rounds > 0)?;
+    let salt = from_hex(fields.next()?)?;
+    let key = from_hex(fields.next()?)?;
+
+    fields.next().is_none().then_some((rounds, salt, key))
+}
+
+fn verify_passphrase(hash: &str, attempt: &str) -> bool {
+    parse_hash(hash).is_some_and(|(rounds, salt, key)| same(&derive(attempt, &salt, rounds), &key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idle_timeouts_per_workspace() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lock");
+        let mut notes = WorkspaceLock::load(&path, std::path::Path::new("/home/me/Notes"));
+
+        assert_eq!(notes.idle, None);
+        assert!(!notes.is_due(std::time::Duration::from_secs(3600)));
+
+        notes.idle = Some(std::time::Duration::from_secs(5 * 60));
+        notes.save(&path).unwrap();
+
+        let mut work = WorkspaceLock::load(&path, std::path::Path::new("/home/me/Work"));
+        work.idle = Some(std::time::Duration::from_secs(60));
+        work.save(&path).unwrap();
+        notes.idle = Some(std::time::Duration::from_secs(10 * 60));
+        notes.save(&path).unwrap();
+
+        let notes = WorkspaceLock::load(&path, &notes.root);
+
+        assert_eq!(notes.idle, Some(std::time::Duration::from_secs(600)));
+        assert!(notes.is_due(std::time::Duration::from_secs(600)));
+        assert!(!notes.is_due(std::time::Duration::from_secs(599)));
+        assert_eq!(
+            WorkspaceLock::load(&path, &work.root).idle,
+            Some(std::time::Duration::from_secs(60))
+        );
+
+        assert!(same(b"open sesame", b"open sesame"));
+        assert!(!same(b"open sesame", b"open sesamE"));
+        assert!(!same(b"open", b"open sesame"));
+    }
+
+    #[test]
+    fn test_passphrases_are_stored_hashed() {
+        let hash = hash_passphrase("open sesame", 1_000).unwrap();
+
+        assert!(!hash.contains("open sesame"));
+        assert_ne!(hash, hash_passphrase("open sesame", 1_000).unwrap());
+        assert!(verify_passphrase(&hash, "open sesame"));
+        assert!(!verify_passphrase(&hash, "open sesamE"));
+        assert!(!verify_passphrase("open sesame", "open sesame"));
+
+        let mut lock =
+            WorkspaceLock::load(std::path::Path::new("missing"), std::path::Path::new("/n"));
+        assert!(!lock.has_passphrase() && !lock.verify(""));
+        lock.hash = Some(hash);
+        assert!(lock.has_passphrase() && lock.verify("open sesame"));
+    }
+}
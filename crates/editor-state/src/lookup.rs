@@ -0,0 +1,157 @@
+/// One meaning or translation a provider found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Definition {
+    /// The entry as the provider lists it, which may differ from what was looked
+    /// up, e.g. in case.
+    pub headword: String,
+    pub text: String,
+}
+
+/// Something words can be looked up in: a dictionary, a thesaurus or a
+/// translation service. [`Lookup`] asks each registered provider in turn.
+pub trait LookupProvider {
+    /// Shown above the provider's definitions.
+    fn name(&self) -> &str;
+
+    /// What `word` means according to this provider, none if it doesn't know it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the provider can't be reached or read.
+    fn lookup(&self, word: &str) -> std::io::Result<Vec<Definition>>;
+}
+
+/// A local dictionary: a text file with one `word\tdefinition` entry per line,
+/// looked up without regard to case. A word may have several entries. Lines
+/// starting with `#` are comments.
+pub struct DictionaryFile {
+    path: std::path::PathBuf,
+}
+
+impl DictionaryFile {
+    pub fn new(path: impl Into<std::path::PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// `dictionary.tsv` next to the session store.
+    pub fn default_path() -> Option<std::path::PathBuf> {
+        Some(crate::session::config_dir()?.join("dictionary.tsv"))
+    }
+}
+
+impl LookupProvider for DictionaryFile {
+    fn name(&self) -> &str {
+        "Dictionary"
+    }
+
+    fn lookup(&self, word: &str) -> std::io::Result<Vec<Definition>> {
+        let word = word.to_lowercase();
+        let content = match std::fs::read_to_string(&self.path) {
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            content => content?,
+        };
+
+        Ok(content
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| line.split_once('\t'))
+            .filter(|(headword, _)| headword.trim().to_lowercase() == word)
+            .map(|(headword, text)| Definition {
+                headword: headword.trim().to_string(),
+                text: text.trim().to_string(),
+            })
+            .collect())
+    }
+}
+
+/// What a provider had to say about a word: its definitions, or why it
+/// couldn't be asked.
+pub type LookupResult = (String, Result<Vec<Definition>, String>);
+
+/// The registered lookup providers, asked in the order they were added.
+#[derive(Default)]
+pub struct Lookup {
+    providers: Vec<Box<dyn LookupProvider>>,
+}
+
+impl Lookup {
+    /// The local dictionary, when there's a place for it.
+    pub fn with_defaults() -> Self {
+        let mut lookup = Self::default();
+
+        if let Some(path) = DictionaryFile::default_path() {
+            lookup.register(Box::new(DictionaryFile::new(path)));
+        }
+        lookup
+    }
+
+    pub fn register(&mut self, provider: Box<dyn LookupProvider>) {
+        self.providers.push(provider);
+    }
+
+    /// Every provider's answer for `word`, leaving out those that don't know it.
+    pub fn lookup(&self, word: &str) -> Vec<LookupResult> {
+        self.providers
+            .iter()
+            .filter_map(|provider| {
+                let answer = match provider.lookup(word) {
+                    Ok(definitions) if definitions.is_empty() => return None,
+                    Ok(definitions) => Ok(definitions),
+                    Err(err) => Err(err.to_string()),
+                };
+
+                Some((provider.name().to_string(), answer))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Failing;
+
+    impl LookupProvider for Failing {
+        fn name(&self) -> &str {
+            "Web"
+        }
+
+        fn lookup(&self, _word: &str) -> std::io::Result<Vec<Definition>> {
+            Err(std::io::Error::other("offline"))
+        }
+    }
+
+    #[test]
+    fn test_lookup_asks_providers_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dictionary.tsv");
+
+        std::fs::write(
+            &path,
+            "# comment\nNote\ta short record\nnote\ta musical sound\nother\tsomething else\n",
+        )
+        .unwrap();
+
+        let mut lookup = Lookup::default();
+        lookup.register(Box::new(DictionaryFile::new(&path)));
+        lookup.register(Box::new(DictionaryFile::new(dir.path().join("missing"))));
+        lookup.register(Box::new(Failing));
+
+        let results = lookup.lookup("NOTE");
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0, "Dictionary");
+        assert_eq!(
+            results[0]
+                .1
+                .as_ref()
+                .unwrap()
+                .iter()
+                .map(|definition| definition.text.as_str())
+                .collect::<Vec<_>>(),
+            ["a short record", "a musical sound"]
+        );
+        assert_eq!(results[1], ("Web".to_string(), Err("offline".to_string())));
+    }
+}
@@ -0,0 +1,473 @@
+/// End (exclusive, closing `$` included) of an inline `$...$` formula opening at
+/// `at`. Like Pandoc, the opening `$` can't be followed by a space nor the closing
+/// one preceded by one or followed by a digit, so prices such as `$5 and $6` stay
+/// text. `$$` and `\$` never open one.
+pub fn inline_end(line: &str, at: usize) -> Option<usize> {
+    let bytes = line.as_bytes();
+
+    if bytes.get(at) != Some(&b'$')
+        || bytes
+            .get(at + 1)
+            .is_none_or(|b| b.is_ascii_whitespace() || *b == b'$')
+        || (at > 0 && bytes[at - 1] == b'\\')
+    {
+        return None;
+    }
+
+    let mut i = at + 1;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'$' => {
+                let closes = !bytes[i - 1].is_ascii_whitespace()
+                    && !bytes.get(i + 1).is_some_and(u8::is_ascii_digit);
+
+                return closes.then_some(i + 1);
+            }
+            _ => i += 1,
+        }
+    }
+
+    None
+}
+
+/// A `$$ ... $$` display formula, on one line or over several with the `$$` on
+/// lines of their own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MathBlock {
+    pub start: usize,
+    /// The row holding the closing `$$`.
+    pub end: usize,
+    pub tex: String,
+}
+
+/// Whether `line` can open or close a formula or a code block. [`blocks`] pairs
+/// these up; other lines are only ever formula text.
+pub fn is_marker(line: &str) -> bool {
+    let trimmed = line.trim();
+
+    ["```", "~~~", "$$"]
+        .into_iter()
+        .any(|marker| trimmed.starts_with(marker))
+        || trimmed.ends_with("$$")
+}
+
+/// The display formulas in `lines`. `$$` in code blocks doesn't count, and one
+/// left open runs to no block at all.
+pub fn blocks<S: AsRef<str>>(lines: &[S]) -> Vec<MathBlock> {
+    let mut blocks = Vec::new();
+    let mut open: Option<(usize, Vec<&str>)> = None;
+    let mut in_code = false;
+
+    for (row, line) in lines.iter().enumerate() {
+        let trimmed = line.as_ref().trim();
+
+        if open.is_none() && (trimmed.starts_with("```") || trimmed.starts_with("~~~")) {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            continue;
+        }
+
+        match &mut open {
+            Some((start, body)) => {
+                if let Some(last) = trimmed.strip_suffix("$$") {
+                    body.push(last);
+                    blocks.push(MathBlock {
+                        start: *start,
+                        end: row,
+                        tex: body.join("\n").trim().to_string(),
+                    });
+                    open = None;
+                } else {
+                    body.push(trimmed);
+                }
+            }
+            None => {
+                let Some(rest) = trimmed.strip_prefix("$$") else {
+                    continue;
+                };
+
+                match rest.strip_suffix("$$") {
+                    Some(tex) => blocks.push(MathBlock {
+                        start: row,
+                        end: row,
+                        tex: tex.trim().to_string(),
+                    }),
+                    None => open = Some((row, vec![rest])),
+                }
+            }
+        }
+    }
+
+    blocks
+}
+
+const SYMBOLS: &[(&str, &str)] = &[
+    ("alpha", "α"),
+    ("beta", "β"),
+    ("gamma", "γ"),
+    ("delta", "δ"),
+    ("epsilon", "ε"),
+    ("varepsilon", "ε"),
+    ("zeta", "ζ"),
+    ("eta", "η"),
+    ("theta", "θ"),
+    ("iota", "ι"),
+    ("kappa", "κ"),
+    ("lambda", "λ"),
+    ("mu", "μ"),
+    ("nu", "ν"),
+    ("xi", "ξ"),
+    ("pi", "π"),
+    ("rho", "ρ"),
+    ("sigma", "σ"),
+    ("tau", "τ"),
+    ("upsilon", "υ"),
+    ("phi", "φ"),
+    ("varphi", "φ"),
+    ("chi", "χ"),
+    ("psi", "ψ"),
+    ("omega", "ω"),
+    ("Gamma", "Γ"),
+    ("Delta", "Δ"),
+    ("Theta", "Θ"),
+    ("Lambda", "Λ"),
+    ("Xi", "Ξ"),
+    ("Pi", "Π"),
+    ("Sigma", "Σ"),
+    ("Phi", "Φ"),
+    ("Psi", "Ψ"),
+    ("Omega", "Ω"),
+    ("sum", "∑"),
+    ("prod", "∏"),
+    ("int", "∫"),
+    ("oint", "∮"),
+    ("partial", "∂"),
+    ("nabla", "∇"),
+    ("infty", "∞"),
+    ("pm", "±"),
+    ("mp", "∓"),
+    ("times", "×"),
+    ("div", "÷"),
+    ("cdot", "·"),
+    ("cdots", "⋯"),
+    ("ldots", "…"),
+    ("dots", "…"),
+    ("le", "≤"),
+    ("leq", "≤"),
+    ("ge", "≥"),
+    ("geq", "≥"),
+    ("ne", "≠"),
+    ("neq", "≠"),
+    ("approx", "≈"),
+    ("equiv", "≡"),
+    ("sim", "∼"),
+    ("propto", "∝"),
+    ("in", "∈"),
+    ("notin", "∉"),
+    ("subset", "⊂"),
+    ("subseteq", "⊆"),
+    ("supset", "⊃"),
+    ("cup", "∪"),
+    ("cap", "∩"),
+    ("emptyset", "∅"),
+    ("forall", "∀"),
+    ("exists", "∃"),
+    ("neg", "¬"),
+    ("land", "∧"),
+    ("lor", "∨"),
+    ("to", "→"),
+    ("rightarrow", "→"),
+    ("leftarrow", "←"),
+    ("Rightarrow", "⇒"),
+    ("Leftarrow", "⇐"),
+    ("iff", "⇔"),
+    ("mapsto", "↦"),
+    ("circ", "∘"),
+    ("degree", "°"),
+    ("prime", "′"),
+    ("sin", "sin"),
+    ("cos", "cos"),
+    ("tan", "tan"),
+    ("log", "log"),
+    ("ln", "ln"),
+    ("exp", "exp"),
+    ("lim", "lim"),
+    ("max", "max"),
+    ("min", "min"),
+    ("quad", "  "),
+    ("qquad", "    "),
+    (",", " "),
+    (";", " "),
+    (" ", " "),
+    ("{", "{"),
+    ("}", "}"),
+    ("%", "%"),
+    ("$", "$"),
+    ("_", "_"),
+    ("\\", " "),
+];
+
+/// Commands that only change spacing or sizing; their output is what follows.
+const IGNORED: &[&str] = &["left", "right", "big", "Big", "displaystyle", "limits"];
+
+const BLACKBOARD: &[(char, &str)] = &[
+    ('R', "ℝ"),
+    ('N', "ℕ"),
+    ('Z', "ℤ"),
+    ('Q', "ℚ"),
+    ('C', "ℂ"),
+    ('P', "ℙ"),
+];
+
+const SUPERSCRIPTS: &[(char, char)] = &[
+    ('0', '⁰'),
+    ('1', '¹'),
+    ('2', '²'),
+    ('3', '³'),
+    ('4', '⁴'),
+    ('5', '⁵'),
+    ('6', '⁶'),
+    ('7', '⁷'),
+    ('8', '⁸'),
+    ('9', '⁹'),
+    ('+', '⁺'),
+    ('-', '⁻'),
+    ('=', '⁼'),
+    ('(', '⁽'),
+    (')', '⁾'),
+    ('n', 'ⁿ'),
+    ('i', 'ⁱ'),
+    ('T', 'ᵀ'),
+    ('′', '′'),
+];
+
+const SUBSCRIPTS: &[(char, char)] = &[
+    ('0', '₀'),
+    ('1', '₁'),
+    ('2', '₂'),
+    ('3', '₃'),
+    ('4', '₄'),
+    ('5', '₅'),
+    ('6', '₆'),
+    ('7', '₇'),
+    ('8', '₈'),
+    ('9', '₉'),
+    ('+', '₊'),
+    ('-', '₋'),
+    ('=', '₌'),
+    ('(', '₍'),
+    (')', '₎'),
+    ('a', 'ₐ'),
+    ('e', 'ₑ'),
+    ('i', 'ᵢ'),
+    ('j', 'ⱼ'),
+    ('k', 'ₖ'),
+    ('n', 'ₙ'),
+    ('m', 'ₘ'),
+    ('x', 'ₓ'),
+    ('t', 'ₜ'),
+];
+
+/// Reads TeX math and sets it as plain Unicode, see [`to_unicode`].
+struct Typesetter<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    /// Groups and commands being read inside one another.
+    depth: usize,
+}
+
+impl Typesetter<'_> {
+    /// Deeper groups and commands than this are refused rather than recursed into.
+    const MAX_DEPTH: usize = 64;
+
+    /// Reads a group or command nested in the one being read.
+    fn nested(
+        &mut self,
+        read: impl FnOnce(&mut Self) -> Result<String, String>,
+    ) -> Result<String, String> {
+        if self.depth == Self::MAX_DEPTH {
+            return Err("Nested too deeply".to_string());
+        }
+
+        self.depth += 1;
+        let read = read(self);
+        self.depth -= 1;
+        read
+    }
+
+    /// Everything up to `until` (a closing `}`) or the end.
+    fn sequence(&mut self, until: Option<char>) -> Result<String, String> {
+        let mut out = String::new();
+
+        loop {
+            match self.chars.next() {
+                None if until.is_some() => return Err("Missing }".to_string()),
+                None => return Ok(out),
+                Some(c) if Some(c) == until => return Ok(out),
+                Some('}') => return Err("Unmatched }".to_string()),
+                Some('{') => out.push_str(&self.nested(|t| t.sequence(Some('}')))?),
+                Some('\\') => out.push_str(&self.nested(Self::command)?),
+                Some(c @ ('^' | '_')) => {
+                    let arg = self.argument()?;
+                    out.push_str(&script(&arg, c == '^'));
+                }
+                Some(c) if c.is_whitespace() => {}
+                Some(c) => out.push(c),
+            }
+        }
+    }
+
+    /// One argument: a `{group}`, a command or a single character.
+    fn argument(&mut self) -> Result<String, String> {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        match self.chars.next() {
+            Some('{') => self.nested(|t| t.sequence(Some('}'))),
+            Some('\\') => self.nested(Self::command),
+            Some('}') | None => Err("Missing argument".to_string()),
+            Some(c) => Ok(c.to_string()),
+        }
+    }
+
+    fn command(&mut self) -> Result<String, String> {
+        let mut name: String =
+            std::iter::from_fn(|| self.chars.next_if(char::is_ascii_alphabetic)).collect();
+
+        if name.is_empty() {
+            name.extend(self.chars.next());
+        }
+
+        let wrap = |text: String| {
+            if text.chars().count() > 1 {
+                format!("({text})")
+            } else {
+                text
+            }
+        };
+
+        Ok(match name.as_str() {
+            "frac" => {
+                let (top, bottom) = (self.argument()?, self.argument()?);
+                format!("{}/{}", wrap(top), wrap(bottom))
+            }
+            "sqrt" => format!("√{}", wrap(self.argument()?)),
+            "text" | "mathrm" | "mathit" | "mathbf" | "operatorname" => self.argument()?,
+            "mathbb" => self
+                .argument()?
+                .chars()
+                .map(|c| {
+                    BLACKBOARD
+                        .iter()
+                        .find(|(letter, _)| *letter == c)
+                        .map_or_else(|| c.to_string(), |(_, set)| set.to_string())
+                })
+                .collect(),
+            "overline" | "bar" => format!("{}\u{305}", self.argument()?),
+            "hat" => format!("{}\u{302}", self.argument()?),
+            "vec" => format!("{}\u{20d7}", self.argument()?),
+            _ if IGNORED.contains(&name.as_str()) => String::new(),
+            _ => match SYMBOLS.iter().find(|(command, _)| *command == name) {
+                Some((_, symbol)) => symbol.to_string(),
+                None => return Err(format!("Unknown command \\{name}")),
+            },
+        })
+    }
+}
+
+/// `text` raised or lowered, with Unicode's super- and subscript characters when it
+/// has them all and as `^(text)` / `_(text)` otherwise.
+fn script(text: &str, up: bool) -> String {
+    let table = if up { SUPERSCRIPTS } else { SUBSCRIPTS };
+    let mapped: Option<String> = text
+        .chars()
+        .map(|c| table.iter().find(|(from, _)| *from == c).map(|(_, to)| *to))
+        .collect();
+
+    match mapped {
+        Some(mapped) => mapped,
+        None if text.chars().count() == 1 => format!("{}{text}", if up { '^' } else { '_' }),
+        None => format!("{}({text})", if up { '^' } else { '_' }),
+    }
+}
+
+/// Sets TeX math as a line of Unicode text: Greek letters, operators and arrows,
+/// `^`/`_` scripts, `\frac`, `\sqrt`, `\mathbb` and `\text`. Good enough to read a
+/// formula at a glance, not a typesetter.
+///
+/// # Errors
+///
+/// Returns what went wrong, for showing next to the raw source: unbalanced braces,
+/// a missing argument, a command it doesn't know or groups nested too deeply.
+pub fn to_unicode(tex: &str) -> Result<String, String> {
+    let mut typesetter = Typesetter {
+        chars: tex.chars().peekable(),
+        depth: 0,
+    };
+
+    typesetter.sequence(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inline_end() {
+        let line = r"cost $5 and $6, area $\pi r^2$ and \$x$";
+
+        assert_eq!(inline_end(line, 5), None);
+        assert_eq!(inline_end(line, 21), Some(30));
+        assert_eq!(inline_end(line, line.find(r"\$").unwrap() + 1), None);
+        assert_eq!(inline_end("$$x$$", 0), None);
+    }
+
+    #[test]
+    fn test_blocks() {
+        let lines = [
+            "$$ a^2 $$",
+            "text",
+            "$$",
+            r"\sum_{i=1}^n i",
+            "$$",
+            "```",
+            "$$",
+            "```",
+        ];
+
+        assert_eq!(
+            blocks(&lines),
+            vec![
+                MathBlock {
+                    start: 0,
+                    end: 0,
+                    tex: "a^2".into()
+                },
+                MathBlock {
+                    start: 2,
+                    end: 4,
+                    tex: r"\sum_{i=1}^n i".into()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_to_unicode() {
+        assert_eq!(to_unicode(r"\sum_{i=1}^n x_i^2").unwrap(), "∑ᵢ₌₁ⁿxᵢ²");
+        assert_eq!(
+            to_unicode(r"\frac{a+b}{2} \le \sqrt{x} \in \mathbb{R}").unwrap(),
+            "(a+b)/2≤√x∈ℝ"
+        );
+        assert_eq!(to_unicode(r"e^{i\pi} = -1").unwrap(), "e^(iπ)=-1");
+        assert_eq!(to_unicode(r"\foo x").unwrap_err(), r"Unknown command \foo");
+        assert!(to_unicode(r"\frac{a}{").is_err());
+        assert_eq!(
+            to_unicode(&format!("{}x{}", "{".repeat(100_000), "}".repeat(100_000))).unwrap_err(),
+            "Nested too deeply"
+        );
+        assert!(to_unicode(&r"\sqrt".repeat(100_000)).is_err());
+    }
+}
@@ -0,0 +1,197 @@
+/// A stretch of a three-way merge.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Chunk {
+    /// Unchanged, changed on one side only, or changed the same way on both.
+    Clean(Vec<String>),
+    /// Changed differently on both sides.
+    Conflict {
+        base: Vec<String>,
+        local: Vec<String>,
+        remote: Vec<String>,
+    },
+}
+
+/// For every line of `base`, the line of `other` it survives as, if any.
+fn matches<S: AsRef<str> + PartialEq>(base: &[S], other: &[S]) -> Vec<Option<usize>> {
+    let mut matched = vec![None; base.len()];
+
+    for op in crate::diff::diff(base, other) {
+        if let crate::diff::DiffOp::Equal { old, new } = op {
+            matched[old] = Some(new);
+        }
+    }
+
+    matched
+}
+
+fn owned<S: AsRef<str>>(lines: &[S]) -> Vec<String> {
+    lines.iter().map(|line| line.as_ref().to_string()).collect()
+}
+
+/// Merges `local` and `remote`, both edited from `base`, diff3-style: the lines of
+/// `base` kept by both sides split the files into stretches, and a stretch is only a
+/// conflict when both sides changed it and disagree.
+pub fn merge3<S: AsRef<str> + PartialEq>(base: &[S], local: &[S], remote: &[S]) -> Vec<Chunk> {
+    let (to_local, to_remote) = (matches(base, local), matches(base, remote));
+    let mut chunks: Vec<Chunk> = Vec::new();
+    let (mut b, mut l, mut r) = (0, 0, 0);
+
+    let mut push = |chunk: Chunk| match (chunks.last_mut(), chunk) {
+        (Some(Chunk::Clean(last)), Chunk::Clean(lines)) => last.extend(lines),
+        (_, Chunk::Clean(lines)) if lines.is_empty() => {}
+        (_, chunk) => chunks.push(chunk),
+    };
+
+    loop {
+        // The next base line both sides kept, or the end of all three files.
+        let stable = (b..base.len()).find_map(|i| Some((i, to_local[i]?, to_remote[i]?)));
+        let (b_end, l_end, r_end) = stable.unwrap_or((base.len(), local.len(), remote.len()));
+        let (base_part, local_part, remote_part) =
+            (&base[b..b_end], &local[l..l_end], &remote[r..r_end]);
+
+        if local_part == base_part {
+            push(Chunk::Clean(owned(remote_part)));
+        } else if remote_part == base_part || local_part == remote_part {
+            push(Chunk::Clean(owned(local_part)));
+        } else {
+            push(Chunk::Conflict {
+                base: owned(base_part),
+                local: owned(local_part),
+                remote: owned(remote_part),
+            });
+        }
+
+        let Some((b_next, l_next, r_next)) = stable else {
+            break;
+        };
+
+        push(Chunk::Clean(vec![base[b_next].as_ref().to_string()]));
+        (b, l, r) = (b_next + 1, l_next + 1, r_next + 1);
+    }
+
+    chunks
+}
+
+/// A three-way merge being resolved one conflict at a time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Merge {
+    pub chunks: Vec<Chunk>,
+    /// The side picked for each chunk; only ever set for conflicts.
+    picks: Vec<Option<crate::conflict::Resolution>>,
+}
+
+impl Merge {
+    pub fn new<S: AsRef<str> + PartialEq>(base: &[S], local: &[S], remote: &[S]) -> Self {
+        let chunks = merge3(base, local, remote);
+        let picks = vec![None; chunks.len()];
+
+        Self { chunks, picks }
+    }
+
+    /// How many conflicts still have no side picked.
+    pub fn unresolved(&self) -> usize {
+        self.chunks
+            .iter()
+            .zip(&self.picks)
+            .filter(|(chunk, pick)| matches!(chunk, Chunk::Conflict { .. }) && pick.is_none())
+            .count()
+    }
+
+    #[inline]
+    pub fn pick(&self, chunk: usize) -> Option<crate::conflict::Resolution> {
+        self.picks.get(chunk).copied().flatten()
+    }
+
+    /// Picks a side for the conflict `chunk`, or clears the pick with `None`.
+    /// Clean chunks ignore it.
+    pub fn set_pick(&mut self, chunk: usize, pick: Option<crate::conflict::Resolution>) {
+        if matches!(self.chunks.get(chunk), Some(Chunk::Conflict { .. })) {
+            self.picks[chunk] = pick;
+        }
+    }
+
+    /// The merged lines. Conflicts without a pick are written out as diff3-style
+    /// conflict blocks, so they can still be resolved in the editor later.
+    pub fn result(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        for (chunk, pick) in self.chunks.iter().zip(&self.picks) {
+            match (chunk, pick) {
+                (Chunk::Clean(clean), _) => lines.extend(clean.iter().cloned()),
+                (Chunk::Conflict { local, remote, .. }, Some(pick)) => match pick {
+                    crate::conflict::Resolution::Ours => lines.extend(local.iter().cloned()),
+                    crate::conflict::Resolution::Theirs => lines.extend(remote.iter().cloned()),
+                    crate::conflict::Resolution::Both => {
+                        lines.extend(local.iter().chain(remote).cloned());
+                    }
+                },
+                (
+                    Chunk::Conflict {
+                        base,
+                        local,
+                        remote,
+                    },
+                    None,
+                ) => {
+                    lines.push("<<<<<<< local".to_string());
+                    lines.extend(local.iter().cloned());
+                    lines.push("||||||| base".to_string());
+                    lines.extend(base.iter().cloned());
+                    lines.push("=======".to_string());
+                    lines.extend(remote.iter().cloned());
+                    lines.push(">>>>>>> remote".to_string());
+                }
+            }
+        }
+
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge3() {
+        let base = ["title", "one", "two", "three", "end"];
+        let local = ["title", "one!", "two", "three", "end", "local tail"];
+        let remote = ["title", "one?", "two", "3", "end"];
+
+        let mut merge = Merge::new(&base, &local, &remote);
+
+        assert_eq!(
+            merge.chunks,
+            vec![
+                Chunk::Clean(vec!["title".to_string()]),
+                Chunk::Conflict {
+                    base: vec!["one".to_string()],
+                    local: vec!["one!".to_string()],
+                    remote: vec!["one?".to_string()],
+                },
+                Chunk::Clean(
+                    ["two", "3", "end", "local tail"]
+                        .map(str::to_string)
+                        .to_vec()
+                ),
+            ]
+        );
+        assert_eq!(merge.unresolved(), 1);
+        assert_eq!(
+            crate::conflict::find_conflicts(&merge.result()),
+            vec![crate::conflict::Conflict {
+                start: 1,
+                base: Some(3),
+                separator: 5,
+                end: 7
+            }]
+        );
+
+        merge.set_pick(1, Some(crate::conflict::Resolution::Theirs));
+        assert_eq!(merge.unresolved(), 0);
+        assert_eq!(
+            merge.result(),
+            ["title", "one?", "two", "3", "end", "local tail"].map(str::to_string)
+        );
+    }
+}
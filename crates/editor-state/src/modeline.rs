@@ -0,0 +1,138 @@
+/// What a Vim or Emacs modeline asks of the file it's in.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Modeline {
+    pub file_type: Option<String>,
+    /// Settings as `.editorconfig` keys and values, see
+    /// [`crate::editorconfig::Properties::apply`].
+    pub settings: Vec<(&'static str, String)>,
+}
+
+/// Reads the options of a Vim modeline: `vim: set ts=4 sw=4:`, or
+/// `vim: ts=4 sw=4` without `set`. `vi:` and `ex:` work too.
+fn vim(line: &str) -> Option<Modeline> {
+    let options = ["vim:", "vi:", "ex:"].iter().find_map(|marker| {
+        let at = line.find(marker)?;
+
+        (at == 0 || line[..at].ends_with(char::is_whitespace)).then(|| &line[at + marker.len()..])
+    })?;
+    let options = match options.trim_start().strip_prefix("set ") {
+        // The `set` form ends at the next `:`.
+        Some(set) => set.split(':').next().unwrap_or(set),
+        None => options,
+    };
+    let mut modeline = Modeline::default();
+
+    for option in options.split([' ', ':', '\t']).filter(|o| !o.is_empty()) {
+        let (name, value) = option.split_once('=').unwrap_or((option, ""));
+
+        match name {
+            "ft" | "filetype" | "syntax" | "syn" => modeline.file_type = Some(value.to_string()),
+            "ts" | "tabstop" => modeline.settings.push(("tab_width", value.to_string())),
+            "sw" | "shiftwidth" => modeline.settings.push(("indent_size", value.to_string())),
+            "et" | "expandtab" => modeline.settings.push(("indent_style", "space".into())),
+            "noet" | "noexpandtab" => modeline.settings.push(("indent_style", "tab".into())),
+            "tw" | "textwidth" => modeline
+                .settings
+                .push(("max_line_length", value.to_string())),
+            _ => {}
+        }
+    }
+
+    Some(modeline)
+}
+
+/// Reads an Emacs `-*- mode: python; tab-width: 4 -*-` line, or `-*- python -*-`
+/// naming just the mode.
+fn emacs(line: &str) -> Option<Modeline> {
+    let variables = line.split("-*-").nth(1)?.trim();
+    let mut modeline = Modeline::default();
+
+    if !variables.contains(':') {
+        modeline.file_type = Some(variables.to_string());
+        return Some(modeline);
+    }
+
+    for variable in variables.split(';') {
+        let Some((name, value)) = variable.split_once(':') else {
+            continue;
+        };
+        let (name, value) = (name.trim().to_ascii_lowercase(), value.trim());
+
+        match name.as_str() {
+            "mode" => modeline.file_type = Some(value.to_string()),
+            "tab-width" => modeline.settings.push(("tab_width", value.to_string())),
+            "indent-tabs-mode" => modeline.settings.push((
+                "indent_style",
+                if value == "nil" { "space" } else { "tab" }.into(),
+            )),
+            "fill-column" => modeline
+                .settings
+                .push(("max_line_length", value.to_string())),
+            // Each mode has its own, e.g. `c-basic-offset` or `python-indent-offset`.
+            _ if name.ends_with("-offset") || name.ends_with("-indent-level") => {
+                modeline.settings.push(("indent_size", value.to_string()));
+            }
+            _ => {}
+        }
+    }
+
+    Some(modeline)
+}
+
+/// The first modeline among the first and last five of `lines`, where editors
+/// look for them.
+pub fn find<S: AsRef<str>>(lines: &[S]) -> Option<Modeline> {
+    let bottom = lines.len().saturating_sub(5).max(lines.len().min(5));
+
+    lines[..lines.len().min(5)]
+        .iter()
+        .chain(&lines[bottom..])
+        .find_map(|line| emacs(line.as_ref()).or_else(|| vim(line.as_ref())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find() {
+        let settings = |lines: &[&str]| find(lines).map(|modeline| modeline.settings);
+
+        assert_eq!(
+            find(&["x", "# vim: set ts=8 sw=2 et ft=yaml: not this=1"]),
+            Some(Modeline {
+                file_type: Some("yaml".into()),
+                settings: vec![
+                    ("tab_width", "8".into()),
+                    ("indent_size", "2".into()),
+                    ("indent_style", "space".into()),
+                ],
+            })
+        );
+        assert_eq!(
+            settings(&["// -*- mode: c; c-basic-offset: 4; indent-tabs-mode: t -*-"]),
+            Some(vec![
+                ("indent_size", "4".into()),
+                ("indent_style", "tab".into())
+            ])
+        );
+        assert_eq!(
+            find(&["-*- python -*-"]).unwrap().file_type.as_deref(),
+            Some("python")
+        );
+        assert_eq!(settings(&["Navi: tw=1"]), None);
+
+        let long: Vec<String> = (0..20).map(|n| format!("line {n}")).collect();
+        let mut with_bottom = long.clone();
+        with_bottom.push("vi: noet tw=72".into());
+
+        assert_eq!(find(&long), None);
+        assert_eq!(
+            find(&with_bottom).map(|modeline| modeline.settings),
+            Some(vec![
+                ("indent_style", "tab".into()),
+                ("max_line_length", "72".into())
+            ])
+        );
+    }
+}
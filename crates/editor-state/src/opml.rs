@@ -0,0 +1,206 @@
+/// The text of an outline element's attribute, unescaped.
+fn attribute(
+    tag: &quick_xml::events::BytesStart<'_>,
+    name: &str,
+) -> Result<Option<String>, quick_xml::Error> {
+    match tag.try_get_attribute(name)? {
+        Some(attr) => Ok(Some(attr.unescape_value()?.into_owned())),
+        None => Ok(None),
+    }
+}
+
+/// An OPML outline as a Markdown note: the head's title as a heading, then each
+/// outline element as a list item indented by its depth. An element's `_note`
+/// follows its item as an indented line.
+///
+/// # Errors
+///
+/// Returns an error if `xml` isn't well-formed.
+pub fn import(xml: &str) -> std::io::Result<Vec<String>> {
+    let invalid = |err: quick_xml::Error| std::io::Error::new(std::io::ErrorKind::InvalidData, err);
+    let mut reader = quick_xml::Reader::from_str(xml);
+    let mut lines = Vec::new();
+    let mut depth = 0;
+    let mut in_title = false;
+
+    loop {
+        let event = reader.read_event().map_err(invalid)?;
+        let (tag, nested) = match &event {
+            quick_xml::events::Event::Start(tag) => (tag, true),
+            quick_xml::events::Event::Empty(tag) => (tag, false),
+            quick_xml::events::Event::Text(text) if in_title => {
+                let title = text.unescape().map_err(invalid)?;
+
+                if !title.trim().is_empty() {
+                    lines.push(format!("# {}", title.trim()));
+                    lines.push(String::new());
+                }
+                continue;
+            }
+            quick_xml::events::Event::End(tag) => {
+                match tag.local_name().as_ref() {
+                    b"outline" => depth -= 1,
+                    b"title" => in_title = false,
+                    _ => {}
+                }
+                continue;
+            }
+            quick_xml::events::Event::Eof => break,
+            _ => continue,
+        };
+
+        match tag.local_name().as_ref() {
+            b"title" => in_title = nested,
+            b"outline" => {
+                let indent = "  ".repeat(depth);
+                let text = attribute(tag, "text").map_err(invalid)?.unwrap_or_default();
+
+                lines.push(format!("{indent}- {}", text.trim()));
+                if let Some(note) = attribute(tag, "_note").map_err(invalid)? {
+                    lines.extend(
+                        note.lines()
+                            .filter(|line| !line.trim().is_empty())
+                            .map(|line| format!("{indent}  {}", line.trim())),
+                    );
+                }
+                if nested {
+                    depth += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(lines)
+}
+
+/// Text of a bulleted or numbered list item, and how far it's indented.
+fn list_item(line: &str) -> Option<(usize, &str)> {
+    let trimmed = line.trim_start();
+    let indent = line.len() - trimmed.len();
+    let digits = trimmed.bytes().take_while(u8::is_ascii_digit).count();
+
+    let text = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|marker| trimmed.strip_prefix(marker))
+        .or_else(|| {
+            (digits > 0)
+                .then(|| {
+                    trimmed[digits..]
+                        .strip_prefix(". ")
+                        .or_else(|| trimmed[digits..].strip_prefix(") "))
+                })
+                .flatten()
+        })?;
+
+    Some((indent, text.trim()))
+}
+
+/// A note's headings and list items as an OPML document titled `title`. Headings
+/// nest by level, and list items by indentation under the heading above them.
+/// Other lines, and anything in code blocks, are left out.
+pub fn export<S: AsRef<str>>(lines: &[S], title: &str) -> String {
+    let escape = |text: &str| quick_xml::escape::escape(text).into_owned();
+
+    // Each item with the rank it nests by: headings by level, list items below
+    // every heading and then by indentation.
+    let mut items: Vec<(usize, &str)> = Vec::new();
+    let mut in_code = false;
+
+    for line in lines {
+        let line = line.as_ref();
+
+        if line.trim_start().starts_with("```") || line.trim_start().starts_with("~~~") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code {
+            continue;
+        }
+
+        if let Some((level, heading)) = crate::outline::parse_heading(line) {
+            items.push((usize::from(level), heading));
+        } else if let Some((indent, text)) = list_item(line) {
+            items.push((7 + indent, text));
+        }
+    }
+
+    let mut out = format!(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<opml version=\"2.0\">\n  <head>\n    <title>{}</title>\n  </head>\n  <body>\n",
+        escape(title)
+    );
+    let mut open: Vec<usize> = Vec::new();
+
+    for (i, &(rank, text)) in items.iter().enumerate() {
+        while open.last().is_some_and(|&above| above >= rank) {
+            open.pop();
+        }
+
+        let indent = "  ".repeat(open.len() + 2);
+        let has_children = items.get(i + 1).is_some_and(|&(next, _)| next > rank);
+
+        if has_children {
+            out.push_str(&format!("{indent}<outline text=\"{}\">\n", escape(text)));
+            open.push(rank);
+        } else {
+            out.push_str(&format!("{indent}<outline text=\"{}\"/>\n", escape(text)));
+
+            // Close the parents the next item doesn't belong to.
+            let next = items.get(i + 1).map_or(0, |&(next, _)| next);
+
+            while open.last().is_some_and(|&above| above >= next) {
+                open.pop();
+                out.push_str(&format!("{}</outline>\n", "  ".repeat(open.len() + 2)));
+            }
+        }
+    }
+
+    out.push_str("  </body>\n</opml>\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_and_export() {
+        let xml = r#"<?xml version="1.0"?>
+<opml version="2.0">
+  <head><title>Trip &amp; Plans</title></head>
+  <body>
+    <outline text="Pack">
+      <outline text="Socks" _note="wool"/>
+      <outline text="Tickets"/>
+    </outline>
+    <outline text="Go"/>
+  </body>
+</opml>"#;
+        let lines = import(xml).unwrap();
+
+        assert_eq!(
+            lines,
+            vec![
+                "# Trip & Plans",
+                "",
+                "- Pack",
+                "  - Socks",
+                "    wool",
+                "  - Tickets",
+                "- Go",
+            ]
+        );
+        assert_eq!(
+            export(&lines, "Trip & Plans"),
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<opml version=\"2.0\">\n  <head>\n    <title>Trip &amp; Plans</title>\n  </head>\n  <body>\n    \
+             <outline text=\"Trip &amp; Plans\">\n      \
+             <outline text=\"Pack\">\n        \
+             <outline text=\"Socks\"/>\n        \
+             <outline text=\"Tickets\"/>\n      \
+             </outline>\n      \
+             <outline text=\"Go\"/>\n    \
+             </outline>\n  </body>\n</opml>\n"
+        );
+        assert!(import("<opml><body><outline text=\"a\"></body>").is_err());
+    }
+}
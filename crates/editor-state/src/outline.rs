@@ -0,0 +1,373 @@
+/// A Markdown ATX heading (`# Title`) and the line it sits on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heading {
+    /// 1 for `#`, up to 6 for `######`.
+    pub level: u8,
+    pub title: String,
+    pub row: usize,
+}
+
+/// Parses an ATX heading. Closing `#`s are dropped, as Markdown renders them.
+pub fn parse_heading(line: &str) -> Option<(u8, &str)> {
+    // Up to three spaces of indentation are allowed; more makes it a code block.
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+
+    let level = trimmed.bytes().take_while(|&b| b == b'#').count();
+    let rest = &trimmed[level..];
+
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with([' ', '\t'])) {
+        return None;
+    }
+
+    let title = rest.trim();
+    let title = match title.trim_end_matches('#') {
+        stripped if stripped.is_empty() || stripped.ends_with([' ', '\t']) => stripped.trim_end(),
+        _ => title,
+    };
+
+    Some((level as u8, title))
+}
+
+/// `line`, a heading, at `level` instead, e.g. `### Title` for `# Title` at 3.
+pub fn with_level(line: &str, level: u8) -> Option<String> {
+    parse_heading(line)?;
+
+    let trimmed = line.trim_start_matches(' ');
+    let indent = &line[..line.len() - trimmed.len()];
+    let rest = trimmed.trim_start_matches('#');
+
+    Some(format!("{indent}{}{rest}", "#".repeat(level.into())))
+}
+
+/// Whether `line` opens or closes a fenced code block.
+fn is_fence(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    trimmed.starts_with("```") || trimmed.starts_with("~~~")
+}
+
+/// Heading index of a document, kept in sync with edits line range by line range.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Outline {
+    /// Sorted by row.
+    pub headings: Vec<Heading>,
+    /// Rows of code fence lines, sorted. `#` lines between a pair aren't headings.
+    fences: Vec<usize>,
+    /// Bumped on every change so views can cheaply tell when to redraw.
+    pub revision: u64,
+}
+
+impl Outline {
+    pub fn build(buffer: &editor_core::text::TextBuffer) -> Self {
+        let mut outline = Self::default();
+
+        outline.rebuild(buffer);
+        outline
+    }
+
+    fn rebuild(&mut self, buffer: &editor_core::text::TextBuffer) {
+        self.headings.clear();
+        self.fences.clear();
+
+        let mut in_code = false;
+
+        for (row, line) in buffer
+            .get_lines_stripped(0..buffer.line_count())
+            .iter()
+            .enumerate()
+        {
+            if is_fence(line) {
+                self.fences.push(row);
+                in_code = !in_code;
+            } else if !in_code && let Some((level, title)) = parse_heading(line) {
+                self.headings.push(Heading {
+                    level,
+                    title: title.to_string(),
+                    row,
+                });
+            }
+        }
+
+        self.revision += 1;
+    }
+
+    /// Updates the index after rows `start..=old_end` were replaced by `start..=new_end`.
+    ///
+    /// Only the touched rows are re-parsed and the rows below are shifted. Adding or
+    /// removing a code fence changes what's code for the rest of the file, so that
+    /// falls back to a full rebuild.
+    pub fn update(
+        &mut self,
+        buffer: &editor_core::text::TextBuffer,
+        start: usize,
+        old_end: usize,
+        new_end: usize,
+    ) {
+        let lines = buffer.get_lines_stripped(start..new_end + 1);
+        let touched_fence = self
+            .fences
+            .iter()
+            .any(|&row| (start..=old_end).contains(&row));
+
+        if touched_fence || lines.iter().any(|line| is_fence(line)) {
+            self.rebuild(buffer);
+            return;
+        }
+
+        // Only called for rows below the edit, so this never underflows.
+        let moved = |row: usize| row + new_end - old_end;
+
+        self.headings
+            .retain(|heading| !(start..=old_end).contains(&heading.row));
+        for heading in self.headings.iter_mut().filter(|h| h.row > old_end) {
+            heading.row = moved(heading.row);
+        }
+        for fence in self.fences.iter_mut().filter(|&&mut row| row > old_end) {
+            *fence = moved(*fence);
+        }
+
+        let in_code = self.fences.partition_point(|&row| row < start) % 2 == 1;
+
+        if !in_code {
+            let at = self.headings.partition_point(|h| h.row < start);
+            let fresh = lines.iter().enumerate().filter_map(|(i, line)| {
+                parse_heading(line).map(|(level, title)| Heading {
+                    level,
+                    title: title.to_string(),
+                    row: start + i,
+                })
+            });
+
+            self.headings.splice(at..at, fresh);
+        }
+
+        self.revision += 1;
+    }
+
+    /// The heading on `row` itself, if that row is one.
+    pub fn heading_on(&self, row: usize) -> Option<&Heading> {
+        self.headings
+            .binary_search_by_key(&row, |heading| heading.row)
+            .ok()
+            .map(|index| &self.headings[index])
+    }
+
+    /// Whether `row` is inside a fenced code block, fence lines included.
+    pub fn in_code(&self, row: usize) -> bool {
+        let before = self.fences.partition_point(|&fence| fence < row);
+
+        before % 2 == 1 || self.fences.get(before) == Some(&row)
+    }
+
+    /// The opening fence of the code block `row` is inside of, fence lines excluded.
+    pub fn code_block_at(&self, row: usize) -> Option<usize> {
+        let before = self.fences.partition_point(|&fence| fence < row);
+
+        (before % 2 == 1 && self.fences.get(before) != Some(&row)).then(|| self.fences[before - 1])
+    }
+
+    /// The closing fence of the code block opened at `fence`, if it's closed.
+    pub fn code_block_end(&self, fence: usize) -> Option<usize> {
+        let index = self.fences.binary_search(&fence).ok()?;
+
+        (index % 2 == 0).then(|| self.fences.get(index + 1).copied())?
+    }
+
+    /// Rows of the opening fences of code blocks, top to bottom.
+    pub fn code_blocks(&self) -> impl Iterator<Item = usize> + '_ {
+        self.fences.iter().step_by(2).copied()
+    }
+
+    /// Index of the heading whose section contains `row`.
+    pub fn section_at(&self, row: usize) -> Option<usize> {
+        self.headings
+            .partition_point(|heading| heading.row <= row)
+            .checked_sub(1)
+    }
+
+    /// The enclosing headings of `row`, outermost first, e.g. `H1 ▸ H2 ▸ H3`.
+    pub fn path_at(&self, row: usize) -> Vec<&Heading> {
+        let Some(mut index) = self.section_at(row) else {
+            return Vec::new();
+        };
+        let mut path = vec![&self.headings[index]];
+
+        while let Some(parent) = self.parent_of(index) {
+            path.push(&self.headings[parent]);
+            index = parent;
+        }
+
+        path.reverse();
+        path
+    }
+
+    /// The heading path of `row` as a single line, e.g. `Project Ideas ▸ Editor ▸ TODO`.
+    pub fn breadcrumb(&self, row: usize) -> String {
+        self.path_at(row)
+            .iter()
+            .map(|heading| heading.title.as_str())
+            .collect::<Vec<_>>()
+            .join(" ▸ ")
+    }
+
+    /// Rows of the section of heading `index`: the heading and everything up to
+    /// the next heading at its level or above, subsections included, in a
+    /// document of `line_count` rows.
+    pub fn section_rows(&self, index: usize, line_count: usize) -> std::ops::Range<usize> {
+        let heading = &self.headings[index];
+        let end = self.headings[index + 1..]
+            .iter()
+            .find(|next| next.level <= heading.level)
+            .map_or(line_count, |next| next.row);
+
+        heading.row..end
+    }
+
+    /// Index of the section before or after heading `index` under the same
+    /// parent, at the same level.
+    pub fn sibling_of(&self, index: usize, after: bool) -> Option<usize> {
+        let level = self.headings.get(index)?.level;
+        let same_parent = |heading: &Heading| heading.level >= level;
+        if after {
+            self.headings[index + 1..]
+                .iter()
+                .take_while(|heading| same_parent(heading))
+                .position(|heading| heading.level == level)
+                .map(|i| index + 1 + i)
+        } else {
+            self.headings[..index]
+                .iter()
+                .rev()
+                .take_while(|heading| same_parent(heading))
+                .position(|heading| heading.level == level)
+                .map(|i| index - 1 - i)
+        }
+    }
+
+    /// The heading a `#anchor` or `[[note#heading]]` link leads to: the one whose
+    /// anchor is the slug of `target`, see [`crate::toc::anchors`].
+    pub fn find_anchor(&self, target: &str) -> Option<&Heading> {
+        let slug = crate::toc::slug(target.trim_start_matches('#'));
+
+        crate::toc::anchors(&self.headings)
+            .iter()
+            .position(|anchor| *anchor == slug)
+            .map(|index| &self.headings[index])
+    }
+
+    /// Index of the closest heading above `index` with a lower level.
+    pub fn parent_of(&self, index: usize) -> Option<usize> {
+        let level = self.headings.get(index)?.level;
+
+        self.headings[..index]
+            .iter()
+            .rposition(|heading| heading.level < level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use editor_core::cursor::Cursor;
+    use editor_core::text::TextBuffer;
+
+    const NOTE: &str = "# Project Ideas\nintro\n## Editor\n```\n# not a heading\n```\n### TODO ###\n- item\n## Sync\n";
+
+    fn titles(outline: &Outline) -> Vec<(u8, &str, usize)> {
+        outline
+            .headings
+            .iter()
+            .map(|h| (h.level, h.title.as_str(), h.row))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_heading() {
+        assert_eq!(parse_heading("## Title ##"), Some((2, "Title")));
+        assert_eq!(parse_heading("#"), Some((1, "")));
+        assert_eq!(parse_heading("   # Indented"), Some((1, "Indented")));
+        assert_eq!(parse_heading("    # Code"), None);
+        assert_eq!(parse_heading("#hashtag"), None);
+        assert_eq!(parse_heading("####### Seven"), None);
+        assert_eq!(parse_heading("# C#"), Some((1, "C#")));
+    }
+
+    #[test]
+    fn test_build_skips_code_blocks() {
+        let outline = Outline::build(&TextBuffer::new_with_text(NOTE).unwrap());
+
+        assert_eq!(
+            titles(&outline),
+            vec![
+                (1, "Project Ideas", 0),
+                (2, "Editor", 2),
+                (3, "TODO", 6),
+                (2, "Sync", 8)
+            ]
+        );
+        assert_eq!(outline.heading_on(2).map(|h| h.level), Some(2));
+        assert_eq!(outline.heading_on(4), None);
+        assert_eq!(
+            (2..=6).map(|row| outline.in_code(row)).collect::<Vec<_>>(),
+            [false, true, true, true, false]
+        );
+    }
+
+    #[test]
+    fn test_incremental_update_matches_rebuild() {
+        let mut buffer = TextBuffer::new_with_text(NOTE).unwrap();
+        let mut outline = Outline::build(&buffer);
+
+        // Turn "intro" into two new heading lines.
+        buffer
+            .insert(&Cursor::new(1, 0), "## New\n# Top\n")
+            .unwrap();
+        outline.update(&buffer, 1, 1, 3);
+        assert_eq!(outline.headings, Outline::build(&buffer).headings);
+
+        // Delete the "## New" line again.
+        buffer
+            .delete_selection(&Cursor::new_selection(
+                editor_core::cursor::Position::new(1, 0),
+                editor_core::cursor::Position::new(2, 0),
+            ))
+            .unwrap();
+        outline.update(&buffer, 1, 2, 1);
+        assert_eq!(outline.headings, Outline::build(&buffer).headings);
+        assert_eq!(outline.headings[1].title, "Top");
+    }
+
+    #[test]
+    fn test_path_at() {
+        let outline = Outline::build(&TextBuffer::new_with_text(NOTE).unwrap());
+        let path: Vec<&str> = outline
+            .path_at(7)
+            .iter()
+            .map(|h| h.title.as_str())
+            .collect();
+
+        assert_eq!(path, ["Project Ideas", "Editor", "TODO"]);
+        assert_eq!(outline.breadcrumb(8), "Project Ideas ▸ Sync");
+        assert!(outline.path_at(0).len() == 1);
+
+        assert_eq!(outline.section_rows(1, 9), 2..8);
+        assert_eq!(outline.section_rows(0, 9), 0..9);
+        assert_eq!(outline.sibling_of(1, true), Some(3));
+        assert_eq!(outline.sibling_of(3, false), Some(1));
+        assert_eq!(outline.sibling_of(2, true), None, "Sync is a level up");
+        assert_eq!(outline.sibling_of(0, false), None);
+        assert_eq!(
+            with_level("  ## Editor ##", 3).as_deref(),
+            Some("  ### Editor ##")
+        );
+        assert_eq!(with_level("#hashtag", 2), None);
+        assert_eq!(outline.find_anchor("sync").map(|h| h.row), Some(8));
+        assert_eq!(
+            outline.find_anchor("#Project Ideas").map(|h| h.row),
+            Some(0)
+        );
+        assert_eq!(outline.find_anchor("nowhere"), None);
+    }
+}
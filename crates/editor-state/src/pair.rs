@@ -0,0 +1,828 @@
+/// A line of the pair-editing protocol: tab-separated fields, with operations written
+/// the way [`crate::collab::Op::encode`] writes them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    /// The first thing a joining peer sends, with the code the host gave out.
+    Hello {
+        token: String,
+        name: String,
+    },
+    /// The host's answer: the site to edit as and a snapshot of the shared text.
+    Welcome {
+        site: u32,
+        snapshot: String,
+    },
+    /// Someone is in the session. Sent about every peer, the host included.
+    Peer {
+        site: u32,
+        name: String,
+    },
+    /// Someone left.
+    Bye {
+        site: u32,
+    },
+    /// Where someone's cursor is, anchored as [`crate::collab::Replica::anchor_at`] does.
+    Cursor {
+        site: u32,
+        anchor: Option<crate::collab::CharId>,
+    },
+    Op(crate::collab::Op),
+}
+
+/// Names travel inside tab-separated lines.
+fn clean_name(name: &str) -> String {
+    name.replace(['\t', '\n', '\r'], " ")
+}
+
+impl Message {
+    pub fn encode(&self) -> String {
+        match self {
+            Message::Hello { token, name } => {
+                format!("H\t{}\t{}", clean_name(token), clean_name(name))
+            }
+            Message::Welcome { site, snapshot } => format!("W\t{site}\t{snapshot}"),
+            Message::Peer { site, name } => format!("P\t{site}\t{}", clean_name(name)),
+            Message::Bye { site } => format!("B\t{site}"),
+            Message::Cursor { site, anchor } => format!(
+                "C\t{site}\t{}",
+                anchor.map_or_else(|| "-".to_string(), crate::collab::CharId::encode)
+            ),
+            Message::Op(op) => op.encode(),
+        }
+    }
+
+    /// Parses a line written by [`Message::encode`].
+    pub fn decode(line: &str) -> Option<Self> {
+        let mut fields = line.splitn(3, '\t');
+        let kind = fields.next()?;
+        let mut site = || fields.next()?.parse::<u32>().ok();
+
+        match kind {
+            "H" => Some(Message::Hello {
+                token: fields.next()?.to_string(),
+                name: fields.next()?.to_string(),
+            }),
+            "W" => Some(Message::Welcome {
+                site: site()?,
+                snapshot: fields.next()?.to_string(),
+            }),
+            "P" => Some(Message::Peer {
+                site: site()?,
+                name: fields.next()?.to_string(),
+            }),
+            "B" => Some(Message::Bye { site: site()? }),
+            "C" => Some(Message::Cursor {
+                site: site()?,
+                anchor: match fields.next()? {
+                    "-" => None,
+                    anchor => Some(crate::collab::CharId::decode(anchor)?),
+                },
+            }),
+            _ => crate::collab::Op::decode(line).map(Message::Op),
+        }
+    }
+}
+
+/// The longest line read from a peer, which bounds the snapshot a session can share.
+const MAX_LINE: usize = 16 << 20;
+
+/// The longest line read from a peer that hasn't shown the code yet, plenty for a
+/// `Hello`.
+const MAX_HELLO: usize = 4096;
+
+/// The next line from `reader` without its newline, `None` at the end. A line longer
+/// than `max` is an error instead of being read into memory.
+fn read_line(reader: &mut impl std::io::BufRead, max: usize) -> std::io::Result<Option<String>> {
+    let mut line = Vec::new();
+    let read = std::io::BufRead::read_until(
+        &mut std::io::Read::take(&mut *reader, max as u64 + 1),
+        b'\n',
+        &mut line,
+    )?;
+
+    if read == 0 {
+        return Ok(None);
+    }
+    if line.last() == Some(&b'\n') {
+        line.pop();
+    } else if line.len() > max {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "Pair-editing message too long",
+        ));
+    }
+
+    String::from_utf8(line)
+        .map(Some)
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+}
+
+/// What the network threads hand to the session.
+enum Event {
+    Connected(usize, Connection),
+    Received(usize, Message),
+    Closed(usize),
+}
+
+/// Reads messages from `reader` until the connection closes, reporting them as
+/// coming from connection `id`.
+fn read_from<R: std::io::BufRead + Send + 'static>(
+    id: usize,
+    mut reader: R,
+    events: std::sync::mpsc::Sender<Event>,
+) {
+    std::thread::spawn(move || {
+        while let Ok(Some(line)) = read_line(&mut reader, MAX_LINE) {
+            if let Some(message) = Message::decode(&line)
+                && events.send(Event::Received(id, message)).is_err()
+            {
+                return;
+            }
+        }
+        let _ = events.send(Event::Closed(id));
+    });
+}
+
+struct Connection {
+    /// Lines for the connection's writer thread, so a slow peer never holds up the
+    /// editor.
+    outbox: std::sync::mpsc::SyncSender<String>,
+    stream: std::net::TcpStream,
+    /// The site editing at the other end, once it's known.
+    site: Option<u32>,
+}
+
+impl Connection {
+    /// How many lines may wait for a peer before it's let go.
+    const OUTBOX: usize = 4096;
+
+    /// Writes to `stream` on a thread of its own. Once the connection is dropped the
+    /// thread writes what's left and shuts the stream, which ends its reader too.
+    fn new(stream: std::net::TcpStream, site: Option<u32>) -> std::io::Result<Self> {
+        let (outbox, lines) = std::sync::mpsc::sync_channel::<String>(Self::OUTBOX);
+        let mut writer = stream.try_clone()?;
+
+        std::thread::spawn(move || {
+            for line in lines {
+                if std::io::Write::write_all(&mut writer, line.as_bytes()).is_err() {
+                    break;
+                }
+            }
+            let _ = writer.shutdown(std::net::Shutdown::Both);
+        });
+
+        Ok(Self {
+            outbox,
+            stream,
+            site,
+        })
+    }
+
+    /// Failures are left to the reading side, which sees the connection close.
+    fn send(&self, message: &Message) {
+        if self
+            .outbox
+            .try_send(format!("{}\n", message.encode()))
+            .is_err()
+        {
+            let _ = self.stream.shutdown(std::net::Shutdown::Both);
+        }
+    }
+}
+
+/// A fresh code for peers to join with.
+fn new_token() -> std::io::Result<String> {
+    let mut bytes = [0u8; 16];
+
+    getrandom::getrandom(&mut bytes).map_err(|err| std::io::Error::other(err.to_string()))?;
+    Ok(bytes.iter().map(|byte| format!("{byte:02x}")).collect())
+}
+
+/// Compares codes in time independent of where they differ.
+fn same_token(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// A collaborator's cursor, resolved against the local text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerCursor {
+    pub site: u32,
+    pub name: String,
+    pub pos: editor_core::cursor::Position,
+}
+
+#[derive(Debug, Clone)]
+struct Peer {
+    name: String,
+    anchor: Option<crate::collab::CharId>,
+}
+
+/// Distinct colors for collaborators' cursors, picked by site.
+pub fn peer_color(site: u32) -> crate::theme::Rgb {
+    const COLORS: [crate::theme::Rgb; 6] = [
+        (230, 90, 80),
+        (60, 160, 90),
+        (70, 130, 220),
+        (210, 150, 40),
+        (170, 90, 200),
+        (40, 170, 170),
+    ];
+
+    COLORS[site as usize % COLORS.len()]
+}
+
+/// One note edited together over the network. The host listens for peers, hands
+/// each a site and a snapshot, and relays everything between them; a peer only talks
+/// to the host. Nothing happens in the background beyond reading sockets: call
+/// [`PairSession::poll`] regularly to send local edits and apply remote ones.
+pub struct PairSession {
+    pub collab: crate::collab::CollabSession,
+    name: String,
+    /// The address listened on, when hosting.
+    hosting: Option<std::net::SocketAddr>,
+    /// The code peers join with, when hosting.
+    token: Option<String>,
+    next_site: u32,
+    connections: std::collections::HashMap<usize, Connection>,
+    peers: std::collections::BTreeMap<u32, Peer>,
+    events: std::sync::mpsc::Receiver<Event>,
+    /// The last cursor position sent, to send it only when it moves.
+    sent_anchor: Option<Option<crate::collab::CharId>>,
+    /// Tells the thread accepting peers to stop.
+    closed: std::sync::Arc<std::sync::atomic::AtomicBool>,
+}
+
+impl PairSession {
+    pub const DEFAULT_PORT: u16 = 47_800;
+    const HOST_SITE: u32 = 1;
+    const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+    /// Connections let wait for their `Hello` at once; more are hung up on.
+    const MAX_HANDSHAKES: usize = 8;
+
+    fn new(
+        collab: crate::collab::CollabSession,
+        name: &str,
+        hosting: Option<std::net::SocketAddr>,
+        events: std::sync::mpsc::Receiver<Event>,
+    ) -> Self {
+        Self {
+            collab,
+            name: name.to_string(),
+            hosting,
+            token: None,
+            next_site: Self::HOST_SITE + 1,
+            connections: std::collections::HashMap::new(),
+            peers: std::collections::BTreeMap::new(),
+            events,
+            sent_anchor: None,
+            closed: std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false)),
+        }
+    }
+
+    /// Where sessions are shared unless the user opens them to the network.
+    pub fn default_address() -> String {
+        format!("127.0.0.1:{}", Self::DEFAULT_PORT)
+    }
+
+    /// Shares `doc` on `addr` (`ip:port`, port `0` picking a free one; see
+    /// [`Self::port`]). Peers need [`Self::token`] to join.
+    pub fn host(
+        doc: &mut crate::document::Document,
+        addr: &str,
+        name: &str,
+    ) -> std::io::Result<Self> {
+        let listener = std::net::TcpListener::bind(addr)?;
+        let (report, events) = std::sync::mpsc::channel();
+        let mut session = Self::new(
+            crate::collab::CollabSession::start(doc, Self::HOST_SITE),
+            name,
+            Some(listener.local_addr()?),
+            events,
+        );
+        let closed = session.closed.clone();
+        let token = new_token()?;
+
+        session.token = Some(token.clone());
+
+        // Non-blocking, so the thread notices the session closing.
+        listener.set_nonblocking(true)?;
+        std::thread::spawn(move || {
+            let mut next_id = 0;
+            let handshakes = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+
+            while !closed.load(std::sync::atomic::Ordering::Relaxed) {
+                let stream = match listener.accept() {
+                    Ok((stream, _)) => stream,
+                    Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {
+                        std::thread::sleep(std::time::Duration::from_millis(100));
+                        continue;
+                    }
+                    Err(err) => {
+                        tracing::warn!("Error accepting pair-editing peer: {:?}", err);
+                        continue;
+                    }
+                };
+                if handshakes.load(std::sync::atomic::Ordering::Relaxed) >= Self::MAX_HANDSHAKES {
+                    tracing::warn!("Turned away a pair-editing peer, too many are connecting");
+                    continue;
+                }
+
+                next_id += 1;
+                handshakes.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                std::thread::spawn({
+                    let (id, token) = (next_id, token.clone());
+                    let (report, handshakes) = (report.clone(), handshakes.clone());
+
+                    move || {
+                        let greeted = Self::greet(stream, &token);
+
+                        handshakes.fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
+                        if let Some((reader, connection, hello)) = greeted
+                            && report.send(Event::Connected(id, connection)).is_ok()
+                            && report.send(Event::Received(id, hello)).is_ok()
+                        {
+                            read_from(id, reader, report);
+                        }
+                    }
+                });
+            }
+        });
+
+        Ok(session)
+    }
+
+    /// Waits a little for the peer on `stream` to say `Hello` with `token`, reading
+    /// no more than that takes, and returns the connection with the `Hello` once it
+    /// has. `None` if it doesn't, which hangs up.
+    fn greet(
+        stream: std::net::TcpStream,
+        token: &str,
+    ) -> Option<(std::io::BufReader<std::net::TcpStream>, Connection, Message)> {
+        stream.set_nonblocking(false).ok()?;
+        stream.set_write_timeout(Some(Self::TIMEOUT)).ok()?;
+        stream.set_read_timeout(Some(Self::TIMEOUT)).ok()?;
+
+        let mut reader = std::io::BufReader::new(stream.try_clone().ok()?);
+        let hello = read_line(&mut reader, MAX_HELLO)
+            .ok()
+            .flatten()
+            .as_deref()
+            .and_then(Message::decode)?;
+
+        if !matches!(&hello, Message::Hello { token: theirs, .. } if same_token(theirs, token)) {
+            tracing::warn!("Turned away a pair-editing peer with the wrong code");
+            return None;
+        }
+        stream.set_read_timeout(None).ok()?;
+
+        Some((reader, Connection::new(stream, None).ok()?, hello))
+    }
+
+    /// Joins the session hosted at `addr` (`host:port`) with the host's `token`. `doc`
+    /// is replaced with the shared text.
+    pub fn join(
+        doc: &mut crate::document::Document,
+        addr: &str,
+        token: &str,
+        name: &str,
+    ) -> std::io::Result<Self> {
+        let addr = std::net::ToSocketAddrs::to_socket_addrs(addr)?
+            .next()
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "No such host"))?;
+        let stream = std::net::TcpStream::connect_timeout(&addr, Self::TIMEOUT)?;
+
+        stream.set_write_timeout(Some(Self::TIMEOUT))?;
+        stream.set_read_timeout(Some(Self::TIMEOUT))?;
+
+        let host = Connection::new(stream.try_clone()?, Some(Self::HOST_SITE))?;
+        let mut reader = std::io::BufReader::new(stream);
+
+        host.send(&Message::Hello {
+            token: token.to_string(),
+            name: name.to_string(),
+        });
+
+        let replica = match read_line(&mut reader, MAX_LINE)?
+            .as_deref()
+            .and_then(Message::decode)
+        {
+            Some(Message::Welcome { site, snapshot }) => {
+                crate::collab::Replica::from_snapshot(site, &snapshot)
+            }
+            _ => None,
+        }
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Not a MyNotes pair-editing session",
+            )
+        })?;
+
+        reader.get_ref().set_read_timeout(None)?;
+
+        let (report, events) = std::sync::mpsc::channel();
+        let mut session = Self::new(
+            crate::collab::CollabSession::join(doc, replica),
+            name,
+            None,
+            events,
+        );
+
+        read_from(0, reader, report);
+        session.connections.insert(0, host);
+        Ok(session)
+    }
+
+    /// The port peers connect to, when hosting.
+    #[inline]
+    pub fn port(&self) -> Option<u16> {
+        self.hosting.map(|addr| addr.port())
+    }
+
+    /// Whether peers on other computers can reach the session.
+    pub fn is_public(&self) -> bool {
+        self.hosting.is_some_and(|addr| !addr.ip().is_loopback())
+    }
+
+    /// The code peers have to join with, when hosting.
+    #[inline]
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_deref()
+    }
+
+    #[inline]
+    pub fn site(&self) -> u32 {
+        self.collab.replica.site()
+    }
+
+    /// `false` once a joined session lost its host.
+    pub fn is_connected(&self) -> bool {
+        self.hosting.is_some() || !self.connections.is_empty()
+    }
+
+    /// Sends `message` to every peer met, except over connection `skip`.
+    fn broadcast(&self, message: &Message, skip: Option<usize>) {
+        for (id, connection) in &self.connections {
+            if connection.site.is_some() && Some(*id) != skip {
+                connection.send(message);
+            }
+        }
+    }
+
+    /// Sends what was edited locally and where the cursor is, then applies whatever
+    /// arrived. Returns whether `doc` was changed by a peer.
+    pub fn poll(&mut self, doc: &mut crate::document::Document) -> bool {
+        let mut changed = false;
+
+        self.flush(doc);
+
+        while let Ok(event) = self.events.try_recv() {
+            match event {
+                Event::Connected(id, connection) => {
+                    self.connections.insert(id, connection);
+                }
+                Event::Received(id, message) => {
+                    changed |= self.receive(doc, id, message);
+                }
+                Event::Closed(id) => {
+                    let Some(connection) = self.connections.remove(&id) else {
+                        continue;
+                    };
+
+                    if self.hosting.is_none() {
+                        // The host went away, and everyone with it.
+                        self.peers.clear();
+                    } else if let Some(site) = connection.site {
+                        self.peers.remove(&site);
+                        self.broadcast(&Message::Bye { site }, None);
+                    }
+                }
+            }
+        }
+
+        changed
+    }
+
+    fn flush(&mut self, doc: &mut crate::document::Document) {
+        for op in self.collab.outgoing(doc) {
+            self.broadcast(&Message::Op(op), None);
+        }
+
+        let anchor = self.collab.replica.anchor_at(doc.cursor.head);
+
+        if self.sent_anchor != Some(anchor) {
+            self.sent_anchor = Some(anchor);
+            self.broadcast(
+                &Message::Cursor {
+                    site: self.site(),
+                    anchor,
+                },
+                None,
+            );
+        }
+    }
+
+    fn receive(
+        &mut self,
+        doc: &mut crate::document::Document,
+        id: usize,
+        message: Message,
+    ) -> bool {
+        let relay = self.hosting.is_some();
+
+        if relay && !self.admits(id, &message) {
+            return false;
+        }
+        match &message {
+            // Its code was checked before the connection was handed over.
+            Message::Hello { name, .. } if relay => {
+                self.welcome(doc, id, name.clone());
+                return false;
+            }
+            Message::Op(op) => {
+                self.collab.incoming(doc, op.clone());
+            }
+            Message::Cursor { site, anchor } => {
+                if let Some(peer) = self.peers.get_mut(site) {
+                    peer.anchor = *anchor;
+                }
+            }
+            Message::Peer { site, name } if *site != self.site() => {
+                self.peers.insert(
+                    *site,
+                    Peer {
+                        name: name.clone(),
+                        anchor: None,
+                    },
+                );
+            }
+            Message::Bye { site } => {
+                self.peers.remove(site);
+            }
+            _ => return false,
+        }
+
+        if relay {
+            self.broadcast(&message, Some(id));
+        }
+
+        matches!(message, Message::Op(_))
+    }
+
+    /// Whether the host takes `message` from connection `id`: only a `Hello` before
+    /// the handshake, and after it only edits and news about the peer's own site.
+    fn admits(&self, id: usize, message: &Message) -> bool {
+        let Some(connection) = self.connections.get(&id) else {
+            return false;
+        };
+
+        match (connection.site, message) {
+            (None, Message::Hello { .. }) => true,
+            (None, _) | (Some(_), Message::Hello { .. }) => false,
+            (Some(site), Message::Op(crate::collab::Op::Insert { id, .. })) => id.site == site,
+            (Some(_), Message::Op(crate::collab::Op::Delete { .. })) => true,
+            (Some(site), Message::Cursor { site: about, .. } | Message::Bye { site: about }) => {
+                *about == site
+            }
+            (Some(_), Message::Peer { .. } | Message::Welcome { .. }) => false,
+        }
+    }
+
+    /// Lets the peer on connection `id` in: it gets a site, the text as it is now
+    /// and everyone's whereabouts, and everyone else learns about it.
+    fn welcome(&mut self, doc: &mut crate::document::Document, id: usize, name: String) {
+        // Everyone else has to be up to date with the snapshot too.
+        self.flush(doc);
+
+        let site = self.next_site;
+        let mut introductions = vec![
+            Message::Welcome {
+                site,
+                snapshot: self.collab.replica.snapshot(),
+            },
+            Message::Peer {
+                site: self.site(),
+                name: self.name.clone(),
+            },
+            Message::Cursor {
+                site: self.site(),
+                anchor: self.sent_anchor.flatten(),
+            },
+        ];
+
+        for (peer_site, peer) in &self.peers {
+            introductions.push(Message::Peer {
+                site: *peer_site,
+                name: peer.name.clone(),
+            });
+            introductions.push(Message::Cursor {
+                site: *peer_site,
+                anchor: peer.anchor,
+            });
+        }
+
+        let Some(connection) = self.connections.get_mut(&id) else {
+            return;
+        };
+
+        for message in &introductions {
+            connection.send(message);
+        }
+        connection.site = Some(site);
+        self.next_site += 1;
+        self.broadcast(
+            &Message::Peer {
+                site,
+                name: name.clone(),
+            },
+            Some(id),
+        );
+        self.peers.insert(site, Peer { name, anchor: None });
+    }
+
+    /// Everyone else in the session and where their cursors are now.
+    pub fn peers(&self) -> Vec<PeerCursor> {
+        self.peers
+            .iter()
+            .map(|(site, peer)| PeerCursor {
+                site: *site,
+                name: peer.name.clone(),
+                pos: self.collab.replica.resolve_anchor(peer.anchor),
+            })
+            .collect()
+    }
+
+    /// Who's in the session, for the status bar.
+    pub fn presence_label(&self) -> String {
+        let names: Vec<&str> = self.peers.values().map(|peer| peer.name.as_str()).collect();
+
+        match self.port() {
+            _ if !self.is_connected() => "Pair session ended".to_string(),
+            Some(port) if names.is_empty() => format!("Sharing on port {port}, no one joined yet"),
+            _ => format!("Sharing with {}", names.join(", ")),
+        }
+    }
+
+    /// Says goodbye and stops logging edits of `doc`.
+    pub fn leave(mut self, doc: &mut crate::document::Document) {
+        self.flush(doc);
+        self.broadcast(&Message::Bye { site: self.site() }, None);
+        doc.edit_log = None;
+    }
+}
+
+impl Drop for PairSession {
+    /// The connections close themselves once their writers are done.
+    fn drop(&mut self) {
+        self.closed
+            .store(true, std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::Document;
+    use editor_core::cursor::Cursor;
+    use editor_core::text::TextBuffer;
+
+    /// Polls both sides until `done` holds, failing after a few seconds.
+    fn settle(
+        (a, a_doc): (&mut PairSession, &mut Document),
+        (b, b_doc): (&mut PairSession, &mut Document),
+        done: impl Fn(&PairSession, &Document, &PairSession, &Document) -> bool,
+    ) {
+        for _ in 0..200 {
+            a.poll(a_doc);
+            b.poll(b_doc);
+            if done(a, a_doc, b, b_doc) {
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        panic!("Peers never settled");
+    }
+
+    #[test]
+    fn test_message_encoding_round_trips() {
+        let messages = [
+            Message::Hello {
+                token: "c0de".to_string(),
+                name: "ada".to_string(),
+            },
+            Message::Welcome {
+                site: 2,
+                snapshot: crate::collab::Replica::new(1, "a\tb").snapshot(),
+            },
+            Message::Peer {
+                site: 3,
+                name: "grace hopper".to_string(),
+            },
+            Message::Bye { site: 3 },
+            Message::Cursor {
+                site: 1,
+                anchor: Some(crate::collab::CharId { clock: 4, site: 0 }),
+            },
+            Message::Cursor {
+                site: 1,
+                anchor: None,
+            },
+        ];
+
+        for message in messages {
+            assert_eq!(Message::decode(&message.encode()), Some(message));
+        }
+    }
+
+    #[test]
+    fn test_host_and_join_over_loopback() {
+        let mut host_doc = Document::new(TextBuffer::new_with_text("shared\nnote").unwrap());
+        let mut guest_doc = Document::new(TextBuffer::new_with_text("something else").unwrap());
+
+        // Edits made before anyone joined are part of the snapshot.
+        host_doc.cursor = Cursor::new(1, 4);
+        let mut host = PairSession::host(&mut host_doc, "127.0.0.1:0", "host").unwrap();
+        host_doc.insert("s");
+        assert!(!host.is_public());
+
+        // Without the code the host hangs up.
+        let addr = format!("127.0.0.1:{}", host.port().unwrap());
+        let intruding = std::thread::spawn({
+            let addr = addr.clone();
+            move || {
+                let mut doc = Document::new(TextBuffer::new_with_text("").unwrap());
+                PairSession::join(&mut doc, &addr, "guess", "intruder").is_err()
+            }
+        });
+        while !intruding.is_finished() {
+            host.poll(&mut host_doc);
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(intruding.join().unwrap());
+        assert!(host.peers().is_empty());
+
+        // Nor does it read much from a peer before it shows the code.
+        let mut flooding = std::net::TcpStream::connect(&addr).unwrap();
+        flooding
+            .set_read_timeout(Some(std::time::Duration::from_secs(3)))
+            .unwrap();
+        let _ = std::io::Write::write_all(&mut flooding, &[b'x'; MAX_HELLO * 2]);
+        let hung_up = match std::io::Read::read(&mut flooding, &mut [0; 1]) {
+            Ok(read) => read == 0,
+            Err(err) => !matches!(
+                err.kind(),
+                std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+            ),
+        };
+        assert!(hung_up);
+
+        let token = host.token().unwrap().to_string();
+        let joining = std::thread::spawn(move || {
+            let guest = PairSession::join(&mut guest_doc, &addr, &token, "guest").unwrap();
+            guest_doc.cursor = Cursor::new(0, 6);
+            guest_doc.insert("!");
+            (guest, guest_doc)
+        });
+
+        // The host answers the join from its poll loop.
+        while !joining.is_finished() {
+            host.poll(&mut host_doc);
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        let (mut guest, mut guest_doc) = joining.join().unwrap();
+
+        settle(
+            (&mut host, &mut host_doc),
+            (&mut guest, &mut guest_doc),
+            |host, host_doc, guest, guest_doc| {
+                host_doc.text_buffer.to_string() == guest_doc.text_buffer.to_string()
+                    && host.peers().first().map(|peer| peer.pos)
+                        == Some(editor_core::cursor::Position::new(0, 7))
+                    && !guest.peers().is_empty()
+            },
+        );
+
+        assert_eq!(host_doc.text_buffer.to_string(), "shared!\nnotes");
+        assert_eq!(host.presence_label(), "Sharing with guest");
+        assert_eq!(guest.presence_label(), "Sharing with host");
+        assert_eq!(
+            guest.peers()[0].pos,
+            editor_core::cursor::Position::new(1, 5)
+        );
+
+        guest.leave(&mut guest_doc);
+        for _ in 0..200 {
+            if host.peers().is_empty() {
+                break;
+            }
+            host.poll(&mut host_doc);
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        assert!(host.peers().is_empty());
+        assert!(guest_doc.edit_log.is_none());
+    }
+}
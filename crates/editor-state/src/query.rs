@@ -0,0 +1,271 @@
+/// One condition on a note.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    /// Text anywhere in the note: a word, or a `"quoted phrase"` kept whole.
+    Text(String),
+    /// `tag:name` or `#name`.
+    Tag(String),
+    /// `path:part`, matched against the note's path.
+    Path(String),
+    /// `before:2024-07-01`, last modified before that day.
+    Before(crate::reminders::Date),
+    /// `after:2024-07-01`, last modified after that day.
+    After(crate::reminders::Date),
+}
+
+/// A parsed search, see [`Query::parse`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Query {
+    Filter(Filter),
+    Not(Box<Query>),
+    And(Vec<Query>),
+    Or(Vec<Query>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    /// A term, with `quoted` set if any of it was in quotes.
+    Term {
+        text: String,
+        quoted: bool,
+    },
+}
+
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            _ if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' => {
+                chars.next();
+                tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            }
+            '-' => {
+                chars.next();
+                tokens.push(Token::Not);
+            }
+            _ => {
+                let mut term = String::new();
+                let mut quoted = false;
+
+                while let Some(&c) = chars.peek() {
+                    if c == '"' {
+                        chars.next();
+                        quoted = true;
+                        term.extend(chars.by_ref().take_while(|&c| c != '"'));
+                    } else if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    } else {
+                        chars.next();
+                        term.push(c);
+                    }
+                }
+
+                tokens.push(match term.as_str() {
+                    "AND" if !quoted => Token::And,
+                    "OR" if !quoted => Token::Or,
+                    _ => Token::Term { text: term, quoted },
+                });
+            }
+        }
+    }
+
+    tokens
+}
+
+/// Recursive descent over the tokens: `OR` binds loosest, then `AND` (also implied
+/// between neighbouring terms), then `-` and parentheses.
+struct Parser {
+    tokens: std::iter::Peekable<std::vec::IntoIter<Token>>,
+    /// `-`s and parentheses around the term being read.
+    depth: usize,
+    /// Set once they nest deeper than [`Parser::MAX_DEPTH`], which fails the search.
+    too_deep: bool,
+}
+
+impl Parser {
+    /// Deeper nesting than this is refused rather than recursed into.
+    const MAX_DEPTH: usize = 64;
+
+    fn or(&mut self) -> Option<Query> {
+        let mut any = vec![self.and()?];
+
+        while self.tokens.next_if_eq(&Token::Or).is_some() {
+            any.extend(self.and());
+        }
+        Some(if any.len() == 1 {
+            any.remove(0)
+        } else {
+            Query::Or(any)
+        })
+    }
+
+    fn and(&mut self) -> Option<Query> {
+        let mut all = Vec::new();
+
+        loop {
+            self.tokens.next_if_eq(&Token::And);
+            match self.tokens.peek() {
+                None | Some(Token::Or) | Some(Token::Close) => break,
+                _ => all.extend(self.unary()),
+            }
+        }
+        match all.len() {
+            0 => None,
+            1 => all.pop(),
+            _ => Some(Query::And(all)),
+        }
+    }
+
+    fn unary(&mut self) -> Option<Query> {
+        match self.tokens.next()? {
+            Token::Not => Some(Query::Not(Box::new(self.nested(Self::unary)?))),
+            Token::Open => {
+                let inner = self.nested(Self::or);
+
+                self.tokens.next_if_eq(&Token::Close);
+                inner
+            }
+            Token::Term { text, quoted } => Some(Query::Filter(filter(&text, quoted)?)),
+            // A stray `)` or operator.
+            _ => None,
+        }
+    }
+
+    /// Reads what a `-` or parenthesis holds, one level deeper.
+    fn nested(&mut self, read: impl FnOnce(&mut Self) -> Option<Query>) -> Option<Query> {
+        if self.depth == Self::MAX_DEPTH {
+            self.too_deep = true;
+            // The search fails anyway; nothing more is worth reading.
+            self.tokens.by_ref().for_each(drop);
+            return None;
+        }
+
+        self.depth += 1;
+        let read = read(self);
+        self.depth -= 1;
+        read
+    }
+}
+
+fn filter(term: &str, quoted: bool) -> Option<Filter> {
+    if let Some(tag) = term
+        .strip_prefix('#')
+        .filter(|tag| !tag.is_empty() && !quoted)
+    {
+        return Some(Filter::Tag(tag.to_lowercase()));
+    }
+
+    let keyed = term.split_once(':').and_then(|(key, value)| {
+        Some(match key.to_lowercase().as_str() {
+            "tag" => Filter::Tag(value.trim_start_matches('#').to_lowercase()),
+            "path" => Filter::Path(value.to_lowercase()),
+            "before" => Filter::Before(crate::reminders::Date::parse_iso(value)?),
+            "after" => Filter::After(crate::reminders::Date::parse_iso(value)?),
+            _ => return None,
+        })
+    });
+
+    match keyed {
+        Some(filter) => Some(filter),
+        None if term.is_empty() => None,
+        None => Some(Filter::Text(term.to_lowercase())),
+    }
+}
+
+impl Query {
+    /// Reads a search such as `"road trip" tag:travel OR path:journal after:2024-01-01
+    /// -draft`. Neighbouring terms must all match, `OR` offers alternatives and
+    /// parentheses group; `-` excludes. `#name` is short for `tag:name`. A `key:`
+    /// this doesn't know, or a bad date, is searched for as text. Returns `None`
+    /// for a search with nothing in it, or one nested too deeply.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut parser = Parser {
+            tokens: tokenize(text).into_iter().peekable(),
+            depth: 0,
+            too_deep: false,
+        };
+        let query = parser.or();
+
+        query.filter(|_| !parser.too_deep)
+    }
+
+    /// Whether `note` satisfies the query.
+    pub fn matches(&self, note: &crate::search::IndexedNote) -> bool {
+        match self {
+            Query::Filter(filter) => match filter {
+                Filter::Text(text) => note.lines.iter().any(|line| line.contains(text.as_str())),
+                Filter::Tag(tag) => note.tags.iter().any(|have| have == tag),
+                Filter::Path(part) => note
+                    .path
+                    .to_string_lossy()
+                    .replace('\\', "/")
+                    .to_lowercase()
+                    .contains(part.as_str()),
+                Filter::Before(date) => note.modified.is_some_and(|modified| modified < *date),
+                Filter::After(date) => note.modified.is_some_and(|modified| modified > *date),
+            },
+            Query::Not(inner) => !inner.matches(note),
+            Query::And(all) => all.iter().all(|query| query.matches(note)),
+            Query::Or(any) => any.iter().any(|query| query.matches(note)),
+        }
+    }
+
+    /// The texts a match is worth showing at, those not under a `-`.
+    pub fn texts(&self) -> Vec<&str> {
+        match self {
+            Query::Filter(Filter::Text(text)) => vec![text.as_str()],
+            Query::Filter(_) | Query::Not(_) => Vec::new(),
+            Query::And(queries) | Query::Or(queries) => {
+                queries.iter().flat_map(Query::texts).collect()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let text = |t: &str| Query::Filter(Filter::Text(t.into()));
+        let tag = |t: &str| Query::Filter(Filter::Tag(t.into()));
+
+        assert_eq!(
+            Query::parse(r#""Road Trip" #travel OR path:journal -draft"#),
+            Some(Query::Or(vec![
+                Query::And(vec![text("road trip"), tag("travel")]),
+                Query::And(vec![
+                    Query::Filter(Filter::Path("journal".into())),
+                    Query::Not(Box::new(text("draft"))),
+                ]),
+            ]))
+        );
+        assert_eq!(
+            Query::parse("(a OR b) AND tag:x before:2024-07-01 after:soon"),
+            Some(Query::And(vec![
+                Query::Or(vec![text("a"), text("b")]),
+                tag("x"),
+                Query::Filter(Filter::Before(
+                    crate::reminders::Date::parse_iso("2024-07-01").unwrap()
+                )),
+                text("after:soon"),
+            ]))
+        );
+        assert_eq!(Query::parse("  "), None);
+        assert_eq!(Query::parse(") OR"), None);
+        assert_eq!(Query::parse(&format!("{}a", "(".repeat(100_000))), None);
+        assert_eq!(Query::parse(&format!("{}a", "-".repeat(100_000))), None);
+        assert!(Query::parse(&format!("{}a", "(".repeat(10))).is_some());
+    }
+}
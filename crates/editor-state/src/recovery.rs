@@ -0,0 +1,117 @@
+/// `<config>/mynotes/recovery`, next to the session file.
+pub fn default_dir() -> Option<std::path::PathBuf> {
+    Some(crate::session::config_dir()?.join("recovery"))
+}
+
+/// Writes the contents of `doc` into `dir` if it has unsaved changes, named
+/// after the note and `stamp` so earlier recoveries are never overwritten.
+/// Returns the recovery file, or `None` if there was nothing to save.
+///
+/// # Errors
+///
+/// Returns an error if `dir` can't be created or the file can't be written.
+pub fn rescue(
+    doc: &crate::document::Document,
+    dir: &std::path::Path,
+    stamp: u64,
+) -> std::io::Result<Option<std::path::PathBuf>> {
+    if !doc.text_buffer.is_dirty() {
+        return Ok(None);
+    }
+
+    let path = doc.text_buffer.path();
+    let stem = path
+        .and_then(crate::workspace::note_name)
+        .unwrap_or("untitled");
+    let ext = path.and_then(std::path::Path::extension).map_or_else(
+        || "md".to_string(),
+        |ext| ext.to_string_lossy().into_owned(),
+    );
+    let text: String = (0..doc.get_line_count())
+        .filter_map(|row| doc.get_line(row))
+        .collect();
+
+    std::fs::create_dir_all(dir)?;
+
+    let mut target = dir.join(format!("{stem}-{stamp}.{ext}"));
+    let mut n = 1;
+
+    while target.exists() {
+        target = dir.join(format!("{stem}-{stamp}-{n}.{ext}"));
+        n += 1;
+    }
+
+    std::fs::write(&target, text)?;
+    Ok(Some(target))
+}
+
+/// Text of a crash report: what panicked, where, and which notes were rescued.
+pub fn crash_report(
+    message: &str,
+    backtrace: &str,
+    recovered: &[std::path::PathBuf],
+    pending: usize,
+) -> String {
+    let mut report = format!(
+        "MyNotes {} crashed.\n\n{message}\n\nRecovered notes:\n",
+        env!("CARGO_PKG_VERSION")
+    );
+
+    if recovered.is_empty() {
+        report.push_str("  (none)\n");
+    }
+    for path in recovered {
+        report.push_str(&format!("  {}\n", path.display()));
+    }
+    if pending > 0 {
+        report.push_str(&format!(
+            "  {pending} note(s) were in use and are saved once the panic unwinds.\n"
+        ));
+    }
+
+    report.push_str(&format!("\nBacktrace:\n{backtrace}\n"));
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rescue_only_dirty_documents() {
+        let dir = tempfile::tempdir().unwrap();
+        let note = dir.path().join("ideas.md");
+        std::fs::write(&note, "first\r\n").unwrap();
+
+        let mut doc =
+            crate::document::Document::new(editor_core::text::TextBuffer::open(&note).unwrap());
+        let recovery = dir.path().join("recovery");
+
+        assert_eq!(rescue(&doc, &recovery, 42).unwrap(), None);
+
+        doc.insert("new ");
+
+        let first = rescue(&doc, &recovery, 42).unwrap().unwrap();
+        let second = rescue(&doc, &recovery, 42).unwrap().unwrap();
+
+        assert_eq!(first, recovery.join("ideas-42.md"));
+        assert_eq!(second, recovery.join("ideas-42-1.md"));
+        assert_eq!(std::fs::read_to_string(first).unwrap(), "new first\r\n");
+        assert_eq!(std::fs::read_to_string(&note).unwrap(), "first\r\n");
+    }
+
+    #[test]
+    fn test_crash_report() {
+        let report = crash_report(
+            "panicked at src/lib.rs:1:1:\nboom",
+            "<backtrace>",
+            &[std::path::PathBuf::from("/r/a-1.md")],
+            1,
+        );
+
+        assert!(report.contains("boom"));
+        assert!(report.contains("  /r/a-1.md\n"));
+        assert!(report.contains("1 note(s) were in use"));
+        assert!(report.ends_with("<backtrace>\n"));
+    }
+}
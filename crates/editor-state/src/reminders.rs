@@ -0,0 +1,271 @@
+/// A calendar day, without time zone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    pub year: i32,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl Date {
+    /// The day `time` falls on, in UTC.
+    pub fn from_time(time: std::time::SystemTime) -> Self {
+        let secs = time
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+
+        Self::from_days((secs / 86_400) as i64)
+    }
+
+    /// The day `days` after 1970-01-01, after Howard Hinnant's `civil_from_days`.
+    pub fn from_days(days: i64) -> Self {
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+        Self {
+            year: year as i32,
+            month: month as u8,
+            day: day as u8,
+        }
+    }
+
+    /// Days since 1970-01-01, the inverse of [`Date::from_days`].
+    pub fn days(self) -> i64 {
+        let month = i64::from(self.month);
+        let year = i64::from(self.year) - i64::from(month <= 2);
+        let era = year.div_euclid(400);
+        let year_of_era = year.rem_euclid(400);
+        let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5
+            + i64::from(self.day)
+            - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+        era * 146_097 + day_of_era - 719_468
+    }
+
+    pub fn add_days(self, days: i64) -> Self {
+        Self::from_days(self.days() + days)
+    }
+
+    /// 0 for Monday through 6 for Sunday.
+    pub fn weekday(self) -> u8 {
+        // 1970-01-01 was a Thursday.
+        (self.days() + 3).rem_euclid(7) as u8
+    }
+
+    /// Parses `YYYY-MM-DD`, rejecting days that don't exist.
+    pub fn parse_iso(text: &str) -> Option<Self> {
+        let mut parts = text.splitn(3, '-');
+        let year: i32 = parts.next()?.parse().ok()?;
+        let month: u8 = parts.next()?.parse().ok()?;
+        let day: u8 = parts.next()?.parse().ok()?;
+        let date = Self { year, month, day };
+
+        // Out-of-range days roll over into the next month on the way through days.
+        (date.month >= 1 && Self::from_days(date.days()) == date).then_some(date)
+    }
+}
+
+impl std::fmt::Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+const WEEKDAYS: [&str; 7] = [
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+];
+
+/// Reads a due date: `2024-07-01`, `today`, `tomorrow`, a weekday (`friday`,
+/// `next fri`, the first one after `reference`), `next week` or `in 3 days` /
+/// `in 2 weeks`. Relative dates count from `reference`, the day they were written.
+pub fn parse_date(text: &str, reference: Date) -> Option<Date> {
+    let text = text.trim().to_lowercase();
+    let text = text
+        .strip_prefix("on ")
+        .or_else(|| text.strip_prefix("next "))
+        .unwrap_or(&text)
+        .trim();
+
+    if let Some(date) = Date::parse_iso(text) {
+        return Some(date);
+    }
+
+    match text {
+        "today" => return Some(reference),
+        "tomorrow" => return Some(reference.add_days(1)),
+        "week" => return Some(reference.add_days(7)),
+        _ => {}
+    }
+
+    if let Some(weekday) = WEEKDAYS
+        .iter()
+        .position(|name| text.len() >= 3 && name.starts_with(text))
+    {
+        let ahead = (weekday as i64 - i64::from(reference.weekday())).rem_euclid(7);
+
+        return Some(reference.add_days(if ahead == 0 { 7 } else { ahead }));
+    }
+
+    let mut words = text.strip_prefix("in ")?.split_whitespace();
+    let count: i64 = words.next()?.parse().ok()?;
+    let unit = words.next()?;
+
+    if words.next().is_some() {
+        return None;
+    }
+    match unit {
+        "day" | "days" => Some(reference.add_days(count)),
+        "week" | "weeks" => Some(reference.add_days(count * 7)),
+        _ => None,
+    }
+}
+
+/// Something due on a day, found on one line of a note.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reminder {
+    pub path: std::path::PathBuf,
+    pub row: usize,
+    pub due: Date,
+    /// The line, trimmed, without its list marker.
+    pub text: String,
+}
+
+/// The due date on `line`, if it has one: `@due(...)` anywhere, or `due ...` /
+/// `due: ...` running to the end of the line. Ticked tasks (`- [x]`) have none.
+fn due_on(line: &str, reference: Date) -> Option<Date> {
+    let lower = line.to_lowercase();
+
+    if lower.trim_start().starts_with("- [x]") || lower.trim_start().starts_with("* [x]") {
+        return None;
+    }
+
+    if let Some(at) = lower.find("@due(") {
+        let rest = &lower[at + "@due(".len()..];
+
+        return parse_date(&rest[..rest.find(')')?], reference);
+    }
+
+    lower.match_indices("due").find_map(|(at, _)| {
+        let word_start = !lower[..at]
+            .chars()
+            .next_back()
+            .is_some_and(char::is_alphanumeric);
+        let rest = &lower[at + "due".len()..];
+        let rest = rest.strip_prefix(':').unwrap_or(rest);
+
+        if !word_start || !rest.starts_with(' ') {
+            return None;
+        }
+        parse_date(rest.trim_end_matches(['.', '!']), reference)
+    })
+}
+
+/// The reminders in `text`, the contents of the note at `path` as of `reference`.
+pub fn scan(path: &std::path::Path, text: &str, reference: Date) -> Vec<Reminder> {
+    text.lines()
+        .enumerate()
+        .filter_map(|(row, line)| {
+            let due = due_on(line, reference)?;
+            let text = line
+                .trim()
+                .trim_start_matches(['-', '*', '+'])
+                .trim_start()
+                .trim_start_matches("[ ]")
+                .trim()
+                .to_string();
+
+            Some(Reminder {
+                path: path.to_path_buf(),
+                row,
+                due,
+                text,
+            })
+        })
+        .collect()
+}
+
+/// Every reminder in the notes under `root`, soonest first, see
+/// [`crate::workspace::listed_notes`]. Relative dates count from the day each note
+/// was last modified.
+pub fn index(root: &std::path::Path, include_archived: bool) -> Vec<Reminder> {
+    let mut reminders: Vec<Reminder> = crate::workspace::listed_notes(root, include_archived)
+        .into_iter()
+        .filter_map(|path| {
+            let text = std::fs::read_to_string(&path).ok()?;
+            let modified = std::fs::metadata(&path).and_then(|meta| meta.modified());
+            let reference =
+                Date::from_time(modified.unwrap_or_else(|_| std::time::SystemTime::now()));
+
+            Some(scan(&path, &text, reference))
+        })
+        .flatten()
+        .collect();
+
+    reminders.sort_by(|a, b| (a.due, &a.path, a.row).cmp(&(b.due, &b.path, b.row)));
+    reminders
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dates() {
+        let monday = Date::parse_iso("2024-07-01").unwrap();
+
+        assert_eq!(monday.weekday(), 0);
+        assert_eq!(Date::from_days(monday.days()), monday);
+        assert_eq!(Date::parse_iso("2024-02-30"), None);
+        assert_eq!(
+            parse_date("tomorrow", monday).unwrap().to_string(),
+            "2024-07-02"
+        );
+        assert_eq!(
+            parse_date("Friday", monday).unwrap().to_string(),
+            "2024-07-05"
+        );
+        assert_eq!(
+            parse_date("next mon", monday).unwrap().to_string(),
+            "2024-07-08"
+        );
+        assert_eq!(
+            parse_date("in 2 weeks", monday).unwrap().to_string(),
+            "2024-07-15"
+        );
+        assert_eq!(parse_date("someday", monday), None);
+    }
+
+    #[test]
+    fn test_scan() {
+        let monday = Date::parse_iso("2024-07-01").unwrap();
+        let path = std::path::Path::new("plan.md");
+        let text = "- [ ] taxes @due(2024-07-15)\n- [x] done @due(today)\nPay rent due friday.\nOverdue thoughts\n";
+
+        let found: Vec<(usize, String, String)> = scan(path, text, monday)
+            .into_iter()
+            .map(|reminder| (reminder.row, reminder.due.to_string(), reminder.text))
+            .collect();
+
+        assert_eq!(
+            found,
+            vec![
+                (0, "2024-07-15".into(), "taxes @due(2024-07-15)".into()),
+                (2, "2024-07-05".into(), "Pay rent due friday.".into()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,666 @@
+/// What to look for and what to put in its place.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Pattern {
+    pub find: String,
+    pub replace: String,
+    pub case_sensitive: bool,
+    /// `find` is a regular expression and `replace` a [`Template`].
+    pub regex: bool,
+}
+
+impl Pattern {
+    fn regex(&self) -> Result<regex::Regex, String> {
+        if self.find.is_empty() {
+            return Err("Nothing to find".to_string());
+        }
+
+        let find = if self.regex {
+            self.find.clone()
+        } else {
+            regex::escape(&self.find)
+        };
+
+        regex::RegexBuilder::new(&find)
+            .case_insensitive(!self.case_sensitive)
+            .build()
+            .map_err(|err| err.to_string())
+    }
+
+    fn template(&self, regex: &regex::Regex) -> Result<Template, String> {
+        if !self.regex {
+            return Ok(Template(vec![Piece::Text(self.replace.clone())]));
+        }
+
+        let template = Template::parse(&self.replace)?;
+
+        template.check(regex)?;
+        Ok(template)
+    }
+}
+
+/// Changes the case of what follows it in a [`Template`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CaseOp {
+    /// `\U`: upper case until `\E` or another of these.
+    Upper,
+    /// `\L`: lower case until `\E` or another of these.
+    Lower,
+    /// `\E`: as it is again.
+    End,
+    /// `\u`: the next character in upper case.
+    UpperNext,
+    /// `\l`: the next character in lower case.
+    LowerNext,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Text(String),
+    /// `$1`, `${1}` or `$0` for the whole match.
+    Group(usize),
+    /// `${name}`.
+    Named(String),
+    Case(CaseOp),
+}
+
+/// A regex replacement: text with `$1`, `${1}` or `${name}` standing for what a
+/// group matched (`$0` for the whole match, `$$` for a `$`), and `\U`, `\L`,
+/// `\E`, `\u` and `\l` changing the case of what follows, e.g. `\u$2 $1`.
+/// `\n`, `\t` and `\\` are a line break, tab and backslash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template(Vec<Piece>);
+
+impl Template {
+    /// # Errors
+    ///
+    /// Returns an error for a `${` without its `}`.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut chars = text.chars().peekable();
+        fn push(pieces: &mut Vec<Piece>, literal: &mut String, piece: Piece) {
+            if !literal.is_empty() {
+                pieces.push(Piece::Text(std::mem::take(literal)));
+            }
+            pieces.push(piece);
+        }
+
+        while let Some(c) = chars.next() {
+            match c {
+                '$' => match chars.peek() {
+                    Some('$') => {
+                        chars.next();
+                        literal.push('$');
+                    }
+                    Some('{') => {
+                        chars.next();
+
+                        let mut name = String::new();
+
+                        loop {
+                            match chars.next() {
+                                Some('}') => break,
+                                Some(c) => name.push(c),
+                                None => return Err("Missing } after ${".to_string()),
+                            }
+                        }
+                        let piece = match name.parse() {
+                            Ok(group) => Piece::Group(group),
+                            Err(_) => Piece::Named(name),
+                        };
+                        push(&mut pieces, &mut literal, piece);
+                    }
+                    Some(d) if d.is_ascii_digit() => {
+                        let mut group = 0;
+
+                        while let Some(d) = chars.peek().and_then(|c| c.to_digit(10)) {
+                            group = group * 10 + d as usize;
+                            chars.next();
+                        }
+                        push(&mut pieces, &mut literal, Piece::Group(group));
+                    }
+                    _ => literal.push('$'),
+                },
+                '\\' => {
+                    let op = match chars.peek() {
+                        Some('U') => Some(CaseOp::Upper),
+                        Some('L') => Some(CaseOp::Lower),
+                        Some('E') => Some(CaseOp::End),
+                        Some('u') => Some(CaseOp::UpperNext),
+                        Some('l') => Some(CaseOp::LowerNext),
+                        _ => None,
+                    };
+
+                    if let Some(op) = op {
+                        chars.next();
+                        push(&mut pieces, &mut literal, Piece::Case(op));
+                        continue;
+                    }
+                    match chars.next() {
+                        Some('n') => literal.push('\n'),
+                        Some('t') => literal.push('\t'),
+                        Some(other) => literal.push(other),
+                        None => literal.push('\\'),
+                    }
+                }
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Text(literal));
+        }
+
+        Ok(Self(pieces))
+    }
+
+    /// Whether every group referred to is one of `regex`'s.
+    fn check(&self, regex: &regex::Regex) -> Result<(), String> {
+        for piece in &self.0 {
+            match piece {
+                Piece::Group(group) if *group >= regex.captures_len() => {
+                    return Err(format!("There's no group {group}"));
+                }
+                Piece::Named(name) if !regex.capture_names().flatten().any(|have| have == name) => {
+                    return Err(format!("There's no group named {name}"));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// The replacement for a match with `captures`. A group that didn't take
+    /// part in the match stands for nothing.
+    pub fn expand(&self, captures: &regex::Captures) -> String {
+        let mut out = String::new();
+        let mut case = CaseOp::End;
+        let mut next: Option<CaseOp> = None;
+        let mut emit = |text: &str, case: CaseOp, next: &mut Option<CaseOp>| {
+            let mut chars = text.chars();
+
+            if let Some(op) = next.take_if(|_| !text.is_empty())
+                && let Some(first) = chars.next()
+            {
+                match op {
+                    CaseOp::UpperNext => out.extend(first.to_uppercase()),
+                    _ => out.extend(first.to_lowercase()),
+                }
+            }
+            for c in chars {
+                match case {
+                    CaseOp::Upper => out.extend(c.to_uppercase()),
+                    CaseOp::Lower => out.extend(c.to_lowercase()),
+                    _ => out.push(c),
+                }
+            }
+        };
+
+        for piece in &self.0 {
+            match piece {
+                Piece::Text(text) => emit(text, case, &mut next),
+                Piece::Group(group) => emit(
+                    captures.get(*group).map_or("", |found| found.as_str()),
+                    case,
+                    &mut next,
+                ),
+                Piece::Named(name) => emit(
+                    captures.name(name).map_or("", |found| found.as_str()),
+                    case,
+                    &mut next,
+                ),
+                Piece::Case(op @ (CaseOp::UpperNext | CaseOp::LowerNext)) => next = Some(*op),
+                Piece::Case(op) => case = *op,
+            }
+        }
+
+        out
+    }
+}
+
+/// One occurrence of a pattern: where it is on its row, in bytes, and the text
+/// it's to be replaced with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    pub row: usize,
+    pub start: usize,
+    pub end: usize,
+    pub replacement: String,
+}
+
+/// Every occurrence of `pattern` in `lines`, without their line breaks. Matches
+/// don't span lines.
+///
+/// # Errors
+///
+/// Returns an error if there's nothing to find, or the regular expression or
+/// replacement template is invalid.
+pub fn find_in_lines<S: AsRef<str>>(pattern: &Pattern, lines: &[S]) -> Result<Vec<Match>, String> {
+    let regex = pattern.regex()?;
+    let template = pattern.template(&regex)?;
+
+    Ok(lines
+        .iter()
+        .enumerate()
+        .flat_map(|(row, line)| {
+            regex
+                .captures_iter(line.as_ref())
+                .filter_map(|captures| {
+                    let found = captures.get(0).filter(|found| !found.is_empty())?;
+
+                    Some(Match {
+                        row,
+                        start: found.start(),
+                        end: found.end(),
+                        replacement: template.expand(&captures),
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect())
+}
+
+/// Those of `matches` wholly between `start` and `end`, e.g. in a selection.
+pub fn within(
+    matches: Vec<Match>,
+    start: editor_core::cursor::Position,
+    end: editor_core::cursor::Position,
+) -> Vec<Match> {
+    matches
+        .into_iter()
+        .filter(|found| {
+            editor_core::cursor::Position::new(found.row, found.start) >= start
+                && editor_core::cursor::Position::new(found.row, found.end) <= end
+        })
+        .collect()
+}
+
+/// `line` with those of `matches` on it replaced.
+fn replace_line<'a>(line: &str, matches: impl Iterator<Item = &'a Match>) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut at = 0;
+
+    for found in matches {
+        out.push_str(&line[at..found.start]);
+        out.push_str(&found.replacement);
+        at = found.end;
+    }
+    out.push_str(&line[at..]);
+
+    out
+}
+
+/// The rows of the first `limit` of `matches`, in `lines`, before and after
+/// their matches are replaced: `(row, before, after)`.
+pub fn preview<S: AsRef<str>>(
+    lines: &[S],
+    matches: &[Match],
+    limit: usize,
+) -> Vec<(usize, String, String)> {
+    let mut rows: Vec<usize> = matches.iter().take(limit).map(|found| found.row).collect();
+
+    rows.dedup();
+    rows.into_iter()
+        .map(|row| {
+            let line = lines[row].as_ref();
+            let on_row = matches.iter().filter(|found| found.row == row);
+
+            (row, line.to_string(), replace_line(line, on_row))
+        })
+        .collect()
+}
+
+/// `text` with `matches`, found in its lines, replaced. Line endings are kept as
+/// they are.
+pub fn replace_in_text(text: &str, matches: &[Match]) -> String {
+    let mut out = String::with_capacity(text.len());
+
+    for (row, line) in text.split_inclusive('\n').enumerate() {
+        let content = line.trim_end_matches(['\n', '\r']);
+
+        out.push_str(&replace_line(
+            content,
+            matches.iter().filter(|found| found.row == row),
+        ));
+        out.push_str(&line[content.len()..]);
+    }
+
+    out
+}
+
+/// The occurrences found in one note, and the note's lines to show them with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileMatches {
+    pub path: std::path::PathBuf,
+    pub lines: Vec<String>,
+    pub matches: Vec<Match>,
+}
+
+/// The notes of `notes` holding `pattern`, with their occurrences. Notes that
+/// can't be read are left out.
+///
+/// # Errors
+///
+/// Returns an error if there's nothing to find, or the regular expression or
+/// replacement template is invalid.
+pub fn find_in_notes(
+    notes: &[std::path::PathBuf],
+    pattern: &Pattern,
+) -> Result<Vec<FileMatches>, String> {
+    pattern.template(&pattern.regex()?)?;
+
+    Ok(notes
+        .iter()
+        .filter_map(|path| {
+            let text = std::fs::read_to_string(path).ok()?;
+            let lines: Vec<String> = text
+                .lines()
+                .map(|line| line.trim_end_matches('\r').to_string())
+                .collect();
+            let matches = find_in_lines(pattern, &lines).ok()?;
+
+            (!matches.is_empty()).then(|| FileMatches {
+                path: path.clone(),
+                lines,
+                matches,
+            })
+        })
+        .collect())
+}
+
+/// A note rewritten by a workspace replace, with what it held before so the
+/// change can be taken back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileChange {
+    pub path: std::path::PathBuf,
+    pub before: String,
+    pub after: String,
+}
+
+impl FileChange {
+    /// The change that replaces the matches of `found` in its note.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the note can't be read, or changed since the matches
+    /// were found.
+    pub fn plan(found: &FileMatches) -> std::io::Result<Self> {
+        let before = std::fs::read_to_string(&found.path)?;
+
+        if !before
+            .lines()
+            .map(|line| line.trim_end_matches('\r'))
+            .eq(found.lines.iter().map(String::as_str))
+        {
+            return Err(std::io::Error::other(format!(
+                "{} changed since it was searched",
+                found.path.display()
+            )));
+        }
+
+        let after = replace_in_text(&before, &found.matches);
+
+        Ok(Self {
+            path: found.path.clone(),
+            before,
+            after,
+        })
+    }
+
+    /// Puts back what the note held before, unless it was changed again since.
+    /// Returns whether it was put back.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the note can't be read or written.
+    pub fn undo(&self) -> std::io::Result<bool> {
+        if std::fs::read_to_string(&self.path)? != self.after {
+            return Ok(false);
+        }
+
+        let mut batch = crate::workspace::BatchSave::default();
+
+        batch.write(&self.path, self.before.as_str());
+        batch.commit().map(|_| true)
+    }
+}
+
+/// Writes every change through one [`crate::workspace::BatchSave`], so either all
+/// notes are rewritten or none.
+///
+/// # Errors
+///
+/// Returns the first error hit, with the notes as they were.
+pub fn commit(changes: &[FileChange]) -> std::io::Result<()> {
+    let mut batch = crate::workspace::BatchSave::default();
+
+    for change in changes
+        .iter()
+        .filter(|change| change.before != change.after)
+    {
+        batch.write(&change.path, change.after.as_str());
+    }
+    batch.commit().map(|_| ())
+}
+
+/// Recently used find/replace pairs, newest first, and those pinned to stay.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReplaceHistory {
+    pub pinned: Vec<(String, String)>,
+    pub recent: Vec<(String, String)>,
+}
+
+impl ReplaceHistory {
+    /// Recent pairs kept besides the pinned ones.
+    pub const MAX_RECENT: usize = 20;
+
+    /// `replacements` next to the session store.
+    pub fn default_path() -> Option<std::path::PathBuf> {
+        Some(crate::session::config_dir()?.join("replacements"))
+    }
+
+    /// Loads a history saved by [`ReplaceHistory::save`]. A missing file has none,
+    /// and malformed lines are skipped.
+    pub fn load(path: &std::path::Path) -> Self {
+        let mut history = Self::default();
+
+        for line in std::fs::read_to_string(path).unwrap_or_default().lines() {
+            let mut fields = line.split('\t');
+            let (Some(kind), Some(find), Some(replace)) =
+                (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            let pair = (unescape(find), unescape(replace));
+
+            match kind {
+                "pin" => history.pinned.push(pair),
+                "recent" => history.recent.push(pair),
+                _ => {}
+            }
+        }
+
+        history
+    }
+
+    /// Writes one `pin` or `recent` line per pair, tab-separated.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be written.
+    pub fn save(&self, path: &std::path::Path) -> std::io::Result<()> {
+        let mut batch = crate::workspace::BatchSave::default();
+        let content: String = self
+            .pinned
+            .iter()
+            .map(|pair| ("pin", pair))
+            .chain(self.recent.iter().map(|pair| ("recent", pair)))
+            .map(|(kind, (find, replace))| {
+                format!("{kind}\t{}\t{}\n", escape(find), escape(replace))
+            })
+            .collect();
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        batch.write(path, content);
+        batch.commit().map(|_| ())
+    }
+
+    /// Puts the pair of `pattern` first among the recent ones, unless it's pinned.
+    pub fn record(&mut self, pattern: &Pattern) {
+        let pair = (pattern.find.clone(), pattern.replace.clone());
+
+        if pair.0.is_empty() || self.pinned.contains(&pair) {
+            return;
+        }
+        self.recent.retain(|have| *have != pair);
+        self.recent.insert(0, pair);
+        self.recent.truncate(Self::MAX_RECENT);
+    }
+
+    /// Pins `pair`, or unpins it if it already is, keeping it as a recent one.
+    pub fn toggle_pin(&mut self, pair: (String, String)) {
+        if let Some(i) = self.pinned.iter().position(|have| *have == pair) {
+            self.pinned.remove(i);
+            self.recent.insert(0, pair);
+            self.recent.truncate(Self::MAX_RECENT);
+        } else if !pair.0.is_empty() {
+            self.recent.retain(|have| *have != pair);
+            self.pinned.push(pair);
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_across_notes() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a.md");
+        let b = dir.path().join("b.md");
+
+        std::fs::write(&a, "Teh cat\r\nand teh dog\r\n").unwrap();
+        std::fs::write(&b, "nothing here\n").unwrap();
+
+        let pattern = Pattern {
+            find: "teh".into(),
+            replace: "the".into(),
+            case_sensitive: false,
+            regex: false,
+        };
+        let found = find_in_notes(&[a.clone(), b.clone()], &pattern).unwrap();
+
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].lines, ["Teh cat", "and teh dog"]);
+        assert_eq!(
+            found[0]
+                .matches
+                .iter()
+                .map(|m| (m.row, m.start))
+                .collect::<Vec<_>>(),
+            [(0, 0), (1, 4)]
+        );
+
+        // The first hit is left out.
+        let mut kept = found[0].clone();
+        kept.matches.remove(0);
+        let change = FileChange::plan(&kept).unwrap();
+
+        assert_eq!(change.after, "Teh cat\r\nand the dog\r\n");
+        commit(std::slice::from_ref(&change)).unwrap();
+        assert_eq!(std::fs::read_to_string(&a).unwrap(), change.after);
+
+        assert!(change.undo().unwrap());
+        assert_eq!(std::fs::read_to_string(&a).unwrap(), change.before);
+        assert!(!change.undo().unwrap(), "Already undone");
+        std::fs::write(&a, "Teh cat\n").unwrap();
+        assert!(FileChange::plan(&kept).is_err(), "Changed since");
+
+        let path = dir.path().join("replacements");
+        let mut history = ReplaceHistory::default();
+
+        history.record(&pattern);
+        history.record(&Pattern {
+            find: "a\tb\\n".into(),
+            ..Pattern::default()
+        });
+        history.toggle_pin(("teh".into(), "the".into()));
+        history.record(&pattern);
+        history.save(&path).unwrap();
+
+        let loaded = ReplaceHistory::load(&path);
+
+        assert_eq!(loaded, history);
+        assert_eq!(loaded.pinned, [("teh".to_string(), "the".to_string())]);
+        assert_eq!(loaded.recent, [("a\tb\\n".to_string(), String::new())]);
+        assert!(find_in_lines(&Pattern::default(), &["x"]).is_err());
+
+        let lines = ["let first_name = last_name;", "$x"];
+        let swap = Pattern {
+            find: r"(?<a>\w+)_(\w+)".into(),
+            replace: r"\u${a}\U$2\E$$".into(),
+            case_sensitive: true,
+            regex: true,
+        };
+        let matches = find_in_lines(&swap, &lines).unwrap();
+
+        assert_eq!(
+            preview(&lines, &matches, 1),
+            [(
+                0,
+                lines[0].to_string(),
+                "let FirstNAME$ = LastNAME$;".to_string()
+            )]
+        );
+        assert!(preview(&lines, &matches, 0).is_empty());
+        assert!(
+            find_in_lines(
+                &Pattern {
+                    replace: "$3".into(),
+                    ..swap.clone()
+                },
+                &lines
+            )
+            .is_err()
+        );
+        assert!(Template::parse("${1").is_err());
+
+        let literal = Pattern {
+            find: "$x".into(),
+            replace: "$1".into(),
+            ..Pattern::default()
+        };
+        assert_eq!(
+            find_in_lines(&literal, &lines).unwrap()[0].replacement,
+            "$1"
+        );
+    }
+}
@@ -0,0 +1,422 @@
+/// How the code blocks of one language are run: `program`, then `args`, then the
+/// path of a file holding the block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interpreter {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+impl Interpreter {
+    fn new(command: &str) -> Option<Self> {
+        let mut words = command.split_whitespace().map(str::to_string);
+
+        Some(Self {
+            program: words.next()?,
+            args: words.collect(),
+        })
+    }
+}
+
+/// Fence tags and the commands that run their blocks, unless overridden.
+const DEFAULT_INTERPRETERS: [(&str, &str); 10] = [
+    ("python", "python3"),
+    ("py", "python3"),
+    ("sh", "sh"),
+    ("shell", "sh"),
+    ("bash", "bash"),
+    ("zsh", "zsh"),
+    ("js", "node"),
+    ("javascript", "node"),
+    ("ruby", "ruby"),
+    ("rb", "ruby"),
+];
+
+/// The commands code blocks are run with, by fence tag.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interpreters(Vec<(String, Interpreter)>);
+
+impl Default for Interpreters {
+    fn default() -> Self {
+        Self(
+            DEFAULT_INTERPRETERS
+                .iter()
+                .filter_map(|&(tag, command)| Some((tag.to_string(), Interpreter::new(command)?)))
+                .collect(),
+        )
+    }
+}
+
+impl Interpreters {
+    /// `interpreters` next to the session store.
+    pub fn default_path() -> Option<std::path::PathBuf> {
+        Some(crate::session::config_dir()?.join("interpreters"))
+    }
+
+    /// The defaults, overridden by the `tag\tcommand` lines of `path`, e.g.
+    /// `python\tpython3 -I`. An empty command turns a language off. A missing file
+    /// changes nothing.
+    pub fn load(path: &std::path::Path) -> Self {
+        let mut interpreters = Self::default();
+
+        for line in std::fs::read_to_string(path).unwrap_or_default().lines() {
+            let Some((tag, command)) = line.split_once('\t') else {
+                continue;
+            };
+            let tag = tag.trim().to_lowercase();
+
+            interpreters.0.retain(|(have, _)| *have != tag);
+            if let Some(interpreter) = Interpreter::new(command) {
+                interpreters.0.push((tag, interpreter));
+            }
+        }
+
+        interpreters
+    }
+
+    /// The interpreter for the block opened by the fence line `fence`.
+    pub fn for_fence(&self, fence: &str) -> Option<&Interpreter> {
+        let tag = fence
+            .trim()
+            .trim_start_matches(['`', '~'])
+            .split_whitespace()
+            .next()?
+            .to_lowercase();
+
+        self.0
+            .iter()
+            .find(|(have, _)| *have == tag)
+            .map(|(_, interpreter)| interpreter)
+    }
+}
+
+/// Limits on a block being run. They keep a run from hanging or flooding the note,
+/// not from doing harm: the block runs with the user's own rights.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunLimits {
+    /// The block, and whatever it started, is killed after this long, including
+    /// when something it left running still holds its output open.
+    pub timeout: std::time::Duration,
+    /// Start the interpreter with only `PATH`, `HOME` and the locale set, rather
+    /// than everything the app was started with.
+    pub clean_env: bool,
+    /// Run in an empty directory of its own, removed afterwards, rather than the
+    /// note's folder.
+    pub scratch_dir: bool,
+    /// Output past this many bytes of each stream is dropped, see
+    /// [`RunOutput::truncated`].
+    pub max_output: usize,
+}
+
+impl Default for RunLimits {
+    fn default() -> Self {
+        Self {
+            timeout: std::time::Duration::from_secs(10),
+            clean_env: true,
+            scratch_dir: true,
+            max_output: 64 * 1024,
+        }
+    }
+}
+
+/// What running a block produced.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunOutput {
+    pub stdout: String,
+    pub stderr: String,
+    /// The exit code, `None` if it was killed.
+    pub status: Option<i32>,
+    pub timed_out: bool,
+    /// Some output was dropped for going past [`RunLimits::max_output`].
+    pub truncated: bool,
+}
+
+impl RunOutput {
+    /// The output as a ```` ```output ```` block to insert below the code: standard
+    /// output, then errors, then how it ended if not with success.
+    pub fn result_block(&self, timeout: std::time::Duration) -> Vec<String> {
+        let mut lines = vec![format!("```{RESULT_TAG}")];
+
+        lines.extend(self.stdout.lines().map(str::to_string));
+        lines.extend(self.stderr.lines().map(str::to_string));
+        if self.truncated {
+            lines.push("[output truncated]".to_string());
+        }
+        if self.timed_out {
+            lines.push(format!("[timed out after {}s]", timeout.as_secs()));
+        } else if let Some(code) = self.status.filter(|&code| code != 0) {
+            lines.push(format!("[exit {code}]"));
+        }
+        // A fence in the output would end the block early.
+        for line in &mut lines[1..] {
+            if line.trim_start().starts_with("```") {
+                line.insert(0, ' ');
+            }
+        }
+        lines.push("```".to_string());
+
+        lines
+    }
+}
+
+/// The tag of the blocks holding a run's output, replaced when the block above is
+/// run again.
+pub const RESULT_TAG: &str = "output";
+
+/// Runs `source` with `interpreter` within `limits`, from `dir` unless they ask
+/// for a scratch directory. Blocks until it ends or times out.
+///
+/// # Errors
+///
+/// Returns an error if the block can't be written out or the interpreter started.
+pub fn run(
+    interpreter: &Interpreter,
+    source: &str,
+    limits: RunLimits,
+    dir: Option<&std::path::Path>,
+) -> std::io::Result<RunOutput> {
+    let mut script = tempfile::NamedTempFile::new()?;
+    let scratch = if limits.scratch_dir {
+        Some(tempfile::tempdir()?)
+    } else {
+        None
+    };
+
+    std::io::Write::write_all(&mut script, source.as_bytes())?;
+
+    let mut command = std::process::Command::new(&interpreter.program);
+
+    command.args(&interpreter.args).arg(script.path());
+    if limits.clean_env {
+        command.env_clear();
+        for key in ["PATH", "HOME", "LANG", "LC_ALL", "SYSTEMROOT"] {
+            if let Some(value) = std::env::var_os(key) {
+                command.env(key, value);
+            }
+        }
+    }
+    if let Some(dir) = scratch.as_ref().map(tempfile::TempDir::path).or(dir) {
+        command.current_dir(dir);
+    }
+
+    capture(&mut command, None, limits)
+}
+
+/// Runs `command` within the time and output `limits`, writing `input` to its
+/// standard input if there's any. Blocks until it ends or times out.
+///
+/// # Errors
+///
+/// Returns an error if the command can't be started.
+pub(crate) fn capture(
+    command: &mut std::process::Command,
+    input: Option<String>,
+    limits: RunLimits,
+) -> std::io::Result<RunOutput> {
+    command
+        .stdin(if input.is_some() {
+            std::process::Stdio::piped()
+        } else {
+            std::process::Stdio::null()
+        })
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped());
+    // In a group of its own, so that what it starts is killed along with it.
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(command, 0);
+
+    let mut child = command.spawn()?;
+    // Written on the side too, as the command may not read it all before writing.
+    if let (Some(mut stdin), Some(input)) = (child.stdin.take(), input) {
+        std::thread::spawn(move || std::io::Write::write_all(&mut stdin, input.as_bytes()));
+    }
+    // Read on the side so a chatty block can't fill the pipe and stall, into
+    // buffers that can be taken as they are if it has to be killed.
+    let truncated = std::sync::Arc::new(std::sync::atomic::AtomicBool::new(false));
+    let read = |stream: Option<Box<dyn std::io::Read + Send>>| {
+        let bytes = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let reader = std::thread::spawn({
+            let bytes = bytes.clone();
+            let truncated = truncated.clone();
+            move || {
+                let Some(mut stream) = stream else {
+                    return;
+                };
+                let mut chunk = [0; 4096];
+
+                while let Ok(read @ 1..) = std::io::Read::read(&mut stream, &mut chunk) {
+                    let mut bytes = bytes.lock().unwrap_or_else(|err| err.into_inner());
+                    let room = limits.max_output.saturating_sub(bytes.len());
+
+                    bytes.extend_from_slice(&chunk[..read.min(room)]);
+                    if read > room {
+                        truncated.store(true, std::sync::atomic::Ordering::Relaxed);
+                    }
+                }
+            }
+        });
+
+        (bytes, reader)
+    };
+    let stdout = read(child.stdout.take().map(|s| Box::new(s) as _));
+    let stderr = read(child.stderr.take().map(|s| Box::new(s) as _));
+
+    let started = std::time::Instant::now();
+    let mut exited = None;
+    // Done once the output is closed too: something the block left running in
+    // the background may still be writing to it.
+    let timed_out = loop {
+        if exited.is_none() {
+            exited = child.try_wait()?;
+        }
+        if exited.is_some() && stdout.1.is_finished() && stderr.1.is_finished() {
+            break false;
+        }
+        if started.elapsed() >= limits.timeout {
+            kill(&mut child);
+            if exited.is_none() {
+                let _ = child.wait();
+            }
+            break true;
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    };
+    let status = exited.and_then(|status| status.code());
+
+    // Whatever the block started may still hold the pipes open after a kill.
+    let take = |(bytes, reader): (
+        std::sync::Arc<std::sync::Mutex<Vec<u8>>>,
+        std::thread::JoinHandle<()>,
+    )| {
+        if !timed_out {
+            let _ = reader.join();
+        }
+        let bytes = bytes.lock().unwrap_or_else(|err| err.into_inner());
+
+        String::from_utf8_lossy(&bytes).into_owned()
+    };
+
+    Ok(RunOutput {
+        stdout: take(stdout),
+        stderr: take(stderr),
+        status,
+        timed_out,
+        truncated: truncated.load(std::sync::atomic::Ordering::Relaxed),
+    })
+}
+
+/// Kills `child` and, on Unix, every process in its group.
+fn kill(child: &mut std::process::Child) {
+    #[cfg(unix)]
+    if let Ok(group) = libc::pid_t::try_from(child.id()) {
+        // SAFETY: `kill` only sends a signal; the group is the child's own, as
+        // `capture` started it in one.
+        unsafe {
+            libc::kill(-group, libc::SIGKILL);
+        }
+    }
+    let _ = child.kill();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_interpreters_and_result_block() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("interpreters");
+
+        std::fs::write(&path, "python\tpython3 -I\nruby\t\nlua\tlua5.4\n").unwrap();
+
+        let interpreters = Interpreters::load(&path);
+
+        assert_eq!(
+            interpreters.for_fence("```Python title"),
+            Some(&Interpreter {
+                program: "python3".into(),
+                args: vec!["-I".into()],
+            })
+        );
+        assert_eq!(interpreters.for_fence("```ruby"), None);
+        assert!(interpreters.for_fence("~~~lua").is_some());
+        assert_eq!(interpreters.for_fence("```"), None);
+
+        let output = RunOutput {
+            stdout: "hi\n```\n".into(),
+            stderr: "oops\n".into(),
+            status: Some(2),
+            timed_out: false,
+            truncated: true,
+        };
+
+        assert_eq!(
+            output.result_block(RunLimits::default().timeout),
+            vec![
+                "```output",
+                "hi",
+                " ```",
+                "oops",
+                "[output truncated]",
+                "[exit 2]",
+                "```"
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_captures_output_and_times_out() {
+        let sh = Interpreter::new("sh").unwrap();
+        let output = run(
+            &sh,
+            "echo out; echo err >&2; exit 3",
+            RunLimits::default(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(
+            (output.stdout.as_str(), output.stderr.as_str()),
+            ("out\n", "err\n")
+        );
+        assert_eq!(output.status, Some(3));
+        assert!(!output.truncated);
+
+        let small = RunLimits {
+            max_output: 1000,
+            ..RunLimits::default()
+        };
+        let output = run(&sh, "yes | head -c 5000", small, None).unwrap();
+
+        assert_eq!(output.stdout.len(), 1000);
+        assert!(output.truncated);
+
+        let limits = RunLimits {
+            timeout: std::time::Duration::from_millis(100),
+            ..RunLimits::default()
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let late = dir.path().join("late");
+        let output = run(
+            &sh,
+            &format!("(sleep 1; touch '{}') & sleep 5", late.display()),
+            limits,
+            None,
+        )
+        .unwrap();
+
+        assert!(output.timed_out);
+        // What the block started in the background was killed along with it.
+        std::thread::sleep(std::time::Duration::from_millis(1500));
+        assert!(!late.exists());
+
+        // Even once the block itself is done, if what it left running holds the
+        // output open.
+        let started = std::time::Instant::now();
+        let output = run(&sh, "sleep 5 & echo hi", limits, None).unwrap();
+
+        assert!(output.timed_out);
+        assert_eq!(output.stdout, "hi\n");
+        assert!(started.elapsed() < std::time::Duration::from_secs(2));
+    }
+}
@@ -0,0 +1,412 @@
+/// How long a save may take before it's treated as a dead network drive.
+pub const DEFAULT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// `<config>/mynotes/pending`, next to the session file.
+pub fn default_dir() -> Option<std::path::PathBuf> {
+    Some(crate::session::config_dir()?.join("pending"))
+}
+
+/// Whether `err` means the file handle went stale under us, as NFS reports when the
+/// file was replaced or the export was remounted on the server.
+pub fn is_stale_handle(err: &std::io::Error) -> bool {
+    let code = err.raw_os_error();
+
+    if cfg!(target_os = "linux") {
+        code == Some(116) // ESTALE
+    } else if cfg!(target_os = "macos") {
+        code == Some(70) // ESTALE
+    } else if cfg!(windows) {
+        // ERROR_BAD_NETPATH, ERROR_UNEXP_NET_ERR, ERROR_NETNAME_DELETED
+        matches!(code, Some(53 | 59 | 64))
+    } else {
+        false
+    }
+}
+
+/// Whether `err` looks like the drive being unreachable rather than the write being
+/// refused, so trying again later may succeed.
+pub fn is_network_failure(err: &std::io::Error) -> bool {
+    is_stale_handle(err)
+        || matches!(
+            err.kind(),
+            std::io::ErrorKind::TimedOut
+                | std::io::ErrorKind::NotConnected
+                | std::io::ErrorKind::ConnectionReset
+                | std::io::ErrorKind::ConnectionAborted
+                | std::io::ErrorKind::HostUnreachable
+                | std::io::ErrorKind::NetworkUnreachable
+                | std::io::ErrorKind::NetworkDown
+                | std::io::ErrorKind::StaleNetworkFileHandle
+        )
+}
+
+/// The writes to one file: the latest one started, and a lock renames into place
+/// are made under.
+#[derive(Debug, Default)]
+struct Slot {
+    latest: std::sync::atomic::AtomicU64,
+    renaming: std::sync::Mutex<()>,
+}
+
+/// The next write to a file, claimed in the order saves are made. Once a later
+/// write is claimed, this one no longer lands, so a slow write overtaken by a
+/// newer one can't end up on top of it.
+#[derive(Debug)]
+struct Claim {
+    path: std::path::PathBuf,
+    slot: std::sync::Arc<Slot>,
+    generation: u64,
+}
+
+impl Claim {
+    fn new(path: &std::path::Path) -> Self {
+        static SLOTS: std::sync::OnceLock<
+            std::sync::Mutex<std::collections::HashMap<std::path::PathBuf, std::sync::Arc<Slot>>>,
+        > = std::sync::OnceLock::new();
+        let slot = SLOTS
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .entry(path.to_path_buf())
+            .or_default()
+            .clone();
+        let generation = slot
+            .latest
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst)
+            + 1;
+
+        Self {
+            path: path.to_path_buf(),
+            slot,
+            generation,
+        }
+    }
+
+    /// Writes `bytes` through a temporary file in the same directory and a rename,
+    /// so a failed write never leaves half a note behind. An overtaken write is
+    /// dropped before the rename and counts as done.
+    fn write(&self, bytes: &[u8]) -> std::io::Result<()> {
+        let parent = self
+            .path
+            .parent()
+            .unwrap_or_else(|| std::path::Path::new("."));
+        let mut temp = tempfile::Builder::new()
+            .prefix(".save_tmp_")
+            .tempfile_in(parent)?;
+
+        std::io::Write::write_all(&mut temp, bytes)?;
+        temp.as_file().sync_all()?;
+
+        let _renaming = self
+            .slot
+            .renaming
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+        if self.slot.latest.load(std::sync::atomic::Ordering::SeqCst) != self.generation {
+            tracing::debug!(path = ?self.path, "Dropped a write overtaken by a newer one");
+            return Ok(());
+        }
+        temp.persist(&self.path).map_err(|err| err.error)?;
+
+        Ok(())
+    }
+}
+
+/// Writes `bytes` to `path` atomically, see [`Claim::write`].
+fn write_atomically(path: &std::path::Path, bytes: &[u8]) -> std::io::Result<()> {
+    Claim::new(path).write(bytes)
+}
+
+/// [`write_atomically`] on a worker thread, giving up after `timeout`. A write that
+/// times out keeps going in the background, but is dropped rather than renamed
+/// into place if a later write to `path` starts meanwhile.
+///
+/// # Errors
+///
+/// Returns the write's own error, or `ErrorKind::TimedOut`.
+pub fn write_with_timeout(
+    path: &std::path::Path,
+    bytes: Vec<u8>,
+    timeout: std::time::Duration,
+) -> std::io::Result<()> {
+    let (done, result) = std::sync::mpsc::channel();
+    let claim = Claim::new(path);
+
+    std::thread::spawn(move || {
+        let _ = done.send(claim.write(&bytes));
+    });
+
+    result.recv_timeout(timeout).unwrap_or_else(|_| {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            format!("Saving took longer than {}s", timeout.as_secs_f32()),
+        ))
+    })
+}
+
+/// A save that couldn't reach its file and waits in the local cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingWrite {
+    /// Where the note belongs.
+    pub target: std::path::PathBuf,
+    /// The copy in the cache that will be written there.
+    pub cached: std::path::PathBuf,
+    pub attempts: u32,
+    pub last_error: String,
+}
+
+/// What [`crate::document::Document::save_or_defer`] did with the note.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SaveOutcome {
+    Saved,
+    /// Written to the local cache; the queue writes it to the file later.
+    Deferred,
+}
+
+/// Saves that went to a local cache because their drive was slow or gone, and are
+/// written to their files in the background once it's back. The queue survives
+/// restarts through a manifest in its directory.
+#[derive(Debug)]
+pub struct SaveQueue {
+    dir: std::path::PathBuf,
+    pub timeout: std::time::Duration,
+    pending: Vec<PendingWrite>,
+    /// Cached copies being written right now, by target.
+    in_flight: std::collections::HashMap<std::path::PathBuf, std::path::PathBuf>,
+    results: std::sync::mpsc::Receiver<(std::path::PathBuf, std::io::Result<()>)>,
+    report: std::sync::mpsc::Sender<(std::path::PathBuf, std::io::Result<()>)>,
+}
+
+impl SaveQueue {
+    const MANIFEST: &'static str = "pending.tsv";
+
+    /// The queue kept in `dir`, with whatever writes were still pending when the app
+    /// last quit. Entries whose cached copy is gone are dropped.
+    pub fn load(dir: impl Into<std::path::PathBuf>) -> Self {
+        let dir = dir.into();
+        let pending = std::fs::read_to_string(dir.join(Self::MANIFEST))
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let (target, cached) = line.split_once('\t')?;
+
+                Some(PendingWrite {
+                    target: target.into(),
+                    cached: cached.into(),
+                    attempts: 0,
+                    last_error: String::new(),
+                })
+            })
+            .filter(|write| write.cached.exists())
+            .collect();
+        let (report, results) = std::sync::mpsc::channel();
+
+        Self {
+            dir,
+            timeout: DEFAULT_TIMEOUT,
+            pending,
+            in_flight: std::collections::HashMap::new(),
+            results,
+            report,
+        }
+    }
+
+    #[inline]
+    pub fn pending(&self) -> &[PendingWrite] {
+        &self.pending
+    }
+
+    /// Whether a write to `target` is still waiting. Later saves of it must queue
+    /// behind it, or the older copy could land on top of the newer one.
+    pub fn is_pending(&self, target: &std::path::Path) -> bool {
+        self.pending.iter().any(|write| write.target == target)
+    }
+
+    fn save_manifest(&self) -> std::io::Result<()> {
+        let manifest: String = self
+            .pending
+            .iter()
+            .map(|write| format!("{}\t{}\n", write.target.display(), write.cached.display()))
+            .collect();
+
+        std::fs::create_dir_all(&self.dir)?;
+        write_atomically(&self.dir.join(Self::MANIFEST), manifest.as_bytes())
+    }
+
+    /// Puts `bytes` in the cache to be written to `target` later, replacing anything
+    /// still waiting for the same file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the cache itself can't be written.
+    pub fn defer(
+        &mut self,
+        target: &std::path::Path,
+        bytes: &[u8],
+        reason: &std::io::Error,
+    ) -> std::io::Result<()> {
+        let name = target
+            .file_name()
+            .map_or_else(|| "note".into(), |name| name.to_string_lossy());
+        let stamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos());
+        let cached = self.dir.join(format!("{stamp}-{name}"));
+
+        std::fs::create_dir_all(&self.dir)?;
+        write_atomically(&cached, bytes)?;
+
+        if let Some(old) = self.pending.iter().position(|write| write.target == target) {
+            let old = self.pending.remove(old);
+
+            // A copy being written right now is removed once its write reports back.
+            if self.in_flight.get(&old.target) != Some(&old.cached) {
+                let _ = std::fs::remove_file(&old.cached);
+            }
+        }
+
+        tracing::warn!(?target, ?cached, "Save deferred: {}", reason);
+        self.pending.push(PendingWrite {
+            target: target.to_path_buf(),
+            cached,
+            attempts: 0,
+            last_error: reason.to_string(),
+        });
+        self.save_manifest()
+    }
+
+    /// Starts writing every pending copy to its file on a worker thread, skipping
+    /// files that already have a write under way. Results arrive through [`Self::poll`].
+    pub fn retry(&mut self) {
+        for write in &self.pending {
+            if self.in_flight.contains_key(&write.target) {
+                continue;
+            }
+
+            let report = self.report.clone();
+            let (target, cached) = (write.target.clone(), write.cached.clone());
+            let claim = Claim::new(&target);
+
+            self.in_flight.insert(target.clone(), cached.clone());
+            std::thread::spawn(move || {
+                let result = std::fs::read(&cached).and_then(|bytes| claim.write(&bytes));
+                let _ = report.send((target, result));
+            });
+        }
+    }
+
+    /// Collects the writes [`Self::retry`] finished since the last call. Returns how
+    /// many notes reached their files.
+    pub fn poll(&mut self) -> usize {
+        let mut written = 0;
+
+        while let Ok((target, result)) = self.results.try_recv() {
+            let Some(cached) = self.in_flight.remove(&target) else {
+                continue;
+            };
+            let current = self.pending.iter().position(|write| write.target == target);
+
+            match (result, current) {
+                // Still the latest copy: done with it.
+                (Ok(()), Some(idx)) if self.pending[idx].cached == cached => {
+                    self.pending.remove(idx);
+                    written += 1;
+                    tracing::info!(?target, "Deferred save written");
+                }
+                (Err(err), Some(idx)) if self.pending[idx].cached == cached => {
+                    self.pending[idx].attempts += 1;
+                    self.pending[idx].last_error = err.to_string();
+                    continue;
+                }
+                // Superseded by a newer save while it was being written; the newer copy
+                // is written on the next retry.
+                _ => {}
+            }
+
+            let _ = std::fs::remove_file(&cached);
+        }
+
+        if let Err(err) = self.save_manifest() {
+            tracing::error!("Error saving pending writes: {:?}", err);
+        }
+
+        written
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_classification() {
+        let timed_out = std::io::Error::new(std::io::ErrorKind::TimedOut, "slow");
+        let denied = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "no");
+
+        assert!(is_network_failure(&timed_out));
+        assert!(!is_network_failure(&denied));
+
+        if cfg!(target_os = "linux") {
+            assert!(is_stale_handle(&std::io::Error::from_raw_os_error(116)));
+            assert!(is_network_failure(&std::io::Error::from_raw_os_error(116)));
+        }
+    }
+
+    #[test]
+    fn test_overtaken_writes_are_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("note.md");
+        let (slow, fast) = (Claim::new(&path), Claim::new(&path));
+
+        fast.write(b"new").unwrap();
+        slow.write(b"old").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "new");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_deferred_writes_are_retried_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = dir.path().join("pending");
+        let target = dir.path().join("share").join("note.md");
+        let offline = std::io::Error::new(std::io::ErrorKind::TimedOut, "offline");
+        let mut queue = SaveQueue::load(&cache);
+
+        queue.defer(&target, b"old", &offline).unwrap();
+        queue.defer(&target, b"new", &offline).unwrap();
+        assert_eq!(queue.pending().len(), 1);
+        assert!(queue.is_pending(&target));
+
+        // Survives a restart.
+        drop(queue);
+        let mut queue = SaveQueue::load(&cache);
+        assert_eq!(queue.pending().len(), 1);
+
+        // The drive is still gone: the write fails and stays queued.
+        queue.retry();
+        while !queue.in_flight.is_empty() {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            queue.poll();
+        }
+        assert_eq!(queue.pending()[0].attempts, 1);
+
+        // Back online.
+        std::fs::create_dir_all(target.parent().unwrap()).unwrap();
+        queue.retry();
+        let mut written = 0;
+        while !queue.in_flight.is_empty() {
+            std::thread::sleep(std::time::Duration::from_millis(5));
+            written += queue.poll();
+        }
+
+        assert_eq!(written, 1);
+        assert!(queue.pending().is_empty());
+        assert_eq!(std::fs::read_to_string(&target).unwrap(), "new");
+        assert_eq!(
+            std::fs::read_dir(&cache).unwrap().count(),
+            1,
+            "Only the manifest"
+        );
+    }
+}
@@ -0,0 +1,365 @@
+/// Configurable cleanup passes run right before a document is written to disk.
+/// All passes are off by default so saving never silently rewrites a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SaveTransforms {
+    /// Removes spaces and tabs at the end of every line.
+    pub trim_trailing_whitespace: bool,
+    /// Makes the file end with exactly one line ending.
+    pub ensure_final_newline: bool,
+    /// Squashes runs of consecutive blank lines down to a single blank line.
+    pub collapse_blank_lines: bool,
+    /// Brings an existing table of contents up to date, see [`crate::toc`].
+    pub update_toc: bool,
+    /// Rewrites every line ending to this one, e.g. as `.editorconfig`'s
+    /// `end_of_line` asks.
+    pub line_ending: Option<editor_core::text::LineEnding>,
+}
+
+impl SaveTransforms {
+    #[inline]
+    #[must_use]
+    pub fn is_noop(&self) -> bool {
+        !self.trim_trailing_whitespace
+            && !self.ensure_final_newline
+            && !self.collapse_blank_lines
+            && !self.update_toc
+            && self.line_ending.is_none()
+    }
+
+    /// Computes the edits needed to bring `doc` in line with the enabled passes.
+    /// The edits never overlap and are expressed against the current buffer.
+    pub fn compute_edits(&self, doc: &crate::document::Document) -> Vec<crate::document::TextEdit> {
+        let mut edits = Vec::new();
+
+        if self.is_noop() {
+            return edits;
+        }
+
+        let line_count = doc.get_line_count();
+        let line = |row: usize| doc.get_line_stripped(row).unwrap_or_default();
+        let is_blank = |row: usize| line(row).trim().is_empty();
+
+        // Trailing blank lines belong to the final-newline pass, so the other
+        // passes stop at the last line with real content.
+        let last_content_row = if self.ensure_final_newline {
+            (0..line_count).rev().find(|&row| !is_blank(row))
+        } else {
+            line_count.checked_sub(1)
+        };
+
+        // The very end of the buffer. The line index doesn't always open an empty row
+        // after a trailing newline, so we address the end through the raw line length.
+        let last_row = line_count.saturating_sub(1);
+        let doc_end = editor_core::cursor::Position::new(
+            last_row,
+            doc.get_line(last_row).map_or(0, |raw| raw.len()),
+        );
+
+        let Some(last_content_row) = last_content_row else {
+            // Nothing but whitespace; a clean file is simply empty.
+            if self.ensure_final_newline && doc.text_buffer.byte_length() > 0 {
+                edits.push(crate::document::TextEdit::new(
+                    editor_core::cursor::Position::new(0, 0),
+                    doc_end,
+                    "",
+                ));
+            }
+            return edits;
+        };
+
+        let mut row = 0;
+        // The final-newline pass rewrites the end of the last line with content.
+        let ends_to_fix = if self.ensure_final_newline {
+            last_content_row
+        } else {
+            line_count
+        };
+        let fix_ending = |edits: &mut Vec<crate::document::TextEdit>, row: usize, len: usize| {
+            let Some(wanted) = self.line_ending.filter(|_| row < ends_to_fix) else {
+                return;
+            };
+            let raw = doc.get_line(row).unwrap_or_default();
+            let ending = &raw[len.min(raw.len())..];
+
+            if !ending.is_empty() && ending != wanted.as_str() {
+                edits.push(crate::document::TextEdit::new(
+                    editor_core::cursor::Position::new(row, len),
+                    editor_core::cursor::Position::new(row + 1, 0),
+                    wanted.as_str(),
+                ));
+            }
+        };
+
+        while row <= last_content_row {
+            let text = line(row);
+
+            if self.collapse_blank_lines && text.trim().is_empty() {
+                let run_end = (row..=last_content_row)
+                    .take_while(|&r| is_blank(r))
+                    .last()
+                    .unwrap_or(row);
+
+                if self.trim_trailing_whitespace && !text.is_empty() {
+                    edits.push(crate::document::TextEdit::new(
+                        editor_core::cursor::Position::new(row, 0),
+                        editor_core::cursor::Position::new(row, text.len()),
+                        "",
+                    ));
+                }
+
+                if run_end > row {
+                    edits.push(crate::document::TextEdit::new(
+                        editor_core::cursor::Position::new(row + 1, 0),
+                        editor_core::cursor::Position::new(run_end + 1, 0),
+                        "",
+                    ));
+                }
+                fix_ending(&mut edits, row, text.len());
+
+                row = run_end + 1;
+                continue;
+            }
+
+            if self.trim_trailing_whitespace {
+                let trimmed = text.trim_end_matches([' ', '\t']).len();
+
+                if trimmed < text.len() {
+                    edits.push(crate::document::TextEdit::new(
+                        editor_core::cursor::Position::new(row, trimmed),
+                        editor_core::cursor::Position::new(row, text.len()),
+                        "",
+                    ));
+                }
+            }
+            fix_ending(&mut edits, row, text.len());
+
+            row += 1;
+        }
+
+        if self.ensure_final_newline {
+            let content = line(last_content_row);
+            let content_end = if self.trim_trailing_whitespace {
+                content.trim_end_matches([' ', '\t']).len()
+            } else {
+                content.len()
+            };
+            let tail_start = editor_core::cursor::Position::new(last_content_row, content_end);
+            let line_ending = self
+                .line_ending
+                .unwrap_or(doc.text_buffer.line_ending)
+                .as_str();
+            let current_tail = doc
+                .text_buffer
+                .get_cursor_selection(&editor_core::cursor::Cursor::new_selection(
+                    tail_start, doc_end,
+                ))
+                .ok()
+                .flatten()
+                .unwrap_or_default();
+
+            // Replace everything after the last real character with one line ending.
+            // The trim edit for this row (if any) is superseded by this one.
+            if current_tail != line_ending {
+                edits.retain(|edit| edit.start.row != last_content_row);
+                edits.push(crate::document::TextEdit::new(
+                    tail_start,
+                    doc_end,
+                    line_ending,
+                ));
+            }
+        }
+
+        // The table is rewritten whole, so the other passes keep out of it.
+        if self.update_toc
+            && let Some(toc) = doc.toc_edit()
+        {
+            edits.retain(|edit| edit.end < toc.start || edit.start > toc.end);
+            edits.push(toc);
+        }
+
+        edits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::Document;
+    use editor_core::text::TextBuffer;
+
+    fn doc_with(text: &str, transforms: SaveTransforms) -> Document {
+        let mut doc = Document::new(TextBuffer::new_with_text(text).unwrap());
+        doc.save_transforms = transforms;
+        doc
+    }
+
+    #[test]
+    fn test_noop_by_default() {
+        let mut doc = doc_with("a  \n\n\n\nb", SaveTransforms::default());
+
+        doc.apply_save_transforms();
+
+        assert_eq!(doc.text_buffer.to_string(), "a  \n\n\n\nb");
+        assert!(doc.history.undo_stack.is_empty());
+    }
+
+    #[test]
+    fn test_trim_trailing_whitespace() {
+        let mut doc = doc_with(
+            "one  \ntwo\t\n  \nthree",
+            SaveTransforms {
+                trim_trailing_whitespace: true,
+                ..Default::default()
+            },
+        );
+
+        doc.apply_save_transforms();
+
+        assert_eq!(doc.text_buffer.to_string(), "one\ntwo\n\nthree");
+    }
+
+    #[test]
+    fn test_ensure_single_final_newline() {
+        let transforms = SaveTransforms {
+            ensure_final_newline: true,
+            ..Default::default()
+        };
+
+        let mut missing = doc_with("text", transforms);
+        missing.apply_save_transforms();
+        assert_eq!(missing.text_buffer.to_string(), "text\n");
+
+        let mut extra = doc_with("text\n\n \n\n", transforms);
+        extra.apply_save_transforms();
+        assert_eq!(extra.text_buffer.to_string(), "text\n");
+
+        let mut clean = doc_with("text\n", transforms);
+        clean.apply_save_transforms();
+        assert!(clean.history.undo_stack.is_empty());
+    }
+
+    #[test]
+    fn test_final_newline_respects_crlf() {
+        let mut doc = doc_with(
+            "a\r\nb",
+            SaveTransforms {
+                ensure_final_newline: true,
+                ..Default::default()
+            },
+        );
+
+        doc.apply_save_transforms();
+
+        assert_eq!(doc.text_buffer.to_string(), "a\r\nb\r\n");
+    }
+
+    #[test]
+    fn test_line_endings_are_normalized() {
+        let mut doc = doc_with(
+            "a \r\nb\n\r\n\n\r\nc\r\n\r\n",
+            SaveTransforms {
+                trim_trailing_whitespace: true,
+                ensure_final_newline: true,
+                collapse_blank_lines: true,
+                line_ending: Some(editor_core::text::LineEnding::LF),
+                ..Default::default()
+            },
+        );
+
+        doc.apply_save_transforms();
+        assert_eq!(doc.text_buffer.to_string(), "a\nb\n\nc\n");
+
+        let mut doc = doc_with(
+            "a\nb\r\nc",
+            SaveTransforms {
+                line_ending: Some(editor_core::text::LineEnding::CRLF),
+                ..Default::default()
+            },
+        );
+
+        doc.apply_save_transforms();
+        assert_eq!(doc.text_buffer.to_string(), "a\r\nb\r\nc");
+    }
+
+    #[test]
+    fn test_collapse_blank_lines() {
+        let mut doc = doc_with(
+            "a\n\n\n\nb\n\nc",
+            SaveTransforms {
+                collapse_blank_lines: true,
+                ..Default::default()
+            },
+        );
+
+        doc.apply_save_transforms();
+
+        assert_eq!(doc.text_buffer.to_string(), "a\n\nb\n\nc");
+    }
+
+    #[test]
+    fn test_all_transforms_are_one_undo_step() {
+        let mut doc = doc_with(
+            "title  \n\n \n\nbody \n\n\n",
+            SaveTransforms {
+                trim_trailing_whitespace: true,
+                ensure_final_newline: true,
+                collapse_blank_lines: true,
+                update_toc: true,
+                line_ending: None,
+            },
+        );
+        doc.cursor = editor_core::cursor::Cursor::new(4, 4);
+
+        doc.apply_save_transforms();
+
+        assert_eq!(doc.text_buffer.to_string(), "title\n\nbody\n");
+        assert_eq!(
+            doc.cursor.head,
+            editor_core::cursor::Position::new(2, 4),
+            "Cursor follows its line when lines above are removed"
+        );
+        assert_eq!(doc.history.undo_stack.len(), 1);
+
+        doc.undo();
+        assert_eq!(doc.text_buffer.to_string(), "title  \n\n \n\nbody \n\n\n");
+
+        doc.redo();
+        assert_eq!(doc.text_buffer.to_string(), "title\n\nbody\n");
+    }
+
+    #[test]
+    fn test_update_toc() {
+        let mut doc = doc_with(
+            "# Note  \n<!-- toc -->\n- [Old](#old)   \n<!-- /toc -->\n\n## One\n### Two\n",
+            SaveTransforms {
+                trim_trailing_whitespace: true,
+                update_toc: true,
+                ..Default::default()
+            },
+        );
+
+        doc.apply_save_transforms();
+
+        assert_eq!(
+            doc.text_buffer.to_string(),
+            "# Note\n<!-- toc -->\n- [One](#one)\n  - [Two](#two)\n<!-- /toc -->\n\n## One\n### Two\n"
+        );
+        assert_eq!(doc.history.undo_stack.len(), 1);
+        assert!(!doc.update_toc(), "Already current");
+    }
+
+    #[test]
+    fn test_whitespace_only_document_becomes_empty() {
+        let mut doc = doc_with(
+            " \n\n",
+            SaveTransforms {
+                ensure_final_newline: true,
+                ..Default::default()
+            },
+        );
+
+        doc.apply_save_transforms();
+
+        assert_eq!(doc.text_buffer.to_string(), "");
+    }
+}
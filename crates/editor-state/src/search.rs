@@ -0,0 +1,497 @@
+/// The `#tags` in `text`: a `#` at the start of a word followed by letters, digits,
+/// `-`, `_` or `/`, lowercased and without the `#`. Headings (`# Title`) aren't tags
+/// since the `#` is followed by a space.
+pub fn tags(text: &str) -> Vec<String> {
+    let mut found: Vec<String> = Vec::new();
+
+    for (at, _) in text.match_indices('#') {
+        let word_start = !text[..at]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_alphanumeric() || c == '#' || c == '&');
+        let tag: String = text[at + 1..]
+            .chars()
+            .take_while(|&c| c.is_alphanumeric() || matches!(c, '-' | '_' | '/'))
+            .collect();
+
+        // `#1` is more likely an issue number than a tag.
+        if word_start
+            && tag.chars().any(char::is_alphabetic)
+            && !found.iter().any(|seen| seen.eq_ignore_ascii_case(&tag))
+        {
+            found.push(tag.to_lowercase());
+        }
+    }
+
+    found
+}
+
+/// When a file was last modified, in seconds since the epoch, and its size. A note
+/// whose stamp changed has to be read again.
+pub type Stamp = (u64, u64);
+
+pub fn stamp(meta: &std::fs::Metadata) -> Stamp {
+    let modified = meta
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map_or(0, |since| since.as_secs());
+
+    (modified, meta.len())
+}
+
+/// A note as the index keeps it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexedNote {
+    pub path: std::path::PathBuf,
+    /// The note's lines, lowercased for matching.
+    pub lines: Vec<String>,
+    /// Its first [`PREVIEW_LINES`] non-blank lines as written, to show as a preview.
+    pub preview: Vec<String>,
+    pub tags: Vec<String>,
+    /// The day the note was last modified, if known.
+    pub modified: Option<crate::reminders::Date>,
+    /// The file's stamp when it was read.
+    pub stamp: Stamp,
+}
+
+impl IndexedNote {
+    pub fn new(path: std::path::PathBuf, text: &str) -> Self {
+        Self {
+            path,
+            lines: text.lines().map(str::to_lowercase).collect(),
+            preview: text
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .take(PREVIEW_LINES)
+                .map(str::to_string)
+                .collect(),
+            tags: tags(text),
+            modified: None,
+            stamp: (0, 0),
+        }
+    }
+
+    fn stamped(mut self, stamp: Stamp) -> Self {
+        self.stamp = stamp;
+        self.modified = (stamp.0 > 0).then(|| {
+            crate::reminders::Date::from_time(
+                std::time::UNIX_EPOCH + std::time::Duration::from_secs(stamp.0),
+            )
+        });
+        self
+    }
+
+    /// Where a match for `query` is worth showing: the first row holding one of its
+    /// texts, or the top.
+    fn first_row(&self, query: &crate::query::Query) -> usize {
+        let texts = query.texts();
+
+        self.lines
+            .iter()
+            .position(|line| texts.iter().any(|text| line.contains(text)))
+            .unwrap_or(0)
+    }
+}
+
+/// A note matching a search, and the row to show it at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hit {
+    pub path: std::path::PathBuf,
+    pub row: usize,
+}
+
+/// How many lines of each note the index keeps as they are, see
+/// [`IndexedNote::preview`].
+pub const PREVIEW_LINES: usize = 6;
+
+/// Starts every saved index.
+const MAGIC: &[u8; 4] = b"MNIX";
+
+/// Bumped whenever the saved layout changes; older files are rebuilt.
+pub const VERSION: u8 = 2;
+
+/// Every note of a workspace, ready to search, kept up to date incrementally: see
+/// [`Index::refresh`] and [`Index::update`]. Saved between runs so a large vault is
+/// searchable right away, see [`Index::load`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Index {
+    pub root: std::path::PathBuf,
+    /// Sorted by path.
+    pub notes: Vec<IndexedNote>,
+}
+
+impl Index {
+    pub fn new(root: &std::path::Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            notes: Vec::new(),
+        }
+    }
+
+    /// Reads every note under `root`.
+    pub fn build(root: &std::path::Path) -> Self {
+        let mut index = Self::new(root);
+
+        index.refresh();
+        index
+    }
+
+    /// Catches up with the notes on disk: reads the ones that are new or whose
+    /// [`Stamp`] changed, and drops the ones that are gone. Returns whether
+    /// anything changed.
+    pub fn refresh(&mut self) -> bool {
+        let mut old = std::mem::take(&mut self.notes).into_iter().peekable();
+        let mut changed = false;
+
+        for path in crate::workspace::notes(&self.root) {
+            while old.next_if(|note| note.path < path).is_some() {
+                changed = true;
+            }
+
+            let Ok(meta) = std::fs::metadata(&path) else {
+                continue;
+            };
+            let stamp = stamp(&meta);
+
+            match old.next_if(|note| note.path == path) {
+                Some(note) if note.stamp == stamp => self.notes.push(note),
+                _ => {
+                    let Ok(text) = std::fs::read_to_string(&path) else {
+                        continue;
+                    };
+
+                    self.notes
+                        .push(IndexedNote::new(path, &text).stamped(stamp));
+                    changed = true;
+                }
+            }
+        }
+
+        changed || old.next().is_some()
+    }
+
+    /// Takes in `text`, just saved to `path`, without reading the file back. Notes
+    /// outside the root are left out. Returns whether anything changed.
+    pub fn update(&mut self, path: &std::path::Path, text: &str) -> bool {
+        if !path.starts_with(&self.root) || !crate::workspace::is_note(path) {
+            return false;
+        }
+
+        let stamp = std::fs::metadata(path).map_or((0, 0), |meta| stamp(&meta));
+        let note = IndexedNote::new(path.to_path_buf(), text).stamped(stamp);
+
+        match self
+            .notes
+            .binary_search_by(|indexed| indexed.path.as_path().cmp(path))
+        {
+            Ok(i) if self.notes[i] == note => false,
+            Ok(i) => {
+                self.notes[i] = note;
+                true
+            }
+            Err(i) => {
+                self.notes.insert(i, note);
+                true
+            }
+        }
+    }
+
+    /// The note called `name`, compared case-insensitively, if it's indexed.
+    pub fn find_note(&self, name: &str) -> Option<&IndexedNote> {
+        self.notes.iter().find(|note| {
+            crate::workspace::note_name(&note.path)
+                .is_some_and(|have| have.eq_ignore_ascii_case(name))
+        })
+    }
+
+    /// The stamp `path` had when it was last read, if it's indexed.
+    pub fn stamp_of(&self, path: &std::path::Path) -> Option<Stamp> {
+        self.notes
+            .binary_search_by(|indexed| indexed.path.as_path().cmp(path))
+            .ok()
+            .map(|i| self.notes[i].stamp)
+    }
+
+    /// The notes matching `query`, in path order. Archived notes only with
+    /// `include_archived`, see [`crate::workspace::is_archived`].
+    pub fn search(&self, query: &crate::query::Query, include_archived: bool) -> Vec<Hit> {
+        self.notes
+            .iter()
+            .filter(|note| {
+                include_archived || !crate::workspace::is_archived(&self.root, &note.path)
+            })
+            .filter(|note| query.matches(note))
+            .map(|note| Hit {
+                path: note.path.clone(),
+                row: note.first_row(query),
+            })
+            .collect()
+    }
+
+    /// The index in a compact binary form.
+    ///
+    /// Layout: the magic `MNIX`, a [`VERSION`] byte and the root, then per note its
+    /// path, stamp, lowercased text, preview and tags. Numbers are LEB128 varints and text is
+    /// length-prefixed UTF-8.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = MAGIC.to_vec();
+
+        out.push(VERSION);
+        crate::history_log::put_text(&mut out, &self.root.to_string_lossy());
+        crate::history_log::put_number(&mut out, self.notes.len() as u64);
+
+        for note in &self.notes {
+            crate::history_log::put_text(&mut out, &note.path.to_string_lossy());
+            crate::history_log::put_number(&mut out, note.stamp.0);
+            crate::history_log::put_number(&mut out, note.stamp.1);
+            crate::history_log::put_text(&mut out, &note.lines.join("\n"));
+            crate::history_log::put_text(&mut out, &note.preview.join("\n"));
+            crate::history_log::put_number(&mut out, note.tags.len() as u64);
+            for tag in &note.tags {
+                crate::history_log::put_text(&mut out, tag);
+            }
+        }
+
+        out
+    }
+
+    /// Reads an index written by [`Index::encode`].
+    pub fn decode(bytes: &[u8]) -> std::io::Result<Self> {
+        let rest = bytes
+            .strip_prefix(MAGIC)
+            .ok_or_else(|| crate::history_log::invalid("Not a MyNotes search index"))?;
+        let mut reader = crate::history_log::Reader {
+            bytes: rest,
+            what: "Search index",
+        };
+
+        if reader.byte()? != VERSION {
+            return Err(crate::history_log::invalid(
+                "Search index is from another version",
+            ));
+        }
+
+        let root = std::path::PathBuf::from(reader.text()?);
+        let notes = (0..reader.count()?)
+            .map(|_| {
+                let path = std::path::PathBuf::from(reader.text()?);
+                let stamp = (reader.number()?, reader.number()?);
+                let text = reader.text()?;
+                let preview = reader.text()?;
+                let tags = (0..reader.count()?)
+                    .map(|_| reader.text())
+                    .collect::<std::io::Result<_>>()?;
+                let mut note = IndexedNote::new(path, "").stamped(stamp);
+
+                note.lines = text.split('\n').map(str::to_string).collect();
+                note.preview = preview.lines().map(str::to_string).collect();
+                note.tags = tags;
+                Ok(note)
+            })
+            .collect::<std::io::Result<_>>()?;
+
+        if !reader.bytes.is_empty() {
+            return Err(crate::history_log::invalid(
+                "Search index has trailing data",
+            ));
+        }
+
+        Ok(Self { root, notes })
+    }
+
+    /// Where the index of `root` is saved: `index/<hash of root>.mnix` next to the
+    /// session store.
+    pub fn cache_path(root: &std::path::Path) -> Option<std::path::PathBuf> {
+        let (_, hash) = crate::history_log::fingerprint(&root.to_string_lossy());
+
+        Some(
+            crate::session::config_dir()?
+                .join("index")
+                .join(format!("{hash:016x}.mnix")),
+        )
+    }
+
+    /// The index of `root` saved by [`Index::save`] in `cache`, or an empty one if
+    /// there is none or it can't be read. [`Index::refresh`] brings it up to date.
+    pub fn load(root: &std::path::Path, cache: &std::path::Path) -> Self {
+        std::fs::read(cache)
+            .ok()
+            .and_then(|bytes| Self::decode(&bytes).ok())
+            .filter(|index| index.root == root)
+            .unwrap_or_else(|| Self::new(root))
+    }
+
+    /// Writes the index to `cache` atomically, creating its directory if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory or file cannot be written.
+    pub fn save(&self, cache: &std::path::Path) -> std::io::Result<()> {
+        let dir = cache.parent().unwrap_or(std::path::Path::new("."));
+        std::fs::create_dir_all(dir)?;
+
+        let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+        std::io::Write::write_all(&mut tmp, &self.encode())?;
+        tmp.persist(cache).map_err(|err| err.error)?;
+
+        Ok(())
+    }
+}
+
+/// A query kept under a name, shown as a folder of whatever matches it right now.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SavedSearch {
+    pub name: String,
+    pub query: String,
+}
+
+/// `searches` next to the session store.
+pub fn saved_searches_path() -> Option<std::path::PathBuf> {
+    Some(crate::session::config_dir()?.join("searches"))
+}
+
+/// Loads searches saved by [`save_searches`], one `name\tquery` per line. A missing
+/// file has none.
+pub fn load_searches(path: &std::path::Path) -> Vec<SavedSearch> {
+    std::fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| {
+            let (name, query) = line.split_once('\t')?;
+
+            Some(SavedSearch {
+                name: name.to_string(),
+                query: query.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Writes `searches` for [`load_searches`], creating the directory if needed.
+///
+/// # Errors
+///
+/// Returns an error if the directory or file cannot be written.
+pub fn save_searches(path: &std::path::Path, searches: &[SavedSearch]) -> std::io::Result<()> {
+    let content: String = searches
+        .iter()
+        .map(|search| {
+            // Tabs and line breaks would split the entry.
+            let clean = |text: &str| text.replace(['\t', '\n', '\r'], " ");
+            format!("{}\t{}\n", clean(&search.name), clean(&search.query))
+        })
+        .collect();
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tags() {
+        assert_eq!(
+            tags("# Heading\nTrip #travel #Work/Q3, see issue #12 and a#b\n#travel again"),
+            vec!["travel", "work/q3"]
+        );
+    }
+
+    #[test]
+    fn test_search() {
+        let index = Index {
+            root: "".into(),
+            notes: vec![
+                IndexedNote::new("a.md".into(), "Groceries\nbuy Milk #errands"),
+                IndexedNote::new("b.md".into(), "milk prices #economy"),
+                IndexedNote::new("c.md".into(), "#errands only"),
+            ],
+        };
+        let rows = |query: &str| -> Vec<(String, usize)> {
+            crate::query::Query::parse(query)
+                .map(|query| index.search(&query, false))
+                .unwrap_or_default()
+                .into_iter()
+                .map(|hit| (hit.path.display().to_string(), hit.row))
+                .collect()
+        };
+
+        assert_eq!(rows("milk"), vec![("a.md".into(), 1), ("b.md".into(), 0)]);
+        assert_eq!(rows("milk #Errands"), vec![("a.md".into(), 1)]);
+        assert_eq!(
+            rows("#errands"),
+            vec![("a.md".into(), 0), ("c.md".into(), 0)]
+        );
+        assert_eq!(
+            rows("#economy OR #errands -milk"),
+            vec![("b.md".into(), 0), ("c.md".into(), 0)]
+        );
+        assert!(rows("").is_empty());
+    }
+
+    #[test]
+    fn test_index_refreshes_incrementally_and_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let (a, b, c) = (root.join("a.md"), root.join("b.md"), root.join("c.md"));
+
+        std::fs::write(&a, "\nAlpha #one\n\nmore").unwrap();
+        std::fs::write(&b, "beta").unwrap();
+
+        let mut index = Index::build(root);
+        let query = crate::query::Query::parse("alpha OR gamma OR #two").unwrap();
+
+        assert_eq!(index.notes.len(), 2);
+        assert_eq!(
+            index.find_note("A").unwrap().preview,
+            ["Alpha #one", "more"]
+        );
+        assert!(!index.refresh());
+
+        std::fs::write(&a, "changed #two").unwrap();
+        std::fs::remove_file(&b).unwrap();
+        std::fs::write(&c, "gamma").unwrap();
+
+        assert!(index.refresh());
+        assert_eq!(
+            index.search(&query, false),
+            vec![
+                Hit {
+                    path: a.clone(),
+                    row: 0
+                },
+                Hit {
+                    path: c.clone(),
+                    row: 0
+                }
+            ]
+        );
+        assert!(index.update(&root.join("d.md"), "new gamma"));
+        assert_eq!(index.notes.len(), 3);
+
+        let cache = root.join(".cache").join("index.mnix");
+
+        index.save(&cache).unwrap();
+        assert_eq!(Index::load(root, &cache), index);
+        assert_eq!(Index::load(&root.join("other"), &cache).notes.len(), 0);
+    }
+
+    #[test]
+    fn test_saved_searches_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("searches");
+        let searches = vec![SavedSearch {
+            name: "Errands".into(),
+            query: "#errands milk".into(),
+        }];
+
+        save_searches(&path, &searches).unwrap();
+
+        assert_eq!(load_searches(&path), searches);
+        assert!(load_searches(&dir.path().join("missing")).is_empty());
+    }
+}
@@ -0,0 +1,221 @@
+/// `$XDG_CONFIG_HOME/mynotes` (or the platform equivalent), where everything kept
+/// between runs lives.
+pub fn config_dir() -> Option<std::path::PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(std::path::PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(std::path::PathBuf::from))
+        .or_else(|| {
+            std::env::var_os("HOME").map(|home| std::path::Path::new(&home).join(".config"))
+        })?;
+
+    Some(config_dir.join("mynotes"))
+}
+
+/// How a document was being viewed, restored when it's reopened.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ViewState {
+    pub cursor_row: usize,
+    pub cursor_col: usize,
+    pub scroll_offset: usize,
+    /// Zoom, as the editor font size in points.
+    pub font_size: i32,
+    /// The furthest row read so far, to pick up from there.
+    pub read_row: usize,
+    /// The blocks that were collapsed.
+    pub folds: Vec<crate::folds::FoldKey>,
+}
+
+/// Per-file view state, keyed by absolute path, and the window's panel layout,
+/// persisted between runs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SessionStore {
+    pub views: std::collections::HashMap<std::path::PathBuf, ViewState>,
+    pub layout: crate::docking::Layout,
+}
+
+impl SessionStore {
+    /// `$XDG_CONFIG_HOME/mynotes/session` (or the platform equivalent).
+    pub fn default_path() -> Option<std::path::PathBuf> {
+        Some(config_dir()?.join("session"))
+    }
+
+    /// Loads a store saved by [`SessionStore::save`]. A missing file is an empty store
+    /// and malformed lines are skipped, so a bad session never blocks startup.
+    pub fn load(path: &std::path::Path) -> Self {
+        let Ok(content) = std::fs::read_to_string(path) else {
+            return Self::default();
+        };
+
+        let layout = content
+            .lines()
+            .find_map(|line| line.strip_prefix("layout\t"))
+            .map(crate::docking::Layout::parse)
+            .unwrap_or_default();
+        // `folds\t<keys>\t<path>`, keys comma separated, for notes with folds.
+        let mut folds: std::collections::HashMap<std::path::PathBuf, Vec<_>> = content
+            .lines()
+            .filter_map(|line| line.strip_prefix("folds\t")?.split_once('\t'))
+            .map(|(keys, path)| {
+                let keys = keys
+                    .split(',')
+                    .filter_map(crate::folds::FoldKey::parse)
+                    .collect();
+
+                (std::path::PathBuf::from(path), keys)
+            })
+            .collect();
+        // The layout and folds lines don't start with a number, so they're no views.
+        let views = content
+            .lines()
+            .filter_map(|line| {
+                // The path goes last so it may contain tabs of its own.
+                let mut fields = line.splitn(5, '\t');
+                let mut view = ViewState {
+                    cursor_row: fields.next()?.parse().ok()?,
+                    cursor_col: fields.next()?.parse().ok()?,
+                    scroll_offset: fields.next()?.parse().ok()?,
+                    font_size: fields.next()?.parse().ok()?,
+                    read_row: 0,
+                    folds: Vec::new(),
+                };
+                let mut rest = fields.next()?;
+
+                // Sessions saved before the read row was kept go straight to the path.
+                if let Some((read_row, path)) = rest.split_once('\t')
+                    && let Ok(read_row) = read_row.parse()
+                {
+                    view.read_row = read_row;
+                    rest = path;
+                }
+
+                let path = std::path::PathBuf::from(rest);
+
+                view.folds = folds.remove(&path).unwrap_or_default();
+                Some((path, view))
+            })
+            .collect();
+
+        Self { views, layout }
+    }
+
+    /// Writes the store atomically, creating its directory if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory or file cannot be written.
+    pub fn save(&self, path: &std::path::Path) -> std::io::Result<()> {
+        let mut entries: Vec<_> = self.views.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+
+        let layout = format!("layout\t{}\n", self.layout.encode());
+        let content: String = std::iter::once(layout)
+            .chain(entries.into_iter().map(|(file, view)| {
+                let mut entry = format!(
+                    "{}\t{}\t{}\t{}\t{}\t{}\n",
+                    view.cursor_row,
+                    view.cursor_col,
+                    view.scroll_offset,
+                    view.font_size,
+                    view.read_row,
+                    file.display()
+                );
+
+                if !view.folds.is_empty() {
+                    let keys: Vec<String> = view
+                        .folds
+                        .iter()
+                        .map(crate::folds::FoldKey::encode)
+                        .collect();
+
+                    entry.push_str(&format!("folds\t{}\t{}\n", keys.join(","), file.display()));
+                }
+                entry
+            }))
+            .collect();
+
+        let dir = path.parent().unwrap_or(std::path::Path::new("."));
+        std::fs::create_dir_all(dir)?;
+
+        let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+        std::io::Write::write_all(&mut tmp, content.as_bytes())?;
+        tmp.persist(path).map_err(|err| err.error)?;
+
+        Ok(())
+    }
+
+    pub fn remember(&mut self, file: &std::path::Path, view: ViewState) {
+        let key = std::path::absolute(file).unwrap_or_else(|_| file.to_path_buf());
+
+        self.views.insert(key, view);
+    }
+
+    pub fn recall(&self, file: &std::path::Path) -> Option<ViewState> {
+        let key = std::path::absolute(file).unwrap_or_else(|_| file.to_path_buf());
+
+        self.views.get(&key).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let session_path = dir.path().join("nested").join("session");
+        let note = dir.path().join("my\tnote.md");
+        let view = ViewState {
+            cursor_row: 12,
+            cursor_col: 3,
+            scroll_offset: 7,
+            font_size: 20,
+            read_row: 40,
+            folds: vec![crate::folds::FoldKey {
+                line: "## Day, 2".to_string(),
+                occurrence: 1,
+            }],
+        };
+
+        let mut store = SessionStore::default();
+        store.remember(&note, view.clone());
+        store.layout.set_shown(crate::docking::Panel::Outline, true);
+        store.save(&session_path).unwrap();
+
+        let loaded = SessionStore::load(&session_path);
+        assert_eq!(loaded.recall(&note), Some(view));
+        assert_eq!(loaded, store);
+    }
+
+    #[test]
+    fn test_load_skips_garbage() {
+        let dir = tempfile::tempdir().unwrap();
+        let session_path = dir.path().join("session");
+        std::fs::write(
+            &session_path,
+            "oops\n1\t2\t3\t16\t/notes/a.md\n1\t2\t3\t16\t9\t/notes/b.md\n",
+        )
+        .unwrap();
+
+        let store = SessionStore::load(&session_path);
+
+        assert_eq!(store.views.len(), 2);
+        assert_eq!(
+            store
+                .recall(std::path::Path::new("/notes/a.md"))
+                .map(|view| view.read_row),
+            Some(0)
+        );
+        assert_eq!(
+            store
+                .recall(std::path::Path::new("/notes/b.md"))
+                .map(|view| view.read_row),
+            Some(9)
+        );
+        assert!(
+            SessionStore::load(&dir.path().join("missing"))
+                .views
+                .is_empty()
+        );
+    }
+}
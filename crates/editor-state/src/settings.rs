@@ -0,0 +1,65 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndentStyle {
+    Tabs,
+    Spaces,
+}
+
+/// Editor-wide defaults. Per-buffer sources (`.editorconfig`, detection, ...)
+/// override these on a `Document` without ever mutating them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditorSettings {
+    pub indent_style: IndentStyle,
+    /// Number of columns a single indentation level spans.
+    pub indent_size: usize,
+    /// Number of columns a `\t` character is rendered as.
+    pub tab_width: usize,
+    /// Read notes into memory instead of mapping them, so sync tools can replace
+    /// files that are open. Costs memory on huge files.
+    pub cooperative_files: bool,
+    /// Record when each line of a note was last edited, in a hidden file next to it.
+    pub line_times: bool,
+    /// List and search notes in archive folders along with the rest.
+    pub include_archived: bool,
+    /// What Enter and Tab do on list items.
+    pub autolist: crate::lists::AutoList,
+    /// Columns a vertical guide is drawn at, e.g. 72 for commit messages. Sorted.
+    pub rulers: Vec<usize>,
+    pub caret: crate::caret::Caret,
+}
+
+impl EditorSettings {
+    /// How documents should hold their files under these settings.
+    pub fn access_mode(&self) -> io::backing::AccessMode {
+        if self.cooperative_files {
+            io::backing::AccessMode::Cooperative
+        } else {
+            io::backing::AccessMode::Mapped
+        }
+    }
+
+    /// Adds a guide at `column`, or removes the one there.
+    pub fn toggle_ruler(&mut self, column: usize) {
+        match self.rulers.binary_search(&column) {
+            Ok(i) => {
+                self.rulers.remove(i);
+            }
+            Err(i) => self.rulers.insert(i, column),
+        }
+    }
+}
+
+impl Default for EditorSettings {
+    fn default() -> Self {
+        Self {
+            indent_style: IndentStyle::Tabs,
+            indent_size: 4,
+            tab_width: 4,
+            cooperative_files: false,
+            line_times: false,
+            include_archived: false,
+            autolist: crate::lists::AutoList::default(),
+            rulers: Vec::new(),
+            caret: crate::caret::Caret::default(),
+        }
+    }
+}
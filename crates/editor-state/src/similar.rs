@@ -0,0 +1,255 @@
+/// How many hashes a [`Signature`] keeps.
+const HASHES: usize = 64;
+
+/// [`Signature`]s are split into this many bands; notes sharing a whole band are
+/// compared.
+const BANDS: usize = 16;
+
+/// How many words make a shingle.
+const SHINGLE: usize = 3;
+
+/// Two notes this similar, estimated, are flagged as near-duplicates.
+pub const THRESHOLD: f64 = 0.6;
+
+/// The hashes of every run of [`SHINGLE`] words in `lines`, or of the whole text when
+/// it has fewer words.
+pub fn shingles(lines: &[String]) -> std::collections::HashSet<u64> {
+    let words: Vec<&str> = lines
+        .iter()
+        .flat_map(|line| line.split(|c: char| !c.is_alphanumeric()))
+        .filter(|word| !word.is_empty())
+        .collect();
+
+    words
+        .windows(SHINGLE.min(words.len()).max(1))
+        .map(|window| crate::history_log::fingerprint(&window.join(" ")).1)
+        .collect()
+}
+
+/// A MinHash signature: for each of [`HASHES`] hash functions, the lowest hash of any
+/// shingle. The share of positions two signatures agree on estimates how much their
+/// shingles overlap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Signature([u64; HASHES]);
+
+impl Signature {
+    /// `None` for a note without words, which resembles nothing.
+    pub fn new(shingles: &std::collections::HashSet<u64>) -> Option<Self> {
+        if shingles.is_empty() {
+            return None;
+        }
+
+        let mut mins = [u64::MAX; HASHES];
+
+        for &shingle in shingles {
+            for (seed, min) in mins.iter_mut().enumerate() {
+                *min = (*min).min(mix(
+                    shingle ^ (seed as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15)
+                ));
+            }
+        }
+
+        Some(Self(mins))
+    }
+
+    pub fn similarity(&self, other: &Self) -> f64 {
+        let same = self.0.iter().zip(&other.0).filter(|(a, b)| a == b).count();
+
+        same as f64 / HASHES as f64
+    }
+}
+
+/// The splitmix64 finalizer, scattering a seeded shingle.
+fn mix(mut x: u64) -> u64 {
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+/// Two notes that look alike.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pair {
+    pub a: std::path::PathBuf,
+    pub b: std::path::PathBuf,
+    /// Estimated share of shingles in common, 0 to 1.
+    pub similarity: f64,
+}
+
+/// The notes in `notes` at least `threshold` alike, most alike first. Only notes
+/// sharing a band of their signatures are compared, so this stays quick on large
+/// workspaces.
+pub fn similar_pairs(notes: &[&crate::search::IndexedNote], threshold: f64) -> Vec<Pair> {
+    let signatures: Vec<Option<Signature>> = notes
+        .iter()
+        .map(|note| Signature::new(&shingles(&note.lines)))
+        .collect();
+
+    let mut buckets: std::collections::HashMap<(usize, &[u64]), Vec<usize>> =
+        std::collections::HashMap::new();
+
+    for (i, signature) in signatures.iter().enumerate() {
+        let Some(signature) = signature else {
+            continue;
+        };
+        for (band, rows) in signature.0.chunks(HASHES / BANDS).enumerate() {
+            buckets.entry((band, rows)).or_default().push(i);
+        }
+    }
+
+    let candidates: std::collections::BTreeSet<(usize, usize)> = buckets
+        .values()
+        .flat_map(|bucket| {
+            bucket
+                .iter()
+                .enumerate()
+                .flat_map(|(n, &a)| bucket[n + 1..].iter().map(move |&b| (a, b)))
+        })
+        .collect();
+
+    let mut pairs: Vec<Pair> = candidates
+        .into_iter()
+        .filter_map(|(a, b)| {
+            let similarity = signatures[a].as_ref()?.similarity(signatures[b].as_ref()?);
+
+            (similarity >= threshold).then(|| Pair {
+                a: notes[a].path.clone(),
+                b: notes[b].path.clone(),
+                similarity,
+            })
+        })
+        .collect();
+
+    pairs.sort_by(|x, y| y.similarity.total_cmp(&x.similarity));
+    pairs
+}
+
+/// A heading and the lines under it, up to the next heading. The part before the
+/// first heading has none.
+struct Section<'a> {
+    heading: Option<&'a str>,
+    body: Vec<&'a str>,
+}
+
+fn sections(text: &str) -> Vec<Section<'_>> {
+    let mut sections = vec![Section {
+        heading: None,
+        body: Vec::new(),
+    }];
+
+    for line in text.lines() {
+        if line.trim_start().starts_with('#')
+            && line.trim_start().trim_start_matches('#').starts_with(' ')
+        {
+            sections.push(Section {
+                heading: Some(line),
+                body: Vec::new(),
+            });
+        } else if let Some(section) = sections.last_mut() {
+            section.body.push(line);
+        }
+    }
+
+    sections
+}
+
+fn same_heading(a: Option<&str>, b: Option<&str>) -> bool {
+    a.map(|a| a.trim().to_lowercase()) == b.map(|b| b.trim().to_lowercase())
+}
+
+/// `into` followed by what `from` adds to it, section by section: a section of
+/// `from` whose heading `into` also has contributes only the lines that section
+/// lacks, others are appended whole. Blank lines never count as duplicates.
+pub fn merge(into: &str, from: &str) -> Vec<String> {
+    let mut merged = sections(into);
+    let mut added = Vec::new();
+
+    for section in sections(from) {
+        match merged
+            .iter_mut()
+            .find(|have| same_heading(have.heading, section.heading))
+        {
+            Some(have) => {
+                let mut extra: Vec<&str> = section
+                    .body
+                    .into_iter()
+                    .filter(|line| line.trim().is_empty() || !have.body.contains(line))
+                    .collect();
+
+                while extra.first().is_some_and(|line| line.trim().is_empty()) {
+                    extra.remove(0);
+                }
+                while extra.last().is_some_and(|line| line.trim().is_empty()) {
+                    extra.pop();
+                }
+                if extra.is_empty() {
+                    continue;
+                }
+                while have.body.last().is_some_and(|line| line.trim().is_empty()) {
+                    have.body.pop();
+                }
+                have.body.extend(extra);
+                have.body.push("");
+            }
+            None => added.push(section),
+        }
+    }
+
+    let mut lines: Vec<String> = merged
+        .into_iter()
+        .flat_map(|section| section.heading.into_iter().chain(section.body))
+        .map(str::to_string)
+        .collect();
+
+    for section in added {
+        if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+            lines.push(String::new());
+        }
+        lines.extend(
+            section
+                .heading
+                .into_iter()
+                .chain(section.body)
+                .map(str::to_string),
+        );
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_similar_pairs() {
+        let note = |name: &str, text: &str| {
+            crate::search::IndexedNote::new(name.into(), &text.to_lowercase())
+        };
+        let text = "the quick brown fox jumps over the lazy dog while the cat sleeps in the warm afternoon sun";
+        let notes = [
+            note("a.md", text),
+            note("b.md", &format!("{text} again")),
+            note("c.md", "shopping list milk eggs bread butter cheese apples"),
+            note("d.md", ""),
+        ];
+        let pairs = similar_pairs(&notes.iter().collect::<Vec<_>>(), THRESHOLD);
+
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(
+            (pairs[0].a.as_path(), pairs[0].b.as_path()),
+            ("a.md".as_ref(), "b.md".as_ref())
+        );
+        assert!(pairs[0].similarity > 0.8);
+    }
+
+    #[test]
+    fn test_merge() {
+        let into = "Intro\n\n# Plan\n- one\n- two\n\n# Notes\nkeep\n";
+        let from = "Intro\n\n# plan\n- two\n- three\n\n# Links\nhttps://example.com\n";
+
+        assert_eq!(
+            merge(into, from).join("\n"),
+            "Intro\n\n# Plan\n- one\n- two\n- three\n\n# Notes\nkeep\n\n# Links\nhttps://example.com"
+        );
+    }
+}
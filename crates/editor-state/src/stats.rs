@@ -0,0 +1,119 @@
+/// Counts and readability scores of a stretch of text, see [`analyze`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TextStats {
+    pub words: usize,
+    pub characters: usize,
+    pub characters_no_spaces: usize,
+    pub sentences: usize,
+    pub paragraphs: usize,
+    pub syllables: usize,
+}
+
+impl TextStats {
+    /// Silent reading speed of an average adult.
+    pub const WORDS_PER_MINUTE: usize = 230;
+
+    /// Minutes it takes to read, rounded up.
+    pub fn reading_minutes(&self) -> usize {
+        self.words.div_ceil(Self::WORDS_PER_MINUTE)
+    }
+
+    fn words_per_sentence(&self) -> f64 {
+        self.words as f64 / self.sentences.max(1) as f64
+    }
+
+    fn syllables_per_word(&self) -> f64 {
+        self.syllables as f64 / self.words.max(1) as f64
+    }
+
+    /// Flesch reading ease: around 60–70 is plain English, lower is harder.
+    /// `None` without any words.
+    pub fn reading_ease(&self) -> Option<f64> {
+        (self.words > 0)
+            .then(|| 206.835 - 1.015 * self.words_per_sentence() - 84.6 * self.syllables_per_word())
+    }
+
+    /// Flesch–Kincaid grade level, the US school grade the text suits.
+    pub fn grade_level(&self) -> Option<f64> {
+        (self.words > 0)
+            .then(|| 0.39 * self.words_per_sentence() + 11.8 * self.syllables_per_word() - 15.59)
+    }
+}
+
+/// Syllables in `word`, estimated from its groups of vowels: a silent final `e`
+/// doesn't count, and every word has at least one.
+fn syllables(word: &str) -> usize {
+    let word = word.to_lowercase();
+    let is_vowel = |c: char| "aeiouy".contains(c);
+    let mut count = 0;
+    let mut previous_vowel = false;
+
+    for c in word.chars() {
+        let vowel = is_vowel(c);
+
+        if vowel && !previous_vowel {
+            count += 1;
+        }
+        previous_vowel = vowel;
+    }
+    if word.ends_with('e') && !word.ends_with("le") && count > 1 {
+        count -= 1;
+    }
+
+    count.max(1)
+}
+
+/// Word, character, sentence and paragraph counts of `text`. Words are runs of
+/// letters and digits (with inner `'` and `-`), so Markdown markup isn't counted.
+pub fn analyze(text: &str) -> TextStats {
+    let words: Vec<&str> = text
+        .split(|c: char| !(c.is_alphanumeric() || c == '\'' || c == '’' || c == '-'))
+        .map(|word| word.trim_matches(['\'', '’', '-']))
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .collect();
+    let sentences = text
+        .split(['.', '!', '?'])
+        .filter(|sentence| sentence.chars().any(char::is_alphanumeric))
+        .count();
+    let paragraphs = text
+        .split("\n\n")
+        .filter(|paragraph| !paragraph.trim().is_empty())
+        .count();
+
+    TextStats {
+        words: words.len(),
+        characters: text.chars().filter(|&c| c != '\n' && c != '\r').count(),
+        characters_no_spaces: text.chars().filter(|c| !c.is_whitespace()).count(),
+        sentences,
+        paragraphs,
+        syllables: words
+            .iter()
+            .filter(|word| word.chars().any(char::is_alphabetic))
+            .map(|word| syllables(word))
+            .sum(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analyze() {
+        let stats =
+            analyze("The cat sat on the mat. It was **happy**!\n\nDon't   re-read it, 42 times?");
+
+        assert_eq!(stats.words, 14);
+        assert_eq!(stats.sentences, 3);
+        assert_eq!(stats.paragraphs, 2);
+        assert_eq!(stats.reading_minutes(), 1);
+        assert_eq!(syllables("table"), 2);
+        assert_eq!(syllables("make"), 1);
+        assert_eq!(syllables("readability"), 5);
+
+        let ease = stats.reading_ease().unwrap();
+        assert!(ease > 80.0, "{ease}");
+        assert_eq!(analyze("  \n").reading_ease(), None);
+        assert_eq!(analyze("").reading_minutes(), 0);
+    }
+}
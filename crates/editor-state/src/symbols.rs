@@ -0,0 +1,125 @@
+/// What a [`Symbol`] points at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    /// A heading of the given level.
+    Heading(u8),
+    /// The opening fence of a code block.
+    CodeBlock,
+    /// A `TODO`, `FIXME` or `XXX` marker.
+    Todo,
+}
+
+/// A place worth jumping to in a note.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub kind: SymbolKind,
+    pub row: usize,
+    /// The heading title, the fence's info string or the marker's line, trimmed.
+    pub label: String,
+}
+
+impl Symbol {
+    /// One line for a picker, e.g. `## Ideas`, ```` ```rust ```` or `TODO: call Bob`,
+    /// with the line number at the end.
+    pub fn display(&self) -> String {
+        let text = match self.kind {
+            SymbolKind::Heading(level) => {
+                format!("{} {}", "#".repeat(level as usize), self.label)
+            }
+            SymbolKind::CodeBlock => format!("```{}", self.label),
+            SymbolKind::Todo => self.label.clone(),
+        };
+
+        format!("{text}    :{}", self.row + 1)
+    }
+}
+
+const MARKERS: [&str; 3] = ["TODO", "FIXME", "XXX"];
+
+/// Whether `line` holds one of the [`MARKERS`] as a word of its own.
+fn has_marker(line: &str) -> bool {
+    MARKERS.iter().any(|marker| {
+        line.match_indices(marker).any(|(at, _)| {
+            let before = line[..at].chars().next_back();
+            let after = line[at + marker.len()..].chars().next();
+
+            !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+        })
+    })
+}
+
+/// The headings, code blocks and markers of `doc`, top to bottom. Headings and code
+/// blocks come from the outline index; markers take a pass over the lines.
+pub fn symbols(doc: &crate::document::Document) -> Vec<Symbol> {
+    let mut symbols: Vec<Symbol> = doc
+        .outline
+        .headings
+        .iter()
+        .map(|heading| Symbol {
+            kind: SymbolKind::Heading(heading.level),
+            row: heading.row,
+            label: heading.title.clone(),
+        })
+        .collect();
+
+    for row in doc.outline.code_blocks() {
+        let fence = doc.get_line_stripped(row).unwrap_or_default();
+
+        symbols.push(Symbol {
+            kind: SymbolKind::CodeBlock,
+            row,
+            label: fence
+                .trim()
+                .trim_start_matches(['`', '~'])
+                .trim()
+                .to_string(),
+        });
+    }
+
+    for (row, line) in doc
+        .get_lines_stripped(0..doc.get_line_count())
+        .iter()
+        .enumerate()
+    {
+        if has_marker(line) {
+            symbols.push(Symbol {
+                kind: SymbolKind::Todo,
+                row,
+                label: line.trim().to_string(),
+            });
+        }
+    }
+
+    symbols.sort_by_key(|symbol| symbol.row);
+    symbols
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::Document;
+    use editor_core::text::TextBuffer;
+
+    #[test]
+    fn test_symbols() {
+        let doc = Document::new(
+            TextBuffer::new_with_text(
+                "# Plan\n- TODO: call Bob\n- TODOS aren't markers\n```rust\n# not a heading\n```\n## Later\nFIXME",
+            )
+            .unwrap(),
+        );
+
+        let displayed: Vec<String> = symbols(&doc).iter().map(Symbol::display).collect();
+
+        assert_eq!(
+            displayed,
+            vec![
+                "# Plan    :1",
+                "- TODO: call Bob    :2",
+                "```rust    :4",
+                "## Later    :7",
+                "FIXME    :8",
+            ]
+        );
+    }
+}
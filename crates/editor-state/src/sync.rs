@@ -0,0 +1,687 @@
+/// A note as a sync backend lists it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteNote {
+    /// Path relative to the synced folder, `/`-separated, e.g. `projects/ideas.md`.
+    pub name: String,
+    pub modified: std::time::SystemTime,
+    pub len: u64,
+}
+
+/// What to do with a note that changed on both sides since the last sync.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictChoice {
+    KeepLocal,
+    KeepRemote,
+    /// Keep the local note and save the remote one next to it as a conflict file.
+    KeepBoth,
+}
+
+/// Somewhere notes are synced with. [`sync`] does the bookkeeping; a provider only
+/// moves bytes.
+pub trait SyncProvider {
+    /// Shown in the UI and used in conflict file names.
+    fn name(&self) -> &str;
+
+    /// Every note on the remote side.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the remote can't be reached.
+    fn list(&self) -> std::io::Result<Vec<RemoteNote>>;
+
+    /// The contents of the remote note `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the note can't be downloaded.
+    fn pull(&self, name: &str) -> std::io::Result<Vec<u8>>;
+
+    /// Uploads `bytes` as the remote note `name`, returning the note as the remote
+    /// lists it afterwards.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the note can't be uploaded.
+    fn push(&self, name: &str, bytes: &[u8]) -> std::io::Result<RemoteNote>;
+
+    /// Called when `name` changed on both sides since the last sync.
+    fn on_conflict(&self, _name: &str) -> ConflictChoice {
+        ConflictChoice::KeepBoth
+    }
+}
+
+/// Where a note stands relative to its remote copy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncStatus {
+    Synced,
+    /// Changed locally; the next sync uploads it.
+    Modified,
+    /// Changed remotely; the next sync downloads it.
+    Outdated,
+    /// Changed on both sides.
+    Conflict,
+    /// Only exists locally so far.
+    LocalOnly,
+    /// Only exists remotely so far.
+    RemoteOnly,
+}
+
+impl SyncStatus {
+    pub fn label(self) -> &'static str {
+        match self {
+            SyncStatus::Synced => "synced",
+            SyncStatus::Modified => "modified",
+            SyncStatus::Outdated => "outdated",
+            SyncStatus::Conflict => "conflict",
+            SyncStatus::LocalOnly => "new",
+            SyncStatus::RemoteOnly => "remote",
+        }
+    }
+}
+
+/// Modification time and length, enough to tell whether a file changed. Times are
+/// only ever compared with earlier times from the same side, so clock skew between
+/// machines doesn't matter.
+type Fingerprint = (u128, u64);
+
+fn fingerprint(modified: std::time::SystemTime, len: u64) -> Fingerprint {
+    let nanos = modified
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_nanos());
+
+    (nanos, len)
+}
+
+fn local_fingerprint(path: &std::path::Path) -> std::io::Result<Fingerprint> {
+    let meta = std::fs::metadata(path)?;
+
+    Ok(fingerprint(meta.modified()?, meta.len()))
+}
+
+/// The `/`-separated name of the note at `path` under `root`.
+pub fn note_key(root: &std::path::Path, path: &std::path::Path) -> Option<String> {
+    let parts: Option<Vec<&str>> = path
+        .strip_prefix(root)
+        .ok()?
+        .components()
+        .map(|part| part.as_os_str().to_str())
+        .collect();
+
+    Some(parts?.join("/"))
+}
+
+/// Where the note `name` lives under `root`. Names that would climb out of `root`
+/// are refused, whatever a remote sends.
+pub fn note_path(root: &std::path::Path, name: &str) -> Option<std::path::PathBuf> {
+    let mut path = root.to_path_buf();
+
+    for part in name.split('/') {
+        if part.is_empty() || part == "." || part == ".." || part.contains('\\') {
+            return None;
+        }
+        path.push(part);
+    }
+
+    Some(path)
+}
+
+/// Fingerprints of both copies of every note as of the last sync, kept in the synced
+/// folder as a hidden file so it travels with it but is never synced itself.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncState {
+    synced: std::collections::HashMap<String, (Fingerprint, Fingerprint)>,
+}
+
+impl SyncState {
+    const FILE: &'static str = ".mynotes-sync";
+    /// Copies of every note as last synced, the common ancestor for merging conflicts.
+    const BASE_DIR: &'static str = ".mynotes-sync-base";
+
+    /// The note `name` as it was when last synced, if a copy was kept.
+    pub fn base(root: &std::path::Path, name: &str) -> Option<Vec<u8>> {
+        std::fs::read(note_path(&root.join(Self::BASE_DIR), name)?).ok()
+    }
+
+    /// Where the common ancestor of the conflict file `copy` is kept. Conflict files
+    /// get synced like any note, so their ancestors live apart from the regular copies.
+    fn conflict_base_path(
+        root: &std::path::Path,
+        copy: &std::path::Path,
+    ) -> Option<std::path::PathBuf> {
+        note_path(
+            &root.join(Self::BASE_DIR).join(".conflicts"),
+            &note_key(root, copy)?,
+        )
+    }
+
+    /// The common ancestor of the note and the conflict file `copy` written for it, for
+    /// a three-way merge.
+    pub fn conflict_base(root: &std::path::Path, copy: &std::path::Path) -> Option<Vec<u8>> {
+        std::fs::read(Self::conflict_base_path(root, copy)?).ok()
+    }
+
+    /// Deletes the conflict file `copy` once it's been merged, along with its ancestor.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the conflict file can't be deleted.
+    pub fn resolve_conflict(root: &std::path::Path, copy: &std::path::Path) -> std::io::Result<()> {
+        if let Some(base) = Self::conflict_base_path(root, copy) {
+            let _ = std::fs::remove_file(base);
+        }
+
+        std::fs::remove_file(copy)
+    }
+
+    /// Notes `name` at `path` as synced with the remote copy `remote`.
+    fn record(
+        &mut self,
+        root: &std::path::Path,
+        name: String,
+        path: &std::path::Path,
+        remote: Fingerprint,
+    ) -> std::io::Result<()> {
+        let base = note_path(&root.join(Self::BASE_DIR), &name)
+            .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::InvalidInput))?;
+        let mut batch = crate::workspace::BatchSave::default();
+
+        if let Some(dir) = base.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        batch.write(&base, std::fs::read(path)?);
+        batch.commit()?;
+
+        self.synced.insert(name, (local_fingerprint(path)?, remote));
+        Ok(())
+    }
+
+    /// The state saved in `root`. Missing or malformed state means nothing was synced.
+    pub fn load(root: &std::path::Path) -> Self {
+        let synced = std::fs::read_to_string(root.join(Self::FILE))
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                // The name goes last so it may contain tabs of its own.
+                let mut fields = line.splitn(5, '\t');
+                let local = (fields.next()?.parse().ok()?, fields.next()?.parse().ok()?);
+                let remote = (fields.next()?.parse().ok()?, fields.next()?.parse().ok()?);
+
+                Some((fields.next()?.to_string(), (local, remote)))
+            })
+            .collect();
+
+        Self { synced }
+    }
+
+    /// Writes the state into `root`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be written.
+    pub fn save(&self, root: &std::path::Path) -> std::io::Result<()> {
+        let mut entries: Vec<_> = self.synced.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+
+        let content: String = entries
+            .into_iter()
+            .map(|(name, (local, remote))| {
+                format!(
+                    "{}\t{}\t{}\t{}\t{name}\n",
+                    local.0, local.1, remote.0, remote.1
+                )
+            })
+            .collect();
+        let mut batch = crate::workspace::BatchSave::default();
+
+        batch.write(root.join(Self::FILE), content);
+        batch.commit().map(|_| ())
+    }
+
+    fn status(
+        &self,
+        name: &str,
+        local: Option<Fingerprint>,
+        remote: Option<Fingerprint>,
+    ) -> SyncStatus {
+        let Some(&(synced_local, synced_remote)) = self.synced.get(name) else {
+            return match (local, remote) {
+                (Some(_), None) => SyncStatus::LocalOnly,
+                (None, _) => SyncStatus::RemoteOnly,
+                // Never synced, but both exist: nothing says which one is newer.
+                (Some(_), Some(_)) => SyncStatus::Conflict,
+            };
+        };
+
+        match (local, remote) {
+            (Some(_), None) => SyncStatus::LocalOnly,
+            (None, _) => SyncStatus::RemoteOnly,
+            (Some(local), Some(remote)) => match (local != synced_local, remote != synced_remote) {
+                (false, false) => SyncStatus::Synced,
+                (true, false) => SyncStatus::Modified,
+                (false, true) => SyncStatus::Outdated,
+                (true, true) => SyncStatus::Conflict,
+            },
+        }
+    }
+}
+
+/// The status of every note under `root` or on the remote, sorted by name.
+pub fn status(
+    root: &std::path::Path,
+    remote: &[RemoteNote],
+    state: &SyncState,
+) -> Vec<(String, SyncStatus)> {
+    let mut names: std::collections::BTreeMap<String, (Option<Fingerprint>, Option<Fingerprint>)> =
+        std::collections::BTreeMap::new();
+
+    for path in crate::workspace::notes(root) {
+        if let (Some(name), Ok(local)) = (note_key(root, &path), local_fingerprint(&path)) {
+            names.entry(name).or_default().0 = Some(local);
+        }
+    }
+    for note in remote {
+        names.entry(note.name.clone()).or_default().1 = Some(fingerprint(note.modified, note.len));
+    }
+
+    names
+        .into_iter()
+        .map(|(name, (local, remote))| {
+            let status = state.status(&name, local, remote);
+            (name, status)
+        })
+        .collect()
+}
+
+/// What a [`sync`] run did, by note name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    pub pushed: Vec<String>,
+    pub pulled: Vec<String>,
+    /// Notes changed on both sides, with the conflict files their remote copies were
+    /// written to.
+    pub conflicts: Vec<(String, std::path::PathBuf)>,
+}
+
+/// `<stem> (conflict <provider>).<ext>` next to `path`, numbered if taken.
+fn conflict_path(path: &std::path::Path, provider: &str) -> std::path::PathBuf {
+    let stem = path
+        .file_stem()
+        .map_or_else(|| "note".into(), |stem| stem.to_string_lossy());
+    let ext = path
+        .extension()
+        .map_or_else(String::new, |ext| format!(".{}", ext.to_string_lossy()));
+    let mut candidate = path.with_file_name(format!("{stem} (conflict {provider}){ext}"));
+    let mut n = 2;
+
+    while candidate.exists() {
+        candidate = path.with_file_name(format!("{stem} (conflict {provider} {n}){ext}"));
+        n += 1;
+    }
+
+    candidate
+}
+
+/// Brings the notes under `root` and on `provider` in line: changes on one side are
+/// copied to the other, and notes changed on both sides go through
+/// [`SyncProvider::on_conflict`]. Deletions aren't synced; a note deleted on one
+/// side comes back from the other.
+///
+/// # Errors
+///
+/// Returns the first error hit. Notes synced before it stay recorded in `state`.
+pub fn sync(
+    root: &std::path::Path,
+    provider: &dyn SyncProvider,
+    state: &mut SyncState,
+) -> std::io::Result<SyncReport> {
+    let remote = provider.list()?;
+    let mut report = SyncReport::default();
+
+    let result = (|| {
+        for (name, status) in self::status(root, &remote, state) {
+            let Some(path) = note_path(root, &name) else {
+                tracing::warn!(name, "Skipping a remote note with an unsafe name");
+                continue;
+            };
+
+            // The same edit on both sides, or a first sync of two copies of one folder.
+            if status == SyncStatus::Conflict
+                && let Some(note) = remote.iter().find(|note| note.name == name)
+                && provider.pull(&name)? == std::fs::read(&path)?
+            {
+                state.record(root, name, &path, fingerprint(note.modified, note.len))?;
+                continue;
+            }
+
+            let choice = match status {
+                SyncStatus::Synced => continue,
+                SyncStatus::Modified | SyncStatus::LocalOnly => ConflictChoice::KeepLocal,
+                SyncStatus::Outdated | SyncStatus::RemoteOnly => ConflictChoice::KeepRemote,
+                SyncStatus::Conflict => provider.on_conflict(&name),
+            };
+            let pushed = match choice {
+                ConflictChoice::KeepRemote => {
+                    let bytes = provider.pull(&name)?;
+                    let mut batch = crate::workspace::BatchSave::default();
+
+                    if let Some(dir) = path.parent() {
+                        std::fs::create_dir_all(dir)?;
+                    }
+                    batch.write(&path, bytes);
+                    batch.commit()?;
+                    report.pulled.push(name.clone());
+
+                    remote
+                        .iter()
+                        .find(|note| note.name == name)
+                        .map(|note| fingerprint(note.modified, note.len))
+                }
+                ConflictChoice::KeepLocal | ConflictChoice::KeepBoth => {
+                    if choice == ConflictChoice::KeepBoth {
+                        let copy = conflict_path(&path, provider.name());
+                        let mut batch = crate::workspace::BatchSave::default();
+
+                        // The regular copy is about to become the local version.
+                        if let Some(base) = SyncState::base(root, &name)
+                            && let Some(kept) = SyncState::conflict_base_path(root, &copy)
+                        {
+                            if let Some(dir) = kept.parent() {
+                                std::fs::create_dir_all(dir)?;
+                            }
+                            batch.write(kept, base);
+                        }
+                        batch.write(&copy, provider.pull(&name)?);
+                        batch.commit()?;
+                        report.conflicts.push((name.clone(), copy));
+                    }
+
+                    let note = provider.push(&name, &std::fs::read(&path)?)?;
+                    report.pushed.push(name.clone());
+
+                    Some(fingerprint(note.modified, note.len))
+                }
+            };
+
+            if let Some(remote) = pushed {
+                state.record(root, name, &path, remote)?;
+            }
+        }
+
+        Ok(())
+    })();
+
+    state.save(root)?;
+    result.map(|()| report)
+}
+
+/// A finished [`BackgroundSync`] run: what it did and where every note stands now.
+#[derive(Debug)]
+pub struct SyncRun {
+    pub root: std::path::PathBuf,
+    pub result: std::io::Result<(SyncReport, Vec<(String, SyncStatus)>)>,
+}
+
+/// Runs [`sync`] on a worker thread so a slow server never holds up typing. Only one
+/// run goes at a time; the app starts one every [`Self::INTERVAL`] and on request.
+#[derive(Debug)]
+pub struct BackgroundSync {
+    running: bool,
+    results: std::sync::mpsc::Receiver<SyncRun>,
+    report: std::sync::mpsc::Sender<SyncRun>,
+}
+
+impl Default for BackgroundSync {
+    fn default() -> Self {
+        let (report, results) = std::sync::mpsc::channel();
+
+        Self {
+            running: false,
+            results,
+            report,
+        }
+    }
+}
+
+impl BackgroundSync {
+    pub const INTERVAL: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+    #[inline]
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Syncs `root` with `provider` in the background. Returns `false` without doing
+    /// anything if a run is still going.
+    pub fn start(
+        &mut self,
+        root: std::path::PathBuf,
+        provider: std::sync::Arc<dyn SyncProvider + Send + Sync>,
+    ) -> bool {
+        if self.running {
+            return false;
+        }
+
+        let report = self.report.clone();
+
+        self.running = true;
+        std::thread::spawn(move || {
+            let mut state = SyncState::load(&root);
+            let result = sync(&root, provider.as_ref(), &mut state).and_then(|done| {
+                let remote = provider.list()?;
+                Ok((done, status(&root, &remote, &state)))
+            });
+            let _ = report.send(SyncRun { root, result });
+        });
+
+        true
+    }
+
+    /// The run that finished since the last call, if any.
+    pub fn poll(&mut self) -> Option<SyncRun> {
+        let run = self.results.try_recv().ok()?;
+
+        self.running = false;
+        Some(run)
+    }
+}
+
+/// Syncs with another folder, e.g. one a file sync client or a network share keeps
+/// up to date. Changes are told apart by modification time and size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FolderProvider {
+    pub root: std::path::PathBuf,
+}
+
+impl FolderProvider {
+    fn path(&self, name: &str) -> std::io::Result<std::path::PathBuf> {
+        note_path(&self.root, name).ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Invalid note name: {name}"),
+            )
+        })
+    }
+
+    fn remote_note(&self, name: &str, path: &std::path::Path) -> std::io::Result<RemoteNote> {
+        let meta = std::fs::metadata(path)?;
+
+        Ok(RemoteNote {
+            name: name.to_string(),
+            modified: meta.modified()?,
+            len: meta.len(),
+        })
+    }
+}
+
+impl SyncProvider for FolderProvider {
+    fn name(&self) -> &str {
+        "folder"
+    }
+
+    fn list(&self) -> std::io::Result<Vec<RemoteNote>> {
+        if !self.root.is_dir() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("{} is not a folder", self.root.display()),
+            ));
+        }
+
+        crate::workspace::notes(&self.root)
+            .iter()
+            .filter_map(|path| Some((note_key(&self.root, path)?, path)))
+            .map(|(name, path)| self.remote_note(&name, path))
+            .collect()
+    }
+
+    fn pull(&self, name: &str) -> std::io::Result<Vec<u8>> {
+        std::fs::read(self.path(name)?)
+    }
+
+    fn push(&self, name: &str, bytes: &[u8]) -> std::io::Result<RemoteNote> {
+        let path = self.path(name)?;
+        let mut batch = crate::workspace::BatchSave::default();
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        batch.write(&path, bytes);
+        batch.commit()?;
+
+        self.remote_note(name, &path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_note_paths() {
+        let root = std::path::Path::new("/notes");
+
+        assert_eq!(
+            note_path(root, "a/b.md"),
+            Some(std::path::PathBuf::from("/notes/a/b.md"))
+        );
+        assert_eq!(note_path(root, "../etc/passwd"), None);
+        assert_eq!(note_path(root, "/abs.md"), None);
+        assert_eq!(
+            note_key(root, std::path::Path::new("/notes/a/b.md")),
+            Some("a/b.md".to_string())
+        );
+    }
+
+    #[test]
+    fn test_sync_with_folder() {
+        let local = tempfile::tempdir().unwrap();
+        let remote = tempfile::tempdir().unwrap();
+        let provider = FolderProvider {
+            root: remote.path().to_path_buf(),
+        };
+        let mut state = SyncState::load(local.path());
+
+        std::fs::write(local.path().join("mine.md"), "local").unwrap();
+        std::fs::create_dir(remote.path().join("sub")).unwrap();
+        std::fs::write(remote.path().join("sub").join("theirs.md"), "remote").unwrap();
+
+        let report = sync(local.path(), &provider, &mut state).unwrap();
+
+        assert_eq!(report.pushed, vec!["mine.md"]);
+        assert_eq!(report.pulled, vec!["sub/theirs.md"]);
+        assert_eq!(
+            std::fs::read_to_string(local.path().join("sub").join("theirs.md")).unwrap(),
+            "remote"
+        );
+        assert_eq!(
+            std::fs::read_to_string(remote.path().join("mine.md")).unwrap(),
+            "local"
+        );
+
+        // Reloaded from disk, everything is in sync.
+        let mut state = SyncState::load(local.path());
+        let listed = provider.list().unwrap();
+        assert!(
+            status(local.path(), &listed, &state)
+                .iter()
+                .all(|(_, status)| *status == SyncStatus::Synced)
+        );
+
+        // One side edits one note, both sides edit the other.
+        std::fs::write(local.path().join("mine.md"), "local, edited").unwrap();
+        std::fs::write(remote.path().join("mine.md"), "remote, edited!").unwrap();
+        std::fs::write(
+            remote.path().join("sub").join("theirs.md"),
+            "remote, edited",
+        )
+        .unwrap();
+
+        let listed = provider.list().unwrap();
+        assert_eq!(
+            status(local.path(), &listed, &state),
+            vec![
+                ("mine.md".to_string(), SyncStatus::Conflict),
+                ("sub/theirs.md".to_string(), SyncStatus::Outdated),
+            ]
+        );
+
+        let report = sync(local.path(), &provider, &mut state).unwrap();
+        let conflict = local.path().join("mine (conflict folder).md");
+
+        assert_eq!(
+            report.conflicts,
+            vec![("mine.md".to_string(), conflict.clone())]
+        );
+        assert_eq!(
+            SyncState::base(local.path(), "mine.md").as_deref(),
+            Some(&b"local, edited"[..])
+        );
+        assert_eq!(
+            SyncState::conflict_base(local.path(), &conflict).as_deref(),
+            Some(&b"local"[..])
+        );
+
+        assert_eq!(
+            std::fs::read_to_string(&conflict).unwrap(),
+            "remote, edited!"
+        );
+        assert_eq!(
+            std::fs::read_to_string(remote.path().join("mine.md")).unwrap(),
+            "local, edited"
+        );
+        assert_eq!(
+            std::fs::read_to_string(local.path().join("sub").join("theirs.md")).unwrap(),
+            "remote, edited"
+        );
+
+        SyncState::resolve_conflict(local.path(), &conflict).unwrap();
+        assert!(!conflict.exists());
+        assert_eq!(SyncState::conflict_base(local.path(), &conflict), None);
+    }
+
+    #[test]
+    fn test_background_sync() {
+        let local = tempfile::tempdir().unwrap();
+        let remote = tempfile::tempdir().unwrap();
+        let provider = std::sync::Arc::new(FolderProvider {
+            root: remote.path().to_path_buf(),
+        });
+        let mut background = BackgroundSync::default();
+
+        std::fs::write(local.path().join("note.md"), "text").unwrap();
+
+        assert!(background.start(local.path().to_path_buf(), provider.clone()));
+        assert!(!background.start(local.path().to_path_buf(), provider));
+
+        let run = loop {
+            if let Some(run) = background.poll() {
+                break run;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(5));
+        };
+        let (report, statuses) = run.result.unwrap();
+
+        assert!(!background.is_running());
+        assert_eq!(report.pushed, vec!["note.md"]);
+        assert_eq!(statuses, vec![("note.md".to_string(), SyncStatus::Synced)]);
+    }
+}
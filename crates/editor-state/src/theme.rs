@@ -0,0 +1,276 @@
+/// An sRGB color.
+pub type Rgb = (u8, u8, u8);
+
+/// The colors a note is drawn or exported with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    pub background: Rgb,
+    pub text: Rgb,
+    /// Line numbers, Markdown markers and other secondary text.
+    pub muted: Rgb,
+    pub heading: Rgb,
+    pub link: Rgb,
+    pub code_background: Rgb,
+    pub code_text: Rgb,
+    pub selection: Rgb,
+}
+
+/// `color` as a CSS hex color, e.g. `#282c34`.
+pub fn css_color((r, g, b): Rgb) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// The color written as `#rrggbb` or `#rgb`.
+pub fn parse_css_color(text: &str) -> Option<Rgb> {
+    let hex = text.strip_prefix('#')?;
+    let channel = |digits: &str| u8::from_str_radix(digits, 16).ok();
+
+    match hex.len() {
+        6 => Some((
+            channel(&hex[0..2])?,
+            channel(&hex[2..4])?,
+            channel(&hex[4..6])?,
+        )),
+        3 => {
+            let short = |i: usize| channel(&hex[i..=i]).map(|c| c * 17);
+
+            Some((short(0)?, short(1)?, short(2)?))
+        }
+        _ => None,
+    }
+}
+
+/// The color `amount` of the way from `from` to `to`, `amount` being 0.0 to 1.0.
+pub fn mix(from: Rgb, to: Rgb, amount: f32) -> Rgb {
+    let channel =
+        |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * amount.clamp(0.0, 1.0)).round() as u8;
+
+    (
+        channel(from.0, to.0),
+        channel(from.1, to.1),
+        channel(from.2, to.2),
+    )
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 2] = [Theme::Dark, Theme::Light];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Dark => "Dark",
+            Theme::Light => "Light",
+        }
+    }
+
+    /// Colors the editor is drawn with.
+    pub fn palette(self) -> Palette {
+        match self {
+            Theme::Dark => Palette {
+                background: (40, 44, 52),
+                text: (255, 255, 255),
+                muted: (110, 115, 125),
+                heading: (255, 255, 255),
+                link: (97, 175, 239),
+                code_background: (55, 60, 71),
+                code_text: (224, 175, 120),
+                selection: (62, 68, 81),
+            },
+            Theme::Light => Palette {
+                background: (250, 250, 250),
+                text: (56, 58, 66),
+                muted: (150, 152, 160),
+                heading: (30, 30, 36),
+                link: (64, 120, 242),
+                code_background: (234, 234, 236),
+                code_text: (166, 38, 164),
+                selection: (214, 222, 236),
+            },
+        }
+    }
+
+    /// Variant used when printing or exporting, whatever the screen shows: black
+    /// text on white, keeping the theme's accents but dark enough to read on paper.
+    pub fn export_palette(self) -> Palette {
+        let paper = Palette {
+            background: (255, 255, 255),
+            text: (0, 0, 0),
+            muted: (110, 110, 110),
+            heading: (0, 0, 0),
+            link: (0, 0, 0),
+            code_background: (243, 243, 243),
+            code_text: (0, 0, 0),
+            selection: (230, 230, 230),
+        };
+
+        match self {
+            Theme::Dark => Palette {
+                link: (0, 82, 164),
+                code_text: (150, 70, 0),
+                ..paper
+            },
+            Theme::Light => Palette {
+                link: (40, 90, 200),
+                code_text: (140, 20, 140),
+                ..paper
+            },
+        }
+    }
+}
+
+/// Which theme the editor shows: one picked by hand, or whatever the OS prefers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ThemeMode {
+    #[default]
+    FollowSystem,
+    Fixed(Theme),
+}
+
+impl ThemeMode {
+    /// The theme to show right now. Following the system falls back to the time
+    /// of day when the OS preference can't be read, and to `Dark` without a clock.
+    pub fn resolve(self) -> Theme {
+        match self {
+            ThemeMode::Fixed(theme) => theme,
+            ThemeMode::FollowSystem => system_theme()
+                .or_else(|| local_hour().map(theme_for_hour))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Light during the day, dark from 19:00 to 07:00.
+pub fn theme_for_hour(hour: u32) -> Theme {
+    if (7..19).contains(&hour) {
+        Theme::Light
+    } else {
+        Theme::Dark
+    }
+}
+
+/// Output of a command, if it ran and succeeded.
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(program)
+        .args(args)
+        .stderr(std::process::Stdio::null())
+        .output()
+        .ok()?;
+
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Reads `gsettings get org.gnome.desktop.interface color-scheme` output.
+fn parse_gnome_color_scheme(output: &str) -> Option<Theme> {
+    match output.trim().trim_matches('\'') {
+        "prefer-dark" => Some(Theme::Dark),
+        "prefer-light" | "default" => Some(Theme::Light),
+        _ => None,
+    }
+}
+
+/// Reads `reg query ... /v AppsUseLightTheme` output, whose last word is `0x0` or `0x1`.
+fn parse_windows_light_theme(output: &str) -> Option<Theme> {
+    match output.split_whitespace().last()? {
+        "0x0" => Some(Theme::Dark),
+        "0x1" => Some(Theme::Light),
+        _ => None,
+    }
+}
+
+/// The light/dark preference of the OS, if it has one this can read.
+pub fn system_theme() -> Option<Theme> {
+    if cfg!(target_os = "macos") {
+        // The key only exists while dark mode is on.
+        Some(
+            match command_output("defaults", &["read", "-g", "AppleInterfaceStyle"]) {
+                Some(style) if style.eq_ignore_ascii_case("dark") => Theme::Dark,
+                _ => Theme::Light,
+            },
+        )
+    } else if cfg!(windows) {
+        command_output(
+            "reg",
+            &[
+                "query",
+                r"HKCU\Software\Microsoft\Windows\CurrentVersion\Themes\Personalize",
+                "/v",
+                "AppsUseLightTheme",
+            ],
+        )
+        .as_deref()
+        .and_then(parse_windows_light_theme)
+    } else {
+        if let Ok(gtk_theme) = std::env::var("GTK_THEME") {
+            return Some(if gtk_theme.to_ascii_lowercase().contains("dark") {
+                Theme::Dark
+            } else {
+                Theme::Light
+            });
+        }
+
+        command_output(
+            "gsettings",
+            &["get", "org.gnome.desktop.interface", "color-scheme"],
+        )
+        .as_deref()
+        .and_then(parse_gnome_color_scheme)
+    }
+}
+
+/// The hour of the local time of day. Only Unix-likes are asked, through `date`.
+fn local_hour() -> Option<u32> {
+    if cfg!(unix) {
+        command_output("date", &["+%H"])?.parse().ok()
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_palettes_are_light() {
+        let luma = |(r, g, b): Rgb| (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000;
+
+        for theme in Theme::ALL {
+            let palette = theme.export_palette();
+
+            assert!(luma(palette.background) > 240, "{}", theme.name());
+            assert!(luma(palette.text) < 20, "{}", theme.name());
+            assert!(luma(palette.link) < 128, "{}", theme.name());
+        }
+        assert_eq!(css_color((40, 44, 52)), "#282c34");
+    }
+
+    #[test]
+    fn test_theme_detection_helpers() {
+        assert_eq!(theme_for_hour(6), Theme::Dark);
+        assert_eq!(theme_for_hour(12), Theme::Light);
+        assert_eq!(theme_for_hour(19), Theme::Dark);
+
+        assert_eq!(
+            parse_gnome_color_scheme("'prefer-dark'\n"),
+            Some(Theme::Dark)
+        );
+        assert_eq!(parse_gnome_color_scheme("'default'"), Some(Theme::Light));
+        assert_eq!(parse_gnome_color_scheme(""), None);
+        assert_eq!(
+            parse_windows_light_theme("    AppsUseLightTheme    REG_DWORD    0x0"),
+            Some(Theme::Dark)
+        );
+
+        assert_eq!(ThemeMode::Fixed(Theme::Light).resolve(), Theme::Light);
+        assert_eq!(mix((0, 0, 0), (200, 100, 50), 0.5), (100, 50, 25));
+    }
+}
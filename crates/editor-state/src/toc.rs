@@ -0,0 +1,125 @@
+/// Line a table of contents starts after. Comments, so renderers don't show them.
+pub const START_MARKER: &str = "<!-- toc -->";
+/// Line a table of contents ends before.
+pub const END_MARKER: &str = "<!-- /toc -->";
+
+/// The anchor Markdown renderers give a heading titled `title`, GitHub style:
+/// lower case, spaces as `-`, punctuation other than `-` and `_` dropped.
+pub fn slug(title: &str) -> String {
+    title
+        .trim()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            '-' | '_' => Some(c),
+            c if c.is_alphanumeric() => Some(c),
+            _ => None,
+        })
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// The anchor of the next heading titled `title`, given the anchors `taken` by
+/// the ones before it: its slug, with `-1`, `-2`, ... appended if that's taken,
+/// as renderers do. Adds it to `taken`.
+pub fn unique_anchor(taken: &mut std::collections::HashSet<String>, title: &str) -> String {
+    let base = slug(title);
+    let mut anchor = base.clone();
+    let mut n = 0;
+
+    while !taken.insert(anchor.clone()) {
+        n += 1;
+        anchor = format!("{base}-{n}");
+    }
+    anchor
+}
+
+/// The anchors of `headings` in order, see [`unique_anchor`].
+pub fn anchors(headings: &[crate::outline::Heading]) -> Vec<String> {
+    let mut taken = std::collections::HashSet::new();
+
+    headings
+        .iter()
+        .map(|heading| unique_anchor(&mut taken, &heading.title))
+        .collect()
+}
+
+/// Rows of the start and end markers of the table of contents in `lines`.
+pub fn find<S: AsRef<str>>(lines: &[S]) -> Option<(usize, usize)> {
+    let start = lines
+        .iter()
+        .position(|line| line.as_ref().trim() == START_MARKER)?;
+    let end = lines[start + 1..]
+        .iter()
+        .position(|line| line.as_ref().trim() == END_MARKER)?;
+
+    Some((start, start + 1 + end))
+}
+
+/// A nested list linking to each of `headings`, all of a document's, from row
+/// `from` on, indented from the highest level among them.
+pub fn generate(headings: &[crate::outline::Heading], from: usize) -> Vec<String> {
+    let listed = || {
+        headings
+            .iter()
+            .zip(anchors(headings))
+            .filter(|(h, _)| h.row >= from)
+    };
+    let top = listed()
+        .map(|(heading, _)| heading.level)
+        .min()
+        .unwrap_or(1);
+
+    listed()
+        .map(|(heading, anchor)| {
+            let title = heading.title.replace('[', "\\[").replace(']', "\\]");
+
+            format!(
+                "{}- [{title}](#{anchor})",
+                "  ".repeat((heading.level - top).into())
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_between_markers() {
+        let heading = |level, title: &str, row| crate::outline::Heading {
+            level,
+            title: title.to_string(),
+            row,
+        };
+        let headings = [
+            heading(2, "Getting Started!", 0),
+            heading(3, "Install [macOS]", 1),
+            heading(2, "Getting started", 2),
+            heading(2, "C++ & Rust_2024", 3),
+        ];
+
+        assert_eq!(
+            generate(&headings, 0),
+            [
+                "- [Getting Started!](#getting-started)",
+                "  - [Install \\[macOS\\]](#install-macos)",
+                "- [Getting started](#getting-started-1)",
+                "- [C++ & Rust_2024](#c--rust_2024)",
+            ]
+        );
+        assert_eq!(
+            generate(&headings[1..], 2),
+            [
+                "- [Getting started](#getting-started)",
+                "- [C++ & Rust_2024](#c--rust_2024)"
+            ]
+        );
+        assert_eq!(
+            find(&["# Title", " <!-- toc -->", "- old", "<!-- /toc -->"]),
+            Some((1, 3))
+        );
+        assert_eq!(find(&["<!-- /toc -->", "<!-- toc -->"]), None);
+    }
+}
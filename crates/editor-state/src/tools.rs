@@ -0,0 +1,180 @@
+/// A command the selection, or the whole note, can be piped through, such as
+/// `pandoc -t gfm` or `fmt -w 72`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tool {
+    /// Shown in the Tools menu.
+    pub name: String,
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+/// The external tools the user set up, in the order they were listed.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tools(pub Vec<Tool>);
+
+impl Tools {
+    /// `tools` next to the session store.
+    pub fn default_path() -> Option<std::path::PathBuf> {
+        Some(crate::session::config_dir()?.join("tools"))
+    }
+
+    /// The `name\tcommand` lines of `path`, e.g. `Wrap paragraphs\tfmt -w 72`.
+    /// Lines starting with `#` are comments. A missing file means no tools.
+    pub fn load(path: &std::path::Path) -> Self {
+        Self(
+            std::fs::read_to_string(path)
+                .unwrap_or_default()
+                .lines()
+                .filter(|line| !line.starts_with('#'))
+                .filter_map(|line| line.split_once('\t'))
+                .filter_map(|(name, command)| {
+                    let mut words = command.split_whitespace().map(str::to_string);
+
+                    Some(Tool {
+                        name: name.trim().to_string(),
+                        program: words.next()?,
+                        args: words.collect(),
+                    })
+                })
+                .filter(|tool| !tool.name.is_empty())
+                .collect(),
+        )
+    }
+}
+
+/// Runs `tool` on `input` within `limits`, from `dir` if given, and returns what
+/// it wrote out. Blocks until it ends or times out.
+///
+/// # Errors
+///
+/// Returns why there's nothing to replace the input with: the tool couldn't be
+/// started, timed out, failed, with what it wrote to standard error, or wrote
+/// more than `limits` keep, which would replace the input with part of it.
+pub fn pipe(
+    tool: &Tool,
+    input: String,
+    limits: crate::run::RunLimits,
+    dir: Option<&std::path::Path>,
+) -> Result<String, String> {
+    let mut command = std::process::Command::new(&tool.program);
+
+    command.args(&tool.args);
+    if let Some(dir) = dir {
+        command.current_dir(dir);
+    }
+
+    let output = crate::run::capture(&mut command, Some(input), limits)
+        .map_err(|err| format!("Couldn't start {}: {err}", tool.program))?;
+    let stderr = output.stderr.trim();
+
+    if output.timed_out {
+        return Err(format!(
+            "{} timed out after {}s",
+            tool.name,
+            limits.timeout.as_secs()
+        ));
+    }
+    match output.status {
+        Some(0) if output.truncated => Err(format!(
+            "{} wrote more than {} KiB",
+            tool.name,
+            limits.max_output / 1024
+        )),
+        Some(0) => Ok(output.stdout),
+        status => Err(format!(
+            "{} failed ({}){}{stderr}",
+            tool.name,
+            status.map_or_else(|| "killed".to_string(), |code| format!("exit {code}")),
+            if stderr.is_empty() { "" } else { ": " },
+        )),
+    }
+}
+
+/// `output` fit to replace `input`: with the note's line endings `eol`, and
+/// without a final line break `input` didn't have, as most tools add one.
+pub fn fit_output(input: &str, output: &str, eol: &str) -> String {
+    let mut output = output.replace("\r\n", "\n");
+
+    if !input.ends_with('\n') && output.ends_with('\n') {
+        output.pop();
+    }
+
+    output.replace('\n', eol)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_and_fit_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tools");
+
+        std::fs::write(
+            &path,
+            "# comment\nWrap\tfmt -w 72\nEmpty\t\n\tnameless\nUpper\ttr a-z A-Z\n",
+        )
+        .unwrap();
+
+        let tools = Tools::load(&path);
+
+        assert_eq!(
+            tools.0,
+            [
+                Tool {
+                    name: "Wrap".into(),
+                    program: "fmt".into(),
+                    args: vec!["-w".into(), "72".into()],
+                },
+                Tool {
+                    name: "Upper".into(),
+                    program: "tr".into(),
+                    args: vec!["a-z".into(), "A-Z".into()],
+                },
+            ]
+        );
+        assert_eq!(fit_output("a b", "a\nb\n", "\r\n"), "a\r\nb");
+        assert_eq!(fit_output("a b\n", "a b\n", "\n"), "a b\n");
+
+        #[cfg(unix)]
+        {
+            let limits = crate::run::RunLimits::default();
+
+            assert_eq!(
+                pipe(&tools.0[1], "shout\n".into(), limits, None),
+                Ok("SHOUT\n".to_string())
+            );
+
+            let failing = Tool {
+                name: "Fail".into(),
+                program: "sh".into(),
+                args: vec!["-c".into(), "echo broken >&2; exit 4".into()],
+            };
+
+            assert_eq!(
+                pipe(&failing, String::new(), limits, None),
+                Err("Fail failed (exit 4): broken".to_string())
+            );
+
+            let cat = Tool {
+                name: "Cat".into(),
+                program: "cat".into(),
+                args: Vec::new(),
+            };
+            let small = crate::run::RunLimits {
+                max_output: 1024,
+                ..limits
+            };
+
+            assert_eq!(
+                pipe(&cat, "x".repeat(1025), small, None),
+                Err("Cat wrote more than 1 KiB".to_string())
+            );
+            assert_eq!(
+                pipe(&cat, "x".repeat(1024), small, None),
+                Ok("x".repeat(1024))
+            );
+        }
+    }
+}
@@ -0,0 +1,269 @@
+/// Capitalizes the first letter of every word and lowercases the rest.
+/// Apostrophes don't start a new word, so "don't" stays "Don't".
+pub fn title_case(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut at_word_start = true;
+
+    for c in text.chars() {
+        if c.is_alphanumeric() {
+            if at_word_start {
+                out.extend(c.to_uppercase());
+            } else {
+                out.extend(c.to_lowercase());
+            }
+            at_word_start = false;
+        } else {
+            out.push(c);
+            at_word_start = c != '\'' && c != '’';
+        }
+    }
+
+    out
+}
+
+/// Joins lines with a single space, dropping the indentation of every line after the first.
+/// Blank lines are skipped so joining a paragraph never produces double spaces.
+pub fn join_lines(lines: &[String]) -> String {
+    let mut out = String::new();
+
+    for (i, line) in lines.iter().enumerate() {
+        let part = if i == 0 { line.trim_end() } else { line.trim() };
+
+        if part.is_empty() {
+            continue;
+        }
+
+        if !out.is_empty() {
+            out.push(' ');
+        }
+        out.push_str(part);
+    }
+
+    // Keep the first line's indentation even if it was all we had.
+    if out.is_empty() {
+        return lines.first().cloned().unwrap_or_default();
+    }
+
+    out
+}
+
+/// What a command works on. Selection-scoped commands keep the selection on
+/// the text it held, and with nothing selected fall back to what they'd
+/// otherwise work on, e.g. the cursor's line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Scope {
+    #[default]
+    Selection,
+    Document,
+}
+
+/// Options of the "Sort Lines" command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SortOptions {
+    pub descending: bool,
+    /// Compares the number each line starts with instead of the raw text.
+    pub numeric: bool,
+    /// Compares runs of digits anywhere in the lines by their value, so `item2`
+    /// sorts before `item10`.
+    pub natural: bool,
+    pub case_insensitive: bool,
+    /// Drops lines that compare equal to the one before them after sorting.
+    pub unique: bool,
+}
+
+/// Leading number of `line` (after indentation), e.g. `-1.5` in `"  -1.5 kg"`.
+fn leading_number(line: &str) -> Option<f64> {
+    let trimmed = line.trim_start();
+    let end = trimmed
+        .char_indices()
+        .find(|&(i, c)| !(c.is_ascii_digit() || c == '.' || (i == 0 && (c == '-' || c == '+'))))
+        .map_or(trimmed.len(), |(i, _)| i);
+
+    trimmed[..end].parse().ok()
+}
+
+/// Orders `a` and `b` as text, except that runs of digits compare by value, e.g.
+/// `v2.9` before `v2.10`. Equal values with more leading zeros come later.
+pub fn natural_cmp(a: &str, b: &str) -> std::cmp::Ordering {
+    let (mut a, mut b) = (a, b);
+
+    loop {
+        let digits = |s: &str| s.len() - s.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+
+        match (digits(a), digits(b)) {
+            (0, _) | (_, 0) => {
+                let (Some(x), Some(y)) = (a.chars().next(), b.chars().next()) else {
+                    return a.len().cmp(&b.len());
+                };
+
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a = &a[x.len_utf8()..];
+                b = &b[y.len_utf8()..];
+            }
+            (m, n) => {
+                let (x, y) = (&a[..m], &b[..n]);
+                let (tx, ty) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                let order = tx
+                    .len()
+                    .cmp(&ty.len())
+                    .then_with(|| tx.cmp(ty))
+                    .then_with(|| x.len().cmp(&y.len()));
+
+                if order.is_ne() {
+                    return order;
+                }
+                a = &a[m..];
+                b = &b[n..];
+            }
+        }
+    }
+}
+
+/// Sorts `lines` according to `options`. The sort is stable, so lines that compare
+/// equal keep their original order, descending too. In numeric mode, lines without
+/// a number sort last.
+pub fn sort_lines(mut lines: Vec<String>, options: &SortOptions) -> Vec<String> {
+    let key = |line: &str| {
+        if options.case_insensitive {
+            line.to_lowercase()
+        } else {
+            line.to_string()
+        }
+    };
+    let order = |ordering: std::cmp::Ordering| {
+        if options.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    };
+    let compare = |a: &String, b: &String| {
+        let by_text = || {
+            order(if options.natural {
+                natural_cmp(&key(a), &key(b))
+            } else {
+                key(a).cmp(&key(b))
+            })
+        };
+
+        if !options.numeric {
+            return by_text();
+        }
+
+        match (leading_number(a), leading_number(b)) {
+            (Some(x), Some(y)) => order(x.total_cmp(&y)).then_with(by_text),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => by_text(),
+        }
+    };
+
+    lines.sort_by(compare);
+
+    if options.unique {
+        lines.dedup_by(|a, b| key(a) == key(b));
+    }
+
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_title_case() {
+        assert_eq!(title_case("the QUICK brown-fox"), "The Quick Brown-Fox");
+        assert_eq!(title_case("don't stop"), "Don't Stop");
+        assert_eq!(title_case("  élan vital"), "  Élan Vital");
+    }
+
+    #[test]
+    fn test_join_lines() {
+        let lines = ["  first  ", "   second", "", "\tthird"].map(String::from);
+
+        assert_eq!(join_lines(&lines), "  first second third");
+    }
+
+    fn sorted(lines: &[&str], options: SortOptions) -> Vec<String> {
+        sort_lines(lines.iter().map(|l| l.to_string()).collect(), &options)
+    }
+
+    #[test]
+    fn test_sort_lines_default_and_descending() {
+        assert_eq!(
+            sorted(&["b", "C", "a"], SortOptions::default()),
+            ["C", "a", "b"]
+        );
+        assert_eq!(
+            sorted(
+                &["b", "C", "a"],
+                SortOptions {
+                    descending: true,
+                    case_insensitive: true,
+                    ..Default::default()
+                }
+            ),
+            ["C", "b", "a"]
+        );
+        assert_eq!(
+            sorted(
+                &["a", "B", "A", "n/a", "2 b", "10 a"],
+                SortOptions {
+                    descending: true,
+                    numeric: true,
+                    case_insensitive: true,
+                    ..Default::default()
+                }
+            ),
+            ["10 a", "2 b", "n/a", "B", "a", "A"],
+            "Equal lines keep their order and lines without a number stay last"
+        );
+    }
+
+    #[test]
+    fn test_sort_lines_numeric() {
+        assert_eq!(
+            sorted(
+                &["10 apples", "n/a", "9 pears", "-2.5 debt"],
+                SortOptions {
+                    numeric: true,
+                    ..Default::default()
+                }
+            ),
+            ["-2.5 debt", "9 pears", "10 apples", "n/a"]
+        );
+    }
+
+    #[test]
+    fn test_sort_lines_natural() {
+        assert_eq!(
+            sorted(
+                &["item10", "item2", "item02", "Item1", "v2.10", "v2.9"],
+                SortOptions {
+                    natural: true,
+                    case_insensitive: true,
+                    ..Default::default()
+                }
+            ),
+            ["Item1", "item2", "item02", "item10", "v2.9", "v2.10"]
+        );
+    }
+
+    #[test]
+    fn test_sort_lines_unique() {
+        assert_eq!(
+            sorted(
+                &["b", "A", "a", "b"],
+                SortOptions {
+                    unique: true,
+                    case_insensitive: true,
+                    ..Default::default()
+                }
+            ),
+            ["A", "b"]
+        );
+    }
+}
@@ -0,0 +1,44 @@
+/// What typing `typed` right after `before` (the line up to the cursor) turns
+/// into with smart typography: how many bytes at the end of `before` go along with
+/// the typed character, and what replaces them. Straight quotes curl according to
+/// what's before them, `--` becomes an en dash and a third `-` an em dash, `...`
+/// an ellipsis. Inside a `` `code` `` span nothing changes.
+pub fn substitute(before: &str, typed: char) -> Option<(usize, String)> {
+    if before.matches('`').count() % 2 == 1 {
+        return None;
+    }
+
+    let previous = before.chars().next_back();
+    let opens = previous.is_none_or(|c| c.is_whitespace() || "([{<“‘—–-/".contains(c));
+
+    match typed {
+        '"' => Some((0, if opens { "“" } else { "”" }.to_string())),
+        '\'' => Some((0, if opens { "‘" } else { "’" }.to_string())),
+        '-' if before.ends_with('–') => Some(('–'.len_utf8(), "—".to_string())),
+        '-' if before.ends_with('-') => Some((1, "–".to_string())),
+        '.' if before.ends_with("..") => Some((2, "…".to_string())),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_substitute() {
+        let typed = |before: &str, c: char| {
+            let (back, with) = substitute(before, c).unwrap_or((0, c.to_string()));
+            format!("{}{with}", &before[..before.len() - back])
+        };
+
+        assert_eq!(typed("", '"'), "“");
+        assert_eq!(typed("say “hi", '"'), "say “hi”");
+        assert_eq!(typed("don", '\''), "don’");
+        assert_eq!(typed("(", '\''), "(‘");
+        assert_eq!(typed("a -", '-'), "a –");
+        assert_eq!(typed("a –", '-'), "a —");
+        assert_eq!(typed("wait..", '.'), "wait…");
+        assert_eq!(typed("run `a -", '-'), "run `a --");
+    }
+}
@@ -0,0 +1,970 @@
+/// File extensions treated as notes when scanning a workspace.
+pub const NOTE_EXTENSIONS: &[&str] = &["md", "markdown", "txt"];
+
+/// The name a note is linked by: its file name without the extension.
+pub fn note_name(path: &std::path::Path) -> Option<&str> {
+    path.file_stem()?.to_str()
+}
+
+pub fn is_note(path: &std::path::Path) -> bool {
+    path.extension()
+        .and_then(std::ffi::OsStr::to_str)
+        .is_some_and(|ext| NOTE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// Every note under `root`, sorted. Hidden files and directories (`.git`, ...) are skipped.
+pub fn notes(root: &std::path::Path) -> Vec<std::path::PathBuf> {
+    let mut found = Vec::new();
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+
+            match entry.file_type() {
+                Ok(kind) if kind.is_dir() => pending.push(path),
+                Ok(kind) if kind.is_file() && is_note(&path) => found.push(path),
+                _ => {}
+            }
+        }
+    }
+
+    found.sort();
+    found
+}
+
+/// Folder, next to a note, that archiving moves it into.
+pub const ARCHIVE_DIR: &str = "archive";
+
+/// Whether `note` sits in an [`ARCHIVE_DIR`] somewhere below `root`.
+pub fn is_archived(root: &std::path::Path, note: &std::path::Path) -> bool {
+    note.strip_prefix(root)
+        .ok()
+        .and_then(std::path::Path::parent)
+        .is_some_and(|dir| dir.components().any(|part| part.as_os_str() == ARCHIVE_DIR))
+}
+
+/// The notes under `root` to list and search: [`notes`], without archived ones
+/// unless `include_archived`.
+pub fn listed_notes(root: &std::path::Path, include_archived: bool) -> Vec<std::path::PathBuf> {
+    let mut notes = notes(root);
+
+    if !include_archived {
+        notes.retain(|note| !is_archived(root, note));
+    }
+    notes
+}
+
+/// Where archiving `note` moves it, or `None` if it's archived already.
+pub fn archived_path(note: &std::path::Path) -> Option<std::path::PathBuf> {
+    let dir = note.parent()?;
+
+    if dir.file_name() == Some(std::ffi::OsStr::new(ARCHIVE_DIR)) {
+        return None;
+    }
+    Some(dir.join(ARCHIVE_DIR).join(note.file_name()?))
+}
+
+/// Where unarchiving `note` moves it back to, or `None` if it isn't archived.
+pub fn unarchived_path(note: &std::path::Path) -> Option<std::path::PathBuf> {
+    let dir = note.parent()?;
+
+    if dir.file_name() != Some(std::ffi::OsStr::new(ARCHIVE_DIR)) {
+        return None;
+    }
+    Some(dir.parent()?.join(note.file_name()?))
+}
+
+/// Rewrites the relative Markdown link targets in `line`, `[label](target)` and
+/// `![alt](target)`, for the note moving one folder down into [`ARCHIVE_DIR`]
+/// (`into_archive`) or back up out of it. URLs, absolute paths and `#anchors` stay.
+/// Returns `None` if nothing changed.
+pub fn rebase_links(line: &str, into_archive: bool) -> Option<String> {
+    let mut out = String::with_capacity(line.len() + 8);
+    let mut rest = line;
+    let mut changed = false;
+
+    while let Some(at) = rest.find("](") {
+        let (before, after) = rest.split_at(at + 2);
+        let bracketed = after.starts_with('<');
+        let target_start = usize::from(bracketed);
+        let target_len = if bracketed {
+            after[1..].find('>')
+        } else {
+            after.find([')', ' '])
+        };
+
+        out.push_str(before);
+
+        let Some(target_len) = target_len else {
+            rest = after;
+            continue;
+        };
+        let target = &after[target_start..target_start + target_len];
+        let relative = !target.is_empty()
+            && !target.contains("://")
+            && !target.starts_with(['/', '#', '\\'])
+            && !target.starts_with("mailto:");
+
+        if relative {
+            let rebased = if into_archive {
+                format!("../{target}")
+            } else {
+                target
+                    .strip_prefix("../")
+                    .map_or_else(|| format!("{ARCHIVE_DIR}/{target}"), str::to_string)
+            };
+
+            out.push_str(&after[..target_start]);
+            out.push_str(&rebased);
+            rest = &after[target_start + target_len..];
+            changed = true;
+        } else {
+            rest = after;
+        }
+    }
+
+    out.push_str(rest);
+    changed.then_some(out)
+}
+
+/// `line` with what's between the brackets of each wiki link replaced by what
+/// `f` makes of it, where it makes anything. Returns `None` if nothing changed.
+fn map_wiki_links(line: &str, mut f: impl FnMut(&str) -> Option<String>) -> Option<String> {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    let mut changed = false;
+
+    while let Some(open) = rest.find("[[") {
+        let inner_start = open + 2;
+        let Some(len) = rest[inner_start..].find("]]") else {
+            break;
+        };
+        let inner = &rest[inner_start..inner_start + len];
+
+        out.push_str(&rest[..inner_start]);
+
+        match f(inner) {
+            Some(mapped) => {
+                out.push_str(&mapped);
+                changed = true;
+            }
+            None => out.push_str(inner),
+        }
+
+        out.push_str("]]");
+        rest = &rest[inner_start + len + 2..];
+    }
+
+    out.push_str(rest);
+    changed.then_some(out)
+}
+
+/// Rewrites every `[[old]]` wiki link in `line` to point at `new`, keeping any
+/// `#heading` or `|alias` suffix. Names compare case-insensitively, like most
+/// file systems notes live on. Returns `None` if nothing matched.
+pub fn rename_links(line: &str, old: &str, new: &str) -> Option<String> {
+    map_wiki_links(line, |inner| {
+        let target_len = inner.find(['#', '|']).unwrap_or(inner.len());
+
+        inner[..target_len]
+            .trim()
+            .eq_ignore_ascii_case(old)
+            .then(|| format!("{new}{}", &inner[target_len..]))
+    })
+}
+
+/// Turns every `[[name]]` wiki link in `line` back into plain text: its alias if
+/// it has one, what it links to otherwise. Names compare case-insensitively.
+/// Returns `None` if nothing matched.
+pub fn unlink(line: &str, name: &str) -> Option<String> {
+    let mut out = String::with_capacity(line.len());
+    let mut rest = line;
+    let mut changed = false;
+
+    while let Some(open) = rest.find("[[") {
+        let Some(len) = rest[open + 2..].find("]]") else {
+            break;
+        };
+        let inner = &rest[open + 2..open + 2 + len];
+        let end = open + len + 4;
+
+        out.push_str(&rest[..open]);
+        if WikiLink::parse(inner).note.eq_ignore_ascii_case(name) {
+            let (target, alias) = inner.split_once('|').unwrap_or((inner, ""));
+
+            out.push_str(
+                if alias.trim().is_empty() {
+                    target
+                } else {
+                    alias
+                }
+                .trim(),
+            );
+            changed = true;
+        } else {
+            out.push_str(&rest[open..end]);
+        }
+        rest = &rest[end..];
+    }
+
+    out.push_str(rest);
+    changed.then_some(out)
+}
+
+/// The wiki links in `line`, in order.
+pub fn wiki_links(line: &str) -> Vec<WikiLink> {
+    let mut links = Vec::new();
+
+    map_wiki_links(line, |inner| {
+        links.push(WikiLink::parse(inner));
+        None
+    });
+    links
+}
+
+/// Rewrites every `[[note#old]]` wiki link in `line` to point at heading `new`
+/// instead, keeping any `|alias`. `note` is `""` for `[[#old]]` links within a
+/// note. Headings compare by their slugs, so `[[note#old-title]]` matches too.
+/// Returns `None` if nothing matched.
+pub fn rename_heading_links(line: &str, note: &str, old: &str, new: &str) -> Option<String> {
+    let old = crate::toc::slug(old);
+
+    map_wiki_links(line, |inner| {
+        let link = WikiLink::parse(inner);
+        let alias_at = inner.find('|').unwrap_or(inner.len());
+        let hash = inner[..alias_at].find('#')?;
+
+        (link.note.eq_ignore_ascii_case(note)
+            && link
+                .heading
+                .is_some_and(|heading| crate::toc::slug(&heading) == old))
+        .then(|| format!("{}#{new}{}", &inner[..hash], &inner[alias_at..]))
+    })
+}
+
+/// Where a `[[note#heading|alias]]` wiki link leads.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WikiLink {
+    /// Name of the note, `""` for a heading of the note the link is in.
+    pub note: String,
+    pub heading: Option<String>,
+}
+
+impl WikiLink {
+    /// The link with `inner` between its brackets.
+    fn parse(inner: &str) -> Self {
+        let target = inner.split('|').next().unwrap_or(inner);
+        let (note, heading) = match target.split_once('#') {
+            Some((note, heading)) => (note, Some(heading.trim().to_string())),
+            None => (target, None),
+        };
+
+        Self {
+            note: note.trim().to_string(),
+            heading,
+        }
+    }
+
+    /// The wiki link in `line` that byte `col` is on, brackets included, or
+    /// right after.
+    pub fn at(line: &str, col: usize) -> Option<Self> {
+        let mut from = 0;
+
+        while let Some(open) = line[from..].find("[[").map(|i| from + i) {
+            let len = line[open + 2..].find("]]")?;
+            let end = open + len + 4;
+
+            if col < open {
+                break;
+            }
+            if col <= end {
+                return Some(Self::parse(&line[open + 2..open + 2 + len]));
+            }
+            from = end;
+        }
+        None
+    }
+}
+
+/// Where a link leads: a note, maybe one of its headings, or anything else by URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkTarget {
+    Note(WikiLink),
+    Url(String),
+}
+
+impl LinkTarget {
+    /// What a Markdown link's `target` leads to: a `#heading` of the same note
+    /// or a `note.md#heading` are notes, anything else a URL.
+    fn parse(target: &str) -> Self {
+        let (path, heading) = match target.split_once('#') {
+            Some((path, heading)) => (path, Some(percent_decode(heading))),
+            None => (target, None),
+        };
+        let path = std::path::PathBuf::from(percent_decode(path));
+        let note = if path.as_os_str().is_empty() {
+            Some(String::new())
+        } else if crate::clipboard::as_url(target).is_none() && is_note(&path) {
+            note_name(&path).map(str::to_string)
+        } else {
+            None
+        };
+
+        match note {
+            Some(note) => LinkTarget::Note(WikiLink { note, heading }),
+            None => LinkTarget::Url(target.to_string()),
+        }
+    }
+}
+
+/// Where the `[[wiki link]]` or `[text](target)` Markdown link in `line` that byte
+/// `col` is on leads.
+pub fn link_target_at(line: &str, col: usize) -> Option<LinkTarget> {
+    if let Some(link) = WikiLink::at(line, col) {
+        return Some(LinkTarget::Note(link));
+    }
+
+    let mut from = 0;
+
+    while let Some(open) = line[from..].find('[').map(|i| from + i) {
+        let Some(mid) = line[open..].find("](").map(|i| open + i) else {
+            break;
+        };
+
+        // `[` that doesn't open this link's text.
+        if line[open + 1..mid].contains(['[', ']']) {
+            from = open + 1;
+            continue;
+        }
+
+        let rest = &line[mid + 2..];
+        let (target, len) = match rest.strip_prefix('<') {
+            Some(bracketed) => {
+                let Some(end) = bracketed.find(">)") else {
+                    break;
+                };
+                (&bracketed[..end], end + 3)
+            }
+            None => {
+                let Some(end) = rest.find(')') else {
+                    break;
+                };
+                let target = rest[..end].split_whitespace().next().unwrap_or_default();
+                (target, end + 1)
+            }
+        };
+        let end = mid + 2 + len;
+
+        if col < open {
+            break;
+        }
+        if col < end {
+            return (!target.is_empty()).then(|| LinkTarget::parse(target));
+        }
+        from = end;
+    }
+    None
+}
+
+/// The note under `root` called `name`, compared case-insensitively.
+pub fn find_note(root: &std::path::Path, name: &str) -> Option<std::path::PathBuf> {
+    notes(root)
+        .into_iter()
+        .find(|path| note_name(path).is_some_and(|have| have.eq_ignore_ascii_case(name)))
+}
+
+/// Notes under `root` containing at least one link to `name`.
+pub fn referencing_notes(root: &std::path::Path, name: &str) -> Vec<std::path::PathBuf> {
+    notes(root)
+        .into_iter()
+        .filter(|path| {
+            std::fs::read_to_string(path).is_ok_and(|text| {
+                text.lines()
+                    .any(|line| rename_links(line, name, name).is_some())
+            })
+        })
+        .collect()
+}
+
+/// The text of the file at `path` with all links to `old` pointed at `new`, or
+/// `None` if it has none.
+///
+/// # Errors
+///
+/// Returns an error if the file can't be read.
+pub fn links_rewritten(
+    path: &std::path::Path,
+    old: &str,
+    new: &str,
+) -> std::io::Result<Option<String>> {
+    lines_rewritten(path, |line| rename_links(line, old, new))
+}
+
+/// The text of the file at `path` with all links to heading `old` of `note`
+/// pointed at `new`, see [`rename_heading_links`], or `None` if it has none.
+///
+/// # Errors
+///
+/// Returns an error if the file can't be read.
+pub fn heading_links_rewritten(
+    path: &std::path::Path,
+    note: &str,
+    old: &str,
+    new: &str,
+) -> std::io::Result<Option<String>> {
+    lines_rewritten(path, |line| rename_heading_links(line, note, old, new))
+}
+
+/// The text of the file at `path` with each line `f` rewrites rewritten, or
+/// `None` if it rewrites none.
+fn lines_rewritten(
+    path: &std::path::Path,
+    f: impl Fn(&str) -> Option<String>,
+) -> std::io::Result<Option<String>> {
+    let text = std::fs::read_to_string(path)?;
+    let mut changed = false;
+    let rewritten: String = text
+        .split_inclusive('\n')
+        .map(|line| match f(line) {
+            Some(renamed) => {
+                changed = true;
+                std::borrow::Cow::Owned(renamed)
+            }
+            None => std::borrow::Cow::Borrowed(line),
+        })
+        .collect();
+
+    Ok(changed.then_some(rewritten))
+}
+
+/// Points all links to `old` in the file at `path` to `new`. The file is replaced
+/// atomically, so a crash never leaves it half written. Returns whether anything changed.
+///
+/// # Errors
+///
+/// Returns an error if the file can't be read or written.
+pub fn rewrite_links(path: &std::path::Path, old: &str, new: &str) -> std::io::Result<bool> {
+    let Some(rewritten) = links_rewritten(path, old, new)? else {
+        return Ok(false);
+    };
+    let mut batch = BatchSave::default();
+
+    batch.write(path, rewritten);
+    batch.commit()?;
+
+    Ok(true)
+}
+
+/// Turns all links to `name` in the file at `path` into plain text, see
+/// [`unlink`]. The file is replaced atomically. Returns whether anything changed.
+///
+/// # Errors
+///
+/// Returns an error if the file can't be read or written.
+pub fn remove_links(path: &std::path::Path, name: &str) -> std::io::Result<bool> {
+    let Some(unlinked) = lines_rewritten(path, |line| unlink(line, name))? else {
+        return Ok(false);
+    };
+    let mut batch = BatchSave::default();
+
+    batch.write(path, unlinked);
+    batch.commit()?;
+
+    Ok(true)
+}
+
+#[derive(Debug)]
+enum BatchOp {
+    Write(Vec<u8>),
+    Remove,
+}
+
+/// Changes to several files that land together or not at all, for operations such
+/// as a rename that rewrites links across the workspace.
+///
+/// [`BatchSave::commit`] first writes every new file as a temporary file next to its
+/// target. Only once all of them were written are they moved into place, and the
+/// removals done. If one of those steps fails, the files already touched get their
+/// previous contents back.
+#[derive(Debug, Default)]
+pub struct BatchSave {
+    ops: Vec<(std::path::PathBuf, BatchOp)>,
+}
+
+impl BatchSave {
+    /// Replaces the contents of `path`, creating the file if needed.
+    pub fn write(&mut self, path: impl Into<std::path::PathBuf>, bytes: impl Into<Vec<u8>>) {
+        self.ops.push((path.into(), BatchOp::Write(bytes.into())));
+    }
+
+    /// Deletes `path` once everything else is in place.
+    pub fn remove(&mut self, path: impl Into<std::path::PathBuf>) {
+        self.ops.push((path.into(), BatchOp::Remove));
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+
+    /// Applies every change, or none. Returns the files that were written.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error hit. The files are then as they were before, unless
+    /// restoring one failed too, which is logged.
+    pub fn commit(self) -> std::io::Result<Vec<std::path::PathBuf>> {
+        // Stage: nothing outside temporary files is touched yet, and dropping them
+        // on an early return deletes them.
+        let mut staged = Vec::new();
+        let mut removals = Vec::new();
+
+        for (path, op) in self.ops {
+            match op {
+                BatchOp::Write(bytes) => {
+                    let dir = path.parent().unwrap_or(std::path::Path::new("."));
+                    let mut tmp = tempfile::NamedTempFile::new_in(dir)?;
+
+                    std::io::Write::write_all(&mut tmp, &bytes)?;
+                    tmp.as_file().sync_all()?;
+                    staged.push((path, tmp));
+                }
+                BatchOp::Remove => removals.push(path),
+            }
+        }
+
+        // Apply: each file's previous contents are kept until the whole batch landed.
+        let mut done = Vec::new();
+        let mut written = Vec::new();
+        let result = (|| {
+            for (path, tmp) in staged {
+                let previous = Self::previous_contents(&path)?;
+
+                tmp.persist(&path).map_err(|err| err.error)?;
+                written.push(path.clone());
+                done.push((path, previous));
+            }
+            for path in removals {
+                let Some(previous) = Self::previous_contents(&path)? else {
+                    continue;
+                };
+
+                std::fs::remove_file(&path)?;
+                done.push((path, Some(previous)));
+            }
+            Ok(())
+        })();
+
+        if let Err(err) = result {
+            for (path, previous) in done.into_iter().rev() {
+                let restored = match previous {
+                    Some(bytes) => std::fs::write(&path, bytes),
+                    None => std::fs::remove_file(&path),
+                };
+
+                if let Err(restore_err) = restored {
+                    tracing::error!(?path, "Error rolling back batch save: {:?}", restore_err);
+                }
+            }
+            return Err(err);
+        }
+
+        Ok(written)
+    }
+
+    /// What's at `path` now, or `None` if nothing is.
+    fn previous_contents(path: &std::path::Path) -> std::io::Result<Option<Vec<u8>>> {
+        match std::fs::read(path) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// Folder, next to the note, that attachments are copied into.
+pub const ASSETS_DIR: &str = "assets";
+
+pub const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "bmp", "svg", "webp"];
+
+pub fn is_image(path: &std::path::Path) -> bool {
+    path.extension()
+        .and_then(std::ffi::OsStr::to_str)
+        .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// `text` with everything but unreserved characters percent-encoded, for a URL.
+pub fn percent_encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// Decodes `%XX` escapes. Malformed escapes are kept as they are.
+pub fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| text.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match escaped {
+            Some(byte) => {
+                out.push(byte);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// File paths carried by a drag-and-drop or a paste of copied files: one per
+/// line, either plain paths or `file://` URIs.
+pub fn dropped_paths(text: &str) -> Vec<std::path::PathBuf> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| match line.strip_prefix("file://") {
+            // Drop the host part, which is empty or `localhost` for local files.
+            Some(uri) => {
+                std::path::PathBuf::from(percent_decode(&uri[uri.find('/').unwrap_or(0)..]))
+            }
+            None => std::path::PathBuf::from(line),
+        })
+        .collect()
+}
+
+/// Copies `source` into the assets folder next to `note`, picking a free name if
+/// one is taken. Returns the copy's path relative to the note, with `/` separators
+/// so the Markdown link works everywhere.
+///
+/// # Errors
+///
+/// Returns an error if the assets folder can't be created or the copy fails.
+pub fn import_attachment(
+    note: &std::path::Path,
+    source: &std::path::Path,
+) -> std::io::Result<String> {
+    let assets = note
+        .parent()
+        .unwrap_or(std::path::Path::new("."))
+        .join(ASSETS_DIR);
+    let file_name = source
+        .file_name()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "Not a file"))?;
+
+    std::fs::create_dir_all(&assets)?;
+
+    let stem = source.file_stem().unwrap_or(file_name).to_string_lossy();
+    let ext = source
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    let mut target = assets.join(file_name);
+    let mut n = 1;
+
+    while target.exists() {
+        target = assets.join(format!("{stem}-{n}{ext}"));
+        n += 1;
+    }
+
+    std::fs::copy(source, &target)?;
+
+    Ok(format!(
+        "{ASSETS_DIR}/{}",
+        target.file_name().unwrap_or_default().to_string_lossy()
+    ))
+}
+
+/// Markdown link to an attachment: an image embed for pictures, a plain link otherwise.
+/// Link targets with spaces are wrapped in `<>`, which CommonMark allows.
+pub fn attachment_link(label: &str, target: &str) -> String {
+    let target = if target.contains(' ') {
+        format!("<{target}>")
+    } else {
+        target.to_string()
+    };
+
+    if is_image(std::path::Path::new(label)) {
+        let alt = std::path::Path::new(label)
+            .file_stem()
+            .map_or_else(String::new, |stem| stem.to_string_lossy().into_owned());
+        format!("![{alt}]({target})")
+    } else {
+        format!("[{label}]({target})")
+    }
+}
+
+/// Target of a line that holds nothing but an image embed, `![alt](target)`.
+/// A `<...>` target may contain spaces; a trailing `"title"` is ignored.
+pub fn image_link_target(line: &str) -> Option<&str> {
+    let (_, rest) = line.trim().strip_prefix("![")?.split_once("](")?;
+    let rest = rest.strip_suffix(')')?.trim();
+    let target = match rest.strip_prefix('<') {
+        Some(bracketed) => bracketed.split_once('>')?.0,
+        None => rest.split_whitespace().next()?,
+    };
+
+    (!target.is_empty()).then_some(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_paths_and_links() {
+        let root = std::path::Path::new("/notes");
+        let note = root.join("work").join("plan.md");
+        let archived = archived_path(&note).unwrap();
+
+        assert_eq!(archived, root.join("work").join("archive").join("plan.md"));
+        assert_eq!(archived_path(&archived), None);
+        assert_eq!(unarchived_path(&archived), Some(note.clone()));
+        assert_eq!(unarchived_path(&note), None);
+        assert!(is_archived(root, &archived));
+        assert!(!is_archived(root, &note));
+
+        let line = "![chart](assets/a.png) [up](../b.md) [web](https://x.org) [here](#top)";
+        let moved = rebase_links(line, true).unwrap();
+
+        assert_eq!(
+            moved,
+            "![chart](../assets/a.png) [up](../../b.md) [web](https://x.org) [here](#top)"
+        );
+        assert_eq!(rebase_links(&moved, false).as_deref(), Some(line));
+        assert_eq!(
+            rebase_links("[kept](<my notes/a.md>)", false).as_deref(),
+            Some("[kept](<archive/my notes/a.md>)")
+        );
+        assert_eq!(rebase_links("no links here", true), None);
+    }
+
+    #[test]
+    fn test_rename_links() {
+        assert_eq!(
+            rename_links(
+                "see [[Old Note]] and [[old note#Intro|here]]",
+                "Old Note",
+                "New"
+            ),
+            Some("see [[New]] and [[New#Intro|here]]".to_string())
+        );
+        assert_eq!(
+            rename_links("[[Other]] [[Old Note", "Old Note", "New"),
+            None
+        );
+        assert_eq!(rename_links("no links", "Old Note", "New"), None);
+
+        assert_eq!(
+            rename_heading_links(
+                "[[Note#Old Title|see]], [[note#old-title]], [[#Old Title]], [[Other#Old Title]]",
+                "note",
+                "Old Title",
+                "New Title"
+            ),
+            Some(
+                "[[Note#New Title|see]], [[note#New Title]], [[#Old Title]], [[Other#Old Title]]"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            rename_heading_links("[[#Old Title]]", "", "Old Title", "New").as_deref(),
+            Some("[[#New]]")
+        );
+        assert_eq!(
+            unlink("[[Gone|later]], [[gone#Top]] and [[Kept]]", "Gone").as_deref(),
+            Some("later, gone#Top and [[Kept]]")
+        );
+        assert_eq!(unlink("[[Kept]]", "Gone"), None);
+
+        let line = "see [docs](https://docs.rs \"Docs\") and [[x]] [plan](../Work%20Plan.md#Q3)";
+        let target = |col| link_target_at(line, col);
+
+        assert_eq!(target(2), None);
+        assert_eq!(
+            target(6),
+            Some(LinkTarget::Url("https://docs.rs".to_string()))
+        );
+        assert_eq!(
+            target(39),
+            Some(LinkTarget::Note(WikiLink {
+                note: "x".to_string(),
+                heading: None,
+            }))
+        );
+        assert_eq!(
+            target(48),
+            Some(LinkTarget::Note(WikiLink {
+                note: "Work Plan".to_string(),
+                heading: Some("Q3".to_string()),
+            }))
+        );
+        assert_eq!(
+            link_target_at("[a] b [c](<x (1).md>)", 8),
+            Some(LinkTarget::Note(WikiLink {
+                note: "x (1)".to_string(),
+                heading: None,
+            }))
+        );
+        assert_eq!(link_target_at("[a] b [c](d)", 1), None);
+
+        let line = "go to [[Trips#Day Two|day two]] now";
+        let link = WikiLink {
+            note: "Trips".to_string(),
+            heading: Some("Day Two".to_string()),
+        };
+
+        assert_eq!(WikiLink::at(line, 6), Some(link.clone()));
+        assert_eq!(WikiLink::at(line, 20), Some(link.clone()));
+        assert_eq!(WikiLink::at(line, 31), Some(link));
+        assert_eq!(WikiLink::at(line, 32), None);
+        assert_eq!(WikiLink::at(line, 2), None);
+    }
+
+    #[test]
+    fn test_referencing_notes_and_rewrite() {
+        let dir = tempfile::tempdir().unwrap();
+        let linking = dir.path().join("sub").join("index.md");
+        std::fs::create_dir_all(linking.parent().unwrap()).unwrap();
+        std::fs::write(&linking, "- [[target]]\r\n- [[elsewhere]]\r\n").unwrap();
+        std::fs::write(dir.path().join("target.md"), "body").unwrap();
+        std::fs::write(dir.path().join("image.png"), "[[target]]").unwrap();
+        std::fs::create_dir(dir.path().join(".git")).unwrap();
+        std::fs::write(dir.path().join(".git").join("x.md"), "[[target]]").unwrap();
+
+        assert_eq!(
+            referencing_notes(dir.path(), "target"),
+            vec![linking.clone()]
+        );
+
+        assert!(rewrite_links(&linking, "target", "renamed").unwrap());
+        assert_eq!(
+            std::fs::read_to_string(&linking).unwrap(),
+            "- [[renamed]]\r\n- [[elsewhere]]\r\n"
+        );
+        assert!(!rewrite_links(&linking, "target", "renamed").unwrap());
+    }
+
+    #[test]
+    fn test_batch_save_commits_or_rolls_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a.md");
+        let b = dir.path().join("b.md");
+        let gone = dir.path().join("gone.md");
+        std::fs::write(&a, "old a").unwrap();
+        std::fs::write(&gone, "bye").unwrap();
+
+        let mut batch = BatchSave::default();
+        batch.write(&a, "new a");
+        batch.write(&b, "new b");
+        batch.remove(&gone);
+
+        assert_eq!(batch.commit().unwrap(), vec![a.clone(), b.clone()]);
+        assert_eq!(std::fs::read_to_string(&a).unwrap(), "new a");
+        assert_eq!(std::fs::read_to_string(&b).unwrap(), "new b");
+        assert!(!gone.exists());
+
+        // A directory can't be replaced by a file, so the batch fails after `a` and
+        // `c` already landed, and both are undone.
+        let c = dir.path().join("c.md");
+        let blocker = dir.path().join("blocker");
+        std::fs::create_dir(&blocker).unwrap();
+
+        let mut batch = BatchSave::default();
+        batch.write(&a, "newer a");
+        batch.write(&c, "new c");
+        batch.write(&blocker, "file");
+
+        assert!(batch.commit().is_err());
+        assert_eq!(std::fs::read_to_string(&a).unwrap(), "new a");
+        assert!(!c.exists());
+        assert!(blocker.is_dir());
+        assert_eq!(
+            std::fs::read_dir(dir.path()).unwrap().count(),
+            3,
+            "No temporary files left behind"
+        );
+    }
+
+    #[test]
+    fn test_dropped_paths() {
+        assert_eq!(
+            dropped_paths("file:///home/me/My%20Pics/cat.png\r\n/tmp/dog.jpg\n"),
+            vec![
+                std::path::PathBuf::from("/home/me/My Pics/cat.png"),
+                std::path::PathBuf::from("/tmp/dog.jpg"),
+            ]
+        );
+        assert_eq!(
+            dropped_paths("file://localhost/a%2"),
+            vec![std::path::PathBuf::from("/a%2")]
+        );
+    }
+
+    #[test]
+    fn test_import_attachment_picks_free_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let note = dir.path().join("note.md");
+        let source = dir.path().join("my cat.png");
+        std::fs::write(&source, "png").unwrap();
+
+        assert_eq!(
+            import_attachment(&note, &source).unwrap(),
+            "assets/my cat.png"
+        );
+        assert_eq!(
+            import_attachment(&note, &source).unwrap(),
+            "assets/my cat-1.png"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("assets").join("my cat-1.png")).unwrap(),
+            "png"
+        );
+        assert_eq!(
+            attachment_link("my cat.png", "assets/my cat.png"),
+            "![my cat](<assets/my cat.png>)"
+        );
+        assert_eq!(
+            attachment_link("a.pdf", "assets/a.pdf"),
+            "[a.pdf](assets/a.pdf)"
+        );
+    }
+
+    #[test]
+    fn test_image_link_target() {
+        assert_eq!(
+            image_link_target("![cat](assets/cat.png)"),
+            Some("assets/cat.png")
+        );
+        assert_eq!(
+            image_link_target("  ![my cat](<assets/my cat.png> \"Title\")"),
+            Some("assets/my cat.png")
+        );
+        assert_eq!(image_link_target("text ![cat](cat.png)"), None);
+        assert_eq!(image_link_target("![cat]()"), None);
+    }
+}
@@ -0,0 +1,261 @@
+/// Read-only bytes of a file: memory-mapped when the OS allows it, read into
+/// memory when it doesn't.
+///
+/// Mapping fails on network mounts that forbid it and in some sandboxes, and
+/// virtual files such as those under `/proc` report a length of zero but still
+/// have contents when read. Both fall back to reading the whole file.
+#[derive(Debug)]
+pub enum FileBacking {
+    Mapped(crate::mmap::MmapFile),
+    Memory {
+        bytes: Vec<u8>,
+        path: std::path::PathBuf,
+    },
+}
+
+/// How an opened file is held while it's being edited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AccessMode {
+    /// Map the file and read it lazily. Cheap for huge files, but the open mapping
+    /// keeps other programs from replacing the file on Windows.
+    #[default]
+    Mapped,
+    /// Read the file into memory and close it straight away, so sync tools such as
+    /// Dropbox or OneDrive can rename or overwrite it while the note is open.
+    Cooperative,
+}
+
+impl From<crate::mmap::MmapFile> for FileBacking {
+    fn from(mmap_file: crate::mmap::MmapFile) -> Self {
+        FileBacking::Mapped(mmap_file)
+    }
+}
+
+impl FileBacking {
+    /// Maps the file at `path`, or reads it into memory if it can't be mapped.
+    ///
+    /// # Errors
+    ///
+    /// - `std::io::Error` if the file can't be opened or read at all.
+    pub fn open(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        let reports_empty = std::fs::metadata(path)?.len() == 0;
+
+        if !reports_empty && let Ok(mmap_file) = crate::mmap::MmapFile::open(path) {
+            return Ok(FileBacking::Mapped(mmap_file));
+        }
+
+        Self::read(path)
+    }
+
+    /// Opens `path` the way `mode` asks: see [`Self::open`] and [`Self::read`].
+    ///
+    /// # Errors
+    ///
+    /// - `std::io::Error` if the file can't be opened or read at all.
+    pub fn open_with(path: impl AsRef<std::path::Path>, mode: AccessMode) -> std::io::Result<Self> {
+        match mode {
+            AccessMode::Mapped => Self::open(path),
+            AccessMode::Cooperative => Self::read(path),
+        }
+    }
+
+    /// Reads the whole file at `path` into memory, without trying to map it.
+    ///
+    /// # Errors
+    ///
+    /// - `std::io::Error` if the file can't be opened or read.
+    pub fn read(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        let mut bytes = Vec::new();
+
+        // Read in chunks rather than `fs::read`, which sizes its buffer from the
+        // reported length and would trust the zero of a virtual file.
+        std::io::Read::read_to_end(&mut std::fs::File::open(path)?, &mut bytes)?;
+
+        Ok(FileBacking::Memory {
+            bytes,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Whether the bytes come from a memory map rather than a copy in memory.
+    #[inline]
+    #[must_use]
+    pub fn is_mapped(&self) -> bool {
+        matches!(self, FileBacking::Mapped(_))
+    }
+
+    /// STRICT: Gets an exact slice of bytes. See [`crate::mmap::MmapFile::get_bytes_exact`].
+    #[inline]
+    #[must_use]
+    pub fn get_bytes_exact(&self, start: usize, length: usize) -> Option<&[u8]> {
+        self.as_slice().get(start..start.checked_add(length)?)
+    }
+
+    /// FORGIVING: Gets bytes starting at `start`, up to `length`. See
+    /// [`crate::mmap::MmapFile::get_bytes_clamped`].
+    #[inline]
+    #[must_use]
+    pub fn get_bytes_clamped(&self, start: usize, length: usize) -> &[u8] {
+        match self {
+            FileBacking::Mapped(mmap_file) => mmap_file.get_bytes_clamped(start, length),
+            FileBacking::Memory { bytes, .. } => {
+                let start = start.min(bytes.len());
+                let end = start.saturating_add(length).min(bytes.len());
+
+                &bytes[start..end]
+            }
+        }
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn as_slice(&self) -> &[u8] {
+        match self {
+            FileBacking::Mapped(mmap_file) => mmap_file.as_slice(),
+            FileBacking::Memory { bytes, .. } => bytes,
+        }
+    }
+
+    /// File length in bytes.
+    #[inline]
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.as_slice().len()
+    }
+
+    /// Whether file is empty.
+    #[inline]
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Path of the backing file.
+    #[inline]
+    #[must_use]
+    pub fn path(&self) -> &std::path::Path {
+        match self {
+            FileBacking::Mapped(mmap_file) => mmap_file.path(),
+            FileBacking::Memory { path, .. } => path,
+        }
+    }
+
+    /// Whether the mapped file shrank on disk. Always `false` for a copy in memory.
+    #[must_use]
+    pub fn is_truncated(&self) -> bool {
+        match self {
+            FileBacking::Mapped(mmap_file) => mmap_file.is_truncated(),
+            FileBacking::Memory { .. } => false,
+        }
+    }
+
+    /// Swaps a mapping for a copy in memory, so later reads can't fault however the
+    /// file changes on disk. Only the part still on disk is read, so a truncated file
+    /// comes out shorter. Returns how many bytes it lost.
+    pub fn detach(&mut self) -> usize {
+        let FileBacking::Mapped(mmap_file) = self else {
+            return 0;
+        };
+        let len = mmap_file.len();
+        let readable = mmap_file
+            .len_on_disk()
+            .map_or(len, |on_disk| len.min(on_disk as usize));
+        let bytes = mmap_file.get_bytes_clamped(0, readable).to_vec();
+
+        *self = FileBacking::Memory {
+            bytes,
+            path: mmap_file.path().to_path_buf(),
+        };
+
+        len - readable
+    }
+
+    /// See [`crate::mmap::MmapFile::advise`]. A no-op for bytes already in memory.
+    ///
+    /// # Errors
+    ///
+    /// - `std::io::Error` if `madvise` rejects the hint.
+    pub fn advise(&self, pattern: crate::mmap::AccessPattern) -> std::io::Result<()> {
+        match self {
+            FileBacking::Mapped(mmap_file) => mmap_file.advise(pattern),
+            FileBacking::Memory { .. } => Ok(()),
+        }
+    }
+
+    /// See [`crate::mmap::MmapFile::prefetch`]. A no-op for bytes already in memory.
+    ///
+    /// # Errors
+    ///
+    /// - `std::io::Error` if `madvise` rejects the hint.
+    pub fn prefetch(&self, start: usize, length: usize) -> std::io::Result<()> {
+        match self {
+            FileBacking::Mapped(mmap_file) => mmap_file.prefetch(start, length),
+            FileBacking::Memory { .. } => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_open_maps_or_reads() {
+        let dir = tempfile::tempdir().unwrap();
+        let note = dir.path().join("note.md");
+        let empty = dir.path().join("empty.md");
+        std::fs::write(&note, "hello\n").unwrap();
+        std::fs::write(&empty, "").unwrap();
+
+        let mapped = FileBacking::open(&note).unwrap();
+        assert!(mapped.is_mapped());
+        assert_eq!(mapped.get_bytes_exact(0, 5), Some(&b"hello"[..]));
+
+        let read = FileBacking::read(&note).unwrap();
+        assert!(!read.is_mapped());
+        assert_eq!(read.get_bytes_clamped(3, 100), b"lo\n");
+        assert_eq!(read.get_bytes_clamped(100, 1), b"");
+        assert_eq!(read.get_bytes_exact(4, 5), None);
+        assert_eq!(read.path(), note);
+
+        let empty = FileBacking::open(&empty).unwrap();
+        assert!(!empty.is_mapped());
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn test_detach_after_truncation() {
+        let dir = tempfile::tempdir().unwrap();
+        let note = dir.path().join("note.md");
+        std::fs::write(&note, "0123456789").unwrap();
+
+        let mut backing = FileBacking::open(&note).unwrap();
+        assert!(!backing.is_truncated());
+
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(&note)
+            .unwrap()
+            .set_len(4)
+            .unwrap();
+
+        assert!(backing.is_truncated());
+        assert_eq!(backing.detach(), 6);
+        assert!(!backing.is_mapped());
+        assert!(!backing.is_truncated());
+        assert_eq!(backing.as_slice(), b"0123");
+        assert_eq!(backing.detach(), 0);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_open_virtual_file() {
+        // Reports a length of zero, but has contents.
+        let status = FileBacking::open("/proc/self/status").unwrap();
+
+        assert!(!status.is_mapped());
+        assert!(status.as_slice().starts_with(b"Name:"));
+    }
+}
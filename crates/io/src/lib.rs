@@ -1 +1,2 @@
+pub mod backing;
 pub mod mmap;
@@ -1,6 +1,16 @@
+/// How the mapping is about to be read, passed to the OS as a paging hint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessPattern {
+    /// Front to back, e.g. while building the line index: read ahead aggressively.
+    Sequential,
+    /// Scattered, e.g. while editing: don't read ahead past what was asked for.
+    Random,
+}
+
 #[derive(Debug)]
 pub struct MmapFile {
-    _file: std::fs::File,
+    /// Kept open so the mapping stays valid, and to check the length on disk.
+    file: std::fs::File,
     mmap: memmap2::Mmap,
     path: std::path::PathBuf,
 }
@@ -11,7 +21,16 @@ impl MmapFile {
     /// - `std::io::Error` if the file cannot be opened or mapped.
     pub fn open(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
         let path_buf = path.as_ref().to_path_buf();
-        let file = std::fs::File::open(&path_buf)?;
+        let mut options = std::fs::OpenOptions::new();
+
+        options.read(true);
+        // Let other programs read, write, rename and delete the file while it's open.
+        // Windows still won't let them overwrite the mapped bytes in place; see
+        // `crate::backing::AccessMode::Cooperative` for that.
+        #[cfg(windows)]
+        std::os::windows::fs::OpenOptionsExt::share_mode(&mut options, 0x1 | 0x2 | 0x4);
+
+        let file = options.open(&path_buf)?;
 
         // SAFETY:
         // - File is opened read-only
@@ -20,7 +39,7 @@ impl MmapFile {
         let mmap = unsafe { memmap2::Mmap::map(&file)? };
 
         Ok(Self {
-            _file: file,
+            file,
             mmap,
             path: path_buf,
         })
@@ -64,6 +83,46 @@ impl MmapFile {
         &self.mmap
     }
 
+    /// Hints to the OS how the whole file is about to be read. A no-op outside Unix.
+    ///
+    /// # Errors
+    ///
+    /// - `std::io::Error` if `madvise` rejects the hint. The mapping stays usable.
+    pub fn advise(&self, pattern: AccessPattern) -> std::io::Result<()> {
+        #[cfg(unix)]
+        if !self.is_empty() {
+            self.mmap.advise(match pattern {
+                AccessPattern::Sequential => memmap2::Advice::Sequential,
+                AccessPattern::Random => memmap2::Advice::Random,
+            })?;
+        }
+        #[cfg(not(unix))]
+        let _ = pattern;
+
+        Ok(())
+    }
+
+    /// Asks the OS to start paging in `start..start + length` in the background, so a
+    /// later read doesn't stall on the disk. The range is clamped to the file. A no-op
+    /// outside Unix.
+    ///
+    /// # Errors
+    ///
+    /// - `std::io::Error` if `madvise` rejects the hint. The mapping stays usable.
+    pub fn prefetch(&self, start: usize, length: usize) -> std::io::Result<()> {
+        let length = self.get_bytes_clamped(start, length).len();
+
+        #[cfg(unix)]
+        if length > 0 {
+            self.mmap
+                .advise_range(memmap2::Advice::WillNeed, start, length)?;
+        }
+        #[cfg(not(unix))]
+        let _ = length;
+
+        Ok(())
+    }
+
     /// File length in bytes.
     #[inline]
     #[must_use]
@@ -71,6 +130,26 @@ impl MmapFile {
         self.mmap.len()
     }
 
+    /// Length of the file on disk right now. Differs from [`Self::len`] once another
+    /// program has resized the file since it was mapped.
+    ///
+    /// # Errors
+    ///
+    /// - `std::io::Error` if the file's metadata can't be read.
+    pub fn len_on_disk(&self) -> std::io::Result<u64> {
+        Ok(self.file.metadata()?.len())
+    }
+
+    /// Whether the file shrank below the mapping since it was opened. Touching a
+    /// mapped page past the new end raises `SIGBUS`, so check this before trusting
+    /// the mapping once the file changed on disk. A check can't rule out a
+    /// truncation that lands right after it; [`crate::backing::FileBacking::read`]
+    /// avoids the mapping altogether.
+    #[must_use]
+    pub fn is_truncated(&self) -> bool {
+        self.len_on_disk().is_ok_and(|len| len < self.len() as u64)
+    }
+
     /// Whether file is empty.
     #[inline]
     #[must_use]
@@ -85,3 +164,24 @@ impl MmapFile {
         &self.path
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hints_keep_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hints.txt");
+        std::fs::write(&path, "a".repeat(10_000)).unwrap();
+
+        let file = MmapFile::open(&path).unwrap();
+
+        file.advise(AccessPattern::Sequential).unwrap();
+        file.prefetch(5_000, 1_000_000).unwrap();
+        file.prefetch(20_000, 10).unwrap();
+        file.advise(AccessPattern::Random).unwrap();
+
+        assert_eq!(file.get_bytes_exact(9_999, 1), Some(&b"a"[..]));
+    }
+}
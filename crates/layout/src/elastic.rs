@@ -0,0 +1,198 @@
+/// Elastic tabstops: a tab ends a cell rather than jumping to the next multiple
+/// of the tab width, and the cells in one column of consecutive lines are all as
+/// wide as the widest of them, so tab-separated text lines up by itself.
+///
+/// Widths are in character columns; positions in a line are character indices.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Tabstops {
+    /// Per line, the width of each of its cells that a tab ends.
+    cells: Vec<Vec<usize>>,
+}
+
+impl Tabstops {
+    /// Lays out the cells of `lines`, each at least `min_width` wide and `padding`
+    /// wider than the text of the widest cell in its block.
+    pub fn compute<S: AsRef<str>>(lines: &[S], min_width: usize, padding: usize) -> Self {
+        // The text widths first, then each column block by block.
+        let mut cells: Vec<Vec<usize>> = lines
+            .iter()
+            .map(|line| {
+                let mut parts: Vec<usize> = line
+                    .as_ref()
+                    .split('\t')
+                    .map(|cell| cell.chars().count())
+                    .collect();
+
+                // The text after the last tab isn't a cell.
+                parts.pop();
+                parts
+            })
+            .collect();
+        let columns = cells.iter().map(Vec::len).max().unwrap_or(0);
+
+        for column in 0..columns {
+            let mut row = 0;
+
+            while row < cells.len() {
+                if cells[row].len() <= column {
+                    row += 1;
+                    continue;
+                }
+
+                let block = row..(row..cells.len())
+                    .find(|&r| cells[r].len() <= column)
+                    .unwrap_or(cells.len());
+                let width = cells[block.clone()]
+                    .iter()
+                    .map(|line| line[column] + padding)
+                    .max()
+                    .unwrap_or(0)
+                    .max(min_width);
+
+                for line in &mut cells[block.clone()] {
+                    line[column] = width;
+                }
+                row = block.end;
+            }
+        }
+
+        Self { cells }
+    }
+
+    /// Lays the cells out again after `edits`, each replacing rows `start..=old_end`
+    /// by `start..=new_end` in turn. Only the run of lines with tabs around the
+    /// edited rows is laid out again, the blocks elsewhere can't have changed.
+    /// `lines` reads a range of rows as they are after the last edit.
+    pub fn update<S: AsRef<str>>(
+        &mut self,
+        edits: impl IntoIterator<Item = (usize, usize, usize)>,
+        lines: impl FnOnce(std::ops::Range<usize>) -> Vec<S>,
+        min_width: usize,
+        padding: usize,
+    ) {
+        // First and last row edited, where they are after the last edit.
+        let mut edited: Option<(usize, usize)> = None;
+
+        for (start, old_end, new_end) in edits {
+            let replaced = start.min(self.cells.len())..(old_end + 1).min(self.cells.len());
+
+            self.cells.splice(
+                replaced,
+                std::iter::repeat_n(Vec::new(), new_end + 1 - start),
+            );
+            edited = Some(match edited {
+                Some((first, last)) if last > old_end => {
+                    (first.min(start), last + new_end - old_end)
+                }
+                Some((first, last)) => (first.min(start), last.max(new_end)),
+                None => (start, new_end),
+            });
+        }
+
+        let Some((first, last)) = edited else {
+            return;
+        };
+        let end = (last + 1).min(self.cells.len());
+        let start = (0..first.min(end))
+            .rev()
+            .find(|&row| self.cells[row].is_empty())
+            .map_or(0, |row| row + 1);
+        let end = (end..self.cells.len())
+            .find(|&row| self.cells[row].is_empty())
+            .unwrap_or(self.cells.len());
+        let block = Self::compute(&lines(start..end), min_width, padding);
+
+        self.cells.splice(start..end, block.cells);
+    }
+
+    /// Widths of the tab-ended cells of line `row`.
+    pub fn cells(&self, row: usize) -> &[usize] {
+        self.cells.get(row).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// The visual column character `col` of `line` starts at, its tab-ended cells
+/// being `cells` wide. Tabs past them take one column.
+pub fn visual_col(line: &str, cells: &[usize], col: usize) -> usize {
+    let mut visual = 0;
+    let mut cell_start = 0;
+    let mut cell = 0;
+
+    for c in line.chars().take(col) {
+        if c == '\t' {
+            visual = cells
+                .get(cell)
+                .map_or(visual + 1, |width| cell_start + width);
+            cell_start = visual;
+            cell += 1;
+        } else {
+            visual += 1;
+        }
+    }
+
+    visual
+}
+
+/// The character of `line` nearest visual column `visual`: the one drawn there,
+/// or the one after a tab whose later half it's in. The inverse of [`visual_col`].
+pub fn char_at(line: &str, cells: &[usize], visual: usize) -> usize {
+    let count = line.chars().count();
+
+    for i in 0..count {
+        let start = visual_col(line, cells, i);
+        let end = visual_col(line, cells, i + 1);
+
+        if visual < end {
+            return if visual - start < (end - start).div_ceil(2) {
+                i
+            } else {
+                i + 1
+            };
+        }
+    }
+
+    count
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_columns_align_per_block() {
+        let lines = ["a\tbb\tc", "aaaa\tb", "", "x\ty"];
+        let tabs = Tabstops::compute(&lines, 2, 1);
+
+        // "a" and "aaaa" share a block; the blank line ends it.
+        assert_eq!(tabs.cells(0), &[5, 3]);
+        assert_eq!(tabs.cells(1), &[5]);
+        assert_eq!(tabs.cells(2), &[] as &[usize]);
+        assert_eq!(tabs.cells(3), &[2]);
+
+        assert_eq!(visual_col(lines[0], tabs.cells(0), 2), 5);
+        assert_eq!(visual_col(lines[0], tabs.cells(0), 5), 8);
+        assert_eq!(char_at(lines[0], tabs.cells(0), 2), 1);
+        assert_eq!(char_at(lines[0], tabs.cells(0), 4), 2);
+        assert_eq!(char_at(lines[0], tabs.cells(0), 5), 2);
+        assert_eq!(char_at(lines[0], tabs.cells(0), 20), 6);
+    }
+
+    #[test]
+    fn test_update_matches_compute() {
+        let mut lines = vec!["a\tb", "aaaa\tb", "", "x\ty", "xx\ty"];
+        let mut tabs = Tabstops::compute(&lines, 2, 1);
+
+        // Joins the two blocks, then widens the first cell of the last line.
+        lines[2] = "\t";
+        lines.insert(3, "yyyyyy\tz");
+        lines[5] = "xxxxxxxxx\ty";
+        tabs.update([(2, 2, 3), (5, 5, 5)], |rows| lines[rows].to_vec(), 2, 1);
+        assert_eq!(tabs, Tabstops::compute(&lines, 2, 1));
+
+        lines.drain(1..3);
+        tabs.update([(1, 3, 1)], |rows| lines[rows].to_vec(), 2, 1);
+        lines[1] = "x";
+        tabs.update([(1, 1, 1)], |rows| lines[rows].to_vec(), 2, 1);
+        assert_eq!(tabs, Tabstops::compute(&lines, 2, 1));
+    }
+}
@@ -0,0 +1,113 @@
+/// Vertical layout of a document whose rows don't all share one height.
+///
+/// Most rows are `default` tall, so only the exceptions are stored, each with the
+/// y at which it starts. Going from a row to its y, or from a y back to the row
+/// under it, is a binary search over the exceptions plus some arithmetic.
+/// Rows past the end of the document simply extrapolate at `default`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowHeights {
+    default: i32,
+    /// `(row, height, top)` of every row that isn't `default` tall, sorted by row.
+    tall: Vec<(usize, i32, i64)>,
+}
+
+impl RowHeights {
+    /// `overrides` are `(row, height)` pairs in ascending row order.
+    pub fn new(default: i32, overrides: impl IntoIterator<Item = (usize, i32)>) -> Self {
+        let default = default.max(1);
+        let mut tall: Vec<(usize, i32, i64)> = Vec::new();
+
+        for (row, height) in overrides {
+            if height == default {
+                continue;
+            }
+
+            let top = match tall.last() {
+                Some(&(prev, prev_h, prev_top)) => {
+                    debug_assert!(row > prev, "Overrides must be sorted by row");
+                    prev_top + prev_h as i64 + (row - prev - 1) as i64 * default as i64
+                }
+                None => row as i64 * default as i64,
+            };
+
+            tall.push((row, height, top));
+        }
+
+        Self { default, tall }
+    }
+
+    #[inline]
+    pub fn default_height(&self) -> i32 {
+        self.default
+    }
+
+    pub fn height(&self, row: usize) -> i32 {
+        match self.tall.binary_search_by_key(&row, |&(r, _, _)| r) {
+            Ok(i) => self.tall[i].1,
+            Err(_) => self.default,
+        }
+    }
+
+    /// The y at which `row` starts, with row 0 at y = 0.
+    pub fn top(&self, row: usize) -> i64 {
+        let before = self.tall.partition_point(|&(r, _, _)| r < row);
+
+        match before.checked_sub(1).map(|i| self.tall[i]) {
+            Some((r, h, top)) => top + h as i64 + (row - r - 1) as i64 * self.default as i64,
+            None => row as i64 * self.default as i64,
+        }
+    }
+
+    /// The row covering `y`. Negative ys map to row 0.
+    pub fn row_at(&self, y: i64) -> usize {
+        let y = y.max(0);
+        let before = self.tall.partition_point(|&(_, _, top)| top <= y);
+
+        match before.checked_sub(1).map(|i| self.tall[i]) {
+            Some((r, h, top)) if y < top + h as i64 => r,
+            Some((r, h, top)) => r + 1 + ((y - top - h as i64) / self.default as i64) as usize,
+            None => (y / self.default as i64) as usize,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uniform_rows() {
+        let heights = RowHeights::new(16, []);
+
+        assert_eq!(heights.top(10), 160);
+        assert_eq!(heights.row_at(159), 9);
+        assert_eq!(heights.row_at(160), 10);
+        assert_eq!(heights.row_at(-5), 0);
+    }
+
+    #[test]
+    fn test_tall_rows() {
+        // Row 2 carries a 100px image, row 5 a 24px heading.
+        let heights = RowHeights::new(16, [(2, 116), (5, 24)]);
+
+        assert_eq!(heights.height(2), 116);
+        assert_eq!(heights.height(3), 16);
+        assert_eq!(heights.top(2), 32);
+        assert_eq!(heights.top(3), 148);
+        assert_eq!(heights.top(5), 180);
+        assert_eq!(heights.top(6), 204);
+        assert_eq!(heights.top(8), 236);
+
+        assert_eq!(heights.row_at(31), 1);
+        assert_eq!(heights.row_at(32), 2);
+        assert_eq!(heights.row_at(147), 2);
+        assert_eq!(heights.row_at(148), 3);
+        assert_eq!(heights.row_at(203), 5);
+        assert_eq!(heights.row_at(204), 6);
+        assert_eq!(heights.row_at(236), 8);
+
+        for row in 0..20 {
+            assert_eq!(heights.row_at(heights.top(row)), row);
+        }
+    }
+}
@@ -1 +1,3 @@
-
+pub mod elastic;
+pub mod heights;
+pub mod wrap;
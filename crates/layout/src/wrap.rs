@@ -0,0 +1,86 @@
+/// Byte offsets at which each visual row of `line` starts when soft-wrapped to
+/// `max_cols` characters. The first entry is always `0`; `None` disables wrapping.
+///
+/// Rows break after the last space that fits, or mid-word if a single word is
+/// wider than the whole row.
+pub fn wrap_points(line: &str, max_cols: Option<usize>) -> Vec<usize> {
+    let mut starts = vec![0];
+    let Some(max_cols) = max_cols.filter(|&cols| cols > 0) else {
+        return starts;
+    };
+
+    let mut row_start = 0;
+    let mut row_cols = 0;
+    let mut last_break = None;
+
+    for (i, c) in line.char_indices() {
+        // Spaces may hang past the edge; only visible characters force a break.
+        if row_cols >= max_cols && c != ' ' && c != '\t' {
+            let next = match last_break {
+                Some(after_space) if after_space > row_start => after_space,
+                _ => i,
+            };
+
+            starts.push(next);
+            row_start = next;
+            row_cols = line[next..i].chars().count();
+            last_break = None;
+        }
+
+        row_cols += 1;
+
+        if c == ' ' || c == '\t' {
+            last_break = Some(i + c.len_utf8());
+        }
+    }
+
+    starts
+}
+
+/// The `[start, end)` byte range of the visual row that `col` falls on.
+///
+/// A column sitting exactly on a break belongs to the row it starts, which is
+/// where the caret is drawn. The end of a wrapped row excludes the space the
+/// row was broken at, so End never leaves the caret dangling past the text.
+pub fn visual_row_bounds(line: &str, starts: &[usize], col: usize) -> (usize, usize) {
+    let row = starts
+        .partition_point(|&start| start <= col)
+        .saturating_sub(1);
+    let start = starts.get(row).copied().unwrap_or(0);
+    let end = match starts.get(row + 1) {
+        Some(&next) => {
+            let row_text = &line[start..next];
+            start + row_text.trim_end_matches([' ', '\t']).len()
+        }
+        None => line.len(),
+    };
+
+    (start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrap_points_break_at_spaces() {
+        assert_eq!(wrap_points("hello brave new world", Some(11)), vec![0, 12]);
+        assert_eq!(wrap_points("hello brave new world", None), vec![0]);
+        assert_eq!(wrap_points("short", Some(80)), vec![0]);
+    }
+
+    #[test]
+    fn test_wrap_points_hard_break_long_words() {
+        assert_eq!(wrap_points("abcdefghij", Some(4)), vec![0, 4, 8]);
+    }
+
+    #[test]
+    fn test_visual_row_bounds() {
+        let line = "hello brave new world";
+        let starts = wrap_points(line, Some(11));
+
+        assert_eq!(visual_row_bounds(line, &starts, 3), (0, 11));
+        assert_eq!(visual_row_bounds(line, &starts, 12), (12, 21));
+        assert_eq!(visual_row_bounds(line, &[0], 5), (0, 21));
+    }
+}
@@ -11,26 +11,862 @@ fn as_usize(val: u64) -> usize {
     val.try_into().expect("future error handling")
 }
 
+#[inline]
+fn color((r, g, b): editor_state::theme::Rgb) -> fltk::enums::Color {
+    fltk::enums::Color::from_rgb(r, g, b)
+}
+
+/// Recolors FLTK's stock widgets (menus, frames, trees, dialogs) for `theme` and
+/// redraws every window. Custom-drawn views read their colors when they draw.
+fn apply_theme_colors(theme: editor_state::theme::Theme) {
+    let palette = theme.palette();
+    let (r, g, b) = editor_state::theme::mix(palette.background, palette.text, 0.1);
+
+    fltk::app::background(r, g, b);
+    fltk::app::background2(
+        palette.background.0,
+        palette.background.1,
+        palette.background.2,
+    );
+    fltk::app::foreground(palette.text.0, palette.text.1, palette.text.2);
+    fltk::app::set_selection_color(palette.link.0, palette.link.1, palette.link.2);
+    fltk::app::redraw();
+}
+
 // ==========================================
 // 1. STATE
 // ==========================================
+/// What the cached row heights depend on: document, folds, diagrams and images
+/// revisions, font size and whether inline images and Markdown styles are shown.
+type HeightsKey = (u64, u64, u64, u64, i32, bool, bool);
+/// What cached elastic tabstops depend on: document revision and tab width.
+type TabstopsKey = (u64, usize);
+
+/// Something drawn over the text without being part of it, such as a collaborator's
+/// cursor: a caret before column `col` of `row`, tagged with `label`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Decoration {
+    pub row: usize,
+    pub col: usize,
+    pub color: editor_state::theme::Rgb,
+    pub label: String,
+}
+
+/// An image decoded and scaled down to fit the inline image box, or why it couldn't be.
+type Picture = Result<fltk::image::RgbImage, String>;
+
+/// Inline images and rendered diagrams, decoded and scaled down on a worker thread
+/// so a large picture doesn't stall typing. Every image's size is remembered for
+/// the layout, but only the [`Images::KEPT`] most recently drawn stay decoded.
+struct Images {
+    /// Drawn size of each image decoded so far, or why it couldn't be.
+    sizes: std::collections::HashMap<std::path::PathBuf, Result<(i32, i32), String>>,
+    /// By path, with when each was last asked for: `None` while decoding.
+    decoded: std::collections::HashMap<std::path::PathBuf, (u64, Option<fltk::image::RgbImage>)>,
+    /// Counts requests, to tell which image was used least recently.
+    uses: u64,
+    /// Bumped whenever a decoding finishes, so rows are laid out again.
+    revision: u64,
+    /// Started along with the first decoding.
+    requests: Option<std::sync::mpsc::Sender<std::path::PathBuf>>,
+    results: std::sync::mpsc::Receiver<(std::path::PathBuf, Picture)>,
+    report: std::sync::mpsc::Sender<(std::path::PathBuf, Picture)>,
+}
+
+impl Default for Images {
+    fn default() -> Self {
+        let (report, results) = std::sync::mpsc::channel();
+
+        Self {
+            sizes: std::collections::HashMap::new(),
+            decoded: std::collections::HashMap::new(),
+            uses: 0,
+            revision: 0,
+            requests: None,
+            results,
+            report,
+        }
+    }
+}
+
+impl Images {
+    const KEPT: usize = 64;
+
+    /// The size `path` is drawn at, or `None` until it's decoded.
+    fn size(&mut self, path: &std::path::Path) -> Option<Result<(i32, i32), String>> {
+        if let Some(size) = self.sizes.get(path) {
+            return Some(size.clone());
+        }
+        self.request(path);
+        None
+    }
+
+    /// `path` decoded, or `None` until it is or if it can't be.
+    fn image(&mut self, path: &std::path::Path) -> Option<fltk::image::RgbImage> {
+        self.uses += 1;
+        match self.decoded.get_mut(path) {
+            Some((used, image)) => {
+                *used = self.uses;
+                image.clone()
+            }
+            None if matches!(self.sizes.get(path), Some(Err(_))) => None,
+            None => {
+                self.request(path);
+                None
+            }
+        }
+    }
+
+    /// Has the worker decode `path`, unless it's on it, making room for it first.
+    fn request(&mut self, path: &std::path::Path) {
+        if self.decoded.contains_key(path) {
+            return;
+        }
+        while self.decoded.len() >= Self::KEPT {
+            let Some(oldest) = self
+                .decoded
+                .iter()
+                .min_by_key(|(_, (used, _))| *used)
+                .map(|(path, _)| path.clone())
+            else {
+                break;
+            };
+
+            self.decoded.remove(&oldest);
+        }
+
+        let requests = self.requests.get_or_insert_with(|| {
+            let (requests, paths) = std::sync::mpsc::channel::<std::path::PathBuf>();
+            let report = self.report.clone();
+
+            std::thread::spawn(move || {
+                for path in paths {
+                    let picture = Self::decode(&path);
+
+                    if report.send((path, picture)).is_err() {
+                        break;
+                    }
+                }
+            });
+            requests
+        });
+
+        self.uses += 1;
+        self.decoded.insert(path.to_path_buf(), (self.uses, None));
+        let _ = requests.send(path.to_path_buf());
+    }
+
+    /// Loads `path` by its extension and scales it down to fit the inline image box.
+    fn decode(path: &std::path::Path) -> Picture {
+        let extension = path
+            .extension()
+            .and_then(std::ffi::OsStr::to_str)
+            .map(str::to_ascii_lowercase);
+        let image = match extension.as_deref() {
+            Some("png") => fltk::image::PngImage::load(path).and_then(|image| image.to_rgb()),
+            Some("jpg" | "jpeg") => {
+                fltk::image::JpegImage::load(path).and_then(|image| image.to_rgb())
+            }
+            Some("gif") => fltk::image::GifImage::load(path).and_then(|image| image.to_rgb()),
+            Some("bmp") => fltk::image::BmpImage::load(path).and_then(|image| image.to_rgb()),
+            Some("svg") => fltk::image::SvgImage::load(path).and_then(|mut image| {
+                image.normalize();
+                image.to_rgb()
+            }),
+            _ => return Err("Unsupported image format".to_string()),
+        }
+        .map_err(|err| err.to_string())?;
+        let (w, h) = (image.data_w().max(1), image.data_h().max(1));
+        let scale = (State::MAX_IMAGE_W as f64 / w as f64)
+            .min(State::MAX_IMAGE_H as f64 / h as f64)
+            .min(1.0);
+
+        if scale == 1.0 {
+            return Ok(image);
+        }
+        Ok(image.copy_sized(
+            ((w as f64 * scale) as i32).max(1),
+            ((h as f64 * scale) as i32).max(1),
+        ))
+    }
+
+    /// Picks up finished decodings. Returns whether there were any.
+    fn poll(&mut self) -> bool {
+        let mut changed = false;
+
+        while let Ok((path, picture)) = self.results.try_recv() {
+            self.sizes.insert(
+                path.clone(),
+                picture
+                    .as_ref()
+                    .map(|image| (image.data_w(), image.data_h()))
+                    .map_err(Clone::clone),
+            );
+            match picture {
+                Ok(image) => {
+                    if let Some((_, decoded)) = self.decoded.get_mut(&path) {
+                        *decoded = Some(image);
+                    }
+                }
+                Err(_) => {
+                    self.decoded.remove(&path);
+                }
+            }
+            changed = true;
+        }
+        if changed {
+            self.revision += 1;
+        }
+        changed
+    }
+}
+
+/// Mermaid diagrams drawn below their code blocks. Each is rendered once on a
+/// worker thread into [`editor_state::diagrams::cache_path`] and shown from there
+/// through [`Images`].
+struct Diagrams {
+    /// The document's diagrams, cached by revision.
+    fences: Option<(u64, Rc<[editor_state::diagrams::Diagram]>)>,
+    /// The document's diagrams, kept in step with edits.
+    index: editor_state::journal::BlockIndex<editor_state::diagrams::Diagram>,
+    /// Renderings by cache path: `None` while under way.
+    rendered: std::collections::HashMap<std::path::PathBuf, Option<Result<(), String>>>,
+    /// Whether a rendering is under way. One at a time, so typing in a block
+    /// doesn't start one per keystroke; the others wait for the next layout.
+    rendering: bool,
+    /// Bumped whenever a rendering finishes, so rows are laid out again.
+    revision: u64,
+    results: std::sync::mpsc::Receiver<(std::path::PathBuf, std::io::Result<()>)>,
+    report: std::sync::mpsc::Sender<(std::path::PathBuf, std::io::Result<()>)>,
+}
+
+impl Default for Diagrams {
+    fn default() -> Self {
+        let (report, results) = std::sync::mpsc::channel();
+
+        Self {
+            fences: None,
+            index: editor_state::journal::BlockIndex::default(),
+            rendered: std::collections::HashMap::new(),
+            rendering: false,
+            revision: 0,
+            results,
+            report,
+        }
+    }
+}
+
+impl Diagrams {
+    fn fences(
+        &mut self,
+        d: &editor_state::document::Document,
+    ) -> Rc<[editor_state::diagrams::Diagram]> {
+        match &self.fences {
+            Some((revision, fences)) if *revision == d.revision => fences.clone(),
+            _ => {
+                self.index.update(
+                    d,
+                    editor_state::diagrams::is_fence,
+                    editor_state::diagrams::fences,
+                );
+                let fences: Rc<[_]> = self.index.blocks().into();
+
+                self.fences = Some((d.revision, fences.clone()));
+                fences
+            }
+        }
+    }
+
+    /// Where the image of `source` is, or `None` until it's rendered. Starts
+    /// rendering it if it isn't cached yet and nothing else is rendering.
+    fn image(&mut self, source: &str) -> Option<Result<std::path::PathBuf, String>> {
+        let Some(path) = editor_state::diagrams::cache_path(source) else {
+            return Some(Err("No cache directory".to_string()));
+        };
+
+        if let Some(rendered) = self.rendered.get(&path) {
+            return rendered.clone().map(|rendered| rendered.map(|()| path));
+        }
+        if path.exists() {
+            self.rendered.insert(path.clone(), Some(Ok(())));
+            return Some(Ok(path));
+        }
+        if !self.rendering {
+            let (report, source) = (self.report.clone(), source.to_string());
+
+            self.rendering = true;
+            self.rendered.insert(path.clone(), None);
+            std::thread::spawn(move || {
+                let rendered = editor_state::diagrams::render(&source, &path);
+                let _ = report.send((path, rendered));
+            });
+        }
+        None
+    }
+
+    /// Picks up finished renderings. Returns whether there were any.
+    fn poll(&mut self) -> bool {
+        let mut changed = false;
+
+        while let Ok((path, rendered)) = self.results.try_recv() {
+            self.rendering = false;
+            self.rendered
+                .insert(path, Some(rendered.map_err(|err| err.to_string())));
+            changed = true;
+        }
+        if changed {
+            self.revision += 1;
+        }
+        changed
+    }
+}
+
+/// A `$$` block with its typeset text, or the error that kept it from being set.
+type MathBlock = (editor_state::math::MathBlock, Result<String, String>);
+
+/// Whether a fence line's code block can be run, and what runs the one at a row.
+type BlockRunner = (Rc<dyn Fn(&str) -> bool>, Rc<dyn Fn(usize)>);
+/// The first lines of the note a link leads to, `None` if there's no such note.
+type NotePreviewer = Rc<dyn Fn(&editor_state::workspace::WikiLink) -> Option<Vec<String>>>;
+/// Brings a view that follows the editor up to date, see [`TextEditor::watch`].
+type Watcher = Rc<RefCell<dyn FnMut()>>;
+
+/// The link under the pointer, and what its preview shows.
+struct LinkHover {
+    row: usize,
+    target: editor_state::workspace::LinkTarget,
+    /// Where the pointer entered the link, from the canvas' left edge.
+    x: i32,
+    lines: Vec<String>,
+}
+
+/// What clicking each part of the gutter does; a part that's switched off is
+/// clicked like the text next to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GutterClicks {
+    /// Line numbers select their line, Shift extends the selection by lines.
+    pub select_lines: bool,
+    /// The strip left of the numbers toggles a bookmark on the row.
+    pub bookmarks: bool,
+    /// The strip right of the numbers folds and unfolds the block starting there.
+    pub fold_markers: bool,
+}
+
+impl Default for GutterClicks {
+    fn default() -> Self {
+        Self {
+            select_lines: true,
+            bookmarks: true,
+            fold_markers: true,
+        }
+    }
+}
+
+/// The parts of the gutter, left to right.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GutterZone {
+    /// ▶ on runnable fences, ◆ on bookmarks.
+    Markers,
+    Numbers,
+    /// ▸ and ▾ on blocks that fold.
+    Folds,
+}
+
+/// Where following the cursor with the view and scrollbar stands. Edits and moves
+/// only ask for it, so a burst of keystrokes handled in one turn of the event loop
+/// pays for it once, right before the frame they share is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ViewSync {
+    Idle,
+    Requested,
+    Scheduled,
+}
+
 pub struct State {
     pub doc: Rc<RefCell<editor_state::document::Document>>,
     pub cursor_visible: bool,
     pub scroll_offset: usize,
     pub scrolloff: usize,
     pub last_interaction: std::time::Instant,
+    /// How HTML/RTF found on the clipboard is converted before insertion.
+    pub paste_conversion: editor_state::clipboard::PasteConversion,
+    /// Set right before requesting a paste so the incoming `Event::Paste`
+    /// is routed through "Paste and Match Indentation".
+    pub paste_match_indent: bool,
+    /// Set right before requesting a paste so the incoming `Event::Paste` swaps
+    /// the selection with the clipboard.
+    pub paste_swap: bool,
+    /// Pasting a URL over a selection links the selection to it instead of
+    /// replacing it.
+    pub paste_links: bool,
+    /// Set when something is dropped on the canvas, so the `Event::Paste` that
+    /// delivers it is handled as files to attach.
+    pub dropping: bool,
+    /// Distraction-free writing: no gutter, and the text indented so that a line
+    /// of `focus_measure` chars is centered. Longer lines aren't wrapped and run on
+    /// to the right, as soft wrap isn't rendered yet.
+    pub focus_mode: bool,
+    pub focus_measure: usize,
+    /// In focus mode, fades every paragraph except the one holding the cursor.
+    pub dim_unfocused: bool,
+    /// Editor font size; zooming changes it. Text lines are exactly one font size tall.
+    pub font_size: i32,
+    /// Draws the image of a line holding only `![alt](path)` right below it, and
+    /// Mermaid diagrams below their code blocks.
+    pub inline_images: bool,
+    /// Renders Markdown in place: larger bold headings, italic and bold emphasis,
+    /// boxed code spans, highlighted fenced code and `$$` math typeset below its
+    /// block. The text itself,
+    /// markers included, is left as is.
+    pub rich_text: bool,
+    /// Colors the editor is drawn with. Change it through `TextEditor::set_theme`.
+    pub theme: editor_state::theme::Theme,
+    /// Overlays per-frame timings and buffer statistics on the canvas.
+    pub perf_hud: bool,
+    /// Shows a ruler of columns along the top edge; clicking it places a guide at
+    /// a column, or removes the one there. Guides are kept in the document's
+    /// settings, see [`editor_state::settings::EditorSettings::rulers`].
+    pub column_ruler: bool,
+    /// Lets the cursor go past the end of a line, by clicking there or with the
+    /// arrow keys. The spaces up to it are only added once something is typed.
+    pub virtual_space: bool,
+    /// Lays tabs out as elastic tabstops, see [`layout::elastic`], instead of one
+    /// column each.
+    pub elastic_tabs: bool,
+    /// Only scrolls and copies: typing, pasting and clicking leave the document
+    /// and its cursor alone, e.g. in a detached preview.
+    pub read_only: bool,
+    /// Presentation mode: what it finds is masked on screen, e.g. while sharing
+    /// it. The document is left as is.
+    pub redactor: Option<Rc<editor_state::redact::Redactor>>,
+    /// Drawn on top of the text and cursor. Owners replace the whole list.
+    pub decorations: Vec<Decoration>,
+    pub gutter_clicks: GutterClicks,
+    /// Row whose line number the pointer is over, to show when it was last edited.
+    gutter_hover: Option<usize>,
+    /// Row a selection by line numbers started on, while it's extended by
+    /// dragging or Shift+clicking them.
+    gutter_anchor: Option<usize>,
+    /// Tells which code blocks can be run, by their fence line, and runs the one
+    /// opened on a row when its ▶ in the gutter is clicked.
+    block_runner: Option<BlockRunner>,
+    /// Looks up the notes hovered links lead to, see [`TextEditor::set_note_previewer`].
+    note_previewer: Option<NotePreviewer>,
+    /// Called after every frame, see [`TextEditor::watch`].
+    watchers: Vec<Watcher>,
+    link_hover: Option<LinkHover>,
+    /// The text last copied or cut as a whole line, with nothing selected. Pasting
+    /// exactly that inserts it as a line above the cursor's, wherever the cursor is
+    /// on its line.
+    line_clipboard: Option<String>,
+    /// The row and column the cursor shows at in virtual space. The document's
+    /// cursor stays at the end of the line meanwhile; see [`State::virtual_col`].
+    virtual_col: Option<(usize, usize)>,
+    /// The furthest row drawn since the note was opened, i.e. read up to.
+    read_row: std::cell::Cell<usize>,
+    /// Moving average of the frame time in milliseconds, for the HUD.
+    frame_ms: std::cell::Cell<f64>,
+    view_sync: ViewSync,
+    /// When the oldest input not drawn yet came in and how many came in since, while
+    /// the HUD is shown.
+    input_since: std::cell::Cell<Option<(std::time::Instant, usize)>>,
+    /// Milliseconds from input to the end of the frame showing it, and how many
+    /// inputs that frame showed, for the HUD.
+    input_latency: std::cell::Cell<(f64, usize)>,
+    images: RefCell<Images>,
+    /// Where each line holding only a local image link points, kept in step with edits.
+    image_rows: RefCell<editor_state::journal::RowIndex<String>>,
+    /// The `$$` blocks of the document and how each typesets, or why it doesn't,
+    /// cached by document revision.
+    math: RefCell<Option<(u64, Rc<[MathBlock]>)>>,
+    /// The private keys in the document, masked whole in presentation mode.
+    key_blocks: RefCell<editor_state::journal::BlockIndex<editor_state::redact::KeyBlock>>,
+    /// The `$$` blocks of the document, kept in step with edits.
+    math_index: RefCell<editor_state::journal::BlockIndex<editor_state::math::MathBlock>>,
+    /// Rows blocks that fold start on, cached by document revision.
+    fold_starts: RefCell<Option<(u64, Rc<[usize]>)>>,
+    tabstops: RefCell<Option<(TabstopsKey, Rc<layout::elastic::Tabstops>)>>,
+    diagrams: RefCell<Diagrams>,
+    /// Row heights of the document, cached until its contents or the zoom change.
+    heights: RefCell<Option<(HeightsKey, layout::heights::RowHeights)>>,
+}
+
+impl State {
+    pub const DEFAULT_FONT_SIZE: i32 = 16;
+    pub const MIN_FONT_SIZE: i32 = 8;
+    pub const MAX_FONT_SIZE: i32 = 48;
+
+    /// Inline images are scaled down to fit this box.
+    const MAX_IMAGE_W: i32 = 480;
+    const MAX_IMAGE_H: i32 = 240;
+    /// Space between an inline image or typeset math and the lines around it.
+    const IMAGE_PAD: i32 = 6;
+    /// Columns between the text of the widest cell and the next elastic tabstop.
+    const TAB_PADDING: usize = 2;
+    /// Font size of `#` through `######` headings relative to body text.
+    const HEADING_SCALE: [f64; 6] = [1.6, 1.4, 1.25, 1.1, 1.0, 1.0];
+
+    #[inline]
+    pub fn line_height(&self) -> i32 {
+        self.font_size
+    }
+
+    /// Font of the text on `row`. Headings are bold and, at the top levels, larger;
+    /// all fonts are monospaced, so every column of a row is equally wide.
+    fn row_font(
+        &self,
+        d: &editor_state::document::Document,
+        row: usize,
+    ) -> (fltk::enums::Font, i32) {
+        match d.outline.heading_on(row).filter(|_| self.rich_text) {
+            Some(heading) => {
+                let scale = Self::HEADING_SCALE[(heading.level as usize).clamp(1, 6) - 1];
+                (
+                    fltk::enums::Font::CourierBold,
+                    (self.font_size as f64 * scale).round() as i32,
+                )
+            }
+            None => (fltk::enums::Font::Courier, self.font_size),
+        }
+    }
+
+    /// Vertical layout of `d`: every row is as tall as its font, plus the inline
+    /// image or typeset math below it, if any, and rows inside collapsed folds take no room at all.
+    /// Rebuilt only after an edit, a fold, a zoom or a settings change.
+    pub fn row_heights(
+        &self,
+        d: &editor_state::document::Document,
+    ) -> std::cell::Ref<'_, layout::heights::RowHeights> {
+        let key = (
+            d.revision,
+            d.folds.revision,
+            self.diagrams.borrow().revision,
+            self.images.borrow().revision,
+            self.font_size,
+            self.inline_images,
+            self.rich_text,
+        );
+
+        if self
+            .heights
+            .borrow()
+            .as_ref()
+            .is_none_or(|(built, _)| *built != key)
+        {
+            let line_h = self.line_height();
+            let mut overrides: Vec<(usize, i32)> = Vec::new();
+
+            if self.rich_text {
+                overrides.extend(
+                    d.outline
+                        .headings
+                        .iter()
+                        .map(|heading| (heading.row, self.row_font(d, heading.row).1)),
+                );
+                overrides.extend(
+                    self.math_blocks(d)
+                        .iter()
+                        .map(|(block, _)| (block.end, 2 * line_h + 2 * Self::IMAGE_PAD)),
+                );
+            }
+
+            if self.inline_images {
+                self.image_rows.borrow_mut().update(d, |line| {
+                    editor_state::workspace::image_link_target(line)
+                        .filter(|target| !target.contains("://"))
+                        .map(str::to_string)
+                });
+                for (row, target) in self.image_rows.borrow().rows() {
+                    let path = Self::image_path(d, target);
+
+                    if let Some(Ok((_, image_h))) = self.images.borrow_mut().size(&path) {
+                        // A line holding only an image link can't also be a heading.
+                        overrides.push((*row, line_h + image_h + 2 * Self::IMAGE_PAD));
+                    }
+                }
+
+                let fences = self.diagrams.borrow_mut().fences(d);
+
+                for diagram in fences.iter() {
+                    // Pending and failed ones get a line saying so.
+                    let below = match self.diagram_size(&diagram.source) {
+                        Some(Ok((_, image_h))) => image_h,
+                        _ => line_h,
+                    };
+
+                    overrides.push((diagram.end, line_h + below + 2 * Self::IMAGE_PAD));
+                }
+            }
+
+            if !d.folds.is_empty() {
+                let hidden: std::collections::BTreeSet<usize> = d
+                    .folds
+                    .folded()
+                    .iter()
+                    .flat_map(|fold| fold.start + 1..=fold.end)
+                    .collect();
+
+                overrides.retain(|(row, _)| !hidden.contains(row));
+                overrides.extend(hidden.into_iter().map(|row| (row, 0)));
+            }
+            overrides.sort_unstable_by_key(|&(row, _)| row);
+
+            *self.heights.borrow_mut() =
+                Some((key, layout::heights::RowHeights::new(line_h, overrides)));
+        }
+
+        std::cell::Ref::map(self.heights.borrow(), |heights| {
+            &heights.as_ref().expect("Built right above").1
+        })
+    }
+
+    /// The column the cursor shows at past the end of its line, if it's there: the
+    /// document's cursor must still be at the line's end, with nothing selected.
+    fn virtual_col(&self, d: &editor_state::document::Document) -> Option<usize> {
+        let (row, col) = self.virtual_col?;
+        let head = d.cursor.head;
+        let len = as_usize(d.get_visible_line_len_at(row).unwrap_or(0));
+
+        (self.virtual_space && d.cursor.no_selection() && head.row == row && head.col == len)
+            .then_some(col)
+            .filter(|&col| col > len)
+    }
+
+    /// Shows the cursor at `col` on its row, in virtual space if that's past the
+    /// end of the line.
+    fn set_virtual_col(&mut self, d: &editor_state::document::Document, col: usize) {
+        let row = d.cursor.head.row;
+        let len = as_usize(d.get_visible_line_len_at(row).unwrap_or(0));
+
+        self.virtual_col = (self.virtual_space && col > len).then_some((row, col));
+    }
+
+    /// The elastic tabstops of `d` if they're on. After an edit, only the block of
+    /// lines with tabs around it is laid out again.
+    fn tabstops(
+        &self,
+        d: &editor_state::document::Document,
+    ) -> Option<Rc<layout::elastic::Tabstops>> {
+        if !self.elastic_tabs {
+            return None;
+        }
+
+        let key = (d.revision, d.settings.tab_width);
+        let mut tabstops = self.tabstops.borrow_mut();
+
+        let edits = match &*tabstops {
+            Some((cached, tabs)) if *cached == key => return Some(tabs.clone()),
+            Some(((revision, tab_width), _)) if *tab_width == key.1 => d.journal.since(*revision),
+            _ => None,
+        };
+        let tabs = match (tabstops.take(), edits) {
+            (Some((_, mut tabs)), Some(edits)) => {
+                Rc::make_mut(&mut tabs).update(
+                    edits
+                        .iter()
+                        .map(|edit| (edit.start, edit.old_end, edit.new_end)),
+                    |rows| d.get_lines_stripped(rows),
+                    d.settings.tab_width,
+                    Self::TAB_PADDING,
+                );
+                tabs
+            }
+            _ => Rc::new(layout::elastic::Tabstops::compute(
+                &d.get_lines_stripped(0..d.get_line_count()),
+                d.settings.tab_width,
+                Self::TAB_PADDING,
+            )),
+        };
+
+        *tabstops = Some((key, tabs.clone()));
+        Some(tabs)
+    }
+
+    /// The visual column character `col` of `row` is drawn at. Past the line's
+    /// end, in virtual space, each column is one more.
+    fn visual_col(&self, d: &editor_state::document::Document, row: usize, col: usize) -> usize {
+        let Some(tabs) = self.tabstops(d) else {
+            return col;
+        };
+        let line = d.get_line_stripped(row).unwrap_or_default();
+        let count = line.chars().count();
+
+        layout::elastic::visual_col(&line, tabs.cells(row), col.min(count))
+            + col.saturating_sub(count)
+    }
+
+    /// The character of `row` at visual column `visual`, the inverse of
+    /// [`Self::visual_col`].
+    fn char_col(&self, d: &editor_state::document::Document, row: usize, visual: usize) -> usize {
+        let Some(tabs) = self.tabstops(d) else {
+            return visual;
+        };
+        let line = d.get_line_stripped(row).unwrap_or_default();
+        let cells = tabs.cells(row);
+        let end = layout::elastic::visual_col(&line, cells, usize::MAX);
+
+        if visual > end {
+            line.chars().count() + visual - end
+        } else {
+            layout::elastic::char_at(&line, cells, visual)
+        }
+    }
+
+    /// The rows blocks that fold start on, found again only after an edit.
+    fn fold_starts(&self, d: &editor_state::document::Document) -> Rc<[usize]> {
+        let mut starts = self.fold_starts.borrow_mut();
+
+        match &*starts {
+            Some((revision, rows)) if *revision == d.revision => rows.clone(),
+            _ => {
+                let mut rows: Vec<usize> = d
+                    .fold_regions()
+                    .into_iter()
+                    .map(|fold| fold.start)
+                    .collect();
+
+                rows.sort_unstable();
+                rows.dedup();
+
+                let rows: Rc<[usize]> = rows.into();
+                *starts = Some((d.revision, rows.clone()));
+                rows
+            }
+        }
+    }
+
+    /// `text` as shown anywhere outside the canvas, such as the outline, the
+    /// breadcrumb or a list of results: with its secrets masked in presentation mode.
+    pub fn display_text<'a>(&self, text: &'a str) -> std::borrow::Cow<'a, str> {
+        match &self.redactor {
+            Some(redactor) => redactor.mask(text),
+            None => std::borrow::Cow::Borrowed(text),
+        }
+    }
+
+    /// The rows of `d` inside a private key, none outside presentation mode.
+    fn key_rows(&self, d: &editor_state::document::Document) -> Vec<(usize, usize)> {
+        if self.redactor.is_none() {
+            return Vec::new();
+        }
+
+        let mut index = self.key_blocks.borrow_mut();
+
+        index.update(
+            d,
+            editor_state::redact::is_key_marker,
+            editor_state::redact::key_blocks,
+        );
+        index
+            .blocks()
+            .iter()
+            .map(|key| (key.start, key.end))
+            .collect()
+    }
+
+    /// The `$$` blocks of `d`. After an edit, only formulas whose text changed are
+    /// typeset again.
+    fn math_blocks(&self, d: &editor_state::document::Document) -> Rc<[MathBlock]> {
+        let mut math = self.math.borrow_mut();
+
+        match &*math {
+            Some((revision, blocks)) if *revision == d.revision => blocks.clone(),
+            _ => {
+                let mut index = self.math_index.borrow_mut();
+
+                index.update(d, editor_state::math::is_marker, editor_state::math::blocks);
+
+                let typeset: std::collections::HashMap<&str, &Result<String, String>> = math
+                    .iter()
+                    .flat_map(|(_, blocks)| blocks.iter())
+                    .map(|(block, typeset)| (block.tex.as_str(), typeset))
+                    .collect();
+                let blocks: Rc<[MathBlock]> = index
+                    .blocks()
+                    .iter()
+                    .map(|block| {
+                        let typeset = typeset.get(block.tex.as_str()).map_or_else(
+                            || editor_state::math::to_unicode(&block.tex),
+                            |&typeset| typeset.clone(),
+                        );
+                        (block.clone(), typeset)
+                    })
+                    .collect();
+
+                *math = Some((d.revision, blocks.clone()));
+                blocks
+            }
+        }
+    }
+
+    /// Where the image a line links to is: relative paths resolve against the
+    /// note's folder.
+    fn image_path(d: &editor_state::document::Document, target: &str) -> std::path::PathBuf {
+        match d.text_buffer.path().and_then(std::path::Path::parent) {
+            Some(dir) => dir.join(target),
+            None => std::path::PathBuf::from(target),
+        }
+    }
+
+    /// The inline image shown under `row`, once it's decoded. Remote images aren't
+    /// fetched.
+    fn image_for(
+        &self,
+        d: &editor_state::document::Document,
+        row: usize,
+    ) -> Option<fltk::image::RgbImage> {
+        if !self.inline_images {
+            return None;
+        }
+
+        let path = Self::image_path(d, self.image_rows.borrow().get(row)?);
+
+        self.images.borrow_mut().image(&path)
+    }
+
+    /// The size the diagram of a Mermaid block is drawn at, see [`Diagrams::image`].
+    fn diagram_size(&self, source: &str) -> Option<Result<(i32, i32), String>> {
+        match self.diagrams.borrow_mut().image(source)? {
+            Ok(path) => self.images.borrow_mut().size(&path),
+            Err(err) => Some(Err(err)),
+        }
+    }
+
+    /// The diagram of a Mermaid block, see [`Diagrams::image`].
+    fn diagram_for(&self, source: &str) -> Option<Result<fltk::image::RgbImage, String>> {
+        match self.diagrams.borrow_mut().image(source)? {
+            Ok(path) => match self.images.borrow_mut().size(&path)? {
+                Ok(_) => self.images.borrow_mut().image(&path).map(Ok),
+                Err(err) => Some(Err(err)),
+            },
+            Err(err) => Some(Err(err)),
+        }
+    }
+
+    /// Snapshot of what should be restored when this document is reopened.
+    pub fn view_state(&self) -> editor_state::session::ViewState {
+        let d = self.doc.borrow();
+
+        editor_state::session::ViewState {
+            cursor_row: d.cursor.head.row,
+            cursor_col: d.cursor.head.col,
+            scroll_offset: self.scroll_offset,
+            font_size: self.font_size,
+            read_row: self.read_row.get(),
+            folds: d.fold_keys(),
+        }
+    }
 }
 
 // ==========================================
 // 2. MAIN COMPONENT API
 // ==========================================
+#[derive(Clone)]
 pub struct TextEditor {
     pub group: fltk::group::Group,
     pub canvas: fltk::widget::Widget,
     pub scrollbar: fltk::valuator::Scrollbar,
     pub state: Rc<RefCell<State>>,
-    pub line_height: i32,
 }
 
 impl TextEditor {
@@ -49,10 +885,50 @@ impl TextEditor {
             scroll_offset: 0,
             scrolloff: 5,
             last_interaction: std::time::Instant::now(),
+            paste_conversion: editor_state::clipboard::PasteConversion::default(),
+            paste_match_indent: false,
+            paste_swap: false,
+            paste_links: true,
+            dropping: false,
+            focus_mode: false,
+            focus_measure: 72,
+            dim_unfocused: true,
+            font_size: State::DEFAULT_FONT_SIZE,
+            inline_images: false,
+            rich_text: false,
+            theme: editor_state::theme::Theme::default(),
+            perf_hud: false,
+            column_ruler: false,
+            virtual_space: false,
+            elastic_tabs: false,
+            read_only: false,
+            redactor: None,
+            decorations: Vec::new(),
+            gutter_clicks: GutterClicks::default(),
+            gutter_hover: None,
+            gutter_anchor: None,
+            block_runner: None,
+            note_previewer: None,
+            watchers: Vec::new(),
+            link_hover: None,
+            line_clipboard: None,
+            virtual_col: None,
+            read_row: std::cell::Cell::new(0),
+            frame_ms: std::cell::Cell::new(0.0),
+            view_sync: ViewSync::Idle,
+            input_since: std::cell::Cell::new(None),
+            input_latency: std::cell::Cell::new((0.0, 0)),
+            images: RefCell::default(),
+            image_rows: RefCell::default(),
+            math: RefCell::default(),
+            key_blocks: RefCell::default(),
+            math_index: RefCell::default(),
+            fold_starts: RefCell::default(),
+            tabstops: RefCell::default(),
+            diagrams: RefCell::default(),
+            heights: RefCell::default(),
         }));
 
-        let line_height = 16;
-
         let mut canvas = fltk::widget::Widget::default()
             .with_pos(x, y)
             .with_size(w - 15, h);
@@ -68,79 +944,314 @@ impl TextEditor {
         grp.resizable(&canvas);
         grp.end();
 
-        LayoutSync::apply_to_scrollbar(
-            &mut state.borrow_mut(),
-            &mut scrollbar,
-            canvas.height(),
-            line_height,
-        );
-        Renderer::wire(&mut canvas, state.clone(), line_height);
-        Controller::wire(&mut canvas, &mut scrollbar, state.clone(), line_height);
+        LayoutSync::apply_to_scrollbar(&mut state.borrow_mut(), &mut scrollbar, canvas.height());
+        Renderer::wire(&mut canvas, state.clone());
+        Controller::wire(&mut canvas, &mut scrollbar, state.clone());
 
-        Self {
+        let editor = Self {
             group: grp,
             canvas,
             scrollbar,
             state,
-            line_height,
-        }
+        };
+
+        editor.follow_changes();
+        editor
+    }
+
+    /// Keeps this view in step with edits made through other views of the same
+    /// document, e.g. in another window, until its canvas is deleted.
+    fn follow_changes(&self) {
+        let mut editor = self.clone();
+        let mut shown_revision = editor.state.borrow().doc.borrow().revision;
+
+        fltk::app::add_timeout3(0.1, move |handle| {
+            if editor.canvas.was_deleted() {
+                return;
+            }
+
+            let revision = editor
+                .state
+                .try_borrow()
+                .ok()
+                .and_then(|state| state.doc.try_borrow().ok().map(|d| d.revision));
+
+            if let Some(revision) = revision
+                && revision != shown_revision
+            {
+                shown_revision = revision;
+                editor.on_content_changed();
+            }
+            fltk::app::repeat_timeout3(0.1, handle);
+        });
     }
 
     pub fn on_content_changed(&mut self) {
-        LayoutSync::apply_to_scrollbar(
-            &mut self.state.borrow_mut(),
-            &mut self.scrollbar,
-            self.canvas.height(),
-            self.line_height,
-        );
+        let mut state = self.state.borrow_mut();
+
+        LayoutSync::apply_to_scrollbar(&mut state, &mut self.scrollbar, self.canvas.height());
         self.canvas.redraw();
     }
-}
 
-// ==========================================
-// 3. LAYOUT & SCROLL MATH
-// ==========================================
-struct LayoutSync;
+    /// Marks the code blocks whose fence line `runnable` accepts with a ▶ in the
+    /// gutter, which calls `run` with the fence row when clicked.
+    pub fn set_block_runner(
+        &mut self,
+        runnable: impl Fn(&str) -> bool + 'static,
+        run: impl Fn(usize) + 'static,
+    ) {
+        self.state.borrow_mut().block_runner = Some((Rc::new(runnable), Rc::new(run)));
+        self.canvas.redraw();
+    }
 
-impl LayoutSync {
-    fn apply_to_scrollbar(
-        state: &mut State,
-        scrollbar: &mut fltk::valuator::Scrollbar,
-        canvas_h: i32,
-        line_h: i32,
+    /// Previews the note a hovered link leads to with the lines `preview` finds
+    /// for it. Links within the note preview its own lines, URLs themselves.
+    pub fn set_note_previewer(
+        &mut self,
+        preview: impl Fn(&editor_state::workspace::WikiLink) -> Option<Vec<String>> + 'static,
     ) {
-        let doc_lines = state.doc.borrow().get_line_count();
-        let visible_lines = (canvas_h / line_h).max(1) as usize;
-        let max_scroll = doc_lines.saturating_sub(visible_lines);
+        self.state.borrow_mut().note_previewer = Some(Rc::new(preview));
+    }
 
-        state.scroll_offset = state.scroll_offset.clamp(0, max_scroll);
-        scrollbar.set_bounds(0.0, max_scroll as f64);
-        scrollbar.set_slider_size((visible_lines as f32 / doc_lines.max(1) as f32).clamp(0.0, 1.0));
-        scrollbar.set_value(state.scroll_offset as f64);
+    /// Calls `watch` after every frame of the editor, which is redrawn whenever
+    /// its document, cursor or settings change, for views that follow it to check
+    /// whether they need updating rather than poll on a timer.
+    pub fn watch(&self, watch: impl FnMut() + 'static) {
+        self.state
+            .borrow_mut()
+            .watchers
+            .push(Rc::new(RefCell::new(watch)));
     }
 
-    fn sync_view_to_cursor(state: &mut State, canvas_h: i32, line_h: i32) {
-        let visible_lines = (canvas_h / line_h).max(1) as usize;
-        let actual_scrolloff = state.scrolloff.min(visible_lines.saturating_sub(1) / 2);
-        let head_row = state.doc.borrow().cursor.head.row;
-        let top = state.scroll_offset + actual_scrolloff;
-        let bottom = state.scroll_offset + visible_lines.saturating_sub(1) - actual_scrolloff;
+    /// Switches to `theme`, recoloring and redrawing the whole UI.
+    pub fn set_theme(&mut self, theme: editor_state::theme::Theme) {
+        self.state.borrow_mut().theme = theme;
+        apply_theme_colors(theme);
+    }
 
-        if head_row < top {
-            state.scroll_offset = head_row.saturating_sub(actual_scrolloff);
-        } else if head_row > bottom {
-            state.scroll_offset = head_row + actual_scrolloff + 1 - visible_lines;
+    /// Changes the font size by `delta` points, or resets it when `delta` is 0.
+    pub fn zoom(&mut self, delta: i32) {
+        {
+            let mut state = self.state.borrow_mut();
+            state.font_size = if delta == 0 {
+                State::DEFAULT_FONT_SIZE
+            } else {
+                (state.font_size + delta).clamp(State::MIN_FONT_SIZE, State::MAX_FONT_SIZE)
+            };
         }
+
+        self.reveal_cursor();
     }
 
-    fn sync_cursor_to_view(state: &mut State, canvas_h: i32, line_h: i32) {
-        let visible_lines = (canvas_h / line_h).max(1) as usize;
-        let actual_scrolloff = state.scrolloff.min(visible_lines.saturating_sub(1) / 2);
+    /// Puts the cursor, scroll position and zoom back the way `view` recorded them.
+    pub fn restore_view_state(&mut self, view: &editor_state::session::ViewState) {
+        {
+            let mut state = self.state.borrow_mut();
+            state.font_size = view
+                .font_size
+                .clamp(State::MIN_FONT_SIZE, State::MAX_FONT_SIZE);
+            state.scroll_offset = view.scroll_offset;
+            state.read_row.set(view.read_row);
 
-        let top = state.scroll_offset + actual_scrolloff;
-        let bottom = state.scroll_offset + visible_lines.saturating_sub(1) - actual_scrolloff;
+            let mut d = state.doc.borrow_mut();
+            let row = view.cursor_row.min(d.get_line_count().saturating_sub(1));
+            let len = d.get_line_stripped(row).map_or(0, |line| line.len());
 
-        let mut d = state.doc.borrow_mut();
+            d.cursor.head.row = row;
+            d.cursor.head.col = view.cursor_col.min(len);
+            d.cursor.anchor = d.cursor.head;
+            d.restore_folds(&view.folds);
+        }
+
+        self.on_content_changed();
+    }
+
+    /// Starts tracking how far the note is read from its top again, e.g. after
+    /// opening another one.
+    pub fn reset_read_position(&mut self) {
+        self.state.borrow().read_row.set(0);
+    }
+
+    /// Jumps to the furthest row read so far.
+    pub fn continue_reading(&mut self) {
+        let row = self.state.borrow().read_row.get();
+
+        self.jump_to_row(row);
+    }
+
+    /// Moves the cursor to the start of `row` and scrolls it into view.
+    pub fn jump_to_row(&mut self, row: usize) {
+        {
+            let state = self.state.borrow();
+            let mut d = state.doc.borrow_mut();
+            let row = row.min(d.get_line_count().saturating_sub(1));
+
+            d.cursor.head.row = row;
+            d.cursor.head.col = 0;
+            d.cursor.anchor = d.cursor.head;
+        }
+
+        self.reveal_cursor();
+        let _ = self.canvas.take_focus();
+    }
+
+    /// Scrolls the cursor into view, unfolding whatever hides it. For commands
+    /// that move the cursor on the document directly.
+    pub fn reveal_cursor(&mut self) {
+        let mut state = self.state.borrow_mut();
+
+        {
+            let mut d = state.doc.borrow_mut();
+            let row = d.cursor.head.row;
+
+            d.folds.reveal(row);
+        }
+        state.last_interaction = std::time::Instant::now();
+        state.cursor_visible = true;
+        LayoutSync::sync_view_to_cursor(&mut state, self.canvas.height());
+        LayoutSync::apply_to_scrollbar(&mut state, &mut self.scrollbar, self.canvas.height());
+        drop(state);
+
+        self.canvas.redraw();
+    }
+}
+
+// ==========================================
+// 3. LAYOUT & SCROLL MATH
+// ==========================================
+struct LayoutSync;
+
+/// Where a document row lands on the canvas. `y` is relative to the canvas top
+/// and `h` spans the line of text plus anything drawn below it.
+#[derive(Debug, Clone, Copy)]
+struct RowSlot {
+    row: usize,
+    y: i32,
+    h: i32,
+}
+
+impl LayoutSync {
+    /// The rows from `scroll_offset` down to the bottom edge of the canvas.
+    fn visible_rows(
+        state: &State,
+        d: &editor_state::document::Document,
+        canvas_h: i32,
+    ) -> Vec<RowSlot> {
+        let heights = state.row_heights(d);
+        let top = heights.top(state.scroll_offset);
+        let last = heights
+            .row_at(top + canvas_h as i64 - 1)
+            .min(d.get_line_count().saturating_sub(1));
+
+        (state.scroll_offset..=last)
+            .map(|row| RowSlot {
+                row,
+                y: (heights.top(row) - top) as i32,
+                h: heights.height(row),
+            })
+            // Folded away.
+            .filter(|slot| slot.h > 0)
+            .collect()
+    }
+
+    /// How many whole rows starting at `from` fit on the canvas. Always at least one.
+    fn rows_fitting(
+        state: &State,
+        d: &editor_state::document::Document,
+        from: usize,
+        canvas_h: i32,
+    ) -> usize {
+        let heights = state.row_heights(d);
+
+        // Every row above the one crossing the bottom edge is fully visible.
+        let crossing = heights.row_at(heights.top(from) + canvas_h as i64);
+
+        (crossing - from).max(1)
+    }
+
+    /// The topmost row that still shows `last` in full at the bottom of the canvas.
+    fn first_row_showing(
+        state: &State,
+        d: &editor_state::document::Document,
+        last: usize,
+        canvas_h: i32,
+    ) -> usize {
+        let heights = state.row_heights(d);
+        let limit = heights.top(last + 1) - canvas_h as i64;
+        let first = heights.row_at(limit);
+
+        // `first` may stick out above the canvas; then the next row is the first that fits.
+        let first = if heights.top(first) < limit {
+            first + 1
+        } else {
+            first
+        };
+
+        first.min(last)
+    }
+
+    fn apply_to_scrollbar(
+        state: &mut State,
+        scrollbar: &mut fltk::valuator::Scrollbar,
+        canvas_h: i32,
+    ) {
+        let doc = state.doc.clone();
+        let d = doc.borrow();
+        let doc_lines = d.get_line_count();
+        let max_scroll = Self::first_row_showing(state, &d, doc_lines.saturating_sub(1), canvas_h);
+        let visible_lines = doc_lines - max_scroll;
+
+        state.scroll_offset = state.scroll_offset.clamp(0, max_scroll);
+        scrollbar.set_bounds(0.0, max_scroll as f64);
+        scrollbar.set_slider_size((visible_lines as f32 / doc_lines.max(1) as f32).clamp(0.0, 1.0));
+        scrollbar.set_value(state.scroll_offset as f64);
+    }
+
+    /// Pages in the screens the user is scrolling towards, plus the one on screen, so
+    /// scrolling through a large file that was never read doesn't stall on the disk.
+    fn prefetch_ahead(state: &State, old_offset: usize, canvas_h: i32) {
+        const SCREENS_AHEAD: usize = 2;
+
+        let d = state.doc.borrow();
+        let page = Self::rows_fitting(state, &d, state.scroll_offset, canvas_h);
+        let top = state.scroll_offset;
+        let rows = if top >= old_offset {
+            top..top + page * (SCREENS_AHEAD + 1)
+        } else {
+            top.saturating_sub(page * SCREENS_AHEAD)..top + page
+        };
+
+        d.text_buffer.prefetch_lines(rows);
+    }
+
+    fn sync_view_to_cursor(state: &mut State, canvas_h: i32) {
+        let doc = state.doc.clone();
+        let d = doc.borrow();
+        let visible_lines = Self::rows_fitting(state, &d, state.scroll_offset, canvas_h);
+        let actual_scrolloff = state.scrolloff.min(visible_lines.saturating_sub(1) / 2);
+        let head_row = d.cursor.head.row;
+        let top = state.scroll_offset + actual_scrolloff;
+        let bottom = state.scroll_offset + visible_lines.saturating_sub(1) - actual_scrolloff;
+
+        if head_row < top {
+            state.scroll_offset = head_row.saturating_sub(actual_scrolloff);
+        } else if head_row > bottom {
+            let first = Self::first_row_showing(state, &d, head_row + actual_scrolloff, canvas_h);
+
+            // A row taller than the canvas is shown from its top.
+            state.scroll_offset = first.min(head_row);
+        }
+    }
+
+    fn sync_cursor_to_view(state: &mut State, canvas_h: i32) {
+        let visible_lines =
+            Self::rows_fitting(state, &state.doc.borrow(), state.scroll_offset, canvas_h);
+        let actual_scrolloff = state.scrolloff.min(visible_lines.saturating_sub(1) / 2);
+
+        let top = state.scroll_offset + actual_scrolloff;
+        let bottom = state.scroll_offset + visible_lines.saturating_sub(1) - actual_scrolloff;
+
+        let mut d = state.doc.borrow_mut();
         let total_lines = d.get_line_count();
         let mut r = d.cursor.head.row;
 
@@ -167,54 +1278,261 @@ impl LayoutSync {
 struct Renderer;
 
 impl Renderer {
-    const FONT_SIZE: i32 = 16;
     const LEFT_PAD: i32 = 6;
-    const MARGIN_W: i32 = 45;
+    const MARGIN_W: i32 = 58;
+    /// Widths of the marker and fold strips either side of the line numbers.
+    const MARKER_W: i32 = 12;
+    const FOLD_W: i32 = 14;
+    const RULER_H: i32 = 14;
+
+    /// X coordinate of column 0. Outside focus mode the gutter sits to its left;
+    /// in focus mode there's no gutter and a line of `focus_measure` chars is centered.
+    /// Expects the editor font to be set.
+    fn text_x(w: &fltk::widget::Widget, be: &State) -> i32 {
+        if !be.focus_mode {
+            return w.x() + Self::MARGIN_W + Self::LEFT_PAD;
+        }
 
-    fn wire(canvas: &mut fltk::widget::Widget, state: Rc<RefCell<State>>, line_h: i32) {
+        let column_w = be.focus_measure as i32 * fltk::draw::width("a") as i32;
+
+        w.x() + ((w.width() - column_w) / 2).max(Self::LEFT_PAD)
+    }
+
+    fn wire(canvas: &mut fltk::widget::Widget, state: Rc<RefCell<State>>) {
         canvas.draw({
             let state = state.clone();
             move |w| {
                 // 1. Lock drawing strictly to the canvas dimensions!
                 // This prevents text from bleeding into the scrollbar area.
+                let started = std::time::Instant::now();
                 let be = state.borrow();
                 let d = be.doc.borrow();
+                let reads_before = d.text_buffer.stats().reads;
+                let rows = LayoutSync::visible_rows(&be, &d, w.height());
+
+                let palette = be.theme.palette();
+
+                Self::draw_bg(w, color(palette.background));
+                Self::draw_conflicts(w, &d, &rows, &palette);
+                Self::draw_selection(w, &be, &d, &rows);
+                Self::draw_guides(w, &be, &d);
+                Self::draw_text(w, &be, &d, &rows);
+                Self::draw_read_marker(w, &be, &d, &rows);
+                Self::draw_cursor(w, &be, &d, &rows);
+                Self::draw_calculation(w, &be, &d, &rows);
+                Self::draw_decorations(w, &be, &d, &rows);
+                Self::draw_line_time(w, &be, &d, &rows);
+                Self::draw_link_preview(w, &be, &rows);
+                Self::draw_column_ruler(w, &be, &d);
+
+                if be.perf_hud {
+                    Self::draw_hud(w, &be, &d, started.elapsed(), rows.len(), reads_before);
+                }
+
+                let watchers = be.watchers.clone();
 
-                Self::draw_bg(w);
-                Self::draw_selection(w, &be, &d, line_h);
-                Self::draw_text(w, &be, &d, line_h);
-                Self::draw_cursor(w, &be, &d, line_h);
+                drop(d);
+                drop(be);
+                for watcher in watchers {
+                    (watcher.borrow_mut())();
+                }
             }
         });
 
         let mut t_canvas = canvas.clone();
+        fltk::app::add_timeout3(0.5, {
+            let state = state.clone();
+            move |handle| {
+                let be = state.borrow();
+                let rendered = be.diagrams.borrow_mut().poll();
+
+                if be.images.borrow_mut().poll() || rendered {
+                    t_canvas.redraw();
+                }
+                fltk::app::repeat_timeout3(0.5, handle);
+            }
+        });
+
+        // Blinks at the caret's own pace, read on every tick so changes apply at once.
+        let mut b_canvas = canvas.clone();
         fltk::app::add_timeout3(0.5, move |handle| {
             let mut be = state.borrow_mut();
-            if be.last_interaction.elapsed().as_millis() >= 500 {
+            let blink = be.doc.borrow().settings.caret.blink;
+
+            if blink.is_some()
+                && be.last_interaction.elapsed() >= editor_state::caret::Caret::BLINK_DELAY
+            {
                 be.cursor_visible = !be.cursor_visible;
-                t_canvas.redraw();
-            } else {
+                b_canvas.redraw();
+            } else if !be.cursor_visible {
                 be.cursor_visible = true;
+                b_canvas.redraw();
             }
-            fltk::app::repeat_timeout3(0.5, handle);
+            fltk::app::repeat_timeout3(blink.map_or(0.5, |blink| blink.as_secs_f64()), handle);
         });
     }
 
-    fn draw_bg(w: &mut fltk::widget::Widget) {
+    /// The part of the gutter at `x`, `None` past it or in focus mode.
+    fn gutter_zone(w: &fltk::widget::Widget, be: &State, x: i32) -> Option<GutterZone> {
+        match x - w.x() {
+            _ if be.focus_mode => None,
+            x if x < Self::MARKER_W => Some(GutterZone::Markers),
+            x if x < Self::MARGIN_W - Self::FOLD_W => Some(GutterZone::Numbers),
+            x if x < Self::MARGIN_W => Some(GutterZone::Folds),
+            _ => None,
+        }
+    }
+
+    /// Whether `row`, holding `text`, opens a closed code block that can be run.
+    fn runnable_fence(
+        be: &State,
+        d: &editor_state::document::Document,
+        row: usize,
+        text: &str,
+    ) -> bool {
+        be.block_runner
+            .as_ref()
+            .is_some_and(|(runnable, _)| d.outline.code_block_end(row).is_some() && runnable(text))
+    }
+
+    /// X coordinate of the left edge of `column`. Sets the editor font.
+    fn column_x(w: &fltk::widget::Widget, be: &State, column: usize) -> i32 {
+        fltk::draw::set_font(fltk::enums::Font::Courier, be.font_size);
+
+        Self::text_x(w, be) + column as i32 * fltk::draw::width("a") as i32
+    }
+
+    /// The column under `x` on the ruler, `None` left of the text.
+    fn ruler_column(w: &fltk::widget::Widget, be: &State, x: i32) -> Option<usize> {
+        fltk::draw::set_font(fltk::enums::Font::Courier, be.font_size);
+
+        let rel_x = x - Self::text_x(w, be);
+        let char_w = fltk::draw::width("a") as i32;
+
+        // The nearest boundary between columns, where a guide is drawn.
+        (rel_x >= 0).then(|| ((rel_x + char_w / 2) / char_w) as usize)
+    }
+
+    /// A faint vertical line at each of the document's ruler columns.
+    fn draw_guides(w: &mut fltk::widget::Widget, be: &State, d: &editor_state::document::Document) {
+        let palette = be.theme.palette();
+        let guide = color(editor_state::theme::mix(
+            palette.background,
+            palette.muted,
+            0.35,
+        ));
+
+        for &column in &d.settings.rulers {
+            let x = Self::column_x(w, be, column);
+
+            if x < w.x() + w.width() {
+                fltk::draw::draw_rect_fill(x, w.y(), 1, w.height(), guide);
+            }
+        }
+    }
+
+    /// Column ticks along the top edge, numbered every ten, with the guides marked.
+    fn draw_column_ruler(
+        w: &mut fltk::widget::Widget,
+        be: &State,
+        d: &editor_state::document::Document,
+    ) {
+        if !be.column_ruler {
+            return;
+        }
+
+        let palette = be.theme.palette();
+        let right = w.x() + w.width();
+
         fltk::draw::draw_rect_fill(
             w.x(),
             w.y(),
             w.width(),
-            w.height(),
-            fltk::enums::Color::from_rgb(40, 44, 52),
+            Self::RULER_H,
+            color(editor_state::theme::mix(
+                palette.background,
+                palette.muted,
+                0.15,
+            )),
         );
+
+        let mut column = 0;
+        loop {
+            let x = Self::column_x(w, be, column);
+            if x >= right {
+                break;
+            }
+
+            let tick_h = match column % 10 {
+                0 => Self::RULER_H,
+                5 => Self::RULER_H / 2,
+                _ => Self::RULER_H / 4,
+            };
+
+            fltk::draw::draw_rect_fill(
+                x,
+                w.y() + Self::RULER_H - tick_h,
+                1,
+                tick_h,
+                color(palette.muted),
+            );
+            if column % 10 == 0 && column > 0 {
+                fltk::draw::set_font(fltk::enums::Font::Helvetica, 9);
+                fltk::draw::set_draw_color(color(palette.muted));
+                fltk::draw::draw_text2(
+                    &column.to_string(),
+                    x + 2,
+                    w.y(),
+                    30,
+                    Self::RULER_H - 2,
+                    fltk::enums::Align::Left | fltk::enums::Align::Inside,
+                );
+            }
+            column += 1;
+        }
+
+        for &column in &d.settings.rulers {
+            let x = Self::column_x(w, be, column);
+
+            if x < right {
+                fltk::draw::draw_rect_fill(x - 1, w.y(), 3, Self::RULER_H, color(palette.link));
+            }
+        }
+    }
+
+    fn draw_bg(w: &mut fltk::widget::Widget, background: fltk::enums::Color) {
+        fltk::draw::draw_rect_fill(w.x(), w.y(), w.width(), w.height(), background);
+    }
+
+    /// Tints merge conflict blocks: markers, our side and their side each get a shade.
+    fn draw_conflicts(
+        w: &mut fltk::widget::Widget,
+        d: &editor_state::document::Document,
+        rows: &[RowSlot],
+        palette: &editor_state::theme::Palette,
+    ) {
+        for slot in rows {
+            let Some(conflict) = d.conflicts.iter().find(|c| c.contains(slot.row)) else {
+                continue;
+            };
+            let accent = if conflict.ours().contains(&slot.row) {
+                (70, 130, 200)
+            } else if conflict.theirs().contains(&slot.row) {
+                (80, 170, 90)
+            } else {
+                (220, 170, 60)
+            };
+            let tint = editor_state::theme::mix(palette.background, accent, 0.3);
+
+            fltk::draw::draw_rect_fill(w.x(), w.y() + slot.y, w.width(), slot.h, color(tint));
+        }
     }
 
     fn draw_selection(
         w: &mut fltk::widget::Widget,
         be: &State,
         d: &editor_state::document::Document,
-        line_h: i32,
+        rows: &[RowSlot],
     ) {
         let (start, end) = d.cursor.range();
 
@@ -222,25 +1540,32 @@ impl Renderer {
             return;
         }
 
-        fltk::draw::set_font(fltk::enums::Font::Courier, Self::FONT_SIZE);
+        fltk::draw::set_font(fltk::enums::Font::Courier, be.font_size);
 
-        let char_w = fltk::draw::width("a") as i32;
-        let base_x = w.x() + Self::MARGIN_W + Self::LEFT_PAD;
+        let base_x = Self::text_x(w, be);
         // Define the color once
-        let selection_color = fltk::enums::Color::from_rgb(62, 68, 81);
+        let selection_color = color(be.theme.palette().selection);
 
-        for i in start.row..=end.row {
-            if i < be.scroll_offset || i > be.scroll_offset + (w.height() / line_h) as usize + 1 {
-                continue;
-            }
+        for slot in rows
+            .iter()
+            .filter(|slot| (start.row..=end.row).contains(&slot.row))
+        {
+            let i = slot.row;
+            let y = w.y() + slot.y;
+            let (font, line_h) = be.row_font(d, i);
 
-            let y = w.y() + ((i - be.scroll_offset) as i32 * line_h);
+            fltk::draw::set_font(font, line_h);
+            let char_w = fltk::draw::width("a") as i32;
 
-            let start_col = if i == start.row { start.col as i32 } else { 0 };
+            let start_col = if i == start.row {
+                be.visual_col(d, i, start.col) as i32
+            } else {
+                0
+            };
             let end_col = if i == end.row {
-                end.col as i32
+                be.visual_col(d, i, end.col) as i32
             } else {
-                as_usize(d.get_visible_line_len_at(i).unwrap_or(0)) as i32 + 1
+                be.visual_col(d, i, as_usize(d.get_visible_line_len_at(i).unwrap_or(0))) as i32 + 1
             };
 
             let rect_x = base_x + (start_col * char_w);
@@ -255,66 +1580,739 @@ impl Renderer {
         w: &mut fltk::widget::Widget,
         be: &State,
         d: &editor_state::document::Document,
+        rows: &[RowSlot],
+    ) {
+        fltk::draw::set_font(fltk::enums::Font::Courier, be.font_size);
+
+        let text_x = Self::text_x(w, be);
+        let palette = be.theme.palette();
+        let focused =
+            (be.focus_mode && be.dim_unfocused).then(|| d.paragraph_at(d.cursor.head.row));
+        let math = if be.rich_text {
+            be.math_blocks(d)
+        } else {
+            Rc::from([])
+        };
+        let diagrams = if be.inline_images {
+            be.diagrams.borrow_mut().fences(d)
+        } else {
+            Rc::from([])
+        };
+        // Source files are highlighted throughout, notes only in their code blocks.
+        let file_language = d.file_type().language();
+        let fold_starts = if be.focus_mode {
+            Rc::from([])
+        } else {
+            be.fold_starts(d)
+        };
+        let tabstops = be.tabstops(d);
+        let key_rows = be.key_rows(d);
+
+        for slot in rows {
+            let i = slot.row;
+            let (font, line_h) = be.row_font(d, i);
+
+            if let Some(text) = d.get_line_stripped(i) {
+                let y = w.y() + slot.y;
+
+                if !be.focus_mode {
+                    fltk::draw::set_font(fltk::enums::Font::Courier, be.font_size);
+                    fltk::draw::set_draw_color(color(palette.muted));
+                    fltk::draw::draw_text2(
+                        &format!("{:3}", i + 1),
+                        w.x(),
+                        y,
+                        Self::MARGIN_W - Self::FOLD_W - 2,
+                        line_h,
+                        fltk::enums::Align::Right | fltk::enums::Align::Inside,
+                    );
+
+                    let marker = if Self::runnable_fence(be, d, i, &text) {
+                        Some(("▶", palette.link))
+                    } else if d.bookmarks.contains(i) {
+                        Some(("◆", palette.heading))
+                    } else {
+                        None
+                    };
+                    let fold = if d.folds.folded_at(i).is_some() {
+                        Some("▸")
+                    } else if fold_starts.binary_search(&i).is_ok() {
+                        Some("▾")
+                    } else {
+                        None
+                    };
+
+                    if let Some((marker, marker_color)) = marker {
+                        fltk::draw::set_draw_color(color(marker_color));
+                        fltk::draw::draw_text2(
+                            marker,
+                            w.x() + 2,
+                            y,
+                            Self::MARKER_W,
+                            line_h,
+                            fltk::enums::Align::Left | fltk::enums::Align::Inside,
+                        );
+                    }
+
+                    if let Some(fold) = fold {
+                        fltk::draw::set_draw_color(color(palette.muted));
+                        fltk::draw::draw_text2(
+                            fold,
+                            w.x() + Self::MARGIN_W - Self::FOLD_W,
+                            y,
+                            Self::FOLD_W - 3,
+                            line_h,
+                            fltk::enums::Align::Center | fltk::enums::Align::Inside,
+                        );
+                    }
+
+                    if let Some(change) = d.changes.get(i) {
+                        let bar = match change {
+                            editor_state::changes::LineChange::Added => (80, 180, 90),
+                            editor_state::changes::LineChange::Modified => (230, 150, 50),
+                        };
+
+                        fltk::draw::draw_rect_fill(
+                            w.x() + Self::MARGIN_W - 3,
+                            y,
+                            3,
+                            line_h,
+                            color(bar),
+                        );
+                    }
+                }
+
+                let dimmed = focused.as_ref().is_some_and(|rows| !rows.contains(&i));
+                let text_color = if dimmed {
+                    palette.muted
+                } else if be.rich_text && d.outline.heading_on(i).is_some() {
+                    palette.heading
+                } else {
+                    palette.text
+                };
+                let language = file_language.or_else(|| {
+                    d.outline
+                        .code_block_at(i)
+                        .and_then(|fence| d.get_line_stripped(fence))
+                        .and_then(|fence| editor_state::highlight::Language::of_fence(&fence))
+                });
+                let spans = match language {
+                    _ if !be.rich_text => Vec::new(),
+                    Some(language) => editor_state::highlight::code_spans(&text, language),
+                    None if d.outline.in_code(i) => Vec::new(),
+                    None => editor_state::highlight::inline_spans(&text),
+                };
+
+                let cells = tabstops.as_ref().map(|tabs| tabs.cells(i));
+
+                fltk::draw::set_font(font, line_h);
+                Self::draw_line(
+                    w,
+                    &text,
+                    &spans,
+                    cells,
+                    (text_x, y, line_h),
+                    (font, color(text_color)),
+                    &palette,
+                );
+                if let Some(redactor) = &be.redactor {
+                    let in_key = key_rows
+                        .iter()
+                        .any(|(start, end)| (*start..=*end).contains(&i));
+
+                    Self::draw_masks(
+                        &text,
+                        &if in_key {
+                            std::iter::once(0..text.len()).collect()
+                        } else {
+                            redactor.secrets(&text)
+                        },
+                        cells,
+                        (text_x, y, line_h),
+                        &palette,
+                    );
+                }
+
+                if d.folds.folded_at(i).is_some() {
+                    let end = be.visual_col(d, i, text.chars().count());
+                    let marker_x = text_x + (end as i32 + 1) * fltk::draw::width("a") as i32;
+
+                    fltk::draw::set_font(fltk::enums::Font::Courier, be.font_size);
+                    fltk::draw::set_draw_color(color(palette.muted));
+                    fltk::draw::draw_rect(
+                        marker_x,
+                        y + 1,
+                        3 * fltk::draw::width("a") as i32,
+                        line_h - 2,
+                    );
+                    fltk::draw::draw_text2(
+                        "…",
+                        marker_x,
+                        y,
+                        3 * fltk::draw::width("a") as i32,
+                        line_h,
+                        fltk::enums::Align::Center,
+                    );
+                }
+            }
+
+            if let Some(mut image) = be.image_for(d, i) {
+                let (image_w, image_h) = (image.data_w(), image.data_h());
+
+                image.draw(
+                    text_x,
+                    w.y() + slot.y + be.line_height() + State::IMAGE_PAD,
+                    image_w,
+                    image_h,
+                );
+            }
+
+            let below = (text_x, w.y() + slot.y + be.line_height() + State::IMAGE_PAD);
+
+            if let Some((block, typeset)) = math.iter().find(|(block, _)| block.end == i) {
+                match typeset {
+                    Ok(text) => Self::draw_below(
+                        w,
+                        text,
+                        (fltk::enums::Font::TimesItalic, color(palette.text)),
+                        below,
+                        be.line_height(),
+                    ),
+                    Err(err) => Self::draw_below(
+                        w,
+                        &format!("⚠ {err}: {}", block.tex.replace('\n', " ")),
+                        (fltk::enums::Font::Courier, color(palette.muted)),
+                        below,
+                        be.line_height(),
+                    ),
+                }
+            }
+
+            if let Some(diagram) = diagrams.iter().find(|diagram| diagram.end == i) {
+                let status = match be.diagram_for(&diagram.source) {
+                    Some(Ok(mut image)) => {
+                        let (image_w, image_h) = (image.data_w(), image.data_h());
+
+                        image.draw(below.0, below.1, image_w, image_h);
+                        None
+                    }
+                    Some(Err(err)) => Some(format!("⚠ Couldn't render diagram: {err}")),
+                    None => Some("Rendering diagram…".to_string()),
+                };
+
+                if let Some(status) = status {
+                    Self::draw_below(
+                        w,
+                        &status,
+                        (fltk::enums::Font::Courier, color(palette.muted)),
+                        below,
+                        be.line_height(),
+                    );
+                }
+            }
+        }
+    }
+
+    /// One line of `text` drawn at `(x, y)` under a block: typeset math, or what
+    /// became of a diagram.
+    fn draw_below(
+        w: &fltk::widget::Widget,
+        text: &str,
+        (font, text_color): (fltk::enums::Font, fltk::enums::Color),
+        (x, y): (i32, i32),
         line_h: i32,
     ) {
-        fltk::draw::set_font(fltk::enums::Font::Courier, Self::FONT_SIZE);
-        let end = std::cmp::min(
-            d.get_line_count(),
-            be.scroll_offset + (w.height() / line_h) as usize + 1,
+        fltk::draw::set_font(font, line_h);
+        fltk::draw::set_draw_color(text_color);
+        fltk::draw::draw_text2(
+            text,
+            x,
+            y,
+            (w.x() + w.width() - x).max(0),
+            line_h,
+            fltk::enums::Align::Left,
         );
+    }
 
-        for i in be.scroll_offset..end {
-            if let Some(text) = d.get_line_stripped(i) {
-                let y = w.y() + ((i - be.scroll_offset) as i32 * line_h);
-                fltk::draw::set_draw_color(fltk::enums::Color::from_rgb(120, 120, 120));
-                fltk::draw::draw_text2(
-                    &format!("{:3}", i + 1),
-                    w.x(),
+    /// Draws one line of text with its inline Markdown spans, starting at `x`.
+    /// Plain text uses `font` and `color`; every font is monospaced, so a span's
+    /// x is just its column times the width of a character.
+    fn draw_line(
+        w: &fltk::widget::Widget,
+        text: &str,
+        spans: &[editor_state::highlight::Span],
+        cells: Option<&[usize]>,
+        (x, y, line_h): (i32, i32, i32),
+        (font, text_color): (fltk::enums::Font, fltk::enums::Color),
+        palette: &editor_state::theme::Palette,
+    ) {
+        let char_w = fltk::draw::width("a") as i32;
+        let right = w.x() + w.width();
+        let x_of = |byte: usize| x + Self::text_col(text, cells, byte) * char_w;
+        let mut at = 0;
+
+        let draw = |from: usize, to: usize, font, piece_color, code: bool| {
+            let piece_x = x_of(from);
+
+            if code {
+                fltk::draw::draw_rect_fill(
+                    piece_x,
                     y,
-                    Self::MARGIN_W - 5,
+                    x_of(to) - piece_x,
                     line_h,
-                    fltk::enums::Align::Right | fltk::enums::Align::Inside,
+                    color(palette.code_background),
                 );
-                fltk::draw::set_draw_color(fltk::enums::Color::White);
+            }
+
+            fltk::draw::set_font(font, line_h);
+            fltk::draw::set_draw_color(piece_color);
+
+            // With elastic tabstops each run between tabs goes where its cell starts.
+            let mut start = from;
+            for end in text[from..to]
+                .match_indices('\t')
+                .filter(|_| cells.is_some())
+                .map(|(i, _)| from + i)
+                .chain([to])
+            {
+                let run_x = x_of(start);
+
                 fltk::draw::draw_text2(
-                    &text,
-                    w.x() + Self::MARGIN_W + Self::LEFT_PAD,
+                    &text[start..end],
+                    run_x,
                     y,
-                    w.width() - Self::MARGIN_W,
+                    (right - run_x).max(0),
                     line_h,
                     fltk::enums::Align::Left,
                 );
+                start = (end + 1).min(to);
             }
+        };
+
+        for span in spans {
+            if span.range.start > at {
+                draw(at, span.range.start, font, text_color, false);
+            }
+
+            let (span_font, span_color) = match span.style {
+                editor_state::highlight::SpanStyle::Marker => (font, color(palette.muted)),
+                editor_state::highlight::SpanStyle::Emphasis => {
+                    (fltk::enums::Font::CourierItalic, text_color)
+                }
+                editor_state::highlight::SpanStyle::Strong => {
+                    (fltk::enums::Font::CourierBold, text_color)
+                }
+                editor_state::highlight::SpanStyle::Code => (font, color(palette.code_text)),
+                editor_state::highlight::SpanStyle::Math => {
+                    (fltk::enums::Font::CourierItalic, color(palette.code_text))
+                }
+                editor_state::highlight::SpanStyle::Keyword => {
+                    (fltk::enums::Font::CourierBold, color(palette.heading))
+                }
+                editor_state::highlight::SpanStyle::String
+                | editor_state::highlight::SpanStyle::Number => (font, color(palette.link)),
+                editor_state::highlight::SpanStyle::Comment => {
+                    (fltk::enums::Font::CourierItalic, color(palette.muted))
+                }
+            };
+
+            draw(
+                span.range.start,
+                span.range.end,
+                span_font,
+                span_color,
+                span.style == editor_state::highlight::SpanStyle::Code,
+            );
+            at = span.range.end;
+        }
+
+        if at < text.len() {
+            draw(at, text.len(), font, text_color, false);
         }
     }
 
-    fn draw_cursor(
-        w: &mut fltk::widget::Widget,
-        be: &State,
-        d: &editor_state::document::Document,
-        line_h: i32,
-    ) {
+    /// The visual column byte `byte` of `text` is drawn at, its tab-ended cells
+    /// `cells` wide with elastic tabstops, or one column per character.
+    fn text_col(text: &str, cells: Option<&[usize]>, byte: usize) -> i32 {
+        let col = text[..byte].chars().count();
+
+        cells.map_or(col, |cells| layout::elastic::visual_col(text, cells, col)) as i32
+    }
+
+    /// Covers the `secrets` of `text`, drawn at `(x, y)`, with solid bars.
+    fn draw_masks(
+        text: &str,
+        secrets: &[std::ops::Range<usize>],
+        cells: Option<&[usize]>,
+        (x, y, line_h): (i32, i32, i32),
+        palette: &editor_state::theme::Palette,
+    ) {
+        let char_w = fltk::draw::width("a") as i32;
+
+        for secret in secrets {
+            let col = Self::text_col(text, cells, secret.start);
+            let len = Self::text_col(text, cells, secret.end) - col;
+
+            fltk::draw::draw_rect_fill(
+                x + col * char_w,
+                y + 1,
+                len * char_w,
+                line_h - 2,
+                color(palette.muted),
+            );
+        }
+    }
+
+    /// Overlay in the top-right corner with the cost of the frame just drawn, how long
+    /// the input it shows waited for it, and the shape of the buffer behind it.
+    /// `reads_before` is the piece table's read count when the frame started.
+    fn draw_hud(
+        w: &mut fltk::widget::Widget,
+        be: &State,
+        d: &editor_state::document::Document,
+        frame: std::time::Duration,
+        rows: usize,
+        reads_before: u64,
+    ) {
+        const LINE_H: i32 = 14;
+        const BOX_W: i32 = 150;
+
+        let stats = d.text_buffer.stats();
+        let frame_ms = frame.as_secs_f64() * 1000.0;
+        let average_ms = be.frame_ms.get() * 0.9 + frame_ms * 0.1;
+
+        if let Some((since, inputs)) = be.input_since.take() {
+            be.input_latency
+                .set((since.elapsed().as_secs_f64() * 1000.0, inputs));
+        }
+
+        let (input_ms, inputs) = be.input_latency.get();
+        let lines = [
+            format!("frame  {frame_ms:7.2} ms"),
+            format!("avg    {average_ms:7.2} ms"),
+            format!("input  {input_ms:7.2} ms"),
+            format!("batch  {inputs:7}"),
+            format!("rows   {rows:7}"),
+            format!("reads  {:7}", stats.reads - reads_before),
+            format!("pieces {:7}", stats.pieces),
+            format!("depth  {:7}", stats.index_depth),
+        ];
+        let box_h = lines.len() as i32 * LINE_H + 8;
+        let x = w.x() + w.width() - BOX_W - 8;
+        let y = w.y() + 8;
+
+        be.frame_ms.set(average_ms);
+        fltk::draw::draw_rect_fill(x, y, BOX_W, box_h, fltk::enums::Color::from_rgb(0, 0, 0));
+        fltk::draw::set_font(fltk::enums::Font::Courier, 11);
+        fltk::draw::set_draw_color(fltk::enums::Color::from_rgb(140, 220, 140));
+
+        for (i, line) in lines.iter().enumerate() {
+            fltk::draw::draw_text2(
+                line,
+                x + 6,
+                y + 4 + i as i32 * LINE_H,
+                BOX_W - 12,
+                LINE_H,
+                fltk::enums::Align::Left,
+            );
+        }
+    }
+
+    /// A short muted bar under the furthest row read, unless that's the last one.
+    /// Also moves the furthest row read down to the bottom of this frame.
+    fn draw_read_marker(
+        w: &mut fltk::widget::Widget,
+        be: &State,
+        d: &editor_state::document::Document,
+        rows: &[RowSlot],
+    ) {
+        let read_row = be
+            .read_row
+            .get()
+            .max(rows.last().map_or(0, |slot| slot.row));
+
+        be.read_row.set(read_row);
+
+        if read_row + 1 >= d.get_line_count() {
+            return;
+        }
+        let Some(slot) = rows.iter().find(|slot| slot.row == read_row) else {
+            return;
+        };
+
+        fltk::draw::set_font(fltk::enums::Font::Courier, be.font_size);
+
+        let x = if be.focus_mode {
+            Self::text_x(w, be) - 16
+        } else {
+            w.x() + 4
+        };
+
+        fltk::draw::draw_rect_fill(
+            x,
+            w.y() + slot.y + slot.h - 2,
+            if be.focus_mode {
+                12
+            } else {
+                Self::MARGIN_W - 8
+            },
+            2,
+            color(be.theme.palette().muted),
+        );
+    }
+
+    /// Each decoration as a caret in its color with its label in a tag above it. Tags
+    /// of the top row hang below the caret instead.
+    fn draw_decorations(
+        w: &mut fltk::widget::Widget,
+        be: &State,
+        d: &editor_state::document::Document,
+        rows: &[RowSlot],
+    ) {
+        const TAG_FONT: i32 = 11;
+
+        fltk::draw::set_font(fltk::enums::Font::Courier, be.font_size);
+
+        let text_x = Self::text_x(w, be);
+
+        for decoration in &be.decorations {
+            let Some(slot) = rows.iter().find(|slot| slot.row == decoration.row) else {
+                continue;
+            };
+            let (font, line_h) = be.row_font(d, decoration.row);
+
+            fltk::draw::set_font(font, line_h);
+
+            let x = text_x + (decoration.col as i32 * fltk::draw::width("a") as i32);
+            let caret_y = w.y() + slot.y + (line_h - fltk::draw::height()) / 2;
+            let caret_h = fltk::draw::height();
+
+            fltk::draw::draw_rect_fill(x, caret_y, 2, caret_h, color(decoration.color));
+
+            if decoration.label.is_empty() {
+                continue;
+            }
+
+            fltk::draw::set_font(fltk::enums::Font::Helvetica, TAG_FONT);
+
+            let tag_w = fltk::draw::width(&decoration.label) as i32 + 6;
+            let tag_h = fltk::draw::height();
+            let tag_y = if caret_y - tag_h < w.y() {
+                caret_y + caret_h
+            } else {
+                caret_y - tag_h
+            };
+
+            fltk::draw::draw_rect_fill(x, tag_y, tag_w, tag_h, color(decoration.color));
+            fltk::draw::set_draw_color(fltk::enums::Color::White);
+            fltk::draw::draw_text2(
+                &decoration.label,
+                x + 3,
+                tag_y,
+                tag_w - 6,
+                tag_h,
+                fltk::enums::Align::Left,
+            );
+        }
+    }
+
+    /// When the line under the pointer in the gutter was last edited, in a tag right
+    /// below its number. Only lines with a recorded time get one.
+    fn draw_line_time(
+        w: &mut fltk::widget::Widget,
+        be: &State,
+        d: &editor_state::document::Document,
+        rows: &[RowSlot],
+    ) {
+        let Some(row) = be.gutter_hover else {
+            return;
+        };
+        let Some(time) = d.line_times.as_ref().and_then(|times| times.get(row)) else {
+            return;
+        };
+        let Some(slot) = rows.iter().find(|slot| slot.row == row) else {
+            return;
+        };
+        let label = editor_state::line_times::describe(time, std::time::SystemTime::now());
+        let palette = be.theme.palette();
+
+        fltk::draw::set_font(fltk::enums::Font::Helvetica, 12);
+
+        let (tag_w, tag_h) = (
+            fltk::draw::width(&label) as i32 + 10,
+            fltk::draw::height() + 4,
+        );
+        let x = w.x() + 2;
+        let y = w.y() + slot.y + slot.h;
+
+        fltk::draw::draw_rect_fill(x, y, tag_w, tag_h, color(palette.text));
+        fltk::draw::set_draw_color(color(palette.background));
+        fltk::draw::draw_text2(
+            &label,
+            x + 5,
+            y,
+            tag_w - 10,
+            tag_h,
+            fltk::enums::Align::Left,
+        );
+    }
+
+    /// What the link under the pointer leads to, in a box right below it.
+    fn draw_link_preview(w: &mut fltk::widget::Widget, be: &State, rows: &[RowSlot]) {
+        const MAX_CHARS: usize = 80;
+
+        let Some(hover) = be
+            .link_hover
+            .as_ref()
+            .filter(|hover| !hover.lines.is_empty())
+        else {
+            return;
+        };
+        let Some(slot) = rows.iter().find(|slot| slot.row == hover.row) else {
+            return;
+        };
+        let lines = match &be.redactor {
+            Some(redactor) => redactor.mask_lines(&hover.lines),
+            None => hover.lines.clone(),
+        };
+        let lines: Vec<String> = lines
+            .iter()
+            .map(|line| match line.char_indices().nth(MAX_CHARS) {
+                Some((end, _)) => format!("{}…", &line[..end]),
+                None => line.clone(),
+            })
+            .collect();
+        let palette = be.theme.palette();
+
+        fltk::draw::set_font(fltk::enums::Font::Helvetica, 12);
+
+        let line_h = fltk::draw::height();
+        let box_w = lines
+            .iter()
+            .map(|line| fltk::draw::width(line) as i32)
+            .max()
+            .unwrap_or(0)
+            + 10;
+        let box_h = line_h * lines.len() as i32 + 6;
+        let x = (w.x() + hover.x).min(w.x() + w.w() - box_w).max(w.x());
+        let below = w.y() + slot.y + slot.h;
+        // Above the link when there's no room below it.
+        let y = if below + box_h > w.y() + w.h() {
+            (w.y() + slot.y - box_h).max(w.y())
+        } else {
+            below
+        };
+
+        fltk::draw::draw_rect_fill(x, y, box_w, box_h, color(palette.text));
+        fltk::draw::set_draw_color(color(palette.background));
+        for (i, line) in lines.iter().enumerate() {
+            fltk::draw::draw_text2(
+                line,
+                x + 5,
+                y + 3 + line_h * i as i32,
+                box_w - 10,
+                line_h,
+                fltk::enums::Align::Left,
+            );
+        }
+    }
+
+    /// The result offered for the expression just typed, greyed out after the
+    /// cursor until Tab inserts it.
+    fn draw_calculation(
+        w: &mut fltk::widget::Widget,
+        be: &State,
+        d: &editor_state::document::Document,
+        rows: &[RowSlot],
+    ) {
+        let Some((at, result)) = &d.calculation else {
+            return;
+        };
+        let Some(slot) = rows.iter().find(|slot| slot.row == at.row) else {
+            return;
+        };
+        if *at != d.cursor.head {
+            return;
+        }
+
+        let (font, line_h) = be.row_font(d, at.row);
+
+        fltk::draw::set_font(font, line_h);
+        fltk::draw::set_draw_color(color(be.theme.palette().muted));
+        fltk::draw::draw_text2(
+            &format!("{result}  ⇥"),
+            Self::text_x(w, be) + (at.col as i32 * fltk::draw::width("a") as i32) + 2,
+            w.y() + slot.y,
+            0,
+            line_h,
+            fltk::enums::Align::Left,
+        );
+    }
+
+    fn draw_cursor(
+        w: &mut fltk::widget::Widget,
+        be: &State,
+        d: &editor_state::document::Document,
+        rows: &[RowSlot],
+    ) {
         if !be.cursor_visible {
             return;
         }
         let head = d.cursor.head;
 
-        if head.row >= be.scroll_offset
-            && head.row <= be.scroll_offset + (w.height() / line_h) as usize
-        {
-            let x = w.x()
-                + Self::MARGIN_W
-                + Self::LEFT_PAD
-                + (head.col as i32 * fltk::draw::width("a") as i32);
-            let y = w.y() + ((head.row - be.scroll_offset) as i32 * line_h);
+        if let Some(slot) = rows.iter().find(|slot| slot.row == head.row) {
+            fltk::draw::set_font(fltk::enums::Font::Courier, be.font_size);
 
-            fltk::draw::draw_rect_fill(
-                x,
-                y + (line_h - fltk::draw::height()) / 2,
-                2,
-                fltk::draw::height(),
-                fltk::enums::Color::White,
-            );
+            let text_x = Self::text_x(w, be);
+            let (font, line_h) = be.row_font(d, head.row);
+
+            fltk::draw::set_font(font, line_h);
+
+            let caret = d.settings.caret;
+            let palette = be.theme.palette();
+            let caret_color = color(caret.color.unwrap_or(palette.text));
+            let thickness = i32::from(caret.width.max(1));
+            let char_w = fltk::draw::width("a") as i32;
+            let col = be.visual_col(d, head.row, be.virtual_col(d).unwrap_or(head.col));
+            let x = text_x + (col as i32 * char_w);
+            let text_h = fltk::draw::height();
+            let y = w.y() + slot.y + (line_h - text_h) / 2;
+
+            match caret.shape {
+                editor_state::caret::CaretShape::Bar => {
+                    fltk::draw::draw_rect_fill(x, y, thickness, text_h, caret_color);
+                }
+                editor_state::caret::CaretShape::Underline => {
+                    fltk::draw::draw_rect_fill(
+                        x,
+                        y + text_h - thickness,
+                        char_w,
+                        thickness,
+                        caret_color,
+                    );
+                }
+                editor_state::caret::CaretShape::Block => {
+                    fltk::draw::draw_rect_fill(x, y, char_w, text_h, caret_color);
+
+                    // The character under it, inverted so it stays readable.
+                    let under = d
+                        .get_line_stripped(head.row)
+                        .and_then(|line| line.get(head.col..)?.chars().next())
+                        .filter(|c| !c.is_whitespace());
+
+                    if let Some(under) = under {
+                        fltk::draw::set_draw_color(color(palette.background));
+                        fltk::draw::draw_text2(
+                            &under.to_string(),
+                            x,
+                            y,
+                            char_w,
+                            text_h,
+                            fltk::enums::Align::Left | fltk::enums::Align::Inside,
+                        );
+                    }
+                }
+            }
         }
     }
 }
@@ -329,22 +2327,38 @@ impl Controller {
         canvas: &mut fltk::widget::Widget,
         sb: &mut fltk::valuator::Scrollbar,
         state: Rc<RefCell<State>>,
-        lh: i32,
     ) {
         sb.set_callback({
             let state = state.clone();
             let mut c = canvas.clone();
             let mut sbc = sb.clone();
             move |s| {
+                let old_offset = state.borrow().scroll_offset;
+
                 state.borrow_mut().scroll_offset = s.value() as usize;
-                Self::refresh_view(&mut state.borrow_mut(), &mut c, &mut sbc, lh);
+                LayoutSync::prefetch_ahead(&state.borrow(), old_offset, c.height());
+                Self::refresh_view(&mut state.borrow_mut(), &mut c, &mut sbc);
             }
         });
 
         let st = state.clone();
         let mut handle_sb = sb.clone();
 
-        canvas.handle(move |c, ev| match ev {
+        canvas.handle(move |c, ev| {
+            let handled = Self::handle(c, ev, &st, &mut handle_sb);
+
+            Self::schedule_sync(c, &st, &handle_sb);
+            handled
+        });
+    }
+
+    fn handle(
+        c: &mut fltk::widget::Widget,
+        ev: fltk::enums::Event,
+        st: &Rc<RefCell<State>>,
+        sb: &mut fltk::valuator::Scrollbar,
+    ) -> bool {
+        match ev {
             fltk::enums::Event::Enter => {
                 if let Some(mut w) = c.window() {
                     w.set_cursor(fltk::enums::Cursor::Insert);
@@ -355,69 +2369,200 @@ impl Controller {
                 if let Some(mut w) = c.window() {
                     w.set_cursor(fltk::enums::Cursor::Default);
                 }
+                let mut be = st.borrow_mut();
+
+                if be.gutter_hover.take().is_some() | be.link_hover.take().is_some() {
+                    c.redraw();
+                }
                 true
             }
-            fltk::enums::Event::MouseWheel => {
-                Self::on_mouse_wheel(c, &mut st.borrow_mut(), &mut handle_sb, lh)
+            fltk::enums::Event::Move => Self::on_move(c, &mut st.borrow_mut()),
+            fltk::enums::Event::MouseWheel => Self::on_mouse_wheel(c, &mut st.borrow_mut(), sb),
+            fltk::enums::Event::Resize => Self::on_resize(c, &mut st.borrow_mut(), sb),
+            // A read-only view only scrolls and copies.
+            fltk::enums::Event::Push
+            | fltk::enums::Event::Drag
+            | fltk::enums::Event::Paste
+            | fltk::enums::Event::KeyDown
+            | fltk::enums::Event::DndRelease
+                if st.borrow().read_only =>
+            {
+                false
             }
-            fltk::enums::Event::Resize => {
-                Self::on_resize(c, &mut st.borrow_mut(), &mut handle_sb, lh)
+            fltk::enums::Event::Shortcut
+                if st.borrow().read_only
+                    && fltk::app::event_key() != fltk::enums::Key::from_char('c') =>
+            {
+                false
             }
-            fltk::enums::Event::Push => Self::on_push(c, &mut st.borrow_mut(), &mut handle_sb, lh),
-            fltk::enums::Event::Drag => Self::on_drag(c, &mut st.borrow_mut(), &mut handle_sb, lh),
+            fltk::enums::Event::Push => Self::on_push(c, &mut st.borrow_mut(), sb),
+            fltk::enums::Event::Drag => Self::on_drag(c, &mut st.borrow_mut()),
             fltk::enums::Event::Shortcut => {
                 let event_key = fltk::app::event_key();
 
                 if event_key == fltk::enums::Key::from_char('v') {
+                    st.borrow_mut().paste_match_indent =
+                        fltk::app::event_state().contains(fltk::enums::EventState::Shift);
                     fltk::app::paste(c);
                 } else if event_key == fltk::enums::Key::from_char('c') {
-                    return Self::on_copy(c, &mut st.borrow_mut(), &mut handle_sb, lh);
+                    return Self::on_copy(c, &mut st.borrow_mut(), sb);
                 } else if event_key == fltk::enums::Key::from_char('x') {
-                    return Self::on_cut(c, &mut st.borrow_mut(), &mut handle_sb, lh);
+                    return Self::on_cut(c, &mut st.borrow_mut(), sb);
                 } else if event_key == fltk::enums::Key::from_char('a') {
                 }
 
                 true
             }
-            fltk::enums::Event::Paste => {
-                Self::on_paste(c, &mut st.borrow_mut(), &mut handle_sb, lh)
-            }
-            fltk::enums::Event::KeyDown => {
-                Self::on_keydown(c, &mut st.borrow_mut(), &mut handle_sb, lh)
+            fltk::enums::Event::DndEnter
+            | fltk::enums::Event::DndDrag
+            | fltk::enums::Event::DndLeave => true,
+            fltk::enums::Event::DndRelease => {
+                st.borrow_mut().dropping = true;
+                true
             }
+            fltk::enums::Event::Paste => Self::on_paste(c, &mut st.borrow_mut(), sb),
+            fltk::enums::Event::KeyDown => Self::on_keydown(c, &mut st.borrow_mut()),
             fltk::enums::Event::Focus | fltk::enums::Event::Unfocus => true,
             _ => false,
+        }
+    }
+
+    /// Follows the cursor with the view and scrollbar once for everything handled in
+    /// this turn of the event loop. Zero-delay timeouts run before FLTK flushes the
+    /// redraws they asked for, so the frame still draws with the view in place.
+    fn schedule_sync(
+        c: &fltk::widget::Widget,
+        st: &Rc<RefCell<State>>,
+        sb: &fltk::valuator::Scrollbar,
+    ) {
+        {
+            let mut be = st.borrow_mut();
+
+            if be.view_sync != ViewSync::Requested {
+                return;
+            }
+            be.view_sync = ViewSync::Scheduled;
+        }
+
+        let st = st.clone();
+        let mut c = c.clone();
+        let mut sb = sb.clone();
+
+        fltk::app::add_timeout3(0.0, move |_| {
+            let mut be = st.borrow_mut();
+
+            if be.view_sync != ViewSync::Scheduled {
+                return;
+            }
+            be.view_sync = ViewSync::Idle;
+            LayoutSync::sync_view_to_cursor(&mut be, c.height());
+            LayoutSync::apply_to_scrollbar(&mut be, &mut sb, c.height());
+            c.redraw();
         });
     }
 
     // --- Utility Input Math ---
 
-    fn mouse_to_pos(c: &fltk::widget::Widget, be: &State, lh: i32) -> (usize, usize) {
-        fltk::draw::set_font(fltk::enums::Font::Courier, Renderer::FONT_SIZE);
-        let row = be.scroll_offset + ((fltk::app::event_y() - c.y()) / lh).max(0) as usize;
-        let rel_x = fltk::app::event_x() - (c.x() + Renderer::MARGIN_W + Renderer::LEFT_PAD);
+    fn mouse_to_pos(c: &fltk::widget::Widget, be: &State) -> (usize, usize) {
+        let (row, col) = Self::mouse_to_column(c, be);
+        let line_len = as_usize(be.doc.borrow().get_visible_line_len_at(row).unwrap_or(0));
+
+        (row, col.min(line_len))
+    }
+
+    /// The row and column under the pointer, the column possibly past the line's end.
+    fn mouse_to_column(c: &fltk::widget::Widget, be: &State) -> (usize, usize) {
+        fltk::draw::set_font(fltk::enums::Font::Courier, be.font_size);
+        let d = be.doc.borrow();
+        // Binary search through the row heights for the row under the pointer.
+        let row = {
+            let heights = be.row_heights(&d);
+            heights.row_at(heights.top(be.scroll_offset) + (fltk::app::event_y() - c.y()) as i64)
+        };
+        let rel_x = fltk::app::event_x() - Renderer::text_x(c, be);
+        let (font, size) = be.row_font(&d, row);
+
+        fltk::draw::set_font(font, size);
         let col = if rel_x < 0 {
             0
         } else {
             (rel_x / fltk::draw::width("a") as i32) as usize
         };
+        let row = row.min(d.get_line_count().saturating_sub(1));
 
-        let d = be.doc.borrow();
-        let max_row = d.get_line_count().saturating_sub(1);
-        let t_row = row.min(max_row);
-        let line_len = as_usize(d.get_visible_line_len_at(t_row).unwrap_or(0));
-        let t_col = col.min(line_len);
-
-        (t_row, t_col)
+        (row, be.char_col(&d, row, col))
     }
 
     // --- Event Handlers ---
 
+    /// Tracks the line number under the pointer while line times are recorded, and
+    /// the link under it to preview.
+    fn on_move(c: &mut fltk::widget::Widget, be: &mut State) -> bool {
+        let in_gutter = !be.focus_mode && fltk::app::event_x() < c.x() + Renderer::MARGIN_W;
+        let (row, col) = Self::mouse_to_pos(c, be);
+        let hover = (in_gutter && be.doc.borrow().line_times.is_some()).then_some(row);
+
+        if hover != be.gutter_hover {
+            be.gutter_hover = hover;
+            c.redraw();
+        }
+
+        let target = (!in_gutter)
+            .then(|| be.doc.borrow().get_line_stripped(row))
+            .flatten()
+            .and_then(|line| editor_state::workspace::link_target_at(&line, col));
+        let same = match (&be.link_hover, &target) {
+            (Some(hover), Some(target)) => hover.row == row && hover.target == *target,
+            (hovered, target) => hovered.is_none() && target.is_none(),
+        };
+
+        if !same {
+            be.link_hover = target.map(|target| LinkHover {
+                lines: Self::link_preview(be, &target),
+                row,
+                target,
+                x: fltk::app::event_x() - c.x(),
+            });
+            c.redraw();
+        }
+        true
+    }
+
+    /// What the preview of a link to `target` shows.
+    fn link_preview(be: &State, target: &editor_state::workspace::LinkTarget) -> Vec<String> {
+        let link = match target {
+            editor_state::workspace::LinkTarget::Url(url) => return vec![url.clone()],
+            editor_state::workspace::LinkTarget::Note(link) => link,
+        };
+
+        if link.note.is_empty() {
+            let d = be.doc.borrow();
+            let Some(heading) = link
+                .heading
+                .as_deref()
+                .and_then(|heading| d.outline.find_anchor(heading))
+            else {
+                return Vec::new();
+            };
+
+            return (heading.row..d.get_line_count())
+                .filter_map(|row| d.get_line_stripped(row))
+                .filter(|line| !line.trim().is_empty())
+                .take(editor_state::search::PREVIEW_LINES)
+                .collect();
+        }
+
+        match &be.note_previewer {
+            Some(preview) => preview(link)
+                .unwrap_or_else(|| vec![format!("There's no note called {}.", link.note)]),
+            None => Vec::new(),
+        }
+    }
+
     fn on_mouse_wheel(
         c: &mut fltk::widget::Widget,
         be: &mut State,
         sb: &mut fltk::valuator::Scrollbar,
-        lh: i32,
     ) -> bool {
         let dy = fltk::app::event_dy_value();
         if dy == 0 {
@@ -430,10 +2575,11 @@ impl Controller {
         if be.scroll_offset != old_off {
             // Only enforce scrolloff (moving the cursor to stay visible) if we are NOT selecting
             if !fltk::app::event_state().contains(fltk::enums::EventState::Button1) {
-                LayoutSync::sync_cursor_to_view(be, c.height(), lh);
+                LayoutSync::sync_cursor_to_view(be, c.height());
             }
 
-            LayoutSync::apply_to_scrollbar(be, sb, c.height(), lh);
+            LayoutSync::apply_to_scrollbar(be, sb, c.height());
+            LayoutSync::prefetch_ahead(be, old_off, c.height());
             c.redraw();
             sb.redraw();
 
@@ -446,10 +2592,9 @@ impl Controller {
         c: &mut fltk::widget::Widget,
         be: &mut State,
         sb: &mut fltk::valuator::Scrollbar,
-        lh: i32,
     ) -> bool {
-        LayoutSync::sync_view_to_cursor(be, c.height(), lh);
-        LayoutSync::apply_to_scrollbar(be, sb, c.height(), lh);
+        LayoutSync::sync_view_to_cursor(be, c.height());
+        LayoutSync::apply_to_scrollbar(be, sb, c.height());
         false
     }
 
@@ -457,10 +2602,24 @@ impl Controller {
         c: &mut fltk::widget::Widget,
         be: &mut State,
         sb: &mut fltk::valuator::Scrollbar,
-        lh: i32,
     ) -> bool {
         c.take_focus().unwrap();
-        let (row, col) = Self::mouse_to_pos(c, be, lh);
+        let (row, col) = Self::mouse_to_pos(c, be);
+
+        if be.column_ruler
+            && fltk::app::event_y() < c.y() + Renderer::RULER_H
+            && let Some(column) = Renderer::ruler_column(c, be, fltk::app::event_x())
+        {
+            be.doc.borrow_mut().settings.toggle_ruler(column);
+            c.redraw();
+            return true;
+        }
+        if let Some(zone) = Renderer::gutter_zone(c, be, fltk::app::event_x())
+            && Self::on_gutter_push(c, be, sb, zone, row)
+        {
+            return true;
+        }
+        be.gutter_anchor = None;
 
         let mut d = be.doc.borrow_mut();
         d.cursor.head.row = row;
@@ -469,53 +2628,170 @@ impl Controller {
         d.cursor.anchor.col = col;
         drop(d);
 
-        Self::refresh_cursor(be, c, sb, lh)
+        let (_, column) = Self::mouse_to_column(c, be);
+        let doc = be.doc.clone();
+        be.set_virtual_col(&doc.borrow(), column);
+
+        Self::refresh_cursor(be, c)
     }
 
-    fn on_drag(
-        c: &mut fltk::widget::Widget,
-        be: &mut State,
-        sb: &mut fltk::valuator::Scrollbar,
-        lh: i32,
-    ) -> bool {
-        let (row, col) = Self::mouse_to_pos(c, be, lh);
+    fn on_drag(c: &mut fltk::widget::Widget, be: &mut State) -> bool {
+        let (row, col) = Self::mouse_to_pos(c, be);
 
         let mut d = be.doc.borrow_mut();
-        d.cursor.head.row = row;
-        d.cursor.head.col = col;
+        if let Some(anchor) = be.gutter_anchor {
+            d.select_lines(anchor, row);
+        } else {
+            d.cursor.head.row = row;
+            d.cursor.head.col = col;
+        }
         drop(d);
 
-        Self::refresh_cursor(be, c, sb, lh)
+        Self::refresh_cursor(be, c)
     }
 
-    fn on_keydown(
+    /// Routes a click on the gutter by the part hit. Returns whether it was handled
+    /// there; if not, it places the cursor like a click on the text.
+    fn on_gutter_push(
         c: &mut fltk::widget::Widget,
         be: &mut State,
         sb: &mut fltk::valuator::Scrollbar,
-        lh: i32,
+        zone: GutterZone,
+        row: usize,
     ) -> bool {
+        match zone {
+            GutterZone::Markers => {
+                let run = {
+                    let d = be.doc.borrow();
+
+                    d.get_line_stripped(row)
+                        .filter(|text| Renderer::runnable_fence(be, &d, row, text))
+                        .and(be.block_runner.as_ref())
+                        .map(|(_, run)| run.clone())
+                };
+
+                if let Some(run) = run {
+                    run(row);
+                } else if be.gutter_clicks.bookmarks && !be.read_only {
+                    be.doc.borrow_mut().bookmarks.toggle(row);
+                    c.redraw();
+                } else {
+                    return false;
+                }
+                true
+            }
+            GutterZone::Numbers if be.gutter_clicks.select_lines => {
+                let shift = fltk::app::event_state().contains(fltk::enums::EventState::Shift);
+                let mut d = be.doc.borrow_mut();
+                let anchor = match be.gutter_anchor {
+                    _ if !shift => row,
+                    Some(anchor) => anchor,
+                    None => d.cursor.anchor.row,
+                };
+
+                d.select_lines(anchor, row);
+                drop(d);
+                be.gutter_anchor = Some(anchor);
+
+                Self::refresh_cursor(be, c)
+            }
+            GutterZone::Folds if be.gutter_clicks.fold_markers => {
+                let mut d = be.doc.borrow_mut();
+                let folds = d.folds.folded_at(row).is_some()
+                    || be.fold_starts(&d).binary_search(&row).is_ok();
+
+                if !(folds && d.toggle_fold(row)) {
+                    return false;
+                }
+                drop(d);
+
+                Self::refresh_view(be, c, sb);
+                true
+            }
+            GutterZone::Numbers | GutterZone::Folds => false,
+        }
+    }
+
+    fn on_keydown(c: &mut fltk::widget::Widget, be: &mut State) -> bool {
         let key = fltk::app::event_key();
         let shift = fltk::app::event_state().contains(fltk::enums::EventState::Shift);
+        let ctrl = fltk::app::event_state().contains(fltk::enums::EventState::Ctrl);
 
         let d = be.doc.borrow_mut();
         let row = d.cursor.head.row;
         let is_last = row + 1 >= d.get_line_count();
+        let at_line_end = d.cursor.no_selection()
+            && d.cursor.head.col >= as_usize(d.get_visible_line_len_at(row).unwrap_or(0));
+        // Only the keys below that keep the cursor in virtual space put it back.
+        let virtual_col = be.virtual_col(&d);
 
         drop(d);
+        be.virtual_col = None;
 
         let handled = match key {
+            k if ctrl && k == fltk::enums::Key::from_char('/') => {
+                be.doc.borrow_mut().toggle_comment();
+                true
+            }
+            k if ctrl && k == fltk::enums::Key::from_char('d') => {
+                be.doc.borrow_mut().duplicate();
+                true
+            }
             fltk::enums::Key::Up if row > 0 => {
-                let mut d = be.doc.borrow_mut();
-                // FIX: Extract length
-                let prev_len = as_usize(d.get_visible_line_len_at(row - 1).unwrap_or(0));
-                d.cursor.move_up(prev_len, shift);
+                let doc = be.doc.clone();
+                let mut d = doc.borrow_mut();
+
+                if let Some(col) = virtual_col {
+                    d.cursor.preferred_column = Some(col);
+                }
+                // Folded rows are stepped over.
+                let mut target = row - 1;
+
+                while target > 0 && d.folds.is_hidden(target) {
+                    target -= 1;
+                }
+                for from in (target + 1..=row).rev() {
+                    // FIX: Extract length
+                    let prev_len = as_usize(d.get_visible_line_len_at(from - 1).unwrap_or(0));
+                    d.cursor.move_up(prev_len, shift);
+                }
+                if !shift && let Some(col) = d.cursor.preferred_column {
+                    be.set_virtual_col(&d, col);
+                }
                 true
             }
             fltk::enums::Key::Down if !is_last => {
-                let mut d = be.doc.borrow_mut();
-                // FIX: Extract length
-                let next_len = as_usize(d.get_visible_line_len_at(row + 1).unwrap_or(0));
-                d.cursor.move_down(next_len, is_last, shift);
+                let doc = be.doc.clone();
+                let mut d = doc.borrow_mut();
+                let line_count = d.get_line_count();
+                // Folded rows are stepped over.
+                let mut target = row + 1;
+
+                while target + 1 < line_count && d.folds.is_hidden(target) {
+                    target += 1;
+                }
+                for from in row..target {
+                    // FIX: Extract length
+                    let next_len = as_usize(d.get_visible_line_len_at(from + 1).unwrap_or(0));
+                    d.cursor.move_down(next_len, from + 1 >= line_count, shift);
+                }
+                if !shift && let Some(col) = d.cursor.preferred_column {
+                    be.set_virtual_col(&d, col);
+                }
+                true
+            }
+            fltk::enums::Key::Left | fltk::enums::Key::BackSpace
+                if !shift && let Some(col) = virtual_col =>
+            {
+                // Back towards the line's end, nothing to delete out here.
+                let doc = be.doc.clone();
+                be.set_virtual_col(&doc.borrow(), col - 1);
+                true
+            }
+            fltk::enums::Key::Right if be.virtual_space && !shift && at_line_end => {
+                let doc = be.doc.clone();
+                let d = doc.borrow();
+                be.set_virtual_col(&d, virtual_col.unwrap_or(d.cursor.head.col) + 1);
                 true
             }
             fltk::enums::Key::Left => {
@@ -536,6 +2812,22 @@ impl Controller {
                 d.cursor.move_right(curr_len, is_last, shift);
                 true
             }
+            fltk::enums::Key::Home | fltk::enums::Key::End => {
+                let mut d = be.doc.borrow_mut();
+                let line = d.get_line_stripped(row).unwrap_or_default();
+                // Soft wrap isn't rendered yet, so every logical line is one visual row.
+                let starts = layout::wrap::wrap_points(&line, None);
+                let (visual_start, visual_end) =
+                    layout::wrap::visual_row_bounds(&line, &starts, d.cursor.head.col);
+
+                if key == fltk::enums::Key::Home {
+                    let indent_end = line.len() - line.trim_start_matches([' ', '\t']).len();
+                    d.cursor.move_home(visual_start, indent_end, shift);
+                } else {
+                    d.cursor.move_end(visual_end, line.len(), shift);
+                }
+                true
+            }
             fltk::enums::Key::BackSpace => {
                 let mut d = be.doc.borrow_mut();
                 d.delete(true);
@@ -550,13 +2842,20 @@ impl Controller {
             }
             fltk::enums::Key::Enter => {
                 let mut d = be.doc.borrow_mut();
-                d.insert("\n");
+                d.press_enter();
 
                 true
             }
             fltk::enums::Key::Tab => {
                 let mut d = be.doc.borrow_mut();
-                d.insert("\t");
+
+                if !d.accept_calculation() && !d.indent_list_item() {
+                    if let Some(col) = virtual_col {
+                        d.pad_line_to(col);
+                    }
+                    let indent = d.indent_unit();
+                    d.insert(&indent);
+                }
 
                 true
             }
@@ -568,37 +2867,83 @@ impl Controller {
             if !text.is_empty() && !text.chars().any(|c| c.is_control()) {
                 let mut d = be.doc.borrow_mut();
 
-                d.insert(&text);
+                if let Some(col) = virtual_col {
+                    d.pad_line_to(col);
+                }
+                d.type_text(&text);
 
                 drop(d);
 
-                return Self::refresh_cursor(be, c, sb, lh);
+                return Self::refresh_cursor(be, c);
             }
             return false;
         }
 
-        Self::refresh_cursor(be, c, sb, lh)
+        Self::refresh_cursor(be, c)
     }
 
     fn on_paste(
         c: &mut fltk::widget::Widget,
         be: &mut State,
         sb: &mut fltk::valuator::Scrollbar,
-        lh: i32,
     ) -> bool {
-        let text = fltk::app::event_text();
+        let raw = fltk::app::event_text();
 
-        if text.is_empty() {
+        if raw.is_empty() {
             return false;
         }
 
+        // Dropped files are attached; so are pasted copies of image files.
+        let dropped = std::mem::take(&mut be.dropping);
+        let paths = editor_state::workspace::dropped_paths(&raw);
+
+        if !paths.is_empty()
+            && paths.iter().all(|path| path.is_file())
+            && (dropped
+                || paths
+                    .iter()
+                    .all(|path| editor_state::workspace::is_image(path)))
+        {
+            if let Err(err) = be.doc.borrow_mut().insert_attachments(&paths) {
+                tracing::error!("Error attaching files: {:?}", err);
+            }
+
+            Self::refresh_view(be, c, sb);
+            return true;
+        }
+
+        let text = editor_state::clipboard::sanitize(&raw, be.paste_conversion);
+        let match_indent = std::mem::take(&mut be.paste_match_indent);
+        let swap = std::mem::take(&mut be.paste_swap);
+        let virtual_col = be.virtual_col(&be.doc.borrow());
+        be.virtual_col = None;
         let mut d = be.doc.borrow_mut();
 
-        d.insert(&text);
+        if swap {
+            if let Some(replaced) = d.swap_selection(&text) {
+                fltk::app::copy(&replaced);
+                be.line_clipboard = None;
+            }
+        } else if d.cursor.no_selection() && be.line_clipboard.as_deref() == Some(raw.as_str()) {
+            d.paste_lines(&raw);
+        } else if be.paste_links
+            && editor_state::clipboard::as_url(&text).is_some_and(|url| d.paste_link(url))
+        {
+            // The selection now links to the pasted URL.
+        } else {
+            if let Some(col) = virtual_col {
+                d.pad_line_to(col);
+            }
+            if match_indent {
+                d.paste_and_match_indent(&text);
+            } else {
+                d.insert(&text);
+            }
+        }
 
         drop(d);
 
-        Self::refresh_view(be, c, sb, lh);
+        Self::refresh_view(be, c, sb);
 
         true
     }
@@ -607,37 +2952,45 @@ impl Controller {
         _c: &mut fltk::widget::Widget,
         be: &mut State,
         _sb: &mut fltk::valuator::Scrollbar,
-        _lh: i32,
     ) -> bool {
         let d = be.doc.borrow();
         let selected = d.get_selected_text();
 
-        drop(d);
+        if selected.is_empty() {
+            let line = d.get_current_line();
 
-        if !selected.is_empty() {
+            fltk::app::copy(&line);
+            be.line_clipboard = Some(line);
+        } else {
             fltk::app::copy(&selected);
-
-            return true;
+            be.line_clipboard = None;
         }
 
-        false
+        true
     }
 
     pub fn on_cut(
-        _c: &mut fltk::widget::Widget,
+        c: &mut fltk::widget::Widget,
         be: &mut State,
-        _sb: &mut fltk::valuator::Scrollbar,
-        _lh: i32,
+        sb: &mut fltk::valuator::Scrollbar,
     ) -> bool {
         // ---- 1. READ selection (immutable borrow) ----
         let mut d = be.doc.borrow_mut();
         let selected = d.get_selected_text();
 
         if selected.is_empty() {
+            let line = d.cut_line();
+
+            fltk::app::copy(&line);
+            be.line_clipboard = Some(line);
+            drop(d);
+
+            Self::refresh_view(be, c, sb);
             return true;
         }
 
         fltk::app::copy(&selected);
+        be.line_clipboard = None;
         d.delete(true);
 
         true
@@ -649,27 +3002,2195 @@ impl Controller {
         be: &mut State,
         c: &mut fltk::widget::Widget,
         sb: &mut fltk::valuator::Scrollbar,
-        lh: i32,
     ) {
         be.cursor_visible = true;
         be.last_interaction = std::time::Instant::now();
-        LayoutSync::sync_cursor_to_view(be, c.height(), lh);
-        LayoutSync::apply_to_scrollbar(be, sb, c.height(), lh);
+        LayoutSync::sync_cursor_to_view(be, c.height());
+        LayoutSync::apply_to_scrollbar(be, sb, c.height());
         c.redraw();
         sb.redraw();
     }
 
-    fn refresh_cursor(
-        be: &mut State,
-        c: &mut fltk::widget::Widget,
-        sb: &mut fltk::valuator::Scrollbar,
-        lh: i32,
-    ) -> bool {
+    fn refresh_cursor(be: &mut State, c: &mut fltk::widget::Widget) -> bool {
         be.cursor_visible = true;
         be.last_interaction = std::time::Instant::now();
-        LayoutSync::sync_view_to_cursor(be, c.height(), lh);
-        LayoutSync::apply_to_scrollbar(be, sb, c.height(), lh);
+
+        // The cursor never rests out of sight, in a collapsed fold.
+        {
+            let mut d = be.doc.borrow_mut();
+            let row = d.cursor.head.row;
+
+            d.folds.reveal(row);
+        }
+
+        if be.perf_hud {
+            let (since, inputs) = be.input_since.get().unwrap_or((be.last_interaction, 0));
+
+            be.input_since.set(Some((since, inputs + 1)));
+        }
+
+        // Typing faster than frames draw only syncs the view for the last keystroke,
+        // see `schedule_sync`.
+        if be.view_sync == ViewSync::Idle {
+            be.view_sync = ViewSync::Requested;
+        }
         c.redraw();
         true
     }
 }
+
+// ==========================================
+// 6. OUTLINE PANEL
+// ==========================================
+/// Sidebar tree of the document's Markdown headings. Clicking one jumps the editor there.
+#[derive(Clone)]
+pub struct OutlinePanel {
+    pub tree: fltk::tree::Tree,
+    editor: TextEditor,
+    /// Tree items and the rows their headings sit on, for click lookups.
+    items: Rc<RefCell<Vec<(fltk::tree::TreeItem, usize)>>>,
+    /// `Outline::revision` the tree was last built from, and whether its labels
+    /// were masked for presentation mode.
+    revision: Rc<std::cell::Cell<Option<(u64, bool)>>>,
+}
+
+impl OutlinePanel {
+    pub fn new(x: i32, y: i32, w: i32, h: i32, editor: TextEditor) -> Self {
+        let mut tree = fltk::tree::Tree::default().with_pos(x, y).with_size(w, h);
+
+        tree.set_show_root(false);
+        tree.set_select_mode(fltk::tree::TreeSelect::Single);
+        tree.set_callback_reason(fltk::tree::TreeReason::Selected);
+
+        let panel = Self {
+            tree,
+            editor,
+            items: Rc::new(RefCell::new(Vec::new())),
+            revision: Rc::new(std::cell::Cell::new(None)),
+        };
+
+        panel.tree.clone().set_callback({
+            let panel = panel.clone();
+            move |t| {
+                if t.callback_reason() != fltk::tree::TreeReason::Selected {
+                    return;
+                }
+
+                let row = t.callback_item().and_then(|clicked| {
+                    panel
+                        .items
+                        .borrow()
+                        .iter()
+                        .find(|(item, _)| *item == clicked)
+                        .map(|&(_, row)| row)
+                });
+
+                if let Some(row) = row {
+                    panel.editor.clone().jump_to_row(row);
+                }
+            }
+        });
+
+        panel.editor.watch({
+            let mut panel = panel.clone();
+            move || panel.refresh()
+        });
+
+        panel
+    }
+
+    /// Rebuilds the tree if it's shown and the outline changed since the last build.
+    pub fn refresh(&mut self) {
+        if !self.tree.visible() {
+            return;
+        }
+
+        let state = self.editor.state.borrow();
+        let d = state.doc.borrow();
+        let outline = &d.outline;
+        let key = (outline.revision, state.redactor.is_some());
+
+        if self.revision.get() == Some(key) {
+            return;
+        }
+        self.revision.set(Some(key));
+
+        let mut items = self.items.borrow_mut();
+        let Some(root) = self.tree.root() else {
+            return;
+        };
+
+        self.tree.clear_children(&root);
+        items.clear();
+
+        // Stack of (level, tree item) for the headings enclosing the current one.
+        let mut parents: Vec<(u8, fltk::tree::TreeItem)> = Vec::new();
+
+        for heading in &outline.headings {
+            while parents
+                .last()
+                .is_some_and(|(level, _)| *level >= heading.level)
+            {
+                parents.pop();
+            }
+
+            let parent = parents.last().map_or(&root, |(_, item)| item).clone();
+            let label = if heading.title.is_empty() {
+                "(untitled)"
+            } else {
+                heading.title.as_str()
+            };
+
+            if let Some(item) =
+                self.tree
+                    .insert(&parent, &state.display_text(label), parent.children())
+            {
+                items.push((item.clone(), heading.row));
+                parents.push((heading.level, item));
+            }
+        }
+
+        self.tree.redraw();
+    }
+}
+
+// ==========================================
+// 7. BREADCRUMB BAR
+// ==========================================
+/// Shows the heading path of the cursor's line, e.g. "Project Ideas ▸ Editor ▸ TODO".
+#[derive(Clone)]
+pub struct BreadcrumbBar {
+    pub frame: fltk::frame::Frame,
+}
+
+impl BreadcrumbBar {
+    pub fn new(x: i32, y: i32, w: i32, h: i32, editor: &TextEditor) -> Self {
+        let mut frame = fltk::frame::Frame::default().with_pos(x, y).with_size(w, h);
+
+        frame.set_frame(fltk::enums::FrameType::FlatBox);
+        frame.set_align(fltk::enums::Align::Left | fltk::enums::Align::Inside);
+        frame.set_label_size(12);
+
+        let state = editor.state.clone();
+        let mut label_frame = frame.clone();
+        // (outline revision, cursor row, masked) the label was computed for.
+        let mut shown: Option<(u64, usize, bool)> = None;
+
+        editor.watch(move || {
+            let state = state.borrow();
+            let d = state.doc.borrow();
+            let key = (
+                d.outline.revision,
+                d.cursor.head.row,
+                state.redactor.is_some(),
+            );
+
+            if shown != Some(key) {
+                shown = Some(key);
+                label_frame.set_label(&format!(
+                    " {}",
+                    state.display_text(&d.outline.breadcrumb(key.1))
+                ));
+                label_frame.redraw();
+            }
+        });
+
+        Self { frame }
+    }
+}
+
+// ==========================================
+// 8. DIALOGS
+// ==========================================
+
+/// Modal "Sort Lines" dialog. Returns `None` if the user cancels.
+pub fn sort_lines_dialog() -> Option<editor_state::transform::SortOptions> {
+    let mut win = fltk::window::Window::default()
+        .with_size(240, 205)
+        .with_label("Sort Lines");
+    let descending = fltk::button::CheckButton::new(20, 15, 200, 22, "Descending");
+    let numeric = fltk::button::CheckButton::new(20, 40, 200, 22, "Numeric");
+    let natural = fltk::button::CheckButton::new(20, 65, 200, 22, "Natural (item2 before item10)");
+    let case_insensitive = fltk::button::CheckButton::new(20, 90, 200, 22, "Ignore case");
+    let unique = fltk::button::CheckButton::new(20, 115, 200, 22, "Remove duplicates");
+    let mut ok = fltk::button::ReturnButton::new(40, 160, 80, 28, "Sort");
+    let mut cancel = fltk::button::Button::new(130, 160, 80, 28, "Cancel");
+    let confirmed = Rc::new(std::cell::Cell::new(false));
+
+    win.end();
+    win.make_modal(true);
+    win.show();
+
+    ok.set_callback({
+        let confirmed = confirmed.clone();
+        let mut win = win.clone();
+        move |_| {
+            confirmed.set(true);
+            win.hide();
+        }
+    });
+    cancel.set_callback({
+        let mut win = win.clone();
+        move |_| win.hide()
+    });
+
+    while win.shown() {
+        fltk::app::wait();
+    }
+
+    confirmed
+        .get()
+        .then(|| editor_state::transform::SortOptions {
+            descending: descending.is_checked(),
+            numeric: numeric.is_checked(),
+            natural: natural.is_checked(),
+            case_insensitive: case_insensitive.is_checked(),
+            unique: unique.is_checked(),
+        })
+}
+
+/// Modal "Export" dialog asking whose export palette to use, preselecting
+/// `current`. Returns `None` if the user cancels.
+pub fn export_dialog(current: editor_state::theme::Theme) -> Option<editor_state::theme::Theme> {
+    let mut win = fltk::window::Window::default()
+        .with_size(260, 110)
+        .with_label("Export");
+    let mut palette = fltk::menu::Choice::new(90, 15, 150, 25, "Palette:");
+    let mut ok = fltk::button::ReturnButton::new(50, 65, 80, 28, "Export");
+    let mut cancel = fltk::button::Button::new(140, 65, 80, 28, "Cancel");
+    let confirmed = Rc::new(std::cell::Cell::new(false));
+
+    for theme in editor_state::theme::Theme::ALL {
+        palette.add_choice(&format!("{} (print)", theme.name()));
+    }
+    palette.set_value(
+        editor_state::theme::Theme::ALL
+            .iter()
+            .position(|&theme| theme == current)
+            .unwrap_or(0) as i32,
+    );
+
+    win.end();
+    win.make_modal(true);
+    win.show();
+
+    ok.set_callback({
+        let confirmed = confirmed.clone();
+        let mut win = win.clone();
+        move |_| {
+            confirmed.set(true);
+            win.hide();
+        }
+    });
+    cancel.set_callback({
+        let mut win = win.clone();
+        move |_| win.hide()
+    });
+
+    while win.shown() {
+        fltk::app::wait();
+    }
+
+    confirmed
+        .get()
+        .then(|| editor_state::theme::Theme::ALL.get(palette.value().max(0) as usize))
+        .flatten()
+        .copied()
+}
+
+/// What each line of the Replace in Notes list is: a note, or one of its matches.
+type ReplaceRows = Vec<(usize, Option<usize>)>;
+
+/// Modal "Replace in Notes" dialog: `search` finds what the pattern typed matches,
+/// shown grouped by note with a checkbox per hit, and per note to flip all of its
+/// hits. Returns the matches still checked when Replace is pressed, by note,
+/// or `None` if the user cancels. The History menu fills in a pinned or recent
+/// pair from `history`, or pins the one typed; the pair replaced with is
+/// recorded there. While typing, the first few matches are shown before and
+/// after, which for a regular expression shows what its template expands to.
+/// With `in_selection` set, there's a selection to search instead, as `search`'s
+/// second argument, ticked to start with if `Some(true)`. The preview is masked by
+/// `redactor` in presentation mode.
+pub fn replace_in_notes_dialog(
+    history: Rc<RefCell<editor_state::replace::ReplaceHistory>>,
+    in_selection: Option<bool>,
+    redactor: Option<Rc<editor_state::redact::Redactor>>,
+    search: impl Fn(
+        &editor_state::replace::Pattern,
+        bool,
+    ) -> Result<Vec<editor_state::replace::FileMatches>, String>
+    + 'static,
+) -> Option<Vec<editor_state::replace::FileMatches>> {
+    const W: i32 = 560;
+    const H: i32 = 540;
+    /// How many matches the live preview shows.
+    const LIVE_MATCHES: usize = 10;
+
+    let mut win = fltk::window::Window::default()
+        .with_size(W, H)
+        .with_label("Replace in Notes");
+    let find = fltk::input::Input::new(80, 10, W - 200, 25, "Find:");
+    let replace = fltk::input::Input::new(80, 40, W - 200, 25, "Replace:");
+    let mut history_menu = fltk::menu::MenuButton::new(W - 110, 10, 100, 25, "History");
+    let mut case_sensitive = fltk::button::CheckButton::new(80, 70, 120, 22, "Match case");
+    let mut regex = fltk::button::CheckButton::new(200, 70, 150, 22, "Regular expression");
+    let mut selection = fltk::button::CheckButton::new(350, 70, 95, 22, "In selection");
+    let mut preview = fltk::button::Button::new(W - 110, 68, 100, 26, "Preview");
+    let mut live = fltk::browser::Browser::new(10, 102, W - 20, 110, None);
+    let mut hits = fltk::browser::CheckBrowser::new(10, 220, W - 20, H - 268, None);
+    let mut summary = fltk::frame::Frame::new(10, H - 40, W - 200, 28, None);
+    let mut ok = fltk::button::ReturnButton::new(W - 180, H - 40, 80, 28, "Replace");
+    let mut cancel = fltk::button::Button::new(W - 90, H - 40, 80, 28, "Cancel");
+    let found: Rc<RefCell<Vec<editor_state::replace::FileMatches>>> = Rc::default();
+    let rows: Rc<RefCell<ReplaceRows>> = Rc::default();
+    // What the matches shown were found with.
+    let pattern: Rc<RefCell<editor_state::replace::Pattern>> = Rc::default();
+    let confirmed = Rc::new(std::cell::Cell::new(false));
+
+    let search = Rc::new({
+        let selection = selection.clone();
+        move |pattern: &editor_state::replace::Pattern| search(pattern, selection.is_checked())
+    });
+    let typed = {
+        let find = find.clone();
+        let replace = replace.clone();
+        let case_sensitive = case_sensitive.clone();
+        let regex = regex.clone();
+        move || editor_state::replace::Pattern {
+            find: find.value(),
+            replace: replace.value(),
+            case_sensitive: case_sensitive.is_checked(),
+            regex: regex.is_checked(),
+        }
+    };
+
+    match in_selection {
+        Some(on) => selection.set_checked(on),
+        None => selection.deactivate(),
+    }
+
+    win.end();
+    win.make_modal(true);
+    hits.set_text_font(fltk::enums::Font::Courier);
+    hits.set_text_size(13);
+    live.set_text_size(13);
+
+    // Searched again a moment after the typing stops, not on every key.
+    let typing = Rc::new(std::cell::Cell::new(0_u32));
+    let refresh = {
+        let search = search.clone();
+        let typed = typed.clone();
+        let live = live.clone();
+        move || {
+            typing.set(typing.get().wrapping_add(1));
+
+            let at = typing.get();
+            let typing = typing.clone();
+            let search = search.clone();
+            let typed = typed.clone();
+            let mut live = live.clone();
+            let redactor = redactor.clone();
+
+            fltk::app::add_timeout3(0.3, move |_| {
+                if typing.get() != at {
+                    return;
+                }
+
+                let pattern = typed();
+
+                live.clear();
+                if pattern.find.is_empty() {
+                    return;
+                }
+                match search(&pattern) {
+                    Ok(notes) => {
+                        let mut left = LIVE_MATCHES;
+
+                        for note in &notes {
+                            let name =
+                                editor_state::workspace::note_name(&note.path).unwrap_or_default();
+                            let rows =
+                                editor_state::replace::preview(&note.lines, &note.matches, left);
+                            let keys = match &redactor {
+                                Some(_) => editor_state::redact::key_blocks(&note.lines),
+                                None => Vec::new(),
+                            };
+
+                            for (row, before, after) in rows {
+                                let in_key =
+                                    keys.iter().any(|key| (key.start..=key.end).contains(&row));
+                                let mask = |line: String| match &redactor {
+                                    Some(_) if in_key => line.chars().map(|_| '•').collect(),
+                                    Some(redactor) => redactor.mask(&line).into_owned(),
+                                    None => line,
+                                };
+                                let (before, after) = (mask(before), mask(after));
+
+                                // `@b` and `@f` set a bold and fixed font, `@.` keeps the text from
+                                // being read as more formatting.
+                                live.add(&format!("@b@.{name}:{}", row + 1));
+                                live.add(&format!("@f@.  - {before}"));
+                                live.add(&format!("@f@.  + {after}"));
+                            }
+                            left = left.saturating_sub(note.matches.len());
+                            if left == 0 {
+                                break;
+                            }
+                        }
+                        if notes.is_empty() {
+                            live.add("No matches");
+                        }
+                    }
+                    Err(err) => live.add(&err),
+                }
+            });
+        }
+    };
+
+    for input in [&mut find.clone(), &mut replace.clone()] {
+        let refresh = refresh.clone();
+
+        input.set_trigger(fltk::enums::CallbackTrigger::Changed);
+        input.set_callback(move |_| refresh());
+    }
+    for button in [&mut case_sensitive, &mut regex, &mut selection] {
+        let refresh = refresh.clone();
+
+        button.set_callback(move |_| refresh());
+    }
+    summary.set_align(fltk::enums::Align::Left | fltk::enums::Align::Inside);
+    ok.deactivate();
+
+    // Rebuilt each time it opens, as pinning changes it.
+    history_menu.handle({
+        let history = history.clone();
+        let find = find.clone();
+        let replace = replace.clone();
+        move |menu, ev| {
+            if ev != fltk::enums::Event::Push {
+                return false;
+            }
+
+            let label = |(from, to): &(String, String)| {
+                let text = format!("{from}  →  {to}");
+                let text: String = text.chars().take(60).collect();
+
+                text.replace('\\', "\\\\")
+                    .replace('/', "\\/")
+                    .replace('&', "&&")
+                    .replace('\t', " ")
+            };
+            let history = history.clone();
+            let current = (find.value(), replace.value());
+            let pinned = history.borrow().pinned.contains(&current);
+
+            menu.clear();
+            menu.add(
+                if pinned {
+                    "Unpin This Pair"
+                } else {
+                    "Pin This Pair"
+                },
+                fltk::enums::Shortcut::None,
+                fltk::menu::MenuFlag::MenuDivider,
+                {
+                    let history = history.clone();
+                    move |_| history.borrow_mut().toggle_pin(current.clone())
+                },
+            );
+            for (group, pairs) in [
+                ("Pinned", history.borrow().pinned.clone()),
+                ("Recent", history.borrow().recent.clone()),
+            ] {
+                for pair in pairs {
+                    let mut find = find.clone();
+                    let mut replace = replace.clone();
+
+                    menu.add(
+                        &format!("{group}/{}", label(&pair)),
+                        fltk::enums::Shortcut::None,
+                        fltk::menu::MenuFlag::Normal,
+                        move |_| {
+                            find.set_value(&pair.0);
+                            replace.set_value(&pair.1);
+                        },
+                    );
+                }
+            }
+            false
+        }
+    });
+
+    preview.set_callback({
+        let found = found.clone();
+        let rows = rows.clone();
+        let mut hits = hits.clone();
+        let mut ok = ok.clone();
+        let pattern = pattern.clone();
+        move |_| {
+            let pattern = {
+                let mut pattern = pattern.borrow_mut();
+
+                *pattern = typed();
+                pattern.clone()
+            };
+            let mut rows = rows.borrow_mut();
+
+            hits.clear();
+            rows.clear();
+            match search(&pattern) {
+                Ok(notes) => {
+                    let count: usize = notes.iter().map(|note| note.matches.len()).sum();
+
+                    for (i, note) in notes.iter().enumerate() {
+                        let name =
+                            editor_state::workspace::note_name(&note.path).unwrap_or_default();
+
+                        hits.add(&format!("{name} ({})", note.matches.len()), true);
+                        rows.push((i, None));
+                        for (j, found) in note.matches.iter().enumerate() {
+                            let line = &note.lines[found.row];
+
+                            hits.add(
+                                &format!(
+                                    "    {:>4}: {}[{}→{}]{}",
+                                    found.row + 1,
+                                    &line[..found.start],
+                                    &line[found.start..found.end],
+                                    found.replacement,
+                                    &line[found.end..]
+                                ),
+                                true,
+                            );
+                            rows.push((i, Some(j)));
+                        }
+                    }
+                    summary.set_label(&format!("{count} matches in {} notes", notes.len()));
+                    if count > 0 {
+                        ok.activate();
+                    } else {
+                        ok.deactivate();
+                    }
+                    *found.borrow_mut() = notes;
+                }
+                Err(err) => {
+                    summary.set_label(&err);
+                    ok.deactivate();
+                    found.borrow_mut().clear();
+                }
+            }
+        }
+    });
+    hits.set_callback({
+        let rows = rows.clone();
+        move |hits| {
+            let rows = rows.borrow();
+            let Some(&(note, None)) = rows.get((hits.value() - 1).max(0) as usize) else {
+                return;
+            };
+            // A note's box sets all of its matches the same.
+            let on = hits.checked(hits.value());
+            let checked: Vec<bool> = rows
+                .iter()
+                .enumerate()
+                .map(|(i, &(of, _))| {
+                    if of == note {
+                        on
+                    } else {
+                        hits.checked(i as i32 + 1)
+                    }
+                })
+                .collect();
+
+            hits.check_none();
+            for (i, _) in checked.iter().enumerate().filter(|(_, on)| **on) {
+                hits.set_checked(i as i32 + 1);
+            }
+        }
+    });
+    ok.set_callback({
+        let confirmed = confirmed.clone();
+        let mut win = win.clone();
+        move |_| {
+            confirmed.set(true);
+            win.hide();
+        }
+    });
+    cancel.set_callback({
+        let mut win = win.clone();
+        move |_| win.hide()
+    });
+
+    win.show();
+    while win.shown() {
+        fltk::app::wait();
+    }
+
+    if !confirmed.get() {
+        return None;
+    }
+
+    let mut notes = found.take();
+    history.borrow_mut().record(&pattern.borrow());
+    let rows = rows.borrow();
+    let kept: std::collections::HashSet<(usize, usize)> = rows
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &(note, found))| {
+            Some((note, found?)).filter(|_| hits.checked(i as i32 + 1))
+        })
+        .collect();
+
+    for (i, note) in notes.iter_mut().enumerate() {
+        let mut j = 0;
+
+        note.matches.retain(|_| {
+            j += 1;
+            kept.contains(&(i, j - 1))
+        });
+    }
+    notes.retain(|note| !note.matches.is_empty());
+    Some(notes)
+}
+
+/// Borderless popover at the mouse pointer listing `rows` of names and values
+/// under `title`, gone on the next click, key press or when it loses focus.
+/// Long values wrap.
+fn popover(title: &str, rows: &[(String, String)]) {
+    const W: i32 = 340;
+    const NAME_W: i32 = 110;
+    const PAD: i32 = 8;
+    const ROW_H: i32 = 20;
+    let value_w = W - NAME_W - 2 * PAD;
+
+    fltk::draw::set_font(fltk::enums::Font::Helvetica, fltk::app::font_size());
+    let heights: Vec<i32> = rows
+        .iter()
+        .map(|(_, value)| fltk::draw::wrap_measure(value, value_w, false).1.max(ROW_H))
+        .collect();
+    let h = ROW_H + heights.iter().sum::<i32>() + 2 * PAD;
+    let mut win = fltk::window::Window::default()
+        .with_size(W, h)
+        .with_pos(fltk::app::event_x_root(), fltk::app::event_y_root());
+    let mut heading = fltk::frame::Frame::new(PAD, PAD, W - 2 * PAD, ROW_H, None);
+    let mut y = PAD + ROW_H;
+
+    heading.set_label(title);
+    heading.set_label_font(fltk::enums::Font::HelveticaBold);
+    heading.set_align(fltk::enums::Align::Left | fltk::enums::Align::Inside);
+    for ((name, value), row_h) in rows.iter().zip(heights) {
+        let mut name_frame = fltk::frame::Frame::new(PAD, y, NAME_W, ROW_H, None);
+        let mut value_frame = fltk::frame::Frame::new(PAD + NAME_W, y, value_w, row_h, None);
+
+        name_frame.set_label(name);
+        name_frame.set_align(fltk::enums::Align::Left | fltk::enums::Align::Inside);
+        value_frame.set_label(value);
+        value_frame.set_align(
+            fltk::enums::Align::Left
+                | fltk::enums::Align::Top
+                | fltk::enums::Align::Inside
+                | fltk::enums::Align::Wrap,
+        );
+        y += row_h;
+    }
+
+    win.end();
+    win.set_border(false);
+    win.make_modal(true);
+    win.handle(|w, ev| match ev {
+        fltk::enums::Event::Push | fltk::enums::Event::KeyDown | fltk::enums::Event::Unfocus => {
+            w.hide();
+            true
+        }
+        _ => false,
+    });
+    win.show();
+
+    while win.shown() {
+        fltk::app::wait();
+    }
+}
+
+/// Popover with the counts and readability of `stats`, see [`popover`].
+pub fn stats_popover(title: &str, stats: &editor_state::stats::TextStats) {
+    let score = |value: Option<f64>| value.map_or_else(|| "–".to_string(), |v| format!("{v:.1}"));
+    let rows = [
+        ("Words".to_string(), stats.words.to_string()),
+        (
+            "Characters".to_string(),
+            format!(
+                "{} ({} without spaces)",
+                stats.characters, stats.characters_no_spaces
+            ),
+        ),
+        ("Sentences".to_string(), stats.sentences.to_string()),
+        ("Paragraphs".to_string(), stats.paragraphs.to_string()),
+        (
+            "Reading time".to_string(),
+            format!("about {} min", stats.reading_minutes()),
+        ),
+        ("Reading ease".to_string(), score(stats.reading_ease())),
+        ("Grade level".to_string(), score(stats.grade_level())),
+    ];
+
+    popover(title, &rows);
+}
+
+/// Popover with what each lookup provider found for `word`, see [`popover`].
+pub fn lookup_popover(word: &str, results: &[editor_state::lookup::LookupResult]) {
+    let mut rows = Vec::new();
+
+    for (provider, answer) in results {
+        match answer {
+            Ok(definitions) => {
+                for (i, definition) in definitions.iter().enumerate() {
+                    let name = if i == 0 {
+                        provider.clone()
+                    } else {
+                        String::new()
+                    };
+
+                    rows.push((name, definition.text.clone()));
+                }
+            }
+            Err(err) => rows.push((provider.clone(), format!("⚠ {err}"))),
+        }
+    }
+    if rows.is_empty() {
+        rows.push((String::new(), "Nothing found.".to_string()));
+    }
+
+    popover(word, &rows);
+}
+
+// ==========================================
+// 9. DIFF VIEW
+// ==========================================
+struct DiffState {
+    left: Vec<String>,
+    right: Vec<String>,
+    rows: Vec<editor_state::diff::DiffRow>,
+    scroll_offset: usize,
+    font_size: i32,
+}
+
+impl DiffState {
+    fn line_height(&self) -> i32 {
+        self.font_size + 4
+    }
+}
+
+/// Read-only side-by-side comparison of two texts. Both panes share one scroll
+/// offset, so they always show the same aligned rows.
+pub struct DiffView {
+    pub window: fltk::window::Window,
+}
+
+impl DiffView {
+    const HEADER_H: i32 = 20;
+    const SCROLLBAR_W: i32 = 15;
+
+    pub fn open(
+        left_title: &str,
+        left: Vec<String>,
+        right_title: &str,
+        right: Vec<String>,
+    ) -> Self {
+        let (w, h) = (900, 560);
+        let pane_w = (w - Self::SCROLLBAR_W) / 2;
+        let body_h = h - Self::HEADER_H;
+
+        let mut window = fltk::window::Window::default()
+            .with_size(w, h)
+            .with_label(&format!("{left_title} ↔ {right_title}"));
+
+        for (i, title) in [left_title, right_title].into_iter().enumerate() {
+            let mut header = fltk::frame::Frame::default()
+                .with_pos(i as i32 * pane_w, 0)
+                .with_size(pane_w, Self::HEADER_H)
+                .with_label(title);
+            header.set_frame(fltk::enums::FrameType::FlatBox);
+            header.set_align(fltk::enums::Align::Left | fltk::enums::Align::Inside);
+        }
+
+        let state = Rc::new(RefCell::new(DiffState {
+            rows: editor_state::diff::side_by_side(&left, &right),
+            left,
+            right,
+            scroll_offset: 0,
+            font_size: State::DEFAULT_FONT_SIZE,
+        }));
+
+        let mut panes = [0, 1].map(|i| {
+            fltk::widget::Widget::default()
+                .with_pos(i * pane_w, Self::HEADER_H)
+                .with_size(pane_w, body_h)
+        });
+        let mut scrollbar = fltk::valuator::Scrollbar::default()
+            .with_pos(w - Self::SCROLLBAR_W, Self::HEADER_H)
+            .with_size(Self::SCROLLBAR_W, body_h);
+
+        scrollbar.set_type(fltk::valuator::ScrollbarType::VerticalNice);
+        scrollbar.set_color(fltk::enums::Color::from_rgb(200, 200, 200));
+        scrollbar.set_selection_color(fltk::enums::Color::from_rgb(100, 100, 100));
+        scrollbar.set_step(0.5, 1);
+
+        window.end();
+        window.make_resizable(true);
+
+        for (side, mut pane) in panes.clone().into_iter().enumerate() {
+            pane.draw({
+                let state = state.clone();
+                move |w| Self::draw_pane(w, &state.borrow(), side == 0)
+            });
+            pane.handle({
+                let state = state.clone();
+                let mut panes = panes.clone();
+                let mut scrollbar = scrollbar.clone();
+                move |c, ev| match ev {
+                    fltk::enums::Event::MouseWheel => {
+                        let dy = fltk::app::event_dy_value();
+                        let mut st = state.borrow_mut();
+
+                        st.scroll_offset =
+                            (st.scroll_offset as isize + (dy * 3) as isize).max(0) as usize;
+                        Self::sync(&mut st, &mut panes, &mut scrollbar, c.height());
+                        dy != 0
+                    }
+                    fltk::enums::Event::Resize => {
+                        Self::sync(
+                            &mut state.borrow_mut(),
+                            &mut panes,
+                            &mut scrollbar,
+                            c.height(),
+                        );
+                        false
+                    }
+                    _ => false,
+                }
+            });
+        }
+
+        scrollbar.set_callback({
+            let state = state.clone();
+            let mut panes = panes.clone();
+            move |s| {
+                let mut st = state.borrow_mut();
+                let canvas_h = panes[0].height();
+
+                st.scroll_offset = s.value() as usize;
+                Self::sync(&mut st, &mut panes, s, canvas_h);
+            }
+        });
+
+        Self::sync(&mut state.borrow_mut(), &mut panes, &mut scrollbar, body_h);
+        window.show();
+
+        Self { window }
+    }
+
+    /// Clamps the shared offset and redraws both panes with it.
+    fn sync(
+        st: &mut DiffState,
+        panes: &mut [fltk::widget::Widget; 2],
+        sb: &mut fltk::valuator::Scrollbar,
+        canvas_h: i32,
+    ) {
+        let visible_rows = (canvas_h / st.line_height()).max(1) as usize;
+        let max_scroll = st.rows.len().saturating_sub(visible_rows);
+
+        st.scroll_offset = st.scroll_offset.min(max_scroll);
+        sb.set_bounds(0.0, max_scroll as f64);
+        sb.set_slider_size((visible_rows as f32 / st.rows.len().max(1) as f32).clamp(0.0, 1.0));
+        sb.set_value(st.scroll_offset as f64);
+        sb.redraw();
+
+        for pane in panes {
+            pane.redraw();
+        }
+    }
+
+    fn draw_pane(w: &mut fltk::widget::Widget, st: &DiffState, is_left: bool) {
+        let line_h = st.line_height();
+        // The editor's theme is applied to FLTK's stock colors, so this follows it too.
+        let background = fltk::enums::Color::Background2.to_rgb();
+        let foreground = fltk::enums::Color::Foreground.to_rgb();
+        let (lines, accent) = if is_left {
+            (&st.left, (200, 70, 80))
+        } else {
+            (&st.right, (80, 170, 90))
+        };
+        let tint = color(editor_state::theme::mix(background, accent, 0.25));
+        let highlight = color(editor_state::theme::mix(background, accent, 0.5));
+
+        fltk::draw::push_clip(w.x(), w.y(), w.width(), w.height());
+        Renderer::draw_bg(w, color(background));
+        fltk::draw::set_font(fltk::enums::Font::Courier, st.font_size);
+
+        let text_x = w.x() + Renderer::MARGIN_W + Renderer::LEFT_PAD;
+        let end = st
+            .rows
+            .len()
+            .min(st.scroll_offset + (w.height() / line_h) as usize + 1);
+
+        for (i, row) in st.rows[st.scroll_offset..end].iter().enumerate() {
+            let y = w.y() + i as i32 * line_h;
+            let (index, spans) = if is_left {
+                (row.left, &row.left_spans)
+            } else {
+                (row.right, &row.right_spans)
+            };
+
+            let Some(index) = index else {
+                // Filler opposite a line that only exists on the other side.
+                fltk::draw::draw_rect_fill(
+                    w.x(),
+                    y,
+                    w.width(),
+                    line_h,
+                    color(editor_state::theme::mix(background, (0, 0, 0), 0.15)),
+                );
+                continue;
+            };
+            let text = &lines[index];
+
+            if !row.is_equal() {
+                fltk::draw::draw_rect_fill(w.x(), y, w.width(), line_h, tint);
+
+                for span in spans {
+                    let x = text_x + fltk::draw::width(&text[..span.start]) as i32;
+                    let span_w = fltk::draw::width(&text[span.clone()]) as i32;
+
+                    fltk::draw::draw_rect_fill(x, y, span_w.max(2), line_h, highlight);
+                }
+            }
+
+            fltk::draw::set_draw_color(color(editor_state::theme::mix(
+                background, foreground, 0.45,
+            )));
+            fltk::draw::draw_text2(
+                &format!("{:3}", index + 1),
+                w.x(),
+                y,
+                Renderer::MARGIN_W - 5,
+                line_h,
+                fltk::enums::Align::Right | fltk::enums::Align::Inside,
+            );
+            fltk::draw::set_draw_color(color(foreground));
+            fltk::draw::draw_text2(
+                text,
+                text_x,
+                y,
+                w.x() + w.width() - text_x,
+                line_h,
+                fltk::enums::Align::Left,
+            );
+        }
+
+        fltk::draw::pop_clip();
+    }
+}
+
+// ==========================================
+// 10. DEBUG CONSOLE
+// ==========================================
+
+/// Window listing recent log events, newest last, down to a chosen level.
+pub struct DebugConsole {
+    pub window: fltk::window::Window,
+}
+
+impl DebugConsole {
+    const LEVELS: [tracing::Level; 4] = [
+        tracing::Level::ERROR,
+        tracing::Level::WARN,
+        tracing::Level::INFO,
+        tracing::Level::DEBUG,
+    ];
+    const TOOLBAR_H: i32 = 35;
+
+    pub fn open(log: util::logging::LogBuffer) -> Self {
+        let (w, h) = (720, 320);
+        let mut window = fltk::window::Window::default()
+            .with_size(w, h)
+            .with_label("Debug Console");
+        let mut level = fltk::menu::Choice::new(60, 5, 110, 25, "Level:");
+        let mut browser =
+            fltk::browser::Browser::new(0, Self::TOOLBAR_H, w, h - Self::TOOLBAR_H, "");
+
+        for shown in Self::LEVELS {
+            level.add_choice(&shown.to_string());
+        }
+        level.set_value(2);
+        browser.set_text_size(12);
+
+        window.end();
+        window.resizable(&browser);
+        window.show();
+
+        let refresh = {
+            let log = log.clone();
+            let level = level.clone();
+            let mut browser = browser.clone();
+            move || {
+                let max_level = Self::LEVELS[level.value().clamp(0, 3) as usize];
+
+                browser.clear();
+                for record in log.records(max_level) {
+                    browser.add(&Self::format(&record));
+                }
+                browser.bottom_line(browser.size());
+            }
+        };
+
+        level.set_callback({
+            let mut refresh = refresh.clone();
+            move |_| refresh()
+        });
+
+        let mut shown_revision = None;
+        let mut refresh = refresh;
+        let watched = window.clone();
+        fltk::app::add_timeout3(0.0, move |handle| {
+            if !watched.shown() {
+                return;
+            }
+            if shown_revision != Some(log.revision()) {
+                shown_revision = Some(log.revision());
+                refresh();
+            }
+            fltk::app::repeat_timeout3(0.5, handle);
+        });
+
+        Self { window }
+    }
+
+    /// `hh:mm:ss LEVEL target: message`, with the time of day in UTC. The `@f@.`
+    /// prefix makes the browser use a fixed-width font and ignore any other `@`.
+    fn format(record: &util::logging::LogRecord) -> String {
+        let secs = record
+            .at
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+
+        format!(
+            "@f@.{:02}:{:02}:{:02} {:>5} {}: {}",
+            secs / 3600 % 24,
+            secs / 60 % 60,
+            secs % 60,
+            record.level,
+            record.target,
+            record.message
+        )
+    }
+}
+
+// ==========================================
+// 11. SYNC PANEL
+// ==========================================
+/// Sidebar list of the notes in the synced folder and where each stands against its
+/// remote copy.
+#[derive(Clone)]
+pub struct SyncPanel {
+    pub browser: fltk::browser::HoldBrowser,
+}
+
+impl SyncPanel {
+    pub fn new(x: i32, y: i32, w: i32, h: i32) -> Self {
+        let mut browser = fltk::browser::HoldBrowser::new(x, y, w, h, "");
+
+        browser.set_text_size(12);
+        browser.add("@i@.Not synced");
+
+        Self { browser }
+    }
+
+    /// Replaces the list with `statuses`, as returned by `editor_state::sync::status`.
+    pub fn show_statuses(&mut self, statuses: &[(String, editor_state::sync::SyncStatus)]) {
+        self.browser.clear();
+
+        for (name, status) in statuses {
+            // Anything but "synced" stands out in bold.
+            let style = match status {
+                editor_state::sync::SyncStatus::Synced => "",
+                _ => "@b",
+            };
+
+            self.browser
+                .add(&format!("{style}@.[{}] {name}", status.label()));
+        }
+        self.browser.redraw();
+    }
+
+    /// Replaces the list with why the last sync failed.
+    pub fn show_error(&mut self, message: &str) {
+        self.browser.clear();
+        self.browser.add("@i@.Sync failed:");
+        self.browser.add(&format!("@.{message}"));
+        self.browser.redraw();
+    }
+}
+
+// ==========================================
+// 12. MERGE VIEW
+// ==========================================
+/// A displayed row: the merge chunk it belongs to and its line in the local, base
+/// and remote panes. `None` is padding where a side has fewer lines.
+type MergeRow = (usize, [Option<String>; 3]);
+
+struct MergeState {
+    merge: editor_state::merge::Merge,
+    rows: Vec<MergeRow>,
+    scroll_offset: usize,
+    font_size: i32,
+}
+
+impl MergeState {
+    fn line_height(&self) -> i32 {
+        self.font_size + 4
+    }
+
+    fn rows(merge: &editor_state::merge::Merge) -> Vec<MergeRow> {
+        let mut rows = Vec::new();
+
+        for (i, chunk) in merge.chunks.iter().enumerate() {
+            match chunk {
+                editor_state::merge::Chunk::Clean(lines) => {
+                    rows.extend(
+                        lines
+                            .iter()
+                            .map(|line| (i, [0, 1, 2].map(|_| Some(line.clone())))),
+                    );
+                }
+                editor_state::merge::Chunk::Conflict {
+                    base,
+                    local,
+                    remote,
+                } => {
+                    let height = local.len().max(base.len()).max(remote.len()).max(1);
+
+                    rows.extend(
+                        (0..height)
+                            .map(|k| (i, [local, base, remote].map(|side| side.get(k).cloned()))),
+                    );
+                }
+            }
+        }
+
+        rows
+    }
+
+    /// Clicking a side picks it; clicking the other one too takes both, clicking a
+    /// picked side drops it, and clicking the base clears the pick.
+    fn toggle(&mut self, chunk: usize, pane: usize) {
+        let clicked = match pane {
+            0 => editor_state::conflict::Resolution::Ours,
+            2 => editor_state::conflict::Resolution::Theirs,
+            _ => {
+                self.merge.set_pick(chunk, None);
+                return;
+            }
+        };
+        let other = match clicked {
+            editor_state::conflict::Resolution::Ours => editor_state::conflict::Resolution::Theirs,
+            _ => editor_state::conflict::Resolution::Ours,
+        };
+        let pick = match self.merge.pick(chunk) {
+            None => Some(clicked),
+            Some(editor_state::conflict::Resolution::Both) => Some(other),
+            Some(current) if current == clicked => None,
+            Some(_) => Some(editor_state::conflict::Resolution::Both),
+        };
+
+        self.merge.set_pick(chunk, pick);
+    }
+}
+
+/// Three-way merge of a synced note that changed on both sides: local, common
+/// ancestor and remote side by side, with each conflicting hunk resolved by clicking
+/// the side to keep.
+pub struct MergeView {
+    pub window: fltk::window::Window,
+}
+
+impl MergeView {
+    const HEADER_H: i32 = 20;
+    const FOOTER_H: i32 = 35;
+    const SCROLLBAR_W: i32 = 15;
+
+    /// Opens the view. `on_apply` gets the merged lines; conflicts left unpicked come
+    /// as conflict markers.
+    pub fn open(
+        title: &str,
+        merge: editor_state::merge::Merge,
+        mut on_apply: impl FnMut(Vec<String>) + 'static,
+    ) -> Self {
+        let (w, h) = (1100, 600);
+        let pane_w = (w - Self::SCROLLBAR_W) / 3;
+        let body_h = h - Self::HEADER_H - Self::FOOTER_H;
+
+        let mut window = fltk::window::Window::default()
+            .with_size(w, h)
+            .with_label(&format!("Merge {title}"));
+
+        for (i, title) in ["Local", "Base", "Remote"].into_iter().enumerate() {
+            let mut header = fltk::frame::Frame::default()
+                .with_pos(i as i32 * pane_w, 0)
+                .with_size(pane_w, Self::HEADER_H)
+                .with_label(title);
+            header.set_frame(fltk::enums::FrameType::FlatBox);
+            header.set_align(fltk::enums::Align::Left | fltk::enums::Align::Inside);
+        }
+
+        let state = Rc::new(RefCell::new(MergeState {
+            rows: MergeState::rows(&merge),
+            merge,
+            scroll_offset: 0,
+            font_size: State::DEFAULT_FONT_SIZE,
+        }));
+
+        let mut panes = [0, 1, 2].map(|i| {
+            fltk::widget::Widget::default()
+                .with_pos(i * pane_w, Self::HEADER_H)
+                .with_size(pane_w, body_h)
+        });
+        let mut scrollbar = fltk::valuator::Scrollbar::default()
+            .with_pos(w - Self::SCROLLBAR_W, Self::HEADER_H)
+            .with_size(Self::SCROLLBAR_W, body_h);
+        let footer_y = h - Self::FOOTER_H + 5;
+        let mut status = fltk::frame::Frame::default()
+            .with_pos(10, footer_y)
+            .with_size(w - 230, 25);
+        let mut apply = fltk::button::Button::default()
+            .with_pos(w - 210, footer_y)
+            .with_size(95, 25)
+            .with_label("Apply");
+        let mut cancel = fltk::button::Button::default()
+            .with_pos(w - 105, footer_y)
+            .with_size(95, 25)
+            .with_label("Cancel");
+
+        scrollbar.set_type(fltk::valuator::ScrollbarType::VerticalNice);
+        scrollbar.set_color(fltk::enums::Color::from_rgb(200, 200, 200));
+        scrollbar.set_selection_color(fltk::enums::Color::from_rgb(100, 100, 100));
+        scrollbar.set_step(0.5, 1);
+        status.set_align(fltk::enums::Align::Left | fltk::enums::Align::Inside);
+
+        window.end();
+        window.make_resizable(true);
+
+        let mut update_status = {
+            let state = state.clone();
+            let mut status = status.clone();
+            move || {
+                status.set_label(&match state.borrow().merge.unresolved() {
+                    0 => "All conflicts resolved".to_string(),
+                    n => format!("{n} conflict(s) left; click the side to keep"),
+                });
+            }
+        };
+        update_status();
+
+        for (pane_index, mut pane) in panes.clone().into_iter().enumerate() {
+            pane.draw({
+                let state = state.clone();
+                move |w| Self::draw_pane(w, &state.borrow(), pane_index)
+            });
+            pane.handle({
+                let state = state.clone();
+                let mut panes = panes.clone();
+                let mut scrollbar = scrollbar.clone();
+                let mut update_status = update_status.clone();
+                move |c, ev| match ev {
+                    fltk::enums::Event::Push => {
+                        let mut st = state.borrow_mut();
+                        let row = st.scroll_offset
+                            + ((fltk::app::event_y() - c.y()) / st.line_height()).max(0) as usize;
+                        let Some(&(chunk, _)) = st.rows.get(row) else {
+                            return false;
+                        };
+
+                        st.toggle(chunk, pane_index);
+                        drop(st);
+                        update_status();
+                        for pane in &mut panes {
+                            pane.redraw();
+                        }
+                        true
+                    }
+                    fltk::enums::Event::MouseWheel => {
+                        let dy = fltk::app::event_dy_value();
+                        let mut st = state.borrow_mut();
+
+                        st.scroll_offset =
+                            (st.scroll_offset as isize + (dy * 3) as isize).max(0) as usize;
+                        Self::sync(&mut st, &mut panes, &mut scrollbar, c.height());
+                        dy != 0
+                    }
+                    fltk::enums::Event::Resize => {
+                        Self::sync(
+                            &mut state.borrow_mut(),
+                            &mut panes,
+                            &mut scrollbar,
+                            c.height(),
+                        );
+                        false
+                    }
+                    _ => false,
+                }
+            });
+        }
+
+        scrollbar.set_callback({
+            let state = state.clone();
+            let mut panes = panes.clone();
+            move |s| {
+                let mut st = state.borrow_mut();
+                let canvas_h = panes[0].height();
+
+                st.scroll_offset = s.value() as usize;
+                Self::sync(&mut st, &mut panes, s, canvas_h);
+            }
+        });
+
+        apply.set_callback({
+            let state = state.clone();
+            let mut window = window.clone();
+            move |_| {
+                let unresolved = state.borrow().merge.unresolved();
+
+                if unresolved > 0
+                    && fltk::dialog::choice2_default(
+                        &format!(
+                            "{unresolved} conflict(s) are unresolved and will be kept as conflict markers."
+                        ),
+                        "Keep Merging",
+                        "Apply Anyway",
+                        "",
+                    ) != Some(1)
+                {
+                    return;
+                }
+
+                on_apply(state.borrow().merge.result());
+                window.hide();
+            }
+        });
+        cancel.set_callback({
+            let mut window = window.clone();
+            move |_| window.hide()
+        });
+
+        Self::sync(&mut state.borrow_mut(), &mut panes, &mut scrollbar, body_h);
+        window.show();
+
+        Self { window }
+    }
+
+    /// Clamps the shared offset and redraws every pane with it.
+    fn sync(
+        st: &mut MergeState,
+        panes: &mut [fltk::widget::Widget; 3],
+        sb: &mut fltk::valuator::Scrollbar,
+        canvas_h: i32,
+    ) {
+        let visible_rows = (canvas_h / st.line_height()).max(1) as usize;
+        let max_scroll = st.rows.len().saturating_sub(visible_rows);
+
+        st.scroll_offset = st.scroll_offset.min(max_scroll);
+        sb.set_bounds(0.0, max_scroll as f64);
+        sb.set_slider_size((visible_rows as f32 / st.rows.len().max(1) as f32).clamp(0.0, 1.0));
+        sb.set_value(st.scroll_offset as f64);
+        sb.redraw();
+
+        for pane in panes {
+            pane.redraw();
+        }
+    }
+
+    /// Whether the pane at `pane` (local, base, remote) ends up in the result under `pick`.
+    fn is_kept(pick: Option<editor_state::conflict::Resolution>, pane: usize) -> bool {
+        matches!(
+            (pick, pane),
+            (Some(editor_state::conflict::Resolution::Both), 0 | 2)
+                | (Some(editor_state::conflict::Resolution::Ours), 0)
+                | (Some(editor_state::conflict::Resolution::Theirs), 2)
+        )
+    }
+
+    fn draw_pane(w: &mut fltk::widget::Widget, st: &MergeState, pane: usize) {
+        let line_h = st.line_height();
+        let background = fltk::enums::Color::Background2.to_rgb();
+        let foreground = fltk::enums::Color::Foreground.to_rgb();
+        let unresolved = color(editor_state::theme::mix(background, (230, 150, 40), 0.3));
+        let kept = color(editor_state::theme::mix(background, (80, 170, 90), 0.3));
+        let dropped = color(editor_state::theme::mix(background, (0, 0, 0), 0.08));
+
+        fltk::draw::push_clip(w.x(), w.y(), w.width(), w.height());
+        Renderer::draw_bg(w, color(background));
+        fltk::draw::set_font(fltk::enums::Font::Courier, st.font_size);
+
+        let text_x = w.x() + Renderer::LEFT_PAD;
+        let end = st
+            .rows
+            .len()
+            .min(st.scroll_offset + (w.height() / line_h) as usize + 1);
+
+        for (i, (chunk, sides)) in st.rows[st.scroll_offset..end].iter().enumerate() {
+            let y = w.y() + i as i32 * line_h;
+            let is_conflict = matches!(
+                st.merge.chunks[*chunk],
+                editor_state::merge::Chunk::Conflict { .. }
+            );
+
+            if is_conflict {
+                let fill = match st.merge.pick(*chunk) {
+                    None => unresolved,
+                    pick if Self::is_kept(pick, pane) => kept,
+                    _ => dropped,
+                };
+
+                fltk::draw::draw_rect_fill(w.x(), y, w.width(), line_h, fill);
+            }
+
+            let Some(text) = &sides[pane] else {
+                continue;
+            };
+
+            fltk::draw::set_draw_color(color(foreground));
+            fltk::draw::draw_text2(
+                text,
+                text_x,
+                y,
+                w.x() + w.width() - text_x,
+                line_h,
+                fltk::enums::Align::Left,
+            );
+        }
+
+        fltk::draw::pop_clip();
+    }
+}
+
+// ==========================================
+// 13. PICKER
+// ==========================================
+/// Modal list filtered as you type, best fuzzy matches first. Up and Down move
+/// through the list, Enter or a double click picks and Escape cancels. Returns the
+/// index into `items` of the one picked.
+pub fn pick(title: &str, items: &[String]) -> Option<usize> {
+    const W: i32 = 460;
+    const H: i32 = 340;
+
+    let mut win = fltk::window::Window::default()
+        .with_size(W, H)
+        .with_label(title);
+    let mut input = fltk::input::Input::new(8, 8, W - 16, 26, "");
+    let mut list = fltk::browser::HoldBrowser::new(8, 40, W - 16, H - 48, "");
+    // What each line of the list is, as indexes into `items`.
+    let shown: Rc<RefCell<Vec<usize>>> = Rc::new(RefCell::new(Vec::new()));
+    let picked: Rc<std::cell::Cell<Option<usize>>> = Rc::new(std::cell::Cell::new(None));
+    let items: Rc<Vec<String>> = Rc::new(items.to_vec());
+
+    win.end();
+    win.make_modal(true);
+    list.set_text_size(13);
+
+    let mut refilter = {
+        let shown = shown.clone();
+        let items = items.clone();
+        let mut list = list.clone();
+        move |query: &str| {
+            let matches = editor_state::fuzzy::filter(query, &items);
+
+            list.clear();
+            for &i in &matches {
+                list.add(&format!("@.{}", items[i]));
+            }
+            if !matches.is_empty() {
+                list.select(1);
+            }
+            *shown.borrow_mut() = matches;
+        }
+    };
+    let choose = {
+        let shown = shown.clone();
+        let picked = picked.clone();
+        let list = list.clone();
+        let mut win = win.clone();
+        move || {
+            let line = list.value();
+
+            if line > 0 {
+                picked.set(shown.borrow().get(line as usize - 1).copied());
+                win.hide();
+            }
+        }
+    };
+
+    refilter("");
+    input.set_trigger(fltk::enums::CallbackTrigger::Changed);
+    input.set_callback({
+        let mut refilter = refilter.clone();
+        move |input| refilter(&input.value())
+    });
+    input.handle({
+        let mut list = list.clone();
+        let mut choose = choose.clone();
+        move |_, ev| {
+            if ev != fltk::enums::Event::KeyDown {
+                return false;
+            }
+
+            let step = match fltk::app::event_key() {
+                fltk::enums::Key::Up => -1,
+                fltk::enums::Key::Down => 1,
+                fltk::enums::Key::Enter | fltk::enums::Key::KPEnter => {
+                    choose();
+                    return true;
+                }
+                _ => return false,
+            };
+            let line = (list.value() + step).clamp(1, list.size().max(1));
+
+            list.select(line);
+            list.make_visible(line);
+            true
+        }
+    });
+    list.set_callback({
+        let mut choose = choose.clone();
+        move |_| {
+            if fltk::app::event_clicks() {
+                choose();
+            }
+        }
+    });
+
+    win.show();
+    let _ = input.take_focus();
+
+    while win.shown() {
+        fltk::app::wait();
+    }
+
+    picked.get()
+}
+
+// ==========================================
+// 14. UPCOMING PANEL
+// ==========================================
+/// Sidebar list of reminders across the workspace, overdue and soonest first.
+#[derive(Clone)]
+pub struct UpcomingPanel {
+    pub browser: fltk::browser::HoldBrowser,
+    /// The reminder behind each line of the list.
+    shown: Rc<RefCell<Vec<editor_state::reminders::Reminder>>>,
+}
+
+impl UpcomingPanel {
+    /// How far ahead the list looks, in days.
+    pub const HORIZON: i64 = 14;
+
+    pub fn new(x: i32, y: i32, w: i32, h: i32) -> Self {
+        let mut browser = fltk::browser::HoldBrowser::new(x, y, w, h, "");
+
+        browser.set_text_size(12);
+        browser.add("@i@.Nothing coming up");
+
+        Self {
+            browser,
+            shown: Rc::new(RefCell::new(Vec::new())),
+        }
+    }
+
+    /// Replaces the list with the `reminders` due by [`Self::HORIZON`] days after `today`.
+    pub fn show_reminders(
+        &mut self,
+        reminders: &[editor_state::reminders::Reminder],
+        today: editor_state::reminders::Date,
+    ) {
+        let until = today.add_days(Self::HORIZON);
+        let mut shown = self.shown.borrow_mut();
+
+        self.browser.clear();
+        shown.clear();
+
+        for reminder in reminders.iter().filter(|reminder| reminder.due <= until) {
+            // Overdue ones stand out in bold, today's in italics.
+            let (style, when) = match reminder.due.days() - today.days() {
+                ..0 => ("@b", "Overdue".to_string()),
+                0 => ("@i", "Today".to_string()),
+                1 => ("", "Tomorrow".to_string()),
+                _ => ("", reminder.due.to_string()),
+            };
+
+            self.browser
+                .add(&format!("{style}@.{when}: {}", reminder.text));
+            shown.push(reminder.clone());
+        }
+        if shown.is_empty() {
+            self.browser.add("@i@.Nothing coming up");
+        }
+        self.browser.redraw();
+    }
+
+    /// The reminder on the selected line, if any.
+    pub fn selected(&self) -> Option<editor_state::reminders::Reminder> {
+        let line = self.browser.value();
+
+        (line > 0)
+            .then(|| self.shown.borrow().get(line as usize - 1).cloned())
+            .flatten()
+    }
+}
+
+// ==========================================
+// 15. SMART FOLDERS
+// ==========================================
+type SearchRunner = Box<dyn FnMut(&str) -> Vec<editor_state::search::Hit>>;
+type HitPicker = Box<dyn FnMut(&editor_state::search::Hit)>;
+
+/// Sidebar tree of saved searches, each a folder of the notes matching it. A folder
+/// runs its search again every time it's opened; clicking a note opens it.
+#[derive(Clone)]
+pub struct SmartFolders {
+    pub tree: fltk::tree::Tree,
+    /// Folder items and the query each stands for.
+    folders: Rc<RefCell<Vec<(fltk::tree::TreeItem, String)>>>,
+    /// Note items and the hit each leads to.
+    hits: Rc<RefCell<Vec<(fltk::tree::TreeItem, editor_state::search::Hit)>>>,
+}
+
+impl SmartFolders {
+    /// `run` evaluates a query against the workspace, `on_pick` opens a hit.
+    pub fn new(
+        x: i32,
+        y: i32,
+        w: i32,
+        h: i32,
+        run: impl FnMut(&str) -> Vec<editor_state::search::Hit> + 'static,
+        on_pick: impl FnMut(&editor_state::search::Hit) + 'static,
+    ) -> Self {
+        let mut tree = fltk::tree::Tree::default().with_pos(x, y).with_size(w, h);
+
+        tree.set_show_root(false);
+        tree.set_select_mode(fltk::tree::TreeSelect::Single);
+
+        let panel = Self {
+            tree,
+            folders: Rc::new(RefCell::new(Vec::new())),
+            hits: Rc::new(RefCell::new(Vec::new())),
+        };
+        let mut run: SearchRunner = Box::new(run);
+        let mut on_pick: HitPicker = Box::new(on_pick);
+
+        panel.tree.clone().set_callback({
+            let panel = panel.clone();
+            move |t| {
+                let Some(item) = t.callback_item() else {
+                    return;
+                };
+
+                match t.callback_reason() {
+                    fltk::tree::TreeReason::Opened => {
+                        let query = panel
+                            .folders
+                            .borrow()
+                            .iter()
+                            .find(|(folder, _)| *folder == item)
+                            .map(|(_, query)| query.clone());
+
+                        if let Some(query) = query {
+                            panel.clone().fill(&item, run(&query));
+                        }
+                    }
+                    fltk::tree::TreeReason::Selected => {
+                        let hit = panel
+                            .hits
+                            .borrow()
+                            .iter()
+                            .find(|(note, _)| *note == item)
+                            .map(|(_, hit)| hit.clone());
+
+                        if let Some(hit) = hit {
+                            on_pick(&hit);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        });
+
+        panel
+    }
+
+    /// Replaces the folders with `searches`, all closed.
+    pub fn set_searches(&mut self, searches: &[editor_state::search::SavedSearch]) {
+        let Some(root) = self.tree.root() else {
+            return;
+        };
+        let mut folders = self.folders.borrow_mut();
+
+        self.tree.clear_children(&root);
+        folders.clear();
+        self.hits.borrow_mut().clear();
+
+        for search in searches {
+            let Some(mut folder) = self.tree.insert(&root, &search.name, root.children()) else {
+                continue;
+            };
+
+            // Something to open until the search has run.
+            self.tree.insert(&folder, "...", 0);
+            folder.close();
+            folders.push((folder, search.query.clone()));
+        }
+
+        self.tree.redraw();
+    }
+
+    /// Lists `hits` under `folder`, replacing what it showed before.
+    fn fill(&mut self, folder: &fltk::tree::TreeItem, hits: Vec<editor_state::search::Hit>) {
+        let mut shown = self.hits.borrow_mut();
+
+        shown.retain(|(note, _)| note.parent().as_ref() != Some(folder));
+        self.tree.clear_children(folder);
+
+        if hits.is_empty() {
+            self.tree.insert(folder, "(no matches)", 0);
+        }
+        for hit in hits {
+            let label = editor_state::workspace::note_name(&hit.path)
+                .unwrap_or("(untitled)")
+                .to_string();
+
+            if let Some(note) = self.tree.insert(folder, &label, folder.children()) {
+                shown.push((note, hit));
+            }
+        }
+
+        self.tree.redraw();
+    }
+}
+
+// ==========================================
+// 16. CSV GRID
+// ==========================================
+struct GridState {
+    table: editor_state::csv::Table,
+    header: Vec<String>,
+    /// Table row shown on each grid row.
+    order: Vec<usize>,
+    /// Column sorted by, and whether descending.
+    sorted: Option<(usize, bool)>,
+    /// The row drawn last, so its cells don't each parse it again.
+    cached: Option<(usize, Vec<String>)>,
+}
+
+impl GridState {
+    fn field(&mut self, row: usize, col: usize) -> String {
+        let Some(&row) = self.order.get(row) else {
+            return String::new();
+        };
+
+        if self
+            .cached
+            .as_ref()
+            .is_none_or(|(cached, _)| *cached != row)
+        {
+            self.cached = Some((row, self.table.row(row)));
+        }
+        self.cached
+            .as_ref()
+            .and_then(|(_, fields)| fields.get(col).cloned())
+            .unwrap_or_default()
+    }
+}
+
+/// Read-only grid of a CSV or TSV file. Clicking a column header sorts by it,
+/// clicking it again reverses the order.
+pub struct CsvGrid {
+    pub window: fltk::window::Window,
+}
+
+impl CsvGrid {
+    const COL_W: i32 = 140;
+    const ROW_H: i32 = 22;
+
+    pub fn open(table: editor_state::csv::Table) -> Self {
+        let (w, h) = (900, 560);
+        let title = table
+            .path()
+            .file_name()
+            .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+        let subtitle = format!("{title} ({} rows)", table.rows());
+
+        let mut window = fltk::window::Window::default()
+            .with_size(w, h)
+            .with_label(&subtitle);
+        let mut grid = fltk::table::Table::default().with_size(w, h);
+
+        let state = Rc::new(RefCell::new(GridState {
+            header: table.header(),
+            order: (0..table.rows()).collect(),
+            sorted: None,
+            cached: None,
+            table,
+        }));
+
+        {
+            let st = state.borrow();
+            let rows = st.order.len().min(i32::MAX as usize) as i32;
+
+            grid.set_rows(rows);
+            grid.set_cols(st.table.columns() as i32);
+        }
+        grid.set_row_header(true);
+        grid.set_row_header_width(64);
+        grid.set_col_header(true);
+        grid.set_col_header_height(Self::ROW_H + 2);
+        grid.set_col_resize(true);
+        grid.set_col_width_all(Self::COL_W);
+        grid.set_row_height_all(Self::ROW_H);
+        grid.end();
+
+        window.end();
+        window.make_resizable(true);
+
+        grid.draw_cell({
+            let state = state.clone();
+            move |t, ctx, row, col, x, y, w, h| match ctx {
+                fltk::table::TableContext::StartPage => {
+                    fltk::draw::set_font(fltk::enums::Font::Helvetica, 13);
+                }
+                fltk::table::TableContext::ColHeader => {
+                    let st = state.borrow();
+                    let mut label = st.header.get(col as usize).cloned().unwrap_or_default();
+
+                    match st.sorted {
+                        Some((sorted, false)) if sorted == col as usize => label.push_str("  ▲"),
+                        Some((sorted, true)) if sorted == col as usize => label.push_str("  ▼"),
+                        _ => {}
+                    }
+                    Self::draw_header(&label, x, y, w, h, t.col_header_color());
+                }
+                fltk::table::TableContext::RowHeader => {
+                    let shown = state.borrow().order.get(row as usize).map_or(0, |&r| r + 1);
+
+                    Self::draw_header(&shown.to_string(), x, y, w, h, t.row_header_color());
+                }
+                fltk::table::TableContext::Cell => {
+                    let text = state.borrow_mut().field(row as usize, col as usize);
+                    // Multi-line fields show their first line.
+                    let text = text.lines().next().unwrap_or_default();
+
+                    fltk::draw::push_clip(x, y, w, h);
+                    fltk::draw::set_draw_color(fltk::enums::Color::Background2);
+                    fltk::draw::draw_rectf(x, y, w, h);
+                    fltk::draw::set_draw_color(fltk::enums::Color::Foreground);
+                    fltk::draw::draw_text2(text, x + 4, y, w - 8, h, fltk::enums::Align::Left);
+                    fltk::draw::set_draw_color(fltk::enums::Color::Light1);
+                    fltk::draw::draw_rect(x, y, w, h);
+                    fltk::draw::pop_clip();
+                }
+                _ => {}
+            }
+        });
+
+        grid.set_callback({
+            let state = state.clone();
+            move |t| {
+                if t.callback_context() != fltk::table::TableContext::ColHeader
+                    || fltk::app::event() != fltk::enums::Event::Released
+                {
+                    return;
+                }
+
+                let col = t.callback_col() as usize;
+                let mut st = state.borrow_mut();
+                let descending = st.sorted == Some((col, false));
+
+                st.order = st.table.sorted_by(col, descending);
+                st.sorted = Some((col, descending));
+                drop(st);
+                t.redraw();
+            }
+        });
+
+        window.show();
+
+        Self { window }
+    }
+
+    fn draw_header(label: &str, x: i32, y: i32, w: i32, h: i32, color: fltk::enums::Color) {
+        fltk::draw::push_clip(x, y, w, h);
+        fltk::draw::draw_box(fltk::enums::FrameType::ThinUpBox, x, y, w, h, color);
+        fltk::draw::set_draw_color(fltk::enums::Color::Foreground);
+        fltk::draw::draw_text2(label, x + 4, y, w - 8, h, fltk::enums::Align::Left);
+        fltk::draw::pop_clip();
+    }
+}
+
+// ==========================================
+// 17. EDITOR WINDOW
+// ==========================================
+
+/// Another top-level window onto a document already open in the main window: a
+/// second editor, or a read-only preview with Markdown rendered. Every view shares
+/// the one document, cursor included, and follows edits made through the others.
+pub struct EditorWindow {
+    pub window: fltk::window::Window,
+    pub editor: TextEditor,
+}
+
+impl EditorWindow {
+    pub fn open(
+        title: &str,
+        doc: Rc<RefCell<editor_state::document::Document>>,
+        preview: bool,
+    ) -> Self {
+        let (w, h) = (600, 500);
+        let mut window = fltk::window::Window::default()
+            .with_size(w, h)
+            .with_label(title);
+        let editor = TextEditor::new(0, 0, w, h, doc);
+
+        {
+            let mut state = editor.state.borrow_mut();
+            state.read_only = preview;
+            state.rich_text = true;
+        }
+
+        window.end();
+        window.resizable(&editor.group);
+        // Deleting the window stops its editor following the document.
+        window.set_callback(|w| fltk::window::Window::delete(w.clone()));
+        window.show();
+
+        Self { window, editor }
+    }
+}
+
+// ==========================================
+// 18. LOCK SCREEN
+// ==========================================
+
+/// Covers a window with a passphrase prompt, hiding every other window and
+/// shutting off the widgets underneath, menu shortcuts included, until the
+/// passphrase is given.
+#[derive(Clone)]
+pub struct LockScreen {
+    overlay: fltk::group::Group,
+    passphrase: fltk::input::SecretInput,
+    message: fltk::frame::Frame,
+    /// What [`LockScreen::lock`] turned off or hid, to bring back.
+    covered: Rc<RefCell<(Vec<fltk::widget::Widget>, Vec<fltk::widget::Widget>)>>,
+}
+
+impl LockScreen {
+    /// Width of the prompt, and where each of its rows sits from the middle of
+    /// the window down.
+    const W: i32 = 260;
+    const ROWS: [(i32, i32); 4] = [(-70, 30), (-30, 28), (8, 28), (44, 24)];
+
+    /// Adds the (hidden) lock screen to `win`, unlocked when `verify` accepts the
+    /// passphrase typed.
+    pub fn new(win: &mut fltk::window::Window, verify: impl Fn(&str) -> bool + 'static) -> Self {
+        win.begin();
+        let mut overlay = fltk::group::Group::new(0, 0, win.w(), win.h(), None);
+        let mut title = fltk::frame::Frame::default().with_label("Locked");
+        let mut passphrase = fltk::input::SecretInput::default();
+        let mut unlock = fltk::button::ReturnButton::default().with_label("Unlock");
+        let mut message = fltk::frame::Frame::default();
+        overlay.end();
+        win.end();
+
+        overlay.set_frame(fltk::enums::FrameType::FlatBox);
+        overlay.set_color(fltk::enums::Color::from_rgb(0x20, 0x20, 0x24));
+        title.set_label_size(20);
+        title.set_label_color(fltk::enums::Color::White);
+        message.set_label_color(fltk::enums::Color::from_rgb(0xe0, 0x70, 0x70));
+        overlay.hide();
+
+        let screen = Self {
+            overlay,
+            passphrase: passphrase.clone(),
+            message,
+            covered: Rc::new(RefCell::new((Vec::new(), Vec::new()))),
+        };
+
+        unlock.set_callback({
+            let mut screen = screen.clone();
+            move |_| {
+                if verify(&screen.passphrase.value()) {
+                    screen.unlock();
+                } else {
+                    screen.passphrase.set_value("");
+                    screen.message.set_label("Wrong passphrase");
+                    let _ = screen.passphrase.take_focus();
+                }
+            }
+        });
+        passphrase.set_trigger(fltk::enums::CallbackTrigger::EnterKeyAlways);
+        passphrase.set_callback(move |_| unlock.do_callback());
+
+        screen
+    }
+
+    pub fn is_locked(&self) -> bool {
+        self.overlay.visible()
+    }
+
+    /// Blanks every window until the passphrase is given.
+    pub fn lock(&mut self) {
+        if self.is_locked() {
+            return;
+        }
+
+        let Some(mut win) = self.overlay.window() else {
+            return;
+        };
+        let mut covered = self.covered.borrow_mut();
+
+        for i in 0..win.children() {
+            if let Some(mut child) = win.child(i)
+                && !child.is_same(&self.overlay)
+                && child.active()
+            {
+                child.deactivate();
+                covered.0.push(child);
+            }
+        }
+        for mut other in fltk::app::windows().unwrap_or_default() {
+            if other.shown() && other.raw_handle() != win.raw_handle() {
+                other.hide();
+                covered.1.push(other.as_base_widget());
+            }
+        }
+
+        self.overlay.resize(0, 0, win.w(), win.h());
+        for (i, (dy, h)) in Self::ROWS.into_iter().enumerate() {
+            if let Some(mut row) = self.overlay.child(i as i32) {
+                row.resize(win.w() / 2 - Self::W / 2, win.h() / 2 + dy, Self::W, h);
+            }
+        }
+        self.overlay.show();
+        self.passphrase.set_value("");
+        self.message.set_label("");
+        let _ = self.passphrase.take_focus();
+        win.redraw();
+    }
+
+    fn unlock(&mut self) {
+        let (widgets, windows) = std::mem::take(&mut *self.covered.borrow_mut());
+
+        for mut widget in widgets {
+            widget.activate();
+        }
+        for mut window in windows {
+            window.show();
+        }
+        self.passphrase.set_value("");
+        self.overlay.hide();
+        if let Some(mut win) = self.overlay.window() {
+            win.redraw();
+        }
+    }
+}
+
+// ==========================================
+// 19. DIAGNOSTICS PANEL
+// ==========================================
+/// One line of the [`DiagnosticsPanel`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Diagnostic {
+    /// A note no other note links to.
+    Orphan(std::path::PathBuf),
+    Broken(editor_state::diagnostics::BrokenLink),
+}
+
+/// Sidebar list of link problems across the workspace: links to missing notes
+/// first, then notes nothing links to.
+#[derive(Clone)]
+pub struct DiagnosticsPanel {
+    pub browser: fltk::browser::HoldBrowser,
+    /// The problem behind each line of the list, `None` for section headers.
+    shown: Rc<RefCell<Vec<Option<Diagnostic>>>>,
+}
+
+impl DiagnosticsPanel {
+    pub fn new(x: i32, y: i32, w: i32, h: i32) -> Self {
+        let mut browser = fltk::browser::HoldBrowser::new(x, y, w, h, "");
+
+        browser.set_text_size(12);
+        browser.add("@i@.No link problems");
+
+        Self {
+            browser,
+            shown: Rc::new(RefCell::new(Vec::new())),
+        }
+    }
+
+    /// Replaces the list with the problems in `report`, notes named relative to `root`.
+    pub fn show_report(
+        &mut self,
+        report: &editor_state::diagnostics::Report,
+        root: &std::path::Path,
+    ) {
+        let mut shown = self.shown.borrow_mut();
+        let name = |path: &std::path::Path| {
+            path.strip_prefix(root)
+                .unwrap_or(path)
+                .with_extension("")
+                .display()
+                .to_string()
+        };
+
+        self.browser.clear();
+        shown.clear();
+
+        if !report.broken.is_empty() {
+            self.browser
+                .add(&format!("@b@.Broken links ({})", report.broken.len()));
+            shown.push(None);
+        }
+        for link in &report.broken {
+            self.browser.add(&format!(
+                "@.[[{}]] in {}:{}",
+                link.target,
+                name(&link.path),
+                link.row + 1
+            ));
+            shown.push(Some(Diagnostic::Broken(link.clone())));
+        }
+        if !report.orphans.is_empty() {
+            self.browser
+                .add(&format!("@b@.Orphans ({})", report.orphans.len()));
+            shown.push(None);
+        }
+        for path in &report.orphans {
+            self.browser.add(&format!("@.{}", name(path)));
+            shown.push(Some(Diagnostic::Orphan(path.clone())));
+        }
+        if shown.is_empty() {
+            self.browser.add("@i@.No link problems");
+        }
+        self.browser.redraw();
+    }
+
+    /// The problem on the selected line, if any.
+    pub fn selected(&self) -> Option<Diagnostic> {
+        let line = self.browser.value();
+
+        (line > 0)
+            .then(|| {
+                self.shown
+                    .borrow()
+                    .get(line as usize - 1)
+                    .cloned()
+                    .flatten()
+            })
+            .flatten()
+    }
+}
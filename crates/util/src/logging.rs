@@ -0,0 +1,185 @@
+use tracing_subscriber::Layer;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+/// One event as the debug console shows it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogRecord {
+    pub at: std::time::SystemTime,
+    pub level: tracing::Level,
+    /// Module the event came from, e.g. `editor_state::document`.
+    pub target: String,
+    /// The message followed by any other fields as ` key=value`.
+    pub message: String,
+}
+
+/// The most recent events, shared between the subscriber and the debug console.
+/// Cloning shares the same buffer.
+#[derive(Debug, Clone, Default)]
+pub struct LogBuffer {
+    records: std::sync::Arc<std::sync::Mutex<std::collections::VecDeque<LogRecord>>>,
+    /// Bumped on every push so a viewer can cheaply tell when to refresh.
+    revision: std::sync::Arc<std::sync::atomic::AtomicU64>,
+}
+
+impl LogBuffer {
+    /// Older records are dropped past this many.
+    pub const CAPACITY: usize = 2000;
+
+    pub fn push(&self, record: LogRecord) {
+        // A panic while logging mustn't take the log down with it.
+        let mut records = self
+            .records
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+
+        if records.len() == Self::CAPACITY {
+            records.pop_front();
+        }
+        records.push_back(record);
+        self.revision
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// Records at `max_level` or more severe, oldest first.
+    pub fn records(&self, max_level: tracing::Level) -> Vec<LogRecord> {
+        self.records
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+            .iter()
+            .filter(|record| record.level <= max_level)
+            .cloned()
+            .collect()
+    }
+
+    #[inline]
+    pub fn revision(&self) -> u64 {
+        self.revision.load(std::sync::atomic::Ordering::Relaxed)
+    }
+}
+
+/// Collects an event's `message` and its other fields into one line.
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl tracing::field::Visit for MessageVisitor {
+    fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            self.fields.push_str(&format!(" {}={value}", field.name()));
+        }
+    }
+
+    fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            self.message.push_str(&format!("{value:?}"));
+        } else {
+            self.fields
+                .push_str(&format!(" {}={value:?}", field.name()));
+        }
+    }
+}
+
+/// Layer feeding every event it sees into a [`LogBuffer`].
+pub struct BufferLayer {
+    buffer: LogBuffer,
+}
+
+impl BufferLayer {
+    pub fn new(buffer: LogBuffer) -> Self {
+        Self { buffer }
+    }
+}
+
+impl<S: tracing::Subscriber> Layer<S> for BufferLayer {
+    fn on_event(
+        &self,
+        event: &tracing::Event<'_>,
+        _ctx: tracing_subscriber::layer::Context<'_, S>,
+    ) {
+        let mut visitor = MessageVisitor::default();
+
+        event.record(&mut visitor);
+        self.buffer.push(LogRecord {
+            at: std::time::SystemTime::now(),
+            level: *event.metadata().level(),
+            target: event.metadata().target().to_string(),
+            message: visitor.message + &visitor.fields,
+        });
+    }
+}
+
+/// Installs the global subscriber and returns the buffer the debug console reads.
+///
+/// Events go to stderr at the level named by `MYNOTES_LOG` (`info` if unset or
+/// invalid), and at `debug` and above into the buffer. Calling this twice keeps
+/// the first subscriber; the second buffer then stays empty.
+pub fn init() -> LogBuffer {
+    let buffer = LogBuffer::default();
+    let stderr_level = std::env::var("MYNOTES_LOG")
+        .ok()
+        .and_then(|level| level.parse().ok())
+        .unwrap_or(tracing_subscriber::filter::LevelFilter::INFO);
+
+    let _ = tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::fmt::layer()
+                .with_writer(std::io::stderr)
+                .with_filter(stderr_level),
+        )
+        .with(
+            BufferLayer::new(buffer.clone())
+                .with_filter(tracing_subscriber::filter::LevelFilter::DEBUG),
+        )
+        .try_init();
+
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buffer_layer_records_events() {
+        let buffer = LogBuffer::default();
+        let subscriber = tracing_subscriber::registry().with(BufferLayer::new(buffer.clone()));
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!(path = "a.md", "Saved");
+            tracing::error!("Couldn't save: {}", "disk full");
+        });
+
+        let all = buffer.records(tracing::Level::TRACE);
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].message, "Saved path=a.md");
+        assert_eq!(all[0].target, module_path!());
+        assert_eq!(buffer.revision(), 2);
+
+        let errors = buffer.records(tracing::Level::ERROR);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message, "Couldn't save: disk full");
+    }
+
+    #[test]
+    fn test_buffer_drops_oldest() {
+        let buffer = LogBuffer::default();
+
+        for i in 0..LogBuffer::CAPACITY + 5 {
+            buffer.push(LogRecord {
+                at: std::time::SystemTime::UNIX_EPOCH,
+                level: tracing::Level::INFO,
+                target: String::new(),
+                message: i.to_string(),
+            });
+        }
+
+        let records = buffer.records(tracing::Level::INFO);
+        assert_eq!(records.len(), LogBuffer::CAPACITY);
+        assert_eq!(records[0].message, "5");
+    }
+}
@@ -0,0 +1,478 @@
+/// Keyring service the WebDAV passwords are stored under, one entry per server and user.
+const KEYRING_SERVICE: &str = "MyNotes WebDAV";
+
+/// The server and user to sync with. The password lives in the OS keyring, never in
+/// this file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WebDavAccount {
+    /// The folder holding the notes, e.g.
+    /// `https://cloud.example.com/remote.php/dav/files/me/Notes/`.
+    pub url: String,
+    pub user: String,
+    /// The user agreed to a plain `http://` URL, which sends the password and
+    /// notes unencrypted.
+    pub allow_http: bool,
+}
+
+impl WebDavAccount {
+    /// `<config>/mynotes/webdav`, next to the session file.
+    pub fn default_path() -> Option<std::path::PathBuf> {
+        Some(editor_state::session::config_dir()?.join("webdav"))
+    }
+
+    /// The account saved at `path`, if any.
+    pub fn load(path: &std::path::Path) -> Option<Self> {
+        let content = std::fs::read_to_string(path).ok()?;
+        let mut fields = content.lines().next()?.split('\t');
+
+        Some(Self {
+            url: fields.next()?.to_string(),
+            user: fields.next()?.to_string(),
+            allow_http: fields.next() == Some("http"),
+        })
+    }
+
+    /// Whether the URL is plain `http://`, see [`WebDavAccount::allow_http`].
+    pub fn is_http(&self) -> bool {
+        self.url
+            .trim()
+            .get(..7)
+            .is_some_and(|scheme| scheme.eq_ignore_ascii_case("http://"))
+    }
+
+    /// # Errors
+    ///
+    /// Returns an error if the file can't be written.
+    pub fn save(&self, path: &std::path::Path) -> std::io::Result<()> {
+        let mut batch = editor_state::workspace::BatchSave::default();
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let allow_http = if self.allow_http { "\thttp" } else { "" };
+
+        batch.write(path, format!("{}\t{}{allow_http}\n", self.url, self.user));
+        batch.commit().map(|_| ())
+    }
+
+    fn keyring_entry(&self) -> std::io::Result<keyring::Entry> {
+        keyring::Entry::new(&format!("{KEYRING_SERVICE} {}", self.url), &self.user)
+            .map_err(std::io::Error::other)
+    }
+
+    /// Stores `password` in the OS keyring.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the keyring refuses it.
+    pub fn store_password(&self, password: &str) -> std::io::Result<()> {
+        self.keyring_entry()?
+            .set_password(password)
+            .map_err(std::io::Error::other)
+    }
+
+    /// The password stored for this account.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ErrorKind::NotFound` if none is stored, or the keyring's own error.
+    pub fn password(&self) -> std::io::Result<String> {
+        match self.keyring_entry()?.get_password() {
+            Ok(password) => Ok(password),
+            Err(keyring::Error::NoEntry) => Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("No password stored for {} at {}", self.user, self.url),
+            )),
+            Err(err) => Err(std::io::Error::other(err)),
+        }
+    }
+}
+
+/// Percent-encodes one path segment.
+fn encode_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+fn decode_path(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// The path part of `url` (or of an `href`, which may be either), decoded.
+fn url_path(url: &str) -> String {
+    let path = match url.split_once("://") {
+        Some((_, rest)) => rest.find('/').map_or("/", |slash| &rest[slash..]),
+        None => url,
+    };
+
+    decode_path(path)
+}
+
+/// One `<response>` of a PROPFIND answer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct DavEntry {
+    /// Decoded path on the server.
+    path: String,
+    modified: Option<std::time::SystemTime>,
+    len: u64,
+    is_dir: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum DavField {
+    Href,
+    Modified,
+    Len,
+}
+
+/// Reads the entries out of a `207 Multi-Status` body. Namespace prefixes vary
+/// between servers (`d:`, `D:`, none), so elements are matched by local name.
+fn parse_multistatus(xml: &str) -> std::io::Result<Vec<DavEntry>> {
+    let invalid = |err: quick_xml::Error| std::io::Error::new(std::io::ErrorKind::InvalidData, err);
+    let mut reader = quick_xml::Reader::from_str(xml);
+    let mut entries = Vec::new();
+    let mut current: Option<DavEntry> = None;
+    let mut field: Option<DavField> = None;
+
+    loop {
+        match reader.read_event().map_err(invalid)? {
+            quick_xml::events::Event::Start(tag) => match tag.local_name().as_ref() {
+                b"response" => current = Some(DavEntry::default()),
+                b"href" => field = Some(DavField::Href),
+                b"getlastmodified" => field = Some(DavField::Modified),
+                b"getcontentlength" => field = Some(DavField::Len),
+                b"collection" => current.iter_mut().for_each(|entry| entry.is_dir = true),
+                _ => {}
+            },
+            quick_xml::events::Event::Empty(tag) if tag.local_name().as_ref() == b"collection" => {
+                current.iter_mut().for_each(|entry| entry.is_dir = true);
+            }
+            quick_xml::events::Event::Text(text) => {
+                let (Some(entry), Some(field)) = (current.as_mut(), field) else {
+                    continue;
+                };
+                let text = text.unescape().map_err(invalid)?;
+                let text = text.trim();
+
+                match field {
+                    DavField::Href => entry.path = url_path(text),
+                    DavField::Modified => entry.modified = httpdate::parse_http_date(text).ok(),
+                    DavField::Len => entry.len = text.parse().unwrap_or(0),
+                }
+            }
+            quick_xml::events::Event::End(tag) => {
+                field = None;
+
+                if tag.local_name().as_ref() == b"response"
+                    && let Some(entry) = current.take()
+                {
+                    entries.push(entry);
+                }
+            }
+            quick_xml::events::Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(entries)
+}
+
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:">
+  <d:prop><d:getlastmodified/><d:getcontentlength/><d:resourcetype/></d:prop>
+</d:propfind>"#;
+
+/// Turns a failed request into an `io::Error` that [`editor_state::save_queue::is_network_failure`]
+/// recognizes when the server couldn't be reached at all.
+fn request_error(err: ureq::Error) -> std::io::Error {
+    match err {
+        ureq::Error::Status(code, response) => {
+            let kind = match code {
+                401 | 403 => std::io::ErrorKind::PermissionDenied,
+                404 => std::io::ErrorKind::NotFound,
+                _ => std::io::ErrorKind::Other,
+            };
+
+            std::io::Error::new(
+                kind,
+                format!("Server answered {code} {}", response.status_text()),
+            )
+        }
+        ureq::Error::Transport(transport) => {
+            std::io::Error::new(std::io::ErrorKind::NotConnected, transport.to_string())
+        }
+    }
+}
+
+/// Syncs with a folder on a WebDAV server, such as Nextcloud or ownCloud.
+pub struct WebDavProvider {
+    /// The folder's URL, ending in `/`.
+    base: String,
+    user: String,
+    password: String,
+    agent: ureq::Agent,
+}
+
+impl std::fmt::Debug for WebDavProvider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WebDavProvider")
+            .field("base", &self.base)
+            .field("user", &self.user)
+            .finish_non_exhaustive()
+    }
+}
+
+impl WebDavProvider {
+    const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+    /// # Errors
+    ///
+    /// Returns `ErrorKind::InvalidInput` for a plain `http://` URL the user didn't
+    /// agree to.
+    pub fn new(account: &WebDavAccount, password: impl Into<String>) -> std::io::Result<Self> {
+        let mut base = account.url.trim().to_string();
+
+        if account.is_http() && !account.allow_http {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("{base} isn't HTTPS; the password would be sent unencrypted"),
+            ));
+        }
+        if !base.ends_with('/') {
+            base.push('/');
+        }
+
+        Ok(Self {
+            base,
+            user: account.user.clone(),
+            password: password.into(),
+            agent: ureq::AgentBuilder::new().timeout(Self::TIMEOUT).build(),
+        })
+    }
+
+    /// A provider for `account` with the password from the OS keyring.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no password is stored, or see [`WebDavProvider::new`].
+    pub fn from_keyring(account: &WebDavAccount) -> std::io::Result<Self> {
+        Self::new(account, account.password()?)
+    }
+
+    /// The URL of the note or folder `name`, relative to the synced folder.
+    fn url(&self, name: &str) -> String {
+        let path: Vec<String> = name
+            .split('/')
+            .filter(|part| !part.is_empty())
+            .map(encode_segment)
+            .collect();
+
+        format!("{}{}", self.base, path.join("/"))
+    }
+
+    fn request(&self, method: &str, url: &str) -> ureq::Request {
+        let credentials = base64::Engine::encode(
+            &base64::engine::general_purpose::STANDARD,
+            format!("{}:{}", self.user, self.password),
+        );
+
+        self.agent
+            .request(method, url)
+            .set("Authorization", &format!("Basic {credentials}"))
+    }
+
+    fn propfind(&self, url: &str, depth: &str) -> std::io::Result<Vec<DavEntry>> {
+        let body = self
+            .request("PROPFIND", url)
+            .set("Depth", depth)
+            .set("Content-Type", "application/xml; charset=utf-8")
+            .send_string(PROPFIND_BODY)
+            .map_err(request_error)?
+            .into_string()?;
+
+        parse_multistatus(&body)
+    }
+
+    /// `entry`'s name relative to the synced folder, or `None` for the folder itself
+    /// and anything outside it.
+    fn note_name(&self, entry: &DavEntry) -> Option<String> {
+        let name = entry.path.strip_prefix(&url_path(&self.base))?;
+        let name = name.trim_end_matches('/');
+
+        (!name.is_empty()).then(|| name.to_string())
+    }
+
+    fn remote_note(&self, name: &str, entry: &DavEntry) -> editor_state::sync::RemoteNote {
+        editor_state::sync::RemoteNote {
+            name: name.to_string(),
+            modified: entry.modified.unwrap_or(std::time::UNIX_EPOCH),
+            len: entry.len,
+        }
+    }
+}
+
+impl editor_state::sync::SyncProvider for WebDavProvider {
+    fn name(&self) -> &str {
+        "webdav"
+    }
+
+    /// Walks the folder one level at a time; many servers refuse `Depth: infinity`.
+    fn list(&self) -> std::io::Result<Vec<editor_state::sync::RemoteNote>> {
+        let mut found = Vec::new();
+        let mut pending = vec![String::new()];
+
+        while let Some(dir) = pending.pop() {
+            for entry in self.propfind(&self.url(&dir), "1")? {
+                let Some(name) = self.note_name(&entry) else {
+                    continue;
+                };
+                let file_name = name.rsplit('/').next().unwrap_or(&name);
+
+                if name == dir || file_name.starts_with('.') {
+                    continue;
+                }
+
+                if entry.is_dir {
+                    pending.push(name);
+                } else if editor_state::workspace::is_note(std::path::Path::new(&name)) {
+                    found.push(self.remote_note(&name, &entry));
+                }
+            }
+        }
+
+        found.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(found)
+    }
+
+    fn pull(&self, name: &str) -> std::io::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+
+        let response = self
+            .request("GET", &self.url(name))
+            .call()
+            .map_err(request_error)?;
+
+        std::io::Read::read_to_end(&mut response.into_reader(), &mut bytes)?;
+
+        Ok(bytes)
+    }
+
+    fn push(&self, name: &str, bytes: &[u8]) -> std::io::Result<editor_state::sync::RemoteNote> {
+        // Create missing parent folders; 405 means one already exists.
+        let parts: Vec<&str> = name.split('/').collect();
+
+        for depth in 1..parts.len() {
+            match self
+                .request("MKCOL", &self.url(&parts[..depth].join("/")))
+                .call()
+            {
+                Ok(_) | Err(ureq::Error::Status(405, _)) => {}
+                Err(err) => return Err(request_error(err)),
+            }
+        }
+
+        let url = self.url(name);
+
+        self.request("PUT", &url)
+            .send_bytes(bytes)
+            .map_err(request_error)?;
+
+        let entry = self
+            .propfind(&url, "0")?
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Server didn't list {name} after uploading it"),
+                )
+            })?;
+
+        Ok(self.remote_note(name, &entry))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_multistatus() {
+        let xml = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:">
+  <d:response>
+    <d:href>/dav/files/me/Notes/</d:href>
+    <d:propstat><d:prop>
+      <d:resourcetype><d:collection/></d:resourcetype>
+    </d:prop></d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>https://cloud.example.com/dav/files/me/Notes/Project%20Ideas.md</d:href>
+    <d:propstat><d:prop>
+      <d:getlastmodified>Sun, 06 Nov 1994 08:49:37 GMT</d:getlastmodified>
+      <d:getcontentlength>42</d:getcontentlength>
+      <d:resourcetype/>
+    </d:prop></d:propstat>
+  </d:response>
+</d:multistatus>"#;
+        let entries = parse_multistatus(xml).unwrap();
+        let mut account = WebDavAccount {
+            url: "http://cloud.example.com/dav/files/me/Notes".to_string(),
+            user: "me".to_string(),
+            allow_http: false,
+        };
+
+        assert_eq!(
+            WebDavProvider::new(&account, "secret").unwrap_err().kind(),
+            std::io::ErrorKind::InvalidInput
+        );
+        account.url.insert(4, 's');
+        let provider = WebDavProvider::new(&account, "secret").unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert!(entries[0].is_dir);
+        assert_eq!(provider.note_name(&entries[0]), None);
+        assert_eq!(
+            provider.note_name(&entries[1]).as_deref(),
+            Some("Project Ideas.md")
+        );
+        assert_eq!(entries[1].len, 42);
+        assert_eq!(
+            entries[1].modified,
+            Some(std::time::UNIX_EPOCH + std::time::Duration::from_secs(784111777))
+        );
+        assert_eq!(
+            provider.url("sub dir/Project Ideas.md"),
+            "https://cloud.example.com/dav/files/me/Notes/sub%20dir/Project%20Ideas.md"
+        );
+    }
+}